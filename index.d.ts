@@ -3,19 +3,1672 @@
 
 /* auto-generated by NAPI-RS */
 
-/** 加密文件 - 适用于小到中等大小的文件 */
-export declare function encryptFile(algorithm: string, key: Buffer, inputPath: string, outputPath: string): object
+/** 抛出的 JS 错误的 `code` 属性，错误信息保持不变 */
+export const enum ErrorCode {
+  /** 密钥、IV、KID 等长度不正确 */
+  InvalidKeyLength = 'ERR_INVALID_KEY_LENGTH',
+   /** 参数或选项无效，或选项之间互相冲突 */
+  InvalidArgument = 'ERR_INVALID_ARGUMENT',
+   /** 文件头或结尾字段无效 */
+  BadHeader = 'ERR_BAD_HEADER',
+   /** 输入不是预期的格式，或内容已损坏 */
+  BadFormat = 'ERR_BAD_FORMAT',
+   /** 文件被截断 */
+  Truncated = 'ERR_TRUNCATED',
+   /** 不支持的格式版本、算法或特性 */
+  Unsupported = 'ERR_UNSUPPORTED',
+   /** 认证失败：密钥、密码错误或数据被篡改 */
+  AuthFailed = 'ERR_AUTH_FAILED',
+   /** 违反解密策略或配置中的算法限制 */
+  Policy = 'ERR_POLICY',
+   /** 无法打开输入文件或创建输出文件 */
+  IoOpen = 'ERR_IO_OPEN',
+   IoRead = 'ERR_IO_READ',
+   IoWrite = 'ERR_IO_WRITE',
+   Timeout = 'ERR_TIMEOUT',
+   Aborted = 'ERR_ABORTED',
+   Unknown = 'ERR_UNKNOWN'
+}
+/** 任务生命周期事件，通过 events() 订阅 */
+export interface JobEvent {
+  jobId: number
+  /** queued / started / chunkDone / paused / resumed / finished / failed */
+  type: string
+  /** 产生事件的操作，例如 "chunkEncryptFile" */
+  operation: string
+  /** 事件时间，Unix 毫秒时间戳 */
+  timestamp: number
+  chunkIndex?: number
+  /** 本分块处理的明文字节数 */
+  bytes?: number
+  error?: string
+}
+/** Job.status() 的返回值 */
+export interface JobStatus {
+  /** idle / queued / running / paused / finished / failed */
+  state: string
+  /** 最近一次关联的任务 id，与 events() 中的 jobId 相同 */
+  jobId?: number
+  operation?: string
+  /** 已完成的分块数 */
+  chunksDone: number
+  /** 已处理的明文字节数 */
+  bytesProcessed: number
+  error?: string
+}
+/**
+ * 可暂停的任务句柄，作为 `*Async` 版本的最后一个参数传入
+ *
+ * pause() 之后操作在下一个分块之间停下，resume() 之后从停下的位置继续，不会重新开始文件；停下和继续时分别发送
+ * paused 和 resumed 事件。暂停的操作保持占用线程池线程，同时暂停的操作占满线程池（默认 4 个线程）后，其它异步操作都会排队。
+ * 同一个 Job 可以传给多个操作，pause() 对所有操作生效，status() 返回最近一次传入的操作的进度。
+ */
+export class Job {
+   constructor()
+  /** 暂停关联的操作，也可以在传入操作之前调用 */
+   pause(): void
+   resume(): void
+   status(): JobStatus
+}
+/** 加密操作的可选参数 */
+export interface EncryptOptions {
+  /** 超时时间（毫秒），超时后中止操作并删除未完成的输出文件，0 或不设置表示不限制 */
+  timeoutMs?: number
+  /** 为 false 时输出路径上已有文件则出错，不覆盖（默认为 true） */
+  overwrite?: boolean
+  /** 将输入文件名加密保存到文件头中 */
+  storeFilename?: boolean
+  /** 指定要保存的原始文件名（代替输入文件名），设置后隐含 storeFilename */
+  filename?: string
+  /** 加密保存到文件头中的用户元数据，Buffer 原样保存，其它值按 JSON 保存 */
+  metadata?: Buffer | any
+  /** 明文的 MIME 类型，代替根据文件开头的字节自动检测的结果；加密保存到文件头中 */
+  mimeType?: string
+  /** 将输入文件的权限、修改和访问时间、所有者以及扩展属性加密保存到文件头中，解密时以 restoreAttributes 还原 */
+  preserveAttributes?: boolean
+  /** 加密完成并校验输出文件后，以随机数据覆盖并删除输入文件；不能与文件描述符和分卷同时使用 */
+  shredSource?: boolean
+  /** 输出旧格式（无容器文件头），供旧版本读取；不能与文件名和元数据选项同时使用 */
+  legacyFormat?: boolean
+  /** 明文摘要算法（"sha256" 或 "blake3"），摘要加密保存在文件中，解密时自动核对 */
+  plaintextHash?: 'sha256' | 'blake3'
+  /** 加密前压缩明文（"zstd"、"gzip" 或 "lz4"），分片文件逐个分片压缩，算法记录在文件头中，解密时自动解压 */
+  compression?: 'zstd' | 'gzip' | 'lz4'
+  /** 压缩级别：zstd 为 1-22（默认 3），gzip 为 0-9（默认 6），lz4 不支持 */
+  compressionLevel?: number
+  /** （仅 chunkEncryptFile）分卷大小（MB），输出切分为 `<outputPath>.001`、`.002`……，每个分卷不超过该大小 */
+  volumeSizeMb?: number
+  /** （仅 chunkEncryptFile）在文件末尾附加 Reed-Solomon 纠错数据，数值为冗余百分比（1-100），损坏的块可用 repairFile 恢复 */
+  parityPercent?: number
+  /** （仅 chunkEncryptFile）全零的分片以空分片帧保存，跳过输入文件中的空洞，解密时在输出文件中还原空洞；全零区域的位置不加密 */
+  sparse?: boolean
+  /** （仅 chunkEncryptFile）输出可追加的分片文件，之后可用 appendChunkedFile 在末尾追加数据；不记录明文摘要 */
+  appendable?: boolean
+  /** （仅 chunkEncryptFile）可续传：加密过程中定期在 `<outputPath>.checkpoint` 记录检查点，中断后以相同参数重试时从最后一个完整的分片继续 */
+  resume?: boolean
+  /** （仅 encryptFile 和 chunkEncryptFile）把输入文件映射到内存后直接读取，省去 read() 的复制；映射失败时改用普通读取。操作期间输入文件不能被截断 */
+  mmap?: boolean
+  /** （仅 chunkEncryptFile）绕过页缓存读写输入和输出文件（O_DIRECT / F_NOCACHE / FILE_FLAG_NO_BUFFERING），文件系统不支持时改用普通读写；不能与 mmap 和分卷同时使用 */
+  directIo?: boolean
+}
+/** 解密操作的可选参数 */
+export interface DecryptOptions {
+  /** 超时时间（毫秒），超时后中止操作并删除未完成的输出文件，0 或不设置表示不限制 */
+  timeoutMs?: number
+  /** 为 false 时输出路径上已有文件则出错，不覆盖（默认为 true） */
+  overwrite?: boolean
+  /** 恢复文件头中保存的原始文件名，此时输出路径视为目录 */
+  restoreFilename?: boolean
+  /** 把文件头中保存的文件属性（见 EncryptOptions.preserveAttributes）还原到输出文件；文件中没有保存时出错 */
+  restoreAttributes?: boolean
+  /** （仅 chunkDecryptFile）容错解密：无法读取或解密的分片不中止操作，而是以零填充并在结果中报告 */
+  tolerateErrors?: boolean
+  /** （配合 tolerateErrors）跳过损坏的分片而不是以零填充，输出中不保留其位置 */
+  skipDamagedChunks?: boolean
+  /** （仅 chunkDecryptFile）绕过页缓存读写输入和输出文件，文件系统不支持时改用普通读写；不能与 tolerateErrors 同时使用 */
+  directIo?: boolean
+}
+/** 格式转换和重新分片的可选参数 */
+export interface ConvertOptions {
+  /** 超时时间（毫秒），超时后中止操作并删除未完成的输出文件，0 或不设置表示不限制 */
+  timeoutMs?: number
+}
+/** verifyChunkedFile() 的可选参数 */
+export interface VerifyOptions {
+  /** 超时时间（毫秒），超时后中止校验，0 或不设置表示不限制 */
+  timeoutMs?: number
+  /** 加密算法，v2 文件默认使用文件头中记录的算法，旧格式文件必须指定 */
+  algorithm?: string
+}
+/** DecryptedReader 和 ChunkedFileHandle 的可选参数 */
+export interface ReaderOptions {
+  /** 取用一个分片后在后台线程中预先解密之后的几个分片，0 或不设置表示不预取；最多 64 */
+  prefetch?: number
+}
+/** storeFile() 的可选参数 */
+export interface StoreOptions {
+  /** 超时时间（毫秒），超时后中止操作，0 或不设置表示不限制；已写入的对象会保留，供下次复用 */
+  timeoutMs?: number
+  /** 清单中记录的文件名称，默认为输入文件名；同名文件会被替换 */
+  name?: string
+  /** 对象加密前的压缩算法（"zstd"、"gzip" 或 "lz4"） */
+  compression?: 'zstd' | 'gzip' | 'lz4'
+  /** 压缩级别，与 EncryptOptions.compressionLevel 相同 */
+  compressionLevel?: number
+}
+/** restoreFile() 的可选参数 */
+export interface RestoreOptions {
+  /** 超时时间（毫秒），超时后中止操作并删除未完成的输出文件，0 或不设置表示不限制 */
+  timeoutMs?: number
+}
+/** ageEncryptFile() 的参数，passphrase 和 recipients 二选一 */
+export interface AgeEncryptOptions {
+  /** 超时时间（毫秒），超时后中止操作并删除未完成的输出文件，0 或不设置表示不限制 */
+  timeoutMs?: number
+  /** 口令，使用 scrypt 派生文件密钥 */
+  passphrase?: string
+  /** X25519 接收者（`age1...`），任意一个接收者的身份都能解密 */
+  recipients?: Array<string>
+  /** 输出 ASCII 封装（PEM 风格）的文本文件 */
+  armor?: boolean
+}
+/** ageDecryptFile() 的参数，口令加密的文件需要 passphrase，其它文件需要 identities */
+export interface AgeDecryptOptions {
+  /** 超时时间（毫秒），超时后中止操作并删除未完成的输出文件，0 或不设置表示不限制 */
+  timeoutMs?: number
+  /** 口令 */
+  passphrase?: string
+  /** X25519 身份（`AGE-SECRET-KEY-1...`），也可以是身份文件的内容 */
+  identities?: Array<string>
+}
+/** opensslEncryptFile() / opensslDecryptFile() 的参数，解密时必须与加密时的 `openssl enc` 参数一致 */
+export interface OpensslOptions {
+  /** 超时时间（毫秒），超时后中止操作并删除未完成的输出文件，0 或不设置表示不限制 */
+  timeoutMs?: number
+  /** 使用 PBKDF2 派生密钥（`-pbkdf2`），默认为 true；false 时使用 `EVP_BytesToKey` */
+  pbkdf2?: boolean
+  /** PBKDF2 迭代次数（`-iter`），默认为 10000 */
+  iterations?: number
+  /** 派生密钥的摘要算法（`-md`）：md5、sha256 或 sha512，默认为 sha256 */
+  digest?: string
+}
+/** deriveKey() 的参数，默认值与使用口令的文件接口相同 */
+export interface KdfOptions {
+  /** 派生算法："scrypt"（默认）或 "pbkdf2" */
+  algorithm?: 'scrypt' | 'pbkdf2'
+  /** 派生的密钥长度（字节），默认为 32 */
+  keyLength?: number
+  /** scrypt 的成本参数 log2(N)，默认为 16，与 gocryptfsEncryptDirectory() 相同 */
+  logN?: number
+  /** scrypt 的块大小参数 r，默认为 8 */
+  r?: number
+  /** scrypt 的并行度参数 p，默认为 1 */
+  p?: number
+  /** PBKDF2 迭代次数，默认为 10000，与 opensslEncryptFile() 相同 */
+  iterations?: number
+  /** PBKDF2 的摘要算法：md5、sha256 或 sha512，默认为 sha256 */
+  digest?: string
+}
+/** gpgDecryptFile() 的参数 */
+export interface GpgDecryptOptions {
+  /** 超时时间（毫秒），超时后中止操作并删除未完成的输出文件，0 或不设置表示不限制 */
+  timeoutMs?: number
+}
+/** gpgEncryptFile() 的参数 */
+export interface GpgEncryptOptions {
+  /** 超时时间（毫秒），超时后中止操作并删除未完成的输出文件，0 或不设置表示不限制 */
+  timeoutMs?: number
+  /** 消息格式：rfc9580（默认，v6 SKESK + v2 SEIPD，Sequoia 等实现可以解密）或 librepgp（OCB 加密数据包，GnuPG 2.3 及以上版本可以解密） */
+  profile?: string
+  /** 对称算法：aes128、aes192 或 aes256（默认） */
+  cipher?: string
+  /** AEAD 算法：ocb（默认）或 gcm（只用于 rfc9580） */
+  aead?: string
+}
+/** encryptToZip() 的参数 */
+export interface ZipOptions {
+  /** 超时时间（毫秒），超时后中止操作并删除未完成的输出文件，0 或不设置表示不限制 */
+  timeoutMs?: number
+  /** 加密前用 Deflate 压缩，默认为 true；内容已经压缩时可以设为 false */
+  compress?: boolean
+}
+/** create7zArchive() 的参数 */
+export interface SevenZipOptions {
+  /** 超时时间（毫秒），超时后中止操作并删除未完成的输出文件，0 或不设置表示不限制 */
+  timeoutMs?: number
+  /** 加密前用 LZMA2 压缩，默认为 true；内容已经压缩时可以设为 false */
+  compress?: boolean
+}
+/** extract7zArchive() 的参数 */
+export interface ExtractOptions {
+  /** 超时时间（毫秒），超时后中止操作并删除已解压的文件，0 或不设置表示不限制 */
+  timeoutMs?: number
+}
+/** secretstreamEncryptFile() / secretstreamDecryptFile() 的参数 */
+export interface SecretStreamOptions {
+  /** 超时时间（毫秒），超时后中止操作并删除未完成的输出文件，0 或不设置表示不限制 */
+  timeoutMs?: number
+  /** 每条消息的明文长度（字节），默认为 4096（与 libsodium 文档中的示例相同）；解密时必须与加密时相同 */
+  chunkSize?: number
+}
+/** tinkEncryptFile() / tinkDecryptFile() 的参数，必须与 Tink 密钥的 AesGcmHkdfStreamingParams 一致 */
+export interface TinkStreamingOptions {
+  /** 超时时间（毫秒），超时后中止操作并删除未完成的输出文件，0 或不设置表示不限制 */
+  timeoutMs?: number
+  /** 附加数据（Tink 的 associatedData），加密和解密时必须相同，默认为空 */
+  associatedData?: Buffer
+  /** 密文分段长度（ciphertext_segment_size），默认为 4096，对应 AES128/AES256_GCM_HKDF_4KB 模板 */
+  segmentSize?: number
+  /** 派生密钥长度（derived_key_size），16 或 32，默认为密钥长度不小于 32 字节时 32，否则 16 */
+  derivedKeySize?: number
+  /** HKDF 摘要算法（hkdf_hash_type）："sha1"、"sha256"（默认）或 "sha512" */
+  hkdfHash?: string
+}
+/** fernetDecrypt() 的参数 */
+export interface FernetDecryptOptions {
+  /** 令牌的最长有效期（秒），超过时报错；不设置表示不检查令牌时间，与 Python 的 `decrypt(token, ttl)` 相同 */
+  ttl?: number
+}
+/** pasetoEncrypt() / pasetoDecrypt() 的参数 */
+export interface PasetoOptions {
+  /** 加密时附在令牌末尾的 footer（明文，受认证保护）；解密时为期望的 footer，令牌的 footer 不同时报错 */
+  footer?: Buffer | string
+  /** 隐式断言，不写入令牌，解密时必须提供与加密时相同的值 */
+  implicitAssertion?: Buffer | string
+}
+/** encryptJwe() 的参数 */
+export interface JweEncryptOptions {
+  /** 密钥管理算法："dir"、"A128KW"、"A192KW"、"A256KW"、"RSA-OAEP" 或 "RSA-OAEP-256" */
+  alg: string
+  /** 内容加密算法："A128GCM"、"A192GCM" 或 "A256GCM"（默认） */
+  enc?: string
+  /** 写入受保护头部的密钥 ID */
+  kid?: string
+  /** 写入受保护头部的 typ，例如 "JWT" */
+  typ?: string
+  /** 写入受保护头部的 cty，嵌套 JWT 时为 "JWT" */
+  cty?: string
+  /** 其它写入受保护头部的参数 */
+  header?: Record<string, any>
+}
+/** cmsEncryptFile() 的参数 */
+export interface CmsEncryptOptions {
+  /** 超时时间（毫秒），超时后中止操作并删除未完成的输出文件，0 或不设置表示不限制 */
+  timeoutMs?: number
+  /** 内容密钥的传输方式：pkcs1（默认，与 `openssl cms -encrypt` 相同）、oaep-sha1 或 oaep-sha256 */
+  keyTransport?: string
+  /** 内容加密算法：aes-128-cbc、aes-192-cbc 或 aes-256-cbc（默认） */
+  contentEncryption?: string
+  /** 输出编码：der（默认）或 pem */
+  format?: string
+}
+/** generateMinisignKeyPair() 的参数 */
+export interface MinisignKeyOptions {
+  /** 加密私钥的口令（scrypt，与 minisign 默认参数相同，约需 1 GiB 内存）；不设置时私钥不加密，与 `minisign -G -W` 相同 */
+  password?: string
+}
+/** minisignSignFile() 的参数 */
+export interface MinisignSignOptions {
+  /** 超时时间（毫秒），超时后中止签名，0 或不设置表示不限制 */
+  timeoutMs?: number
+  /** 加密私钥的口令 */
+  password?: string
+  /** 受签名保护的注释，默认为时间戳和文件名 */
+  trustedComment?: string
+  /** 不受保护的注释，默认为 "signature from minisign secret key" */
+  untrustedComment?: string
+  /** 签名文件的路径，默认为输入文件路径加 ".minisig"；输入为文件描述符时必须指定 */
+  signaturePath?: string
+}
+/** minisignVerifyFile() 的参数 */
+export interface MinisignVerifyOptions {
+  /** 超时时间（毫秒），超时后中止验证，0 或不设置表示不限制 */
+  timeoutMs?: number
+  /** 签名文件的路径，默认为输入文件路径加 ".minisig"；输入为文件描述符时必须指定 */
+  signaturePath?: string
+}
+/** saltpackEncryptFile() 的参数 */
+export interface SaltpackEncryptOptions {
+  /** 超时时间（毫秒），超时后中止操作并删除未完成的输出文件，0 或不设置表示不限制 */
+  timeoutMs?: number
+  /** 接收者的 Curve25519 公钥（32 字节），任意一个接收者的私钥都能解密 */
+  recipients: Array<Buffer>
+  /** 发送者的 Curve25519 私钥（32 字节），接收者解密时可以得知发送者公钥；不设置时匿名发送 */
+  senderSecretKey?: Buffer
+  /** 不在头部写出接收者公钥，解密方需要逐个尝试 */
+  hideRecipients?: boolean
+  /** 输出 base62 文本封装（`BEGIN SALTPACK ENCRYPTED MESSAGE.`），默认为二进制 */
+  armor?: boolean
+}
+/** saltpackDecryptFile() 的参数 */
+export interface SaltpackDecryptOptions {
+  /** 超时时间（毫秒），超时后中止操作并删除未完成的输出文件，0 或不设置表示不限制 */
+  timeoutMs?: number
+  /** 接收者的 Curve25519 私钥（32 字节） */
+  secretKey: Buffer
+}
+/** hlsEncryptSegment() 的参数，sequence 和 iv 二选一 */
+export interface HlsSegmentOptions {
+  /** 超时时间（毫秒），超时后中止操作并删除未完成的输出文件，0 或不设置表示不限制 */
+  timeoutMs?: number
+  /** 分段的媒体序号（`#EXT-X-MEDIA-SEQUENCE` 加分段在播放列表中的位置），作为 IV 使用，`#EXT-X-KEY` 不写 IV 属性 */
+  sequence?: number
+  /** 显式的 16 字节 IV，写入 `#EXT-X-KEY` 的 IV 属性 */
+  iv?: Buffer
+  /** `#EXT-X-KEY` 的 URI 属性，默认为 keyPath 的文件名 */
+  keyUri?: string
+  /** 写出密钥文件（16 字节原始密钥）的路径，不设置时不写密钥文件 */
+  keyPath?: string
+}
+/** cencEncryptFile() 的参数 */
+export interface CencEncryptOptions {
+  /** 超时时间（毫秒），超时后中止操作并删除未完成的输出文件，0 或不设置表示不限制 */
+  timeoutMs?: number
+  /** 保护方案：cenc（AES-CTR，默认）或 cbcs（AES-CBC 模式加密，Safari / FairPlay 兼容的方案） */
+  scheme?: string
+  /** cenc 的起始样本 IV（8 字节）或 cbcs 的常量 IV（16 字节），默认随机生成 */
+  iv?: Buffer
+  /** 在 moov 中加入 W3C Clear Key 的 pssh，默认为 true */
+  clearKeyPssh?: boolean
+}
+/** rclone crypt 相关函数的参数，与 rclone.conf 中 crypt 远端的配置项对应 */
+export interface RcloneCryptOptions {
+  /** 超时时间（毫秒），超时后中止操作并删除未完成的输出文件，0 或不设置表示不限制；只用于文件加解密 */
+  timeoutMs?: number
+  /** 口令，对应 `password`（rclone.conf 中保存的是混淆后的值，需要先用 `rclone reveal` 还原） */
+  password: string
+  /** 盐口令，对应 `password2`，不设置时使用 rclone 的默认盐 */
+  salt?: string
+  /** 文件名加密方式：standard（默认）、obfuscate 或 off，对应 `filename_encryption` */
+  filenameEncryption?: string
+  /** 是否加密目录名，默认为 true，对应 `directory_name_encryption` */
+  directoryNameEncryption?: boolean
+  /** 标准文件名加密的编码：base32（默认）或 base64，对应 `filename_encoding` */
+  filenameEncoding?: string
+}
+/** gocryptfs 相关函数的参数 */
+export interface GocryptfsOptions {
+  /** 超时时间（毫秒），超时后中止操作并删除已写入的文件，0 或不设置表示不限制；只用于加密目录 */
+  timeoutMs?: number
+  /** 文件系统口令；加密目录时必须设置，路径转换时可以用 masterKey 代替 */
+  password?: string
+  /** 32 字节主密钥（gocryptfsEncryptDirectory() 的返回值），设置后路径转换不再读取 gocryptfs.conf 和运行 scrypt */
+  masterKey?: Buffer
+  /** 加密目录时 scrypt 的成本参数 log2(N)，默认为 16，与 `gocryptfs -init -scryptn` 相同 */
+  scryptLogN?: number
+}
+/** s3EncryptFile() 的参数 */
+export interface S3EncryptOptions {
+  /** 超时时间（毫秒），超时后中止操作并删除未完成的输出文件，0 或不设置表示不限制 */
+  timeoutMs?: number
+  /** 材料描述（x-amz-matdesc），解密时官方客户端用它选择主密钥，默认为空对象 */
+  materialDescription?: Record<string, string>
+  /** 指令文件路径，设置后把加密元数据以 JSON 写入该文件（对应 S3 上的 `<key>.instruction` 对象） */
+  instructionPath?: string
+}
+/** s3DecryptFile() 的参数 */
+export interface S3DecryptOptions {
+  /** 超时时间（毫秒），超时后中止操作并删除未完成的输出文件，0 或不设置表示不限制 */
+  timeoutMs?: number
+}
+/** veracryptListFiles() / veracryptExtractFiles() 的参数 */
+export interface VeraCryptOptions {
+  /** 超时时间（毫秒），超时后中止操作并删除已解压的文件，0 或不设置表示不限制；只用于解压 */
+  timeoutMs?: number
+  /** 创建卷时设置的 PIM，0 或不设置表示使用默认迭代次数 */
+  pim?: number
+  /** 卷头密钥派生的哈希算法：sha512 或 sha256，不设置时依次尝试 */
+  hash?: string
+}
+/** encryptFiles() 中的一个文件 */
+export interface BatchEncryptEntry {
+  inputPath: string
+  /** 不设置时按 BatchOptions.outputTemplate 生成 */
+  outputPath?: string
+  /** 该文件的加密选项，其中的 timeoutMs 不起作用，整批的超时由 BatchOptions.timeoutMs 指定 */
+  options?: EncryptOptions
+}
+/** decryptFiles() 中的一个文件 */
+export interface BatchDecryptEntry {
+  inputPath: string
+  /** 不设置时按 BatchOptions.outputTemplate 生成；restoreFilename 时为输出目录 */
+  outputPath?: string
+  /** 该文件的解密选项，其中的 timeoutMs 不起作用，整批的超时由 BatchOptions.timeoutMs 指定 */
+  options?: DecryptOptions
+}
+/** encryptFiles() / decryptFiles() 的可选参数 */
+export interface BatchOptions {
+  /** 整批的超时时间（毫秒），超时后不再开始新的文件并以 ERR_TIMEOUT 失败，0 或不设置表示不限制 */
+  timeoutMs?: number
+  /** 同时处理的文件数，默认为 CPU 核数 */
+  concurrency?: number
+  /** （仅 encryptFiles）设置后以该分片大小（MB）输出分片文件（同 chunkEncryptFile），否则整体加密（同 encryptFile） */
+  chunkSizeMb?: number
+  /** 只计算每个文件的输出路径、大小、分片数和冲突并在 plan 中返回，不加解密也不写入任何文件，默认为 false */
+  dryRun?: boolean
+  /** 没有 outputPath 的条目的输出路径模板，例如 `{dir}/{name}.{hash8}.zenc`；占位符见 README */
+  outputTemplate?: string
+}
+/** encryptDirectory() 的可选参数 */
+export interface DirectoryEncryptOptions {
+  /** 超时时间（毫秒），超时后中止操作并删除已写入的文件，0 或不设置表示不限制 */
+  timeoutMs?: number
+  /** 加密算法（"aes" 或 "chacha20poly1305"），默认为 "aes" */
+  algorithm?: string
+  /** 每个文件的分片大小（MB），默认为 10 */
+  chunkSizeMb?: number
+  /** 加密前压缩每个文件（"zstd"、"gzip" 或 "lz4"），同 chunkEncryptFile */
+  compression?: 'zstd' | 'gzip' | 'lz4'
+  compressionLevel?: number
+  /** 同时加密的文件数，默认为 CPU 核数 */
+  concurrency?: number
+  /** 只加密匹配这些模式的路径（相对于源目录，`**` 匹配任意层目录），目录匹配时加密其下的所有内容；不设置时加密全部 */
+  include?: Array<string>
+  /** 跳过匹配这些模式的路径，语法与 `.gitignore` 相同；被跳过的目录不再遍历 */
+  exclude?: Array<string>
+  /** 额外的忽略文件，每行一个 exclude 模式；源目录根部的 `.zippyignore` 总是会读取 */
+  ignoreFiles?: Array<string>
+  /** 加密文件名和目录名，目标目录中的名称不再透露原始名称，默认为 false */
+  encryptFilenames?: boolean
+  /** 只检查目录并在 plan 中返回将要写出的文件，不创建目标目录也不写入任何文件，默认为 false */
+  dryRun?: boolean
+  /** 每个加密文件在目标目录中的相对路径模板，默认为 `{dir}/{name}.enc`；`{hash}` 为明文的摘要 */
+  outputTemplate?: string
+}
+/** decryptDirectory() 的可选参数 */
+export interface DirectoryDecryptOptions {
+  /** 超时时间（毫秒），超时后中止操作并删除已写入的文件，0 或不设置表示不限制 */
+  timeoutMs?: number
+  /** 只还原匹配这些模式的路径（相对于目录根部，`**` 匹配任意层目录），目录匹配时还原其下的所有内容；不设置时还原整个目录树 */
+  include?: Array<string>
+  /** 跳过匹配这些模式的路径，语法与 `.gitignore` 相同 */
+  exclude?: Array<string>
+  /** 同时解密的文件数，默认为 CPU 核数 */
+  concurrency?: number
+  /** 只读取清单并在 plan 中返回将要还原的文件，不解密也不写入任何文件，默认为 false */
+  dryRun?: boolean
+  /** 每个还原的文件在目标目录中的相对路径模板，默认为 `{dir}/{name}`；`{dir}` 和 `{name}` 取自原始路径，`{hash}` 为加密文件的摘要 */
+  outputTemplate?: string
+}
+/** encryptArchive() 的可选参数 */
+export interface ArchiveEncryptOptions {
+  /** 超时时间（毫秒），超时后中止操作并删除未完成的归档，0 或不设置表示不限制 */
+  timeoutMs?: number
+  /** 加密算法（"aes" 或 "chacha20poly1305"），默认为 "aes" */
+  algorithm?: string
+  /** 分片大小（MB），默认为 10 */
+  chunkSizeMb?: number
+  /** 加密前压缩（"zstd"、"gzip" 或 "lz4"），同 chunkEncryptFile */
+  compression?: 'zstd' | 'gzip' | 'lz4'
+  compressionLevel?: number
+  /** 只打包匹配这些模式的路径，同 encryptDirectory */
+  include?: Array<string>
+  /** 跳过匹配这些模式的路径，同 encryptDirectory */
+  exclude?: Array<string>
+  /** 额外的忽略文件，同 encryptDirectory；源目录根部的 `.zippyignore` 总是会读取 */
+  ignoreFiles?: Array<string>
+}
+/** extractArchive() 的可选参数 */
+export interface ArchiveExtractOptions {
+  /** 超时时间（毫秒），超时后中止操作并删除已写入的文件，0 或不设置表示不限制 */
+  timeoutMs?: number
+  /** 只解开匹配这些模式的路径，同 decryptDirectory */
+  include?: Array<string>
+  /** 跳过匹配这些模式的路径，同 decryptDirectory */
+  exclude?: Array<string>
+}
+/** watchAndEncrypt() 的可选参数 */
+export interface WatchOptions {
+  /** 加密算法（"aes" 或 "chacha20poly1305"），默认为 "aes" */
+  algorithm?: string
+  /** 每个文件的分片大小（MB），默认为 10 */
+  chunkSizeMb?: number
+  /** 加密前压缩每个文件（"zstd"、"gzip" 或 "lz4"），同 chunkEncryptFile */
+  compression?: 'zstd' | 'gzip' | 'lz4'
+  compressionLevel?: number
+  /** 只加密匹配这些模式的路径，同 encryptDirectory */
+  include?: Array<string>
+  /** 跳过匹配这些模式的路径，同 encryptDirectory */
+  exclude?: Array<string>
+  /** 额外的忽略文件，同 encryptDirectory；监视目录根部的 `.zippyignore` 总是会读取 */
+  ignoreFiles?: Array<string>
+  /** 文件大小和修改时间保持不变多久（毫秒）后才加密，默认为 1000 */
+  debounceMs?: number
+  /** 扫描目录的间隔（毫秒），默认为 500 */
+  intervalMs?: number
+  /** 不加密开始监视时已经存在的文件，直到它们被修改 */
+  ignoreExisting?: boolean
+  /** 加密成功后删除源文件 */
+  removeSource?: boolean
+}
+/** setDecryptPolicy() 的参数，未设置或为 false 的项保持不变 */
+export interface DecryptPolicyOptions {
+  /** 拒绝没有容器文件头的旧格式 */
+  rejectLegacyFormats?: boolean
+  /** 拒绝任何未认证的内容（旧格式、未认证的文件头或分片、AES-CBC 整体加密文件） */
+  requireAuthentication?: boolean
+}
+/** benchmarkAlgorithms() 的可选参数 */
+export interface BenchmarkOptions {
+  /** 测量使用的数据大小（KB），每个大小分别测量，默认为 [64, 1024]，每个最大 65536 */
+  sizesKb?: Array<number>
+  /** 每项测量持续的时间（毫秒），默认为 100，最大 10000 */
+  durationMs?: number
+  /** 测量的加密算法，默认为配置允许的全部算法（"aes" 和 "chacha20poly1305"） */
+  algorithms?: Array<string>
+  /** 测量的摘要算法，默认为 ["sha256", "blake3"]，传空数组时不测量摘要 */
+  hashes?: Array<string>
+}
+/** encryptFile() 的结果 */
+export interface EncryptFileResult {
+  /** 明文大小（KB） */
+  fileSize: number
+  /** 明文大小（字节） */
+  fileSizeBytes: number | bigint
+  /** 压缩后的大小（KB），未启用压缩时为 null */
+  compressedSizeKB: number | null
+  /** 压缩后的大小（字节），未启用压缩时为 null */
+  compressedSizeBytes: number | bigint | null
+  /** 压缩没有减小体积、按原样保存时为 true，未启用压缩时为 null */
+  compressionSkipped: boolean | null
+}
+/** decryptFile() 的结果 */
+export interface DecryptFileResult {
+  /** 明文大小（KB） */
+  fileSize: number
+  /** 明文大小（字节） */
+  fileSizeBytes: number | bigint
+  /** 加密文件大小（KB） */
+  encryptedSize: number
+  /** 加密文件大小（字节） */
+  encryptedSizeBytes: number | bigint
+  /** 文件头中保存的原始文件名 */
+  filename: string | null
+  mimeType: string | null
+  /** 实际写入的输出路径（restoreFilename 时可能与传入的不同） */
+  outputPath: string
+  /** 是否已核对文件头中保存的明文摘要 */
+  hashVerified: boolean
+}
+/** chunkEncryptFile() 的结果 */
+export interface ChunkEncryptFileResult {
+  totalChunks: number
+  /** 明文大小（KB） */
+  fileSize: number
+  /** 明文大小（字节） */
+  fileSizeBytes: number | bigint
+  /** 分片大小（KB） */
+  chunkSize: number
+  /** 分片大小（字节） */
+  chunkSizeBytes: number | bigint
+  /** 写出的分卷路径，未设置 volumeSizeMb 时为 null */
+  volumes: Array<string> | null
+  paritySizeKB: number | null
+  paritySizeBytes: number | bigint | null
+  compressedSizeKB: number | null
+  compressedSizeBytes: number | bigint | null
+  /** 压缩无效、按原样保存的分片数 */
+  uncompressedChunks: number | null
+  /** 以空分片帧保存的全零分片数，未启用 sparse 时为 null */
+  sparseChunks: number | null
+  /** 从检查点继续时跳过的分片数，未启用 resumable 时为 null */
+  resumedChunks: number | null
+}
+/** 容错解密时无法解密的分片 */
+export interface DamagedChunk {
+  index: number
+  /** 分片在明文中的起止位置（字节） */
+  start: number
+  end: number
+  error: string
+}
+/** chunkDecryptFile() / chunkDecryptVolumes() 的结果 */
+export interface ChunkDecryptFileResult {
+  totalChunks: number
+  /** 写出的明文大小（KB） */
+  totalBytesKB: number
+  /** 写出的明文大小（字节） */
+  totalBytes: number | bigint
+  /** 文件头记录的明文大小（KB） */
+  originalSizeKB: number
+  /** 文件头记录的明文大小（字节） */
+  originalSizeBytes: number | bigint
+  chunkSizeKB: number
+  chunkSizeBytes: number | bigint
+  filename: string | null
+  mimeType: string | null
+  outputPath: string
+  /** 是否已核对结尾字段中的明文摘要，tolerateErrors 时为 false */
+  hashVerified: boolean
+  /** tolerateErrors 时跳过或以零填充的分片，其他情况为空数组 */
+  damagedChunks: Array<DamagedChunk>
+  /** 整个文件的 MAC 和结尾字段是否校验通过，只有 tolerateErrors 时可能为 false */
+  verified: boolean
+  verificationError: string | null
+  /** chunkDecryptVolumes() 读取的分卷数，其他情况为 null */
+  volumes: number | null
+}
+/** convertToChunked() 的结果 */
+export interface ConvertToChunkedResult {
+  totalChunks: number
+  /** 明文大小（KB） */
+  fileSize: number
+  /** 明文大小（字节） */
+  fileSizeBytes: number | bigint
+  /** 分片大小（KB） */
+  chunkSize: number
+  /** 分片大小（字节） */
+  chunkSizeBytes: number | bigint
+}
+/** convertToMonolithic() 的结果 */
+export interface ConvertToMonolithicResult {
+  /** 源文件的分片数 */
+  totalChunks: number
+  /** 明文大小（KB） */
+  fileSize: number
+  /** 明文大小（字节） */
+  fileSizeBytes: number | bigint
+}
+/** rechunkFile() 的结果 */
+export interface RechunkFileResult {
+  sourceChunks: number
+  totalChunks: number
+  /** 明文大小（KB） */
+  fileSize: number
+  /** 明文大小（字节） */
+  fileSizeBytes: number | bigint
+  /** 新的分片大小（KB） */
+  chunkSize: number
+  /** 新的分片大小（字节） */
+  chunkSizeBytes: number | bigint
+}
+/** encryptFiles() / decryptFiles() 中每个文件的结果 */
+export interface BatchFileResult {
+  inputPath: string
+  /** 实际写出的文件；解密时使用 restoreFilename 为恢复文件名后的路径 */
+  outputPath: string
+  /** ok / failed，dryRun 时为 planned / failed */
+  status: string
+  /** 明文大小（字节），失败时为 null */
+  fileSizeBytes: number | bigint | null
+  error: string | null
+}
+/** encryptFiles() / decryptFiles() 的结果 */
+export interface BatchResult {
+  succeeded: number
+  failed: number
+  /** 成功处理的明文总大小（KB） */
+  totalSizeKB: number
+  /** 成功处理的明文总大小（字节） */
+  totalSizeBytes: number | bigint
+  /** 整批耗时（毫秒） */
+  elapsedMs: number
+  /** 与输入顺序相同的每个文件的结果 */
+  files: Array<BatchFileResult>
+  /** dryRun 时计划处理的文件，否则为 null */
+  plan: Array<PlannedFile> | null
+}
+/** dryRun 时计划处理的一个文件，不读写文件内容 */
+export interface PlannedFile {
+  inputPath: string
+  /** 将要写出的文件；解密时使用 restoreFilename 为恢复文件名后的路径 */
+  outputPath: string
+  /** 明文大小（KB），旧格式的整体加密文件没有记录时为 null */
+  fileSizeKB: number | null
+  /** 明文大小（字节） */
+  fileSizeBytes: number | bigint | null
+  /** 分片数，整体加密或解密时为 null */
+  totalChunks: number | null
+  /** 输出路径上已有文件，执行时会被替换 */
+  exists: boolean
+  /** 与同一批中其它文件的冲突：输出路径相同、输出路径是另一个文件的输入，或只有大小写不同；没有冲突时为 null */
+  collision: string | null
+}
+/** migrateFile() / migrateFiles() 中每个文件的结果；失败时只有 path、status 和 error */
+export interface MigrateFileResult {
+  path: string
+  /** migrated / skipped / failed */
+  status: string
+  /** 迁移前的格式版本 */
+  fromVersion: number | null
+  originalSizeKB: number | null
+  originalSizeBytes: number | bigint | null
+  chunkSizeKB: number | null
+  chunkSizeBytes: number | bigint | null
+  totalChunks: number | null
+  encryptedSizeBeforeKB: number | null
+  encryptedSizeBeforeBytes: number | bigint | null
+  encryptedSizeAfterKB: number | null
+  encryptedSizeAfterBytes: number | bigint | null
+  error: string | null
+}
+/** updateEncryptedFile() 的结果 */
+export interface UpdateEncryptedFileResult {
+  totalChunks: number
+  changedChunks: number
+  rewrittenSizeKB: number
+  rewrittenSizeBytes: number | bigint
+  /** 是否原地改写了变化的分片（否则重写了整个文件） */
+  inPlace: boolean
+}
+/** appendChunkedFile() 的结果 */
+export interface AppendChunkedFileResult {
+  totalChunks: number
+  /** 追加时写入（或重写）的分片数 */
+  writtenChunks: number
+  appendedSizeKB: number
+  appendedSizeBytes: number | bigint
+  /** 追加后的明文大小（KB） */
+  fileSizeKB: number
+  /** 追加后的明文大小（字节） */
+  fileSizeBytes: number | bigint
+}
+/** verifyChunkedFile() 中每个分片的结果 */
+export interface ChunkReport {
+  index: number
+  /** ok / damaged */
+  status: string
+  /** 分片在明文中的起止位置（字节） */
+  start: number
+  end: number
+  error: string | null
+}
+/** verifyChunkedFile() 的结果 */
+export interface VerifyChunkedFileResult {
+  path: string
+  passed: boolean
+  totalChunks: number
+  damagedChunks: number
+  originalSizeKB: number
+  originalSizeBytes: number | bigint
+  chunkSizeKB: number
+  chunkSizeBytes: number | bigint
+  error: string | null
+  chunks: Array<ChunkReport>
+}
+/** getChunkedFileMetadata() 中的结尾字段信息 */
+export interface ChunkedFooterInfo {
+  indexedChunks: number
+  hasChunkChecksums: boolean
+  plaintextSizeKB: number | null
+  plaintextSizeBytes: number | bigint | null
+  hasDigest: boolean
+  hashAlgorithm: string | null
+  /** 明文摘要（十六进制），需要提供密钥 */
+  digest: string | null
+  /** 摘要算法为 SHA-256 时与 digest 相同 */
+  sha256: string | null
+}
+/** getChunkedFileMetadata() 的结果 */
+export interface ChunkedFileMetadata {
+  totalChunks: number
+  fileSizeKB: number
+  fileSizeBytes: number | bigint
+  chunkSizeKB: number
+  chunkSizeBytes: number | bigint
+  compression: string | null
+  /** 文件没有结尾字段时为 null */
+  footer: ChunkedFooterInfo | null
+}
+/** repairFile() 的结果 */
+export interface RepairFileResult {
+  checkedBlocks: number
+  damagedBlocks: number
+  repairedBlocks: number
+  unrecoverableBlocks: number
+  /** 所有损坏的块都已修复 */
+  repaired: boolean
+}
+/** storeFile() 的结果 */
+export interface StoreFileResult {
+  name: string
+  fileSizeKB: number
+  fileSizeBytes: number | bigint
+  totalChunks: number
+  /** 新写入存储的分片数 */
+  newChunks: number
+  /** 存储中已有、直接复用的分片数 */
+  reusedChunks: number
+  storedSizeKB: number
+  storedSizeBytes: number | bigint
+}
+/** restoreFile() 的结果 */
+export interface RestoreFileResult {
+  name: string
+  fileSizeKB: number
+  fileSizeBytes: number | bigint
+  totalChunks: number
+  outputPath: string
+}
+/** listStoredFiles() 中的一个文件 */
+export interface StoredFileEntry {
+  name: string
+  fileSizeKB: number
+  fileSizeBytes: number | bigint
+  totalChunks: number
+  /** 保存时间，Unix 毫秒时间戳 */
+  createdAt: number
+  algorithm: string
+}
+/** 只报告明文和密文大小的操作的结果（opensslEncryptFile、rcloneEncryptFile） */
+export interface FileSizes {
+  fileSizeKB: number
+  fileSizeBytes: number | bigint
+  encryptedSizeKB: number
+  encryptedSizeBytes: number | bigint
+}
+/** 只报告大小和输出路径的解密操作的结果（opensslDecryptFile、rcloneDecryptFile、s3DecryptFile） */
+export interface DecryptedFile {
+  fileSizeKB: number
+  fileSizeBytes: number | bigint
+  encryptedSizeKB: number
+  encryptedSizeBytes: number | bigint
+  outputPath: string
+}
+/** ageEncryptFile() 的结果 */
+export interface AgeEncryptResult {
+  fileSizeKB: number
+  fileSizeBytes: number | bigint
+  encryptedSizeKB: number
+  encryptedSizeBytes: number | bigint
+  /** 是否使用口令（scrypt）加密 */
+  passphrase: boolean
+  armored: boolean
+}
+/** ageDecryptFile() 的结果 */
+export interface AgeDecryptResult {
+  fileSizeKB: number
+  fileSizeBytes: number | bigint
+  encryptedSizeKB: number
+  encryptedSizeBytes: number | bigint
+  passphrase: boolean
+  outputPath: string
+}
+/** generateAgeIdentity() 的结果 */
+export interface AgeIdentity {
+  /** AGE-SECRET-KEY-1... 私钥 */
+  identity: string
+  /** age1... 公钥 */
+  recipient: string
+}
+/** gpgDecryptFile() 的结果 */
+export interface GpgDecryptResult {
+  fileSizeKB: number
+  fileSizeBytes: number | bigint
+  encryptedSizeKB: number
+  encryptedSizeBytes: number | bigint
+  /** 字面数据包中的文件名 */
+  filename: string
+  cipher: string
+  compression: string
+  outputPath: string
+}
+/** gpgEncryptFile() 的结果 */
+export interface GpgEncryptResult {
+  fileSizeKB: number
+  fileSizeBytes: number | bigint
+  encryptedSizeKB: number
+  encryptedSizeBytes: number | bigint
+  filename: string
+  profile: string
+  cipher: string
+  aead: string
+}
+/** encryptToZip() 的结果 */
+export interface ZipResult {
+  files: number
+  directories: number
+  fileSizeKB: number
+  fileSizeBytes: number | bigint
+  encryptedSizeKB: number
+  encryptedSizeBytes: number | bigint
+}
+/** create7zArchive() 的结果 */
+export interface SevenZipResult {
+  files: number
+  fileSizeKB: number
+  fileSizeBytes: number | bigint
+  encryptedSizeKB: number
+  encryptedSizeBytes: number | bigint
+}
+/** extract7zArchive() 的结果 */
+export interface ExtractResult {
+  files: number
+  directories: number
+  fileSizeKB: number
+  fileSizeBytes: number | bigint
+  encryptedSizeKB: number
+  encryptedSizeBytes: number | bigint
+  outputDir: string
+}
+/** secretstreamEncryptFile() 的结果 */
+export interface SecretStreamEncryptResult {
+  fileSizeKB: number
+  fileSizeBytes: number | bigint
+  encryptedSizeKB: number
+  encryptedSizeBytes: number | bigint
+  chunks: number
+}
+/** secretstreamDecryptFile() 的结果 */
+export interface SecretStreamDecryptResult {
+  fileSizeKB: number
+  fileSizeBytes: number | bigint
+  encryptedSizeKB: number
+  encryptedSizeBytes: number | bigint
+  chunks: number
+  outputPath: string
+}
+/** tinkEncryptFile() 的结果 */
+export interface TinkEncryptResult {
+  fileSizeKB: number
+  fileSizeBytes: number | bigint
+  encryptedSizeKB: number
+  encryptedSizeBytes: number | bigint
+  segments: number
+}
+/** tinkDecryptFile() 的结果 */
+export interface TinkDecryptResult {
+  fileSizeKB: number
+  fileSizeBytes: number | bigint
+  encryptedSizeKB: number
+  encryptedSizeBytes: number | bigint
+  segments: number
+  outputPath: string
+}
+/** decryptJwe() 的结果 */
+export interface JweDecryptResult {
+  plaintext: Buffer
+  /** 受保护的头部 */
+  header: Record<string, unknown>
+}
+/** pasetoDecrypt() 的结果 */
+export interface PasetoDecryptResult {
+  payload: Buffer
+  /** 没有 footer 时为空 Buffer */
+  footer: Buffer
+}
+/** cmsEncryptFile() 的结果 */
+export interface CmsEncryptResult {
+  fileSizeKB: number
+  fileSizeBytes: number | bigint
+  encryptedSizeKB: number
+  encryptedSizeBytes: number | bigint
+  recipients: number
+}
+/** generateMinisignKeyPair() 的结果 */
+export interface MinisignKeyPair {
+  publicKey: string
+  secretKey: string
+  /** 十六进制密钥 id */
+  keyId: string
+}
+/** minisignSignFile() 的结果 */
+export interface MinisignSignResult {
+  signaturePath: string
+  /** .minisig 文件的内容 */
+  signature: string
+  keyId: string
+  trustedComment: string
+  fileSizeKB: number
+  fileSizeBytes: number | bigint
+}
+/** minisignVerifyFile() 的结果 */
+export interface MinisignVerifyResult {
+  valid: boolean
+  keyId: string
+  trustedComment: string
+  /** 验证失败的原因 */
+  error: string | null
+}
+/** generateSaltpackKeyPair() 的结果 */
+export interface SaltpackKeyPair {
+  publicKey: Buffer
+  secretKey: Buffer
+}
+/** saltpackEncryptFile() 的结果 */
+export interface SaltpackEncryptResult {
+  fileSizeKB: number
+  fileSizeBytes: number | bigint
+  encryptedSizeKB: number
+  encryptedSizeBytes: number | bigint
+  recipients: number
+  armored: boolean
+}
+/** saltpackDecryptFile() 的结果 */
+export interface SaltpackDecryptResult {
+  fileSizeKB: number
+  fileSizeBytes: number | bigint
+  encryptedSizeKB: number
+  encryptedSizeBytes: number | bigint
+  /** 发送者公钥，匿名发送时为 null */
+  sender: Buffer | null
+  outputPath: string
+}
+/** hlsEncryptSegment() 的结果 */
+export interface HlsSegmentResult {
+  fileSizeKB: number
+  fileSizeBytes: number | bigint
+  encryptedSizeKB: number
+  encryptedSizeBytes: number | bigint
+  /** 可直接写入播放列表的 #EXT-X-KEY 行 */
+  keyLine: string
+  /** 0x 开头的十六进制 IV */
+  iv: string
+  keyPath: string | null
+}
+/** cencEncryptFile() 的结果 */
+export interface CencEncryptResult {
+  fileSizeKB: number
+  fileSizeBytes: number | bigint
+  encryptedSizeKB: number
+  encryptedSizeBytes: number | bigint
+  /** cenc / cbcs */
+  scheme: string
+  /** 十六进制 KID */
+  kid: string
+  tracks: number
+  fragments: number
+  samples: number
+}
+/** gocryptfsEncryptDirectory() 的结果 */
+export interface GocryptfsResult {
+  files: number
+  directories: number
+  fileSizeKB: number
+  fileSizeBytes: number | bigint
+  encryptedSizeKB: number
+  encryptedSizeBytes: number | bigint
+  /** 解密 gocryptfs.conf 得到的主密钥，可用于 gocryptfsEncryptPath() 等 */
+  masterKey: Buffer
+}
+/** encryptDirectory() 的结果 */
+export interface DirectoryEncryptResult {
+  files: number
+  directories: number
+  fileSizeKB: number
+  fileSizeBytes: number | bigint
+  /** 加密后的总大小（KB），dryRun 时为 0 */
+  encryptedSizeKB: number
+  encryptedSizeBytes: number | bigint
+  /** 加密清单的路径 */
+  manifestPath: string
+  /** dryRun 时计划加密的文件，否则为 null */
+  plan: Array<PlannedFile> | null
+}
+/** decryptDirectory() 的结果 */
+export interface DirectoryDecryptResult {
+  /** 还原的文件数 */
+  files: number
+  /** 还原的目录数 */
+  directories: number
+  fileSizeKB: number
+  fileSizeBytes: number | bigint
+  /** dryRun 时计划还原的文件，否则为 null */
+  plan: Array<PlannedFile> | null
+}
+/** encryptArchive() 的结果 */
+export interface ArchiveEncryptResult {
+  files: number
+  directories: number
+  fileSizeKB: number
+  fileSizeBytes: number | bigint
+  /** 加密后的归档大小（KB） */
+  archiveSizeKB: number
+  archiveSizeBytes: number | bigint
+  totalChunks: number
+}
+/** listArchive() 返回的一个条目 */
+export interface ArchiveEntry {
+  /** 相对于归档根部的路径，使用 `/` 分隔 */
+  path: string
+  /** file / directory */
+  type: string
+  /** 明文大小，目录为 0 */
+  sizeKB: number
+  sizeBytes: number | bigint
+  mode: number
+  /** 修改时间，Unix 毫秒时间戳 */
+  modified: number
+}
+/** listArchive() 的结果 */
+export interface ArchiveListResult {
+  /** archive（encryptArchive 创建的文件）/ directory（encryptDirectory 创建的目录） */
+  format: string
+  algorithm: string
+  /** 创建时间，Unix 毫秒时间戳 */
+  createdAt: number
+  /** 先列出目录再列出文件，各自按路径排序 */
+  entries: Array<ArchiveEntry>
+  files: number
+  directories: number
+  fileSizeKB: number
+  fileSizeBytes: number | bigint
+}
+/** s3EncryptFile() 的结果 */
+export interface S3EncryptResult {
+  fileSizeKB: number
+  fileSizeBytes: number | bigint
+  encryptedSizeKB: number
+  encryptedSizeBytes: number | bigint
+  /** 上传时需要设置的 x-amz-meta-* 对象元数据（写入指令文件时同样返回） */
+  metadata: Record<string, string>
+}
+/** veracryptListFiles() 中的一个条目 */
+export interface VeraCryptEntry {
+  path: string
+  isDirectory: boolean
+  fileSizeKB: number
+  fileSizeBytes: number | bigint
+  /** 修改时间，Unix 毫秒时间戳 */
+  modified: number | null
+}
+/** veracryptExtractFiles() 的结果 */
+export interface VeraCryptExtractResult {
+  files: number
+  directories: number
+  fileSizeKB: number
+  fileSizeBytes: number | bigint
+  encryptedSizeKB: number
+  encryptedSizeBytes: number | bigint
+  /** FAT12 / FAT16 / FAT32 / exFAT */
+  filesystem: string
+  hash: string
+  hiddenVolume: boolean
+  outputDir: string
+}
+/** inspectFile() 的结果，文件中未记录的字段为 null */
+export interface InspectFileResult {
+  formatVersion: number
+  /** monolithic / chunked */
+  layout: string
+  chunked: boolean
+  algorithm: string | null
+  fileSizeKB: number | null
+  fileSizeBytes: number | bigint | null
+  encryptedSizeKB: number
+  encryptedSizeBytes: number | bigint
+  chunkSizeKB: number | null
+  chunkSizeBytes: number | bigint | null
+  totalChunks: number
+  kdf: string | null
+  hasFilename: boolean
+  /** 需要提供密钥 */
+  filename: string | null
+  hasMetadata: boolean
+  /** 需要提供密钥，保存时为 Buffer 的返回 Buffer，否则为 JSON 值 */
+  metadata: Buffer | any | null
+  mimeType: string | null
+  /** 加密时间，Unix 毫秒时间戳 */
+  createdAt: number | null
+  toolVersion: string | null
+  formatRevision: number | null
+  headerAuthenticated: boolean
+  chunksAuthenticated: boolean
+  hasParity: boolean
+  hashAlgorithm: string | null
+  compression: string | null
+  sparse: boolean
+  appendable: boolean
+}
+/** warmup() 的结果 */
+export interface WarmupResult {
+  /** 调用前是否已完成初始化 */
+  alreadyWarm: boolean
+  durationMs: number
+}
+/** getHardwareInfo() 的结果 */
+export interface HardwareInfo {
+  /** CPU 架构，如 "x86_64"、"aarch64" */
+  arch: string
+  /** x86 AES-NI 指令 */
+  aesNi: boolean
+  /** x86 PCLMULQDQ 指令 */
+  pclmul: boolean
+  /** ARM NEON 指令 */
+  neon: boolean
+  /** ARMv8 AES 指令 */
+  armAes: boolean
+  /** 本库的 AES 实现是否使用硬件指令 */
+  aesAccelerated: boolean
+  /** algorithm 为 "auto" 时选择的算法 */
+  autoAlgorithm: string
+}
+/** benchmarkAlgorithms() 中的一项测量 */
+export interface BenchmarkMeasurement {
+  /** "aes"、"chacha20poly1305"、"sha256" 或 "blake3" */
+  algorithm: string
+  /** "encrypt"、"decrypt" 或 "hash" */
+  operation: string
+  /** 每次处理的数据大小（KB） */
+  sizeKb: number
+  /** 计时期间执行的次数 */
+  iterations: number
+  /** 吞吐量，MB 按 1024 × 1024 字节计算 */
+  mbPerSec: number
+}
+/** benchmarkAlgorithms() 的结果 */
+export interface BenchmarkResult {
+  /** CPU 架构，如 "x86_64"、"aarch64" */
+  arch: string
+  /** 本库的 AES 实现是否使用硬件指令 */
+  aesAccelerated: boolean
+  measurements: Array<BenchmarkMeasurement>
+  /** 测量的加密算法中加密和解密平均吞吐量最高的算法，没有测量加密算法时为 null */
+  fastestAlgorithm: string | null
+  /** 测量的摘要算法中吞吐量最高的算法，没有测量摘要算法时为 null */
+  fastestHash: string | null
+  /** 整个测量耗费的时间（毫秒） */
+  durationMs: number
+}
+/** getConfig() 的结果 */
+export interface ModuleConfig {
+  threads: number
+  strict: boolean
+  logLevel: string
+  allowedAlgorithms: Array<string>
+  rejectLegacyFormats: boolean
+  requireAuthentication: boolean
+}
+/** setDecryptPolicy() 返回的生效策略 */
+export interface DecryptPolicyResult {
+  rejectLegacyFormats: boolean
+  requireAuthentication: boolean
+}
+/** watchAndEncrypt() 回调收到的事件 */
+export interface WatchEvent {
+  /** encrypted / failed */
+  type: string
+  /** 相对于监视目录的路径，扫描目录失败时为空字符串 */
+  path: string
+  outputPath?: string
+  /** 加密的明文大小（字节） */
+  fileSizeBytes?: number | bigint
+  error?: string
+  /** 事件时间，Unix 毫秒时间戳 */
+  timestamp: number
+}
+/** 加密文件 - 普通文件边读边加密；管道输入、压缩和 mmap 时整体读入内存 */
+export declare function encryptFile(algorithm: string, key: Buffer, inputPath: string | number, outputPath: string | number, options?: EncryptOptions | undefined | null): EncryptFileResult
+/** encryptFile() 的异步版本，在 libuv 线程池中运行并返回 Promise */
+export declare function encryptFileAsync(algorithm: string, key: Buffer, inputPath: string | number, outputPath: string | number, options?: EncryptOptions | undefined | null, signal?: AbortSignal | Job | undefined | null): Promise<EncryptFileResult>
 /** 解密文件 - 适用于小到中等大小的文件 */
-export declare function decryptFile(algorithm: string, key: Buffer, inputPath: string, outputPath: string): object
+export declare function decryptFile(algorithm: string, key: Buffer, inputPath: string | number, outputPath: string | number, options?: DecryptOptions | undefined | null): DecryptFileResult
+/** decryptFile() 的异步版本，在 libuv 线程池中运行并返回 Promise */
+export declare function decryptFileAsync(algorithm: string, key: Buffer, inputPath: string | number, outputPath: string | number, options?: DecryptOptions | undefined | null, signal?: AbortSignal | Job | undefined | null): Promise<DecryptFileResult>
 /** 分片加密文件 - 用于超大文件，带有分片处理功能 */
-export declare function chunkEncryptFile(algorithm: string, key: Buffer, inputPath: string, outputPath: string, chunkSizeMb: number): object
+export declare function chunkEncryptFile(algorithm: string, key: Buffer, inputPath: string | number, outputPath: string | number, chunkSizeMb: number, options?: EncryptOptions | undefined | null): ChunkEncryptFileResult
+/** chunkEncryptFile() 的异步版本，在 libuv 线程池中运行并返回 Promise */
+export declare function chunkEncryptFileAsync(algorithm: string, key: Buffer, inputPath: string | number, outputPath: string | number, chunkSizeMb: number, options?: EncryptOptions | undefined | null, signal?: AbortSignal | Job | undefined | null): Promise<ChunkEncryptFileResult>
 /** 分片解密文件 - 用于超大文件，处理分片加密的文件 */
-export declare function chunkDecryptFile(algorithm: string, key: Buffer, inputPath: string, outputPath: string): object
+export declare function chunkDecryptFile(algorithm: string, key: Buffer, inputPath: string | number, outputPath: string | number, options?: DecryptOptions | undefined | null): ChunkDecryptFileResult
+/** chunkDecryptFile() 的异步版本，在 libuv 线程池中运行并返回 Promise */
+export declare function chunkDecryptFileAsync(algorithm: string, key: Buffer, inputPath: string | number, outputPath: string | number, options?: DecryptOptions | undefined | null, signal?: AbortSignal | Job | undefined | null): Promise<ChunkDecryptFileResult>
+/** 分卷解密 - 校验分卷的顺序、完整性和归属后跨分卷流式解密；volumes 为分卷路径数组，或基础路径/第一个分卷的路径 */
+export declare function chunkDecryptVolumes(algorithm: string, key: Buffer, volumes: string | Array<string>, outputPath: string | number, options?: DecryptOptions | undefined | null): ChunkDecryptFileResult
+/** chunkDecryptVolumes() 的异步版本，在 libuv 线程池中运行并返回 Promise */
+export declare function chunkDecryptVolumesAsync(algorithm: string, key: Buffer, volumes: string | Array<string>, outputPath: string | number, options?: DecryptOptions | undefined | null, signal?: AbortSignal | Job | undefined | null): Promise<ChunkDecryptFileResult>
+/** 整体加密文件转换为分片文件 - 在内存中解密后逐个分片重新加密写出，明文不会写入磁盘 */
+export declare function convertToChunked(algorithm: string, key: Buffer, inputPath: string | number, outputPath: string | number, chunkSizeMb: number, options?: ConvertOptions | undefined | null): ConvertToChunkedResult
+/** convertToChunked() 的异步版本，在 libuv 线程池中运行并返回 Promise */
+export declare function convertToChunkedAsync(algorithm: string, key: Buffer, inputPath: string | number, outputPath: string | number, chunkSizeMb: number, options?: ConvertOptions | undefined | null, signal?: AbortSignal | Job | undefined | null): Promise<ConvertToChunkedResult>
+/** 分片文件转换为整体加密文件 - 逐个分片解密到内存后整体重新加密写出，明文不会写入磁盘 */
+export declare function convertToMonolithic(algorithm: string, key: Buffer, inputPath: string | number, outputPath: string | number, options?: ConvertOptions | undefined | null): ConvertToMonolithicResult
+/** convertToMonolithic() 的异步版本，在 libuv 线程池中运行并返回 Promise */
+export declare function convertToMonolithicAsync(algorithm: string, key: Buffer, inputPath: string | number, outputPath: string | number, options?: ConvertOptions | undefined | null, signal?: AbortSignal | Job | undefined | null): Promise<ConvertToMonolithicResult>
+/** 重新分片 - 逐个分片解密后按新的分片大小重新加密写出，明文不会写入磁盘 */
+export declare function rechunkFile(algorithm: string, key: Buffer, inputPath: string | number, outputPath: string | number, newChunkSizeMb: number, options?: ConvertOptions | undefined | null): RechunkFileResult
+/** rechunkFile() 的异步版本，在 libuv 线程池中运行并返回 Promise */
+export declare function rechunkFileAsync(algorithm: string, key: Buffer, inputPath: string | number, outputPath: string | number, newChunkSizeMb: number, options?: ConvertOptions | undefined | null, signal?: AbortSignal | Job | undefined | null): Promise<RechunkFileResult>
+/** 迁移旧格式文件 - 将 v1 `CHUNKS:` 分片文件原地改写为带认证的 v2 容器格式，保留原始大小和分片大小，返回迁移报告 */
+export declare function migrateFile(algorithm: string, key: Buffer, path: string, options?: ConvertOptions | undefined | null): MigrateFileResult
+/** migrateFile() 的异步版本，在 libuv 线程池中运行并返回 Promise */
+export declare function migrateFileAsync(algorithm: string, key: Buffer, path: string, options?: ConvertOptions | undefined | null, signal?: AbortSignal | Job | undefined | null): Promise<MigrateFileResult>
+/** 批量迁移旧格式文件 - 逐个迁移，单个文件失败不影响其它文件，失败原因记录在对应的报告中 */
+export declare function migrateFiles(algorithm: string, key: Buffer, paths: Array<string>, options?: ConvertOptions | undefined | null): Array<MigrateFileResult>
+/** migrateFiles() 的异步版本，在 libuv 线程池中运行并返回 Promise */
+export declare function migrateFilesAsync(algorithm: string, key: Buffer, paths: Array<string>, options?: ConvertOptions | undefined | null, signal?: AbortSignal | Job | undefined | null): Promise<Array<MigrateFileResult>>
+/** 增量更新分片文件 - 明文修改后（大小不变）依据分片校验值只重新加密变化的分片，并更新分片索引中的校验值、明文摘要和文件 MAC */
+export declare function updateEncryptedFile(plainPath: string | number, encryptedPath: string, key: Buffer, options?: ConvertOptions | undefined | null): UpdateEncryptedFileResult
+/** updateEncryptedFile() 的异步版本，在 libuv 线程池中运行并返回 Promise */
+export declare function updateEncryptedFileAsync(plainPath: string | number, encryptedPath: string, key: Buffer, options?: ConvertOptions | undefined | null, signal?: AbortSignal | Job | undefined | null): Promise<UpdateEncryptedFileResult>
+/** 追加数据 - 在用 appendable 选项创建的分片文件末尾追加加密数据，之前的完整分片不会重写，适合持续增长的日志 */
+export declare function appendChunkedFile(encryptedPath: string, key: Buffer, data: Buffer, options?: ConvertOptions | undefined | null): AppendChunkedFileResult
+/** appendChunkedFile() 的异步版本，在 libuv 线程池中运行并返回 Promise */
+export declare function appendChunkedFileAsync(encryptedPath: string, key: Buffer, data: Buffer, options?: ConvertOptions | undefined | null, signal?: AbortSignal | Job | undefined | null): Promise<AppendChunkedFileResult>
+/** 校验分片文件 - 逐个分片在内存中解密和认证，不写出任何明文；返回每个分片的状态和整体结果 */
+export declare function verifyChunkedFile(path: string | number, key: Buffer, options?: VerifyOptions | undefined | null): VerifyChunkedFileResult
+/** verifyChunkedFile() 的异步版本，在 libuv 线程池中运行并返回 Promise */
+export declare function verifyChunkedFileAsync(path: string | number, key: Buffer, options?: VerifyOptions | undefined | null, signal?: AbortSignal | Job | undefined | null): Promise<VerifyChunkedFileResult>
 /** 单个分片的解密 - 用于视频实时播放场景 */
-export declare function decryptSingleChunk(algorithm: string, key: Buffer, inputPath: string, chunkIndex: number): Buffer
-/** 获取分片加密文件的元数据 - 用于视频播放前获取文件信息 */
-export declare function getChunkedFileMetadata(inputPath: string): object
+export declare function decryptSingleChunk(algorithm: string, key: Buffer, inputPath: string | number, chunkIndex: number): Buffer
+/** decryptSingleChunk() 的异步版本，在 libuv 线程池中运行并返回 Promise */
+export declare function decryptSingleChunkAsync(algorithm: string, key: Buffer, inputPath: string | number, chunkIndex: number, signal?: AbortSignal | Job | undefined | null): Promise<Buffer>
+/** 读取文件末尾的明文 - 通过分片索引只解密返回最后 bytes 个字节所需的最后几个分片，用于查看持续追加的加密日志的最新内容 */
+export declare function decryptTail(path: string | number, key: Buffer, bytes: number): Buffer
+/** decryptTail() 的异步版本，在 libuv 线程池中运行并返回 Promise */
+export declare function decryptTailAsync(path: string | number, key: Buffer, bytes: number, signal?: AbortSignal | Job | undefined | null): Promise<Buffer>
+/**
+ * 随机读取分片加密文件的明文 - 只打开一次文件、读取一次文件头和分片索引，适合视频拖动播放等频繁的随机读取
+ *
+ * 没有分片索引的文件在打开时逐个跳过分片帧建立索引。每次 read() 只解密覆盖读取范围的分片，
+ * 并保留最近解密的一个分片，连续的小块读取不会重复解密同一分片；设置 prefetch 时在后台预先解密之后的分片。
+ */
+export class DecryptedReader {
+   constructor(algorithm: string, key: Buffer, inputPath: string | number, options?: ReaderOptions | undefined | null)
+  /** 明文总大小 */
+  get size(): number
+  /** 读取从 offset 开始的 length 个明文字节；超出文件末尾的部分不返回，offset 位于末尾或之后时返回空 Buffer */
+   read(offset: number, length: number): Buffer
+  /** 关闭文件，之后不能再读取；重复调用没有影响 */
+   close(): void
+}
+/**
+ * 分片加密文件的句柄 - 只打开一次文件、校验一次文件头并读取分片索引，之后按序号解密分片
+ *
+ * 用于代替对同一文件重复调用 decryptSingleChunk，省去每次打开文件、解析文件头和定位分片的开销。
+ */
+export class ChunkedFileHandle {
+   constructor(algorithm: string, key: Buffer, inputPath: string | number, options?: ReaderOptions | undefined | null)
+  /** 明文总大小 */
+  get size(): number
+  /** 分片大小（字节） */
+  get chunkSize(): number
+  /** 分片总数 */
+  get totalChunks(): number
+  /** 解密并返回第 chunk_index 个分片（从 0 开始）；与 decryptSingleChunk 相同，只校验该分片自身的标签、序号和校验值 */
+   decryptChunk(chunkIndex: number): Buffer
+  /** 关闭文件，之后不能再解密；重复调用没有影响 */
+   close(): void
+}
+/** 获取分片加密文件的元数据 - 用于视频播放前获取文件信息；v2 文件同时返回结尾字段，提供密钥时解密明文 SHA-256 */
+export declare function getChunkedFileMetadata(inputPath: string | number, key?: Buffer | undefined | null): ChunkedFileMetadata
+/** 修复文件 - 用加密时附加的纠错数据找出并恢复损坏的块，无需密钥；修复后的内容在解密时仍会经过完整的认证 */
+export declare function repairFile(path: string): RepairFileResult
+/** repairFile() 的异步版本，在 libuv 线程池中运行并返回 Promise */
+export declare function repairFileAsync(path: string, signal?: AbortSignal | Job | undefined | null): Promise<RepairFileResult>
+/** 存入内容寻址存储 - 文件按内容切分为分片，分片加密后以 keyed hash 为名保存，相同内容的分片只保存一次 */
+export declare function storeFile(storeDir: string, algorithm: string, key: Buffer, inputPath: string | number, options?: StoreOptions | undefined | null): StoreFileResult
+/** storeFile() 的异步版本，在 libuv 线程池中运行并返回 Promise */
+export declare function storeFileAsync(storeDir: string, algorithm: string, key: Buffer, inputPath: string | number, options?: StoreOptions | undefined | null, signal?: AbortSignal | Job | undefined | null): Promise<StoreFileResult>
+/** 从内容寻址存储取回文件 - 按清单解密并核对每个分片，写入 outputPath */
+export declare function restoreFile(storeDir: string, key: Buffer, name: string, outputPath: string | number, options?: RestoreOptions | undefined | null): RestoreFileResult
+/** restoreFile() 的异步版本，在 libuv 线程池中运行并返回 Promise */
+export declare function restoreFileAsync(storeDir: string, key: Buffer, name: string, outputPath: string | number, options?: RestoreOptions | undefined | null, signal?: AbortSignal | Job | undefined | null): Promise<RestoreFileResult>
+/** 列出内容寻址存储中可以用该密钥解密的文件 */
+export declare function listStoredFiles(storeDir: string, key: Buffer): Array<StoredFileEntry>
+/** 以 age v1 格式加密文件 - 输出可以用标准的 age 命令行工具解密，使用口令或 X25519 接收者 */
+export declare function ageEncryptFile(inputPath: string | number, outputPath: string | number, options: AgeEncryptOptions): AgeEncryptResult
+/** ageEncryptFile() 的异步版本，在 libuv 线程池中运行并返回 Promise */
+export declare function ageEncryptFileAsync(inputPath: string | number, outputPath: string | number, options: AgeEncryptOptions, signal?: AbortSignal | Job | undefined | null): Promise<AgeEncryptResult>
+/** 解密 age v1 文件（二进制或 ASCII 封装）- 可以解密标准 age 命令行工具生成的文件 */
+export declare function ageDecryptFile(inputPath: string | number, outputPath: string | number, options: AgeDecryptOptions): AgeDecryptResult
+/** ageDecryptFile() 的异步版本，在 libuv 线程池中运行并返回 Promise */
+export declare function ageDecryptFileAsync(inputPath: string | number, outputPath: string | number, options: AgeDecryptOptions, signal?: AbortSignal | Job | undefined | null): Promise<AgeDecryptResult>
+/** 生成新的 age X25519 身份，返回 { identity, recipient }；identity 为私钥，需要妥善保存 */
+export declare function generateAgeIdentity(): AgeIdentity
+/** 以 `openssl enc -aes-256-cbc -salt` 的格式加密文件 - 输出可以用 `openssl enc -d` 以相同的口令和参数解密 */
+export declare function opensslEncryptFile(inputPath: string | number, outputPath: string | number, passphrase: string, options?: OpensslOptions | undefined | null): FileSizes
+/** opensslEncryptFile() 的异步版本，在 libuv 线程池中运行并返回 Promise */
+export declare function opensslEncryptFileAsync(inputPath: string | number, outputPath: string | number, passphrase: string, options?: OpensslOptions | undefined | null, signal?: AbortSignal | Job | undefined | null): Promise<FileSizes>
+/** 解密 `openssl enc -aes-256-cbc` 生成的加盐文件 - 口令和密钥派生参数必须与加密时一致 */
+export declare function opensslDecryptFile(inputPath: string | number, outputPath: string | number, passphrase: string, options?: OpensslOptions | undefined | null): DecryptedFile
+/** opensslDecryptFile() 的异步版本，在 libuv 线程池中运行并返回 Promise */
+export declare function opensslDecryptFileAsync(inputPath: string | number, outputPath: string | number, passphrase: string, options?: OpensslOptions | undefined | null, signal?: AbortSignal | Job | undefined | null): Promise<DecryptedFile>
+/** 解密 `gpg -c` 生成的 OpenPGP 对称加密文件（AES，SEIPD + MDC）- 完整性校验失败时删除输出文件 */
+export declare function gpgDecryptFile(inputPath: string | number, outputPath: string | number, passphrase: string, options?: GpgDecryptOptions | undefined | null): GpgDecryptResult
+/** gpgDecryptFile() 的异步版本，在 libuv 线程池中运行并返回 Promise */
+export declare function gpgDecryptFileAsync(inputPath: string | number, outputPath: string | number, passphrase: string, options?: GpgDecryptOptions | undefined | null, signal?: AbortSignal | Job | undefined | null): Promise<GpgDecryptResult>
+/** 以口令加密文件，输出 OpenPGP AEAD 加密消息 - 默认为 RFC 9580 格式（v6 SKESK + v2 SEIPD），也可以输出 GnuPG 使用的 LibrePGP OCB 格式 */
+export declare function gpgEncryptFile(inputPath: string | number, outputPath: string | number, passphrase: string, options?: GpgEncryptOptions | undefined | null): GpgEncryptResult
+/** gpgEncryptFile() 的异步版本，在 libuv 线程池中运行并返回 Promise */
+export declare function gpgEncryptFileAsync(inputPath: string | number, outputPath: string | number, passphrase: string, options?: GpgEncryptOptions | undefined | null, signal?: AbortSignal | Job | undefined | null): Promise<GpgEncryptResult>
+/** 把文件和目录打包为 WinZip AES-256（AE-2）加密的 ZIP 文件 - 可以直接用 7-Zip、WinZip 等工具输入口令解压 */
+export declare function encryptToZip(inputs: Array<string>, outputPath: string | number, password: string, options?: ZipOptions | undefined | null): ZipResult
+/** encryptToZip() 的异步版本，在 libuv 线程池中运行并返回 Promise */
+export declare function encryptToZipAsync(inputs: Array<string>, outputPath: string | number, password: string, options?: ZipOptions | undefined | null, signal?: AbortSignal | Job | undefined | null): Promise<ZipResult>
+/** 把文件和目录打包为 AES-256 加密的 7z 归档（可选 LZMA2 压缩）- 文件列表同样加密，可以用 7-Zip 输入口令解压 */
+export declare function create7zArchive(inputs: Array<string>, outputPath: string | number, password: string, options?: SevenZipOptions | undefined | null): SevenZipResult
+/** create7zArchive() 的异步版本，在 libuv 线程池中运行并返回 Promise */
+export declare function create7zArchiveAsync(inputs: Array<string>, outputPath: string | number, password: string, options?: SevenZipOptions | undefined | null, signal?: AbortSignal | Job | undefined | null): Promise<SevenZipResult>
+/** 把 7z 归档解压到目录 - 支持 AES-256 加密的归档，未加密的归档 password 传 null；失败时删除已解压的文件 */
+export declare function extract7zArchive(inputPath: string | number, outputDir: string, password?: string | undefined | null, options?: ExtractOptions | undefined | null): ExtractResult
+/** extract7zArchive() 的异步版本，在 libuv 线程池中运行并返回 Promise */
+export declare function extract7zArchiveAsync(inputPath: string | number, outputDir: string, password?: string | undefined | null, options?: ExtractOptions | undefined | null, signal?: AbortSignal | Job | undefined | null): Promise<ExtractResult>
+/** 以 libsodium `crypto_secretstream_xchacha20poly1305` 的格式加密文件 - 输出可以直接用 libsodium（Python、Go 等）按相同的分块长度解密 */
+export declare function secretstreamEncryptFile(key: Buffer, inputPath: string | number, outputPath: string | number, options?: SecretStreamOptions | undefined | null): SecretStreamEncryptResult
+/** secretstreamEncryptFile() 的异步版本，在 libuv 线程池中运行并返回 Promise */
+export declare function secretstreamEncryptFileAsync(key: Buffer, inputPath: string | number, outputPath: string | number, options?: SecretStreamOptions | undefined | null, signal?: AbortSignal | Job | undefined | null): Promise<SecretStreamEncryptResult>
+/** 解密 libsodium `crypto_secretstream_xchacha20poly1305` 格式的文件 - 分块长度必须与加密时相同，被截断或篡改时报错并删除输出文件 */
+export declare function secretstreamDecryptFile(key: Buffer, inputPath: string | number, outputPath: string | number, options?: SecretStreamOptions | undefined | null): SecretStreamDecryptResult
+/** secretstreamDecryptFile() 的异步版本，在 libuv 线程池中运行并返回 Promise */
+export declare function secretstreamDecryptFileAsync(key: Buffer, inputPath: string | number, outputPath: string | number, options?: SecretStreamOptions | undefined | null, signal?: AbortSignal | Job | undefined | null): Promise<SecretStreamDecryptResult>
+/** 以 Tink 流式 AEAD（AES-GCM-HKDF-STREAMING）的格式加密文件 - 输出可以用 Java 等语言的 Tink 以相同的密钥、参数和附加数据解密 */
+export declare function tinkEncryptFile(key: Buffer, inputPath: string | number, outputPath: string | number, options?: TinkStreamingOptions | undefined | null): TinkEncryptResult
+/** tinkEncryptFile() 的异步版本，在 libuv 线程池中运行并返回 Promise */
+export declare function tinkEncryptFileAsync(key: Buffer, inputPath: string | number, outputPath: string | number, options?: TinkStreamingOptions | undefined | null, signal?: AbortSignal | Job | undefined | null): Promise<TinkEncryptResult>
+/** 解密 Tink 流式 AEAD（AES-GCM-HKDF-STREAMING）文件 - 密钥、参数和附加数据必须与加密时一致，被截断或篡改时报错并删除输出文件 */
+export declare function tinkDecryptFile(key: Buffer, inputPath: string | number, outputPath: string | number, options?: TinkStreamingOptions | undefined | null): TinkDecryptResult
+/** tinkDecryptFile() 的异步版本，在 libuv 线程池中运行并返回 Promise */
+export declare function tinkDecryptFileAsync(key: Buffer, inputPath: string | number, outputPath: string | number, options?: TinkStreamingOptions | undefined | null, signal?: AbortSignal | Job | undefined | null): Promise<TinkDecryptResult>
+/** 生成新的 Fernet 密钥（URL 安全 base64 编码的 32 字节），与 Python 的 `Fernet.generate_key()` 相同 */
+export declare function generateFernetKey(): string
+/** 生成 Fernet 令牌 - 与 Python cryptography 库的 `Fernet(key).encrypt(data)` 兼容，字符串按 UTF-8 加密 */
+export declare function fernetEncrypt(key: string, data: Buffer | string): string
+/** 验证并解密 Fernet 令牌 - 与 Python 的 `Fernet(key).decrypt(token, ttl)` 兼容，设置 ttl 时拒绝过期的令牌 */
+export declare function fernetDecrypt(key: string, token: string | Buffer, options?: FernetDecryptOptions | undefined | null): Buffer
+/** 生成 JWE 紧凑序列化令牌 - 支持 dir、A128KW/A192KW/A256KW 和 RSA-OAEP/RSA-OAEP-256 密钥管理，内容以 AES-GCM 加密 */
+export declare function encryptJwe(payload: Buffer | string, key: Buffer | object | string, options: JweEncryptOptions): string
+/** 解密 JWE 紧凑序列化令牌，返回 { plaintext, header } - 密钥类型决定可以接受的 alg，认证失败时报错 */
+export declare function decryptJwe(token: string, key: Buffer | object | string): JweDecryptResult
+/** 生成 PASETO v4.local 令牌 - XChaCha20 加密、BLAKE2b-MAC 认证，可选 footer 和隐式断言 */
+export declare function pasetoEncrypt(key: Buffer, payload: Buffer | string, options?: PasetoOptions | undefined | null): string
+/** 验证并解密 PASETO v4.local 令牌，返回 { payload, footer } - 设置 footer 时令牌的 footer 必须与之相同 */
+export declare function pasetoDecrypt(key: Buffer, token: string, options?: PasetoOptions | undefined | null): PasetoDecryptResult
+/** 把文件加密为 CMS / PKCS#7 EnvelopedData - 内容密钥以每个接收方 X.509 证书中的 RSA 公钥加密，输出 DER 或 PEM */
+export declare function cmsEncryptFile(recipients: Array<Buffer | string>, inputPath: string, outputPath: string, options?: CmsEncryptOptions | undefined | null): CmsEncryptResult
+/** cmsEncryptFile() 的异步版本，在 libuv 线程池中运行并返回 Promise */
+export declare function cmsEncryptFileAsync(recipients: Array<Buffer | string>, inputPath: string, outputPath: string, options?: CmsEncryptOptions | undefined | null, signal?: AbortSignal | Job | undefined | null): Promise<CmsEncryptResult>
+/** 生成 minisign 密钥对，返回 { publicKey, secretKey, keyId } - 公钥和私钥为 minisign 公钥文件和私钥文件的内容 */
+export declare function generateMinisignKeyPair(options?: MinisignKeyOptions | undefined | null): MinisignKeyPair
+/** 以 minisign 格式为文件签名并写出 .minisig 文件 - 可以用 `minisign -V` 或 `rsign verify` 验证 */
+export declare function minisignSignFile(inputPath: string | number, secretKey: string, options?: MinisignSignOptions | undefined | null): MinisignSignResult
+/** minisignSignFile() 的异步版本，在 libuv 线程池中运行并返回 Promise */
+export declare function minisignSignFileAsync(inputPath: string | number, secretKey: string, options?: MinisignSignOptions | undefined | null, signal?: AbortSignal | Job | undefined | null): Promise<MinisignSignResult>
+/** 验证文件的 minisign 签名，返回 { valid, keyId, trustedComment, error } - 签名不匹配时 valid 为 false，格式错误或密钥 ID 不符时报错 */
+export declare function minisignVerifyFile(inputPath: string | number, publicKey: string, options?: MinisignVerifyOptions | undefined | null): MinisignVerifyResult
+/** minisignVerifyFile() 的异步版本，在 libuv 线程池中运行并返回 Promise */
+export declare function minisignVerifyFileAsync(inputPath: string | number, publicKey: string, options?: MinisignVerifyOptions | undefined | null, signal?: AbortSignal | Job | undefined | null): Promise<MinisignVerifyResult>
+/** 生成 saltpack 使用的 Curve25519 密钥对，返回 { publicKey, secretKey }，均为 32 字节 */
+export declare function generateSaltpackKeyPair(): SaltpackKeyPair
+/** 以 saltpack 加密格式（v2）加密文件 - 二进制或 base62 文本封装，可以用 Keybase 时代的 saltpack 工具解密 */
+export declare function saltpackEncryptFile(inputPath: string | number, outputPath: string | number, options: SaltpackEncryptOptions): SaltpackEncryptResult
+/** saltpackEncryptFile() 的异步版本，在 libuv 线程池中运行并返回 Promise */
+export declare function saltpackEncryptFileAsync(inputPath: string | number, outputPath: string | number, options: SaltpackEncryptOptions, signal?: AbortSignal | Job | undefined | null): Promise<SaltpackEncryptResult>
+/** 解密 saltpack 加密消息（v1 或 v2，二进制或 base62 文本封装），返回发送者公钥，匿名发送时为 null */
+export declare function saltpackDecryptFile(inputPath: string | number, outputPath: string | number, options: SaltpackDecryptOptions): SaltpackDecryptResult
+/** saltpackDecryptFile() 的异步版本，在 libuv 线程池中运行并返回 Promise */
+export declare function saltpackDecryptFileAsync(inputPath: string | number, outputPath: string | number, options: SaltpackDecryptOptions, signal?: AbortSignal | Job | undefined | null): Promise<SaltpackDecryptResult>
+/** 按 HLS 的 AES-128 方法加密一个 MPEG-TS / fMP4 分段，返回播放列表中使用的 `#EXT-X-KEY` 行，可选写出密钥文件 */
+export declare function hlsEncryptSegment(key: Buffer, inputPath: string | number, outputPath: string | number, options: HlsSegmentOptions): HlsSegmentResult
+/** hlsEncryptSegment() 的异步版本，在 libuv 线程池中运行并返回 Promise */
+export declare function hlsEncryptSegmentAsync(key: Buffer, inputPath: string | number, outputPath: string | number, options: HlsSegmentOptions, signal?: AbortSignal | Job | undefined | null): Promise<HlsSegmentResult>
+/** 以通用加密（CENC，cenc 或 cbcs 方案）加密分片 MP4 文件 - 使用给定的 16 字节密钥和 KID，输出可以在浏览器中通过 EME Clear Key 播放 */
+export declare function cencEncryptFile(key: Buffer, kid: Buffer, inputPath: string | number, outputPath: string | number, options?: CencEncryptOptions | undefined | null): CencEncryptResult
+/** cencEncryptFile() 的异步版本，在 libuv 线程池中运行并返回 Promise */
+export declare function cencEncryptFileAsync(key: Buffer, kid: Buffer, inputPath: string | number, outputPath: string | number, options?: CencEncryptOptions | undefined | null, signal?: AbortSignal | Job | undefined | null): Promise<CencEncryptResult>
+/** 以 rclone crypt 远端的格式加密文件 - 上传到 crypt 远端底层存储后可以直接由 rclone 读取 */
+export declare function rcloneEncryptFile(inputPath: string | number, outputPath: string | number, options: RcloneCryptOptions): FileSizes
+/** rcloneEncryptFile() 的异步版本，在 libuv 线程池中运行并返回 Promise */
+export declare function rcloneEncryptFileAsync(inputPath: string | number, outputPath: string | number, options: RcloneCryptOptions, signal?: AbortSignal | Job | undefined | null): Promise<FileSizes>
+/** 解密 rclone crypt 远端底层存储中的加密文件 */
+export declare function rcloneDecryptFile(inputPath: string | number, outputPath: string | number, options: RcloneCryptOptions): DecryptedFile
+/** rcloneDecryptFile() 的异步版本，在 libuv 线程池中运行并返回 Promise */
+export declare function rcloneDecryptFileAsync(inputPath: string | number, outputPath: string | number, options: RcloneCryptOptions, signal?: AbortSignal | Job | undefined | null): Promise<DecryptedFile>
+/** 按 rclone crypt 的文件名加密规则加密以 `/` 分隔的相对路径，返回底层存储中的路径 */
+export declare function rcloneEncryptName(path: string, options: RcloneCryptOptions): string
+/** 把 rclone crypt 底层存储中的路径还原为原始路径 */
+export declare function rcloneDecryptName(path: string, options: RcloneCryptOptions): string
+/** 把明文目录树加密为 gocryptfs 密文目录（v2 格式，`gocryptfs -init` 的默认特性），可以直接用 gocryptfs 挂载读取 */
+export declare function gocryptfsEncryptDirectory(inputDir: string, outputDir: string, options: GocryptfsOptions): GocryptfsResult
+/** gocryptfsEncryptDirectory() 的异步版本，在 libuv 线程池中运行并返回 Promise */
+export declare function gocryptfsEncryptDirectoryAsync(inputDir: string, outputDir: string, options: GocryptfsOptions, signal?: AbortSignal | Job | undefined | null): Promise<GocryptfsResult>
+/** 计算明文相对路径在 gocryptfs 密文目录中对应的路径（同一目录中相同的名称总是得到相同的密文） */
+export declare function gocryptfsEncryptPath(cipherDir: string, path: string, options: GocryptfsOptions): string
+/** 把 gocryptfs 密文目录中的相对路径还原为明文路径 */
+export declare function gocryptfsDecryptPath(cipherDir: string, path: string, options: GocryptfsOptions): string
+/** 按 AWS S3 加密客户端 V2/V3 的信封格式加密文件 - 内容以 AES-256-GCM 加密，返回的 metadata 作为对象的用户元数据上传后可以由官方 SDK 解密 */
+export declare function s3EncryptFile(inputPath: string, outputPath: string, key: Buffer | object | string, options?: S3EncryptOptions | undefined | null): S3EncryptResult
+/** s3EncryptFile() 的异步版本，在 libuv 线程池中运行并返回 Promise */
+export declare function s3EncryptFileAsync(inputPath: string, outputPath: string, key: Buffer | object | string, options?: S3EncryptOptions | undefined | null, signal?: AbortSignal | Job | undefined | null): Promise<S3EncryptResult>
+/** 解密 AWS S3 加密客户端 V2/V3 加密的对象 - metadata 为对象的用户元数据（可以带 x-amz-meta- 前缀）或指令文件的内容，不支持 KMS 包装的数据密钥 */
+export declare function s3DecryptFile(inputPath: string, outputPath: string, key: Buffer | object | string, metadata: Record<string, string>, options?: S3DecryptOptions | undefined | null): DecryptedFile
+/** s3DecryptFile() 的异步版本，在 libuv 线程池中运行并返回 Promise */
+export declare function s3DecryptFileAsync(inputPath: string, outputPath: string, key: Buffer | object | string, metadata: Record<string, string>, options?: S3DecryptOptions | undefined | null, signal?: AbortSignal | Job | undefined | null): Promise<DecryptedFile>
+/** 列出 VeraCrypt 文件容器中的文件和目录 - 只读打开标准卷或隐藏卷，支持 AES 加密、SHA-512 / SHA-256 卷头和 FAT / exFAT 文件系统 */
+export declare function veracryptListFiles(containerPath: string | number, password: string, options?: VeraCryptOptions | undefined | null): Array<VeraCryptEntry>
+/** veracryptListFiles() 的异步版本，在 libuv 线程池中运行并返回 Promise */
+export declare function veracryptListFilesAsync(containerPath: string | number, password: string, options?: VeraCryptOptions | undefined | null, signal?: AbortSignal | Job | undefined | null): Promise<Array<VeraCryptEntry>>
+/** 把 VeraCrypt 文件容器中的文件解压到目录 - 用于把旧的 VeraCrypt 保险库迁移为本库的格式，容器本身不会被修改 */
+export declare function veracryptExtractFiles(containerPath: string | number, outputDir: string, password: string, options?: VeraCryptOptions | undefined | null): VeraCryptExtractResult
+/** veracryptExtractFiles() 的异步版本，在 libuv 线程池中运行并返回 Promise */
+export declare function veracryptExtractFilesAsync(containerPath: string | number, outputDir: string, password: string, options?: VeraCryptOptions | undefined | null, signal?: AbortSignal | Job | undefined | null): Promise<VeraCryptExtractResult>
+/** 生成随机字节 - 使用与加密相同的随机数生成器，适合在本库之上构建自定义协议时生成密钥、盐值等 */
+export declare function randomBytes(size: number): Buffer
+/** 生成该算法一次加密所需长度的随机 IV / nonce：aes 为 16 字节，chacha20poly1305 为 12 字节 */
+export declare function generateNonce(algorithm: string): Buffer
+/**
+ * 由口令派生密钥 - 与使用口令的文件接口相同的 scrypt / PBKDF2，派生一次后可以在多次调用和会话之间复用
+ *
+ * 相同的口令、盐和参数总是得到相同的密钥；字符串按 UTF-8 处理。scrypt 的默认参数需要约 64 MiB 内存，耗时较长时请使用 deriveKeyAsync()。
+ */
+export declare function deriveKey(password: Buffer | string, salt: Buffer | string, options?: KdfOptions | undefined | null): Buffer
+/** deriveKey() 的异步版本，在 libuv 线程池中运行并返回 Promise */
+export declare function deriveKeyAsync(password: Buffer | string, salt: Buffer | string, options?: KdfOptions | undefined | null, signal?: AbortSignal | Job | undefined | null): Promise<Buffer>
+/** 加密字符串 - 按 UTF-8 加密，返回 base64（默认）或 hex 编码的密文，适合配置项和数据库字段；密文与 encryptFile() 写出的文件内容格式相同 */
+export declare function encryptString(algorithm: string, key: Buffer, text: string, encoding?: 'base64' | 'hex'): string
+/** 解密 encryptString() 生成的密文，encoding 须与加密时相同；明文必须是有效的 UTF-8 */
+export declare function decryptString(algorithm: string, key: Buffer, ciphertext: string, encoding?: 'base64' | 'hex'): string
+/**
+ * 创建加密用的 TransformStream（WHATWG Web Streams），可用于 fetch 的请求体和响应体
+ *
+ * 输出与 chunkEncryptFile() 写出的文件相同；size 为明文的总字节数（如 Content-Length），写入的数据总量必须与之相同。
+ * options 与 chunkEncryptFile() 相同，但只涉及输出文件的选项（分卷、纠错数据、稀疏、可追加、续传）不适用；未指定 MIME 类型时根据第一段数据检测。
+ */
+export declare function createEncryptStream(algorithm: string, key: Buffer, size: number, chunkSizeMb: number, options?: EncryptOptions | undefined | null): TransformStream<Uint8Array, Uint8Array>
+/**
+ * 创建解密用的 TransformStream，输入为 chunkEncryptFile() 或 createEncryptStream() 的输出
+ *
+ * 每个分片收齐后立即解密输出，输入结束时校验整个文件的 MAC 和明文摘要；出错时两端的流都会收到带错误码的错误。
+ */
+export declare function createDecryptStream(algorithm: string, key: Buffer): TransformStream<Uint8Array, Uint8Array>
+/**
+ * 从 JS 数据源加密到 JS 接收端 - 数据来自 gRPC 流或自定义存储驱动时不必经过中间文件
+ *
+ * source 为异步可迭代对象、可迭代对象，或每次返回一段明文（可以返回 Promise，结束时返回 null）的函数；
+ * sink 对每段密文调用一次，返回 Promise 时等它完成后再读取更多明文。输出以及 size 和 options 的要求与 createEncryptStream() 相同。
+ */
+export declare function encryptFromSource(algorithm: string, key: Buffer, source: AsyncIterable<Uint8Array> | Iterable<Uint8Array> | (() => Uint8Array | null | undefined | Promise<Uint8Array | null | undefined>), sink: (chunk: Buffer) => void | Promise<void>, size: number, chunkSizeMb: number, options?: EncryptOptions | undefined | null): Promise<void>
+/** 从 JS 数据源解密到 JS 接收端，source 和 sink 同 encryptFromSource()，解密和校验同 createDecryptStream() */
+export declare function decryptFromSource(algorithm: string, key: Buffer, source: AsyncIterable<Uint8Array> | Iterable<Uint8Array> | (() => Uint8Array | null | undefined | Promise<Uint8Array | null | undefined>), sink: (chunk: Buffer) => void | Promise<void>): Promise<void>
+/**
+ * 分段写入的分片加密文件 - 数据陆续到达时（如 HTTP 上传）边收边加密，不必先缓存到临时文件再调用 chunkEncryptFile
+ *
+ * 与 createEncryptStream() 相同，文件头记录原始大小，构造时必须声明明文的总长度（如 Content-Length）；
+ * 写入的数据超出声明的大小或 finish() 时不足都会出错。数据先写入临时文件，finish() 成功后才替换输出文件，
+ * 出错或调用 abort() 后删除临时文件。
+ */
+export class ChunkedWriter {
+   constructor(algorithm: string, key: Buffer, outputPath: string | number, size: number, chunkSizeMb: number, options?: EncryptOptions | undefined | null)
+  /** 加密并写出一段数据，凑满一个分片后立即写入输出文件 */
+   write(data: Buffer): void
+  /** 写入最后一个分片和文件结尾并关闭文件，之后不能再写入 */
+   finish(): ChunkEncryptFileResult
+  /** 放弃写入，关闭并删除未完成的输出文件；已经结束时没有影响 */
+   abort(): void
+}
+/** 批量加密文件 - 在 Rust 中以多个线程并行加密，单个文件失败不影响其它文件，失败原因记录在对应的结果中 */
+export declare function encryptFiles(algorithm: string, key: Buffer, entries: Array<BatchEncryptEntry>, options?: BatchOptions | undefined | null): BatchResult
+/** encryptFiles() 的异步版本，在 libuv 线程池中运行并返回 Promise */
+export declare function encryptFilesAsync(algorithm: string, key: Buffer, entries: Array<BatchEncryptEntry>, options?: BatchOptions | undefined | null, signal?: AbortSignal | Job | undefined | null): Promise<BatchResult>
+/** 批量解密文件 - 在 Rust 中以多个线程并行解密，根据文件头自动选择整体或分片解密；单个文件失败不影响其它文件 */
+export declare function decryptFiles(algorithm: string, key: Buffer, entries: Array<BatchDecryptEntry>, options?: BatchOptions | undefined | null): BatchResult
+/** decryptFiles() 的异步版本，在 libuv 线程池中运行并返回 Promise */
+export declare function decryptFilesAsync(algorithm: string, key: Buffer, entries: Array<BatchDecryptEntry>, options?: BatchOptions | undefined | null, signal?: AbortSignal | Job | undefined | null): Promise<BatchResult>
+/**
+ * 加密整个目录 - 递归地把每个文件加密为目标目录中同一相对路径下的 `<name>.enc` 分片文件，并写入加密清单
+ *
+ * 清单记录每个文件的相对路径、大小、权限、修改时间和明文 SHA-256。目标目录必须为空或不存在；出错时删除已写入的文件。
+ */
+export declare function encryptDirectory(inputDir: string, outputDir: string, key: Buffer, options?: DirectoryEncryptOptions | undefined | null): DirectoryEncryptResult
+/** encryptDirectory() 的异步版本，在 libuv 线程池中运行并返回 Promise */
+export declare function encryptDirectoryAsync(inputDir: string, outputDir: string, key: Buffer, options?: DirectoryEncryptOptions | undefined | null, signal?: AbortSignal | Job | undefined | null): Promise<DirectoryEncryptResult>
+/**
+ * 解密整个目录 - 读取 encryptDirectory() 写入的加密清单，还原整个目录树或 include 选中的路径
+ *
+ * 每个文件解密前核对清单中的明文 SHA-256，解密时校验分片 MAC 和结尾字段，并还原权限和修改时间。目标目录可以已存在；出错时删除本次创建的文件和目录。
+ */
+export declare function decryptDirectory(inputDir: string, outputDir: string, key: Buffer, options?: DirectoryDecryptOptions | undefined | null): DirectoryDecryptResult
+/** decryptDirectory() 的异步版本，在 libuv 线程池中运行并返回 Promise */
+export declare function decryptDirectoryAsync(inputDir: string, outputDir: string, key: Buffer, options?: DirectoryDecryptOptions | undefined | null, signal?: AbortSignal | Job | undefined | null): Promise<DirectoryDecryptResult>
+/**
+ * 加密为单个归档 - 把目录打包为 tar 并以流的方式分片加密为一个文件（通常以 `.zenc` 结尾），不会像 encryptDirectory() 那样暴露文件数量和大小
+ *
+ * tar 的第一个条目是记录目录和文件的清单，解密后的明文是普通的 tar 文件。出错时删除未完成的归档。
+ */
+export declare function encryptArchive(inputDir: string, outputPath: string, key: Buffer, options?: ArchiveEncryptOptions | undefined | null): ArchiveEncryptResult
+/** encryptArchive() 的异步版本，在 libuv 线程池中运行并返回 Promise */
+export declare function encryptArchiveAsync(inputDir: string, outputPath: string, key: Buffer, options?: ArchiveEncryptOptions | undefined | null, signal?: AbortSignal | Job | undefined | null): Promise<ArchiveEncryptResult>
+/**
+ * 解开加密归档 - 流式解密 encryptArchive() 的输出并解开整个目录树或 include 选中的路径，还原权限和修改时间
+ *
+ * 算法使用文件头中记录的算法。读完归档后校验整个文件的 MAC 和明文摘要，失败时删除本次创建的文件和目录。
+ */
+export declare function extractArchive(inputPath: string, outputDir: string, key: Buffer, options?: ArchiveExtractOptions | undefined | null): DirectoryDecryptResult
+/** extractArchive() 的异步版本，在 libuv 线程池中运行并返回 Promise */
+export declare function extractArchiveAsync(inputPath: string, outputDir: string, key: Buffer, options?: ArchiveExtractOptions | undefined | null, signal?: AbortSignal | Job | undefined | null): Promise<DirectoryDecryptResult>
+/** 列出归档内容 - 只解密清单，不解密文件数据；支持 encryptArchive 创建的文件和 encryptDirectory 创建的目录 */
+export declare function listArchive(path: string, key: Buffer): ArchiveListResult
+/** watchAndEncrypt() 返回的监视器 */
+export class Watcher {
+  /** 停止监视；正在加密的文件完成后监视线程退出，之后不再调用回调 */
+   close(): void
+  get closed(): boolean
+}
+/**
+ * 监视目录并自动加密 - 定期扫描 src_dir，把新出现或修改过的文件加密为 dest_dir 中同一相对路径下的 `<name>.enc` 分片文件
+ *
+ * 文件的大小和修改时间在 debounceMs 内不再变化才会被加密；每个文件加密成功或失败后调用 callback。返回的 Watcher 在 close() 之前保持进程运行。
+ */
+export declare function watchAndEncrypt(srcDir: string, destDir: string, key: Buffer, options: WatchOptions | undefined | null, callback: (event: WatchEvent) => void): Watcher
+/** 查看加密文件信息 - 无需密钥即可读取文件头中的元数据，支持分片和整体加密的文件；提供密钥时还会解密保存的原始文件名和用户元数据 */
+export declare function inspectFile(inputPath: string | number, key?: Buffer | undefined | null): InspectFileResult
+/** 检测文件格式 - 只读取文件开头的少量字节，返回 "plain"、"zippy-monolithic"、"zippy-chunked-v1" 等 */
+export declare function detectFormat(inputPath: string | number): string
+/** 判断文件是否为本库加密的文件 */
+export declare function isEncrypted(inputPath: string | number): boolean
+/** 预热 - 提前完成配置读取和算法自检，避免第一次加密时的额外延迟 */
+export declare function warmup(): WarmupResult
+/** 获取硬件加速信息 - CPU 支持的加密指令，以及 algorithm 为 "auto" 时选择的算法 */
+export declare function getHardwareInfo(): HardwareInfo
+/**
+ * 测量本机上各加密算法和摘要算法的吞吐量 - 用于应用启动时按设备选择默认算法
+ *
+ * 同步版本在测量期间阻塞 JS 线程，默认设置下约 1 秒，请优先使用 benchmarkAlgorithmsAsync()。
+ */
+export declare function benchmarkAlgorithms(options?: BenchmarkOptions | undefined | null): BenchmarkResult
+/** benchmarkAlgorithms() 的异步版本，在 libuv 线程池中运行并返回 Promise */
+export declare function benchmarkAlgorithmsAsync(options?: BenchmarkOptions | undefined | null, signal?: AbortSignal | Job | undefined | null): Promise<BenchmarkResult>
+/** 订阅所有任务的生命周期事件（queued、started、chunkDone、finished、failed），返回订阅 id；订阅不会阻止进程退出 */
+export declare function events(callback: (event: JobEvent) => void): number
+/** 取消事件订阅，订阅不存在时返回 false */
+export declare function unsubscribeEvents(subscriptionId: number): boolean
+/** 获取当前生效的模块配置 - 来自配置文件和 ZIPPY_* 环境变量 */
+export declare function getConfig(): ModuleConfig
+/** 收紧本进程的解密策略 - 只能开启限制，已开启的限制（包括配置中的）不能关闭，返回生效的策略 */
+export declare function setDecryptPolicy(policy: DecryptPolicyOptions): DecryptPolicyResult
 /** 获取文件大小通用函数，用于测试文件操作 */
-export declare function getFileSize(filePath: string): number
+export declare function getFileSize(filePath: string | number): number
 /** 计算文件的MD5哈希值 */
-export declare function computeFileMd5(filePath: string): string
+export declare function computeFileMd5(filePath: string | number): string
+/** computeFileMd5() 的异步版本，在 libuv 线程池中运行并返回 Promise */
+export declare function computeFileMd5Async(filePath: string | number, signal?: AbortSignal | Job | undefined | null): Promise<string>
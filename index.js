@@ -310,13 +310,155 @@ if (!nativeBinding) {
   throw new Error(`Failed to load native binding`)
 }
 
-const { encryptFile, decryptFile, chunkEncryptFile, chunkDecryptFile, decryptSingleChunk, getChunkedFileMetadata, getFileSize, computeFileMd5 } = nativeBinding
+const { ErrorCode, Job, encryptFile, encryptFileAsync, decryptFile, decryptFileAsync, chunkEncryptFile, chunkEncryptFileAsync, chunkDecryptFile, chunkDecryptFileAsync, chunkDecryptVolumes, chunkDecryptVolumesAsync, convertToChunked, convertToChunkedAsync, convertToMonolithic, convertToMonolithicAsync, rechunkFile, rechunkFileAsync, migrateFile, migrateFileAsync, migrateFiles, migrateFilesAsync, updateEncryptedFile, updateEncryptedFileAsync, appendChunkedFile, appendChunkedFileAsync, verifyChunkedFile, verifyChunkedFileAsync, decryptSingleChunk, decryptSingleChunkAsync, decryptTail, decryptTailAsync, DecryptedReader, ChunkedFileHandle, getChunkedFileMetadata, repairFile, repairFileAsync, storeFile, storeFileAsync, restoreFile, restoreFileAsync, listStoredFiles, ageEncryptFile, ageEncryptFileAsync, ageDecryptFile, ageDecryptFileAsync, generateAgeIdentity, opensslEncryptFile, opensslEncryptFileAsync, opensslDecryptFile, opensslDecryptFileAsync, gpgDecryptFile, gpgDecryptFileAsync, gpgEncryptFile, gpgEncryptFileAsync, encryptToZip, encryptToZipAsync, create7zArchive, create7zArchiveAsync, extract7zArchive, extract7zArchiveAsync, secretstreamEncryptFile, secretstreamEncryptFileAsync, secretstreamDecryptFile, secretstreamDecryptFileAsync, tinkEncryptFile, tinkEncryptFileAsync, tinkDecryptFile, tinkDecryptFileAsync, generateFernetKey, fernetEncrypt, fernetDecrypt, encryptJwe, decryptJwe, pasetoEncrypt, pasetoDecrypt, cmsEncryptFile, cmsEncryptFileAsync, generateMinisignKeyPair, minisignSignFile, minisignSignFileAsync, minisignVerifyFile, minisignVerifyFileAsync, generateSaltpackKeyPair, saltpackEncryptFile, saltpackEncryptFileAsync, saltpackDecryptFile, saltpackDecryptFileAsync, hlsEncryptSegment, hlsEncryptSegmentAsync, cencEncryptFile, cencEncryptFileAsync, rcloneEncryptFile, rcloneEncryptFileAsync, rcloneDecryptFile, rcloneDecryptFileAsync, rcloneEncryptName, rcloneDecryptName, gocryptfsEncryptDirectory, gocryptfsEncryptDirectoryAsync, gocryptfsEncryptPath, gocryptfsDecryptPath, s3EncryptFile, s3EncryptFileAsync, s3DecryptFile, s3DecryptFileAsync, veracryptListFiles, veracryptListFilesAsync, veracryptExtractFiles, veracryptExtractFilesAsync, randomBytes, generateNonce, deriveKey, deriveKeyAsync, encryptString, decryptString, createEncryptStream, createDecryptStream, encryptFromSource, decryptFromSource, ChunkedWriter, encryptFiles, encryptFilesAsync, decryptFiles, decryptFilesAsync, encryptDirectory, encryptDirectoryAsync, decryptDirectory, decryptDirectoryAsync, encryptArchive, encryptArchiveAsync, extractArchive, extractArchiveAsync, listArchive, Watcher, watchAndEncrypt, inspectFile, detectFormat, isEncrypted, warmup, getHardwareInfo, benchmarkAlgorithms, benchmarkAlgorithmsAsync, events, unsubscribeEvents, getConfig, setDecryptPolicy, getFileSize, computeFileMd5, computeFileMd5Async } = nativeBinding
 
+module.exports.ErrorCode = ErrorCode
+module.exports.Job = Job
 module.exports.encryptFile = encryptFile
+module.exports.encryptFileAsync = encryptFileAsync
 module.exports.decryptFile = decryptFile
+module.exports.decryptFileAsync = decryptFileAsync
 module.exports.chunkEncryptFile = chunkEncryptFile
+module.exports.chunkEncryptFileAsync = chunkEncryptFileAsync
 module.exports.chunkDecryptFile = chunkDecryptFile
+module.exports.chunkDecryptFileAsync = chunkDecryptFileAsync
+module.exports.chunkDecryptVolumes = chunkDecryptVolumes
+module.exports.chunkDecryptVolumesAsync = chunkDecryptVolumesAsync
+module.exports.convertToChunked = convertToChunked
+module.exports.convertToChunkedAsync = convertToChunkedAsync
+module.exports.convertToMonolithic = convertToMonolithic
+module.exports.convertToMonolithicAsync = convertToMonolithicAsync
+module.exports.rechunkFile = rechunkFile
+module.exports.rechunkFileAsync = rechunkFileAsync
+module.exports.migrateFile = migrateFile
+module.exports.migrateFileAsync = migrateFileAsync
+module.exports.migrateFiles = migrateFiles
+module.exports.migrateFilesAsync = migrateFilesAsync
+module.exports.updateEncryptedFile = updateEncryptedFile
+module.exports.updateEncryptedFileAsync = updateEncryptedFileAsync
+module.exports.appendChunkedFile = appendChunkedFile
+module.exports.appendChunkedFileAsync = appendChunkedFileAsync
+module.exports.verifyChunkedFile = verifyChunkedFile
+module.exports.verifyChunkedFileAsync = verifyChunkedFileAsync
 module.exports.decryptSingleChunk = decryptSingleChunk
+module.exports.decryptSingleChunkAsync = decryptSingleChunkAsync
+module.exports.decryptTail = decryptTail
+module.exports.decryptTailAsync = decryptTailAsync
+module.exports.DecryptedReader = DecryptedReader
+module.exports.ChunkedFileHandle = ChunkedFileHandle
 module.exports.getChunkedFileMetadata = getChunkedFileMetadata
+module.exports.repairFile = repairFile
+module.exports.repairFileAsync = repairFileAsync
+module.exports.storeFile = storeFile
+module.exports.storeFileAsync = storeFileAsync
+module.exports.restoreFile = restoreFile
+module.exports.restoreFileAsync = restoreFileAsync
+module.exports.listStoredFiles = listStoredFiles
+module.exports.ageEncryptFile = ageEncryptFile
+module.exports.ageEncryptFileAsync = ageEncryptFileAsync
+module.exports.ageDecryptFile = ageDecryptFile
+module.exports.ageDecryptFileAsync = ageDecryptFileAsync
+module.exports.generateAgeIdentity = generateAgeIdentity
+module.exports.opensslEncryptFile = opensslEncryptFile
+module.exports.opensslEncryptFileAsync = opensslEncryptFileAsync
+module.exports.opensslDecryptFile = opensslDecryptFile
+module.exports.opensslDecryptFileAsync = opensslDecryptFileAsync
+module.exports.gpgDecryptFile = gpgDecryptFile
+module.exports.gpgDecryptFileAsync = gpgDecryptFileAsync
+module.exports.gpgEncryptFile = gpgEncryptFile
+module.exports.gpgEncryptFileAsync = gpgEncryptFileAsync
+module.exports.encryptToZip = encryptToZip
+module.exports.encryptToZipAsync = encryptToZipAsync
+module.exports.create7zArchive = create7zArchive
+module.exports.create7zArchiveAsync = create7zArchiveAsync
+module.exports.extract7zArchive = extract7zArchive
+module.exports.extract7zArchiveAsync = extract7zArchiveAsync
+module.exports.secretstreamEncryptFile = secretstreamEncryptFile
+module.exports.secretstreamEncryptFileAsync = secretstreamEncryptFileAsync
+module.exports.secretstreamDecryptFile = secretstreamDecryptFile
+module.exports.secretstreamDecryptFileAsync = secretstreamDecryptFileAsync
+module.exports.tinkEncryptFile = tinkEncryptFile
+module.exports.tinkEncryptFileAsync = tinkEncryptFileAsync
+module.exports.tinkDecryptFile = tinkDecryptFile
+module.exports.tinkDecryptFileAsync = tinkDecryptFileAsync
+module.exports.generateFernetKey = generateFernetKey
+module.exports.fernetEncrypt = fernetEncrypt
+module.exports.fernetDecrypt = fernetDecrypt
+module.exports.encryptJwe = encryptJwe
+module.exports.decryptJwe = decryptJwe
+module.exports.pasetoEncrypt = pasetoEncrypt
+module.exports.pasetoDecrypt = pasetoDecrypt
+module.exports.cmsEncryptFile = cmsEncryptFile
+module.exports.cmsEncryptFileAsync = cmsEncryptFileAsync
+module.exports.generateMinisignKeyPair = generateMinisignKeyPair
+module.exports.minisignSignFile = minisignSignFile
+module.exports.minisignSignFileAsync = minisignSignFileAsync
+module.exports.minisignVerifyFile = minisignVerifyFile
+module.exports.minisignVerifyFileAsync = minisignVerifyFileAsync
+module.exports.generateSaltpackKeyPair = generateSaltpackKeyPair
+module.exports.saltpackEncryptFile = saltpackEncryptFile
+module.exports.saltpackEncryptFileAsync = saltpackEncryptFileAsync
+module.exports.saltpackDecryptFile = saltpackDecryptFile
+module.exports.saltpackDecryptFileAsync = saltpackDecryptFileAsync
+module.exports.hlsEncryptSegment = hlsEncryptSegment
+module.exports.hlsEncryptSegmentAsync = hlsEncryptSegmentAsync
+module.exports.cencEncryptFile = cencEncryptFile
+module.exports.cencEncryptFileAsync = cencEncryptFileAsync
+module.exports.rcloneEncryptFile = rcloneEncryptFile
+module.exports.rcloneEncryptFileAsync = rcloneEncryptFileAsync
+module.exports.rcloneDecryptFile = rcloneDecryptFile
+module.exports.rcloneDecryptFileAsync = rcloneDecryptFileAsync
+module.exports.rcloneEncryptName = rcloneEncryptName
+module.exports.rcloneDecryptName = rcloneDecryptName
+module.exports.gocryptfsEncryptDirectory = gocryptfsEncryptDirectory
+module.exports.gocryptfsEncryptDirectoryAsync = gocryptfsEncryptDirectoryAsync
+module.exports.gocryptfsEncryptPath = gocryptfsEncryptPath
+module.exports.gocryptfsDecryptPath = gocryptfsDecryptPath
+module.exports.s3EncryptFile = s3EncryptFile
+module.exports.s3EncryptFileAsync = s3EncryptFileAsync
+module.exports.s3DecryptFile = s3DecryptFile
+module.exports.s3DecryptFileAsync = s3DecryptFileAsync
+module.exports.veracryptListFiles = veracryptListFiles
+module.exports.veracryptListFilesAsync = veracryptListFilesAsync
+module.exports.veracryptExtractFiles = veracryptExtractFiles
+module.exports.veracryptExtractFilesAsync = veracryptExtractFilesAsync
+module.exports.randomBytes = randomBytes
+module.exports.generateNonce = generateNonce
+module.exports.deriveKey = deriveKey
+module.exports.deriveKeyAsync = deriveKeyAsync
+module.exports.encryptString = encryptString
+module.exports.decryptString = decryptString
+module.exports.createEncryptStream = createEncryptStream
+module.exports.createDecryptStream = createDecryptStream
+module.exports.encryptFromSource = encryptFromSource
+module.exports.decryptFromSource = decryptFromSource
+module.exports.ChunkedWriter = ChunkedWriter
+module.exports.encryptFiles = encryptFiles
+module.exports.encryptFilesAsync = encryptFilesAsync
+module.exports.decryptFiles = decryptFiles
+module.exports.decryptFilesAsync = decryptFilesAsync
+module.exports.encryptDirectory = encryptDirectory
+module.exports.encryptDirectoryAsync = encryptDirectoryAsync
+module.exports.decryptDirectory = decryptDirectory
+module.exports.decryptDirectoryAsync = decryptDirectoryAsync
+module.exports.encryptArchive = encryptArchive
+module.exports.encryptArchiveAsync = encryptArchiveAsync
+module.exports.extractArchive = extractArchive
+module.exports.extractArchiveAsync = extractArchiveAsync
+module.exports.listArchive = listArchive
+module.exports.Watcher = Watcher
+module.exports.watchAndEncrypt = watchAndEncrypt
+module.exports.inspectFile = inspectFile
+module.exports.detectFormat = detectFormat
+module.exports.isEncrypted = isEncrypted
+module.exports.warmup = warmup
+module.exports.getHardwareInfo = getHardwareInfo
+module.exports.benchmarkAlgorithms = benchmarkAlgorithms
+module.exports.benchmarkAlgorithmsAsync = benchmarkAlgorithmsAsync
+module.exports.events = events
+module.exports.unsubscribeEvents = unsubscribeEvents
+module.exports.getConfig = getConfig
+module.exports.setDecryptPolicy = setDecryptPolicy
 module.exports.getFileSize = getFileSize
 module.exports.computeFileMd5 = computeFileMd5
+module.exports.computeFileMd5Async = computeFileMd5Async
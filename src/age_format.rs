@@ -0,0 +1,232 @@
+use std::io::{BufRead, BufReader, BufWriter, Read, Write};
+use std::iter;
+use std::str::FromStr;
+
+use age::armor::{ArmoredReader, ArmoredWriter, Format};
+use age::secrecy::{ExposeSecret, SecretString};
+use age::{x25519, Decryptor, Encryptor, Identity, Recipient};
+
+use crate::errors::{open_error, read_error, write_error, CodedError};
+use crate::fd::{self, SyncOutput, Target};
+use crate::job::JobControl;
+
+/// age v1 二进制文件的第一行
+pub const AGE_MAGIC: &[u8] = b"age-encryption.org/v1";
+/// ASCII 封装的 age 文件的第一行
+pub const AGE_ARMOR_MAGIC: &[u8] = b"-----BEGIN AGE ENCRYPTED FILE-----";
+
+/// 复制数据时每次读取的长度，每读完一块检查一次超时并发送 chunkDone 事件
+const COPY_BUFFER_LEN: usize = 1024 * 1024;
+
+/// 加密或解密 age 文件的统计
+pub struct AgeReport {
+    pub plaintext_size: u64,
+    pub encrypted_size: u64,
+    /// 文件是否以口令（scrypt）加密
+    pub passphrase: bool,
+}
+
+/// 逐块复制数据，每块之间检查超时
+fn copy<R: Read, W: Write>(reader: &mut R, writer: &mut W, what: &str, control: &JobControl) -> Result<u64, CodedError> {
+    let mut buffer = vec![0u8; COPY_BUFFER_LEN];
+    let mut total = 0u64;
+    let mut index = 0u32;
+    loop {
+        control.check()?;
+        let read = match reader.read(&mut buffer) {
+            Ok(0) => return Ok(total),
+            Ok(read) => read,
+            Err(err) if err.kind() == std::io::ErrorKind::Interrupted => continue,
+            Err(err) => return Err(read_error(&format!("reading {}", what), err)),
+        };
+        writer
+            .write_all(&buffer[..read])
+            .map_err(|e| write_error("writing output file", e))?;
+        total += read as u64;
+        index += 1;
+        control.chunk_done(index, read);
+    }
+}
+
+/// 解析 X25519 接收者（`age1...`）
+fn parse_recipients(recipients: &[String]) -> Result<Vec<x25519::Recipient>, CodedError> {
+    recipients
+        .iter()
+        .map(|recipient| {
+            x25519::Recipient::from_str(recipient.trim()).map_err(|_| CodedError::invalid_argument(format!("Invalid age recipient: {}", recipient)))
+        })
+        .collect()
+}
+
+/// 解析 X25519 身份（`AGE-SECRET-KEY-1...`）；每个字符串也可以是身份文件的内容，忽略空行和 `#` 开头的注释
+fn parse_identities(identities: &[String]) -> Result<Vec<x25519::Identity>, CodedError> {
+    identities
+        .iter()
+        .flat_map(|identity| identity.lines())
+        .map(str::trim)
+        .filter(|line| !line.is_empty() && !line.starts_with('#'))
+        .map(|line| x25519::Identity::from_str(line).map_err(|_| CodedError::bad_format("Invalid age identity")))
+        .collect()
+}
+
+/// 以 age v1 格式加密文件：提供口令时使用 scrypt 接收者，否则加密给所有 X25519 接收者；armor 为 true 时输出 ASCII 封装
+///
+/// 按 age 规范，口令不能与其它接收者同时使用。
+pub fn encrypt_file(input_path: &Target, output_path: &Target, passphrase: Option<&str>, recipients: &[String], armor: bool, control: &JobControl) -> Result<AgeReport, CodedError> {
+    let encryptor = match (passphrase, recipients.is_empty()) {
+        (Some(_), false) => return Err(CodedError::invalid_argument("age passphrases cannot be combined with recipients")),
+        (Some(passphrase), true) => Encryptor::with_user_passphrase(SecretString::from(passphrase.to_string())),
+        (None, true) => return Err(CodedError::invalid_argument("age encryption requires a passphrase or at least one recipient")),
+        (None, false) => {
+            let recipients = parse_recipients(recipients)?;
+            Encryptor::with_recipients(recipients.iter().map(|recipient| recipient as &dyn Recipient))
+                .map_err(|e| CodedError::unknown(format!("age encryption error: {}", e)))?
+        }
+    };
+
+    let mut input = fd::open(input_path).map_err(|e| open_error("opening input file", e))?;
+    let output = fd::create(output_path).map_err(|e| open_error("creating output file", e))?;
+    let format = if armor { Format::AsciiArmor } else { Format::Binary };
+    let armored = ArmoredWriter::wrap_output(BufWriter::new(output), format)
+        .map_err(|e| write_error("writing output file", e))?;
+    let mut writer = encryptor
+        .wrap_output(armored)
+        .map_err(|e| write_error("writing output file", e))?;
+    let plaintext_size = copy(&mut input, &mut writer, "input file", control)?;
+    let output = writer
+        .finish()
+        .and_then(|armored| armored.finish())
+        .and_then(|buffered| buffered.into_inner().map_err(|e| e.into_error()))
+        .map_err(|e| write_error("writing output file", e))?;
+    output.sync_output().map_err(|e| write_error("flushing output file", e))?;
+    let encrypted_size = output
+        .metadata()
+        .map_err(|e| read_error("reading output file metadata", e))?
+        .len();
+    Ok(AgeReport { plaintext_size, encrypted_size, passphrase: passphrase.is_some() })
+}
+
+/// 解析 age 文件头并用口令或 X25519 身份解开文件密钥，返回明文读取器和文件是否以口令加密
+fn open_decryptor<R: BufRead>(input: R, passphrase: Option<&str>, identities: &[String]) -> Result<(impl Read, bool), CodedError> {
+    let decryptor = Decryptor::new_buffered(ArmoredReader::new(input))
+        .map_err(|e| CodedError::bad_format(format!("Invalid age file: {}", e)))?;
+
+    let is_passphrase = decryptor.is_scrypt();
+    let reader = match (is_passphrase, passphrase) {
+        (true, Some(passphrase)) => {
+            let identity = age::scrypt::Identity::new(SecretString::from(passphrase.to_string()));
+            decryptor.decrypt(iter::once(&identity as &dyn Identity))
+        }
+        (true, None) => return Err(CodedError::invalid_argument("age file is passphrase-protected - provide the passphrase")),
+        (false, _) => {
+            let identities = parse_identities(identities)?;
+            if identities.is_empty() {
+                return Err(CodedError::invalid_argument("age file is encrypted to recipients - provide an identity"));
+            }
+            decryptor.decrypt(identities.iter().map(|identity| identity as &dyn Identity))
+        }
+    }
+    .map_err(|e| CodedError::auth_failed(format!("age decryption error: {}", e)))?;
+    Ok((reader, is_passphrase))
+}
+
+/// 解密 age v1 文件（二进制或 ASCII 封装）：口令加密的文件需要 passphrase，其它文件使用 identities 中的 X25519 身份
+///
+/// age 的分段认证在读到文件末尾时才能发现截断，出错时调用方应删除未完成的输出文件。
+pub fn decrypt_file(input_path: &Target, output_path: &Target, passphrase: Option<&str>, identities: &[String], control: &JobControl) -> Result<AgeReport, CodedError> {
+    let input = fd::open(input_path).map_err(|e| open_error("opening input file", e))?;
+    let encrypted_size = input
+        .metadata()
+        .map_err(|e| read_error("reading input file metadata", e))?
+        .len();
+    let (mut reader, is_passphrase) = open_decryptor(BufReader::new(input), passphrase, identities)?;
+
+    let output = fd::create(output_path).map_err(|e| open_error("creating output file", e))?;
+    let mut writer = BufWriter::new(output);
+    let plaintext_size = copy(&mut reader, &mut writer, "age file", control)?;
+    writer
+        .into_inner()
+        .map_err(|e| write_error("writing output file", e.into_error()))?
+        .sync_output()
+        .map_err(|e| write_error("flushing output file", e))?;
+    Ok(AgeReport { plaintext_size, encrypted_size, passphrase: is_passphrase })
+}
+
+/// 生成新的 X25519 身份，返回（身份 `AGE-SECRET-KEY-1...`，对应的接收者 `age1...`）
+pub fn generate_identity() -> (String, String) {
+    let identity = x25519::Identity::generate();
+    let recipient = identity.to_public().to_string();
+    (identity.to_string().expose_secret().to_string(), recipient)
+}
+
+#[cfg(test)]
+mod tests {
+    use sha2::{Digest, Sha256};
+
+    use super::*;
+    use crate::errors::ErrorCode;
+
+    /// C2SP age 测试套件（CCTV age testkit）中的向量：头部为 `key: value` 行，空行之后是 age 文件本身
+    struct Vector {
+        expect: String,
+        payload: Option<String>,
+        identity: Option<String>,
+        passphrase: Option<String>,
+        file: &'static [u8],
+    }
+
+    fn vector(data: &'static [u8]) -> Vector {
+        let split = data.windows(2).position(|window| window == b"\n\n").unwrap();
+        let mut vector = Vector { expect: String::new(), payload: None, identity: None, passphrase: None, file: &data[split + 2..] };
+        for line in std::str::from_utf8(&data[..split]).unwrap().lines() {
+            let (key, value) = line.split_once(": ").unwrap();
+            let value = value.to_string();
+            match key {
+                "expect" => vector.expect = value,
+                "payload" => vector.payload = Some(value),
+                "identity" => vector.identity = Some(value),
+                "passphrase" => vector.passphrase = Some(value),
+                _ => {}
+            }
+        }
+        vector
+    }
+
+    fn decrypt(vector: &Vector) -> Result<Vec<u8>, CodedError> {
+        let identities: Vec<String> = vector.identity.iter().cloned().collect();
+        let (mut reader, _) = open_decryptor(vector.file, vector.passphrase.as_deref(), &identities)?;
+        let mut plaintext = Vec::new();
+        reader
+            .read_to_end(&mut plaintext)
+            .map_err(|e| read_error("reading age file", e))?;
+        Ok(plaintext)
+    }
+
+    #[test]
+    fn decrypts_testkit_vectors() {
+        for data in [
+            &include_bytes!("../testdata/age/x25519")[..],
+            include_bytes!("../testdata/age/scrypt"),
+            include_bytes!("../testdata/age/armor"),
+            include_bytes!("../testdata/age/stream_two_chunks"),
+        ] {
+            let vector = vector(data);
+            assert_eq!(vector.expect, "success");
+            let plaintext = decrypt(&vector).unwrap();
+            assert_eq!(hex::encode(Sha256::digest(&plaintext)), vector.payload.unwrap());
+        }
+    }
+
+    #[test]
+    fn rejects_testkit_failure_vectors() {
+        for (data, code) in [
+            (&include_bytes!("../testdata/age/hmac_bad")[..], ErrorCode::AuthFailed),
+            (include_bytes!("../testdata/age/x25519_bad_tag"), ErrorCode::AuthFailed),
+            (include_bytes!("../testdata/age/stream_bad_tag_second_chunk"), ErrorCode::IoRead),
+        ] {
+            let vector = vector(data);
+            assert_ne!(vector.expect, "success");
+            assert_eq!(decrypt(&vector).unwrap_err().code, code);
+        }
+    }
+}
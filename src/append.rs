@@ -0,0 +1,206 @@
+use std::fs::OpenOptions;
+use std::io::{BufReader, Read, Seek, SeekFrom, Write};
+
+use crate::chunked::{scan_frames, ChunkEncoder};
+use crate::crypto::{decrypt_single_chunk, ChunkChecksum, CryptoAlgorithm, HEADER_MAC_LEN};
+use crate::errors::{open_error, read_error, write_error, CodedError};
+use crate::format::ChunkedHeader;
+use crate::parity;
+
+/// 追加的统计
+pub struct AppendReport {
+    /// 追加后的分片总数
+    pub total_chunks: u64,
+    /// 本次写出的分片数（包括与新数据合并后重新加密的最后一个分片）
+    pub written_chunks: u64,
+    /// 追加后的明文大小
+    pub original_size: u64,
+}
+
+/// 在可追加的分片文件末尾追加明文：之前的完整分片保持不变，不满的最后一个分片解密后与新数据一起重新加密，
+/// 随后重写分片索引、文件 MAC 和文件头中的原始大小
+///
+/// header 为已校验过的文件头。新的分片和结尾先在内存中加密完成，之后才开始改写文件，因此超时或取消不会破坏原文件；
+/// check 在每个分片之前调用，chunk_done 在每个分片写出后调用。改写文件时崩溃会使文件无法通过整个文件的认证，
+/// 之前的分片仍可通过容错解密取回。
+pub fn append_file(path: &str, header: ChunkedHeader, algorithm: &CryptoAlgorithm, key: &[u8], data: &[u8], check: impl Fn() -> Result<(), CodedError>, mut chunk_done: impl FnMut(u32, usize)) -> Result<AppendReport, CodedError> {
+    let container = match &header.container {
+        Some(container) if container.appendable => container,
+        _ => return Err(CodedError::unsupported("File is not appendable - create it with chunkEncryptFile and the appendable option")),
+    };
+    let parity_percent = match container.has_parity {
+        true => Some(parity::parity_percent(path)?),
+        false => None,
+    };
+    let mut file = OpenOptions::new()
+        .read(true)
+        .write(true)
+        .open(path)
+        .map_err(|e| open_error("opening encrypted file", e))?;
+
+    // 确认原文件完好后再追加
+    let mut reader = BufReader::new(&file);
+    reader
+        .seek(SeekFrom::Start(header.header_len))
+        .map_err(|e| read_error("seeking in encrypted file", e))?;
+    let scan = scan_frames(&mut reader, &header, algorithm, key)?;
+    drop(reader);
+    let total_chunks = header.total_chunks();
+    if data.is_empty() {
+        return Ok(AppendReport { total_chunks, written_chunks: 0, original_size: header.original_size });
+    }
+
+    // 最后一个分片不满时取出它的明文，与新数据一起重新加密
+    let mut pending = Vec::new();
+    let mut retained = total_chunks as usize;
+    if let Some(last) = total_chunks.checked_sub(1).filter(|&last| header.chunk_len(last) < header.chunk_size) {
+        let frame_entry = &scan.frames[last as usize];
+        let mut frame = vec![0u8; frame_entry.len];
+        file.seek(SeekFrom::Start(frame_entry.data_offset))
+            .and_then(|_| file.read_exact(&mut frame))
+            .map_err(|e| read_error("reading encrypted file", e))?;
+        let sequence = header.chunk_sequence_total().map(|total| (last, total));
+        let decrypted = decrypt_single_chunk(algorithm, key, &frame, sequence)
+            .map_err(|e| e.context("Chunk decryption error"))?;
+        pending = header.restore_chunk(last, decrypted)?;
+        if scan.footer.chunk_checksums.get(last as usize) != Some(&ChunkChecksum::new(key).compute(&pending)) {
+            return Err(CodedError::auth_failed(format!("Chunk checksum mismatch in chunk {}", last)));
+        }
+        retained = last as usize;
+    }
+
+    // 文件头只有原始大小变化，长度不变
+    let original_size = header.original_size + data.len() as u64;
+    let mut updated = container.clone();
+    updated.original_size = original_size;
+    let header_bytes = updated.to_bytes(key)?;
+    if header_bytes.len() as u64 != header.header_len {
+        return Err(CodedError::bad_header("File header changed size during append"));
+    }
+
+    let mut footer = scan.footer;
+    footer.chunk_offsets.truncate(retained);
+    footer.chunk_checksums.truncate(retained);
+    footer.encrypted_digest = None;
+    let position = scan.frames.get(retained).map_or(scan.end, |frame| frame.offset);
+    let header_tag = &header_bytes[header_bytes.len() - HEADER_MAC_LEN..];
+    let mut encoder = ChunkEncoder::append(Vec::new(), algorithm.clone(), key, &updated, header_tag, &scan.frames[..retained], footer, position)?;
+
+    encoder.write(&pending)?;
+    for piece in data.chunks(header.chunk_size) {
+        check()?;
+        encoder.write(piece)?;
+        chunk_done(encoder.chunks_written(), piece.len());
+    }
+    let written_chunks = original_size.div_ceil(header.chunk_size as u64) - retained as u64;
+    let tail = encoder.finish()?;
+
+    // 从这里开始改写文件，不再响应取消：写入新的分片和结尾，截掉多余的部分（新的结尾可能比原来短，原来的纠错数据也已失效），
+    // 最后写入新的文件头
+    file.seek(SeekFrom::Start(position))
+        .and_then(|_| file.write_all(&tail))
+        .and_then(|_| file.set_len(position + tail.len() as u64))
+        .map_err(|e| write_error("writing encrypted file", e))?;
+    file.seek(SeekFrom::Start(0))
+        .and_then(|_| file.write_all(&header_bytes))
+        .and_then(|_| file.sync_all())
+        .map_err(|e| write_error("writing file header", e))?;
+
+    if let Some(percent) = parity_percent {
+        parity::append_parity(path, percent)?;
+    }
+    Ok(AppendReport { total_chunks: original_size.div_ceil(header.chunk_size as u64), written_chunks, original_size })
+}
+
+#[cfg(test)]
+mod tests {
+    use std::fs::{self, File};
+    use std::io::Cursor;
+
+    use super::*;
+    use crate::chunked::ChunkDecoder;
+    use crate::errors::ErrorCode;
+    use crate::format::{read_chunked_header, ContainerHeader, Layout};
+
+    const KEY: [u8; 32] = [3; 32];
+    const CHUNK_SIZE: usize = 1000;
+
+    fn temp_path(name: &str) -> String {
+        std::env::temp_dir()
+            .join(format!("zippy-append-{}-{}", std::process::id(), name))
+            .to_string_lossy()
+            .into_owned()
+    }
+
+    /// 写出可追加的分片文件
+    fn create(path: &str, data: &[u8]) {
+        let mut container = ContainerHeader::for_new_file(Layout::Chunked, CryptoAlgorithm::Aes, data.len() as u64, CHUNK_SIZE as u64);
+        container.appendable = true;
+        let mut encoder = ChunkEncoder::new(File::create(path).unwrap(), CryptoAlgorithm::Aes, &KEY, Some(&container), data.len() as u64, CHUNK_SIZE).unwrap();
+        encoder.write(data).unwrap();
+        encoder.finish().unwrap();
+    }
+
+    fn read_header(path: &str) -> ChunkedHeader {
+        let header = read_chunked_header(&mut BufReader::new(File::open(path).unwrap())).unwrap();
+        header.container.as_ref().unwrap().verify(&KEY).unwrap();
+        header
+    }
+
+    fn decrypt(path: &str) -> Vec<u8> {
+        let file = fs::read(path).unwrap();
+        let mut reader = Cursor::new(&file[..]);
+        let header = read_chunked_header(&mut reader).unwrap();
+        header.container.as_ref().unwrap().verify(&KEY).unwrap();
+        let mut decoder = ChunkDecoder::new(reader, header, CryptoAlgorithm::Aes, &KEY).unwrap();
+        let mut plaintext = Vec::new();
+        while let Some(chunk) = decoder.next_chunk().unwrap() {
+            plaintext.extend_from_slice(&chunk);
+        }
+        decoder.finish().unwrap();
+        plaintext
+    }
+
+    #[test]
+    fn appends_to_a_partial_last_chunk() {
+        let path = temp_path("round-trip.enc");
+        let mut data: Vec<u8> = (0..1500u32).map(|i| (i % 251) as u8).collect();
+        create(&path, &data);
+
+        let more: Vec<u8> = (0..2200u32).map(|i| (i % 13) as u8).collect();
+        let mut progress = Vec::new();
+        let report = append_file(&path, read_header(&path), &CryptoAlgorithm::Aes, &KEY, &more, || Ok(()), |index, bytes| progress.push((index, bytes))).unwrap();
+        data.extend_from_slice(&more);
+        assert_eq!(report.original_size, 3700);
+        assert_eq!(report.total_chunks, 4);
+        // 不满的第二个分片与新数据一起重新加密
+        assert_eq!(report.written_chunks, 3);
+        assert_eq!(progress.len(), 3);
+        assert_eq!(decrypt(&path), data);
+        let _ = fs::remove_file(&path);
+    }
+
+    #[test]
+    fn aborted_append_leaves_the_file_unchanged() {
+        let path = temp_path("aborted.enc");
+        let data: Vec<u8> = (0..1500u32).map(|i| (i % 251) as u8).collect();
+        create(&path, &data);
+        let original = fs::read(&path).unwrap();
+
+        // 第一个分片加密后取消
+        let checks = std::cell::Cell::new(0);
+        let check = || {
+            checks.set(checks.get() + 1);
+            match checks.get() > 1 {
+                true => Err(CodedError::aborted("Operation aborted")),
+                false => Ok(()),
+            }
+        };
+        let more = vec![9u8; 5000];
+        let err = append_file(&path, read_header(&path), &CryptoAlgorithm::Aes, &KEY, &more, check, |_, _| {}).err().unwrap();
+        assert_eq!(err.code, ErrorCode::Aborted);
+        assert_eq!(fs::read(&path).unwrap(), original);
+        assert_eq!(decrypt(&path), data);
+        let _ = fs::remove_file(&path);
+    }
+}
@@ -0,0 +1,449 @@
+use std::collections::HashSet;
+use std::fs::{self, File};
+use std::io::{self, Read, Write};
+use std::path::{Path, PathBuf};
+
+use serde_json::{json, Value};
+use tar::{Builder, Entries, Entry, EntryType, Header};
+
+use crate::chunked::ChunkEncoder;
+use crate::directory::{self, DirectoryTree, ManifestDirectory};
+use crate::errors::{CodedError, ErrorCode};
+use crate::glob::PathFilter;
+use crate::job::JobControl;
+use crate::walk;
+
+/// 归档中的第一个条目，记录归档的内容
+pub const MANIFEST_ENTRY: &str = ".zippy-manifest.json";
+/// 归档明文（tar）的 MIME 类型，记录在文件头中
+pub const MIME_TYPE: &str = "application/x-tar";
+/// tar 的块大小，文件头和数据都按块对齐
+const BLOCK_SIZE: u64 = 512;
+
+/// 清单中记录的文件
+pub struct ArchiveFile {
+    pub path: String,
+    pub size: u64,
+    pub mode: u32,
+    pub modified: u64,
+    /// 文件数据在 tar 中的位置
+    pub offset: u64,
+}
+
+/// 归档清单：与加密目录的清单相同，但以明文 JSON 保存在加密的 tar 中
+pub struct ArchiveManifest {
+    pub created_at: u64,
+    pub directories: Vec<ManifestDirectory>,
+    pub files: Vec<ArchiveFile>,
+}
+
+impl ArchiveManifest {
+    fn to_json(&self) -> Vec<u8> {
+        let directories: Vec<Value> = self
+            .directories
+            .iter()
+            .map(|dir| json!({ "path": dir.path, "mode": dir.mode, "modified": dir.modified }))
+            .collect();
+        let files: Vec<Value> = self
+            .files
+            .iter()
+            .map(|file| json!({ "path": file.path, "size": file.size, "mode": file.mode, "modified": file.modified, "offset": file.offset }))
+            .collect();
+        json!({ "createdAt": self.created_at, "directories": directories, "files": files }).to_string().into_bytes()
+    }
+
+    fn from_json(data: &[u8]) -> Result<Self, CodedError> {
+        let manifest: Value = serde_json::from_slice(data).map_err(|e| CodedError::bad_format(format!("Invalid archive manifest: {}", e)))?;
+        let invalid = |what: &str| CodedError::bad_format(format!("Invalid archive manifest: {}", what));
+        let entries = |name: &str| manifest[name].as_array().ok_or_else(|| invalid(name));
+        let string = |entry: &Value, name: &str| entry[name].as_str().map(str::to_string).ok_or_else(|| invalid(name));
+        let number = |entry: &Value, name: &str| entry[name].as_u64().ok_or_else(|| invalid(name));
+        let directories = entries("directories")?
+            .iter()
+            .map(|dir| {
+                Ok(ManifestDirectory {
+                    path: string(dir, "path")?,
+                    mode: number(dir, "mode")? as u32,
+                    modified: number(dir, "modified")?,
+                })
+            })
+            .collect::<Result<_, CodedError>>()?;
+        let files = entries("files")?
+            .iter()
+            .map(|file| {
+                Ok(ArchiveFile {
+                    path: string(file, "path")?,
+                    size: number(file, "size")?,
+                    mode: number(file, "mode")? as u32,
+                    modified: number(file, "modified")?,
+                    offset: number(file, "offset")?,
+                })
+            })
+            .collect::<Result<_, CodedError>>()?;
+        Ok(ArchiveManifest { created_at: manifest["createdAt"].as_u64().unwrap_or(0), directories, files })
+    }
+
+    /// 按过滤条件保留要解开的目录和文件；include 中的模式没有选中任何路径时返回错误
+    pub fn select(mut self, filter: &PathFilter) -> Result<Self, CodedError> {
+        let paths = self.directories.iter().map(|dir| (dir.path.as_str(), true)).chain(self.files.iter().map(|file| (file.path.as_str(), false)));
+        if let Some(pattern) = filter.unmatched_include(paths) {
+            return Err(CodedError::invalid_argument(format!("Path not found in archive manifest: {}", pattern)));
+        }
+        filter.retain(&mut self.directories, &mut self.files, |dir| dir.path.as_str(), |file| file.path.as_str());
+        Ok(self)
+    }
+}
+
+fn padding(size: u64) -> u64 {
+    (BLOCK_SIZE - size % BLOCK_SIZE) % BLOCK_SIZE
+}
+
+/// 条目在 tar 中的路径，目录以 `/` 结尾
+fn entry_path(name: &str, is_dir: bool) -> String {
+    if is_dir { format!("{}/", name) } else { name.to_string() }
+}
+
+/// 条目的 GNU 格式文件头，大小或修改时间超出八进制字段时以 base-256 编码
+fn entry_header(is_dir: bool, size: u64, mode: u32, modified: u64) -> Header {
+    let mut header = Header::new_gnu();
+    header.set_entry_type(if is_dir { EntryType::Directory } else { EntryType::Regular });
+    header.set_size(size);
+    header.set_mode(mode & 0o7777);
+    header.set_mtime(modified);
+    header
+}
+
+/// 一个条目的文件头（名称过长时包括之前的 GNU 长文件名条目）占用的字节数，即数据在条目中的位置
+fn header_len(name: &str, is_dir: bool) -> Result<u64, CodedError> {
+    let mut builder = Builder::new(Vec::new());
+    builder
+        .append_data(&mut entry_header(is_dir, 0, 0, 0), entry_path(name, is_dir), io::empty())
+        .map_err(|e| CodedError::unsupported(format!("Cannot archive {}: {}", name, e)))?;
+    Ok(builder.get_ref().len() as u64)
+}
+
+/// 一个条目在 tar 中占用的字节数（文件头和对齐后的数据）
+fn entry_len(name: &str, is_dir: bool, size: u64) -> Result<u64, CodedError> {
+    Ok(header_len(name, is_dir)? + size + padding(size))
+}
+
+/// 规划归档：生成清单并计算 tar 的总大小（文件头记录原始大小，必须在写入前确定）
+///
+/// 清单的长度取决于其中记录的位置，而位置又取决于清单的长度，因此重复计算直到长度不再变化。
+pub fn plan(tree: &DirectoryTree) -> Result<(ArchiveManifest, Vec<u8>, u64), CodedError> {
+    let created_at = std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .map(|elapsed| elapsed.as_millis() as u64)
+        .unwrap_or(0);
+    let mut manifest = ArchiveManifest {
+        created_at,
+        directories: tree
+            .directories
+            .iter()
+            .map(|dir| ManifestDirectory { path: dir.name.clone(), mode: dir.mode, modified: dir.modified })
+            .collect(),
+        files: tree
+            .files
+            .iter()
+            .map(|file| ArchiveFile { path: file.name.clone(), size: file.size, mode: file.mode, modified: file.modified, offset: 0 })
+            .collect(),
+    };
+    let mut directories_len = 0;
+    for dir in &tree.directories {
+        directories_len += entry_len(&dir.name, true, 0)?;
+    }
+    let file_headers = tree.files.iter().map(|file| header_len(&file.name, false)).collect::<Result<Vec<_>, CodedError>>()?;
+    let mut manifest_len = 0;
+    loop {
+        let mut position = entry_len(MANIFEST_ENTRY, false, manifest_len)? + directories_len;
+        for ((file, entry), header) in manifest.files.iter_mut().zip(&tree.files).zip(&file_headers) {
+            file.offset = position + header;
+            position += header + entry.size + padding(entry.size);
+        }
+        let json = manifest.to_json();
+        if json.len() as u64 == manifest_len {
+            // 归档以两个全零块结尾
+            return Ok((manifest, json, position + 2 * BLOCK_SIZE));
+        }
+        manifest_len = json.len() as u64;
+    }
+}
+
+/// 把 ChunkEncoder 包装为 Write，供 tar 写入
+struct EncoderWriter<'a, W: Write>(&'a mut ChunkEncoder<W>);
+
+impl<W: Write> Write for EncoderWriter<'_, W> {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.0.write(buf).map_err(io::Error::other)?;
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+/// 把清单和目录树写成 tar 并交给 encoder 加密；文件大小与规划时不同时出错
+pub fn write<W: Write>(tree: &DirectoryTree, manifest_json: &[u8], encoder: &mut ChunkEncoder<W>, control: &JobControl) -> Result<(), CodedError> {
+    write_tar(tree, manifest_json, EncoderWriter(encoder), || control.check()).map(drop)
+}
+
+/// 写出 tar 并返回 writer；每个文件之前调用 check()，返回错误时中止
+fn write_tar<W: Write>(tree: &DirectoryTree, manifest_json: &[u8], writer: W, check: impl Fn() -> Result<(), CodedError>) -> Result<W, CodedError> {
+    let write_error = |e: io::Error| CodedError::io(ErrorCode::IoWrite, "Failed to write archive", e);
+    let mut builder = Builder::new(writer);
+    let created_at = std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .map_or(0, |elapsed| elapsed.as_secs());
+    let mut header = entry_header(false, manifest_json.len() as u64, 0o644, created_at);
+    builder.append_data(&mut header, MANIFEST_ENTRY, manifest_json).map_err(write_error)?;
+    for dir in &tree.directories {
+        let mut header = entry_header(true, 0, dir.mode, dir.modified);
+        builder.append_data(&mut header, entry_path(&dir.name, true), io::empty()).map_err(write_error)?;
+    }
+    for file in &tree.files {
+        check()?;
+        let input = File::open(&file.path).map_err(|e| CodedError::io_open(format!("Failed to open input file {}: {}", file.path.display(), e)))?;
+        let read_error = |e: io::Error| CodedError::io(ErrorCode::IoRead, format_args!("Error reading {}", file.path.display()), e);
+        let mut input = input.take(file.size);
+        let mut header = entry_header(false, file.size, file.mode, file.modified);
+        builder.append_data(&mut header, &file.name, &mut input).map_err(|e| match e.kind() {
+            io::ErrorKind::Other => write_error(e),
+            _ => read_error(e),
+        })?;
+        // 没有读满，或者之后还能再读出一个字节，说明文件在规划之后变小或变大
+        if input.limit() != 0 || input.into_inner().read(&mut [0u8; 1]).map_err(read_error)? != 0 {
+            return Err(CodedError::io_read(format!("{} changed size while being archived (expected {} bytes)", file.name, file.size)));
+        }
+    }
+    builder.into_inner().map_err(write_error)
+}
+
+fn read_error(err: io::Error) -> CodedError {
+    match err.kind() {
+        io::ErrorKind::UnexpectedEof => CodedError::truncated("Archive is truncated: unexpected end of tar data"),
+        _ => CodedError::io(ErrorCode::IoRead, "Error reading archive", err),
+    }
+}
+
+/// 读取到的条目类型
+#[derive(PartialEq)]
+enum EntryKind {
+    File,
+    Directory,
+    /// 链接、设备文件等，数据被跳过
+    Other,
+}
+
+/// 读取下一个条目，返回条目、使用 `/` 分隔的名称（目录不含结尾的 `/`）和类型；到达归档结尾时返回 None
+fn next_entry<'a, R: Read>(entries: &mut Entries<'a, R>) -> Result<Option<(Entry<'a, R>, String, EntryKind)>, CodedError> {
+    let Some(entry) = entries.next() else {
+        return Ok(None);
+    };
+    let entry = entry.map_err(read_error)?;
+    let mut name = String::from_utf8_lossy(&entry.path_bytes()).into_owned();
+    let kind = match entry.header().entry_type() {
+        EntryType::Regular | EntryType::Continuous if !name.ends_with('/') => EntryKind::File,
+        EntryType::Regular | EntryType::Directory => EntryKind::Directory,
+        _ => EntryKind::Other,
+    };
+    while name.ends_with('/') {
+        name.pop();
+    }
+    Ok(Some((entry, name, kind)))
+}
+
+/// 把条目的数据写入 writer
+fn copy_data<R: Read, W: Write + ?Sized>(entry: &mut Entry<'_, R>, writer: &mut W) -> Result<(), CodedError> {
+    let copied = io::copy(entry, writer).map_err(|e| CodedError::io_write(format!("Error extracting archive entry: {}", e)))?;
+    if copied < entry.size() {
+        return Err(CodedError::truncated("Archive is truncated: unexpected end of tar data"));
+    }
+    Ok(())
+}
+
+/// 读取归档的第一个条目（清单）
+pub fn read_manifest<R: Read>(entries: &mut Entries<'_, R>) -> Result<ArchiveManifest, CodedError> {
+    let (mut entry, name, kind) = next_entry(entries)?.ok_or_else(|| CodedError::bad_header("Not an encrypted archive: archive is empty"))?;
+    if name != MANIFEST_ENTRY || kind != EntryKind::File {
+        return Err(CodedError::bad_header(format!("Not an encrypted archive: first entry is {}", name)));
+    }
+    let mut json = Vec::with_capacity(entry.size() as usize);
+    copy_data(&mut entry, &mut json)?;
+    ArchiveManifest::from_json(&json)
+}
+
+/// 解开清单中选中的目录和文件，创建的文件和目录记录在 created 中（出错时由调用方删除）
+///
+/// 文件的权限和修改时间在写入后还原，目录的在全部写入之后从最深处开始还原。每个条目之前调用 check()，返回错误时中止。
+pub fn extract<R: Read>(entries: &mut Entries<'_, R>, manifest: &ArchiveManifest, output_dir: &str, created: &mut Vec<PathBuf>, check: impl Fn() -> Result<(), CodedError>) -> Result<(), CodedError> {
+    let output_root = Path::new(output_dir);
+    let mut directories: HashSet<&str> = manifest.directories.iter().map(|dir| dir.path.as_str()).collect();
+    let mut files: HashSet<&str> = manifest.files.iter().map(|file| file.path.as_str()).collect();
+    let mut restored = Vec::new();
+    walk::create_dirs(output_root, created)?;
+    while let Some((mut entry, name, kind)) = next_entry(entries)? {
+        check()?;
+        let selected = match kind {
+            EntryKind::Directory => directories.remove(name.as_str()),
+            EntryKind::File => files.remove(name.as_str()),
+            EntryKind::Other => false,
+        };
+        if !selected {
+            continue;
+        }
+        let header = entry.header();
+        let mode = header.mode().map_err(|_| CodedError::bad_header("Invalid tar header: bad mode field"))?;
+        let modified = header.mtime().map_err(|_| CodedError::bad_header("Invalid tar header: bad mtime field"))?;
+        let path = walk::safe_join(output_root, &name)?;
+        if kind == EntryKind::Directory {
+            walk::create_dirs(&path, created)?;
+            restored.push((path, mode, modified));
+            continue;
+        }
+        if let Some(parent) = path.parent() {
+            walk::create_dirs(parent, created)?;
+        }
+        if !path.exists() {
+            created.push(path.clone());
+        }
+        let mut output = File::create(&path).map_err(|e| CodedError::io_open(format!("Failed to create {}: {}", path.display(), e)))?;
+        copy_data(&mut entry, &mut output)?;
+        drop(output);
+        directory::restore_metadata(&path, mode, modified);
+    }
+    if let Some(missing) = files.iter().chain(directories.iter()).next() {
+        return Err(CodedError::truncated(format!("Archive is truncated: {} is missing", missing)));
+    }
+    for (path, mode, modified) in restored.iter().rev() {
+        directory::restore_metadata(path, *mode, *modified);
+    }
+    Ok(())
+}
+
+/// 归档输出文件不能位于源目录之内，否则会把自己打包进去
+pub fn check_output(input_dir: &Path, output_path: &str) -> Result<(), CodedError> {
+    let parent = Path::new(output_path).parent().filter(|parent| !parent.as_os_str().is_empty()).unwrap_or(Path::new("."));
+    let inside = fs::canonicalize(parent).is_ok_and(|parent| parent.starts_with(input_dir));
+    match inside {
+        true => Err(CodedError::invalid_argument(format!("Output file {} must not be inside the input directory", output_path))),
+        false => Ok(()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tar::Archive;
+
+    /// 测试用的临时目录，结束时删除
+    struct TempDir(PathBuf);
+
+    impl TempDir {
+        fn new(name: &str) -> Self {
+            let path = std::env::temp_dir().join(format!("zippy-archive-{}-{}", std::process::id(), name));
+            let _ = fs::remove_dir_all(&path);
+            fs::create_dir_all(&path).unwrap();
+            TempDir(path)
+        }
+    }
+
+    impl Drop for TempDir {
+        fn drop(&mut self) {
+            let _ = fs::remove_dir_all(&self.0);
+        }
+    }
+
+    const LONG_DIR: &str = "a-directory-name-long-enough-that-the-full-path-does-not-fit-in-the-hundred-byte-ustar-name-field";
+
+    /// 打包测试目录，返回清单、tar 和规划的大小
+    fn archive_source(source: &TempDir) -> (ArchiveManifest, Vec<u8>, u64) {
+        fs::create_dir_all(source.0.join("empty")).unwrap();
+        fs::create_dir_all(source.0.join(LONG_DIR)).unwrap();
+        fs::write(source.0.join("a.txt"), b"abc").unwrap();
+        fs::write(source.0.join(LONG_DIR).join("data.bin"), vec![7u8; 1000]).unwrap();
+        fs::write(source.0.join("zero"), b"").unwrap();
+        let tree = directory::collect_tree(&source.0, &PathFilter::new(None, None).unwrap()).unwrap();
+        let (manifest, json, size) = plan(&tree).unwrap();
+        let tar = write_tar(&tree, &json, Vec::new(), || Ok(())).unwrap();
+        (manifest, tar, size)
+    }
+
+    #[test]
+    fn written_tar_matches_plan() {
+        let source = TempDir::new("plan-source");
+        let (manifest, tar, size) = archive_source(&source);
+        assert_eq!(tar.len() as u64, size);
+        assert_eq!(manifest.files.len(), 3);
+        for file in &manifest.files {
+            let data = fs::read(source.0.join(&file.path)).unwrap();
+            assert_eq!(&tar[file.offset as usize..(file.offset + file.size) as usize], &data[..], "{}", file.path);
+        }
+
+        let output = TempDir::new("plan-output");
+        let mut archive = Archive::new(&tar[..]);
+        let mut entries = archive.entries().unwrap();
+        let manifest = read_manifest(&mut entries).unwrap();
+        let mut created = Vec::new();
+        extract(&mut entries, &manifest, output.0.to_str().unwrap(), &mut created, || Ok(())).unwrap();
+        assert_eq!(fs::read(output.0.join(LONG_DIR).join("data.bin")).unwrap(), vec![7u8; 1000]);
+        assert_eq!(fs::read(output.0.join("a.txt")).unwrap(), b"abc");
+        assert!(output.0.join("empty").is_dir());
+    }
+
+    #[test]
+    fn rejects_truncated_tar() {
+        let source = TempDir::new("truncated-source");
+        let (manifest, tar, _) = archive_source(&source);
+        let output = TempDir::new("truncated-output");
+        let extract_prefix = |len: usize| {
+            let mut archive = Archive::new(&tar[..len]);
+            let mut entries = archive.entries().unwrap();
+            let manifest = read_manifest(&mut entries).unwrap();
+            extract(&mut entries, &manifest, output.0.to_str().unwrap(), &mut Vec::new(), || Ok(()))
+        };
+        let data = manifest.files.iter().find(|file| file.size == 1000).unwrap();
+        // 文件数据不完整
+        assert_eq!(extract_prefix(data.offset as usize + 10).unwrap_err(), CodedError::truncated("Archive is truncated: unexpected end of tar data"));
+        // 文件头不完整
+        assert!(extract_prefix(data.offset as usize - 100).is_err());
+        // 在条目之间截断时，清单中的条目缺失
+        let text = manifest.files.iter().find(|file| file.path == "a.txt").unwrap();
+        let err = extract_prefix(text.offset as usize - BLOCK_SIZE as usize).unwrap_err();
+        assert_eq!(err.code, ErrorCode::Truncated);
+        assert!(err.message.starts_with("Archive is truncated: "));
+        // 只缺少结尾的全零块时所有条目都已读到
+        extract_prefix(tar.len() - 2 * BLOCK_SIZE as usize).unwrap();
+    }
+
+    #[test]
+    fn reads_pax_long_names() {
+        // 早期版本写入的 ustar 归档：名称超过 100 字节时写入 PAX 扩展头
+        let long_name = format!("{}/data.bin", LONG_DIR);
+        let mut builder = Builder::new(Vec::new());
+        let json = br#"{"createdAt":0,"directories":[],"files":[]}"#;
+        let mut header = Header::new_ustar();
+        header.set_size(json.len() as u64);
+        header.set_mode(0o644);
+        builder.append_data(&mut header, MANIFEST_ENTRY, &json[..]).unwrap();
+        builder.append_pax_extensions([("path", long_name.as_bytes())]).unwrap();
+        let mut header = Header::new_ustar();
+        header.as_old_mut().name.copy_from_slice(&long_name.as_bytes()[..100]);
+        header.set_size(5);
+        header.set_mode(0o600);
+        header.set_cksum();
+        builder.append(&header, &b"hello"[..]).unwrap();
+        let tar = builder.into_inner().unwrap();
+
+        let mut archive = Archive::new(&tar[..]);
+        let mut entries = archive.entries().unwrap();
+        read_manifest(&mut entries).unwrap();
+        let (mut entry, name, kind) = next_entry(&mut entries).unwrap().unwrap();
+        assert_eq!(name, long_name);
+        assert!(kind == EntryKind::File);
+        let mut data = Vec::new();
+        copy_data(&mut entry, &mut data).unwrap();
+        assert_eq!(data, b"hello");
+        assert!(next_entry(&mut entries).unwrap().is_none());
+    }
+}
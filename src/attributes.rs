@@ -0,0 +1,313 @@
+use std::fs::{File, FileTimes};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use base64::engine::general_purpose::STANDARD;
+use base64::Engine;
+use serde_json::{json, Map, Value};
+
+use crate::errors::CodedError;
+
+/// 加密时记录的文件属性，加密保存在文件头中，解密时可以还原到输出文件
+///
+/// 平台不支持的属性不记录（如 Windows 上的权限和所有者）。
+#[derive(Default)]
+pub struct FileAttributes {
+    /// POSIX 权限位（含 setuid、setgid 和 sticky）
+    pub mode: Option<u32>,
+    /// 修改时间和访问时间（Unix 纳秒）
+    pub modified: Option<u64>,
+    pub accessed: Option<u64>,
+    pub uid: Option<u32>,
+    pub gid: Option<u32>,
+    /// 扩展属性（名称和值），名称不是 UTF-8 的属性不记录
+    pub xattrs: Vec<(String, Vec<u8>)>,
+}
+
+fn unix_ns(time: std::io::Result<SystemTime>) -> Option<u64> {
+    time.ok()?.duration_since(UNIX_EPOCH).ok().map(|elapsed| elapsed.as_nanos() as u64)
+}
+
+impl FileAttributes {
+    /// 读取已打开文件的属性；应在读取文件内容之前调用，否则访问时间可能已被更新
+    pub fn capture(file: &File) -> Result<Self, CodedError> {
+        let metadata = file.metadata().map_err(|e| CodedError::io_read(format!("Failed to get file metadata: {}", e)))?;
+        let mut attributes = FileAttributes {
+            modified: unix_ns(metadata.modified()),
+            accessed: unix_ns(metadata.accessed()),
+            xattrs: xattr::list(file)?,
+            ..FileAttributes::default()
+        };
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::MetadataExt;
+            attributes.mode = Some(metadata.mode() & 0o7777);
+            attributes.uid = Some(metadata.uid());
+            attributes.gid = Some(metadata.gid());
+        }
+        Ok(attributes)
+    }
+
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let xattrs: Map<String, Value> = self.xattrs.iter().map(|(name, value)| (name.clone(), Value::from(STANDARD.encode(value)))).collect();
+        json!({
+            "mode": self.mode,
+            "modified": self.modified,
+            "accessed": self.accessed,
+            "uid": self.uid,
+            "gid": self.gid,
+            "xattrs": xattrs,
+        })
+        .to_string()
+        .into_bytes()
+    }
+
+    pub fn from_bytes(bytes: &[u8]) -> Result<Self, CodedError> {
+        let invalid = || CodedError::bad_format("Invalid stored file attributes");
+        let value: Value = serde_json::from_slice(bytes).map_err(|_| invalid())?;
+        let number = |name: &str| value[name].as_u64();
+        let xattrs = match value["xattrs"].as_object() {
+            Some(xattrs) => xattrs
+                .iter()
+                .map(|(name, data)| Ok((name.clone(), data.as_str().and_then(|data| STANDARD.decode(data).ok()).ok_or_else(invalid)?)))
+                .collect::<Result<_, CodedError>>()?,
+            None => Vec::new(),
+        };
+        Ok(FileAttributes {
+            mode: number("mode").map(|mode| mode as u32),
+            modified: number("modified"),
+            accessed: number("accessed"),
+            uid: number("uid").map(|uid| uid as u32),
+            gid: number("gid").map(|gid| gid as u32),
+            xattrs,
+        })
+    }
+
+    /// 把属性还原到已写完的输出文件，尽力而为：不是普通文件时直接返回，单项失败时忽略
+    ///
+    /// 修改所有者通常需要 root 权限，没有权限时保持当前用户；所有者在权限之前还原，
+    /// 因为修改所有者会清除 setuid 和 setgid 位。
+    pub fn restore(&self, file: &File) {
+        if !file.metadata().is_ok_and(|metadata| metadata.is_file()) {
+            return;
+        }
+        for (name, value) in &self.xattrs {
+            let _ = xattr::set(file, name, value);
+        }
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::PermissionsExt;
+            if self.uid.is_some() || self.gid.is_some() {
+                let _ = std::os::unix::fs::fchown(file, self.uid, self.gid);
+            }
+            if let Some(mode) = self.mode {
+                let _ = file.set_permissions(std::fs::Permissions::from_mode(mode));
+            }
+        }
+        let time = |ns: u64| UNIX_EPOCH + Duration::from_nanos(ns);
+        let mut times = FileTimes::new();
+        if let Some(modified) = self.modified {
+            times = times.set_modified(time(modified));
+        }
+        if let Some(accessed) = self.accessed {
+            times = times.set_accessed(time(accessed));
+        }
+        let _ = file.set_times(times);
+    }
+}
+
+/// 扩展属性的读写，通过文件描述符进行，因此也适用于以文件描述符传入的文件
+#[cfg(any(target_os = "linux", target_os = "android", target_os = "macos"))]
+mod xattr {
+    use std::ffi::CString;
+    use std::fs::File;
+    use std::io;
+    use std::os::unix::io::AsRawFd;
+
+    use crate::errors::CodedError;
+
+    #[cfg(target_os = "macos")]
+    unsafe fn flistxattr(fd: i32, list: *mut libc::c_char, size: usize) -> isize {
+        libc::flistxattr(fd, list, size, 0)
+    }
+    #[cfg(not(target_os = "macos"))]
+    unsafe fn flistxattr(fd: i32, list: *mut libc::c_char, size: usize) -> isize {
+        libc::flistxattr(fd, list, size)
+    }
+    #[cfg(target_os = "macos")]
+    unsafe fn fgetxattr(fd: i32, name: *const libc::c_char, value: *mut libc::c_void, size: usize) -> isize {
+        libc::fgetxattr(fd, name, value, size, 0, 0)
+    }
+    #[cfg(not(target_os = "macos"))]
+    unsafe fn fgetxattr(fd: i32, name: *const libc::c_char, value: *mut libc::c_void, size: usize) -> isize {
+        libc::fgetxattr(fd, name, value, size)
+    }
+    #[cfg(target_os = "macos")]
+    unsafe fn fsetxattr(fd: i32, name: *const libc::c_char, value: *const libc::c_void, size: usize) -> i32 {
+        libc::fsetxattr(fd, name, value, size, 0, 0)
+    }
+    #[cfg(not(target_os = "macos"))]
+    unsafe fn fsetxattr(fd: i32, name: *const libc::c_char, value: *const libc::c_void, size: usize) -> i32 {
+        libc::fsetxattr(fd, name, value, size, 0)
+    }
+
+    /// 先查询长度再读取；两次调用之间属性可能变大（ERANGE），此时重试
+    fn read_sized(mut read: impl FnMut(&mut [u8]) -> isize) -> io::Result<Vec<u8>> {
+        loop {
+            let len = read(&mut []);
+            if len < 0 {
+                return Err(io::Error::last_os_error());
+            }
+            let mut buffer = vec![0u8; len as usize];
+            let read_len = read(&mut buffer);
+            if read_len >= 0 {
+                buffer.truncate(read_len as usize);
+                return Ok(buffer);
+            }
+            let err = io::Error::last_os_error();
+            if err.raw_os_error() != Some(libc::ERANGE) {
+                return Err(err);
+            }
+        }
+    }
+
+    fn is_unsupported(err: &io::Error) -> bool {
+        matches!(err.raw_os_error(), Some(libc::ENOTSUP) | Some(libc::ENOSYS))
+    }
+
+    pub fn list(file: &File) -> Result<Vec<(String, Vec<u8>)>, CodedError> {
+        let fd = file.as_raw_fd();
+        let names = match read_sized(|buffer| unsafe { flistxattr(fd, buffer.as_mut_ptr().cast(), buffer.len()) }) {
+            Ok(names) => names,
+            // 文件系统不支持扩展属性，或者是管道等
+            Err(err) if is_unsupported(&err) || err.raw_os_error() == Some(libc::EBADF) => return Ok(Vec::new()),
+            Err(err) => return Err(CodedError::io_read(format!("Failed to list extended attributes: {}", err))),
+        };
+        let mut xattrs = Vec::new();
+        for name in names.split(|&byte| byte == 0).filter(|name| !name.is_empty()) {
+            let (Ok(text), Ok(c_name)) = (std::str::from_utf8(name), CString::new(name)) else {
+                continue;
+            };
+            match read_sized(|buffer| unsafe { fgetxattr(fd, c_name.as_ptr(), buffer.as_mut_ptr().cast(), buffer.len()) }) {
+                Ok(value) => xattrs.push((text.to_string(), value)),
+                // 列出之后被删除的属性
+                Err(err) if err.raw_os_error() == Some(libc::ENODATA) => {}
+                Err(err) => return Err(CodedError::io_read(format!("Failed to read extended attribute {}: {}", text, err))),
+            }
+        }
+        Ok(xattrs)
+    }
+
+    pub fn set(file: &File, name: &str, value: &[u8]) -> io::Result<()> {
+        let name = CString::new(name).map_err(io::Error::other)?;
+        match unsafe { fsetxattr(file.as_raw_fd(), name.as_ptr(), value.as_ptr().cast(), value.len()) } {
+            0 => Ok(()),
+            _ => Err(io::Error::last_os_error()),
+        }
+    }
+}
+
+#[cfg(not(any(target_os = "linux", target_os = "android", target_os = "macos")))]
+mod xattr {
+    use std::fs::File;
+    use std::io;
+
+    use crate::errors::CodedError;
+
+    pub fn list(_file: &File) -> Result<Vec<(String, Vec<u8>)>, CodedError> {
+        Ok(Vec::new())
+    }
+
+    pub fn set(_file: &File, _name: &str, _value: &[u8]) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::errors::ErrorCode;
+    use std::fs;
+    use std::path::PathBuf;
+
+    struct TempFile(PathBuf);
+
+    impl TempFile {
+        fn new(name: &str) -> Self {
+            let path = std::env::temp_dir().join(format!("zippy-attributes-{}-{}", std::process::id(), name));
+            fs::write(&path, b"data").unwrap();
+            TempFile(path)
+        }
+
+        fn open(&self) -> File {
+            fs::OpenOptions::new().read(true).write(true).open(&self.0).unwrap()
+        }
+    }
+
+    impl Drop for TempFile {
+        fn drop(&mut self) {
+            let _ = fs::remove_file(&self.0);
+        }
+    }
+
+    #[test]
+    fn round_trips_through_bytes() {
+        let attributes = FileAttributes {
+            mode: Some(0o4755),
+            modified: Some(1_700_000_000_123_456_789),
+            accessed: Some(1_600_000_000_000_000_000),
+            uid: Some(1000),
+            gid: None,
+            xattrs: vec![("user.comment".to_string(), vec![0, 1, 2, 0xff])],
+        };
+        let parsed = FileAttributes::from_bytes(&attributes.to_bytes()).unwrap();
+        assert_eq!(parsed.mode, Some(0o4755));
+        assert_eq!(parsed.modified, attributes.modified);
+        assert_eq!(parsed.accessed, attributes.accessed);
+        assert_eq!((parsed.uid, parsed.gid), (Some(1000), None));
+        assert_eq!(parsed.xattrs, attributes.xattrs);
+
+        // 缺少的字段视为未记录
+        let empty = FileAttributes::from_bytes(b"{}").unwrap();
+        assert!(empty.mode.is_none() && empty.modified.is_none() && empty.xattrs.is_empty());
+    }
+
+    #[test]
+    fn rejects_invalid_bytes() {
+        for bytes in [&b"not json"[..], b"", br#"{"xattrs":{"user.a":"***"}}"#, br#"{"xattrs":{"user.a":1}}"#] {
+            assert_eq!(FileAttributes::from_bytes(bytes).err().unwrap().code, ErrorCode::BadFormat);
+        }
+    }
+
+    #[test]
+    fn captures_and_restores_times_and_mode() {
+        let source = TempFile::new("source");
+        let target = TempFile::new("target");
+        let modified = UNIX_EPOCH + Duration::from_secs(1_500_000_000);
+        let accessed = UNIX_EPOCH + Duration::from_secs(1_400_000_000);
+        source.open().set_times(FileTimes::new().set_modified(modified).set_accessed(accessed)).unwrap();
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::PermissionsExt;
+            fs::set_permissions(&source.0, fs::Permissions::from_mode(0o640)).unwrap();
+        }
+        let has_xattr = xattr::set(&source.open(), "user.zippy", b"value").is_ok();
+
+        let attributes = FileAttributes::capture(&source.open()).unwrap();
+        assert_eq!(attributes.modified, Some(1_500_000_000 * 1_000_000_000));
+        attributes.restore(&target.open());
+
+        let metadata = fs::metadata(&target.0).unwrap();
+        assert_eq!(metadata.modified().unwrap(), modified);
+        assert_eq!(metadata.accessed().unwrap(), accessed);
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::PermissionsExt;
+            assert_eq!(metadata.permissions().mode() & 0o7777, 0o640);
+        }
+        // 临时目录的文件系统可能不支持用户扩展属性
+        if has_xattr {
+            let xattrs = xattr::list(&target.open()).unwrap();
+            assert!(xattrs.contains(&("user.zippy".to_string(), b"value".to_vec())));
+        }
+    }
+}
@@ -0,0 +1,100 @@
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Mutex;
+use std::thread;
+
+use crate::config;
+use crate::errors::CodedError;
+
+/// 未指定并发数时使用的工作线程数：配置的 threads，为 0 时按 CPU 核数
+pub fn default_concurrency() -> usize {
+    match config::get().map_or(0, |config| config.threads) {
+        0 => thread::available_parallelism().map_or(4, |threads| threads.get()),
+        threads => threads,
+    }
+}
+
+/// 以最多 concurrency 个工作线程依次处理 0..count 的各项，按序号顺序返回结果
+///
+/// 单项的失败由 process 记录在结果中，不影响其它项；超时或取消时不再开始新的项，等正在处理的项结束后返回错误。
+pub fn run<T: Send>(count: usize, concurrency: usize, check: impl Fn() -> Result<(), CodedError> + Sync, process: impl Fn(usize) -> T + Sync) -> Result<Vec<T>, CodedError> {
+    let next = AtomicUsize::new(0);
+    let results = Mutex::new((0..count).map(|_| None).collect::<Vec<Option<T>>>());
+    let stopped = Mutex::new(None);
+    let workers = concurrency.clamp(1, count.max(1));
+
+    thread::scope(|scope| {
+        for _ in 0..workers {
+            scope.spawn(|| loop {
+                let index = next.fetch_add(1, Ordering::Relaxed);
+                if index >= count {
+                    return;
+                }
+                if let Err(err) = check() {
+                    stopped.lock().unwrap().get_or_insert(err);
+                    return;
+                }
+                let result = process(index);
+                results.lock().unwrap()[index] = Some(result);
+            });
+        }
+    });
+
+    if let Some(err) = stopped.into_inner().unwrap() {
+        return Err(err);
+    }
+    Ok(results.into_inner().unwrap().into_iter().map(|result| result.unwrap()).collect())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::errors::ErrorCode;
+    use std::sync::atomic::AtomicBool;
+    use std::time::Duration;
+
+    #[test]
+    fn returns_results_in_order() {
+        let results = run(100, 8, || Ok(()), |index| {
+            // 让后面的项先完成
+            thread::sleep(Duration::from_micros(((100 - index) * 10) as u64));
+            index * 2
+        })
+        .unwrap();
+        assert_eq!(results, (0..100).map(|index| index * 2).collect::<Vec<_>>());
+        assert!(run(0, 8, || Ok(()), |index| index).unwrap().is_empty());
+        // 并发数为 0 时仍使用一个工作线程
+        assert_eq!(run(3, 0, || Ok(()), |index| index).unwrap(), [0, 1, 2]);
+    }
+
+    #[test]
+    fn limits_concurrency() {
+        let active = AtomicUsize::new(0);
+        let peak = AtomicUsize::new(0);
+        run(40, 3, || Ok(()), |_| {
+            let now = active.fetch_add(1, Ordering::SeqCst) + 1;
+            peak.fetch_max(now, Ordering::SeqCst);
+            thread::sleep(Duration::from_millis(1));
+            active.fetch_sub(1, Ordering::SeqCst);
+        })
+        .unwrap();
+        assert!(peak.load(Ordering::SeqCst) <= 3);
+    }
+
+    #[test]
+    fn stops_starting_items_when_cancelled() {
+        let cancelled = AtomicBool::new(false);
+        let started = AtomicUsize::new(0);
+        let check = || match cancelled.load(Ordering::SeqCst) {
+            true => Err(CodedError::aborted("Operation aborted")),
+            false => Ok(()),
+        };
+        let result = run(100, 2, check, |index| {
+            started.fetch_add(1, Ordering::SeqCst);
+            if index == 5 {
+                cancelled.store(true, Ordering::SeqCst);
+            }
+        });
+        assert_eq!(result.err().unwrap().code, ErrorCode::Aborted);
+        assert!(started.load(Ordering::SeqCst) < 10);
+    }
+}
@@ -0,0 +1,1181 @@
+use std::collections::HashMap;
+use std::io::{BufReader, BufWriter, Read, Write};
+
+use aes::cipher::{BlockEncrypt, KeyInit};
+use aes::Aes128;
+use rand::RngCore;
+
+use crate::errors::{open_error, read_error, write_error, CodedError};
+use crate::fd::{self, SyncOutput, Target};
+use crate::pipeline;
+
+/// CENC 使用 AES-128，密钥和 KID 都是 16 字节
+pub const KEY_LEN: usize = 16;
+pub const KID_LEN: usize = 16;
+const BLOCK_LEN: usize = 16;
+/// cenc 方案每个样本的 IV 长度（8 字节 IV 加 8 字节分组计数器）
+const CENC_IV_LEN: usize = 8;
+/// cbcs 方案的常量 IV 长度
+const CBCS_IV_LEN: usize = 16;
+/// cbcs 视频的加密模式：每 10 个分组加密 1 个
+const CBCS_VIDEO_PATTERN: (u8, u8) = (1, 9);
+/// W3C Clear Key 的 DRM 系统 ID（1077efec-c0b2-4d02-ace3-3c1e52e2fb4b）
+const CLEAR_KEY_SYSTEM_ID: [u8; 16] = [0x10, 0x77, 0xef, 0xec, 0xc0, 0xb2, 0x4d, 0x02, 0xac, 0xe3, 0x3c, 0x1e, 0x52, 0xe2, 0xfb, 0x4b];
+/// 整体读入内存处理的 moov、moof、mfra 的大小上限
+const MAX_METADATA_LEN: u64 = 64 * 1024 * 1024;
+/// 片段的 mdat 整体读入内存加密，超过此大小时报错
+const MAX_FRAGMENT_LEN: u64 = 1024 * 1024 * 1024;
+
+/// tfhd 和 trun 的标志位
+const TFHD_BASE_DATA_OFFSET: u32 = 0x1;
+const TFHD_SAMPLE_DESCRIPTION_INDEX: u32 = 0x2;
+const TFHD_DEFAULT_SAMPLE_DURATION: u32 = 0x8;
+const TFHD_DEFAULT_SAMPLE_SIZE: u32 = 0x10;
+const TFHD_DEFAULT_SAMPLE_FLAGS: u32 = 0x20;
+const TFHD_DEFAULT_BASE_IS_MOOF: u32 = 0x20000;
+const TRUN_DATA_OFFSET: u32 = 0x1;
+const TRUN_FIRST_SAMPLE_FLAGS: u32 = 0x4;
+const TRUN_SAMPLE_DURATION: u32 = 0x100;
+const TRUN_SAMPLE_SIZE: u32 = 0x200;
+const TRUN_SAMPLE_FLAGS: u32 = 0x400;
+const TRUN_SAMPLE_COMPOSITION_TIME_OFFSET: u32 = 0x800;
+/// senc 中包含子样本信息
+const SENC_USE_SUBSAMPLES: u32 = 0x2;
+
+fn malformed() -> CodedError {
+    CodedError::bad_format("Invalid MP4 file: malformed box structure")
+}
+
+/// 通用加密（ISO/IEC 23001-7）的保护方案
+#[derive(Clone, Copy, PartialEq)]
+pub enum Scheme {
+    /// AES-CTR，整段加密受保护的数据，每个样本一个 8 字节 IV
+    Cenc,
+    /// AES-CBC，视频按 1:9 的模式加密，所有样本使用同一个常量 IV
+    Cbcs,
+}
+
+impl Scheme {
+    pub fn parse(name: &str) -> Result<Self, CodedError> {
+        match name {
+            "cenc" => Ok(Scheme::Cenc),
+            "cbcs" => Ok(Scheme::Cbcs),
+            _ => Err(CodedError::unsupported(format!("Unsupported CENC scheme: {}, supported: cenc, cbcs", name))),
+        }
+    }
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Scheme::Cenc => "cenc",
+            Scheme::Cbcs => "cbcs",
+        }
+    }
+
+    /// cenc 的 IV 为起始的样本 IV，cbcs 的 IV 为常量 IV
+    pub fn iv_len(&self) -> usize {
+        match self {
+            Scheme::Cenc => CENC_IV_LEN,
+            Scheme::Cbcs => CBCS_IV_LEN,
+        }
+    }
+}
+
+/// 加密参数：iv 为 cenc 的起始样本 IV（8 字节）或 cbcs 的常量 IV（16 字节），None 时随机生成；clear_key_pssh 时在 moov 中加入 Clear Key 的 pssh
+pub struct Protection<'a> {
+    pub key: &'a [u8; KEY_LEN],
+    pub kid: &'a [u8; KID_LEN],
+    pub scheme: Scheme,
+    pub iv: Option<&'a [u8]>,
+    pub clear_key_pssh: bool,
+}
+
+pub struct CencReport {
+    pub plaintext_size: u64,
+    pub encrypted_size: u64,
+    pub tracks: u32,
+    pub fragments: u32,
+    pub samples: u64,
+}
+
+/// 顶层盒子的头部，body_len 为 None 表示盒子一直延续到文件末尾
+struct BoxHeader {
+    kind: [u8; 4],
+    raw: Vec<u8>,
+    body_len: Option<u64>,
+}
+
+impl BoxHeader {
+    fn read<R: Read>(reader: &mut R) -> Result<Option<Self>, CodedError> {
+        let mut raw = vec![0u8; 8];
+        match pipeline::read_full(reader, &mut raw).map_err(|err| read_error("reading input file", err))? {
+            0 => return Ok(None),
+            8 => {}
+            _ => return Err(malformed()),
+        }
+        let kind: [u8; 4] = raw[4..8].try_into().unwrap();
+        let body_len = match u32::from_be_bytes(raw[..4].try_into().unwrap()) {
+            0 => None,
+            1 => {
+                raw.resize(16, 0);
+                if pipeline::read_full(reader, &mut raw[8..]).map_err(|err| read_error("reading input file", err))? != 8 {
+                    return Err(malformed());
+                }
+                let size = u64::from_be_bytes(raw[8..].try_into().unwrap());
+                Some(size.checked_sub(16).ok_or_else(malformed)?)
+            }
+            size => Some((size as u64).checked_sub(8).ok_or_else(malformed)?),
+        };
+        Ok(Some(BoxHeader { kind, raw, body_len }))
+    }
+
+    fn name(&self) -> String {
+        String::from_utf8_lossy(&self.kind).into_owned()
+    }
+
+    /// 把盒子内容整体读入内存
+    fn read_body<R: Read>(&self, reader: &mut R, limit: u64) -> Result<Vec<u8>, CodedError> {
+        let mut body = Vec::new();
+        match self.body_len {
+            Some(len) if len > limit => return Err(CodedError::unsupported(format!("MP4 {} box is too large ({} bytes)", self.name(), len))),
+            Some(len) => {
+                body.resize(len as usize, 0);
+                if pipeline::read_full(reader, &mut body).map_err(|err| read_error("reading input file", err))? != body.len() {
+                    return Err(CodedError::truncated("Invalid MP4 file: file is truncated"));
+                }
+            }
+            None => {
+                reader
+                    .take(limit + 1)
+                    .read_to_end(&mut body)
+                    .map_err(|e| read_error("reading input file", e))?;
+                if body.len() as u64 > limit {
+                    return Err(CodedError::unsupported(format!("MP4 {} box is too large", self.name())));
+                }
+            }
+        }
+        Ok(body)
+    }
+
+    /// 原样复制盒子
+    fn copy<R: Read, W: Write>(&self, reader: &mut R, writer: &mut W) -> Result<u64, CodedError> {
+        writer
+            .write_all(&self.raw)
+            .map_err(|e| write_error("writing output file", e))?;
+        let limit = self.body_len.unwrap_or(u64::MAX);
+        let copied = std::io::copy(&mut reader.take(limit), writer).map_err(|e| read_error("copying MP4 box", e))?;
+        if self.body_len.is_some_and(|len| copied != len) {
+            return Err(CodedError::truncated("Invalid MP4 file: file is truncated"));
+        }
+        Ok(self.raw.len() as u64 + copied)
+    }
+}
+
+/// 内存中的盒子，data 包含头部
+struct Mp4Box<'a> {
+    kind: [u8; 4],
+    header_len: usize,
+    data: &'a [u8],
+}
+
+impl<'a> Mp4Box<'a> {
+    fn body(&self) -> &'a [u8] {
+        &self.data[self.header_len..]
+    }
+}
+
+fn parse_boxes(mut data: &[u8]) -> Result<Vec<Mp4Box<'_>>, CodedError> {
+    let mut boxes = Vec::new();
+    while !data.is_empty() {
+        if data.len() < 8 {
+            return Err(malformed());
+        }
+        let kind: [u8; 4] = data[4..8].try_into().unwrap();
+        let (size, header_len) = match u32::from_be_bytes(data[..4].try_into().unwrap()) {
+            0 => (data.len(), 8),
+            1 if data.len() >= 16 => (usize::try_from(u64::from_be_bytes(data[8..16].try_into().unwrap())).map_err(|_| malformed())?, 16),
+            1 => return Err(malformed()),
+            size => (size as usize, 8),
+        };
+        if size < header_len || size > data.len() {
+            return Err(malformed());
+        }
+        boxes.push(Mp4Box { kind, header_len, data: &data[..size] });
+        data = &data[size..];
+    }
+    Ok(boxes)
+}
+
+fn make_box(kind: &[u8; 4], body: &[u8]) -> Vec<u8> {
+    let mut output = Vec::with_capacity(8 + body.len());
+    output.extend_from_slice(&((8 + body.len()) as u32).to_be_bytes());
+    output.extend_from_slice(kind);
+    output.extend_from_slice(body);
+    output
+}
+
+fn make_full_box(kind: &[u8; 4], version: u8, flags: u32, body: &[u8]) -> Vec<u8> {
+    let mut content = ((version as u32) << 24 | flags).to_be_bytes().to_vec();
+    content.extend_from_slice(body);
+    make_box(kind, &content)
+}
+
+/// 盒子内容的顺序读取
+struct ByteReader<'a> {
+    data: &'a [u8],
+    pos: usize,
+}
+
+impl<'a> ByteReader<'a> {
+    fn new(data: &'a [u8]) -> Self {
+        ByteReader { data, pos: 0 }
+    }
+
+    fn bytes(&mut self, len: usize) -> Result<&'a [u8], CodedError> {
+        let bytes = self.data.get(self.pos..self.pos + len).ok_or_else(malformed)?;
+        self.pos += len;
+        Ok(bytes)
+    }
+
+    fn u32(&mut self) -> Result<u32, CodedError> {
+        Ok(u32::from_be_bytes(self.bytes(4)?.try_into().unwrap()))
+    }
+
+    fn u64(&mut self) -> Result<u64, CodedError> {
+        Ok(u64::from_be_bytes(self.bytes(8)?.try_into().unwrap()))
+    }
+
+    /// 全盒子（FullBox）的版本和标志
+    fn version_flags(&mut self) -> Result<(u8, u32), CodedError> {
+        let value = self.u32()?;
+        Ok(((value >> 24) as u8, value & 0x00ff_ffff))
+    }
+}
+
+/// 需要加密的轨道
+#[derive(Clone, Copy)]
+enum TrackKind {
+    /// H.264 / H.265 视频：按 NAL 单元划分子样本，NAL 长度前缀、NAL 头和非 VCL 单元保持明文
+    Video { nal_length_size: usize, hevc: bool },
+    /// 音频：整个样本加密
+    Audio,
+}
+
+struct Track {
+    kind: TrackKind,
+    /// mvex/trex 中的默认样本大小
+    default_sample_size: Option<u32>,
+}
+
+/// 加密参数和样本 IV 的状态
+struct SampleEncrypter {
+    cipher: Aes128,
+    scheme: Scheme,
+    kid: [u8; KID_LEN],
+    /// cenc：下一个样本的 IV；cbcs：常量 IV
+    iv: [u8; CBCS_IV_LEN],
+}
+
+/// 一个样本在 senc 中的辅助信息
+struct SampleAux {
+    iv: Vec<u8>,
+    subsamples: Option<Vec<(u16, u32)>>,
+}
+
+impl SampleAux {
+    fn len(&self) -> usize {
+        self.iv.len() + self.subsamples.as_ref().map_or(0, |subsamples| 2 + 6 * subsamples.len())
+    }
+
+    fn write(&self, output: &mut Vec<u8>) {
+        output.extend_from_slice(&self.iv);
+        if let Some(subsamples) = &self.subsamples {
+            output.extend_from_slice(&(subsamples.len() as u16).to_be_bytes());
+            for (clear, protected) in subsamples {
+                output.extend_from_slice(&clear.to_be_bytes());
+                output.extend_from_slice(&protected.to_be_bytes());
+            }
+        }
+    }
+}
+
+/// 按 NAL 单元划分子样本 (明文字节数, 受保护字节数)；只加密 VCL 单元 NAL 头之后的数据，相邻的明文部分合并
+///
+/// align 时受保护部分对齐到 16 字节，余下的字节并入前面的明文部分（cenc 对视频的建议做法）。
+fn video_subsamples(sample: &[u8], nal_length_size: usize, hevc: bool, align: bool) -> Result<Vec<(u16, u32)>, CodedError> {
+    let invalid = || CodedError::bad_format("Invalid video sample: NAL unit length exceeds the sample");
+    let header_len = if hevc { 2 } else { 1 };
+    let mut subsamples = Vec::new();
+    let mut clear = 0usize;
+    let mut pos = 0;
+    while pos < sample.len() {
+        let prefix = sample.get(pos..pos + nal_length_size).ok_or_else(invalid)?;
+        let len = prefix.iter().fold(0usize, |len, &byte| (len << 8) | byte as usize);
+        let start = pos + nal_length_size;
+        let nal = sample.get(start..start + len).ok_or_else(invalid)?;
+        let vcl = match nal.first() {
+            Some(&byte) if hevc => (byte >> 1) & 0x3f < 32,
+            Some(&byte) => matches!(byte & 0x1f, 1..=5),
+            None => false,
+        };
+        let mut protected = if vcl && len > header_len { len - header_len } else { 0 };
+        if align {
+            protected -= protected % BLOCK_LEN;
+        }
+        clear += nal_length_size + len - protected;
+        if protected > 0 {
+            while clear > u16::MAX as usize {
+                subsamples.push((u16::MAX, 0));
+                clear -= u16::MAX as usize;
+            }
+            subsamples.push((clear as u16, protected as u32));
+            clear = 0;
+        }
+        pos = start + len;
+    }
+    while clear > 0 {
+        let part = clear.min(u16::MAX as usize);
+        subsamples.push((part as u16, 0));
+        clear -= part;
+    }
+    Ok(subsamples)
+}
+
+impl SampleEncrypter {
+    fn new(key: &[u8; KEY_LEN], kid: &[u8; KID_LEN], scheme: Scheme, iv: &[u8]) -> Self {
+        let mut full_iv = [0u8; CBCS_IV_LEN];
+        full_iv[..iv.len()].copy_from_slice(iv);
+        SampleEncrypter { cipher: Aes128::new(&(*key).into()), scheme, kid: *kid, iv: full_iv }
+    }
+
+    fn pattern(&self, kind: TrackKind) -> (u8, u8) {
+        match (self.scheme, kind) {
+            (Scheme::Cbcs, TrackKind::Video { .. }) => CBCS_VIDEO_PATTERN,
+            _ => (0, 0),
+        }
+    }
+
+    /// 轨道的 tenc 盒子：cenc 为版本 0 和 8 字节样本 IV，cbcs 为版本 1、加密模式和常量 IV
+    fn tenc(&self, kind: TrackKind) -> Vec<u8> {
+        let mut body = Vec::new();
+        match self.scheme {
+            Scheme::Cenc => {
+                body.extend_from_slice(&[0, 0, 1, CENC_IV_LEN as u8]);
+                body.extend_from_slice(&self.kid);
+                make_full_box(b"tenc", 0, 0, &body)
+            }
+            Scheme::Cbcs => {
+                let (crypt, skip) = self.pattern(kind);
+                body.extend_from_slice(&[0, (crypt << 4) | skip, 1, 0]);
+                body.extend_from_slice(&self.kid);
+                body.push(CBCS_IV_LEN as u8);
+                body.extend_from_slice(&self.iv);
+                make_full_box(b"tenc", 1, 0, &body)
+            }
+        }
+    }
+
+    /// 就地加密一个样本，返回它在 senc 中的辅助信息
+    fn encrypt_sample(&mut self, sample: &mut [u8], kind: TrackKind) -> Result<SampleAux, CodedError> {
+        let subsamples = match kind {
+            TrackKind::Video { nal_length_size, hevc } => Some(video_subsamples(sample, nal_length_size, hevc, self.scheme == Scheme::Cenc)?),
+            TrackKind::Audio => None,
+        };
+        let ranges: Vec<std::ops::Range<usize>> = match &subsamples {
+            Some(subsamples) => {
+                let mut pos = 0;
+                subsamples
+                    .iter()
+                    .map(|&(clear, protected)| {
+                        pos += clear as usize;
+                        let range = pos..pos + protected as usize;
+                        pos += protected as usize;
+                        range
+                    })
+                    .collect()
+            }
+            None => std::iter::once(0..sample.len()).collect(),
+        };
+        match self.scheme {
+            Scheme::Cenc => {
+                // 计数器分组为 8 字节 IV 加 8 字节分组序号，同一样本的各受保护部分使用连续的密钥流
+                let iv = self.iv[..CENC_IV_LEN].to_vec();
+                let mut counter = u128::from_be_bytes(self.iv);
+                let mut keystream = [0u8; BLOCK_LEN];
+                let mut used = BLOCK_LEN;
+                for range in ranges {
+                    for byte in &mut sample[range] {
+                        if used == BLOCK_LEN {
+                            keystream = counter.to_be_bytes();
+                            self.cipher.encrypt_block((&mut keystream).into());
+                            counter = counter.wrapping_add(1);
+                            used = 0;
+                        }
+                        *byte ^= keystream[used];
+                        used += 1;
+                    }
+                }
+                let next = u64::from_be_bytes(self.iv[..CENC_IV_LEN].try_into().unwrap()).wrapping_add(1);
+                self.iv[..CENC_IV_LEN].copy_from_slice(&next.to_be_bytes());
+                Ok(SampleAux { iv, subsamples })
+            }
+            Scheme::Cbcs => {
+                // 每个受保护部分都从常量 IV 开始；按模式加密完整的分组，末尾不足一个分组的部分保持明文
+                let (crypt, skip) = match self.pattern(kind) {
+                    (0, _) => (1, 0),
+                    (crypt, skip) => (crypt as usize, skip as usize),
+                };
+                for range in ranges {
+                    let mut chain = self.iv;
+                    for (index, block) in sample[range].chunks_exact_mut(BLOCK_LEN).enumerate() {
+                        if index % (crypt + skip) >= crypt {
+                            continue;
+                        }
+                        block.iter_mut().zip(chain.iter()).for_each(|(byte, chain)| *byte ^= chain);
+                        self.cipher.encrypt_block(block.into());
+                        chain.copy_from_slice(block);
+                    }
+                }
+                Ok(SampleAux { iv: Vec::new(), subsamples })
+            }
+        }
+    }
+}
+
+/// 把容器盒子中第一个指定类型的子盒子替换为 rewrite 的结果，prefix_len 为子盒子之前的固定字段长度
+fn rewrite_child<F>(container: &Mp4Box, kind: &[u8; 4], prefix_len: usize, rewrite: F) -> Result<Vec<u8>, CodedError>
+where
+    F: FnOnce(&Mp4Box) -> Result<Vec<u8>, CodedError>,
+{
+    let body = container.body();
+    let prefix = body.get(..prefix_len).ok_or_else(malformed)?;
+    let mut content = prefix.to_vec();
+    let mut rewrite = Some(rewrite);
+    for child in parse_boxes(&body[prefix_len..])? {
+        match rewrite.take_if(|_| &child.kind == kind) {
+            Some(rewrite) => content.extend_from_slice(&rewrite(&child)?),
+            None => content.extend_from_slice(child.data),
+        }
+    }
+    if rewrite.is_some() {
+        return Err(CodedError::bad_format(format!("Invalid MP4 file: missing {} box", String::from_utf8_lossy(kind))));
+    }
+    Ok(make_box(&container.kind, &content))
+}
+
+fn find_child<'a>(boxes: &'a [Mp4Box<'a>], kind: &[u8; 4]) -> Option<&'a Mp4Box<'a>> {
+    boxes.iter().find(|child| &child.kind == kind)
+}
+
+/// VisualSampleEntry 和 AudioSampleEntry 子盒子之前的固定字段长度
+const VISUAL_SAMPLE_ENTRY_LEN: usize = 78;
+
+/// 把样本描述改为 encv / enca，并在末尾加上 sinf（frma、schm、schi/tenc）
+fn protect_sample_entry(entry: &Mp4Box, video: bool, encrypter: &SampleEncrypter) -> Result<(Vec<u8>, TrackKind), CodedError> {
+    let codec = String::from_utf8_lossy(&entry.kind).into_owned();
+    let kind = if video {
+        let config = |config_kind: &[u8; 4], offset: usize| -> Result<usize, CodedError> {
+            let children = parse_boxes(entry.body().get(VISUAL_SAMPLE_ENTRY_LEN..).ok_or_else(malformed)?)?;
+            let config = find_child(&children, config_kind).ok_or_else(|| CodedError::bad_format(format!("Invalid MP4 file: {} sample entry without {} box", codec, String::from_utf8_lossy(config_kind))))?;
+            Ok((config.body().get(offset).ok_or_else(malformed)? & 3) as usize + 1)
+        };
+        match &entry.kind {
+            b"avc1" | b"avc3" => TrackKind::Video { nal_length_size: config(b"avcC", 4)?, hevc: false },
+            b"hvc1" | b"hev1" => TrackKind::Video { nal_length_size: config(b"hvcC", 21)?, hevc: true },
+            b"encv" => return Err(CodedError::invalid_argument("MP4 file is already encrypted")),
+            _ => return Err(CodedError::unsupported(format!("Unsupported video codec for CENC: {}, supported: avc1, avc3, hvc1, hev1", codec))),
+        }
+    } else {
+        if &entry.kind == b"enca" {
+            return Err(CodedError::invalid_argument("MP4 file is already encrypted"));
+        }
+        TrackKind::Audio
+    };
+
+    let mut schm = encrypter.scheme.as_str().as_bytes().to_vec();
+    schm.extend_from_slice(&0x0001_0000u32.to_be_bytes());
+    let mut sinf = make_box(b"frma", &entry.kind);
+    sinf.extend_from_slice(&make_full_box(b"schm", 0, 0, &schm));
+    sinf.extend_from_slice(&make_box(b"schi", &encrypter.tenc(kind)));
+    let mut body = entry.body().to_vec();
+    body.extend_from_slice(&make_box(b"sinf", &sinf));
+    Ok((make_box(if video { b"encv" } else { b"enca" }, &body), kind))
+}
+
+/// 重写音频和视频轨道的样本描述并记录到 tracks 中，返回新的 trak；其它轨道原样保留
+fn protect_track(trak: &Mp4Box, encrypter: &SampleEncrypter, trex_sizes: &HashMap<u32, u32>, tracks: &mut HashMap<u32, Track>) -> Result<Vec<u8>, CodedError> {
+    let children = parse_boxes(trak.body())?;
+    let tkhd = find_child(&children, b"tkhd").ok_or_else(|| CodedError::bad_format("Invalid MP4 file: missing tkhd box"))?;
+    let mut reader = ByteReader::new(tkhd.body());
+    let (version, _) = reader.version_flags()?;
+    reader.bytes(if version == 1 { 16 } else { 8 })?;
+    let track_id = reader.u32()?;
+    let mdia = find_child(&children, b"mdia").ok_or_else(|| CodedError::bad_format("Invalid MP4 file: missing mdia box"))?;
+    let mdia_children = parse_boxes(mdia.body())?;
+    let hdlr = find_child(&mdia_children, b"hdlr").ok_or_else(|| CodedError::bad_format("Invalid MP4 file: missing hdlr box"))?;
+    let video = match hdlr.body().get(8..12) {
+        Some(b"vide") => true,
+        Some(b"soun") => false,
+        _ => return Ok(trak.data.to_vec()),
+    };
+
+    let mut kind = None;
+    let trak = rewrite_child(trak, b"mdia", 0, |mdia| {
+        rewrite_child(mdia, b"minf", 0, |minf| {
+            rewrite_child(minf, b"stbl", 0, |stbl| {
+                rewrite_child(stbl, b"stsd", 0, |stsd| {
+                    let body = stsd.body();
+                    let mut content = body.get(..8).ok_or_else(malformed)?.to_vec();
+                    for entry in parse_boxes(&body[8..])? {
+                        let (entry, entry_kind) = protect_sample_entry(&entry, video, encrypter)?;
+                        content.extend_from_slice(&entry);
+                        kind.get_or_insert(entry_kind);
+                    }
+                    Ok(make_box(b"stsd", &content))
+                })
+            })
+        })
+    })?;
+    if let Some(kind) = kind {
+        tracks.insert(track_id, Track { kind, default_sample_size: trex_sizes.get(&track_id).copied() });
+    }
+    Ok(trak)
+}
+
+/// 重写 moov：加密所有音频和视频轨道的样本描述，可选加入 Clear Key 的 pssh
+fn protect_movie(moov: &[u8], encrypter: &SampleEncrypter, clear_key_pssh: bool) -> Result<(Vec<u8>, HashMap<u32, Track>), CodedError> {
+    let children = parse_boxes(moov)?;
+    let mut trex_sizes = HashMap::new();
+    let mvex = find_child(&children, b"mvex").ok_or_else(|| CodedError::unsupported("Only fragmented MP4 files are supported: the moov box has no mvex box"))?;
+    for trex in parse_boxes(mvex.body())?.iter().filter(|child| &child.kind == b"trex") {
+        let mut reader = ByteReader::new(trex.body());
+        reader.version_flags()?;
+        let track_id = reader.u32()?;
+        reader.bytes(8)?;
+        trex_sizes.insert(track_id, reader.u32()?);
+    }
+
+    let mut content = Vec::new();
+    let mut tracks = HashMap::new();
+    for child in &children {
+        if &child.kind == b"trak" {
+            content.extend_from_slice(&protect_track(child, encrypter, &trex_sizes, &mut tracks)?);
+        } else {
+            content.extend_from_slice(child.data);
+        }
+    }
+    if tracks.is_empty() {
+        return Err(CodedError::unsupported("MP4 file has no audio or video track to encrypt"));
+    }
+    if clear_key_pssh {
+        let mut pssh = CLEAR_KEY_SYSTEM_ID.to_vec();
+        pssh.extend_from_slice(&1u32.to_be_bytes());
+        pssh.extend_from_slice(&encrypter.kid);
+        pssh.extend_from_slice(&0u32.to_be_bytes());
+        content.extend_from_slice(&make_full_box(b"pssh", 1, 0, &pssh));
+    }
+    Ok((make_box(b"moov", &content), tracks))
+}
+
+struct TrackFragmentHeader {
+    track_id: u32,
+    base_data_offset: Option<u64>,
+    default_sample_size: Option<u32>,
+    default_base_is_moof: bool,
+}
+
+impl TrackFragmentHeader {
+    fn parse(body: &[u8]) -> Result<Self, CodedError> {
+        let mut reader = ByteReader::new(body);
+        let (_, flags) = reader.version_flags()?;
+        let track_id = reader.u32()?;
+        let base_data_offset = if flags & TFHD_BASE_DATA_OFFSET != 0 { Some(reader.u64()?) } else { None };
+        for flag in [TFHD_SAMPLE_DESCRIPTION_INDEX, TFHD_DEFAULT_SAMPLE_DURATION] {
+            if flags & flag != 0 {
+                reader.u32()?;
+            }
+        }
+        let default_sample_size = if flags & TFHD_DEFAULT_SAMPLE_SIZE != 0 { Some(reader.u32()?) } else { None };
+        if flags & TFHD_DEFAULT_SAMPLE_FLAGS != 0 {
+            reader.u32()?;
+        }
+        Ok(TrackFragmentHeader { track_id, base_data_offset, default_sample_size, default_base_is_moof: flags & TFHD_DEFAULT_BASE_IS_MOOF != 0 })
+    }
+}
+
+struct TrackRun {
+    data_offset: Option<i32>,
+    sample_sizes: Vec<Option<u32>>,
+}
+
+impl TrackRun {
+    /// max_samples 限制样本数，没有逐样本字段的 trun 不能靠盒子长度约束样本数
+    fn parse(body: &[u8], max_samples: usize) -> Result<Self, CodedError> {
+        let mut reader = ByteReader::new(body);
+        let (_, flags) = reader.version_flags()?;
+        let sample_count = reader.u32()?;
+        if sample_count as usize > max_samples {
+            return Err(CodedError::bad_format("Invalid MP4 file: track run has more samples than its mdat has bytes"));
+        }
+        let data_offset = if flags & TRUN_DATA_OFFSET != 0 { Some(reader.u32()? as i32) } else { None };
+        if flags & TRUN_FIRST_SAMPLE_FLAGS != 0 {
+            reader.u32()?;
+        }
+        let mut sample_sizes = Vec::new();
+        for _ in 0..sample_count {
+            if flags & TRUN_SAMPLE_DURATION != 0 {
+                reader.u32()?;
+            }
+            sample_sizes.push(if flags & TRUN_SAMPLE_SIZE != 0 { Some(reader.u32()?) } else { None });
+            for flag in [TRUN_SAMPLE_FLAGS, TRUN_SAMPLE_COMPOSITION_TIME_OFFSET] {
+                if flags & flag != 0 {
+                    reader.u32()?;
+                }
+            }
+        }
+        Ok(TrackRun { data_offset, sample_sizes })
+    }
+}
+
+/// moof 变大后需要回填的字段
+enum OffsetPatch {
+    /// trun 的 data_offset，加上 moof 增加的长度
+    TrunData { pos: usize, value: i32 },
+    /// tfhd 的 base_data_offset，加上此前所有盒子增加的长度
+    TfhdBase { pos: usize, value: u64 },
+    /// saio 的偏移，指向 senc 中第一个样本的辅助信息，相对于轨道片段的基准偏移
+    SaioAuxInfo { pos: usize, senc_data: usize, base: u64 },
+}
+
+/// 片段在输入文件中的位置
+#[derive(Clone, Copy)]
+struct FragmentLayout {
+    /// moof 盒子（含头部）的位置和长度
+    moof_offset: u64,
+    moof_len: usize,
+    /// mdat 内容的位置
+    mdat_offset: u64,
+    /// 此前输出比输入增加的长度，去掉 64 位长度字段的盒子会使它为负
+    shift: i64,
+}
+
+/// 加密一个片段 mdat 中的样本并重写 moof（不含头部的内容），返回 (新的 moof, 加密的样本数)
+fn protect_fragment(moof: &[u8], mdat: &mut [u8], layout: &FragmentLayout, tracks: &HashMap<u32, Track>, encrypter: &mut SampleEncrypter) -> Result<(Vec<u8>, u64), CodedError> {
+    let FragmentLayout { moof_offset, moof_len: old_moof_len, mdat_offset, shift } = *layout;
+    let mut output = vec![0u8; 8];
+    let mut patches = Vec::new();
+    let mut samples = 0u64;
+    let mut first_traf = true;
+    for child in parse_boxes(moof)? {
+        if &child.kind != b"traf" {
+            output.extend_from_slice(child.data);
+            continue;
+        }
+        let traf_start = output.len();
+        output.extend_from_slice(&[0u8; 8]);
+        let children = parse_boxes(child.body())?;
+        let tfhd = find_child(&children, b"tfhd").ok_or_else(|| CodedError::bad_format("Invalid MP4 file: missing tfhd box"))?;
+        let header = TrackFragmentHeader::parse(tfhd.body())?;
+        let base = match header.base_data_offset {
+            Some(offset) => offset,
+            None if header.default_base_is_moof || first_traf => moof_offset,
+            None => return Err(CodedError::unsupported("Unsupported fragmented MP4 layout: track fragment without a base data offset")),
+        };
+        if base >= mdat_offset {
+            return Err(CodedError::unsupported("Unsupported fragmented MP4 layout: base data offset points into mdat"));
+        }
+        first_traf = false;
+        let track = tracks.get(&header.track_id);
+        let mut aux = Vec::new();
+        let mut data_pos = base as i64;
+        for box_ in &children {
+            let pos = output.len() + box_.header_len;
+            match &box_.kind {
+                b"tfhd" => {
+                    if let Some(value) = header.base_data_offset {
+                        patches.push(OffsetPatch::TfhdBase { pos: pos + 8, value });
+                    }
+                }
+                b"trun" => {
+                    let run = TrackRun::parse(box_.body(), mdat.len())?;
+                    if let Some(value) = run.data_offset {
+                        patches.push(OffsetPatch::TrunData { pos: pos + 8, value });
+                        data_pos = base as i64 + value as i64;
+                    }
+                    if let Some(track) = track {
+                        for size in run.sample_sizes {
+                            let size = size
+                                .or(header.default_sample_size)
+                                .or(track.default_sample_size)
+                                .ok_or_else(|| CodedError::bad_format("Invalid MP4 file: sample size is not specified"))? as usize;
+                            let start = usize::try_from(data_pos - mdat_offset as i64)
+                                .ok()
+                                .filter(|start| start + size <= mdat.len())
+                                .ok_or_else(|| CodedError::unsupported("Unsupported fragmented MP4 layout: sample data is not in the mdat following its moof"))?;
+                            aux.push(encrypter.encrypt_sample(&mut mdat[start..start + size], track.kind)?);
+                            data_pos += size as i64;
+                            samples += 1;
+                        }
+                    }
+                }
+                b"senc" | b"saiz" | b"saio" => return Err(CodedError::invalid_argument("MP4 file is already encrypted")),
+                _ => {}
+            }
+            output.extend_from_slice(box_.data);
+        }
+
+        // 样本有 IV 或子样本信息时写出 saiz、saio 和 senc；cbcs 的音频只用 tenc 中的常量 IV，不需要辅助信息
+        if aux.iter().any(|aux| aux.len() > 0) {
+            let sizes: Vec<usize> = aux.iter().map(SampleAux::len).collect();
+            if sizes.iter().any(|&size| size > u8::MAX as usize) {
+                return Err(CodedError::unsupported("Video sample has too many NAL units to describe in saiz"));
+            }
+            let mut saiz = Vec::new();
+            if sizes.iter().all(|&size| size == sizes[0]) {
+                saiz.push(sizes[0] as u8);
+                saiz.extend_from_slice(&(sizes.len() as u32).to_be_bytes());
+            } else {
+                saiz.push(0);
+                saiz.extend_from_slice(&(sizes.len() as u32).to_be_bytes());
+                saiz.extend(sizes.iter().map(|&size| size as u8));
+            }
+            output.extend_from_slice(&make_full_box(b"saiz", 0, 0, &saiz));
+            let saio_pos = output.len() + 16;
+            output.extend_from_slice(&make_full_box(b"saio", 0, 0, &[0, 0, 0, 1, 0, 0, 0, 0]));
+            let mut senc = (aux.len() as u32).to_be_bytes().to_vec();
+            aux.iter().for_each(|aux| aux.write(&mut senc));
+            let flags = if aux.iter().any(|aux| aux.subsamples.is_some()) { SENC_USE_SUBSAMPLES } else { 0 };
+            patches.push(OffsetPatch::SaioAuxInfo { pos: saio_pos, senc_data: output.len() + 16, base });
+            output.extend_from_slice(&make_full_box(b"senc", 0, flags, &senc));
+        }
+        let traf_len = (output.len() - traf_start) as u32;
+        output[traf_start..traf_start + 4].copy_from_slice(&traf_len.to_be_bytes());
+        output[traf_start + 4..traf_start + 8].copy_from_slice(b"traf");
+    }
+
+    let growth = output.len() as i64 - old_moof_len as i64;
+    let moof_len = output.len() as u32;
+    output[..4].copy_from_slice(&moof_len.to_be_bytes());
+    output[4..8].copy_from_slice(b"moof");
+    let too_large = || CodedError::unsupported("MP4 fragment offsets do not fit after adding encryption boxes");
+    for patch in patches {
+        match patch {
+            OffsetPatch::TrunData { pos, value } => {
+                let value = i32::try_from(value as i64 + growth).map_err(|_| too_large())?;
+                output[pos..pos + 4].copy_from_slice(&value.to_be_bytes());
+            }
+            OffsetPatch::TfhdBase { pos, value } => {
+                let value = value.checked_add_signed(shift).ok_or_else(too_large)?;
+                output[pos..pos + 8].copy_from_slice(&value.to_be_bytes());
+            }
+            OffsetPatch::SaioAuxInfo { pos, senc_data, base } => {
+                let value = (moof_offset + senc_data as u64)
+                    .checked_sub(base)
+                    .and_then(|value| u32::try_from(value).ok())
+                    .ok_or_else(too_large)?;
+                output[pos..pos + 4].copy_from_slice(&value.to_be_bytes());
+            }
+        }
+    }
+    Ok((output, samples))
+}
+
+/// 按新的 moof 位置更新 mfra/tfra 中的随机访问偏移
+fn update_random_access(mfra: &[u8], moof_offsets: &HashMap<u64, u64>) -> Result<Vec<u8>, CodedError> {
+    let mut content = Vec::new();
+    for child in parse_boxes(mfra)? {
+        if &child.kind != b"tfra" {
+            content.extend_from_slice(child.data);
+            continue;
+        }
+        let mut body = child.body().to_vec();
+        let mut reader = ByteReader::new(child.body());
+        let (version, _) = reader.version_flags()?;
+        reader.u32()?;
+        let lengths = reader.u32()?;
+        let entries = reader.u32()?;
+        let field_len = if version == 1 { 8 } else { 4 };
+        let index_len = (((lengths >> 4) & 3) + ((lengths >> 2) & 3) + (lengths & 3) + 3) as usize;
+        for _ in 0..entries {
+            reader.bytes(field_len)?;
+            let pos = reader.pos;
+            let offset = if version == 1 { reader.u64()? } else { reader.u32()? as u64 };
+            if let Some(&new_offset) = moof_offsets.get(&offset) {
+                if version == 1 {
+                    body[pos..pos + 8].copy_from_slice(&new_offset.to_be_bytes());
+                } else {
+                    let new_offset = u32::try_from(new_offset).map_err(|_| CodedError::unsupported("MP4 tfra offset does not fit after adding encryption boxes"))?;
+                    body[pos..pos + 4].copy_from_slice(&new_offset.to_be_bytes());
+                }
+            }
+            reader.bytes(index_len)?;
+        }
+        content.extend_from_slice(&child.data[..child.header_len]);
+        content.extend_from_slice(&body);
+    }
+    Ok(content)
+}
+
+/// 以通用加密（ISO/IEC 23001-7，cenc 或 cbcs 方案）加密分片 MP4（fMP4）文件，输出可以通过 EME 的 Clear Key 在浏览器中播放
+///
+/// 加密所有 H.264 / H.265 视频轨道和音频轨道：视频按 NAL 单元划分子样本，只加密 VCL 单元 NAL 头之后的数据；音频整个样本加密。
+/// 样本描述改为 encv / enca 并加入 sinf，每个片段加入 senc、saiz、saio。
+pub fn encrypt_file(input_path: &Target, output_path: &Target, protection: &Protection, check: impl Fn() -> Result<(), CodedError>, mut chunk_done: impl FnMut(u32, usize)) -> Result<CencReport, CodedError> {
+    let scheme = protection.scheme;
+    let iv = match protection.iv {
+        Some(iv) if iv.len() != scheme.iv_len() => return Err(CodedError::invalid_key_length(format!("{} IV must be {} bytes, got {}", scheme.as_str(), scheme.iv_len(), iv.len()))),
+        Some(iv) => iv.to_vec(),
+        None => {
+            let mut iv = vec![0u8; scheme.iv_len()];
+            rand::thread_rng().fill_bytes(&mut iv);
+            iv
+        }
+    };
+    let mut encrypter = SampleEncrypter::new(protection.key, protection.kid, scheme, &iv);
+
+    let input = fd::open(input_path).map_err(|e| open_error("opening input file", e))?;
+    let plaintext_size = input
+        .metadata()
+        .map_err(|e| read_error("reading input file metadata", e))?
+        .len();
+    let mut reader = BufReader::new(input);
+    let output = fd::create(output_path).map_err(|e| open_error("creating output file", e))?;
+    let mut writer = BufWriter::new(output);
+    let output_error = |e| write_error("writing output file", e);
+
+    let mut tracks: Option<HashMap<u32, Track>> = None;
+    let mut moof_offsets = HashMap::new();
+    let mut offset = 0u64;
+    let mut shift = 0i64;
+    let mut fragments = 0u32;
+    let mut samples = 0u64;
+    while let Some(header) = BoxHeader::read(&mut reader)? {
+        check()?;
+        if offset == 0 && &header.kind != b"ftyp" && &header.kind != b"styp" {
+            return Err(CodedError::bad_header("Not an MP4 file: missing ftyp box"));
+        }
+        let box_offset = offset;
+        offset += header.raw.len() as u64;
+        match &header.kind {
+            b"moov" => {
+                if tracks.is_some() {
+                    return Err(CodedError::bad_format("Invalid MP4 file: more than one moov box"));
+                }
+                let body = header.read_body(&mut reader, MAX_METADATA_LEN)?;
+                let (moov, protected) = protect_movie(&body, &encrypter, protection.clear_key_pssh)?;
+                writer.write_all(&moov).map_err(output_error)?;
+                offset += body.len() as u64;
+                shift += moov.len() as i64 - (header.raw.len() + body.len()) as i64;
+                tracks = Some(protected);
+            }
+            b"moof" => {
+                let tracks = tracks.as_ref().ok_or_else(|| CodedError::bad_format("Invalid MP4 file: moof box before moov box"))?;
+                let moof = header.read_body(&mut reader, MAX_METADATA_LEN)?;
+                offset += moof.len() as u64;
+                let mdat_header = BoxHeader::read(&mut reader)?
+                    .filter(|mdat| &mdat.kind == b"mdat")
+                    .ok_or_else(|| CodedError::unsupported("Unsupported fragmented MP4 layout: moof box is not followed by an mdat box"))?;
+                let mut mdat = mdat_header.read_body(&mut reader, MAX_FRAGMENT_LEN)?;
+                let mdat_offset = offset + mdat_header.raw.len() as u64;
+                let layout = FragmentLayout { moof_offset: box_offset, moof_len: header.raw.len() + moof.len(), mdat_offset, shift };
+                let (new_moof, count) = protect_fragment(&moof, &mut mdat, &layout, tracks, &mut encrypter)?;
+                writer
+                    .write_all(&new_moof)
+                    .and_then(|_| writer.write_all(&mdat_header.raw))
+                    .and_then(|_| writer.write_all(&mdat))
+                    .map_err(output_error)?;
+                moof_offsets.insert(box_offset, box_offset.checked_add_signed(shift).ok_or_else(malformed)?);
+                offset = mdat_offset + mdat.len() as u64;
+                shift += new_moof.len() as i64 - layout.moof_len as i64;
+                fragments += 1;
+                samples += count;
+                chunk_done(fragments, mdat.len());
+            }
+            b"mfra" => {
+                let body = header.read_body(&mut reader, MAX_METADATA_LEN)?;
+                let mfra = update_random_access(&body, &moof_offsets)?;
+                writer
+                    .write_all(&header.raw)
+                    .and_then(|_| writer.write_all(&mfra))
+                    .map_err(output_error)?;
+                offset += body.len() as u64;
+            }
+            b"sidx" => return Err(CodedError::unsupported("Unsupported fragmented MP4 layout: segment index (sidx) boxes are not supported")),
+            _ => offset += header.copy(&mut reader, &mut writer)? - header.raw.len() as u64,
+        }
+    }
+    let tracks = tracks.ok_or_else(|| CodedError::bad_format("Invalid MP4 file: missing moov box"))?;
+
+    let output = writer
+        .into_inner()
+        .map_err(|e| write_error("writing output file", e.into_error()))?;
+    output.sync_output().map_err(|e| write_error("flushing output file", e))?;
+    let encrypted_size = output
+        .metadata()
+        .map_err(|e| read_error("reading output file metadata", e))?
+        .len();
+    Ok(CencReport { plaintext_size, encrypted_size, tracks: tracks.len() as u32, fragments, samples })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::errors::ErrorCode;
+    use std::fs;
+    use std::path::PathBuf;
+
+    const KEY: [u8; KEY_LEN] = [1; KEY_LEN];
+    const KID: [u8; KID_LEN] = [2; KID_LEN];
+    const IV: [u8; CENC_IV_LEN] = [3; CENC_IV_LEN];
+    const VIDEO: u32 = 1;
+    const AUDIO: u32 = 2;
+
+    struct TempFile(PathBuf);
+
+    impl TempFile {
+        fn new(name: &str, content: &[u8]) -> Self {
+            let path = std::env::temp_dir().join(format!("zippy-cenc-{}-{}", std::process::id(), name));
+            fs::write(&path, content).unwrap();
+            TempFile(path)
+        }
+
+        fn target(&self) -> Target {
+            Target::from(&*self.0.to_string_lossy())
+        }
+    }
+
+    impl Drop for TempFile {
+        fn drop(&mut self) {
+            let _ = fs::remove_file(&self.0);
+        }
+    }
+
+    fn u32s(values: &[u32]) -> Vec<u8> {
+        values.iter().flat_map(|value| value.to_be_bytes()).collect()
+    }
+
+    fn trak(track_id: u32, handler: &[u8; 4], entry: &[u8]) -> Vec<u8> {
+        let tkhd = make_full_box(b"tkhd", 0, 3, &[&[0u8; 8][..], &track_id.to_be_bytes(), &[0u8; 68]].concat());
+        let hdlr = make_full_box(b"hdlr", 0, 0, &[&[0u8; 4][..], handler, &[0u8; 13]].concat());
+        let stsd = make_full_box(b"stsd", 0, 0, &[&1u32.to_be_bytes()[..], entry].concat());
+        let minf = make_box(b"minf", &make_box(b"stbl", &stsd));
+        make_box(b"trak", &[tkhd, make_box(b"mdia", &[hdlr, minf].concat())].concat())
+    }
+
+    /// ftyp 和 moov：H.264 视频轨道（4 字节 NAL 长度）和 AAC 音频轨道
+    fn init_segment() -> Vec<u8> {
+        let avc1 = make_box(b"avc1", &[vec![0u8; VISUAL_SAMPLE_ENTRY_LEN], make_box(b"avcC", &[1, 0x64, 0, 0x1f, 0xff, 0xe0])].concat());
+        let mp4a = make_box(b"mp4a", &[0u8; 28]);
+        let mvex = make_box(b"mvex", &[make_full_box(b"trex", 0, 0, &u32s(&[VIDEO, 1, 0, 0, 0])), make_full_box(b"trex", 0, 0, &u32s(&[AUDIO, 1, 0, 0, 0]))].concat());
+        let moov = make_box(b"moov", &[trak(VIDEO, b"vide", &avc1), trak(AUDIO, b"soun", &mp4a), mvex].concat());
+        [make_box(b"ftyp", b"isom\0\0\0\x01isomiso6"), moov].concat()
+    }
+
+    /// 视频样本：SPS（非 VCL）和一个 IDR 单元（1 字节 NAL 头和 40 字节数据）
+    fn video_sample() -> Vec<u8> {
+        let mut sample = [&4u32.to_be_bytes()[..], &[0x67, 0x64, 0x00, 0x1f]].concat();
+        sample.extend_from_slice(&41u32.to_be_bytes());
+        sample.push(0x65);
+        sample.extend((0..40).map(|index| index as u8));
+        sample
+    }
+
+    fn audio_sample() -> Vec<u8> {
+        vec![0xaa; 32]
+    }
+
+    /// moof 和 mdat：每个轨道一个 traf，trun 给出样本大小，数据偏移相对于 moof
+    fn fragment(tracks: &[(u32, Vec<Vec<u8>>)]) -> Vec<u8> {
+        let moof = |data_offset: usize| {
+            let mut content = make_full_box(b"mfhd", 0, 0, &1u32.to_be_bytes());
+            let mut offset = data_offset;
+            for (track_id, samples) in tracks {
+                let tfhd = make_full_box(b"tfhd", 0, TFHD_DEFAULT_BASE_IS_MOOF, &track_id.to_be_bytes());
+                let mut trun = u32s(&[samples.len() as u32, offset as u32]);
+                trun.extend(samples.iter().flat_map(|sample| (sample.len() as u32).to_be_bytes()));
+                content.extend_from_slice(&make_box(b"traf", &[tfhd, make_full_box(b"trun", 0, TRUN_DATA_OFFSET | TRUN_SAMPLE_SIZE, &trun)].concat()));
+                offset += samples.iter().map(Vec::len).sum::<usize>();
+            }
+            make_box(b"moof", &content)
+        };
+        let moof = moof(moof(0).len() + 8);
+        let mdat: Vec<u8> = tracks.iter().flat_map(|(_, samples)| samples.concat()).collect();
+        [moof, make_box(b"mdat", &mdat)].concat()
+    }
+
+    fn sample_file() -> Vec<u8> {
+        [init_segment(), fragment(&[(VIDEO, vec![video_sample()]), (AUDIO, vec![audio_sample()])])].concat()
+    }
+
+    fn encrypt(name: &str, content: &[u8]) -> Result<(CencReport, Vec<u8>), CodedError> {
+        let input = TempFile::new(&format!("{}-in", name), content);
+        let output = TempFile::new(&format!("{}-out", name), b"");
+        let protection = Protection { key: &KEY, kid: &KID, scheme: Scheme::Cenc, iv: Some(&IV), clear_key_pssh: true };
+        let report = encrypt_file(&input.target(), &output.target(), &protection, || Ok(()), |_, _| {})?;
+        Ok((report, fs::read(&output.0).unwrap()))
+    }
+
+    fn keystream(iv: &[u8], blocks: u128) -> Vec<u8> {
+        let cipher = Aes128::new(&KEY.into());
+        let mut counter = [0u8; BLOCK_LEN];
+        counter[..CENC_IV_LEN].copy_from_slice(iv);
+        let counter = u128::from_be_bytes(counter);
+        (0..blocks)
+            .flat_map(|block| {
+                let mut block = (counter + block).to_be_bytes();
+                cipher.encrypt_block((&mut block).into());
+                block
+            })
+            .collect()
+    }
+
+    fn contains(data: &[u8], needle: &[u8]) -> bool {
+        data.windows(needle.len()).any(|window| window == needle)
+    }
+
+    #[test]
+    fn encrypts_a_fragment_with_cenc() {
+        let (report, output) = encrypt("cenc", &sample_file()).unwrap();
+        assert_eq!((report.tracks, report.fragments, report.samples), (2, 1, 2));
+
+        let boxes = parse_boxes(&output).unwrap();
+        let kinds: Vec<_> = boxes.iter().map(|child| &child.kind).collect();
+        assert_eq!(kinds, [b"ftyp", b"moov", b"moof", b"mdat"]);
+        let moov = boxes[1].data;
+        assert!(contains(moov, b"encv") && contains(moov, b"enca") && contains(moov, b"pssh") && contains(moov, b"cenc"));
+        assert!(!contains(moov, b"avc1\0\0\0\0"));
+        let moof = &boxes[2];
+        assert!(contains(moof.data, b"senc") && contains(moof.data, b"saiz") && contains(moof.data, b"saio"));
+
+        // 第一个 trun 的数据偏移更新为新的 moof 之后
+        let traf = &parse_boxes(moof.body()).unwrap()[1];
+        let traf_children = parse_boxes(traf.body()).unwrap();
+        let trun = find_child(&traf_children, b"trun").unwrap().data;
+        let data_offset = u32::from_be_bytes(trun[16..20].try_into().unwrap()) as usize;
+        assert_eq!(data_offset, moof.data.len() + 8);
+
+        // 视频：SPS 和 IDR 的 NAL 头保持明文，对齐到 16 字节的末尾 32 字节加密；音频使用下一个 IV 整个加密
+        let plain = video_sample();
+        let mdat = boxes[3].body();
+        let protected = plain.len() - 32;
+        assert_eq!(mdat[..protected], plain[..protected]);
+        let decrypted: Vec<u8> = mdat[protected..plain.len()].iter().zip(keystream(&IV, 2)).map(|(byte, key)| byte ^ key).collect();
+        assert_eq!(decrypted, plain[protected..]);
+        let mut next_iv = IV;
+        next_iv[7] += 1;
+        let decrypted: Vec<u8> = mdat[plain.len()..].iter().zip(keystream(&next_iv, 2)).map(|(byte, key)| byte ^ key).collect();
+        assert_eq!(decrypted, audio_sample());
+
+        // 再次加密被拒绝
+        assert_eq!(encrypt("twice", &output).err().unwrap().code, ErrorCode::InvalidArgument);
+    }
+
+    #[test]
+    fn truncated_files_fail_cleanly() {
+        let file = sample_file();
+        let init_len = init_segment().len();
+        for len in 0..file.len() {
+            // 只有 ftyp 和 moov 的初始化分段本身是有效的
+            if len != init_len {
+                assert!(encrypt("truncated", &file[..len]).is_err(), "length {}", len);
+            }
+        }
+    }
+
+    #[test]
+    fn rejects_malformed_boxes() {
+        let undersized = [&4u32.to_be_bytes()[..], b"free"].concat();
+        let oversized = [&100u32.to_be_bytes()[..], b"free", &[0u8; 8]].concat();
+        let short_large_size = [&1u32.to_be_bytes()[..], b"free", &[0u8; 4]].concat();
+        let undersized_large_size = [&1u32.to_be_bytes()[..], b"free", &15u64.to_be_bytes()].concat();
+        let huge_large_size = [&1u32.to_be_bytes()[..], b"free", &u64::MAX.to_be_bytes()].concat();
+        for data in [&b"\0\0\0"[..], &undersized, &oversized, &short_large_size, &undersized_large_size, &huge_large_size] {
+            assert_eq!(parse_boxes(data).err().unwrap().code, ErrorCode::BadFormat);
+        }
+        for data in [&undersized[..5], &undersized, &short_large_size, &undersized_large_size] {
+            assert_eq!(BoxHeader::read(&mut &data[..]).err().unwrap().code, ErrorCode::BadFormat);
+        }
+        let header = BoxHeader::read(&mut &oversized[..]).unwrap().unwrap();
+        assert_eq!(header.read_body(&mut &oversized[8..], 1000).err().unwrap().code, ErrorCode::Truncated);
+        assert_eq!(header.read_body(&mut &oversized[8..], 50).err().unwrap().code, ErrorCode::Unsupported);
+        let header = BoxHeader::read(&mut &huge_large_size[..]).unwrap().unwrap();
+        assert_eq!(header.read_body(&mut &[][..], MAX_METADATA_LEN).err().unwrap().code, ErrorCode::Unsupported);
+
+        // 没有逐样本字段时样本数不受盒子长度约束，必须单独限制
+        let huge_run = make_full_box(b"trun", 0, 0, &u32::MAX.to_be_bytes());
+        assert_eq!(TrackRun::parse(&huge_run[8..], 1000).err().unwrap().code, ErrorCode::BadFormat);
+        let short_run = make_full_box(b"trun", 0, TRUN_SAMPLE_SIZE, &u32s(&[3, 10]));
+        assert_eq!(TrackRun::parse(&short_run[8..], 1000).err().unwrap().code, ErrorCode::BadFormat);
+        let short_tfhd = make_full_box(b"tfhd", 0, TFHD_BASE_DATA_OFFSET, &u32s(&[1, 0]));
+        assert_eq!(TrackFragmentHeader::parse(&short_tfhd[8..]).err().unwrap().code, ErrorCode::BadFormat);
+        let short_tfra = make_box(b"tfra", &u32s(&[0, 1, 0, 5, 0]));
+        assert_eq!(update_random_access(&short_tfra, &HashMap::new()).err().unwrap().code, ErrorCode::BadFormat);
+
+        let sample = video_sample();
+        assert!(video_subsamples(&sample[..sample.len() - 1], 4, false, true).is_err());
+        assert!(video_subsamples(&sample[..10], 4, false, true).is_err());
+        assert!(video_subsamples(&[0, 0, 0xff, 0xff, 0x65], 4, false, true).is_err());
+    }
+
+    #[test]
+    fn rejects_malformed_movies() {
+        let encrypter = SampleEncrypter::new(&KEY, &KID, Scheme::Cenc, &IV);
+        let protect = |moov: Vec<u8>| protect_movie(parse_boxes(&moov).unwrap()[0].body(), &encrypter, false).err().unwrap().code;
+        let mvex = make_box(b"mvex", &make_full_box(b"trex", 0, 0, &u32s(&[VIDEO, 1, 0, 0, 0])));
+        let avc1 = |config: &[u8]| make_box(b"avc1", &[vec![0u8; VISUAL_SAMPLE_ENTRY_LEN], config.to_vec()].concat());
+
+        assert_eq!(protect(make_box(b"moov", &trak(VIDEO, b"vide", &avc1(&[])))), ErrorCode::Unsupported);
+        assert_eq!(protect(make_box(b"moov", &[make_box(b"trak", &[]), mvex.clone()].concat())), ErrorCode::BadFormat);
+        assert_eq!(protect(make_box(b"moov", &[trak(VIDEO, b"vide", &avc1(&[])), mvex.clone()].concat())), ErrorCode::BadFormat);
+        assert_eq!(protect(make_box(b"moov", &[trak(VIDEO, b"vide", &avc1(&make_box(b"avcC", &[1]))), mvex.clone()].concat())), ErrorCode::BadFormat);
+        assert_eq!(protect(make_box(b"moov", &[trak(VIDEO, b"vide", &make_box(b"avc1", &[0; 20])), mvex.clone()].concat())), ErrorCode::BadFormat);
+        assert_eq!(protect(make_box(b"moov", &[trak(VIDEO, b"vide", &make_box(b"vp09", &[])), mvex.clone()].concat())), ErrorCode::Unsupported);
+        assert_eq!(protect(make_box(b"moov", &[trak(VIDEO, b"text", &[]), mvex.clone()].concat())), ErrorCode::Unsupported);
+        let short_trex = make_box(b"mvex", &make_full_box(b"trex", 0, 0, &u32s(&[VIDEO])));
+        assert_eq!(protect(make_box(b"moov", &[trak(VIDEO, b"vide", &avc1(&[])), short_trex].concat())), ErrorCode::BadFormat);
+    }
+
+    #[test]
+    fn rejects_malformed_files() {
+        let init = init_segment();
+        let encrypt_error = |name: &str, content: &[u8]| encrypt(name, content).err().unwrap().code;
+
+        assert_eq!(encrypt_error("empty", b""), ErrorCode::BadFormat);
+        assert_eq!(encrypt_error("not-mp4", &make_box(b"free", b"hello")), ErrorCode::BadHeader);
+        let fragment = fragment(&[(AUDIO, vec![audio_sample()])]);
+        assert_eq!(encrypt_error("no-moov", &[make_box(b"ftyp", b"isom"), fragment.clone()].concat()), ErrorCode::BadFormat);
+        assert_eq!(encrypt_error("two-moov", &[init.clone(), init[init.len() - parse_boxes(&init).unwrap()[1].data.len()..].to_vec()].concat()), ErrorCode::BadFormat);
+        assert_eq!(encrypt_error("undersized", &[init.clone(), [0, 0, 0, 4].to_vec(), b"free".to_vec()].concat()), ErrorCode::BadFormat);
+        assert_eq!(encrypt_error("moof-without-mdat", &[init.clone(), fragment[..fragment.len() - 40].to_vec(), make_box(b"free", &[])].concat()), ErrorCode::Unsupported);
+
+        // trun 的样本超出 mdat
+        let mut beyond = fragment.clone();
+        let size_pos = beyond.len() - 40 - 4;
+        beyond[size_pos..size_pos + 4].copy_from_slice(&33u32.to_be_bytes());
+        assert_eq!(encrypt_error("beyond-mdat", &[init.clone(), beyond].concat()), ErrorCode::Unsupported);
+
+        // trun 声称有 2^32 - 1 个样本
+        let mut huge_count = fragment.clone();
+        let count_pos = huge_count.len() - 40 - 12;
+        huge_count[count_pos..count_pos + 4].copy_from_slice(&u32::MAX.to_be_bytes());
+        assert_eq!(encrypt_error("huge-count", &[init.clone(), huge_count].concat()), ErrorCode::BadFormat);
+
+        // mfra 中的 tfra 被截断
+        let tfra = make_box(b"tfra", &u32s(&[0, AUDIO, 0, 5]));
+        assert_eq!(encrypt_error("short-tfra", &[init.clone(), fragment.clone(), make_box(b"mfra", &tfra)].concat()), ErrorCode::BadFormat);
+    }
+
+    #[test]
+    fn large_size_moof_header_may_shrink() {
+        // 未加密轨道的 moof 使用 64 位长度字段，输出改用 32 位长度字段后比输入短 8 字节
+        let fragment = fragment(&[(3, vec![audio_sample()])]);
+        let moof_len = u32::from_be_bytes(fragment[..4].try_into().unwrap()) as usize;
+        let mut large = [&1u32.to_be_bytes()[..], b"moof", &(moof_len as u64 + 8).to_be_bytes()].concat();
+        large.extend_from_slice(&fragment[8..]);
+        let input = [init_segment(), large].concat();
+        let (report, output) = encrypt("large-size", &input).unwrap();
+        assert_eq!(report.samples, 0);
+        let boxes = parse_boxes(&output).unwrap();
+        assert_eq!(boxes[2].data.len(), moof_len);
+        assert_eq!(boxes[3].body(), audio_sample());
+    }
+}
@@ -0,0 +1,383 @@
+use std::fs::{self, File, OpenOptions};
+use std::io::{BufReader, BufWriter, Read, Seek, SeekFrom, Write};
+use std::time::{Duration, Instant, UNIX_EPOCH};
+
+use crate::chunked::{read_frame_entries, ChunkEncoder, CompressionStats, EncoderState, FrameEntry};
+use crate::config::{self, LogLevel};
+use crate::crypto::{open_field, seal_field, CryptoAlgorithm, CHUNK_CHECKSUM_LEN};
+use crate::errors::{open_error, read_error, write_error, CodedError};
+use crate::format::{self, ContainerHeader};
+use crate::uring::RingFile;
+
+/// 检查点用途，同时作为加密检查点内容的子密钥和关联数据
+const CHECKPOINT_PURPOSE: &str = "checkpoint";
+/// 检查点格式版本
+const CHECKPOINT_VERSION: u8 = 1;
+/// 定长部分的长度：版本(1) + 输入文件大小、修改时间、分片大小(各 8) + 分片数(4) + 位置(8) + 压缩标志(1) + 压缩后大小(8) + 直接保存的分片数(4) + 稀疏分片数(4)
+const CHECKPOINT_FIXED_LEN: usize = 1 + 8 * 3 + 4 + 8 + 1 + 8 + 4 + 4;
+/// 两次记录检查点之间的最短间隔；每次记录都要把输出文件同步到磁盘
+const CHECKPOINT_INTERVAL: Duration = Duration::from_secs(5);
+
+/// 检查点文件路径：`<输出路径>.checkpoint`
+pub fn checkpoint_path(output_path: &str) -> String {
+    format!("{}.checkpoint", output_path)
+}
+
+/// 删除检查点文件，文件不存在时忽略
+pub fn remove(output_path: &str) {
+    let _ = fs::remove_file(checkpoint_path(output_path));
+}
+
+/// 输入文件的标识，只有大小、修改时间和分片大小都相同时才继续上次的输出
+#[derive(Clone, Copy, PartialEq)]
+pub struct InputIdentity {
+    pub size: u64,
+    /// 修改时间（Unix 纳秒），无法获取时为 0
+    pub modified: u64,
+    pub chunk_size: u64,
+}
+
+impl InputIdentity {
+    pub fn of(input: &File, chunk_size: usize) -> Result<Self, CodedError> {
+        let metadata = input
+            .metadata()
+            .map_err(|e| read_error("reading input file metadata", e))?;
+        let modified = metadata
+            .modified()
+            .ok()
+            .and_then(|modified| modified.duration_since(UNIX_EPOCH).ok())
+            .map_or(0, |elapsed| elapsed.as_nanos() as u64);
+        Ok(InputIdentity { size: metadata.len(), modified, chunk_size: chunk_size as u64 })
+    }
+}
+
+/// 检查点：输入文件的标识以及输出文件中已完整写出并同步到磁盘的分片
+///
+/// 以文件密钥派生的子密钥加密并认证后保存，被篡改或使用其它密钥时无法读取。
+struct Checkpoint {
+    input: InputIdentity,
+    state: EncoderState,
+}
+
+impl Checkpoint {
+    fn to_bytes(&self) -> Vec<u8> {
+        let state = &self.state;
+        let mut bytes = Vec::with_capacity(CHECKPOINT_FIXED_LEN + state.chunk_checksums.len() * CHUNK_CHECKSUM_LEN);
+        bytes.push(CHECKPOINT_VERSION);
+        bytes.extend_from_slice(&self.input.size.to_le_bytes());
+        bytes.extend_from_slice(&self.input.modified.to_le_bytes());
+        bytes.extend_from_slice(&self.input.chunk_size.to_le_bytes());
+        bytes.extend_from_slice(&state.chunks.to_le_bytes());
+        bytes.extend_from_slice(&state.position.to_le_bytes());
+        let stats = state.compression_stats.unwrap_or(CompressionStats { compressed_size: 0, stored_chunks: 0 });
+        bytes.push(u8::from(state.compression_stats.is_some()));
+        bytes.extend_from_slice(&stats.compressed_size.to_le_bytes());
+        bytes.extend_from_slice(&stats.stored_chunks.to_le_bytes());
+        bytes.extend_from_slice(&state.sparse_chunks.to_le_bytes());
+        for checksum in &state.chunk_checksums {
+            bytes.extend_from_slice(checksum);
+        }
+        bytes
+    }
+
+    fn from_bytes(bytes: &[u8]) -> Result<Self, CodedError> {
+        if bytes.len() < CHECKPOINT_FIXED_LEN || bytes[0] != CHECKPOINT_VERSION {
+            return Err(CodedError::unsupported("Unsupported checkpoint"));
+        }
+        let u64_at = |offset: usize| u64::from_le_bytes(bytes[offset..offset + 8].try_into().unwrap());
+        let u32_at = |offset: usize| u32::from_le_bytes(bytes[offset..offset + 4].try_into().unwrap());
+        let chunks = u32_at(25);
+        let checksums = &bytes[CHECKPOINT_FIXED_LEN..];
+        if checksums.len() != chunks as usize * CHUNK_CHECKSUM_LEN {
+            return Err(CodedError::bad_format("Invalid checkpoint"));
+        }
+        let compression_stats = (bytes[37] != 0).then(|| CompressionStats {
+            compressed_size: u64_at(38),
+            stored_chunks: u32_at(46),
+        });
+        Ok(Checkpoint {
+            input: InputIdentity { size: u64_at(1), modified: u64_at(9), chunk_size: u64_at(17) },
+            state: EncoderState {
+                chunks,
+                position: u64_at(29),
+                chunk_checksums: checksums
+                    .chunks_exact(CHUNK_CHECKSUM_LEN)
+                    .map(|checksum| checksum.try_into().unwrap())
+                    .collect(),
+                compression_stats,
+                sparse_chunks: u32_at(50),
+            },
+        })
+    }
+
+    /// 读取检查点，文件不存在时返回 None
+    fn load(output_path: &str, key: &[u8]) -> Result<Option<Self>, CodedError> {
+        let sealed = match fs::read(checkpoint_path(output_path)) {
+            Ok(sealed) => sealed,
+            Err(err) if err.kind() == std::io::ErrorKind::NotFound => return Ok(None),
+            Err(err) => return Err(read_error("reading checkpoint", err)),
+        };
+        let bytes = open_field(key, CHECKPOINT_PURPOSE, &sealed)
+            .map_err(|_| CodedError::auth_failed("Failed to authenticate checkpoint - wrong key or corrupted checkpoint"))?;
+        Checkpoint::from_bytes(&bytes).map(Some)
+    }
+
+    /// 先写入临时文件再替换，中途崩溃时原来的检查点仍然完整
+    fn save(&self, output_path: &str, key: &[u8]) -> Result<(), CodedError> {
+        let sealed = seal_field(key, CHECKPOINT_PURPOSE, &self.to_bytes())?;
+        let path = checkpoint_path(output_path);
+        let temp_path = format!("{}.tmp", path);
+        File::create(&temp_path)
+            .and_then(|mut file| file.write_all(&sealed).and_then(|_| file.sync_all()))
+            .and_then(|_| fs::rename(&temp_path, &path))
+            .map_err(|e| {
+                let _ = fs::remove_file(&temp_path);
+                write_error("writing checkpoint", e)
+            })
+    }
+}
+
+/// 加密过程中定期记录检查点：先把已写出的分片同步到磁盘，再记录它们的状态
+pub struct CheckpointWriter {
+    output_path: String,
+    key: Vec<u8>,
+    input: InputIdentity,
+    /// 输出文件的另一个句柄，用于同步到磁盘
+    output: File,
+    last_saved: Instant,
+}
+
+impl CheckpointWriter {
+    pub fn new(output_path: &str, key: &[u8], input: InputIdentity, output: &File) -> Result<Self, CodedError> {
+        Ok(CheckpointWriter {
+            output_path: output_path.to_string(),
+            key: key.to_vec(),
+            input,
+            output: output.try_clone().map_err(|e| open_error("opening output file", e))?,
+            last_saved: Instant::now(),
+        })
+    }
+
+    /// 距上次记录超过间隔时记录检查点
+    pub fn save_if_due<W: Write>(&mut self, encoder: &mut ChunkEncoder<W>) -> Result<(), CodedError> {
+        match self.last_saved.elapsed() >= CHECKPOINT_INTERVAL {
+            true => self.save(encoder),
+            false => Ok(()),
+        }
+    }
+
+    /// 立即记录检查点
+    pub fn save<W: Write>(&mut self, encoder: &mut ChunkEncoder<W>) -> Result<(), CodedError> {
+        let state = encoder.state()?;
+        self.output
+            .sync_data()
+            .map_err(|e| write_error("flushing output file", e))?;
+        Checkpoint { input: self.input, state }.save(&self.output_path, &self.key)?;
+        self.last_saved = Instant::now();
+        config::log(LogLevel::Debug, format!("checkpoint for {} at chunk {}", self.output_path, encoder.chunks_written()));
+        Ok(())
+    }
+}
+
+/// 按检查点继续写入的输出文件
+pub struct ResumedOutput {
+    /// 位于最后一个完整分片帧之后的分片写入器
+    pub encoder: ChunkEncoder<BufWriter<RingFile>>,
+    /// 输出文件的另一个句柄，用于记录之后的检查点
+    pub output: File,
+    /// 已加密的明文字节数，输入 reader 已位于这里
+    pub offset: u64,
+}
+
+/// 上次的输出无法继续时记录原因，从头开始加密
+fn start_over(output_path: &str, reason: &str) -> Result<Option<ResumedOutput>, CodedError> {
+    config::log(LogLevel::Warn, format!("cannot resume {}: {} - starting over", output_path, reason));
+    Ok(None)
+}
+
+/// 检查点记录的分片之后可能还有部分写出的数据，打开输出文件并截断到检查点的位置
+///
+/// 输出文件的文件头必须能用 key 认证，且与本次的选项（expected）一致；沿用输出文件中原来的文件头。
+fn open_partial(output_path: &str, checkpoint: &Checkpoint, expected: &ContainerHeader, algorithm: &CryptoAlgorithm, key: &[u8]) -> Result<(File, ContainerHeader, Vec<FrameEntry>), CodedError> {
+    let file = OpenOptions::new()
+        .read(true)
+        .write(true)
+        .open(output_path)
+        .map_err(|e| open_error("opening output file", e))?;
+    let mut reader = BufReader::new(&file);
+    let header = format::read_chunked_header(&mut reader)?;
+    let container = match &header.container {
+        Some(container) => container,
+        None => return Err(CodedError::bad_header("output file has no container header")),
+    };
+    container.verify(key)?;
+    let matches = container.algorithm == *algorithm
+        && container.original_size == expected.original_size
+        && container.chunk_size == expected.chunk_size
+        && container.compression == expected.compression
+        && container.hash_algorithm == expected.hash_algorithm
+        && container.sparse == expected.sparse
+        && container.appendable == expected.appendable
+        && container.has_parity == expected.has_parity;
+    if !matches || !container.has_footer {
+        return Err(CodedError::invalid_argument("options differ from the interrupted run"));
+    }
+    reader
+        .seek(SeekFrom::Start(header.header_len))
+        .map_err(|e| read_error("seeking in output file", e))?;
+    let (frames, end) = read_frame_entries(&mut reader, &header, algorithm, checkpoint.state.chunks as u64)?;
+    if end != checkpoint.state.position {
+        return Err(CodedError::bad_format("output file does not match the checkpoint"));
+    }
+    drop(reader);
+    let container = header.container.unwrap();
+    Ok((file, container, frames))
+}
+
+/// 按检查点继续上次中断的加密：校验输出文件中已写出的分片帧，重新读入对应的明文（核对分片校验值并计入明文摘要），
+/// 截掉检查点之后不完整的数据
+///
+/// 没有检查点，或检查点与输入文件、选项、输出文件不符时返回 None，调用方从头开始加密；此时 input 已回到文件开头。
+/// check 在每个分片之前调用，返回错误时（超时或取消）中止。
+pub fn resume<R: Read + Seek>(output_path: &str, input: &mut R, identity: InputIdentity, expected: &ContainerHeader, algorithm: &CryptoAlgorithm, key: &[u8], check: impl Fn() -> Result<(), CodedError>) -> Result<Option<ResumedOutput>, CodedError> {
+    let checkpoint = match Checkpoint::load(output_path, key) {
+        Ok(Some(checkpoint)) => checkpoint,
+        Ok(None) => return Ok(None),
+        Err(err) => return start_over(output_path, &err.message),
+    };
+    if checkpoint.input != identity {
+        return start_over(output_path, "input file changed");
+    }
+    let (file, container, frames) = match open_partial(output_path, &checkpoint, expected, algorithm, key) {
+        Ok(partial) => partial,
+        Err(err) => return start_over(output_path, &err.message),
+    };
+    // 截掉检查点之后可能不完整的分片帧；之后无法继续时会从头开始，截断不影响结果
+    let chunks = checkpoint.state.chunks as usize;
+    let position = checkpoint.state.position;
+    let mut file = file;
+    file.set_len(position)
+        .and_then(|_| file.seek(SeekFrom::Start(position)))
+        .map_err(|e| write_error("truncating output file", e))?;
+    let output = file.try_clone().map_err(|e| open_error("opening output file", e))?;
+    let writer = BufWriter::with_capacity(identity.chunk_size as usize, RingFile::new(file));
+    let mut encoder = match ChunkEncoder::resume(writer, algorithm.clone(), key, &container, &frames, checkpoint.state) {
+        Ok(encoder) => encoder,
+        Err(err) => return start_over(output_path, &err.message),
+    };
+
+    // 明文摘要的中间状态无法保存，重新读入已加密部分的明文；读取比加密快得多，同时确认输入文件的内容没有变化
+    let chunk_size = identity.chunk_size as usize;
+    let mut buffer = vec![0u8; chunk_size];
+    let mut offset = 0u64;
+    for index in 0..chunks {
+        check()?;
+        let len = (identity.size - offset).min(chunk_size as u64) as usize;
+        input
+            .read_exact(&mut buffer[..len])
+            .map_err(|e| read_error("reading input file", e))?;
+        if !encoder.replay_chunk(index, &buffer[..len]) {
+            // 前面的分片已经读过，从头加密前回到输入文件开头
+            input
+                .seek(SeekFrom::Start(0))
+                .map_err(|e| read_error("seeking in input file", e))?;
+            return start_over(output_path, &format!("input file changed in chunk {}", index + 1));
+        }
+        offset += len as u64;
+    }
+    config::log(LogLevel::Info, format!("resuming {} at chunk {}", output_path, chunks + 1));
+    Ok(Some(ResumedOutput { encoder, output, offset }))
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::Cursor;
+
+    use super::*;
+    use crate::chunked::ChunkDecoder;
+    use crate::format::{read_chunked_header, Layout};
+
+    const KEY: [u8; 32] = [5; 32];
+    const CHUNK_SIZE: usize = 1000;
+
+    fn temp_path(name: &str) -> String {
+        std::env::temp_dir()
+            .join(format!("zippy-checkpoint-{}-{}", std::process::id(), name))
+            .to_string_lossy()
+            .into_owned()
+    }
+
+    fn identity(data: &[u8]) -> InputIdentity {
+        InputIdentity { size: data.len() as u64, modified: 1, chunk_size: CHUNK_SIZE as u64 }
+    }
+
+    /// 加密前两个分片并记录检查点，之后写出半个分片后“中断”
+    fn interrupted_output(path: &str, container: &ContainerHeader, data: &[u8]) {
+        let file = File::create(path).unwrap();
+        let mut checkpoint = CheckpointWriter::new(path, &KEY, identity(data), &file).unwrap();
+        let mut encoder = ChunkEncoder::new(file, CryptoAlgorithm::Aes, &KEY, Some(container), data.len() as u64, CHUNK_SIZE).unwrap();
+        for chunk in data.chunks(CHUNK_SIZE).take(2) {
+            encoder.write_chunk(chunk).unwrap();
+        }
+        checkpoint.save(&mut encoder).unwrap();
+        encoder.write_chunk(&data[2 * CHUNK_SIZE..3 * CHUNK_SIZE]).unwrap();
+        encoder.get_mut().set_len(fs::metadata(path).unwrap().len() - 500).unwrap();
+    }
+
+    /// 与加密时相同：从 offset 开始读入其余的明文写完输出文件，再解密整个文件
+    fn finish_and_decrypt<W: Write>(mut encoder: ChunkEncoder<W>, input: &mut Cursor<Vec<u8>>, path: &str) -> Vec<u8> {
+        let mut rest = Vec::new();
+        input.read_to_end(&mut rest).unwrap();
+        encoder.write(&rest).unwrap();
+        encoder.finish().unwrap().flush().unwrap();
+
+        let mut reader = BufReader::new(File::open(path).unwrap());
+        let header = read_chunked_header(&mut reader).unwrap();
+        header.container.as_ref().unwrap().verify(&KEY).unwrap();
+        let mut decoder = ChunkDecoder::new(reader, header, CryptoAlgorithm::Aes, &KEY).unwrap();
+        let mut plaintext = Vec::new();
+        while let Some(chunk) = decoder.next_chunk().unwrap() {
+            plaintext.extend_from_slice(&chunk);
+        }
+        decoder.finish().unwrap();
+        plaintext
+    }
+
+    #[test]
+    fn resumes_after_interruption() {
+        let path = temp_path("resume.enc");
+        let data: Vec<u8> = (0..3500u32).map(|i| (i % 251) as u8).collect();
+        let container = ContainerHeader::for_new_file(Layout::Chunked, CryptoAlgorithm::Aes, data.len() as u64, CHUNK_SIZE as u64);
+        interrupted_output(&path, &container, &data);
+
+        let mut input = Cursor::new(data.clone());
+        let resumed = resume(&path, &mut input, identity(&data), &container, &CryptoAlgorithm::Aes, &KEY, || Ok(())).unwrap().unwrap();
+        assert_eq!(resumed.offset, 2 * CHUNK_SIZE as u64);
+        assert_eq!(input.position(), resumed.offset);
+        assert_eq!(finish_and_decrypt(resumed.encoder, &mut input, &path), data);
+        remove(&path);
+        let _ = fs::remove_file(&path);
+    }
+
+    #[test]
+    fn starts_over_from_the_beginning_when_a_chunk_changed() {
+        let path = temp_path("changed.enc");
+        let data: Vec<u8> = (0..3500u32).map(|i| (i % 251) as u8).collect();
+        let container = ContainerHeader::for_new_file(Layout::Chunked, CryptoAlgorithm::Aes, data.len() as u64, CHUNK_SIZE as u64);
+        interrupted_output(&path, &container, &data);
+
+        // 修改第二个分片，大小和修改时间不变：重放到第二个分片时才发现，输入此时已读过第一个分片
+        let mut changed = data.clone();
+        changed[CHUNK_SIZE + 10] ^= 0xff;
+        let mut input = Cursor::new(changed.clone());
+        let resumed = resume(&path, &mut input, identity(&changed), &container, &CryptoAlgorithm::Aes, &KEY, || Ok(())).unwrap();
+        assert!(resumed.is_none());
+        assert_eq!(input.position(), 0);
+
+        // 与调用方相同，从头加密
+        let encoder = ChunkEncoder::new(File::create(&path).unwrap(), CryptoAlgorithm::Aes, &KEY, Some(&container), changed.len() as u64, CHUNK_SIZE).unwrap();
+        assert_eq!(finish_and_decrypt(encoder, &mut input, &path), changed);
+        remove(&path);
+        let _ = fs::remove_file(&path);
+    }
+}
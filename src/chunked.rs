@@ -0,0 +1,978 @@
+use std::io::{BufRead, BufReader, Read, Seek, SeekFrom, Write};
+
+use crate::compression::Compression;
+use crate::crypto::{
+    chunk_tag_len, decrypt_into, encrypt_into, seal_field, ChunkAuthenticator, ChunkChecksum, CryptoAlgorithm, HashAlgorithm, PlaintextHasher,
+    CHUNK_CHECKSUM_LEN, CHUNK_MAC_LEN, HEADER_MAC_LEN,
+};
+use crate::errors::{CodedError, ErrorCode};
+use crate::format::{ChunkedFooter, ChunkedHeader, ContainerHeader, FrameFormat, ERR_TRUNCATED, FIELD_DIGEST, MAX_CHUNK_OVERHEAD};
+
+/// 分片压缩统计
+#[derive(Clone, Copy)]
+pub struct CompressionStats {
+    /// 压缩后的总字节数（含直接保存的分片）
+    pub compressed_size: u64,
+    /// 因无法压缩而直接保存的分片数
+    pub stored_chunks: u32,
+}
+
+/// 分片文件写入器：写入文件头后逐个加密写出分片，finish 时写入结束帧、文件 MAC 和结尾字段
+pub struct ChunkEncoder<W: Write> {
+    writer: W,
+    algorithm: CryptoAlgorithm,
+    key: Vec<u8>,
+    chunk_size: usize,
+    frame_format: FrameFormat,
+    authenticator: Option<ChunkAuthenticator>,
+    chunk_offsets: Option<Vec<u64>>,
+    /// 每个分片明文的校验值，与分片索引一起写入结尾字段
+    chunk_checksums: Vec<[u8; CHUNK_CHECKSUM_LEN]>,
+    checksum: Option<ChunkChecksum>,
+    plaintext_hasher: Option<PlaintextHasher>,
+    plaintext_size: u64,
+    /// 分片加密前的压缩算法，压缩后的累计字节数，以及因无法压缩而直接保存的分片数
+    compression: Option<Compression>,
+    compressed_size: u64,
+    stored_chunks: u32,
+    /// 全零的分片是否以空分片帧保存，以及这样保存的分片数
+    sparse: bool,
+    sparse_chunks: u32,
+    /// 下一个分片帧在文件中的位置
+    position: u64,
+    chunks_written: u32,
+    /// write() 缓存的不足一个分片的明文
+    pending: Vec<u8>,
+    /// 加密后的分片帧，每个分片复用同一个缓冲区
+    frame: Vec<u8>,
+}
+
+impl<W: Write> ChunkEncoder<W> {
+    /// 写入文件头，container 为 None 时写入 v1 文本文件头
+    pub fn new(mut writer: W, algorithm: CryptoAlgorithm, key: &[u8], container: Option<&ContainerHeader>, original_size: u64, chunk_size: usize) -> Result<Self, CodedError> {
+        if chunk_size == 0 {
+            return Err(CodedError::invalid_argument("Chunk size must be greater than 0"));
+        }
+        let header = match container {
+            Some(container) => container.to_bytes(key)?,
+            None => format!("CHUNKS:{}:{}:", original_size, chunk_size).into_bytes(),
+        };
+        writer
+            .write_all(&header)
+            .map_err(|e| CodedError::io_write(format!("Failed to write file header: {}", e)))?;
+
+        // v2 文件的分片认证从文件头认证标签开始串联，并记录每个分片帧的位置用于写入分片索引
+        let frame_format = match container {
+            Some(container) if container.binary_frames => FrameFormat::Binary,
+            _ => FrameFormat::Text,
+        };
+        let authenticator = match container {
+            Some(container) => {
+                let total_chunks = container.chunk_sequence_total(original_size.div_ceil(chunk_size as u64));
+                Some(ChunkAuthenticator::new(algorithm.clone(), key, &header[header.len() - HEADER_MAC_LEN..], total_chunks)?)
+            }
+            None => None,
+        };
+        let chunk_offsets = container.is_some_and(|container| container.has_footer).then(Vec::new);
+        let plaintext_hasher = container.and_then(new_plaintext_hasher);
+
+        Ok(ChunkEncoder {
+            writer,
+            algorithm,
+            key: key.to_vec(),
+            chunk_size,
+            frame_format,
+            authenticator,
+            checksum: chunk_offsets.is_some().then(|| ChunkChecksum::new(key)),
+            chunk_offsets,
+            chunk_checksums: Vec::new(),
+            plaintext_hasher,
+            plaintext_size: 0,
+            compression: container.and_then(|container| container.compression),
+            compressed_size: 0,
+            stored_chunks: 0,
+            sparse: container.is_some_and(|container| container.sparse),
+            sparse_chunks: 0,
+            position: header.len() as u64,
+            chunks_written: 0,
+            pending: Vec::new(),
+            frame: Vec::new(),
+        })
+    }
+
+    /// 继续写入可追加的分片文件：retained 为保留的完整分片帧，footer 为这些分片的索引和校验值，
+    /// header_tag 为更新后文件头的认证标签，writer 位于 position（最后一个保留的分片帧之后）
+    #[allow(clippy::too_many_arguments)]
+    pub fn append(writer: W, algorithm: CryptoAlgorithm, key: &[u8], container: &ContainerHeader, header_tag: &[u8], retained: &[FrameEntry], footer: ChunkedFooter, position: u64) -> Result<Self, CodedError> {
+        if !container.appendable {
+            return Err(CodedError::unsupported("File is not appendable"));
+        }
+        let mut authenticator = ChunkAuthenticator::new(algorithm.clone(), key, header_tag, container.chunk_sequence_total(0))?;
+        for frame in retained {
+            authenticator.add_chunk(frame.len as u64, &frame.tag);
+        }
+        let chunk_size = container.chunk_size as usize;
+
+        Ok(ChunkEncoder {
+            writer,
+            algorithm,
+            key: key.to_vec(),
+            chunk_size,
+            frame_format: if container.binary_frames { FrameFormat::Binary } else { FrameFormat::Text },
+            authenticator: Some(authenticator),
+            checksum: Some(ChunkChecksum::new(key)),
+            chunk_offsets: Some(footer.chunk_offsets),
+            chunk_checksums: footer.chunk_checksums,
+            plaintext_hasher: None,
+            plaintext_size: retained.len() as u64 * chunk_size as u64,
+            compression: container.compression,
+            compressed_size: 0,
+            stored_chunks: 0,
+            sparse: container.sparse,
+            sparse_chunks: 0,
+            position,
+            chunks_written: retained.len() as u32,
+            pending: Vec::new(),
+            frame: Vec::new(),
+        })
+    }
+
+    /// 继续写入中断的分片文件：frames 为已完整写出的分片帧，state 为中断前记录的状态，writer 位于 state.position
+    ///
+    /// 之前分片的明文摘要无法保存，之后必须按顺序用 replay_chunk 重新读入这些分片的明文。
+    pub fn resume(writer: W, algorithm: CryptoAlgorithm, key: &[u8], container: &ContainerHeader, frames: &[FrameEntry], state: EncoderState) -> Result<Self, CodedError> {
+        if !container.has_footer || frames.len() != state.chunk_checksums.len() || frames.len() != state.chunks as usize {
+            return Err(CodedError::bad_format("Checkpoint does not match the written chunks"));
+        }
+        let header_tag = container.header_mac.as_deref().unwrap_or_default();
+        let total_chunks = container.chunk_sequence_total(container.original_size.div_ceil(container.chunk_size));
+        let mut authenticator = ChunkAuthenticator::new(algorithm.clone(), key, header_tag, total_chunks)?;
+        for frame in frames {
+            authenticator.add_chunk(frame.len as u64, &frame.tag);
+        }
+        let compression_stats = state.compression_stats.unwrap_or(CompressionStats { compressed_size: 0, stored_chunks: 0 });
+
+        Ok(ChunkEncoder {
+            writer,
+            algorithm,
+            key: key.to_vec(),
+            chunk_size: container.chunk_size as usize,
+            frame_format: if container.binary_frames { FrameFormat::Binary } else { FrameFormat::Text },
+            authenticator: Some(authenticator),
+            checksum: Some(ChunkChecksum::new(key)),
+            chunk_offsets: Some(frames.iter().map(|frame| frame.offset).collect()),
+            chunk_checksums: state.chunk_checksums,
+            plaintext_hasher: new_plaintext_hasher(container),
+            plaintext_size: 0,
+            compression: container.compression,
+            compressed_size: compression_stats.compressed_size,
+            stored_chunks: compression_stats.stored_chunks,
+            sparse: container.sparse,
+            sparse_chunks: state.sparse_chunks,
+            position: state.position,
+            chunks_written: state.chunks,
+            pending: Vec::new(),
+            frame: Vec::new(),
+        })
+    }
+
+    /// 按顺序重新读入 resume 之前已写出的第 index 个分片的明文，计入明文摘要；明文与记录的校验值不符时返回 false
+    pub fn replay_chunk(&mut self, index: usize, data: &[u8]) -> bool {
+        let matches = match (&self.checksum, self.chunk_checksums.get(index)) {
+            (Some(checksum), Some(expected)) => checksum.compute(data) == *expected,
+            _ => false,
+        };
+        if matches {
+            if let Some(hasher) = &mut self.plaintext_hasher {
+                hasher.update(data);
+            }
+            self.plaintext_size += data.len() as u64;
+        }
+        matches
+    }
+
+    /// 把已写出的分片全部写入底层 writer，返回继续写入所需的状态；不能在 write() 缓存了明文时调用
+    pub fn state(&mut self) -> Result<EncoderState, CodedError> {
+        if !self.pending.is_empty() {
+            return Err(CodedError::unknown("Cannot record the state of a partially written chunk"));
+        }
+        self.writer
+            .flush()
+            .map_err(|e| CodedError::io_write(format!("Failed to flush output file: {}", e)))?;
+        Ok(EncoderState {
+            chunks: self.chunks_written,
+            position: self.position,
+            chunk_checksums: self.chunk_checksums.clone(),
+            compression_stats: self.compression_stats(),
+            sparse_chunks: self.sparse_chunks,
+        })
+    }
+
+    /// 加密并写出一个完整的分片，不能与 write() 混用
+    pub fn write_chunk(&mut self, data: &[u8]) -> Result<(), CodedError> {
+        if let Some(hasher) = &mut self.plaintext_hasher {
+            hasher.update(data);
+        }
+        if let Some(checksum) = &self.checksum {
+            self.chunk_checksums.push(checksum.compute(data));
+        }
+        self.plaintext_size += data.len() as u64;
+
+        // 先压缩再加密，摘要和校验值针对压缩前的明文；无法压缩的分片直接保存；稀疏文件中全零的分片加密空数据
+        let zero = self.sparse && data.iter().all(|&byte| byte == 0);
+        let compressed = match &self.compression {
+            Some(compression) if !zero => Some(compression.compress(data)?),
+            _ => None,
+        };
+        let data = match &compressed {
+            Some((compressed, applied)) => {
+                self.compressed_size += compressed.len() as u64;
+                self.stored_chunks += u32::from(!applied);
+                &compressed[..]
+            }
+            None if zero => {
+                self.sparse_chunks += 1;
+                &[][..]
+            }
+            None => data,
+        };
+
+        let mut frame = std::mem::take(&mut self.frame);
+        let encrypted = match &mut self.authenticator {
+            Some(authenticator) => authenticator.encrypt_chunk(data, &mut frame),
+            None => encrypt_into(&self.algorithm, &self.key, data, &mut frame),
+        };
+        encrypted.map_err(|e| e.context("Chunk encryption error"))?;
+        let written = self.write_frame(&frame);
+        self.frame = frame;
+        written
+    }
+
+    /// 写出一个原样复制的分片帧（增量更新时未变化的分片），data 为该分片的明文，只用于计算摘要和校验值
+    ///
+    /// 分片帧绑定了分片序号和总数，只能复制到分片大小和原始大小都相同的文件的同一位置。
+    pub fn copy_chunk(&mut self, data: &[u8], frame: &[u8]) -> Result<(), CodedError> {
+        if let Some(hasher) = &mut self.plaintext_hasher {
+            hasher.update(data);
+        }
+        if let Some(checksum) = &self.checksum {
+            self.chunk_checksums.push(checksum.compute(data));
+        }
+        self.plaintext_size += data.len() as u64;
+        if let Some(authenticator) = &mut self.authenticator {
+            let tag_len = chunk_tag_len(&self.algorithm);
+            authenticator.add_chunk(frame.len() as u64, &frame[frame.len().saturating_sub(tag_len)..]);
+        }
+        self.write_frame(frame)
+    }
+
+    /// 写入分片帧（长度字段和加密后的数据），记录它在文件中的位置
+    fn write_frame(&mut self, encrypted: &[u8]) -> Result<(), CodedError> {
+        let size_header = self.frame_format.encode_len(encrypted.len())?;
+        self.writer
+            .write_all(&size_header)
+            .map_err(|e| CodedError::io_write(format!("Failed to write chunk size header: {}", e)))?;
+        self.writer
+            .write_all(encrypted)
+            .map_err(|e| CodedError::io_write(format!("Failed to write encrypted chunk: {}", e)))?;
+
+        if let Some(chunk_offsets) = &mut self.chunk_offsets {
+            chunk_offsets.push(self.position);
+        }
+        self.position += (size_header.len() + encrypted.len()) as u64;
+        self.chunks_written += 1;
+        Ok(())
+    }
+
+    /// 写入任意长度的明文，凑满一个分片时加密写出，剩余部分在 finish 时写出
+    pub fn write(&mut self, mut data: &[u8]) -> Result<(), CodedError> {
+        while !data.is_empty() {
+            // 没有缓存的明文时，完整的分片直接从传入的数据加密，不复制到缓冲区
+            if self.pending.is_empty() && data.len() >= self.chunk_size {
+                let (chunk, rest) = data.split_at(self.chunk_size);
+                self.write_chunk(chunk)?;
+                data = rest;
+                continue;
+            }
+            let take = (self.chunk_size - self.pending.len()).min(data.len());
+            self.pending.extend_from_slice(&data[..take]);
+            data = &data[take..];
+            if self.pending.len() == self.chunk_size {
+                let chunk = std::mem::take(&mut self.pending);
+                self.write_chunk(&chunk)?;
+                self.pending = chunk;
+                self.pending.clear();
+            }
+        }
+        Ok(())
+    }
+
+    /// 写出 write() 缓存的不足一个分片的明文，作为最后一个分片；之后只能调用 finish()
+    pub fn write_pending(&mut self) -> Result<(), CodedError> {
+        if !self.pending.is_empty() {
+            let chunk = std::mem::take(&mut self.pending);
+            self.write_chunk(&chunk)?;
+        }
+        Ok(())
+    }
+
+    /// 底层 writer，写入内存缓冲区时用于取走已写出的数据
+    pub fn get_mut(&mut self) -> &mut W {
+        &mut self.writer
+    }
+
+    /// 已写出的分片数
+    pub fn chunks_written(&self) -> u32 {
+        self.chunks_written
+    }
+
+    /// 已写出的字节数（下一个分片帧在文件中的位置）
+    pub fn bytes_written(&self) -> u64 {
+        self.position
+    }
+
+    /// 以空分片帧保存的全零分片数
+    pub fn sparse_chunks(&self) -> u32 {
+        self.sparse_chunks
+    }
+
+    /// 已写出分片的压缩统计，未启用压缩时为 None
+    pub fn compression_stats(&self) -> Option<CompressionStats> {
+        self.compression.map(|_| CompressionStats {
+            compressed_size: self.compressed_size,
+            stored_chunks: self.stored_chunks,
+        })
+    }
+
+    /// 再写入 remaining 字节明文并 finish 之后文件大小的上限，用于预先分配磁盘空间；
+    /// 压缩或稀疏编码时大小无法预知，返回 None
+    pub fn expected_len(&self, remaining: u64) -> Option<u64> {
+        if self.compression.is_some() || self.sparse {
+            return None;
+        }
+        let chunks = remaining.div_ceil(self.chunk_size as u64);
+        let len_field = self.frame_format.encode_len(self.chunk_size + MAX_CHUNK_OVERHEAD).ok()?.len();
+        let frames = chunks * (len_field + MAX_CHUNK_OVERHEAD) as u64 + remaining;
+        // 结尾字段中每个分片的索引和校验值，加上结束帧、明文摘要等定长部分
+        let footer = (self.chunks_written as u64 + chunks) * (8 + CHUNK_CHECKSUM_LEN) as u64 + FOOTER_RESERVE;
+        Some(self.position + frames + footer)
+    }
+
+    /// 写出剩余的明文以及结束帧、整个文件的 MAC 和结尾字段（分片索引、明文大小和摘要），返回底层 writer
+    pub fn finish(mut self) -> Result<W, CodedError> {
+        self.write_pending()?;
+
+        if let Some(authenticator) = self.authenticator.take() {
+            let encrypted_digest = match self.plaintext_hasher.take() {
+                Some(hasher) => Some(seal_field(&self.key, FIELD_DIGEST, &hasher.finalize())?),
+                None => None,
+            };
+            let footer_fields = match self.chunk_offsets.take() {
+                Some(chunk_offsets) => ChunkedFooter {
+                    chunk_offsets,
+                    plaintext_size: Some(self.plaintext_size),
+                    encrypted_digest,
+                    chunk_checksums: std::mem::take(&mut self.chunk_checksums),
+                }
+                .fields(),
+                None => Vec::new(),
+            };
+            let has_footer = !footer_fields.is_empty();
+            self.frame_format
+                .write_footer(&mut self.writer, &authenticator.finalize(&footer_fields))?;
+            if has_footer {
+                let footer_offset = self.position + (self.frame_format.encode_len(0)?.len() + CHUNK_MAC_LEN) as u64;
+                ChunkedFooter::write(&mut self.writer, &footer_fields, footer_offset)?;
+            }
+        }
+
+        // 确保所有数据都写入磁盘
+        self.writer
+            .flush()
+            .map_err(|e| CodedError::io_write(format!("Failed to flush output file: {}", e)))?;
+        Ok(self.writer)
+    }
+}
+
+/// 结尾中与分片数无关的部分（结束帧、文件 MAC、明文大小和摘要、字段头）的上限
+const FOOTER_RESERVE: u64 = 256;
+
+/// 带结尾字段的文件记录明文摘要；可追加的文件追加时无法延续整个明文的摘要，不记录明文摘要
+fn new_plaintext_hasher(container: &ContainerHeader) -> Option<PlaintextHasher> {
+    (container.has_footer && !container.appendable)
+        .then(|| PlaintextHasher::new(container.hash_algorithm.unwrap_or(HashAlgorithm::Sha256)))
+}
+
+/// 分片写入器中断前的状态，记录在检查点中（见 checkpoint.rs）
+pub struct EncoderState {
+    /// 已写出的分片数
+    pub chunks: u32,
+    /// 下一个分片帧在文件中的位置
+    pub position: u64,
+    pub chunk_checksums: Vec<[u8; CHUNK_CHECKSUM_LEN]>,
+    pub compression_stats: Option<CompressionStats>,
+    pub sparse_chunks: u32,
+}
+
+/// 分片帧在文件中的位置、长度和末尾的认证标签
+pub struct FrameEntry {
+    /// 分片帧（长度字段）的起始位置
+    pub offset: u64,
+    /// 加密数据的起始位置
+    pub data_offset: u64,
+    pub len: usize,
+    pub tag: Vec<u8>,
+}
+
+/// scan_frames 的结果
+pub struct FrameScan {
+    pub frames: Vec<FrameEntry>,
+    /// 结束帧的位置
+    pub end: u64,
+    pub footer: ChunkedFooter,
+    /// 结尾字段区（文件 MAC 的输入）
+    pub footer_fields: Vec<u8>,
+}
+
+/// 只读取每个分片帧的长度和认证标签（不解密分片），并校验整个文件的 MAC，用于改写或追加前确认文件完好
+///
+/// header 为已校验过的带结尾字段的文件头，reader 位于第一个分片帧。
+pub fn scan_frames<R: Read + Seek>(reader: &mut BufReader<R>, header: &ChunkedHeader, algorithm: &CryptoAlgorithm, key: &[u8]) -> Result<FrameScan, CodedError> {
+    let container = match &header.container {
+        Some(container) if container.authenticated_chunks && container.has_footer => container,
+        _ => return Err(CodedError::unsupported("File has no chunk index")),
+    };
+    let header_tag = container.header_mac.as_deref().unwrap_or_default();
+    let mut authenticator = ChunkAuthenticator::new(algorithm.clone(), key, header_tag, header.chunk_sequence_total())?;
+    let (frames, position) = read_frame_entries(reader, header, algorithm, header.total_chunks())?;
+    for frame in &frames {
+        authenticator.add_chunk(frame.len as u64, &frame.tag);
+    }
+    let frame_format = header.frame_format();
+    let file_mac = frame_format.read_footer(reader)?;
+    let (footer, footer_fields) = ChunkedFooter::read(reader)?;
+    authenticator.verify(&file_mac, &footer_fields)?;
+    Ok(FrameScan { frames, end: position, footer, footer_fields })
+}
+
+/// 从第一个分片帧开始读取 count 个分片帧的长度和认证标签（不解密分片），返回这些分片帧以及之后的位置
+///
+/// reader 位于第一个分片帧；分片帧本身没有经过认证，调用方需要另外校验。
+pub fn read_frame_entries<R: Read + Seek>(reader: &mut BufReader<R>, header: &ChunkedHeader, algorithm: &CryptoAlgorithm, count: u64) -> Result<(Vec<FrameEntry>, u64), CodedError> {
+    let frame_format = header.frame_format();
+    let tag_len = chunk_tag_len(algorithm);
+    let mut frames = Vec::new();
+    let mut position = header.header_len;
+    for index in 0..count {
+        let what = format!("chunk {}", index + 1);
+        let len = frame_format.read_len(reader, &what)?;
+        if len < tag_len || len > header.max_frame_len() {
+            return Err(CodedError::bad_format(format!("Invalid encrypted chunk size in {}: {} bytes", what, len)));
+        }
+        let data_offset = position + frame_format.encode_len(len)?.len() as u64;
+        reader
+            .seek_relative((len - tag_len) as i64)
+            .map_err(|e| CodedError::io_read(format!("Error seeking in file: {}", e)))?;
+        let mut tag = vec![0u8; tag_len];
+        reader
+            .read_exact(&mut tag)
+            .map_err(|e| CodedError::io_read(format!("Error reading {}: {}", what, e)))?;
+        frames.push(FrameEntry { offset: position, data_offset, len, tag });
+        position = data_offset + len as u64;
+    }
+    Ok((frames, position))
+}
+
+/// 分片文件读取器：逐个读取并解密分片，finish 时校验结尾
+pub struct ChunkDecoder<R: BufRead> {
+    reader: R,
+    header: ChunkedHeader,
+    algorithm: CryptoAlgorithm,
+    key: Vec<u8>,
+    frame_format: FrameFormat,
+    max_frame_len: usize,
+    authenticator: Option<ChunkAuthenticator>,
+    plaintext_hasher: Option<PlaintextHasher>,
+    bytes_decrypted: u64,
+    chunks_read: u32,
+    /// 读取的分片帧，每个分片复用同一个缓冲区
+    frame: Vec<u8>,
+}
+
+impl<R: BufRead> ChunkDecoder<R> {
+    /// header 为已读取并校验过的文件头，reader 位于第一个分片帧
+    pub fn new(reader: R, header: ChunkedHeader, algorithm: CryptoAlgorithm, key: &[u8]) -> Result<Self, CodedError> {
+        // 是否校验分片认证由格式版本决定：v2 文件缺少分片认证时直接拒绝，只有 v1 `CHUNKS:` 文件没有认证
+        let authenticator = match &header.container {
+            Some(container) if !container.authenticated_chunks => return Err(CodedError::auth_failed("Chunks are not authenticated")),
+            Some(container) => {
+                let header_tag = container.header_mac.as_deref().unwrap_or_default();
+                Some(ChunkAuthenticator::new(algorithm.clone(), key, header_tag, header.chunk_sequence_total())?)
+            }
+            None => None,
+        };
+        // 结尾字段中的明文摘要默认为 SHA-256，文件头可以指定其它算法
+        let plaintext_hasher = match &header.container {
+            Some(container) if container.has_footer => Some(PlaintextHasher::new(
+                container.hash_algorithm.unwrap_or(HashAlgorithm::Sha256),
+            )),
+            _ => None,
+        };
+
+        Ok(ChunkDecoder {
+            reader,
+            frame_format: header.frame_format(),
+            max_frame_len: header.max_frame_len(),
+            header,
+            algorithm,
+            key: key.to_vec(),
+            authenticator,
+            plaintext_hasher,
+            bytes_decrypted: 0,
+            chunks_read: 0,
+            frame: Vec::new(),
+        })
+    }
+
+    pub fn header(&self) -> &ChunkedHeader {
+        &self.header
+    }
+
+    /// 底层 reader，从内存缓冲区读取时用于查看和补充尚未读取的数据
+    pub fn get_ref(&self) -> &R {
+        &self.reader
+    }
+
+    pub fn get_mut(&mut self) -> &mut R {
+        &mut self.reader
+    }
+
+    /// 已解密的明文字节数
+    pub fn bytes_decrypted(&self) -> u64 {
+        self.bytes_decrypted
+    }
+
+    /// 已读取的分片数
+    pub fn chunks_read(&self) -> u32 {
+        self.chunks_read
+    }
+
+    /// 读取并解密下一个分片，达到文件头记录的原始大小后返回 None；v2 文件同时校验分片认证标签和序号
+    pub fn next_chunk(&mut self) -> Result<Option<Vec<u8>>, CodedError> {
+        let mut chunk = Vec::new();
+        Ok(self.next_chunk_into(&mut chunk)?.then_some(chunk))
+    }
+
+    /// 与 next_chunk 相同，但明文写入调用方提供的缓冲区（先清空），循环中复用同一个缓冲区不必为每个分片重新分配；
+    /// 读完时返回 false
+    pub fn next_chunk_into(&mut self, out: &mut Vec<u8>) -> Result<bool, CodedError> {
+        let mut frame = std::mem::take(&mut self.frame);
+        let result = match self.read_frame_into(&mut frame) {
+            Ok(true) => self.decrypt_frame_into(&frame, out).map(|_| true),
+            other => other,
+        };
+        self.frame = frame;
+        result
+    }
+
+    /// 读取下一个分片帧但不解密，达到文件头记录的原始大小后返回 None
+    pub fn read_frame(&mut self) -> Result<Option<Vec<u8>>, CodedError> {
+        let mut frame = Vec::new();
+        Ok(self.read_frame_into(&mut frame)?.then_some(frame))
+    }
+
+    fn read_frame_into(&mut self, frame: &mut Vec<u8>) -> Result<bool, CodedError> {
+        if self.bytes_decrypted >= self.header.original_size {
+            return Ok(false);
+        }
+
+        let what = format!("chunk {}", self.chunks_read + 1);
+        self.frame_format.read_frame_into(&mut self.reader, &what, self.max_frame_len, frame)?;
+        if frame.is_empty() {
+            return Err(CodedError::truncated(format!(
+                "{}: end-of-file marker after {} of {} bytes",
+                ERR_TRUNCATED, self.bytes_decrypted, self.header.original_size
+            )));
+        }
+        Ok(true)
+    }
+
+    /// 解密 read_frame 读取的分片帧
+    pub fn decrypt_frame(&mut self, frame: &[u8]) -> Result<Vec<u8>, CodedError> {
+        let mut decrypted = Vec::new();
+        self.decrypt_frame_into(frame, &mut decrypted)?;
+        Ok(decrypted)
+    }
+
+    fn decrypt_frame_into(&mut self, frame: &[u8], out: &mut Vec<u8>) -> Result<(), CodedError> {
+        let decrypted = match &mut self.authenticator {
+            Some(authenticator) => authenticator.decrypt_chunk(frame, out),
+            None => decrypt_into(&self.algorithm, &self.key, frame, out),
+        };
+        decrypted.map_err(|e| e.context("Chunk decryption error"))?;
+        // 压缩和稀疏的分片还原到新的缓冲区，其它分片原样保留在 out 中
+        *out = self.header.restore_chunk(self.chunks_read as u64, std::mem::take(out))?;
+
+        if let Some(hasher) = &mut self.plaintext_hasher {
+            hasher.update(out);
+        }
+        self.bytes_decrypted += out.len() as u64;
+        self.chunks_read += 1;
+        Ok(())
+    }
+
+    /// 容错解密时跳过无法读取或解密的当前分片，返回它在明文中的范围（起始位置，长度）
+    ///
+    /// 跳过分片后整个文件的 MAC 和明文摘要不再可能匹配，不应再调用 finish。
+    pub fn skip_chunk(&mut self) -> (u64, u64) {
+        let start = self.bytes_decrypted;
+        let len = self.header.original_size.saturating_sub(start).min(self.header.chunk_size as u64);
+        if let Some(authenticator) = &mut self.authenticator {
+            authenticator.skip_chunk();
+        }
+        self.plaintext_hasher = None;
+        self.bytes_decrypted += len;
+        self.chunks_read += 1;
+        (start, len)
+    }
+
+    /// 读取最后一个分片之后的结尾，校验整个文件的 MAC 以及结尾字段记录的明文大小和摘要，
+    /// 返回底层 reader 以及是否核对了明文摘要
+    pub fn finish(mut self) -> Result<(R, bool), CodedError> {
+        let mut hash_verified = false;
+        if let Some(authenticator) = self.authenticator.take() {
+            let file_mac = self.frame_format.read_footer(&mut self.reader)?;
+            let footer = match self.plaintext_hasher {
+                Some(_) => Some(ChunkedFooter::read(&mut self.reader)?),
+                None => None,
+            };
+            authenticator.verify(&file_mac, footer.as_ref().map_or(&[][..], |(_, fields)| fields))?;
+            if let (Some((footer, _)), Some(hasher)) = (footer, self.plaintext_hasher.take()) {
+                footer.verify_plaintext(&self.key, self.bytes_decrypted, &hasher.finalize())?;
+                hash_verified = footer.encrypted_digest.is_some();
+            }
+        }
+        Ok((self.reader, hash_verified))
+    }
+}
+
+/// 容错读取时单个分片的结果
+pub enum ChunkOutcome {
+    Decrypted(Vec<u8>),
+    /// 无法读取或解密的分片，start 和 len 为它在明文中的范围
+    Damaged { start: u64, len: u64, error: String },
+}
+
+/// 以 Read 接口顺序读取 ChunkDecoder 解密的明文；分片解密失败时返回 InvalidData 错误，错误信息保持不变
+pub struct DecodedReader<R: BufRead> {
+    decoder: ChunkDecoder<R>,
+    chunk: Vec<u8>,
+    position: usize,
+}
+
+impl<R: BufRead> DecodedReader<R> {
+    pub fn new(decoder: ChunkDecoder<R>) -> Self {
+        DecodedReader { decoder, chunk: Vec::new(), position: 0 }
+    }
+
+    /// 读完剩余的明文后取回解码器，用于 finish() 校验整个文件的 MAC
+    pub fn into_decoder(mut self) -> Result<ChunkDecoder<R>, CodedError> {
+        std::io::copy(&mut self, &mut std::io::sink()).map_err(|e| CodedError::from_io(ErrorCode::IoRead, e))?;
+        Ok(self.decoder)
+    }
+}
+
+impl<R: BufRead> Read for DecodedReader<R> {
+    fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
+        while self.position == self.chunk.len() {
+            match self.decoder.next_chunk_into(&mut self.chunk) {
+                Ok(true) => self.position = 0,
+                Ok(false) => return Ok(0),
+                Err(err) => return Err(std::io::Error::new(std::io::ErrorKind::InvalidData, err)),
+            }
+        }
+        let len = buf.len().min(self.chunk.len() - self.position);
+        buf[..len].copy_from_slice(&self.chunk[self.position..self.position + len]);
+        self.position += len;
+        Ok(len)
+    }
+}
+
+/// 容错读取分片文件：无法读取或解密的分片不中止读取，而是作为损坏的分片返回
+///
+/// 文件有分片索引时，每个损坏的分片之后按索引跳转到下一个分片帧；没有索引时，分片帧本身损坏
+/// （长度字段错误、文件被截断）会使之后的分片都无法定位，全部作为损坏的分片返回。
+pub struct TolerantChunkReader<R: BufRead + Seek> {
+    decoder: ChunkDecoder<R>,
+    chunk_offsets: Option<Vec<u64>>,
+    /// 分片索引中的分片明文校验值，解密成功的分片还要与之核对
+    chunk_checksums: Vec<[u8; CHUNK_CHECKSUM_LEN]>,
+    checksum: ChunkChecksum,
+    /// 分片帧损坏且无法重新定位后，之后的分片都以此原因记为损坏
+    lost: Option<String>,
+    damaged_chunks: u32,
+}
+
+impl<R: BufRead + Seek> TolerantChunkReader<R> {
+    /// header 为已读取并校验过的文件头；结尾字段本身损坏时没有分片索引，只能顺序读取
+    pub fn new(mut reader: R, header: ChunkedHeader, algorithm: CryptoAlgorithm, key: &[u8]) -> Result<Self, CodedError> {
+        let has_footer = header.container.as_ref().is_some_and(|container| container.has_footer);
+        let footer = match has_footer {
+            true => ChunkedFooter::read_at_end(&mut reader).ok(),
+            false => None,
+        };
+        let (chunk_offsets, chunk_checksums) = match footer {
+            Some(footer) => (Some(footer.chunk_offsets), footer.chunk_checksums),
+            None => (None, Vec::new()),
+        };
+        reader
+            .seek(SeekFrom::Start(header.header_len))
+            .map_err(|e| CodedError::io_read(format!("Error seeking in file: {}", e)))?;
+        Ok(TolerantChunkReader {
+            decoder: ChunkDecoder::new(reader, header, algorithm, key)?,
+            chunk_offsets,
+            chunk_checksums,
+            checksum: ChunkChecksum::new(key),
+            lost: None,
+            damaged_chunks: 0,
+        })
+    }
+
+    pub fn decoder(&self) -> &ChunkDecoder<R> {
+        &self.decoder
+    }
+
+    /// 损坏的分片数
+    pub fn damaged_chunks(&self) -> u32 {
+        self.damaged_chunks
+    }
+
+    /// 读取下一个分片，返回（从 0 开始的分片序号，结果）；所有分片读完后返回 None
+    pub fn next_chunk(&mut self) -> Option<(u32, ChunkOutcome)> {
+        if self.decoder.bytes_decrypted() >= self.decoder.header().original_size {
+            return None;
+        }
+        let index = self.decoder.chunks_read();
+        // 第二项表示分片帧是否完整读出，没有分片索引时据此判断能否继续顺序读取
+        let failure = match &self.lost {
+            Some(reason) => (reason.clone(), false),
+            None => match self.decoder.read_frame() {
+                Ok(Some(frame)) => match self.decoder.decrypt_frame(&frame) {
+                    Ok(decrypted) => match self.chunk_checksums.get(index as usize) {
+                        // 分片已通过认证但明文与索引中的校验值不符：分片帧已读完，解码器无需再跳过
+                        Some(expected) if *expected != self.checksum.compute(&decrypted) => {
+                            self.damaged_chunks += 1;
+                            let len = decrypted.len() as u64;
+                            let start = self.decoder.bytes_decrypted() - len;
+                            let error = "Chunk checksum mismatch".to_string();
+                            return Some((index, ChunkOutcome::Damaged { start, len, error }));
+                        }
+                        _ => return Some((index, ChunkOutcome::Decrypted(decrypted))),
+                    },
+                    Err(err) => (err.message, true),
+                },
+                Ok(None) => return None,
+                Err(err) => (err.message, false),
+            },
+        };
+
+        let (error, frame_intact) = failure;
+        let (start, len) = self.decoder.skip_chunk();
+        self.damaged_chunks += 1;
+        if self.lost.is_none() {
+            let next = self.chunk_offsets.as_ref().and_then(|offsets| offsets.get(index as usize + 1));
+            if let Some(&offset) = next {
+                if let Err(err) = self.decoder.reader.seek(SeekFrom::Start(offset)) {
+                    self.lost = Some(format!("Error seeking in file: {}", err));
+                }
+            } else if !frame_intact {
+                self.lost = Some(format!("Chunk cannot be located after damaged chunk {}", index));
+            }
+        }
+        Some((index, ChunkOutcome::Damaged { start, len, error }))
+    }
+
+    /// 没有损坏的分片时校验整个文件的 MAC 和结尾字段，返回校验失败的原因
+    pub fn finish(self) -> Option<String> {
+        match self.damaged_chunks {
+            0 => self.decoder.finish().err().map(|err| err.message),
+            count => Some(format!("{} damaged chunks", count)),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::Cursor;
+
+    use super::*;
+    use crate::format::{read_chunked_header, Layout};
+
+    const KEY: [u8; 32] = [7; 32];
+    const CHUNK_SIZE: usize = 1000;
+    const ALGORITHMS: [CryptoAlgorithm; 2] = [CryptoAlgorithm::Aes, CryptoAlgorithm::Chacha20Poly1305];
+
+    /// 3.5 个分片的明文，每个字节都不同于相邻分片的对应字节
+    fn plaintext() -> Vec<u8> {
+        (0..3500u32).map(|i| (i % 251) as u8).collect()
+    }
+
+    /// 以 chunkEncryptFile() 的 v2 格式加密
+    fn encrypt(algorithm: &CryptoAlgorithm, data: &[u8]) -> Vec<u8> {
+        let container = ContainerHeader::for_new_file(Layout::Chunked, algorithm.clone(), data.len() as u64, CHUNK_SIZE as u64);
+        let mut encoder = ChunkEncoder::new(Vec::new(), algorithm.clone(), &KEY, Some(&container), data.len() as u64, CHUNK_SIZE).unwrap();
+        encoder.write(data).unwrap();
+        encoder.finish().unwrap()
+    }
+
+    /// 与 chunkDecryptFile() 相同的步骤：校验文件头，逐个解密分片，最后校验结尾
+    fn decrypt(algorithm: &CryptoAlgorithm, file: &[u8]) -> Result<Vec<u8>, CodedError> {
+        let mut reader = Cursor::new(file);
+        let header = read_chunked_header(&mut reader)?;
+        header.container.as_ref().unwrap().verify(&KEY)?;
+        let mut decoder = ChunkDecoder::new(reader, header, algorithm.clone(), &KEY)?;
+        let mut plaintext = Vec::new();
+        while let Some(chunk) = decoder.next_chunk()? {
+            plaintext.extend_from_slice(&chunk);
+        }
+        decoder.finish()?;
+        Ok(plaintext)
+    }
+
+    /// 文件头长度
+    fn header_len(file: &[u8]) -> usize {
+        read_chunked_header(&mut Cursor::new(file)).unwrap().header_len as usize
+    }
+
+    /// 各分片帧的位置和长度，以及结束帧之后整个文件的 MAC 的位置
+    fn frames(algorithm: &CryptoAlgorithm, file: &[u8]) -> (Vec<FrameEntry>, usize) {
+        let mut reader = BufReader::new(Cursor::new(file));
+        let header = read_chunked_header(&mut reader).unwrap();
+        let (frames, end) = read_frame_entries(&mut reader, &header, algorithm, header.total_chunks()).unwrap();
+        (frames, end as usize + header.frame_format().encode_len(0).unwrap().len())
+    }
+
+    fn replace_u64(file: &mut [u8], range: std::ops::Range<usize>, from: u64, to: u64) {
+        let at = file[range.clone()].windows(8).position(|bytes| bytes == from.to_le_bytes()).unwrap() + range.start;
+        file[at..at + 8].copy_from_slice(&to.to_le_bytes());
+    }
+
+    #[test]
+    fn round_trip() {
+        for algorithm in &ALGORITHMS {
+            assert_eq!(decrypt(algorithm, &encrypt(algorithm, &plaintext())).unwrap(), plaintext());
+        }
+    }
+
+    #[test]
+    fn rejects_tampered_header() {
+        for algorithm in &ALGORITHMS {
+            let file = encrypt(algorithm, &plaintext());
+            let header_len = header_len(&file);
+            // 改写声明的原始大小和分片大小，文件头仍能解析，但认证标签不再匹配
+            for (from, to) in [(3500, 3000), (CHUNK_SIZE as u64, 500)] {
+                let mut tampered = file.clone();
+                replace_u64(&mut tampered, 0..header_len, from, to);
+                assert_eq!(decrypt(algorithm, &tampered).unwrap_err().code, ErrorCode::AuthFailed);
+            }
+            // 改写认证标签本身
+            let mut tampered = file.clone();
+            tampered[header_len - 1] ^= 1;
+            assert_eq!(decrypt(algorithm, &tampered).unwrap_err().code, ErrorCode::AuthFailed);
+        }
+    }
+
+    #[test]
+    fn rejects_tampered_chunk() {
+        for algorithm in &ALGORITHMS {
+            let file = encrypt(algorithm, &plaintext());
+            let (frames, file_mac) = frames(algorithm, &file);
+            assert_eq!(frames.len(), 4);
+            // 分片数据、分片认证标签和结尾中的整个文件的 MAC 各改写一个字节
+            let data = frames[1].data_offset as usize + 10;
+            let tag = (frames[2].data_offset as usize) + frames[2].len - 1;
+            for at in [data, tag, file_mac] {
+                let mut tampered = file.clone();
+                tampered[at] ^= 0x80;
+                assert_eq!(decrypt(algorithm, &tampered).unwrap_err().code, ErrorCode::AuthFailed, "byte {}", at);
+            }
+        }
+    }
+
+    /// frames 中第 index 个分片帧的字节范围
+    fn frame_range(frames: &[FrameEntry], index: usize) -> std::ops::Range<usize> {
+        let frame = &frames[index];
+        frame.offset as usize..frame.data_offset as usize + frame.len
+    }
+
+    #[test]
+    fn rejects_reordered_and_duplicated_chunks() {
+        for algorithm in &ALGORITHMS {
+            let file = encrypt(algorithm, &plaintext());
+            let (frames, _) = frames(algorithm, &file);
+            let (first, second, third) = (frame_range(&frames, 0), frame_range(&frames, 1), frame_range(&frames, 2));
+            assert_eq!(first.len(), second.len());
+
+            // 交换前两个分片
+            let mut swapped = file.clone();
+            swapped[first.clone()].copy_from_slice(&file[second.clone()]);
+            swapped[second.clone()].copy_from_slice(&file[first.clone()]);
+            assert_eq!(decrypt(algorithm, &swapped).unwrap_err().code, ErrorCode::AuthFailed);
+
+            // 第三个分片换成第二个分片的副本
+            let mut duplicated = file.clone();
+            duplicated[third].copy_from_slice(&file[second.clone()]);
+            assert_eq!(decrypt(algorithm, &duplicated).unwrap_err().code, ErrorCode::AuthFailed);
+
+            // 同一密钥加密的另一个文件中同一位置的分片
+            let other = encrypt(algorithm, &plaintext());
+            let mut spliced = file.clone();
+            spliced[second.clone()].copy_from_slice(&other[second]);
+            assert_eq!(decrypt(algorithm, &spliced).unwrap_err().code, ErrorCode::AuthFailed);
+        }
+    }
+
+    /// 分片认证由格式版本决定：清除标志位并去掉分片认证标签、再篡改密文的 AES-CBC 文件不能解密
+    #[test]
+    fn rejects_stripped_chunk_authentication() {
+        let algorithm = &CryptoAlgorithm::Aes;
+        let file = encrypt(algorithm, &plaintext());
+        let header_len = header_len(&file);
+        let (frames, _) = frames(algorithm, &file);
+        let mut stripped = file[..header_len - HEADER_MAC_LEN].to_vec();
+        stripped[11] = 0x08;
+        for frame in &frames {
+            let data = &file[frame.data_offset as usize..frame.data_offset as usize + frame.len - CHUNK_MAC_LEN];
+            stripped.extend_from_slice(&(data.len() as u32).to_le_bytes());
+            stripped.extend_from_slice(data);
+        }
+        stripped.extend_from_slice(&0u32.to_le_bytes());
+        let at = header_len - HEADER_MAC_LEN + 4 + 20;
+        stripped[at] ^= 0x80;
+        assert_eq!(decrypt(algorithm, &stripped).unwrap_err().code, ErrorCode::AuthFailed);
+
+        // 即使文件头认证标签有效，不带分片认证的 v2 分片文件头也被拒绝
+        let mut container = ContainerHeader::new(Layout::Chunked, algorithm.clone(), 3500, CHUNK_SIZE as u64);
+        container.binary_frames = true;
+        let mut unauthenticated = container.to_bytes(&KEY).unwrap();
+        unauthenticated.extend_from_slice(&stripped[header_len - HEADER_MAC_LEN..]);
+        assert_eq!(decrypt(algorithm, &unauthenticated).unwrap_err().code, ErrorCode::AuthFailed);
+    }
+
+    #[test]
+    fn rejects_truncated_file() {
+        for algorithm in &ALGORITHMS {
+            let file = encrypt(algorithm, &plaintext());
+            let header_len = header_len(&file);
+            // 在分片中间、分片之间、结束帧、整个文件的 MAC 和结尾字段中的任意位置截断
+            for len in header_len..file.len() {
+                assert_eq!(decrypt(algorithm, &file[..len]).unwrap_err().code, ErrorCode::Truncated, "length {}", len);
+            }
+            // 去掉最后一个分片，之后的结尾保持不变
+            let (frames, _) = frames(algorithm, &file);
+            let mut dropped = file.clone();
+            dropped.drain(frame_range(&frames, 3));
+            assert_eq!(decrypt(algorithm, &dropped).unwrap_err().code, ErrorCode::Truncated);
+        }
+    }
+}
@@ -0,0 +1,537 @@
+use std::fs::File;
+use std::io::{BufWriter, Read, Write};
+
+use aes::cipher::{BlockCipher, BlockEncrypt, KeyInit};
+use aes::{Aes128, Aes192, Aes256};
+use base64::engine::general_purpose::STANDARD;
+use base64::Engine;
+use cbc::cipher::block_padding::{NoPadding, Pkcs7};
+use cbc::cipher::{BlockEncryptMut, KeyIvInit};
+use rand::RngCore;
+
+use crate::errors::{open_error, read_error, write_error, CodedError};
+use crate::fd::{self, SyncOutput, Target};
+use crate::job::JobControl;
+use crate::pipeline;
+use crate::rsa::{OaepHash, RsaKey};
+
+/// id-envelopedData（1.2.840.113549.1.7.3）
+const OID_ENVELOPED_DATA: &[u8] = &[0x2A, 0x86, 0x48, 0x86, 0xF7, 0x0D, 0x01, 0x07, 0x03];
+/// id-data（1.2.840.113549.1.7.1）
+const OID_DATA: &[u8] = &[0x2A, 0x86, 0x48, 0x86, 0xF7, 0x0D, 0x01, 0x07, 0x01];
+/// rsaEncryption（1.2.840.113549.1.1.1）
+const OID_RSA_ENCRYPTION: &[u8] = &[0x2A, 0x86, 0x48, 0x86, 0xF7, 0x0D, 0x01, 0x01, 0x01];
+/// id-RSAES-OAEP（1.2.840.113549.1.1.7）
+const OID_RSAES_OAEP: &[u8] = &[0x2A, 0x86, 0x48, 0x86, 0xF7, 0x0D, 0x01, 0x01, 0x07];
+/// id-mgf1（1.2.840.113549.1.1.8）
+const OID_MGF1: &[u8] = &[0x2A, 0x86, 0x48, 0x86, 0xF7, 0x0D, 0x01, 0x01, 0x08];
+/// id-sha256（2.16.840.1.101.3.4.2.1）
+const OID_SHA256: &[u8] = &[0x60, 0x86, 0x48, 0x01, 0x65, 0x03, 0x04, 0x02, 0x01];
+
+const TAG_INTEGER: u8 = 0x02;
+const TAG_BIT_STRING: u8 = 0x03;
+const TAG_OCTET_STRING: u8 = 0x04;
+const TAG_NULL: u8 = 0x05;
+const TAG_OID: u8 = 0x06;
+const TAG_SEQUENCE: u8 = 0x30;
+const TAG_SET: u8 = 0x31;
+
+const BLOCK_LEN: usize = 16;
+/// 复制数据时每次读取的长度（分组长度的整数倍），每读完一块检查一次超时并发送 chunkDone 事件
+const COPY_BUFFER_LEN: usize = 1024 * 1024;
+/// PEM 每行 64 个字符，对应 48 字节
+const PEM_LINE_BYTES: usize = 48;
+
+/// DER 长度字段
+fn header(tag: u8, len: usize) -> Vec<u8> {
+    let mut output = vec![tag];
+    if len < 0x80 {
+        output.push(len as u8);
+    } else {
+        let bytes = len.to_be_bytes();
+        let skip = bytes.iter().take_while(|&&byte| byte == 0).count();
+        output.push(0x80 | (bytes.len() - skip) as u8);
+        output.extend_from_slice(&bytes[skip..]);
+    }
+    output
+}
+
+fn der(tag: u8, parts: &[&[u8]]) -> Vec<u8> {
+    let mut output = header(tag, parts.iter().map(|part| part.len()).sum());
+    parts.iter().for_each(|part| output.extend_from_slice(part));
+    output
+}
+
+/// 给后面还跟着 tail_len 字节内容的结构加上外层标签：只输出外层头部和 prefix，tail 由调用方随后写入
+fn wrap(tag: u8, prefix: &[&[u8]], tail_len: usize) -> Vec<u8> {
+    let mut output = header(tag, prefix.iter().map(|part| part.len()).sum::<usize>() + tail_len);
+    prefix.iter().for_each(|part| output.extend_from_slice(part));
+    output
+}
+
+/// 一个 DER 元素：标签、内容和包括头部在内的完整编码
+struct Element<'a> {
+    tag: u8,
+    content: &'a [u8],
+    raw: &'a [u8],
+}
+
+/// 读取 data 开头的一个 DER 元素，返回该元素和剩余的数据
+fn read_element(data: &[u8]) -> Result<(Element<'_>, &[u8]), CodedError> {
+    let invalid = || CodedError::bad_format("Invalid certificate: malformed DER");
+    let (&tag, rest) = data.split_first().ok_or_else(invalid)?;
+    let (&first, rest) = rest.split_first().ok_or_else(invalid)?;
+    let (len, rest) = if first < 0x80 {
+        (first as usize, rest)
+    } else {
+        let count = (first & 0x7f) as usize;
+        if count == 0 || count > 4 || rest.len() < count {
+            return Err(invalid());
+        }
+        let len = rest[..count].iter().fold(0usize, |len, &byte| (len << 8) | byte as usize);
+        (len, &rest[count..])
+    };
+    if rest.len() < len {
+        return Err(invalid());
+    }
+    let header_len = data.len() - rest.len();
+    let element = Element { tag, content: &rest[..len], raw: &data[..header_len + len] };
+    Ok((element, &rest[len..]))
+}
+
+fn expect_element<'a>(data: &'a [u8], tag: u8, what: &str) -> Result<(Element<'a>, &'a [u8]), CodedError> {
+    let (element, rest) = read_element(data)?;
+    if element.tag != tag {
+        return Err(CodedError::bad_format(format!("Invalid certificate: unexpected {}", what)));
+    }
+    Ok((element, rest))
+}
+
+/// 接收方：从 X.509 证书中取出的 IssuerAndSerialNumber 和 RSA 公钥
+pub struct Recipient {
+    issuer_and_serial: Vec<u8>,
+    key: RsaKey,
+}
+
+impl Recipient {
+    /// 读取 DER 或 PEM 编码的 X.509 证书，只支持 RSA 公钥
+    pub fn from_certificate(data: &[u8]) -> Result<Self, CodedError> {
+        let decoded;
+        let der_bytes = if data.first() == Some(&TAG_SEQUENCE) {
+            data
+        } else {
+            decoded = pem_decode(data, "CERTIFICATE")?;
+            &decoded
+        };
+
+        let (certificate, _) = expect_element(der_bytes, TAG_SEQUENCE, "certificate structure")?;
+        let (tbs, _) = expect_element(certificate.content, TAG_SEQUENCE, "tbsCertificate")?;
+        let mut fields = tbs.content;
+        // 可选的 [0] version
+        if fields.first() == Some(&0xA0) {
+            fields = read_element(fields)?.1;
+        }
+        let (serial, fields) = expect_element(fields, TAG_INTEGER, "serial number")?;
+        let (_, fields) = expect_element(fields, TAG_SEQUENCE, "signature algorithm")?;
+        let (issuer, fields) = expect_element(fields, TAG_SEQUENCE, "issuer")?;
+        let (_, fields) = expect_element(fields, TAG_SEQUENCE, "validity")?;
+        let (_, fields) = expect_element(fields, TAG_SEQUENCE, "subject")?;
+        let (spki, _) = expect_element(fields, TAG_SEQUENCE, "subjectPublicKeyInfo")?;
+
+        let (algorithm, rest) = expect_element(spki.content, TAG_SEQUENCE, "public key algorithm")?;
+        let (oid, _) = expect_element(algorithm.content, TAG_OID, "public key algorithm")?;
+        if oid.content != OID_RSA_ENCRYPTION {
+            return Err(CodedError::unsupported("Only RSA recipient certificates are supported for CMS"));
+        }
+        let (bits, _) = expect_element(rest, TAG_BIT_STRING, "public key")?;
+        let public_key = bits.content.get(1..).filter(|_| bits.content[0] == 0).ok_or_else(|| CodedError::bad_format("Invalid certificate: bad public key"))?;
+        let (public_key, _) = expect_element(public_key, TAG_SEQUENCE, "RSA public key")?;
+        let (n, rest) = expect_element(public_key.content, TAG_INTEGER, "RSA modulus")?;
+        let (e, _) = expect_element(rest, TAG_INTEGER, "RSA exponent")?;
+        let key = RsaKey::from_public_bytes(n.content, e.content)?;
+
+        Ok(Recipient { issuer_and_serial: der(TAG_SEQUENCE, &[issuer.raw, serial.raw]), key })
+    }
+}
+
+/// 取出 PEM 中第一个 label 块的内容
+fn pem_decode(data: &[u8], label: &str) -> Result<Vec<u8>, CodedError> {
+    let text = std::str::from_utf8(data).map_err(|_| CodedError::invalid_argument("Certificate must be DER or PEM"))?;
+    let begin = format!("-----BEGIN {}-----", label);
+    let end = format!("-----END {}-----", label);
+    let start = text.find(&begin).ok_or_else(|| CodedError::invalid_argument("Certificate must be DER or PEM (no BEGIN CERTIFICATE line)"))? + begin.len();
+    let stop = text[start..].find(&end).ok_or_else(|| CodedError::bad_format("Invalid PEM certificate: missing END line"))? + start;
+    let body: String = text[start..stop].chars().filter(|c| !c.is_whitespace()).collect();
+    STANDARD
+        .decode(body)
+        .map_err(|_| CodedError::bad_format("Invalid PEM certificate: bad base64"))
+}
+
+/// 内容加密密钥的传输方式（KeyTransRecipientInfo 的 keyEncryptionAlgorithm）
+#[derive(Clone, Copy)]
+pub enum KeyTransport {
+    /// RSAES-PKCS1-v1_5，`openssl cms -encrypt` 和大多数 S/MIME 客户端的默认值
+    Pkcs1,
+    Oaep(OaepHash),
+}
+
+impl KeyTransport {
+    pub fn parse(name: &str) -> Result<Self, CodedError> {
+        match name.to_ascii_lowercase().as_str() {
+            "pkcs1" => Ok(KeyTransport::Pkcs1),
+            "oaep-sha1" => Ok(KeyTransport::Oaep(OaepHash::Sha1)),
+            "oaep-sha256" => Ok(KeyTransport::Oaep(OaepHash::Sha256)),
+            _ => Err(CodedError::unsupported(format!("Unsupported CMS key transport: {}, supported: pkcs1, oaep-sha1, oaep-sha256", name))),
+        }
+    }
+
+    fn algorithm_identifier(&self) -> Vec<u8> {
+        match self {
+            KeyTransport::Pkcs1 => der(TAG_SEQUENCE, &[&der(TAG_OID, &[OID_RSA_ENCRYPTION]), &der(TAG_NULL, &[])]),
+            // SHA-1 和 MGF1-SHA-1 是 RSAES-OAEP-params 的默认值，参数为空序列
+            KeyTransport::Oaep(OaepHash::Sha1) => der(TAG_SEQUENCE, &[&der(TAG_OID, &[OID_RSAES_OAEP]), &der(TAG_SEQUENCE, &[])]),
+            // RFC 5754：SHA-2 的 AlgorithmIdentifier 省略参数，与 OpenSSL 相同
+            KeyTransport::Oaep(OaepHash::Sha256) => {
+                let sha256 = der(TAG_SEQUENCE, &[&der(TAG_OID, &[OID_SHA256])]);
+                let mgf1 = der(TAG_SEQUENCE, &[&der(TAG_OID, &[OID_MGF1]), &sha256]);
+                let params = der(TAG_SEQUENCE, &[&der(0xA0, &[&sha256]), &der(0xA1, &[&mgf1])]);
+                der(TAG_SEQUENCE, &[&der(TAG_OID, &[OID_RSAES_OAEP]), &params])
+            }
+        }
+    }
+
+    fn encrypt(&self, key: &RsaKey, content_key: &[u8]) -> Result<Vec<u8>, CodedError> {
+        match self {
+            KeyTransport::Pkcs1 => key.pkcs1_encrypt(content_key),
+            KeyTransport::Oaep(hash) => key.oaep_encrypt(*hash, content_key),
+        }
+    }
+}
+
+/// 内容加密算法
+#[derive(Clone, Copy)]
+pub enum ContentCipher {
+    Aes128Cbc,
+    Aes192Cbc,
+    Aes256Cbc,
+}
+
+impl ContentCipher {
+    pub fn parse(name: &str) -> Result<Self, CodedError> {
+        match name.to_ascii_lowercase().as_str() {
+            "aes-128-cbc" => Ok(ContentCipher::Aes128Cbc),
+            "aes-192-cbc" => Ok(ContentCipher::Aes192Cbc),
+            "aes-256-cbc" => Ok(ContentCipher::Aes256Cbc),
+            _ => Err(CodedError::unsupported(format!("Unsupported CMS content encryption: {}, supported: aes-128-cbc, aes-192-cbc, aes-256-cbc", name))),
+        }
+    }
+
+    fn key_len(&self) -> usize {
+        match self {
+            ContentCipher::Aes128Cbc => 16,
+            ContentCipher::Aes192Cbc => 24,
+            ContentCipher::Aes256Cbc => 32,
+        }
+    }
+
+    /// aes128-CBC / aes192-CBC / aes256-CBC（2.16.840.1.101.3.4.1.2 / .22 / .42）
+    fn algorithm_identifier(&self, iv: &[u8]) -> Vec<u8> {
+        let last = match self {
+            ContentCipher::Aes128Cbc => 0x02,
+            ContentCipher::Aes192Cbc => 0x16,
+            ContentCipher::Aes256Cbc => 0x2A,
+        };
+        let oid = [0x60, 0x86, 0x48, 0x01, 0x65, 0x03, 0x04, 0x01, last];
+        der(TAG_SEQUENCE, &[&der(TAG_OID, &[&oid]), &der(TAG_OCTET_STRING, &[iv])])
+    }
+}
+
+/// 输出的编码
+#[derive(Clone, Copy, PartialEq)]
+pub enum OutputFormat {
+    Der,
+    /// `-----BEGIN CMS-----`，与 `openssl cms -outform PEM` 相同
+    Pem,
+}
+
+impl OutputFormat {
+    pub fn parse(name: &str) -> Result<Self, CodedError> {
+        match name.to_ascii_lowercase().as_str() {
+            "der" => Ok(OutputFormat::Der),
+            "pem" => Ok(OutputFormat::Pem),
+            _ => Err(CodedError::unsupported(format!("Unsupported CMS output format: {}, supported: der, pem", name))),
+        }
+    }
+}
+
+/// 把写入的数据编码为每行 64 个字符的 PEM
+struct PemWriter<W: Write> {
+    inner: W,
+    pending: Vec<u8>,
+}
+
+impl<W: Write> PemWriter<W> {
+    fn new(mut inner: W) -> std::io::Result<Self> {
+        inner.write_all(b"-----BEGIN CMS-----\n")?;
+        Ok(PemWriter { inner, pending: Vec::with_capacity(PEM_LINE_BYTES) })
+    }
+
+    fn write_line(&mut self, bytes: &[u8]) -> std::io::Result<()> {
+        self.inner.write_all(STANDARD.encode(bytes).as_bytes())?;
+        self.inner.write_all(b"\n")
+    }
+
+    fn finish(mut self) -> std::io::Result<W> {
+        if !self.pending.is_empty() {
+            let pending = std::mem::take(&mut self.pending);
+            self.write_line(&pending)?;
+        }
+        self.inner.write_all(b"-----END CMS-----\n")?;
+        Ok(self.inner)
+    }
+}
+
+impl<W: Write> Write for PemWriter<W> {
+    fn write(&mut self, mut data: &[u8]) -> std::io::Result<usize> {
+        let written = data.len();
+        if !self.pending.is_empty() {
+            let take = (PEM_LINE_BYTES - self.pending.len()).min(data.len());
+            self.pending.extend_from_slice(&data[..take]);
+            data = &data[take..];
+            if self.pending.len() < PEM_LINE_BYTES {
+                return Ok(written);
+            }
+            let pending = std::mem::take(&mut self.pending);
+            self.write_line(&pending)?;
+        }
+        let mut lines = data.chunks_exact(PEM_LINE_BYTES);
+        for line in &mut lines {
+            self.write_line(line)?;
+        }
+        self.pending.extend_from_slice(lines.remainder());
+        Ok(written)
+    }
+
+    fn flush(&mut self) -> std::io::Result<()> {
+        self.inner.flush()
+    }
+}
+
+/// 生成 CMS 文件的统计
+pub struct CmsReport {
+    pub plaintext_size: u64,
+    pub encrypted_size: u64,
+}
+
+/// 以 CBC 模式流式加密输入文件，最后一块加 PKCS#7 填充；输入的长度必须等于 plaintext_size（写在 DER 长度字段中）
+fn encrypt_content<C, R, W>(input: &mut R, writer: &mut W, key: &[u8], iv: &[u8], plaintext_size: u64, check: impl Fn() -> Result<(), CodedError>, mut chunk_done: impl FnMut(u32, usize)) -> Result<(), CodedError>
+where
+    C: BlockEncrypt + BlockCipher + KeyInit,
+    R: Read,
+    W: Write,
+{
+    let mut iv = iv.to_vec();
+    let mut buffer = vec![0u8; COPY_BUFFER_LEN];
+    let mut read_total = 0u64;
+    let mut index = 0u32;
+    let tail = loop {
+        check()?;
+        let read = pipeline::read_full(input, &mut buffer).map_err(|err| read_error("reading input file", err))?;
+        read_total += read as u64;
+        if read < buffer.len() {
+            break read;
+        }
+        cbc::Encryptor::<C>::new_from_slices(key, &iv)
+            .map_err(|e| CodedError::unknown(format!("AES cipher init failed: {:?}", e)))?
+            .encrypt_padded_mut::<NoPadding>(&mut buffer, read)
+            .map_err(|e| CodedError::unknown(format!("AES-CBC encryption failed: {:?}", e)))?;
+        iv.copy_from_slice(&buffer[read - BLOCK_LEN..read]);
+        writer
+            .write_all(&buffer[..read])
+            .map_err(|e| write_error("writing output file", e))?;
+        index += 1;
+        chunk_done(index, read);
+    };
+    if read_total != plaintext_size {
+        return Err(CodedError::io_read("Input file changed size while it was being encrypted"));
+    }
+
+    let whole = tail - tail % BLOCK_LEN;
+    if whole > 0 {
+        cbc::Encryptor::<C>::new_from_slices(key, &iv)
+            .map_err(|e| CodedError::unknown(format!("AES cipher init failed: {:?}", e)))?
+            .encrypt_padded_mut::<NoPadding>(&mut buffer, whole)
+            .map_err(|e| CodedError::unknown(format!("AES-CBC encryption failed: {:?}", e)))?;
+        iv.copy_from_slice(&buffer[whole - BLOCK_LEN..whole]);
+    }
+    let last = cbc::Encryptor::<C>::new_from_slices(key, &iv)
+        .map_err(|e| CodedError::unknown(format!("AES cipher init failed: {:?}", e)))?
+        .encrypt_padded_vec_mut::<Pkcs7>(&buffer[whole..tail]);
+    writer
+        .write_all(&buffer[..whole])
+        .and_then(|_| writer.write_all(&last))
+        .map_err(|e| write_error("writing output file", e))?;
+    if tail > 0 {
+        chunk_done(index + 1, tail);
+    }
+    Ok(())
+}
+
+/// 随机生成的内容加密密钥和 IV
+struct ContentKey {
+    cipher: ContentCipher,
+    key: Vec<u8>,
+    iv: [u8; BLOCK_LEN],
+}
+
+impl ContentKey {
+    fn generate(cipher: ContentCipher) -> Self {
+        let mut key = vec![0u8; cipher.key_len()];
+        let mut iv = [0u8; BLOCK_LEN];
+        rand::thread_rng().fill_bytes(&mut key);
+        rand::thread_rng().fill_bytes(&mut iv);
+        ContentKey { cipher, key, iv }
+    }
+
+    /// 写入 prefix（密文之前的全部 DER 头部），然后写入加密的文件内容
+    fn write<W: Write>(&self, writer: &mut W, prefix: &[u8], input: &mut File, plaintext_size: u64, control: &JobControl) -> Result<(), CodedError> {
+        writer.write_all(prefix).map_err(|e| write_error("writing output file", e))?;
+        let check = || control.check();
+        let chunk_done = |index, bytes| control.chunk_done(index, bytes);
+        match self.cipher {
+            ContentCipher::Aes128Cbc => encrypt_content::<Aes128, _, W>(input, writer, &self.key, &self.iv, plaintext_size, check, chunk_done),
+            ContentCipher::Aes192Cbc => encrypt_content::<Aes192, _, W>(input, writer, &self.key, &self.iv, plaintext_size, check, chunk_done),
+            ContentCipher::Aes256Cbc => encrypt_content::<Aes256, _, W>(input, writer, &self.key, &self.iv, plaintext_size, check, chunk_done),
+        }
+    }
+}
+
+/// KeyTransRecipientInfo { 0, IssuerAndSerialNumber, keyEncryptionAlgorithm, encryptedKey }
+fn key_trans_recipient_info(recipient: &Recipient, transport: KeyTransport, encrypted_key: &[u8]) -> Vec<u8> {
+    der(TAG_SEQUENCE, &[
+        &der(TAG_INTEGER, &[&[0]]),
+        &recipient.issuer_and_serial,
+        &transport.algorithm_identifier(),
+        &der(TAG_OCTET_STRING, &[encrypted_key]),
+    ])
+}
+
+/// 密文之前的全部 DER 头部，外层长度都包括随后写入的 ciphertext_len 字节密文
+fn enveloped_data_prefix(mut recipient_infos: Vec<Vec<u8>>, cipher: ContentCipher, iv: &[u8], ciphertext_len: usize) -> Vec<u8> {
+    // RecipientInfos 是 SET OF，DER 要求按编码排序
+    recipient_infos.sort();
+    let recipient_infos = der(TAG_SET, &recipient_infos.iter().map(Vec::as_slice).collect::<Vec<_>>());
+
+    // ContentInfo { envelopedData, [0] EnvelopedData { 0, RecipientInfos, EncryptedContentInfo { data, 算法, [0] 密文 } } }
+    let prefix = header(0x80, ciphertext_len);
+    let prefix = wrap(TAG_SEQUENCE, &[&der(TAG_OID, &[OID_DATA]), &cipher.algorithm_identifier(iv), &prefix], ciphertext_len);
+    let prefix = wrap(TAG_SEQUENCE, &[&der(TAG_INTEGER, &[&[0]]), &recipient_infos, &prefix], ciphertext_len);
+    let prefix = wrap(0xA0, &[&prefix], ciphertext_len);
+    wrap(TAG_SEQUENCE, &[&der(TAG_OID, &[OID_ENVELOPED_DATA]), &prefix], ciphertext_len)
+}
+
+/// 把文件加密为 CMS EnvelopedData（RFC 5652），每个证书对应一个 KeyTransRecipientInfo
+///
+/// 输出可以用 `openssl cms -decrypt` 或 S/MIME 客户端以任一接收方的私钥解密。内容长度在加密前就已确定，
+/// 因此整个结构都以 DER 定长编码，文件内容流式加密，不需要整体读入内存。
+pub fn encrypt_file(input_path: &Target, output_path: &Target, recipients: &[Recipient], transport: KeyTransport, cipher: ContentCipher, format: OutputFormat, control: &JobControl) -> Result<CmsReport, CodedError> {
+    if recipients.is_empty() {
+        return Err(CodedError::invalid_argument("At least one recipient certificate is required"));
+    }
+    let content_key = ContentKey::generate(cipher);
+
+    let recipient_infos = recipients
+        .iter()
+        .map(|recipient| Ok(key_trans_recipient_info(recipient, transport, &transport.encrypt(&recipient.key, &content_key.key)?)))
+        .collect::<Result<Vec<_>, CodedError>>()?;
+
+    let mut input = fd::open(input_path).map_err(|e| open_error("opening input file", e))?;
+    let plaintext_size = input
+        .metadata()
+        .map_err(|e| read_error("reading input file metadata", e))?
+        .len();
+    let ciphertext_len = ((plaintext_size / BLOCK_LEN as u64 + 1) * BLOCK_LEN as u64) as usize;
+
+    let prefix = enveloped_data_prefix(recipient_infos, cipher, &content_key.iv, ciphertext_len);
+
+    let output = fd::create(output_path).map_err(|e| open_error("creating output file", e))?;
+    let mut writer = BufWriter::new(output);
+    match format {
+        OutputFormat::Der => content_key.write(&mut writer, &prefix, &mut input, plaintext_size, control)?,
+        OutputFormat::Pem => {
+            let mut pem = PemWriter::new(&mut writer).map_err(|e| write_error("writing output file", e))?;
+            content_key.write(&mut pem, &prefix, &mut input, plaintext_size, control)?;
+            pem.finish().map_err(|e| write_error("writing output file", e))?;
+        }
+    }
+
+    let output = writer
+        .into_inner()
+        .map_err(|e| write_error("writing output file", e.into_error()))?;
+    output.sync_output().map_err(|e| write_error("flushing output file", e))?;
+    let encrypted_size = output
+        .metadata()
+        .map_err(|e| read_error("reading output file metadata", e))?
+        .len();
+    Ok(CmsReport { plaintext_size, encrypted_size })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const PLAINTEXT: &[u8] = b"zippy-encryptor CMS interop test content, long enough for several AES blocks.\n";
+
+    /// 从 EnvelopedData 中取出第一个接收方的 encryptedKey、内容加密的 IV 和密文
+    fn split_enveloped_data(data: &[u8]) -> (&[u8], &[u8], &[u8]) {
+        let (content_info, _) = read_element(data).unwrap();
+        let (_, rest) = read_element(content_info.content).unwrap();
+        let (explicit, _) = read_element(rest).unwrap();
+        let (enveloped, _) = read_element(explicit.content).unwrap();
+        let (_, rest) = read_element(enveloped.content).unwrap();
+        let (recipient_infos, rest) = read_element(rest).unwrap();
+        let (recipient_info, _) = read_element(recipient_infos.content).unwrap();
+        let (_, fields) = read_element(recipient_info.content).unwrap();
+        let (_, fields) = read_element(fields).unwrap();
+        let (_, fields) = read_element(fields).unwrap();
+        let (encrypted_key, _) = read_element(fields).unwrap();
+        let (encrypted_content_info, _) = read_element(rest).unwrap();
+        let (_, fields) = read_element(encrypted_content_info.content).unwrap();
+        let (algorithm, fields) = read_element(fields).unwrap();
+        let (_, iv) = read_element(algorithm.content).unwrap();
+        let (iv, _) = read_element(iv).unwrap();
+        let (ciphertext, _) = read_element(fields).unwrap();
+        (encrypted_key.content, iv.content, ciphertext.content)
+    }
+
+    fn recipient() -> Recipient {
+        Recipient::from_certificate(include_bytes!("../testdata/cms/recipient.pem")).unwrap()
+    }
+
+    #[test]
+    fn matches_openssl_cms_byte_for_byte() {
+        // openssl cms -encrypt -binary -aes128 -keyopt rsa_padding_mode:oaep -keyopt rsa_oaep_md:sha256 -keyopt rsa_mgf1_md:sha256 -outform DER，
+        // 接收方证书为 testdata/rsa/key.jwk 的自签名证书；用私钥解出内容加密密钥后，以相同的密钥、IV 和 encryptedKey 重新生成应得到完全相同的文件
+        let expected = include_bytes!("../testdata/cms/oaep-sha256-aes128.der");
+        let (encrypted_key, iv, ciphertext) = split_enveloped_data(expected);
+        let private_key = crate::rsa::RsaKey::from_jwk(&serde_json::from_str(include_str!("../testdata/rsa/key.jwk")).unwrap()).unwrap();
+        let content_key = private_key.oaep_decrypt(OaepHash::Sha256, encrypted_key).unwrap();
+
+        let transport = KeyTransport::Oaep(OaepHash::Sha256);
+        let recipient_info = key_trans_recipient_info(&recipient(), transport, encrypted_key);
+        let mut output = enveloped_data_prefix(vec![recipient_info], ContentCipher::Aes128Cbc, iv, ciphertext.len());
+        encrypt_content::<Aes128, _, _>(&mut &PLAINTEXT[..], &mut output, &content_key, iv, PLAINTEXT.len() as u64, || Ok(()), |_, _| {}).unwrap();
+        assert_eq!(output, expected);
+    }
+
+    #[test]
+    fn matches_openssl_pkcs1_recipient_info() {
+        // openssl cms -encrypt -binary -aes256 -outform DER（默认的 RSAES-PKCS1-v1_5）
+        let expected = include_bytes!("../testdata/cms/pkcs1-aes256.der");
+        let (encrypted_key, iv, ciphertext) = split_enveloped_data(expected);
+        let recipient_info = key_trans_recipient_info(&recipient(), KeyTransport::Pkcs1, encrypted_key);
+        let mut output = enveloped_data_prefix(vec![recipient_info], ContentCipher::Aes256Cbc, iv, ciphertext.len());
+        output.extend_from_slice(ciphertext);
+        assert_eq!(output, expected);
+    }
+}
@@ -0,0 +1,306 @@
+use std::io::{Read, Write};
+use std::str::FromStr;
+
+use flate2::read::GzDecoder;
+use flate2::write::GzEncoder;
+
+use crate::errors::CodedError;
+
+/// 加密前的压缩算法
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum CompressionMethod {
+    Zstd,
+    Gzip,
+    Lz4,
+}
+
+impl CompressionMethod {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            CompressionMethod::Zstd => "zstd",
+            CompressionMethod::Gzip => "gzip",
+            CompressionMethod::Lz4 => "lz4",
+        }
+    }
+
+    /// 文件头中记录的算法编号
+    pub fn id(&self) -> u8 {
+        match self {
+            CompressionMethod::Zstd => 1,
+            CompressionMethod::Gzip => 2,
+            CompressionMethod::Lz4 => 3,
+        }
+    }
+
+    pub fn from_id(id: u8) -> Option<Self> {
+        match id {
+            1 => Some(CompressionMethod::Zstd),
+            2 => Some(CompressionMethod::Gzip),
+            3 => Some(CompressionMethod::Lz4),
+            _ => None,
+        }
+    }
+
+    fn default_level(&self) -> i32 {
+        match self {
+            CompressionMethod::Zstd => 3,
+            CompressionMethod::Gzip => 6,
+            CompressionMethod::Lz4 => 0,
+        }
+    }
+}
+
+impl FromStr for CompressionMethod {
+    type Err = ();
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_ascii_lowercase().as_str() {
+            "zstd" => Ok(CompressionMethod::Zstd),
+            "gzip" => Ok(CompressionMethod::Gzip),
+            "lz4" => Ok(CompressionMethod::Lz4),
+            _ => Err(()),
+        }
+    }
+}
+
+/// 压缩算法和压缩级别，记录在文件头中，解密时据此解压；格式转换时沿用
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Compression {
+    pub method: CompressionMethod,
+    pub level: i32,
+    /// 每段压缩数据前有一个标记字节，无法压缩的数据不压缩直接保存；早期文件没有标记，所有数据都经过压缩
+    pub adaptive: bool,
+}
+
+/// 文件头中压缩字段的长度：算法编号(u8) + 压缩级别(i32 LE) + 标志位(u8)，早期文件没有标志位
+const COMPRESSION_FIELD_LEN: usize = 6;
+/// 压缩字段标志位：数据前有压缩标记字节
+const COMPRESSION_FLAG_ADAPTIVE: u8 = 0x01;
+
+/// 压缩标记字节：数据未压缩
+const MARKER_STORED: u8 = 0;
+/// 压缩标记字节：数据已压缩
+const MARKER_COMPRESSED: u8 = 1;
+
+/// 估算字节熵时采样的长度
+const ENTROPY_SAMPLE_LEN: usize = 64 * 1024;
+/// 采样的字节熵（比特/字节）超过该值时视为已压缩或加密过的数据，不再尝试压缩
+const MAX_COMPRESSIBLE_ENTROPY: f64 = 7.5;
+
+/// 采样数据的香农熵（比特/字节）
+fn sample_entropy(data: &[u8]) -> f64 {
+    let sample = &data[..data.len().min(ENTROPY_SAMPLE_LEN)];
+    let mut counts = [0usize; 256];
+    for &byte in sample {
+        counts[byte as usize] += 1;
+    }
+    let total = sample.len() as f64;
+    counts
+        .iter()
+        .filter(|&&count| count > 0)
+        .map(|&count| {
+            let p = count as f64 / total;
+            -p * p.log2()
+        })
+        .sum()
+}
+
+impl Compression {
+    /// 检查压缩级别：zstd 为 1-22，gzip 为 0-9，lz4 不支持压缩级别；未指定时使用各算法的默认级别
+    pub fn new(method: CompressionMethod, level: Option<i32>) -> Result<Self, CodedError> {
+        let valid = match (method, level) {
+            (_, None) => true,
+            (CompressionMethod::Zstd, Some(level)) => (1..=22).contains(&level),
+            (CompressionMethod::Gzip, Some(level)) => (0..=9).contains(&level),
+            (CompressionMethod::Lz4, Some(_)) => false,
+        };
+        if !valid {
+            return Err(CodedError::invalid_argument(match method {
+                CompressionMethod::Lz4 => "lz4 does not support compression levels".to_string(),
+                _ => format!("Invalid {} compression level: {}", method.as_str(), level.unwrap_or_default()),
+            }));
+        }
+        Ok(Compression { method, level: level.unwrap_or_else(|| method.default_level()), adaptive: true })
+    }
+
+    pub fn to_bytes(&self) -> [u8; COMPRESSION_FIELD_LEN] {
+        let mut bytes = [0u8; COMPRESSION_FIELD_LEN];
+        bytes[0] = self.method.id();
+        bytes[1..5].copy_from_slice(&self.level.to_le_bytes());
+        bytes[5] = if self.adaptive { COMPRESSION_FLAG_ADAPTIVE } else { 0 };
+        bytes
+    }
+
+    pub fn from_bytes(bytes: &[u8]) -> Option<Self> {
+        if bytes.len() != COMPRESSION_FIELD_LEN && bytes.len() != COMPRESSION_FIELD_LEN - 1 {
+            return None;
+        }
+        Some(Compression {
+            method: CompressionMethod::from_id(bytes[0])?,
+            level: i32::from_le_bytes(bytes[1..5].try_into().unwrap()),
+            adaptive: bytes.get(5).is_some_and(|flags| flags & COMPRESSION_FLAG_ADAPTIVE != 0),
+        })
+    }
+
+    /// 压缩一段明文（整个文件或一个分片），返回要加密的数据以及是否实际进行了压缩
+    ///
+    /// 字节熵很高（JPEG、MP4 等已压缩的内容）或压缩后节省不到 2% 的数据不压缩，直接加上标记字节保存。
+    pub fn compress(&self, data: &[u8]) -> Result<(Vec<u8>, bool), CodedError> {
+        if !self.adaptive {
+            return self.compress_raw(data).map(|compressed| (compressed, true));
+        }
+        if sample_entropy(data) <= MAX_COMPRESSIBLE_ENTROPY {
+            let compressed = self.compress_raw(data)?;
+            if compressed.len() < data.len() - data.len() / 50 {
+                let mut framed = Vec::with_capacity(compressed.len() + 1);
+                framed.push(MARKER_COMPRESSED);
+                framed.extend_from_slice(&compressed);
+                return Ok((framed, true));
+            }
+        }
+        let mut stored = Vec::with_capacity(data.len() + 1);
+        stored.push(MARKER_STORED);
+        stored.extend_from_slice(data);
+        Ok((stored, false))
+    }
+
+    fn compress_raw(&self, data: &[u8]) -> Result<Vec<u8>, CodedError> {
+        let compressed = match self.method {
+            CompressionMethod::Zstd => zstd::bulk::compress(data, self.level).map_err(|e| e.to_string()),
+            CompressionMethod::Gzip => {
+                let mut encoder = GzEncoder::new(Vec::new(), flate2::Compression::new(self.level as u32));
+                encoder.write_all(data).and_then(|_| encoder.finish()).map_err(|e| e.to_string())
+            }
+            CompressionMethod::Lz4 => Ok(lz4_flex::block::compress(data)),
+        };
+        compressed.map_err(|e| CodedError::unknown(format!("Compression error: {}", e)))
+    }
+
+    /// 解压一段明文，解压结果必须恰好为 expected_len 字节；解压时不会产生超过该长度的输出，防止压缩炸弹
+    pub fn decompress(&self, data: &[u8], expected_len: usize) -> Result<Vec<u8>, CodedError> {
+        let data = match (self.adaptive, data.split_first()) {
+            (false, _) => data,
+            (true, Some((&MARKER_COMPRESSED, compressed))) => compressed,
+            (true, Some((&MARKER_STORED, stored))) if stored.len() == expected_len => return Ok(stored.to_vec()),
+            (true, Some((&MARKER_STORED, stored))) => {
+                return Err(CodedError::bad_format(format!("Decompression error: expected {} bytes, got {}", expected_len, stored.len())));
+            }
+            _ => return Err(CodedError::bad_format("Decompression error: invalid compression marker")),
+        };
+        let decompressed = match self.method {
+            CompressionMethod::Zstd => zstd::bulk::decompress(data, expected_len).map_err(|e| e.to_string()),
+            CompressionMethod::Gzip => {
+                let mut decompressed = Vec::with_capacity(expected_len);
+                GzDecoder::new(data)
+                    .take(expected_len as u64 + 1)
+                    .read_to_end(&mut decompressed)
+                    .map(|_| decompressed)
+                    .map_err(|e| e.to_string())
+            }
+            CompressionMethod::Lz4 => lz4_flex::block::decompress(data, expected_len).map_err(|e| e.to_string()),
+        };
+        let decompressed = decompressed.map_err(|e| CodedError::bad_format(format!("Decompression error: {}", e)))?;
+        if decompressed.len() != expected_len {
+            return Err(CodedError::bad_format(format!(
+                "Decompression error: expected {} bytes, got {}", expected_len, decompressed.len()
+            )));
+        }
+        Ok(decompressed)
+    }
+}
+
+/// 压缩后数据的最大长度：不可压缩的数据经过压缩会略微变大，三种算法的额外开销（含标记字节）都在此范围内
+pub fn max_compressed_len(len: usize) -> usize {
+    len.saturating_add(len / 64).saturating_add(256)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::errors::ErrorCode;
+    use rand::RngCore;
+
+    const METHODS: [CompressionMethod; 3] = [CompressionMethod::Zstd, CompressionMethod::Gzip, CompressionMethod::Lz4];
+
+    fn text() -> Vec<u8> {
+        b"the quick brown fox jumps over the lazy dog\n".repeat(500)
+    }
+
+    fn random(len: usize) -> Vec<u8> {
+        let mut data = vec![0u8; len];
+        rand::thread_rng().fill_bytes(&mut data);
+        data
+    }
+
+    #[test]
+    fn round_trips_compressible_data() {
+        let data = text();
+        for method in METHODS {
+            for adaptive in [true, false] {
+                let compression = Compression { adaptive, ..Compression::new(method, None).unwrap() };
+                let (compressed, was_compressed) = compression.compress(&data).unwrap();
+                assert!(was_compressed, "{:?}", method);
+                assert!(compressed.len() < data.len() / 10);
+                assert!(compressed.len() <= max_compressed_len(data.len()));
+                assert_eq!(compression.decompress(&compressed, data.len()).unwrap(), data);
+            }
+        }
+    }
+
+    #[test]
+    fn stores_incompressible_data() {
+        let data = random(100_000);
+        for method in METHODS {
+            let compression = Compression::new(method, None).unwrap();
+            let (stored, was_compressed) = compression.compress(&data).unwrap();
+            assert!(!was_compressed);
+            assert_eq!((stored[0], &stored[1..]), (MARKER_STORED, &data[..]));
+            assert_eq!(compression.decompress(&stored, data.len()).unwrap(), data);
+
+            let raw = Compression { adaptive: false, ..compression }.compress(&data).unwrap().0;
+            assert!(raw.len() <= max_compressed_len(data.len()), "{:?}", method);
+        }
+        let (stored, _) = Compression::new(CompressionMethod::Zstd, None).unwrap().compress(b"").unwrap();
+        assert_eq!(stored, [MARKER_STORED]);
+    }
+
+    #[test]
+    fn validates_levels() {
+        assert_eq!(Compression::new(CompressionMethod::Zstd, None).unwrap().level, 3);
+        assert!(Compression::new(CompressionMethod::Zstd, Some(22)).is_ok());
+        assert!(Compression::new(CompressionMethod::Gzip, Some(0)).is_ok());
+        for (method, level) in [(CompressionMethod::Zstd, 0), (CompressionMethod::Zstd, 23), (CompressionMethod::Gzip, 10), (CompressionMethod::Gzip, -1), (CompressionMethod::Lz4, 1)] {
+            assert_eq!(Compression::new(method, Some(level)).unwrap_err().code, ErrorCode::InvalidArgument);
+        }
+        assert_eq!("ZSTD".parse(), Ok(CompressionMethod::Zstd));
+        assert!("brotli".parse::<CompressionMethod>().is_err());
+    }
+
+    #[test]
+    fn parses_the_header_field() {
+        let compression = Compression::new(CompressionMethod::Gzip, Some(9)).unwrap();
+        assert_eq!(Compression::from_bytes(&compression.to_bytes()), Some(compression));
+        // 早期文件没有标志位
+        let legacy = Compression::from_bytes(&compression.to_bytes()[..5]).unwrap();
+        assert!(!legacy.adaptive);
+        assert_eq!(Compression::from_bytes(&[9, 0, 0, 0, 0, 1]), None);
+        assert_eq!(Compression::from_bytes(&[1, 0, 0, 0]), None);
+        assert_eq!(Compression::from_bytes(&[1, 0, 0, 0, 0, 1, 0]), None);
+    }
+
+    #[test]
+    fn rejects_wrong_lengths_and_corrupt_data() {
+        let data = text();
+        for method in METHODS {
+            let compression = Compression::new(method, None).unwrap();
+            let (compressed, _) = compression.compress(&data).unwrap();
+            // 解压结果超出记录的长度时报错，不会产生更多输出
+            assert_eq!(compression.decompress(&compressed, data.len() - 1).unwrap_err().code, ErrorCode::BadFormat);
+            assert_eq!(compression.decompress(&compressed, data.len() + 1).unwrap_err().code, ErrorCode::BadFormat);
+            assert!(compression.decompress(&compressed[..compressed.len() / 2], data.len()).is_err());
+            assert_eq!(compression.decompress(&[7, 1, 2], 2).unwrap_err().code, ErrorCode::BadFormat);
+            assert_eq!(compression.decompress(&[], 0).unwrap_err().code, ErrorCode::BadFormat);
+            assert_eq!(compression.decompress(&[MARKER_STORED, 1, 2], 3).unwrap_err().code, ErrorCode::BadFormat);
+        }
+    }
+}
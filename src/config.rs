@@ -0,0 +1,329 @@
+use std::collections::HashMap;
+use std::fmt::Display;
+use std::str::FromStr;
+use std::sync::{OnceLock, RwLock};
+
+use crate::crypto::CryptoAlgorithm;
+use crate::errors::CodedError;
+use crate::format::{ContainerHeader, Layout};
+
+/// 指定配置文件路径的环境变量
+pub const CONFIG_PATH_ENV: &str = "ZIPPY_CONFIG";
+
+/// 违反解密策略时的错误码
+pub const ERR_POLICY: &str = "ERR_POLICY";
+
+/// 首次使用时读取的配置；读取失败时保存错误，之后每次使用都会返回该错误
+static CONFIG: OnceLock<Result<Config, CodedError>> = OnceLock::new();
+/// 运行时通过 setDecryptPolicy() 收紧的解密策略，与配置中的策略合并生效
+static POLICY_OVERRIDE: RwLock<DecryptPolicy> = RwLock::new(DecryptPolicy {
+    reject_legacy_formats: false,
+    require_authentication: false,
+});
+
+#[derive(Clone, Copy, PartialEq, PartialOrd)]
+pub enum LogLevel {
+    Off,
+    Error,
+    Warn,
+    Info,
+    Debug,
+}
+
+impl LogLevel {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            LogLevel::Off => "off",
+            LogLevel::Error => "error",
+            LogLevel::Warn => "warn",
+            LogLevel::Info => "info",
+            LogLevel::Debug => "debug",
+        }
+    }
+}
+
+impl FromStr for LogLevel {
+    type Err = ();
+
+    fn from_str(input: &str) -> Result<Self, Self::Err> {
+        match input.to_lowercase().as_str() {
+            "off" | "none" => Ok(LogLevel::Off),
+            "error" => Ok(LogLevel::Error),
+            "warn" | "warning" => Ok(LogLevel::Warn),
+            "info" => Ok(LogLevel::Info),
+            "debug" => Ok(LogLevel::Debug),
+            _ => Err(()),
+        }
+    }
+}
+
+/// 解密时的格式降级保护策略，防止加固部署被诱导解析旧的未认证格式
+#[derive(Clone, Copy, Default)]
+pub struct DecryptPolicy {
+    /// 拒绝没有容器文件头的旧格式（v1 `CHUNKS:` 分片文件和旧整体加密文件）
+    pub reject_legacy_formats: bool,
+    /// 拒绝任何未认证的内容：旧格式，以及内容没有认证的 AES-CBC 整体加密文件（v2 文件头和分片总是带认证，读取时已检查）
+    pub require_authentication: bool,
+}
+
+impl DecryptPolicy {
+    /// 合并两个策略，任一方开启的限制都生效
+    pub fn merge(&self, other: &DecryptPolicy) -> DecryptPolicy {
+        DecryptPolicy {
+            reject_legacy_formats: self.reject_legacy_formats || other.reject_legacy_formats,
+            require_authentication: self.require_authentication || other.require_authentication,
+        }
+    }
+
+    /// 检查待解密文件的格式，container 为 None 表示旧格式文件
+    pub fn check(&self, container: Option<&ContainerHeader>, algorithm: &CryptoAlgorithm) -> Result<(), CodedError> {
+        let container = match container {
+            Some(container) => container,
+            None if self.reject_legacy_formats || self.require_authentication => {
+                return Err(CodedError::policy(format!("{}: legacy formats without a container header are not allowed", ERR_POLICY)));
+            }
+            None => return Ok(()),
+        };
+        if !self.require_authentication {
+            return Ok(());
+        }
+        if container.layout == Layout::Monolithic && *algorithm == CryptoAlgorithm::Aes {
+            return Err(CodedError::policy(format!(
+                "{}: {} {} content is not authenticated", ERR_POLICY, container.layout.as_str(), algorithm.as_str()
+            )));
+        }
+        Ok(())
+    }
+
+    /// 检查没有任何认证的外部格式（如 OpenSSL `enc` 文件），format 为格式名称
+    pub fn check_unauthenticated(&self, format: &str) -> Result<(), CodedError> {
+        if self.require_authentication {
+            return Err(CodedError::policy(format!("{}: {} content is not authenticated", ERR_POLICY, format)));
+        }
+        Ok(())
+    }
+}
+
+/// 模块级默认配置，首次使用时从配置文件和 `ZIPPY_*` 环境变量读取
+#[derive(Clone)]
+pub struct Config {
+    /// 批量操作未指定 concurrency 时的工作线程数，0 表示按 CPU 核数自动选择
+    pub threads: usize,
+    /// 严格模式：对可疑但可恢复的输入直接报错
+    pub strict: bool,
+    pub log_level: LogLevel,
+    /// 允许使用的算法，为空表示全部允许
+    pub allowed_algorithms: Vec<CryptoAlgorithm>,
+    pub decrypt_policy: DecryptPolicy,
+}
+
+impl Default for Config {
+    fn default() -> Self {
+        Config {
+            threads: 0,
+            strict: false,
+            log_level: LogLevel::Warn,
+            allowed_algorithms: Vec::new(),
+            decrypt_policy: DecryptPolicy::default(),
+        }
+    }
+}
+
+impl Config {
+    /// 先读取配置文件，再用环境变量覆盖
+    pub fn load() -> Result<Self, CodedError> {
+        let mut config = Config::default();
+
+        if let Ok(path) = std::env::var(CONFIG_PATH_ENV) {
+            let content = std::fs::read_to_string(&path)
+                .map_err(|e| CodedError::io_read(format!("Failed to read config file {}: {}", path, e)))?;
+            for (key, value) in parse_config_file(&content)? {
+                config.apply(&key, &value)?;
+            }
+        }
+
+        for (name, value) in std::env::vars() {
+            if let Some(key) = name.strip_prefix("ZIPPY_") {
+                if name == CONFIG_PATH_ENV {
+                    continue;
+                }
+                config.apply(&key.to_lowercase(), &value)?;
+            }
+        }
+
+        Ok(config)
+    }
+
+    fn apply(&mut self, key: &str, value: &str) -> Result<(), CodedError> {
+        let value = value.trim();
+        match key {
+            "threads" => {
+                self.threads = value
+                    .parse()
+                    .map_err(|_| CodedError::invalid_argument(format!("Invalid threads value: {}", value)))?;
+            }
+            "strict" => {
+                self.strict = parse_bool(value).ok_or_else(|| CodedError::invalid_argument(format!("Invalid strict value: {}", value)))?;
+            }
+            "log_level" => {
+                self.log_level = LogLevel::from_str(value).map_err(|_| CodedError::invalid_argument(format!("Invalid log level: {}", value)))?;
+            }
+            "allowed_algorithms" => {
+                let mut allowed = Vec::new();
+                for name in value.split(',').map(str::trim).filter(|name| !name.is_empty()) {
+                    let algo = CryptoAlgorithm::from_str(name)
+                        .map_err(|_| CodedError::invalid_argument(format!("Invalid algorithm in allowed_algorithms: {}", name)))?;
+                    allowed.push(algo);
+                }
+                self.allowed_algorithms = allowed;
+            }
+            "reject_legacy_formats" => {
+                self.decrypt_policy.reject_legacy_formats = parse_bool(value)
+                    .ok_or_else(|| CodedError::invalid_argument(format!("Invalid reject_legacy_formats value: {}", value)))?;
+            }
+            "require_authentication" => {
+                self.decrypt_policy.require_authentication = parse_bool(value)
+                    .ok_or_else(|| CodedError::invalid_argument(format!("Invalid require_authentication value: {}", value)))?;
+            }
+            // 未知的键直接忽略，便于旧版本读取新版本的配置
+            _ => {}
+        }
+        Ok(())
+    }
+
+    pub fn algorithm_allowed(&self, algorithm: &CryptoAlgorithm) -> bool {
+        self.allowed_algorithms.is_empty() || self.allowed_algorithms.contains(algorithm)
+    }
+}
+
+fn parse_bool(value: &str) -> Option<bool> {
+    match value.to_lowercase().as_str() {
+        "1" | "true" | "yes" | "on" => Some(true),
+        "0" | "false" | "no" | "off" => Some(false),
+        _ => None,
+    }
+}
+
+/// 解析 `key = value` 格式的配置文件，`#` 开头的行为注释
+fn parse_config_file(content: &str) -> Result<HashMap<String, String>, CodedError> {
+    let mut entries = HashMap::new();
+    for (line_no, line) in content.lines().enumerate() {
+        let line = line.trim();
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+        let (key, value) = line
+            .split_once('=')
+            .ok_or_else(|| CodedError::bad_format(format!("Invalid config line {}: {}", line_no + 1, line)))?;
+        let key = key.trim().to_lowercase();
+        let key = key.strip_prefix("zippy_").unwrap_or(&key).to_string();
+        entries.insert(key, value.trim().trim_matches('"').to_string());
+    }
+    Ok(entries)
+}
+
+/// 获取当前生效的配置，首次调用时读取；配置有误时返回错误，而不是静默使用默认值
+pub fn get() -> Result<&'static Config, CodedError> {
+    CONFIG
+        .get_or_init(|| Config::load().map_err(|err| err.context("Invalid configuration")))
+        .as_ref()
+        .map_err(CodedError::clone)
+}
+
+/// 配置中是否允许该算法，配置有误时不允许任何算法
+pub fn algorithm_allowed(algorithm: &CryptoAlgorithm) -> bool {
+    get().is_ok_and(|config| config.algorithm_allowed(algorithm))
+}
+
+/// 当前生效的解密策略：配置中的策略与运行时收紧的策略合并；配置有误时开启全部限制
+pub fn decrypt_policy() -> DecryptPolicy {
+    configured_policy(get()).merge(&POLICY_OVERRIDE.read().unwrap())
+}
+
+/// 在运行时收紧解密策略，已开启的限制不能再关闭
+pub fn tighten_decrypt_policy(policy: DecryptPolicy) -> DecryptPolicy {
+    let mut current = POLICY_OVERRIDE.write().unwrap();
+    *current = current.merge(&policy);
+    configured_policy(get()).merge(&current)
+}
+
+/// 配置中的解密策略，配置有误时开启全部限制
+fn configured_policy(config: Result<&Config, CodedError>) -> DecryptPolicy {
+    config.map_or(DecryptPolicy { reject_legacy_formats: true, require_authentication: true }, |config| config.decrypt_policy)
+}
+
+/// 按配置的日志级别输出到 stderr，配置有误时按默认级别
+pub fn log(level: LogLevel, message: impl Display) {
+    let configured = get().map_or(LogLevel::Warn, |config| config.log_level);
+    if level != LogLevel::Off && level <= configured {
+        eprintln!("[zippy] {}: {}", level.as_str(), message);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::errors::ErrorCode;
+
+    #[test]
+    fn parses_config_file() {
+        let entries = parse_config_file("# comment\n\nZIPPY_STRICT = true\nlog_level=\"debug\"\n").unwrap();
+        assert_eq!(entries.get("strict").map(String::as_str), Some("true"));
+        assert_eq!(entries.get("log_level").map(String::as_str), Some("debug"));
+        assert_eq!(parse_config_file("strict true").unwrap_err().code, ErrorCode::BadFormat);
+    }
+
+    #[test]
+    fn applies_settings() {
+        let mut config = Config::default();
+        config.apply("threads", "3").unwrap();
+        config.apply("strict", "yes").unwrap();
+        config.apply("allowed_algorithms", "aes, chacha20poly1305").unwrap();
+        config.apply("require_authentication", "on").unwrap();
+        config.apply("unknown_key", "whatever").unwrap();
+        assert_eq!(config.threads, 3);
+        assert!(config.strict);
+        assert!(config.algorithm_allowed(&CryptoAlgorithm::Aes));
+        assert!(config.decrypt_policy.require_authentication);
+        assert!(!config.decrypt_policy.reject_legacy_formats);
+
+        config.apply("allowed_algorithms", "chacha20poly1305").unwrap();
+        assert!(!config.algorithm_allowed(&CryptoAlgorithm::Aes));
+    }
+
+    #[test]
+    fn rejects_invalid_values_as_invalid_arguments() {
+        for (key, value) in [
+            ("threads", "many"),
+            ("strict", "maybe"),
+            ("log_level", "loud"),
+            ("allowed_algorithms", "rot13"),
+            ("reject_legacy_formats", "2"),
+            ("require_authentication", "sure"),
+        ] {
+            let err = Config::default().apply(key, value).unwrap_err();
+            assert_eq!(err.code, ErrorCode::InvalidArgument, "{}", key);
+        }
+    }
+
+    #[test]
+    fn invalid_configuration_fails_closed() {
+        let policy = configured_policy(Err(CodedError::invalid_argument("Invalid strict value: maybe")));
+        assert!(policy.reject_legacy_formats);
+        assert!(policy.require_authentication);
+        assert_eq!(policy.check(None, &CryptoAlgorithm::Aes).unwrap_err().code, ErrorCode::Policy);
+        assert_eq!(policy.check_unauthenticated("OpenSSL enc").unwrap_err().code, ErrorCode::Policy);
+
+        let policy = configured_policy(Ok(&Config::default()));
+        assert!(policy.check(None, &CryptoAlgorithm::Aes).is_ok());
+    }
+
+    #[test]
+    fn merged_policy_keeps_every_restriction() {
+        let legacy = DecryptPolicy { reject_legacy_formats: true, require_authentication: false };
+        let auth = DecryptPolicy { reject_legacy_formats: false, require_authentication: true };
+        let merged = legacy.merge(&auth);
+        assert!(merged.reject_legacy_formats && merged.require_authentication);
+        assert!(!DecryptPolicy::default().merge(&DecryptPolicy::default()).require_authentication);
+    }
+}
@@ -1,13 +1,27 @@
-use aes::Aes256;
-use block_modes::BlockMode;
-use block_modes::Cbc;
-use block_padding::Pkcs7;
-use chacha20poly1305::{ChaCha20Poly1305, Key, Nonce};
-use chacha20poly1305::aead::{Aead, NewAead};
+use aes::cipher::{BlockDecrypt, BlockEncrypt};
+use aes::{Aes128, Aes192, Aes256};
+use cbc::cipher::block_padding::Pkcs7;
+use cbc::cipher::{BlockDecryptMut, BlockEncryptMut, KeyIvInit};
+use chacha20::cipher::{StreamCipher, StreamCipherSeek};
+use chacha20::ChaCha20;
+use chacha20poly1305::{ChaCha20Poly1305, Key};
+use chacha20poly1305::aead::{Aead, AeadInPlace, KeyInit, Payload};
+use hkdf::Hkdf;
+use hmac::{Hmac, Mac};
+use poly1305::universal_hash::UniversalHash;
+use poly1305::Poly1305;
 use rand::RngCore;
+use sha2::{Digest, Sha256};
 use std::str::FromStr;
 
-type Aes256Cbc = Cbc<Aes256, Pkcs7>;
+use crate::errors::CodedError;
+
+type Aes256CbcEnc = cbc::Encryptor<Aes256>;
+type Aes256CbcDec = cbc::Decryptor<Aes256>;
+type HmacSha256 = Hmac<Sha256>;
+
+/// 文件头认证标签长度（HMAC-SHA256）
+pub const HEADER_MAC_LEN: usize = 32;
 
 #[derive(Clone, PartialEq)]
 pub enum CryptoAlgorithm {
@@ -15,6 +29,39 @@ pub enum CryptoAlgorithm {
     Chacha20Poly1305,
 }
 
+impl CryptoAlgorithm {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            CryptoAlgorithm::Aes => "aes",
+            CryptoAlgorithm::Chacha20Poly1305 => "chacha20poly1305",
+        }
+    }
+
+    /// 写入容器文件头的算法编号
+    pub fn id(&self) -> u8 {
+        match self {
+            CryptoAlgorithm::Aes => 1,
+            CryptoAlgorithm::Chacha20Poly1305 => 2,
+        }
+    }
+
+    /// 每次加密随机生成的 IV / nonce 长度：AES-256-CBC 的 IV 为 16 字节，ChaCha20Poly1305 的 nonce 为 12 字节
+    pub fn nonce_len(&self) -> usize {
+        match self {
+            CryptoAlgorithm::Aes => 16,
+            CryptoAlgorithm::Chacha20Poly1305 => 12,
+        }
+    }
+
+    pub fn from_id(id: u8) -> Option<Self> {
+        match id {
+            1 => Some(CryptoAlgorithm::Aes),
+            2 => Some(CryptoAlgorithm::Chacha20Poly1305),
+            _ => None,
+        }
+    }
+}
+
 impl FromStr for CryptoAlgorithm {
     type Err = ();
 
@@ -27,81 +74,829 @@ impl FromStr for CryptoAlgorithm {
     }
 }
 
-pub fn encrypt(algorithm: CryptoAlgorithm, key: &[u8], data: &[u8]) -> Result<Vec<u8>, String> {
+/// 明文摘要算法
+#[derive(Clone, Copy, PartialEq)]
+pub enum HashAlgorithm {
+    Sha256,
+    Blake3,
+}
+
+impl HashAlgorithm {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            HashAlgorithm::Sha256 => "sha256",
+            HashAlgorithm::Blake3 => "blake3",
+        }
+    }
+
+    /// 写入容器文件头的摘要算法编号
+    pub fn id(&self) -> u8 {
+        match self {
+            HashAlgorithm::Sha256 => 1,
+            HashAlgorithm::Blake3 => 2,
+        }
+    }
+
+    pub fn from_id(id: u8) -> Option<Self> {
+        match id {
+            1 => Some(HashAlgorithm::Sha256),
+            2 => Some(HashAlgorithm::Blake3),
+            _ => None,
+        }
+    }
+}
+
+impl FromStr for HashAlgorithm {
+    type Err = ();
+
+    fn from_str(input: &str) -> Result<Self, Self::Err> {
+        match input.to_lowercase().as_str() {
+            "sha256" | "sha-256" => Ok(HashAlgorithm::Sha256),
+            "blake3" => Ok(HashAlgorithm::Blake3),
+            _ => Err(()),
+        }
+    }
+}
+
+/// 按所选算法计算明文摘要
+pub enum PlaintextHasher {
+    Sha256(Sha256),
+    Blake3(Box<blake3::Hasher>),
+}
+
+impl PlaintextHasher {
+    pub fn new(algorithm: HashAlgorithm) -> Self {
+        match algorithm {
+            HashAlgorithm::Sha256 => PlaintextHasher::Sha256(Sha256::new()),
+            HashAlgorithm::Blake3 => PlaintextHasher::Blake3(Box::new(blake3::Hasher::new())),
+        }
+    }
+
+    pub fn update(&mut self, data: &[u8]) {
+        match self {
+            PlaintextHasher::Sha256(hasher) => hasher.update(data),
+            PlaintextHasher::Blake3(hasher) => {
+                hasher.update(data);
+            }
+        }
+    }
+
+    pub fn finalize(self) -> Vec<u8> {
+        match self {
+            PlaintextHasher::Sha256(hasher) => hasher.finalize().to_vec(),
+            PlaintextHasher::Blake3(hasher) => hasher.finalize().as_bytes().to_vec(),
+        }
+    }
+
+    /// 一次计算整段数据的摘要
+    pub fn digest(algorithm: HashAlgorithm, data: &[u8]) -> Vec<u8> {
+        let mut hasher = PlaintextHasher::new(algorithm);
+        hasher.update(data);
+        hasher.finalize()
+    }
+}
+
+/// 从加密使用的同一个随机数生成器（以操作系统熵源播种的 CSPRNG）取得随机字节
+pub fn random_bytes(len: usize) -> Vec<u8> {
+    let mut bytes = vec![0u8; len];
+    rand::thread_rng().fill_bytes(&mut bytes);
+    bytes
+}
+
+/// 按密钥长度选择的 AES-128/192/256 分组密码，供直接在单个分组上构造的模式（OCB、AES 密钥包装）使用
+pub enum AesBlockCipher {
+    Aes128(Aes128),
+    Aes192(Aes192),
+    Aes256(Aes256),
+}
+
+impl AesBlockCipher {
+    /// 密钥不是 16、24 或 32 字节时返回 None
+    pub fn new(key: &[u8]) -> Option<Self> {
+        match key.len() {
+            16 => Aes128::new_from_slice(key).ok().map(AesBlockCipher::Aes128),
+            24 => Aes192::new_from_slice(key).ok().map(AesBlockCipher::Aes192),
+            32 => Aes256::new_from_slice(key).ok().map(AesBlockCipher::Aes256),
+            _ => None,
+        }
+    }
+
+    pub fn encrypt_block(&self, block: &mut [u8; 16]) {
+        match self {
+            AesBlockCipher::Aes128(cipher) => cipher.encrypt_block(block.into()),
+            AesBlockCipher::Aes192(cipher) => cipher.encrypt_block(block.into()),
+            AesBlockCipher::Aes256(cipher) => cipher.encrypt_block(block.into()),
+        }
+    }
+
+    pub fn decrypt_block(&self, block: &mut [u8; 16]) {
+        match self {
+            AesBlockCipher::Aes128(cipher) => cipher.decrypt_block(block.into()),
+            AesBlockCipher::Aes192(cipher) => cipher.decrypt_block(block.into()),
+            AesBlockCipher::Aes256(cipher) => cipher.decrypt_block(block.into()),
+        }
+    }
+}
+
+pub fn encrypt(algorithm: CryptoAlgorithm, key: &[u8], data: &[u8]) -> Result<Vec<u8>, CodedError> {
+    let mut result = Vec::new();
+    encrypt_into(&algorithm, key, data, &mut result)?;
+    Ok(result)
+}
+
+/// 与 encrypt 相同，但写入调用方提供的缓冲区（先清空），逐个分片加密时复用同一个缓冲区，不必每次重新分配
+pub fn encrypt_into(algorithm: &CryptoAlgorithm, key: &[u8], data: &[u8], out: &mut Vec<u8>) -> Result<(), CodedError> {
     match algorithm {
         CryptoAlgorithm::Aes => {
             if key.len() != 32 {
-                return Err("AES key must be 32 bytes (256 bits)".to_string());
+                return Err(CodedError::invalid_key_length("AES key must be 32 bytes (256 bits)"));
             }
             let mut iv = [0u8; 16];
             let mut rng = rand::rngs::ThreadRng::default();
             rng.fill_bytes(&mut iv);
 
-            let cipher = Aes256Cbc::new_from_slices(key, &iv)
-                .map_err(|e| format!("AES cipher init failed: {:?}", e))?;
-            let ciphertext = cipher.encrypt_vec(data);
-
-            let mut result = iv.to_vec();
-            result.extend_from_slice(&ciphertext);
-            Ok(result)
+            let cipher = Aes256CbcEnc::new_from_slices(key, &iv)
+                .map_err(|e| CodedError::unknown(format!("AES cipher init failed: {:?}", e)))?;
+            // PKCS#7 填充后的长度：至少填充 1 字节，补齐到块大小的倍数
+            let padded_len = (data.len() / 16 + 1) * 16;
+            out.clear();
+            out.extend_from_slice(&iv);
+            out.extend_from_slice(data);
+            out.resize(16 + padded_len, 0);
+            cipher.encrypt_padded_mut::<Pkcs7>(&mut out[16..], data.len())
+                .map_err(|e| CodedError::unknown(format!("AES encrypt failed: {:?}", e)))?;
+            Ok(())
         }
         CryptoAlgorithm::Chacha20Poly1305 => {
             if key.len() != 32 {
-                return Err("ChaCha20Poly1305 key must be 32 bytes (256 bits)".to_string());
+                return Err(CodedError::invalid_key_length("ChaCha20Poly1305 key must be 32 bytes (256 bits)"));
             }
 
-            let cipher = ChaCha20Poly1305::new(Key::from_slice(key));
-            let mut nonce = [0u8; 12];
-            let mut rng = rand::rngs::ThreadRng::default();
-            rng.fill_bytes(&mut nonce);
-
-            let ciphertext = cipher.encrypt(Nonce::from_slice(&nonce), data)
-                .map_err(|e| format!("ChaCha20Poly1305 encrypt failed: {:?}", e))?;
-
-            let mut result = nonce.to_vec();
-            result.extend_from_slice(&ciphertext);
-            Ok(result)
+            chacha_encrypt(key, data, b"", out)
         }
     }
 }
 
-pub fn decrypt(algorithm: CryptoAlgorithm, key: &[u8], data: &[u8]) -> Result<Vec<u8>, String> {
+/// ChaCha20Poly1305 加密，输出 nonce(12) + 密文 + 认证标签
+fn chacha_encrypt(key: &[u8], data: &[u8], aad: &[u8], out: &mut Vec<u8>) -> Result<(), CodedError> {
+    let cipher = ChaCha20Poly1305::new_from_slice(key)
+        .map_err(|_| CodedError::invalid_key_length("ChaCha20Poly1305 key must be 32 bytes (256 bits)"))?;
+    let mut nonce = [0u8; 12];
+    let mut rng = rand::rngs::ThreadRng::default();
+    rng.fill_bytes(&mut nonce);
+
+    out.clear();
+    out.extend_from_slice(&nonce);
+    out.extend_from_slice(data);
+    let tag = cipher.encrypt_in_place_detached(&nonce.into(), aad, &mut out[12..])
+        .map_err(|e| CodedError::unknown(format!("ChaCha20Poly1305 encrypt failed: {:?}", e)))?;
+    out.extend_from_slice(&tag);
+    Ok(())
+}
+
+pub fn decrypt(algorithm: CryptoAlgorithm, key: &[u8], data: &[u8]) -> Result<Vec<u8>, CodedError> {
+    let mut result = Vec::new();
+    decrypt_into(&algorithm, key, data, &mut result)?;
+    Ok(result)
+}
+
+/// 与 decrypt 相同，但写入调用方提供的缓冲区（先清空）
+pub fn decrypt_into(algorithm: &CryptoAlgorithm, key: &[u8], data: &[u8], out: &mut Vec<u8>) -> Result<(), CodedError> {
     match algorithm {
         CryptoAlgorithm::Aes => {
             if key.len() != 32 {
-                return Err("AES key must be 32 bytes (256 bits)".to_string());
+                return Err(CodedError::invalid_key_length("AES key must be 32 bytes (256 bits)"));
             }
             if data.len() < 16 {
-                return Err(format!("Invalid AES data: length {} is less than 16", data.len()));
+                return Err(CodedError::bad_format(format!("Invalid AES data: length {} is less than 16", data.len())));
             }
             
             let (iv, ciphertext) = data.split_at(16);
             
             // 确保密文长度是块大小的倍数
             if ciphertext.len() % 16 != 0 {
-                return Err(format!("Invalid AES ciphertext length: {}. Must be multiple of 16", ciphertext.len()));
+                return Err(CodedError::invalid_argument(format!("Invalid AES ciphertext length: {}. Must be multiple of 16", ciphertext.len())));
             }
 
-            let cipher = Aes256Cbc::new_from_slices(key, iv)
-                .map_err(|e| format!("AES cipher init failed: {:?}", e))?;
+            let cipher = Aes256CbcDec::new_from_slices(key, iv)
+                .map_err(|e| CodedError::unknown(format!("AES cipher init failed: {:?}", e)))?;
 
-            cipher.decrypt_vec(ciphertext)
-                .map_err(|e| format!("AES decrypt failed: {:?}, IV length: {}, ciphertext length: {}", 
-                    e, iv.len(), ciphertext.len()))
+            out.clear();
+            out.extend_from_slice(ciphertext);
+            let len = match cipher.decrypt_padded_mut::<Pkcs7>(out) {
+                Ok(plaintext) => plaintext.len(),
+                Err(e) => {
+                    out.clear();
+                    return Err(CodedError::auth_failed(format!("AES decrypt failed: {:?}, IV length: {}, ciphertext length: {}", 
+                        e, iv.len(), ciphertext.len())));
+                }
+            };
+            out.truncate(len);
+            Ok(())
         }
         CryptoAlgorithm::Chacha20Poly1305 => {
             if key.len() != 32 {
-                return Err("ChaCha20Poly1305 key must be 32 bytes (256 bits)".to_string());
+                return Err(CodedError::invalid_key_length("ChaCha20Poly1305 key must be 32 bytes (256 bits)"));
             }
             if data.len() < 12 {
-                return Err("Invalid ChaCha20Poly1305 data".to_string());
+                return Err(CodedError::bad_format("Invalid ChaCha20Poly1305 data"));
+            }
+            chacha_decrypt(key, data, b"", out)
+                .map_err(|e| CodedError::auth_failed(format!("ChaCha20Poly1305 decrypt failed: {:?}", e)))
+        }
+    }
+}
+
+/// ChaCha20Poly1305 解密 nonce(12) + 密文 + 认证标签；认证失败时清空 out，不留下未经认证的明文
+fn chacha_decrypt(key: &[u8], data: &[u8], aad: &[u8], out: &mut Vec<u8>) -> Result<(), chacha20poly1305::aead::Error> {
+    let (nonce, ciphertext) = data.split_at(12);
+    if ciphertext.len() < POLY1305_TAG_LEN {
+        return Err(chacha20poly1305::aead::Error);
+    }
+    let (ciphertext, tag) = ciphertext.split_at(ciphertext.len() - POLY1305_TAG_LEN);
+    let cipher = ChaCha20Poly1305::new_from_slice(key).map_err(|_| chacha20poly1305::aead::Error)?;
+    out.clear();
+    out.extend_from_slice(ciphertext);
+    cipher.decrypt_in_place_detached(nonce.into(), aad, out, tag.into())
+        .inspect_err(|_| out.clear())
+}
+
+/// 逐段加密，输出与 encrypt 相同：IV/nonce + 密文，ChaCha20Poly1305 末尾再加认证标签
+///
+/// 整体加密大文件时不必把整个文件读入内存。ChaCha20Poly1305 按 RFC 8439 的构造分别使用 ChaCha20 和 Poly1305：
+/// 第 0 个分组的密钥流作为 Poly1305 的密钥，从第 1 个分组开始加密。
+pub enum StreamEncryptor {
+    Aes {
+        cipher: Box<Aes256CbcEnc>,
+        /// 不足一个分组、留到下一次加密的明文
+        pending: Vec<u8>,
+    },
+    Chacha {
+        cipher: ChaCha20,
+        mac: Box<Poly1305>,
+        /// 不足 16 字节、留到下一次计入认证标签的密文
+        mac_pending: Vec<u8>,
+        len: u64,
+    },
+}
+
+impl StreamEncryptor {
+    /// 返回加密器和要写在密文前面的 IV/nonce
+    pub fn new(algorithm: &CryptoAlgorithm, key: &[u8]) -> Result<(Self, Vec<u8>), CodedError> {
+        match algorithm {
+            CryptoAlgorithm::Aes => {
+                if key.len() != 32 {
+                    return Err(CodedError::invalid_key_length("AES key must be 32 bytes (256 bits)"));
+                }
+                let iv = random_bytes(16);
+                let cipher = Aes256CbcEnc::new_from_slices(key, &iv)
+                    .map_err(|e| CodedError::unknown(format!("AES cipher init failed: {:?}", e)))?;
+                Ok((StreamEncryptor::Aes { cipher: Box::new(cipher), pending: Vec::new() }, iv))
+            }
+            CryptoAlgorithm::Chacha20Poly1305 => {
+                if key.len() != 32 {
+                    return Err(CodedError::invalid_key_length("ChaCha20Poly1305 key must be 32 bytes (256 bits)"));
+                }
+                let nonce = random_bytes(12);
+                let (cipher, mac) = chacha_stream(key, &nonce);
+                Ok((StreamEncryptor::Chacha { cipher, mac, mac_pending: Vec::new(), len: 0 }, nonce))
             }
-            let (nonce, ciphertext) = data.split_at(12);
+        }
+    }
 
-            let cipher = ChaCha20Poly1305::new(Key::from_slice(key));
+    /// 加密一段明文，把可以写出的密文追加到 out
+    pub fn update(&mut self, data: &[u8], out: &mut Vec<u8>) {
+        let start = out.len();
+        match self {
+            StreamEncryptor::Aes { cipher, pending } => {
+                out.extend_from_slice(pending);
+                out.extend_from_slice(data);
+                let full = start + (out.len() - start) / 16 * 16;
+                pending.clear();
+                pending.extend_from_slice(&out[full..]);
+                out.truncate(full);
+                for block in out[start..].chunks_exact_mut(16) {
+                    cipher.encrypt_block_mut(block.into());
+                }
+            }
+            StreamEncryptor::Chacha { cipher, mac, mac_pending, len } => {
+                out.extend_from_slice(data);
+                cipher.apply_keystream(&mut out[start..]);
+                mac_update(mac, mac_pending, &out[start..]);
+                *len += data.len() as u64;
+            }
+        }
+    }
 
-            cipher.decrypt(Nonce::from_slice(nonce), ciphertext)
-                .map_err(|e| format!("ChaCha20Poly1305 decrypt failed: {:?}", e))
+    /// 加密剩余的明文（AES 填充最后一个分组），ChaCha20Poly1305 追加认证标签
+    pub fn finalize(self, out: &mut Vec<u8>) -> Result<(), CodedError> {
+        match self {
+            StreamEncryptor::Aes { cipher, pending } => {
+                let mut block = [0u8; 16];
+                block[..pending.len()].copy_from_slice(&pending);
+                cipher.encrypt_padded_mut::<Pkcs7>(&mut block, pending.len())
+                    .map_err(|e| CodedError::unknown(format!("AES encrypt failed: {:?}", e)))?;
+                out.extend_from_slice(&block);
+            }
+            StreamEncryptor::Chacha { mac, mac_pending, len, .. } => {
+                out.extend_from_slice(&mac_finalize(mac, &mac_pending, len));
+            }
         }
+        Ok(())
     }
-}
\ No newline at end of file
+}
+
+/// 逐段解密 StreamEncryptor 或 encrypt 的输出，new 的参数为密文前面的 IV/nonce
+///
+/// AES 留下最后一个分组到 finalize 时去除填充，ChaCha20Poly1305 留下最后 16 字节作为认证标签；
+/// 认证标签要到 finalize 才能校验，之前输出的明文都未经认证，调用方必须在 finalize 成功后才能使用。
+pub enum StreamDecryptor {
+    Aes {
+        cipher: Box<Aes256CbcDec>,
+        /// 尚未解密的密文：最后一个完整分组和不足一个分组的部分
+        pending: Vec<u8>,
+    },
+    Chacha {
+        cipher: ChaCha20,
+        mac: Box<Poly1305>,
+        mac_pending: Vec<u8>,
+        /// 最后 16 字节，输入结束时即为认证标签
+        held: Vec<u8>,
+        len: u64,
+    },
+}
+
+impl StreamDecryptor {
+    pub fn new(algorithm: &CryptoAlgorithm, key: &[u8], prefix: &[u8]) -> Result<Self, CodedError> {
+        if key.len() != 32 {
+            return Err(CodedError::invalid_key_length("Key must be 32 bytes (256 bits)"));
+        }
+        match algorithm {
+            CryptoAlgorithm::Aes => {
+                let cipher = Aes256CbcDec::new_from_slices(key, prefix)
+                    .map_err(|e| CodedError::unknown(format!("AES cipher init failed: {:?}", e)))?;
+                Ok(StreamDecryptor::Aes { cipher: Box::new(cipher), pending: Vec::new() })
+            }
+            CryptoAlgorithm::Chacha20Poly1305 => {
+                if prefix.len() != 12 {
+                    return Err(CodedError::bad_format("Invalid ChaCha20Poly1305 data"));
+                }
+                let (cipher, mac) = chacha_stream(key, prefix);
+                Ok(StreamDecryptor::Chacha { cipher, mac, mac_pending: Vec::new(), held: Vec::new(), len: 0 })
+            }
+        }
+    }
+
+    /// 解密一段密文，把可以输出的明文追加到 out
+    pub fn update(&mut self, data: &[u8], out: &mut Vec<u8>) {
+        let start = out.len();
+        match self {
+            StreamDecryptor::Aes { cipher, pending } => {
+                out.extend_from_slice(pending);
+                out.extend_from_slice(data);
+                let total = out.len() - start;
+                let keep = match total % 16 {
+                    0 => total.min(16),
+                    partial => partial,
+                };
+                pending.clear();
+                pending.extend_from_slice(&out[start + total - keep..]);
+                out.truncate(start + total - keep);
+                for block in out[start..].chunks_exact_mut(16) {
+                    cipher.decrypt_block_mut(block.into());
+                }
+            }
+            StreamDecryptor::Chacha { cipher, mac, mac_pending, held, len } => {
+                out.extend_from_slice(held);
+                out.extend_from_slice(data);
+                let ciphertext_len = (out.len() - start).saturating_sub(POLY1305_TAG_LEN);
+                held.clear();
+                held.extend_from_slice(&out[start + ciphertext_len..]);
+                out.truncate(start + ciphertext_len);
+                mac_update(mac, mac_pending, &out[start..]);
+                cipher.apply_keystream(&mut out[start..]);
+                *len += ciphertext_len as u64;
+            }
+        }
+    }
+
+    /// 输入结束：AES 解密最后一个分组并去除填充，ChaCha20Poly1305 以常数时间校验认证标签
+    pub fn finalize(self, out: &mut Vec<u8>) -> Result<(), CodedError> {
+        match self {
+            StreamDecryptor::Aes { cipher, mut pending } => {
+                if pending.len() != 16 {
+                    return Err(CodedError::invalid_argument(format!("Invalid AES ciphertext length: {} bytes in the last block. Must be multiple of 16", pending.len())));
+                }
+                let plaintext = cipher.decrypt_padded_mut::<Pkcs7>(&mut pending)
+                    .map_err(|e| CodedError::auth_failed(format!("AES decrypt failed: {:?}", e)))?;
+                out.extend_from_slice(plaintext);
+                Ok(())
+            }
+            StreamDecryptor::Chacha { mac, mac_pending, held, len, .. } => {
+                if held.len() != POLY1305_TAG_LEN {
+                    return Err(CodedError::bad_format("Invalid ChaCha20Poly1305 data"));
+                }
+                let expected = mac_finalize(mac, &mac_pending, len);
+                match expected.iter().zip(&held).fold(0u8, |diff, (a, b)| diff | (a ^ b)) {
+                    0 => Ok(()),
+                    _ => Err(CodedError::auth_failed("ChaCha20Poly1305 decrypt failed: Error")),
+                }
+            }
+        }
+    }
+}
+
+/// RFC 8439 的 ChaCha20 和 Poly1305：第 0 个分组的密钥流作为 Poly1305 的密钥，返回定位到第 1 个分组的 ChaCha20
+fn chacha_stream(key: &[u8], nonce: &[u8]) -> (ChaCha20, Box<Poly1305>) {
+    let mut cipher = ChaCha20::new_from_slices(key, nonce).expect("ChaCha20 key is 32 bytes and nonce is 12 bytes");
+    let mut poly_key = [0u8; 32];
+    cipher.apply_keystream(&mut poly_key);
+    cipher.seek(64u64);
+    (cipher, Box::new(Poly1305::new(&poly_key.into())))
+}
+
+/// Poly1305 按 16 字节分组计算，不足一个分组的密文留到下一次
+fn mac_update(mac: &mut Poly1305, pending: &mut Vec<u8>, mut ciphertext: &[u8]) {
+    if !pending.is_empty() {
+        let take = (16 - pending.len()).min(ciphertext.len());
+        pending.extend_from_slice(&ciphertext[..take]);
+        ciphertext = &ciphertext[take..];
+        if pending.len() < 16 {
+            return;
+        }
+        mac.update_padded(pending);
+        pending.clear();
+    }
+    let full = ciphertext.len() / 16 * 16;
+    mac.update_padded(&ciphertext[..full]);
+    pending.extend_from_slice(&ciphertext[full..]);
+}
+
+/// 密文补齐到 16 字节，再加上附加数据（为空）和密文的长度，得到认证标签
+fn mac_finalize(mut mac: Box<Poly1305>, pending: &[u8], len: u64) -> [u8; POLY1305_TAG_LEN] {
+    mac.update_padded(pending);
+    let mut lengths = [0u8; 16];
+    lengths[8..].copy_from_slice(&len.to_le_bytes());
+    mac.update_padded(&lengths);
+    mac.finalize().into()
+}
+
+/// 从文件密钥派生出专用的子密钥，避免与内容加密共用同一个密钥
+fn derive_subkey(key: &[u8], info: &str) -> Key {
+    let hkdf = Hkdf::<Sha256>::new(None, key);
+    let mut subkey = Key::default();
+    hkdf.expand(info.as_bytes(), &mut subkey)
+        .expect("32 bytes is a valid HKDF-SHA256 output length");
+    subkey
+}
+
+/// 从文件密钥派生用于 purpose 的 32 字节子密钥（HKDF-SHA256，无盐）
+pub fn subkey(key: &[u8], purpose: &str) -> [u8; 32] {
+    derive_subkey(key, purpose).into()
+}
+
+/// 分片明文校验值长度
+pub const CHUNK_CHECKSUM_LEN: usize = 8;
+
+/// 分片明文校验值：使用从文件密钥派生的子密钥计算 BLAKE3 keyed hash，取前 8 字节
+///
+/// 校验值记录在分片索引中，随机读取单个分片时可以核对该分片的明文；没有密钥无法用它确认明文内容。
+pub struct ChunkChecksum {
+    key: [u8; 32],
+}
+
+impl ChunkChecksum {
+    pub fn new(key: &[u8]) -> Self {
+        ChunkChecksum { key: derive_subkey(key, "zippy chunk checksum").into() }
+    }
+
+    pub fn compute(&self, data: &[u8]) -> [u8; CHUNK_CHECKSUM_LEN] {
+        let hash = blake3::keyed_hash(&self.key, data);
+        hash.as_bytes()[..CHUNK_CHECKSUM_LEN].try_into().unwrap()
+    }
+}
+
+/// 文件头字段专用的子密钥
+fn header_subkey(key: &[u8], purpose: &str) -> Key {
+    derive_subkey(key, &format!("zippy header {}", purpose))
+}
+
+/// 加密并认证文件头中的字段，与文件使用的算法无关，统一使用 ChaCha20Poly1305
+///
+/// 输出为 nonce(12) + 密文 + 认证标签，字段用途作为关联数据，防止字段之间互相替换。
+pub fn seal_field(key: &[u8], purpose: &str, data: &[u8]) -> Result<Vec<u8>, CodedError> {
+    if key.len() != 32 {
+        return Err(CodedError::invalid_key_length("Key must be 32 bytes (256 bits)"));
+    }
+    let cipher = ChaCha20Poly1305::new(&header_subkey(key, purpose));
+    let mut nonce = [0u8; 12];
+    rand::rngs::ThreadRng::default().fill_bytes(&mut nonce);
+
+    let ciphertext = cipher
+        .encrypt(&nonce.into(), Payload { msg: data, aad: purpose.as_bytes() })
+        .map_err(|e| CodedError::bad_header(format!("Header field encrypt failed: {:?}", e)))?;
+
+    let mut result = nonce.to_vec();
+    result.extend_from_slice(&ciphertext);
+    Ok(result)
+}
+
+/// 解密并验证 `seal_field` 生成的字段
+pub fn open_field(key: &[u8], purpose: &str, sealed: &[u8]) -> Result<Vec<u8>, CodedError> {
+    if key.len() != 32 {
+        return Err(CodedError::invalid_key_length("Key must be 32 bytes (256 bits)"));
+    }
+    if sealed.len() < 12 {
+        return Err(CodedError::bad_header("Invalid header field"));
+    }
+    let (nonce, ciphertext) = sealed.split_at(12);
+    let cipher = ChaCha20Poly1305::new(&header_subkey(key, purpose));
+    cipher
+        .decrypt(nonce.into(), Payload { msg: ciphertext, aad: purpose.as_bytes() })
+        .map_err(|_| CodedError::auth_failed(format!("Failed to authenticate header field '{}' - wrong key or corrupted header", purpose)))
+}
+
+fn header_hmac(key: &[u8], header: &[u8]) -> Result<HmacSha256, CodedError> {
+    if key.len() != 32 {
+        return Err(CodedError::invalid_key_length("Key must be 32 bytes (256 bits)"));
+    }
+    let mut mac = <HmacSha256 as Mac>::new_from_slice(&header_subkey(key, "mac"))
+        .map_err(|e| CodedError::bad_header(format!("Header MAC init failed: {:?}", e)))?;
+    mac.update(header);
+    Ok(mac)
+}
+
+/// 计算文件头的认证标签，CBC 没有认证能力，因此两种算法统一使用 HMAC-SHA256 保护文件头
+pub fn header_mac(key: &[u8], header: &[u8]) -> Result<[u8; HEADER_MAC_LEN], CodedError> {
+    Ok(header_hmac(key, header)?.finalize().into_bytes().into())
+}
+
+/// 以常数时间校验文件头的认证标签
+pub fn verify_header_mac(key: &[u8], header: &[u8], tag: &[u8]) -> Result<(), CodedError> {
+    header_hmac(key, header)?
+        .verify_slice(tag)
+        .map_err(|_| CodedError::auth_failed("Header authentication failed - wrong key or tampered header"))
+}
+
+fn volume_hmac(key: &[u8], header: &[u8]) -> Result<HmacSha256, CodedError> {
+    if key.len() != 32 {
+        return Err(CodedError::invalid_key_length("Key must be 32 bytes (256 bits)"));
+    }
+    let mut mac = new_hmac(&derive_subkey(key, "zippy volume mac"));
+    mac.update(header);
+    Ok(mac)
+}
+
+/// 计算分卷头的认证标签
+pub fn volume_mac(key: &[u8], header: &[u8]) -> Result<[u8; HEADER_MAC_LEN], CodedError> {
+    Ok(volume_hmac(key, header)?.finalize().into_bytes().into())
+}
+
+/// 以常数时间校验分卷头的认证标签
+pub fn verify_volume_mac(key: &[u8], header: &[u8], tag: &[u8]) -> Result<(), CodedError> {
+    volume_hmac(key, header)?
+        .verify_slice(tag)
+        .map_err(|_| CodedError::auth_failed("Volume authentication failed - wrong key or tampered volume header"))
+}
+
+/// AES-CBC 分片的认证标签长度（HMAC-SHA256）
+pub const CHUNK_MAC_LEN: usize = 32;
+/// ChaCha20Poly1305 密文末尾自带的认证标签长度
+const POLY1305_TAG_LEN: usize = 16;
+
+fn new_hmac(subkey: &Key) -> HmacSha256 {
+    <HmacSha256 as Mac>::new_from_slice(subkey).expect("HMAC accepts keys of any length")
+}
+
+/// 分片的关联数据：分片序号 + 分片总数（u64 LE），不绑定顺序的早期文件为空
+fn chunk_aad(sequence: Option<(u64, u64)>) -> Vec<u8> {
+    match sequence {
+        Some((index, total)) => [index.to_le_bytes(), total.to_le_bytes()].concat(),
+        None => Vec::new(),
+    }
+}
+
+/// 分片帧末尾认证标签的长度
+pub fn chunk_tag_len(algorithm: &CryptoAlgorithm) -> usize {
+    match algorithm {
+        CryptoAlgorithm::Aes => CHUNK_MAC_LEN,
+        CryptoAlgorithm::Chacha20Poly1305 => POLY1305_TAG_LEN,
+    }
+}
+
+/// 加密一个分片，分片的关联数据参与认证
+///
+/// AES-CBC 没有认证能力，对关联数据和分片密文计算 HMAC-SHA256 并追加到末尾（先加密后认证）；
+/// ChaCha20Poly1305 直接把关联数据交给 AEAD，密文末尾本身就是认证标签。
+fn seal_chunk_frame(algorithm: &CryptoAlgorithm, key: &[u8], chunk_key: &Key, data: &[u8], aad: &[u8], frame: &mut Vec<u8>) -> Result<(), CodedError> {
+    match algorithm {
+        CryptoAlgorithm::Aes => {
+            encrypt_into(&CryptoAlgorithm::Aes, key, data, frame)?;
+            let mut mac = new_hmac(chunk_key);
+            mac.update(aad);
+            mac.update(frame);
+            frame.extend_from_slice(&mac.finalize().into_bytes());
+            Ok(())
+        }
+        CryptoAlgorithm::Chacha20Poly1305 => chacha_encrypt(key, data, aad, frame),
+    }
+}
+
+/// 校验并解密一个分片
+fn open_chunk_frame(algorithm: &CryptoAlgorithm, key: &[u8], chunk_key: &Key, frame: &[u8], aad: &[u8], out: &mut Vec<u8>) -> Result<(), CodedError> {
+    const FAILED: &str = "Chunk authentication failed - chunk was modified, reordered or duplicated";
+    match algorithm {
+        CryptoAlgorithm::Aes => {
+            if frame.len() < CHUNK_MAC_LEN {
+                return Err(CodedError::bad_format("Invalid chunk: missing authentication tag"));
+            }
+            let (ciphertext, tag) = frame.split_at(frame.len() - CHUNK_MAC_LEN);
+            let mut mac = new_hmac(chunk_key);
+            mac.update(aad);
+            mac.update(ciphertext);
+            mac.verify_slice(tag).map_err(|_| CodedError::auth_failed(FAILED))?;
+            decrypt_into(&CryptoAlgorithm::Aes, key, ciphertext, out)
+        }
+        CryptoAlgorithm::Chacha20Poly1305 => {
+            if frame.len() < 12 + POLY1305_TAG_LEN {
+                return Err(CodedError::bad_format("Invalid chunk: missing authentication tag"));
+            }
+            chacha_decrypt(key, frame, aad, out).map_err(|_| CodedError::auth_failed(FAILED))
+        }
+    }
+}
+
+/// 分片文件的认证状态：加密/解密每个分片时把分片序号和总数作为关联数据绑定，
+/// 并把文件头标签和所有分片标签串联起来计算整个文件的 MAC，这样分片被删除、替换、重复或调换顺序都会被发现
+pub struct ChunkAuthenticator {
+    algorithm: CryptoAlgorithm,
+    key: Vec<u8>,
+    chunk_key: Key,
+    file_mac: HmacSha256,
+    chunks: u64,
+    /// 分片总数，None 表示早期文件，分片没有绑定顺序
+    total_chunks: Option<u64>,
+}
+
+impl ChunkAuthenticator {
+    pub fn new(algorithm: CryptoAlgorithm, key: &[u8], header_tag: &[u8], total_chunks: Option<u64>) -> Result<Self, CodedError> {
+        if key.len() != 32 {
+            return Err(CodedError::invalid_key_length("Key must be 32 bytes (256 bits)"));
+        }
+        let mut file_mac = new_hmac(&derive_subkey(key, "zippy file mac"));
+        file_mac.update(header_tag);
+        Ok(ChunkAuthenticator {
+            algorithm,
+            key: key.to_vec(),
+            chunk_key: derive_subkey(key, "zippy chunk mac"),
+            file_mac,
+            chunks: 0,
+            total_chunks,
+        })
+    }
+
+    fn aad(&self) -> Vec<u8> {
+        chunk_aad(self.total_chunks.map(|total| (self.chunks, total)))
+    }
+
+    fn record(&mut self, frame: &[u8]) {
+        let tag = &frame[frame.len().saturating_sub(chunk_tag_len(&self.algorithm))..];
+        self.add_chunk(frame.len() as u64, tag);
+    }
+
+    /// 把一个不经过本对象加解密的分片帧（长度和末尾的认证标签）计入整个文件的 MAC，用于复制或原地重写部分分片
+    pub fn add_chunk(&mut self, frame_len: u64, tag: &[u8]) {
+        self.file_mac.update(&frame_len.to_le_bytes());
+        self.file_mac.update(tag);
+        self.chunks += 1;
+    }
+
+    /// 加密下一个分片，把要写入文件的分片数据写入 frame（先清空）
+    pub fn encrypt_chunk(&mut self, data: &[u8], frame: &mut Vec<u8>) -> Result<(), CodedError> {
+        seal_chunk_frame(&self.algorithm, &self.key, &self.chunk_key, data, &self.aad(), frame)?;
+        self.record(frame);
+        Ok(())
+    }
+
+    /// 校验并解密下一个分片，明文写入 out（先清空）
+    pub fn decrypt_chunk(&mut self, frame: &[u8], out: &mut Vec<u8>) -> Result<(), CodedError> {
+        open_chunk_frame(&self.algorithm, &self.key, &self.chunk_key, frame, &self.aad(), out)?;
+        self.record(frame);
+        Ok(())
+    }
+
+    /// 跳过无法解密的分片，保持后续分片的序号正确；整个文件的 MAC 随之无法通过校验
+    pub fn skip_chunk(&mut self) {
+        self.chunks += 1;
+    }
+
+    /// 整个文件的 MAC，写入文件末尾；footer 为同样需要认证的结尾字段，没有时为空
+    pub fn finalize(self, footer: &[u8]) -> [u8; CHUNK_MAC_LEN] {
+        let mut file_mac = self.file_mac;
+        file_mac.update(&self.chunks.to_le_bytes());
+        file_mac.update(footer);
+        file_mac.finalize().into_bytes().into()
+    }
+
+    /// 以常数时间校验文件末尾的 MAC
+    pub fn verify(self, tag: &[u8], footer: &[u8]) -> Result<(), CodedError> {
+        let mut file_mac = self.file_mac;
+        file_mac.update(&self.chunks.to_le_bytes());
+        file_mac.update(footer);
+        file_mac
+            .verify_slice(tag)
+            .map_err(|_| CodedError::auth_failed("File authentication failed - chunks were removed, reordered or modified"))
+    }
+}
+
+/// 内容寻址存储中对象 id 的长度
+pub const OBJECT_ID_LEN: usize = 32;
+
+/// 内容寻址存储的对象 id：使用派生子密钥对分片明文计算 BLAKE3 keyed hash
+///
+/// 相同的内容得到相同的 id，从而复用已有对象；没有密钥无法由 id 推断或确认内容。
+pub fn object_id(key: &[u8], data: &[u8]) -> [u8; OBJECT_ID_LEN] {
+    let subkey: [u8; 32] = derive_subkey(key, "zippy object id").into();
+    *blake3::keyed_hash(&subkey, data).as_bytes()
+}
+
+/// 加密内容寻址存储中的一个对象，对象 id 作为关联数据参与认证，对象文件被调换时无法解密
+pub fn seal_object(algorithm: &CryptoAlgorithm, key: &[u8], id: &[u8], data: &[u8]) -> Result<Vec<u8>, CodedError> {
+    if key.len() != 32 {
+        return Err(CodedError::invalid_key_length("Key must be 32 bytes (256 bits)"));
+    }
+    let mut sealed = Vec::new();
+    seal_chunk_frame(algorithm, key, &derive_subkey(key, "zippy object mac"), data, id, &mut sealed)?;
+    Ok(sealed)
+}
+
+/// 校验并解密 `seal_object` 生成的对象
+pub fn open_object(algorithm: &CryptoAlgorithm, key: &[u8], id: &[u8], sealed: &[u8]) -> Result<Vec<u8>, CodedError> {
+    if key.len() != 32 {
+        return Err(CodedError::invalid_key_length("Key must be 32 bytes (256 bits)"));
+    }
+    let mut data = Vec::new();
+    open_chunk_frame(algorithm, key, &derive_subkey(key, "zippy object mac"), sealed, id, &mut data)?;
+    Ok(data)
+}
+
+/// 单独加密一个分片，sequence 为（分片序号，分片总数）；用于原地重写分片，整个文件的 MAC 需要另行更新
+pub fn encrypt_single_chunk(algorithm: &CryptoAlgorithm, key: &[u8], data: &[u8], sequence: Option<(u64, u64)>) -> Result<Vec<u8>, CodedError> {
+    if key.len() != 32 {
+        return Err(CodedError::invalid_key_length("Key must be 32 bytes (256 bits)"));
+    }
+    let chunk_key = derive_subkey(key, "zippy chunk mac");
+    let mut frame = Vec::new();
+    seal_chunk_frame(algorithm, key, &chunk_key, data, &chunk_aad(sequence), &mut frame)?;
+    Ok(frame)
+}
+
+/// 随机访问单个分片：只能校验该分片自身的认证标签和序号，sequence 为（分片序号，分片总数）
+pub fn decrypt_single_chunk(algorithm: &CryptoAlgorithm, key: &[u8], frame: &[u8], sequence: Option<(u64, u64)>) -> Result<Vec<u8>, CodedError> {
+    if key.len() != 32 {
+        return Err(CodedError::invalid_key_length("Key must be 32 bytes (256 bits)"));
+    }
+    let chunk_key = derive_subkey(key, "zippy chunk mac");
+    let mut data = Vec::new();
+    open_chunk_frame(algorithm, key, &chunk_key, frame, &chunk_aad(sequence), &mut data)?;
+    Ok(data)
+}
+
+/// 已知答案测试（KAT），确认加密后端在当前平台上工作正常
+pub fn self_test() -> Result<(), CodedError> {
+    // NIST SP 800-38A F.2.5 CBC-AES256.Encrypt 第一个分组
+    let key = hex::decode("603deb1015ca71be2b73aef0857d77811f352c073b6108d72d9810a30914dff4").unwrap();
+    let iv = hex::decode("000102030405060708090a0b0c0d0e0f").unwrap();
+    let plaintext = hex::decode("6bc1bee22e409f96e93d7e117393172a").unwrap();
+    let expected = hex::decode("f58c4c04d6e5f1ba779eabfb5f7bfbd6").unwrap();
+
+    let cipher = Aes256CbcEnc::new_from_slices(&key, &iv)
+        .map_err(|e| CodedError::unknown(format!("AES self-test init failed: {:?}", e)))?;
+    let ciphertext = cipher.encrypt_padded_vec_mut::<Pkcs7>(&plaintext);
+    if ciphertext[..16] != expected[..] {
+        return Err(CodedError::unknown("AES-256-CBC self-test failed"));
+    }
+
+    // RFC 8439 2.8.2 AEAD_CHACHA20_POLY1305 测试向量
+    let key: Vec<u8> = (0x80..0xa0).collect();
+    let nonce = hex::decode("070000004041424344454647").unwrap();
+    let aad = hex::decode("50515253c0c1c2c3c4c5c6c7").unwrap();
+    let plaintext: &[u8] = b"Ladies and Gentlemen of the class of '99: If I could offer you only one tip for the future, sunscreen would be it.";
+    let expected = hex::decode(concat!(
+        "d31a8d34648e60db7b86afbc53ef7ec2a4aded51296e08fea9e2b5a736ee62d63dbea45e8ca9671282fafb69da92728b",
+        "1a71de0a9e060b2905d6a5b67ecd3b3692ddbd7f2d778b8c9803aee328091b58fab324e4fad675945585808b4831d7bc",
+        "3ff4def08e4b7a9de576d26586cec64b61161ae10b594f09e26a7e902ecbd0600691"
+    ))
+    .unwrap();
+
+    let cipher = ChaCha20Poly1305::new_from_slice(&key)
+        .map_err(|e| CodedError::unknown(format!("ChaCha20Poly1305 self-test init failed: {:?}", e)))?;
+    let ciphertext = cipher
+        .encrypt(nonce.as_slice().into(), Payload { msg: plaintext, aad: &aad })
+        .map_err(|e| CodedError::unknown(format!("ChaCha20Poly1305 self-test failed: {:?}", e)))?;
+    if ciphertext != expected {
+        return Err(CodedError::unknown("ChaCha20Poly1305 self-test failed"));
+    }
+
+    Ok(())
+}
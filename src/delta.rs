@@ -0,0 +1,380 @@
+use std::fs::{self, File, OpenOptions};
+use std::io::{BufReader, BufWriter, Read, Seek, SeekFrom, Write};
+
+use crate::chunked::{scan_frames, ChunkEncoder};
+use crate::crypto::{
+    chunk_tag_len, encrypt_single_chunk, seal_field, ChunkAuthenticator, ChunkChecksum, CryptoAlgorithm, HashAlgorithm,
+    PlaintextHasher, CHUNK_CHECKSUM_LEN, CHUNK_MAC_LEN,
+};
+use crate::errors::{open_error, read_error, write_error, CodedError};
+use crate::fd::{self, Target};
+use crate::format::{ChunkedFooter, ChunkedHeader, ContainerHeader, FIELD_DIGEST};
+use crate::parity;
+
+/// 增量更新的统计
+pub struct DeltaReport {
+    pub total_chunks: u64,
+    /// 内容发生变化、重新加密的分片数
+    pub changed_chunks: u64,
+    /// 重新加密写出的分片数据字节数（不含复制的分片）
+    pub rewritten_bytes: u64,
+    /// 是否在原文件中直接改写；压缩或稀疏编码的文件分片长度会变化，只能写出新文件后替换
+    pub in_place: bool,
+}
+
+/// 依据结尾字段中每个分片明文的校验值，只重新加密内容发生变化的分片，并更新明文摘要、分片校验值和整个文件的 MAC
+///
+/// header 为已校验过的文件头。明文大小必须与加密时相同；大小变化时分片数和分片序号都会改变，需要重新加密整个文件。
+/// 未压缩的文件在原文件中直接改写变化的分片和结尾，写入过程中被中断时文件无法通过认证；压缩或稀疏编码的文件写出临时文件后替换。
+pub fn update_file(plain_path: &Target, encrypted_path: &str, header: ChunkedHeader, algorithm: &CryptoAlgorithm, key: &[u8], check: impl Fn() -> Result<(), CodedError>, chunk_done: impl FnMut(u32, usize)) -> Result<DeltaReport, CodedError> {
+    let container = match &header.container {
+        Some(container) if container.authenticated_chunks && container.has_footer => container,
+        _ => return Err(CodedError::invalid_argument("Delta updates require a chunk index and chunk checksums - re-encrypt with chunkEncryptFile first")),
+    };
+    let plain_size = plain_path
+        .metadata()
+        .map_err(|e| read_error("reading plaintext file metadata", e))?
+        .len();
+    if plain_size != header.original_size {
+        return Err(CodedError::invalid_argument(format!(
+            "Plaintext size changed from {} to {} bytes - re-encrypt with chunkEncryptFile",
+            header.original_size, plain_size
+        )));
+    }
+    let parity_percent = match container.has_parity {
+        true => Some(parity::parity_percent(encrypted_path)?),
+        false => None,
+    };
+
+    let plain = BufReader::new(fd::open(plain_path).map_err(|e| open_error("opening plaintext file", e))?);
+    // 压缩或稀疏编码的分片帧长度随内容变化，无法原地改写
+    let report = match header.compression().is_some() || container.sparse {
+        true => rebuild(plain, encrypted_path, &header, container, algorithm, key, check, chunk_done)?,
+        false => rewrite_in_place(plain, encrypted_path, &header, container, algorithm, key, check, chunk_done)?,
+    };
+
+    // 受保护区域的内容变了，按原来的冗余百分比重新生成纠错数据
+    if let (Some(percent), true) = (parity_percent, report.changed_chunks > 0) {
+        if report.in_place {
+            parity::remove_parity(encrypted_path)?;
+        }
+        parity::append_parity(encrypted_path, percent)?;
+    }
+    Ok(report)
+}
+
+/// 结尾字段必须为每个分片记录了校验值，早期文件没有
+fn check_checksums(footer: &ChunkedFooter, total_chunks: u64) -> Result<(), CodedError> {
+    if footer.chunk_checksums.len() as u64 != total_chunks {
+        return Err(CodedError::invalid_argument("Delta updates require a chunk index and chunk checksums - re-encrypt with chunkEncryptFile first"));
+    }
+    Ok(())
+}
+
+fn new_authenticator(header: &ChunkedHeader, container: &ContainerHeader, algorithm: &CryptoAlgorithm, key: &[u8]) -> Result<ChunkAuthenticator, CodedError> {
+    let header_tag = container.header_mac.as_deref().unwrap_or_default();
+    ChunkAuthenticator::new(algorithm.clone(), key, header_tag, header.chunk_sequence_total())
+}
+
+fn read_plain_chunk<R: Read>(plain: &mut R, header: &ChunkedHeader, index: u64) -> Result<Vec<u8>, CodedError> {
+    let mut data = vec![0u8; header.chunk_len(index)];
+    plain
+        .read_exact(&mut data)
+        .map_err(|e| read_error("reading plaintext file", e))?;
+    Ok(data)
+}
+
+/// 未压缩的文件：相同长度的明文加密后分片帧长度不变，直接覆盖变化的分片，最后重写结束帧、文件 MAC 和结尾字段
+#[allow(clippy::too_many_arguments)]
+fn rewrite_in_place<R: Read>(mut plain: R, encrypted_path: &str, header: &ChunkedHeader, container: &ContainerHeader, algorithm: &CryptoAlgorithm, key: &[u8], check: impl Fn() -> Result<(), CodedError>, mut chunk_done: impl FnMut(u32, usize)) -> Result<DeltaReport, CodedError> {
+    let mut file = OpenOptions::new()
+        .read(true)
+        .write(true)
+        .open(encrypted_path)
+        .map_err(|e| open_error("opening encrypted file", e))?;
+    let frame_format = header.frame_format();
+    let tag_len = chunk_tag_len(algorithm);
+    let total_chunks = header.total_chunks();
+
+    // 第一遍：只读取分片帧的长度和认证标签，校验整个文件的 MAC，确认原文件完好后再改写
+    let mut reader = BufReader::new(&file);
+    reader
+        .seek(SeekFrom::Start(header.header_len))
+        .map_err(|e| read_error("seeking in encrypted file", e))?;
+    let scan = scan_frames(&mut reader, header, algorithm, key)?;
+    drop(reader);
+    let (mut frames, position, mut footer, old_fields) = (scan.frames, scan.end, scan.footer, scan.footer_fields);
+    check_checksums(&footer, total_chunks)?;
+
+    // 第二遍：逐个分片比较明文校验值，只重新加密变化的分片
+    let checksum = ChunkChecksum::new(key);
+    let mut hasher = PlaintextHasher::new(container.hash_algorithm.unwrap_or(HashAlgorithm::Sha256));
+    let mut report = DeltaReport { total_chunks, changed_chunks: 0, rewritten_bytes: 0, in_place: true };
+    for (index, frame) in frames.iter_mut().enumerate() {
+        check()?;
+        let data = read_plain_chunk(&mut plain, header, index as u64)?;
+        hasher.update(&data);
+        let new_checksum: [u8; CHUNK_CHECKSUM_LEN] = checksum.compute(&data);
+        if new_checksum != footer.chunk_checksums[index] {
+            let sequence = header.chunk_sequence_total().map(|total| (index as u64, total));
+            let encrypted = encrypt_single_chunk(algorithm, key, &data, sequence)?;
+            if encrypted.len() != frame.len {
+                return Err(CodedError::unknown(format!("Re-encrypted chunk {} changed size from {} to {} bytes", index + 1, frame.len, encrypted.len())));
+            }
+            file.seek(SeekFrom::Start(frame.data_offset))
+                .and_then(|_| file.write_all(&encrypted))
+                .map_err(|e| write_error("writing encrypted chunk", e))?;
+            frame.tag = encrypted[encrypted.len() - tag_len..].to_vec();
+            footer.chunk_checksums[index] = new_checksum;
+            report.changed_chunks += 1;
+            report.rewritten_bytes += encrypted.len() as u64;
+        }
+        chunk_done(index as u32 + 1, data.len());
+    }
+    if report.changed_chunks == 0 {
+        return Ok(report);
+    }
+
+    // 结尾字段只有明文摘要和分片校验值变化，长度不变，原位重写
+    // 可追加的文件不记录明文摘要
+    if footer.encrypted_digest.is_some() {
+        footer.encrypted_digest = Some(seal_field(key, FIELD_DIGEST, &hasher.finalize())?);
+    }
+    let fields = footer.fields();
+    if fields.len() != old_fields.len() {
+        return Err(CodedError::bad_header("File footer changed size during delta update"));
+    }
+    let mut authenticator = new_authenticator(header, container, algorithm, key)?;
+    for frame in &frames {
+        authenticator.add_chunk(frame.len as u64, &frame.tag);
+    }
+    let file_mac = authenticator.finalize(&fields);
+    let footer_offset = position + (frame_format.encode_len(0)?.len() + CHUNK_MAC_LEN) as u64;
+    file.seek(SeekFrom::Start(position))
+        .map_err(|e| read_error("seeking in encrypted file", e))?;
+    let mut writer = BufWriter::new(&file);
+    frame_format.write_footer(&mut writer, &file_mac)?;
+    ChunkedFooter::write(&mut writer, &fields, footer_offset)?;
+    writer.flush().map_err(|e| write_error("writing file footer", e))?;
+    drop(writer);
+    file.sync_all().map_err(|e| write_error("flushing encrypted file", e))?;
+    Ok(report)
+}
+
+/// 压缩或稀疏编码的文件：分片帧的长度随内容变化，写出新文件，未变化的分片帧原样复制，全部校验通过后替换原文件
+#[allow(clippy::too_many_arguments)]
+fn rebuild<R: Read>(mut plain: R, encrypted_path: &str, header: &ChunkedHeader, container: &ContainerHeader, algorithm: &CryptoAlgorithm, key: &[u8], check: impl Fn() -> Result<(), CodedError>, mut chunk_done: impl FnMut(u32, usize)) -> Result<DeltaReport, CodedError> {
+    let mut reader = BufReader::new(fd::open(encrypted_path).map_err(|e| open_error("opening encrypted file", e))?);
+    // 先读取结尾字段中的分片校验值，最后按顺序读到结尾时连同文件 MAC 一起校验
+    let expected = ChunkedFooter::read_at_end(&mut reader)?;
+    let total_chunks = header.total_chunks();
+    check_checksums(&expected, total_chunks)?;
+    reader
+        .seek(SeekFrom::Start(header.header_len))
+        .map_err(|e| read_error("seeking in encrypted file", e))?;
+
+    let temp_path = format!("{}.updating", encrypted_path);
+    let result = (|| {
+        let output = File::create(&temp_path).map_err(|e| open_error("creating output file", e))?;
+        let mut encoder = ChunkEncoder::new(BufWriter::new(output), algorithm.clone(), key, Some(container), header.original_size, header.chunk_size)?;
+        let mut authenticator = new_authenticator(header, container, algorithm, key)?;
+        let frame_format = header.frame_format();
+        let tag_len = chunk_tag_len(algorithm);
+        let checksum = ChunkChecksum::new(key);
+        let mut report = DeltaReport { total_chunks, changed_chunks: 0, rewritten_bytes: 0, in_place: false };
+        for index in 0..total_chunks {
+            check()?;
+            let frame = frame_format.read_frame(&mut reader, &format!("chunk {}", index + 1), header.max_frame_len())?;
+            if frame.len() < tag_len {
+                return Err(CodedError::bad_format(format!("Invalid encrypted chunk size in chunk {}: {} bytes", index + 1, frame.len())));
+            }
+            authenticator.add_chunk(frame.len() as u64, &frame[frame.len() - tag_len..]);
+            let data = read_plain_chunk(&mut plain, header, index)?;
+            if checksum.compute(&data) == expected.chunk_checksums[index as usize] {
+                encoder.copy_chunk(&data, &frame)?;
+            } else {
+                let before = encoder.bytes_written();
+                encoder.write_chunk(&data)?;
+                report.changed_chunks += 1;
+                report.rewritten_bytes += encoder.bytes_written() - before;
+            }
+            chunk_done(index as u32 + 1, data.len());
+        }
+        let file_mac = frame_format.read_footer(&mut reader)?;
+        let (footer, fields) = ChunkedFooter::read(&mut reader)?;
+        authenticator.verify(&file_mac, &fields)?;
+        if footer.chunk_checksums != expected.chunk_checksums {
+            return Err(CodedError::bad_header("File footer changed during delta update"));
+        }
+        encoder
+            .finish()?
+            .into_inner()
+            .map_err(|e| write_error("writing output file", e.into_error()))?
+            .sync_all()
+            .map_err(|e| write_error("flushing output file", e))?;
+        Ok(report)
+    })();
+
+    let report = match result {
+        Ok(report) if report.changed_chunks > 0 => report,
+        other => {
+            let _ = fs::remove_file(&temp_path);
+            return other;
+        }
+    };
+    // 保留原文件的权限
+    fs::metadata(encrypted_path)
+        .and_then(|metadata| fs::set_permissions(&temp_path, metadata.permissions()))
+        .and_then(|_| fs::rename(&temp_path, encrypted_path))
+        .map_err(|e| {
+            let _ = fs::remove_file(&temp_path);
+            write_error("replacing encrypted file", e)
+        })?;
+    Ok(report)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::Cursor;
+
+    use super::*;
+    use crate::chunked::ChunkDecoder;
+    use crate::compression::{Compression, CompressionMethod};
+    use crate::errors::ErrorCode;
+    use crate::format::{read_chunked_header, Layout};
+
+    const KEY: [u8; 32] = [5; 32];
+    const CHUNK_SIZE: usize = 1000;
+
+    struct TempFiles(Vec<String>);
+
+    impl TempFiles {
+        fn path(&mut self, name: &str) -> String {
+            let path = std::env::temp_dir()
+                .join(format!("zippy-delta-{}-{}", std::process::id(), name))
+                .to_string_lossy()
+                .into_owned();
+            self.0.push(path.clone());
+            path
+        }
+    }
+
+    impl Drop for TempFiles {
+        fn drop(&mut self) {
+            for path in &self.0 {
+                let _ = fs::remove_file(path);
+            }
+        }
+    }
+
+    fn plaintext() -> Vec<u8> {
+        (0..4500u32).map(|i| (i % 251) as u8).collect()
+    }
+
+    fn create(path: &str, data: &[u8], customize: impl FnOnce(&mut ContainerHeader)) {
+        let mut container = ContainerHeader::for_new_file(Layout::Chunked, CryptoAlgorithm::Aes, data.len() as u64, CHUNK_SIZE as u64);
+        customize(&mut container);
+        let mut encoder = ChunkEncoder::new(File::create(path).unwrap(), CryptoAlgorithm::Aes, &KEY, Some(&container), data.len() as u64, CHUNK_SIZE).unwrap();
+        encoder.write(data).unwrap();
+        encoder.finish().unwrap();
+    }
+
+    fn read_header(path: &str) -> ChunkedHeader {
+        let header = read_chunked_header(&mut BufReader::new(File::open(path).unwrap())).unwrap();
+        header.container.as_ref().unwrap().verify(&KEY).unwrap();
+        header
+    }
+
+    fn decrypt(path: &str) -> Vec<u8> {
+        let file = fs::read(path).unwrap();
+        let mut reader = Cursor::new(&file[..]);
+        let header = read_chunked_header(&mut reader).unwrap();
+        header.container.as_ref().unwrap().verify(&KEY).unwrap();
+        let mut decoder = ChunkDecoder::new(reader, header, CryptoAlgorithm::Aes, &KEY).unwrap();
+        let mut plaintext = Vec::new();
+        while let Some(chunk) = decoder.next_chunk().unwrap() {
+            plaintext.extend_from_slice(&chunk);
+        }
+        decoder.finish().unwrap();
+        plaintext
+    }
+
+    fn update(plain_path: &str, encrypted_path: &str) -> Result<DeltaReport, CodedError> {
+        update_file(&plain_path.into(), encrypted_path, read_header(encrypted_path), &CryptoAlgorithm::Aes, &KEY, || Ok(()), |_, _| {})
+    }
+
+    #[test]
+    fn rewrites_changed_chunks_in_place() {
+        let mut files = TempFiles(Vec::new());
+        let (plain_path, encrypted_path) = (files.path("in-place.txt"), files.path("in-place.enc"));
+        let mut data = plaintext();
+        create(&encrypted_path, &data, |_| {});
+        let original_len = fs::metadata(&encrypted_path).unwrap().len();
+
+        data[1500] ^= 0xff;
+        data[4400] ^= 0xff;
+        fs::write(&plain_path, &data).unwrap();
+        let report = update(&plain_path, &encrypted_path).unwrap();
+        assert!(report.in_place);
+        assert_eq!((report.total_chunks, report.changed_chunks), (5, 2));
+        assert_eq!(fs::metadata(&encrypted_path).unwrap().len(), original_len);
+        assert_eq!(decrypt(&encrypted_path), data);
+
+        // 再次更新时没有变化的分片，文件保持不变
+        let before = fs::read(&encrypted_path).unwrap();
+        assert_eq!(update(&plain_path, &encrypted_path).unwrap().changed_chunks, 0);
+        assert_eq!(fs::read(&encrypted_path).unwrap(), before);
+    }
+
+    #[test]
+    fn rebuilds_compressed_files() {
+        let mut files = TempFiles(Vec::new());
+        let (plain_path, encrypted_path) = (files.path("rebuild.txt"), files.path("rebuild.enc"));
+        let mut data = plaintext();
+        create(&encrypted_path, &data, |container| container.compression = Some(Compression::new(CompressionMethod::Zstd, None).unwrap()));
+
+        data[10..20].copy_from_slice(b"0123456789");
+        fs::write(&plain_path, &data).unwrap();
+        let report = update(&plain_path, &encrypted_path).unwrap();
+        assert!(!report.in_place);
+        assert_eq!(report.changed_chunks, 1);
+        assert!(report.rewritten_bytes > 0);
+        assert_eq!(decrypt(&encrypted_path), data);
+        assert!(!std::path::Path::new(&format!("{}.updating", encrypted_path)).exists());
+    }
+
+    #[test]
+    fn rejects_unsupported_updates() {
+        let mut files = TempFiles(Vec::new());
+        let (plain_path, encrypted_path) = (files.path("reject.txt"), files.path("reject.enc"));
+        let data = plaintext();
+        create(&encrypted_path, &data, |_| {});
+
+        fs::write(&plain_path, &data[..data.len() - 1]).unwrap();
+        assert_eq!(update(&plain_path, &encrypted_path).err().unwrap().code, ErrorCode::InvalidArgument);
+
+        let legacy_path = files.path("legacy.enc");
+        create(&legacy_path, &data, |container| container.has_footer = false);
+        fs::write(&plain_path, &data).unwrap();
+        assert_eq!(update(&plain_path, &legacy_path).err().unwrap().code, ErrorCode::InvalidArgument);
+    }
+
+    #[test]
+    fn leaves_a_tampered_file_unchanged() {
+        let mut files = TempFiles(Vec::new());
+        let (plain_path, encrypted_path) = (files.path("tampered.txt"), files.path("tampered.enc"));
+        let mut data = plaintext();
+        create(&encrypted_path, &data, |_| {});
+        let header_len = read_header(&encrypted_path).header_len as usize;
+        let mut encrypted = fs::read(&encrypted_path).unwrap();
+        // 改动第一个分片帧的认证标签（帧以 u32 LE 长度开头）
+        let frame_len = u32::from_le_bytes(encrypted[header_len..header_len + 4].try_into().unwrap()) as usize;
+        encrypted[header_len + 4 + frame_len - 1] ^= 1;
+        fs::write(&encrypted_path, &encrypted).unwrap();
+
+        data[0] ^= 1;
+        fs::write(&plain_path, &data).unwrap();
+        assert!(update(&plain_path, &encrypted_path).is_err());
+        assert_eq!(fs::read(&encrypted_path).unwrap(), encrypted);
+    }
+}
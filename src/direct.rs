@@ -0,0 +1,451 @@
+use std::alloc::{self, Layout};
+use std::fs::File;
+use std::io::{self, SeekFrom};
+
+/// 直接 I/O 要求的缓冲区地址、文件位置和长度的对齐单位，同时满足 512 字节和 4 KiB 扇区的设备
+const ALIGN: usize = 4096;
+/// 对齐缓冲区的大小
+const BUFFER_LEN: usize = 1024 * 1024;
+
+/// 绕过页缓存读写文件（Linux 等为 O_DIRECT，macOS 为 F_NOCACHE，Windows 为 FILE_FLAG_NO_BUFFERING），
+/// 加密几百 GB 的数据时不会把其它进程的页缓存挤出去
+///
+/// 所有读写都经过一个对齐的缓冲区，按对齐的位置和长度读写文件。写入不足一个对齐单位的结尾时临时改用普通写入，
+/// 结尾仍保留在缓冲区中，之后写满时整块重新写出。读写位置由这里记录，不使用文件本身的读写位置。
+pub struct DirectIo {
+    /// 绕过页缓存的句柄；Linux 和 macOS 上与原来的文件是同一个句柄
+    unbuffered: Option<File>,
+    buffer: AlignedBuffer,
+    /// 缓冲区对应的文件位置，按 ALIGN 对齐
+    start: u64,
+    /// 缓冲区中有效数据的长度
+    len: usize,
+    /// 缓冲区中的数据是写入的（需要写出），而不是从文件读入的
+    writing: bool,
+    offset: u64,
+}
+
+impl DirectIo {
+    /// 为 file 开启直接 I/O，从 file 当前的读写位置开始；文件系统或平台不支持时返回错误，由调用方改用普通读写
+    pub fn new(file: &File) -> io::Result<DirectIo> {
+        use std::io::Seek;
+        let offset = (&*file).stream_position()?;
+        let unbuffered = platform::enable(file)?;
+        Ok(DirectIo {
+            unbuffered,
+            buffer: AlignedBuffer::new(),
+            start: align_down(offset),
+            len: 0,
+            writing: false,
+            offset,
+        })
+    }
+
+    pub fn read(&mut self, file: &File, buf: &mut [u8]) -> io::Result<usize> {
+        if self.writing {
+            self.flush(file)?;
+            self.writing = false;
+            self.len = 0;
+        }
+        if !self.contains(self.offset) || self.offset == self.start + self.len as u64 {
+            self.start = align_down(self.offset);
+            // 文件结尾处读到的数据可能不足一个对齐单位，下次读取时从对齐的位置重新读入
+            self.len = platform::read_at(handle(&self.unbuffered, file), &mut self.buffer, self.start)?;
+        }
+        let position = (self.offset - self.start) as usize;
+        let available = self.len.saturating_sub(position).min(buf.len());
+        buf[..available].copy_from_slice(&self.buffer[position..position + available]);
+        self.offset += available as u64;
+        Ok(available)
+    }
+
+    pub fn write(&mut self, file: &File, buf: &[u8]) -> io::Result<usize> {
+        if !self.writing || self.offset != self.start + self.len as u64 {
+            // 从新的位置开始写入：先写出之前的数据，再读入对齐位置到写入位置之间已有的内容
+            if self.writing {
+                self.flush(file)?;
+            }
+            self.writing = true;
+            self.start = align_down(self.offset);
+            self.len = (self.offset - self.start) as usize;
+            if self.len > 0 {
+                let read = platform::read_at(handle(&self.unbuffered, file), &mut self.buffer[..ALIGN], self.start)?;
+                self.buffer[read.min(self.len)..self.len].fill(0);
+            }
+        }
+        let len = buf.len().min(BUFFER_LEN - self.len);
+        self.buffer[self.len..self.len + len].copy_from_slice(&buf[..len]);
+        self.len += len;
+        self.offset += len as u64;
+        if self.len == BUFFER_LEN {
+            platform::write_at(handle(&self.unbuffered, file), &self.buffer, self.start)?;
+            self.start += BUFFER_LEN as u64;
+            self.len = 0;
+        }
+        Ok(len)
+    }
+
+    /// 写出缓冲区中的所有数据，并把文件的读写位置移到当前位置
+    pub fn flush(&mut self, file: &File) -> io::Result<()> {
+        use std::io::Seek;
+        if self.writing && self.len > 0 {
+            let aligned = self.len - self.len % ALIGN;
+            if aligned > 0 {
+                platform::write_at(handle(&self.unbuffered, file), &self.buffer[..aligned], self.start)?;
+                self.buffer.copy_within(aligned..self.len, 0);
+                self.start += aligned as u64;
+                self.len -= aligned;
+            }
+            if self.len > 0 {
+                platform::write_tail(file, &self.buffer[..self.len], self.start)?;
+            }
+        }
+        (&*file).seek(SeekFrom::Start(self.offset))?;
+        Ok(())
+    }
+
+    pub fn seek(&mut self, file: &File, pos: SeekFrom) -> io::Result<u64> {
+        let offset = match pos {
+            SeekFrom::Start(offset) => Some(offset),
+            SeekFrom::Current(delta) => self.offset.checked_add_signed(delta),
+            SeekFrom::End(delta) => {
+                self.flush(file)?;
+                file.metadata()?.len().max(self.start + self.len as u64).checked_add_signed(delta)
+            },
+        };
+        self.offset = offset.ok_or_else(|| io::Error::new(io::ErrorKind::InvalidInput, "invalid seek to a negative or overflowing position"))?;
+        Ok(self.offset)
+    }
+
+    fn contains(&self, offset: u64) -> bool {
+        offset >= self.start && offset <= self.start + self.len as u64
+    }
+}
+
+/// 读写对齐数据使用的句柄
+fn handle<'a>(unbuffered: &'a Option<File>, file: &'a File) -> &'a File {
+    unbuffered.as_ref().unwrap_or(file)
+}
+
+fn align_down(offset: u64) -> u64 {
+    offset - offset % ALIGN as u64
+}
+
+/// 按 ALIGN 对齐的缓冲区
+struct AlignedBuffer {
+    ptr: *mut u8,
+}
+
+// 缓冲区只由拥有它的 DirectIo 访问
+unsafe impl Send for AlignedBuffer {}
+
+impl AlignedBuffer {
+    fn new() -> AlignedBuffer {
+        let layout = Self::layout();
+        let ptr = unsafe { alloc::alloc_zeroed(layout) };
+        if ptr.is_null() {
+            alloc::handle_alloc_error(layout);
+        }
+        AlignedBuffer { ptr }
+    }
+
+    fn layout() -> Layout {
+        Layout::from_size_align(BUFFER_LEN, ALIGN).expect("valid buffer layout")
+    }
+}
+
+impl std::ops::Deref for AlignedBuffer {
+    type Target = [u8];
+
+    fn deref(&self) -> &[u8] {
+        unsafe { std::slice::from_raw_parts(self.ptr, BUFFER_LEN) }
+    }
+}
+
+impl std::ops::DerefMut for AlignedBuffer {
+    fn deref_mut(&mut self) -> &mut [u8] {
+        unsafe { std::slice::from_raw_parts_mut(self.ptr, BUFFER_LEN) }
+    }
+}
+
+impl Drop for AlignedBuffer {
+    fn drop(&mut self) {
+        unsafe { alloc::dealloc(self.ptr, Self::layout()) };
+    }
+}
+
+#[cfg(all(unix, not(target_os = "macos")))]
+mod platform {
+    use std::fs::File;
+    use std::io;
+    use std::os::unix::fs::FileExt;
+    use std::os::unix::io::AsRawFd;
+
+    pub fn enable(file: &File) -> io::Result<Option<File>> {
+        set_direct(file, true)?;
+        Ok(None)
+    }
+
+    pub fn read_at(file: &File, buf: &mut [u8], offset: u64) -> io::Result<usize> {
+        loop {
+            match file.read_at(buf, offset) {
+                Err(err) if err.kind() == io::ErrorKind::Interrupted => {},
+                result => return result,
+            }
+        }
+    }
+
+    pub fn write_at(file: &File, buf: &[u8], offset: u64) -> io::Result<()> {
+        file.write_all_at(buf, offset)
+    }
+
+    /// 不足一个对齐单位的结尾临时关闭 O_DIRECT 写入
+    pub fn write_tail(file: &File, buf: &[u8], offset: u64) -> io::Result<()> {
+        set_direct(file, false)?;
+        let result = file.write_all_at(buf, offset);
+        set_direct(file, true)?;
+        result
+    }
+
+    fn set_direct(file: &File, enabled: bool) -> io::Result<()> {
+        let fd = file.as_raw_fd();
+        let flags = unsafe { libc::fcntl(fd, libc::F_GETFL) };
+        if flags < 0 {
+            return Err(io::Error::last_os_error());
+        }
+        let flags = match enabled {
+            true => flags | libc::O_DIRECT,
+            false => flags & !libc::O_DIRECT,
+        };
+        if unsafe { libc::fcntl(fd, libc::F_SETFL, flags) } < 0 {
+            return Err(io::Error::last_os_error());
+        }
+        Ok(())
+    }
+}
+
+#[cfg(target_os = "macos")]
+mod platform {
+    use std::fs::File;
+    use std::io;
+    use std::os::unix::fs::FileExt;
+    use std::os::unix::io::AsRawFd;
+
+    /// F_NOCACHE 不要求对齐，对齐的读写只是沿用同一套缓冲
+    pub fn enable(file: &File) -> io::Result<Option<File>> {
+        if unsafe { libc::fcntl(file.as_raw_fd(), libc::F_NOCACHE, 1) } < 0 {
+            return Err(io::Error::last_os_error());
+        }
+        Ok(None)
+    }
+
+    pub fn read_at(file: &File, buf: &mut [u8], offset: u64) -> io::Result<usize> {
+        loop {
+            match file.read_at(buf, offset) {
+                Err(err) if err.kind() == io::ErrorKind::Interrupted => {},
+                result => return result,
+            }
+        }
+    }
+
+    pub fn write_at(file: &File, buf: &[u8], offset: u64) -> io::Result<()> {
+        file.write_all_at(buf, offset)
+    }
+
+    pub fn write_tail(file: &File, buf: &[u8], offset: u64) -> io::Result<()> {
+        file.write_all_at(buf, offset)
+    }
+}
+
+#[cfg(windows)]
+mod platform {
+    use std::fs::File;
+    use std::io;
+    use std::os::windows::fs::FileExt;
+    use std::os::windows::io::{AsRawHandle, FromRawHandle, RawHandle};
+
+    const GENERIC_READ: u32 = 0x8000_0000;
+    const GENERIC_WRITE: u32 = 0x4000_0000;
+    const FILE_SHARE_ALL: u32 = 0x1 | 0x2 | 0x4;
+    const FILE_FLAG_NO_BUFFERING: u32 = 0x2000_0000;
+    const INVALID_HANDLE_VALUE: RawHandle = -1isize as RawHandle;
+
+    #[link(name = "kernel32")]
+    extern "system" {
+        fn ReOpenFile(original: RawHandle, access: u32, share_mode: u32, flags: u32) -> RawHandle;
+    }
+
+    /// 以 FILE_FLAG_NO_BUFFERING 重新打开同一个文件；只读打开的文件无法以写权限重新打开，此时只申请读权限
+    pub fn enable(file: &File) -> io::Result<Option<File>> {
+        for access in [GENERIC_READ | GENERIC_WRITE, GENERIC_READ] {
+            let handle = unsafe { ReOpenFile(file.as_raw_handle(), access, FILE_SHARE_ALL, FILE_FLAG_NO_BUFFERING) };
+            if handle != INVALID_HANDLE_VALUE && !handle.is_null() {
+                return Ok(Some(unsafe { File::from_raw_handle(handle) }));
+            }
+        }
+        Err(io::Error::last_os_error())
+    }
+
+    pub fn read_at(file: &File, buf: &mut [u8], offset: u64) -> io::Result<usize> {
+        file.seek_read(buf, offset)
+    }
+
+    pub fn write_at(file: &File, mut buf: &[u8], mut offset: u64) -> io::Result<()> {
+        while !buf.is_empty() {
+            let written = file.seek_write(buf, offset)?;
+            if written == 0 {
+                return Err(io::Error::from(io::ErrorKind::WriteZero));
+            }
+            buf = &buf[written..];
+            offset += written as u64;
+        }
+        Ok(())
+    }
+
+    /// 不足一个扇区的结尾通过原来的（经过缓存的）句柄写入
+    pub fn write_tail(file: &File, buf: &[u8], offset: u64) -> io::Result<()> {
+        write_at(file, buf, offset)
+    }
+}
+
+#[cfg(not(any(unix, windows)))]
+mod platform {
+    use std::fs::File;
+    use std::io;
+
+    pub fn enable(_file: &File) -> io::Result<Option<File>> {
+        Err(io::Error::from(io::ErrorKind::Unsupported))
+    }
+
+    pub fn read_at(_file: &File, _buf: &mut [u8], _offset: u64) -> io::Result<usize> {
+        Err(io::Error::from(io::ErrorKind::Unsupported))
+    }
+
+    pub fn write_at(_file: &File, _buf: &[u8], _offset: u64) -> io::Result<()> {
+        Err(io::Error::from(io::ErrorKind::Unsupported))
+    }
+
+    pub fn write_tail(_file: &File, _buf: &[u8], _offset: u64) -> io::Result<()> {
+        Err(io::Error::from(io::ErrorKind::Unsupported))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::fs::{self, OpenOptions};
+    use std::io::Seek;
+    use std::path::PathBuf;
+
+    use super::*;
+
+    struct TempFile(PathBuf);
+
+    impl TempFile {
+        fn new(name: &str, content: &[u8]) -> Self {
+            let path = std::env::temp_dir().join(format!("zippy-direct-{}-{}", std::process::id(), name));
+            fs::write(&path, content).unwrap();
+            TempFile(path)
+        }
+
+        fn open(&self) -> File {
+            OpenOptions::new().read(true).write(true).open(&self.0).unwrap()
+        }
+    }
+
+    impl Drop for TempFile {
+        fn drop(&mut self) {
+            let _ = fs::remove_file(&self.0);
+        }
+    }
+
+    fn pattern(len: usize, seed: u8) -> Vec<u8> {
+        (0..len).map(|i| (i as u8).wrapping_mul(31).wrapping_add(seed)).collect()
+    }
+
+    /// 开启直接 I/O；临时目录的文件系统（如 tmpfs）不支持时返回 None，测试跳过
+    fn direct(file: &File) -> Option<DirectIo> {
+        DirectIo::new(file).ok()
+    }
+
+    fn write_all(io: &mut DirectIo, file: &File, mut data: &[u8]) {
+        while !data.is_empty() {
+            let written = io.write(file, data).unwrap();
+            data = &data[written..];
+        }
+    }
+
+    fn read_to_end(io: &mut DirectIo, file: &File, piece: usize) -> Vec<u8> {
+        let mut data = Vec::new();
+        let mut buffer = vec![0u8; piece];
+        loop {
+            match io.read(file, &mut buffer).unwrap() {
+                0 => return data,
+                read => data.extend_from_slice(&buffer[..read]),
+            }
+        }
+    }
+
+    #[test]
+    fn writes_unaligned_lengths() {
+        let temp = TempFile::new("write", b"");
+        let file = temp.open();
+        let Some(mut io) = direct(&file) else { return };
+        // 跨过缓冲区边界，结尾不足一个对齐单位
+        let data = pattern(BUFFER_LEN * 2 + ALIGN + 123, 1);
+        for piece in data.chunks(77_777) {
+            write_all(&mut io, &file, piece);
+        }
+        io.flush(&file).unwrap();
+        assert_eq!(fs::read(&temp.0).unwrap(), data);
+
+        // 结尾之后继续写入：保留在缓冲区中的结尾整块重新写出
+        write_all(&mut io, &file, b"more");
+        io.flush(&file).unwrap();
+        assert_eq!(fs::read(&temp.0).unwrap(), [&data[..], b"more"].concat());
+    }
+
+    #[test]
+    fn reads_in_odd_pieces() {
+        let data = pattern(BUFFER_LEN + 5000, 2);
+        let temp = TempFile::new("read", &data);
+        let file = temp.open();
+        let Some(mut io) = direct(&file) else { return };
+        assert_eq!(read_to_end(&mut io, &file, 999), data);
+
+        io.seek(&file, SeekFrom::Start(4097)).unwrap();
+        assert_eq!(read_to_end(&mut io, &file, 4096), data[4097..]);
+        io.seek(&file, SeekFrom::End(-10)).unwrap();
+        assert_eq!(read_to_end(&mut io, &file, 3), data[data.len() - 10..]);
+        assert!(io.seek(&file, SeekFrom::Current(-(data.len() as i64) - 1)).is_err());
+    }
+
+    #[test]
+    fn overwrites_in_the_middle() {
+        let data = pattern(3 * ALIGN, 3);
+        let temp = TempFile::new("overwrite", &data);
+        let file = temp.open();
+        let Some(mut io) = direct(&file) else { return };
+
+        // 从不对齐的位置写入，对齐位置到写入位置之间已有的内容保持不变
+        io.seek(&file, SeekFrom::Start(ALIGN as u64 + 10)).unwrap();
+        write_all(&mut io, &file, b"patched");
+        io.seek(&file, SeekFrom::Start(0)).unwrap();
+        let read = read_to_end(&mut io, &file, 1000);
+        let mut expected = data.clone();
+        expected[ALIGN + 10..ALIGN + 17].copy_from_slice(b"patched");
+        assert_eq!(read, expected);
+        assert_eq!(fs::read(&temp.0).unwrap(), expected);
+    }
+
+    #[test]
+    fn starts_at_the_current_position() {
+        let temp = TempFile::new("position", b"header");
+        let file = temp.open();
+        (&file).seek(SeekFrom::End(0)).unwrap();
+        let Some(mut io) = direct(&file) else { return };
+        write_all(&mut io, &file, b" body");
+        io.flush(&file).unwrap();
+        assert_eq!(fs::read(&temp.0).unwrap(), b"header body");
+        assert_eq!((&file).stream_position().unwrap(), 11);
+    }
+}
@@ -0,0 +1,478 @@
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use aes::cipher::KeyInit;
+use aes::Aes256;
+use base64::engine::general_purpose::URL_SAFE_NO_PAD;
+use base64::Engine;
+use serde_json::{json, Value};
+
+use crate::crypto::{open_field, seal_field, subkey};
+use crate::eme;
+use crate::errors::{open_error, read_error, CodedError};
+use crate::glob::{PathFilter, IGNORE_FILE_NAME};
+use crate::store::write_atomically;
+use crate::walk::{self, InputEntry};
+
+/// 加密目录根部的清单文件名；加密文件都带有 `.enc` 后缀，不会与清单重名
+pub const MANIFEST_NAME: &str = ".zippy-manifest";
+/// 每个文件加密后的文件名后缀
+pub const ENCRYPTED_SUFFIX: &str = ".enc";
+/// 清单文件开头的魔数
+const MANIFEST_MAGIC: &[u8] = b"ZIPPYDIR";
+const MANIFEST_VERSION: u8 = 1;
+/// 清单加密使用的字段用途
+const FIELD_MANIFEST: &str = "directory manifest";
+/// 加密后的文件名（含 `.enc` 后缀）的最大长度，更长时改用名称的哈希
+const NAME_MAX: usize = 255;
+
+/// 清单中记录的文件
+pub struct ManifestFile {
+    /// 相对于源目录的路径，使用 `/` 分隔
+    pub path: String,
+    /// 加密文件相对于目标目录的路径
+    pub encrypted_path: String,
+    pub size: u64,
+    pub mode: u32,
+    pub modified: u64,
+    /// 明文 SHA-256（十六进制）
+    pub sha256: String,
+}
+
+/// 要加密的目录树：目录和文件按路径排序，名称相对于源目录
+pub struct DirectoryTree {
+    pub directories: Vec<InputEntry>,
+    pub files: Vec<InputEntry>,
+}
+
+/// 加密目录时的路径过滤：源目录根部的 `.zippyignore` 和 ignore_files 中的模式加入排除规则
+pub fn source_filter(input_dir: &str, include: Option<&[String]>, exclude: Option<&[String]>, ignore_files: Option<&[String]>) -> Result<PathFilter, CodedError> {
+    let mut filter = PathFilter::new(include, exclude)?;
+    let root_ignore = Path::new(input_dir).join(IGNORE_FILE_NAME);
+    if root_ignore.is_file() {
+        filter.add_ignore_file(&root_ignore)?;
+    }
+    for path in ignore_files.unwrap_or_default() {
+        filter.add_ignore_file(Path::new(path))?;
+    }
+    Ok(filter)
+}
+
+/// 检查源目录和目标目录并收集源目录中的文件；目标目录必须为空或不存在，且不能位于源目录之内
+///
+/// 返回目录树和新创建的目标目录（出错时由调用方删除）；dry_run 时只做检查，不创建目标目录。
+pub fn prepare(input_dir: &str, output_dir: &str, filter: &PathFilter, dry_run: bool) -> Result<(DirectoryTree, Option<PathBuf>), CodedError> {
+    if !fs::metadata(input_dir).map_err(|e| read_error(&format!("reading {}", input_dir), e))?.is_dir() {
+        return Err(CodedError::invalid_argument(format!("Input is not a directory: {}", input_dir)));
+    }
+    let input = fs::canonicalize(input_dir).map_err(|e| read_error(&format!("reading {}", input_dir), e))?;
+    let output = Path::new(output_dir);
+    let created = match fs::read_dir(output) {
+        Ok(mut entries) => {
+            if entries.next().is_some() {
+                return Err(CodedError::invalid_argument(format!("Output directory is not empty: {}", output_dir)));
+            }
+            None
+        }
+        Err(err) if err.kind() == std::io::ErrorKind::NotFound && dry_run => None,
+        Err(err) if err.kind() == std::io::ErrorKind::NotFound => {
+            fs::create_dir_all(output).map_err(|e| open_error(&format!("creating directory {}", output_dir), e))?;
+            Some(output.to_path_buf())
+        }
+        Err(err) => return Err(read_error(&format!("reading directory {}", output_dir), err)),
+    };
+    let inside = canonicalize_missing(output).is_some_and(|output| output.starts_with(&input));
+    if inside {
+        if let Some(created) = &created {
+            let _ = fs::remove_dir(created);
+        }
+        return Err(CodedError::invalid_argument(format!("Output directory {} must not be inside the input directory", output_dir)));
+    }
+
+    let tree = collect_tree(&input, filter).inspect_err(|_| {
+        if let Some(created) = &created {
+            let _ = fs::remove_dir(created);
+        }
+    })?;
+    Ok((tree, created))
+}
+
+/// 解析路径中已存在的最深一级祖先目录，再接上其余不存在的部分；dryRun 时目标目录可能尚不存在
+fn canonicalize_missing(path: &Path) -> Option<PathBuf> {
+    let mut missing = Vec::new();
+    let mut ancestor = path;
+    loop {
+        if let Ok(resolved) = fs::canonicalize(ancestor) {
+            return Some(missing.iter().rev().fold(resolved, |path, name| path.join(name)));
+        }
+        missing.push(ancestor.file_name()?);
+        ancestor = ancestor.parent().filter(|parent| !parent.as_os_str().is_empty()).unwrap_or(Path::new("."));
+    }
+}
+
+/// 收集目录中按过滤条件选中的目录和文件，名称相对于该目录
+pub fn collect_tree(input_dir: &Path, filter: &PathFilter) -> Result<DirectoryTree, CodedError> {
+    if !fs::metadata(input_dir).map_err(|e| read_error(&format!("reading {}", input_dir.display()), e))?.is_dir() {
+        return Err(CodedError::invalid_argument(format!("Input is not a directory: {}", input_dir.display())));
+    }
+    // collect() 的名称以源目录本身的名称开头，去掉这一层得到相对路径；被排除的目录不再遍历
+    let relative = |name: &str| name.split_once('/').map_or_else(String::new, |(_, name)| name.to_string());
+    let skip = |name: &str, is_dir: bool| filter.excluded(&relative(name), is_dir);
+    let mut tree = DirectoryTree { directories: Vec::new(), files: Vec::new() };
+    for mut entry in walk::collect_filtered(&[input_dir.to_string_lossy().into_owned()], "directory", &skip)?.into_iter().skip(1) {
+        entry.name = relative(&entry.name);
+        if entry.is_dir {
+            entry.name.pop();
+            tree.directories.push(entry);
+        } else {
+            tree.files.push(entry);
+        }
+    }
+    filter.retain(&mut tree.directories, &mut tree.files, |dir| dir.name.as_str(), |file| file.name.as_str());
+    Ok(tree)
+}
+
+/// 相对路径在目标目录中对应的路径
+pub fn output_path(output_dir: &str, name: &str) -> PathBuf {
+    name.split('/').fold(PathBuf::from(output_dir), |path, part| path.join(part))
+}
+
+/// 加密目录时的文件名加密：每段名称以 PKCS#7 填充后用 EME 加密，再以无填充的 URL 安全 base64 编码
+///
+/// tweak 是所在目录的 IV，由目录的明文相对路径经 keyed BLAKE3 计算得到，因此同一目录中相同的名称总是得到相同的密文，
+/// 不同目录中的相同名称则不同。加密后过长的名称改用 keyed 哈希。解密时通过清单把加密后的路径映射回原始路径。
+pub struct NameEncryptor {
+    cipher: Aes256,
+    iv_key: [u8; 32],
+}
+
+impl NameEncryptor {
+    pub fn new(key: &[u8]) -> Self {
+        let name_key = subkey(key, "zippy directory names");
+        NameEncryptor { cipher: Aes256::new(&name_key.into()), iv_key: subkey(key, "zippy directory IVs") }
+    }
+
+    fn dir_iv(&self, dir: &str) -> [u8; eme::BLOCK_LEN] {
+        blake3::keyed_hash(&self.iv_key, dir.as_bytes()).as_bytes()[..eme::BLOCK_LEN].try_into().unwrap()
+    }
+
+    fn encrypt_name(&self, name: &str, dir_iv: &[u8; eme::BLOCK_LEN]) -> String {
+        let encrypted = eme::pad_name(name.as_bytes()).map(|padded| URL_SAFE_NO_PAD.encode(eme::transform(&self.cipher, dir_iv, &padded, eme::Direction::Encrypt)));
+        match encrypted {
+            Some(encrypted) if encrypted.len() + ENCRYPTED_SUFFIX.len() <= NAME_MAX => encrypted,
+            _ => URL_SAFE_NO_PAD.encode(blake3::keyed_hash(&self.iv_key, &[dir_iv.as_slice(), name.as_bytes()].concat()).as_bytes()),
+        }
+    }
+
+    /// 把明文相对路径的每一段分别加密，返回加密后的相对路径
+    pub fn encrypt_path(&self, path: &str) -> String {
+        let mut dir = String::new();
+        let mut segments = Vec::new();
+        for segment in path.split('/') {
+            segments.push(self.encrypt_name(segment, &self.dir_iv(&dir)));
+            if !dir.is_empty() {
+                dir.push('/');
+            }
+            dir.push_str(segment);
+        }
+        segments.join("/")
+    }
+}
+
+/// 清单中记录的目录
+pub struct ManifestDirectory {
+    pub path: String,
+    pub mode: u32,
+    pub modified: u64,
+}
+
+/// 解密后的清单
+pub struct Manifest {
+    pub algorithm: String,
+    pub created_at: u64,
+    pub directories: Vec<ManifestDirectory>,
+    pub files: Vec<ManifestFile>,
+}
+
+impl Manifest {
+    pub fn new(algorithm: &str, directories: &[InputEntry], files: Vec<ManifestFile>) -> Self {
+        let created_at = std::time::SystemTime::now()
+            .duration_since(std::time::UNIX_EPOCH)
+            .map(|elapsed| elapsed.as_millis() as u64)
+            .unwrap_or(0);
+        Manifest {
+            algorithm: algorithm.to_string(),
+            created_at,
+            directories: directories
+                .iter()
+                .map(|dir| ManifestDirectory { path: dir.name.clone(), mode: dir.mode, modified: dir.modified })
+                .collect(),
+            files,
+        }
+    }
+
+    fn to_json(&self) -> Value {
+        let directories: Vec<Value> = self
+            .directories
+            .iter()
+            .map(|dir| json!({ "path": dir.path, "mode": dir.mode, "modified": dir.modified }))
+            .collect();
+        let files: Vec<Value> = self
+            .files
+            .iter()
+            .map(|file| {
+                json!({
+                    "path": file.path,
+                    "encryptedPath": file.encrypted_path,
+                    "size": file.size,
+                    "mode": file.mode,
+                    "modified": file.modified,
+                    "sha256": file.sha256,
+                })
+            })
+            .collect();
+        json!({
+            "createdAt": self.created_at,
+            "algorithm": self.algorithm,
+            "directories": directories,
+            "files": files,
+        })
+    }
+
+    fn from_json(manifest: &Value) -> Result<Self, CodedError> {
+        let invalid = |what: &str| CodedError::bad_format(format!("Invalid directory manifest: {}", what));
+        let entries = |name: &str| manifest[name].as_array().ok_or_else(|| invalid(name));
+        let string = |entry: &Value, name: &str| entry[name].as_str().map(str::to_string).ok_or_else(|| invalid(name));
+        let number = |entry: &Value, name: &str| entry[name].as_u64().ok_or_else(|| invalid(name));
+        let directories = entries("directories")?
+            .iter()
+            .map(|dir| {
+                Ok(ManifestDirectory {
+                    path: string(dir, "path")?,
+                    mode: number(dir, "mode")? as u32,
+                    modified: number(dir, "modified")?,
+                })
+            })
+            .collect::<Result<_, CodedError>>()?;
+        let files = entries("files")?
+            .iter()
+            .map(|file| {
+                Ok(ManifestFile {
+                    path: string(file, "path")?,
+                    encrypted_path: string(file, "encryptedPath")?,
+                    size: number(file, "size")?,
+                    mode: number(file, "mode")? as u32,
+                    modified: number(file, "modified")?,
+                    sha256: string(file, "sha256")?,
+                })
+            })
+            .collect::<Result<_, CodedError>>()?;
+        Ok(Manifest {
+            algorithm: string(manifest, "algorithm")?,
+            created_at: manifest["createdAt"].as_u64().unwrap_or(0),
+            directories,
+            files,
+        })
+    }
+
+    /// 按过滤条件保留要还原的目录和文件；include 中的模式没有选中任何路径时返回错误
+    pub fn select(mut self, filter: &PathFilter) -> Result<Self, CodedError> {
+        let paths = self.directories.iter().map(|dir| (dir.path.as_str(), true)).chain(self.files.iter().map(|file| (file.path.as_str(), false)));
+        if let Some(pattern) = filter.unmatched_include(paths) {
+            return Err(CodedError::invalid_argument(format!("Path not found in directory manifest: {}", pattern)));
+        }
+        filter.retain(&mut self.directories, &mut self.files, |dir| dir.path.as_str(), |file| file.path.as_str());
+        Ok(self)
+    }
+}
+
+/// 加密清单并写入目标目录根部：记录每个文件的相对路径、大小、权限、修改时间和明文 SHA-256
+pub fn write_manifest(output_dir: &str, key: &[u8], manifest: &Manifest) -> Result<PathBuf, CodedError> {
+    let sealed = seal_field(key, FIELD_MANIFEST, manifest.to_json().to_string().as_bytes())?;
+    let path = Path::new(output_dir).join(MANIFEST_NAME);
+    write_atomically(&path, &[MANIFEST_MAGIC, &[MANIFEST_VERSION], &sealed].concat())?;
+    Ok(path)
+}
+
+/// 读取并解密加密目录根部的清单
+pub fn read_manifest(input_dir: &str, key: &[u8]) -> Result<Manifest, CodedError> {
+    let path = Path::new(input_dir).join(MANIFEST_NAME);
+    let bytes = fs::read(&path).map_err(|e| CodedError::io_read(format!("Failed to read manifest {}: {}", path.display(), e)))?;
+    if !bytes.starts_with(MANIFEST_MAGIC) || bytes.len() <= MANIFEST_MAGIC.len() {
+        return Err(CodedError::bad_header(format!("{} is not a directory manifest", path.display())));
+    }
+    if bytes[MANIFEST_MAGIC.len()] != MANIFEST_VERSION {
+        return Err(CodedError::unsupported(format!("Unsupported manifest version {}", bytes[MANIFEST_MAGIC.len()])));
+    }
+    let json = open_field(key, FIELD_MANIFEST, &bytes[MANIFEST_MAGIC.len() + 1..])?;
+    let manifest = serde_json::from_slice(&json).map_err(|e| CodedError::bad_format(format!("Invalid directory manifest: {}", e)))?;
+    Manifest::from_json(&manifest)
+}
+
+/// 还原文件或目录的权限和修改时间，失败时忽略
+///
+/// 先设置修改时间再设置权限，这样只读文件的修改时间也能还原。
+pub fn restore_metadata(path: &Path, mode: u32, modified: u64) {
+    let modified = std::time::UNIX_EPOCH + std::time::Duration::from_secs(modified);
+    if let Ok(file) = fs::File::options().write(path.is_file()).read(true).open(path) {
+        let _ = file.set_modified(modified);
+    }
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::PermissionsExt;
+        let _ = fs::set_permissions(path, fs::Permissions::from_mode(mode & 0o777));
+    }
+    #[cfg(not(unix))]
+    let _ = mode;
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::errors::ErrorCode;
+
+    const KEY: [u8; 32] = [9; 32];
+
+    struct TempDir(PathBuf);
+
+    impl TempDir {
+        fn new(name: &str) -> Self {
+            let path = std::env::temp_dir().join(format!("zippy-directory-{}-{}", std::process::id(), name));
+            let _ = fs::remove_dir_all(&path);
+            fs::create_dir_all(&path).unwrap();
+            TempDir(path)
+        }
+
+        fn path(&self) -> String {
+            self.0.to_string_lossy().into_owned()
+        }
+
+        fn write(&self, name: &str, content: &[u8]) {
+            let path = output_path(&self.path(), name);
+            fs::create_dir_all(path.parent().unwrap()).unwrap();
+            fs::write(path, content).unwrap();
+        }
+    }
+
+    impl Drop for TempDir {
+        fn drop(&mut self) {
+            let _ = fs::remove_dir_all(&self.0);
+        }
+    }
+
+    fn sample_manifest() -> Manifest {
+        Manifest {
+            algorithm: "aes".to_string(),
+            created_at: 1_700_000_000_000,
+            directories: vec![ManifestDirectory { path: "docs".to_string(), mode: 0o755, modified: 1_600_000_000 }],
+            files: vec![ManifestFile {
+                path: "docs/a.txt".to_string(),
+                encrypted_path: "x/y.enc".to_string(),
+                size: 3,
+                mode: 0o644,
+                modified: 1_600_000_001,
+                sha256: "ab".repeat(32),
+            }],
+        }
+    }
+
+    #[test]
+    fn encrypts_names_per_directory() {
+        let names = NameEncryptor::new(&KEY);
+        let path = names.encrypt_path("docs/readme.txt");
+        assert_eq!(path, names.encrypt_path("docs/readme.txt"));
+        let segments: Vec<_> = path.split('/').collect();
+        assert_eq!(segments.len(), 2);
+        assert!(!path.contains("readme"));
+        // 同一名称在不同目录中加密结果不同
+        assert_ne!(segments[1], names.encrypt_path("other/readme.txt").split('/').nth(1).unwrap());
+        assert_eq!(segments[0], names.encrypt_path("docs").as_str());
+        assert_ne!(path, NameEncryptor::new(&[8; 32]).encrypt_path("docs/readme.txt"));
+
+        // 过长的名称改用哈希
+        let long = names.encrypt_path(&"n".repeat(250));
+        assert!(long.len() + ENCRYPTED_SUFFIX.len() <= NAME_MAX);
+        assert_ne!(long, names.encrypt_path(&"n".repeat(251)));
+    }
+
+    #[test]
+    fn round_trips_the_manifest() {
+        let dir = TempDir::new("manifest");
+        let path = write_manifest(&dir.path(), &KEY, &sample_manifest()).unwrap();
+        assert_eq!(path, dir.0.join(MANIFEST_NAME));
+        let manifest = read_manifest(&dir.path(), &KEY).unwrap();
+        assert_eq!((manifest.algorithm.as_str(), manifest.created_at), ("aes", 1_700_000_000_000));
+        assert_eq!(manifest.directories[0].path, "docs");
+        let file = &manifest.files[0];
+        assert_eq!((file.path.as_str(), file.encrypted_path.as_str(), file.size, file.mode), ("docs/a.txt", "x/y.enc", 3, 0o644));
+
+        assert_eq!(read_manifest(&dir.path(), &[1; 32]).err().unwrap().code, ErrorCode::AuthFailed);
+        let mut bytes = fs::read(&path).unwrap();
+        bytes[MANIFEST_MAGIC.len()] = 2;
+        fs::write(&path, &bytes).unwrap();
+        assert_eq!(read_manifest(&dir.path(), &KEY).err().unwrap().code, ErrorCode::Unsupported);
+        fs::write(&path, MANIFEST_MAGIC).unwrap();
+        assert_eq!(read_manifest(&dir.path(), &KEY).err().unwrap().code, ErrorCode::BadHeader);
+        fs::write(&path, b"not a manifest").unwrap();
+        assert_eq!(read_manifest(&dir.path(), &KEY).err().unwrap().code, ErrorCode::BadHeader);
+        fs::remove_file(&path).unwrap();
+        assert_eq!(read_manifest(&dir.path(), &KEY).err().unwrap().code, ErrorCode::IoRead);
+    }
+
+    #[test]
+    fn rejects_incomplete_manifest_json() {
+        let mut json = sample_manifest().to_json();
+        assert!(Manifest::from_json(&json).is_ok());
+        json["files"][0].as_object_mut().unwrap().remove("sha256");
+        assert_eq!(Manifest::from_json(&json).err().unwrap().code, ErrorCode::BadFormat);
+        assert_eq!(Manifest::from_json(&json!({ "algorithm": "aes", "files": [] })).err().unwrap().code, ErrorCode::BadFormat);
+    }
+
+    #[test]
+    fn selects_manifest_entries() {
+        let filter = PathFilter::new(Some(&["docs/a.txt".to_string()]), None).unwrap();
+        assert_eq!(sample_manifest().select(&filter).unwrap().files.len(), 1);
+        let filter = PathFilter::new(None, Some(&["*.txt".to_string()])).unwrap();
+        assert!(sample_manifest().select(&filter).unwrap().files.is_empty());
+        let filter = PathFilter::new(Some(&["missing".to_string()]), None).unwrap();
+        assert_eq!(sample_manifest().select(&filter).err().unwrap().code, ErrorCode::InvalidArgument);
+    }
+
+    #[test]
+    fn prepares_the_source_tree() {
+        let source = TempDir::new("source");
+        source.write("b.txt", b"b");
+        source.write("sub/a.txt", b"a");
+        source.write("sub/skip.log", b"log");
+        source.write(IGNORE_FILE_NAME, b"*.log\n");
+        let target = TempDir::new("target");
+        let output = target.0.join("out").to_string_lossy().into_owned();
+
+        let filter = source_filter(&source.path(), None, None, None).unwrap();
+        let (tree, created) = prepare(&source.path(), &output, &filter, true).unwrap();
+        assert!(created.is_none() && !Path::new(&output).exists());
+        let files: Vec<_> = tree.files.iter().map(|file| file.name.as_str()).collect();
+        assert!(files.contains(&"b.txt") && files.contains(&"sub/a.txt"));
+        assert!(!files.contains(&"sub/skip.log"));
+        assert_eq!(tree.directories.iter().map(|dir| dir.name.as_str()).collect::<Vec<_>>(), ["sub"]);
+
+        let (_, created) = prepare(&source.path(), &output, &filter, false).unwrap();
+        assert_eq!(created.as_deref(), Some(Path::new(&output)));
+        fs::write(Path::new(&output).join("file"), b"x").unwrap();
+        assert_eq!(prepare(&source.path(), &output, &filter, false).err().unwrap().code, ErrorCode::InvalidArgument);
+    }
+
+    #[test]
+    fn rejects_an_output_inside_the_input() {
+        let source = TempDir::new("nested");
+        source.write("a.txt", b"a");
+        let filter = PathFilter::new(None, None).unwrap();
+        let inside = source.0.join("out/deeper").to_string_lossy().into_owned();
+        assert_eq!(prepare(&source.path(), &inside, &filter, true).err().unwrap().code, ErrorCode::InvalidArgument);
+        let inside = source.0.join("out").to_string_lossy().into_owned();
+        assert_eq!(prepare(&source.path(), &inside, &filter, false).err().unwrap().code, ErrorCode::InvalidArgument);
+        // 新创建的目标目录被删除
+        assert!(!Path::new(&inside).exists());
+        assert_eq!(prepare(&source.0.join("a.txt").to_string_lossy(), "unused", &filter, true).err().unwrap().code, ErrorCode::InvalidArgument);
+    }
+}
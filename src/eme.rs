@@ -0,0 +1,154 @@
+use aes::cipher::{BlockDecrypt, BlockEncrypt};
+use aes::Aes256;
+
+/// EME 的分组长度；数据长度必须是它的整数倍
+pub const BLOCK_LEN: usize = 16;
+/// EME 最多处理 128 个分组
+pub const MAX_BLOCKS: usize = 128;
+
+#[derive(Clone, Copy, PartialEq)]
+pub enum Direction {
+    Encrypt,
+    Decrypt,
+}
+
+/// GF(2^128) 中乘以 2（小端表示，与 EME 规范相同）
+fn mult_by_two(block: &mut [u8; BLOCK_LEN]) {
+    let carry = block[15] >> 7;
+    for index in (1..BLOCK_LEN).rev() {
+        block[index] = (block[index] << 1) | (block[index - 1] >> 7);
+    }
+    block[0] = (block[0] << 1) ^ (carry * 135);
+}
+
+fn xor_block(target: &mut [u8], other: &[u8]) {
+    target.iter_mut().zip(other).for_each(|(byte, other)| *byte ^= other);
+}
+
+/// EME（Halevi-Rogaway 宽分组加密模式）：把 16 字节整数倍的数据作为一个整体加密或解密，
+/// 与 rclone crypt 和 gocryptfs 加密文件名使用的 github.com/rfjakob/eme 相同
+pub fn transform(cipher: &Aes256, tweak: &[u8; BLOCK_LEN], data: &[u8], direction: Direction) -> Vec<u8> {
+    let aes = |block: &mut [u8]| {
+        let block = block.into();
+        match direction {
+            Direction::Encrypt => cipher.encrypt_block(block),
+            Direction::Decrypt => cipher.decrypt_block(block),
+        }
+    };
+    let blocks = data.len() / BLOCK_LEN;
+    // L_j = 2^(j+1) * AES-enc(0)
+    let mut l = [0u8; BLOCK_LEN];
+    cipher.encrypt_block((&mut l).into());
+    let l_table: Vec<[u8; BLOCK_LEN]> = (0..blocks)
+        .map(|_| {
+            mult_by_two(&mut l);
+            l
+        })
+        .collect();
+
+    let mut output = data.to_vec();
+    for (block, l) in output.chunks_exact_mut(BLOCK_LEN).zip(&l_table) {
+        xor_block(block, l);
+        aes(block);
+    }
+    let mut mp = *tweak;
+    output.chunks_exact(BLOCK_LEN).for_each(|block| xor_block(&mut mp, block));
+    let mut mc = mp;
+    aes(&mut mc);
+    let mut m = mp;
+    xor_block(&mut m, &mc);
+    for block in output.chunks_exact_mut(BLOCK_LEN).skip(1) {
+        mult_by_two(&mut m);
+        xor_block(block, &m);
+    }
+    let mut first = mc;
+    xor_block(&mut first, tweak);
+    output.chunks_exact(BLOCK_LEN).skip(1).for_each(|block| xor_block(&mut first, block));
+    output[..BLOCK_LEN].copy_from_slice(&first);
+    for (block, l) in output.chunks_exact_mut(BLOCK_LEN).zip(&l_table) {
+        aes(block);
+        xor_block(block, l);
+    }
+    output
+}
+
+/// 文件名在加密前以 PKCS#7 填充到分组长度的整数倍；超过 EME 的最大长度时返回 None
+pub fn pad_name(name: &[u8]) -> Option<Vec<u8>> {
+    let pad = BLOCK_LEN - name.len() % BLOCK_LEN;
+    if name.len() + pad > BLOCK_LEN * MAX_BLOCKS {
+        return None;
+    }
+    let mut padded = name.to_vec();
+    padded.resize(name.len() + pad, pad as u8);
+    Some(padded)
+}
+
+/// 去除 PKCS#7 填充；填充无效（通常是密钥错误）时返回 None
+pub fn unpad_name(mut padded: Vec<u8>) -> Option<Vec<u8>> {
+    let pad = *padded.last()? as usize;
+    if pad == 0 || pad > BLOCK_LEN || pad > padded.len() || !padded[padded.len() - pad..].iter().all(|&byte| byte as usize == pad) {
+        return None;
+    }
+    padded.truncate(padded.len() - pad);
+    Some(padded)
+}
+
+#[cfg(test)]
+mod tests {
+    use aes::cipher::KeyInit;
+
+    use super::*;
+
+    // 与 rclone 生成的文件名的比对在 rclone 模块的测试中
+    fn cipher() -> Aes256 {
+        Aes256::new(&[7u8; 32].into())
+    }
+
+    #[test]
+    fn decrypts_what_it_encrypts() {
+        let cipher = cipher();
+        let tweak = [1u8; BLOCK_LEN];
+        for blocks in [1, 2, 3, 16, MAX_BLOCKS] {
+            let data: Vec<u8> = (0..blocks * BLOCK_LEN).map(|i| i as u8).collect();
+            let encrypted = transform(&cipher, &tweak, &data, Direction::Encrypt);
+            assert_eq!(encrypted.len(), data.len());
+            assert_ne!(encrypted, data);
+            assert_eq!(transform(&cipher, &tweak, &encrypted, Direction::Decrypt), data);
+        }
+    }
+
+    #[test]
+    fn is_a_wide_block_cipher() {
+        let cipher = cipher();
+        let tweak = [0u8; BLOCK_LEN];
+        let data = [0u8; 4 * BLOCK_LEN];
+        let encrypted = transform(&cipher, &tweak, &data, Direction::Encrypt);
+        // 改动最后一个字节，所有分组都会变化
+        let mut changed = data;
+        changed[data.len() - 1] ^= 1;
+        let changed = transform(&cipher, &tweak, &changed, Direction::Encrypt);
+        for (a, b) in encrypted.chunks(BLOCK_LEN).zip(changed.chunks(BLOCK_LEN)) {
+            assert_ne!(a, b);
+        }
+        // 相同的数据在不同的 tweak 下加密结果不同
+        assert_ne!(transform(&cipher, &[1u8; BLOCK_LEN], &data, Direction::Encrypt), encrypted);
+        // 相同的明文分组加密后也各不相同
+        assert_ne!(encrypted[..BLOCK_LEN], encrypted[BLOCK_LEN..2 * BLOCK_LEN]);
+    }
+
+    #[test]
+    fn pads_names() {
+        assert_eq!(pad_name(b"").unwrap(), [16u8; 16]);
+        assert_eq!(pad_name(b"abc").unwrap(), [&b"abc"[..], &[13u8; 13]].concat());
+        assert_eq!(pad_name(&[b'x'; 16]).unwrap().len(), 32);
+        assert_eq!(pad_name(&[b'x'; BLOCK_LEN * MAX_BLOCKS - 1]).unwrap().len(), BLOCK_LEN * MAX_BLOCKS);
+        assert!(pad_name(&[b'x'; BLOCK_LEN * MAX_BLOCKS]).is_none());
+
+        for name in [&b""[..], b"abc", &[b'x'; 16]] {
+            assert_eq!(unpad_name(pad_name(name).unwrap()).unwrap(), name);
+        }
+        for invalid in [vec![], vec![0u8; 16], vec![17u8; 16], [&[1u8; 14][..], &[3, 2]].concat(), vec![5u8; 3]] {
+            assert!(unpad_name(invalid).is_none());
+        }
+    }
+}
@@ -0,0 +1,276 @@
+use std::{fmt, io};
+
+use napi::bindgen_prelude::*;
+use napi::{Env, JsError};
+use napi_derive::napi;
+
+/// 抛出的 JS 错误的 `code` 属性，错误信息保持不变
+#[napi(string_enum)]
+#[derive(Debug, PartialEq)]
+pub enum ErrorCode {
+    /// 密钥、IV、KID 等长度不正确
+    #[napi(value = "ERR_INVALID_KEY_LENGTH")]
+    InvalidKeyLength,
+    /// 参数或选项无效，或选项之间互相冲突
+    #[napi(value = "ERR_INVALID_ARGUMENT")]
+    InvalidArgument,
+    /// 文件头或结尾字段无效
+    #[napi(value = "ERR_BAD_HEADER")]
+    BadHeader,
+    /// 输入不是预期的格式，或内容已损坏
+    #[napi(value = "ERR_BAD_FORMAT")]
+    BadFormat,
+    /// 文件被截断
+    #[napi(value = "ERR_TRUNCATED")]
+    Truncated,
+    /// 不支持的格式版本、算法或特性
+    #[napi(value = "ERR_UNSUPPORTED")]
+    Unsupported,
+    /// 认证失败：密钥、密码错误或数据被篡改
+    #[napi(value = "ERR_AUTH_FAILED")]
+    AuthFailed,
+    /// 违反解密策略或配置中的算法限制
+    #[napi(value = "ERR_POLICY")]
+    Policy,
+    /// 无法打开输入文件或创建输出文件
+    #[napi(value = "ERR_IO_OPEN")]
+    IoOpen,
+    #[napi(value = "ERR_IO_READ")]
+    IoRead,
+    #[napi(value = "ERR_IO_WRITE")]
+    IoWrite,
+    #[napi(value = "ERR_TIMEOUT")]
+    Timeout,
+    #[napi(value = "ERR_ABORTED")]
+    Aborted,
+    #[napi(value = "ERR_UNKNOWN")]
+    Unknown,
+}
+
+impl ErrorCode {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            ErrorCode::InvalidKeyLength => "ERR_INVALID_KEY_LENGTH",
+            ErrorCode::InvalidArgument => "ERR_INVALID_ARGUMENT",
+            ErrorCode::BadHeader => "ERR_BAD_HEADER",
+            ErrorCode::BadFormat => "ERR_BAD_FORMAT",
+            ErrorCode::Truncated => "ERR_TRUNCATED",
+            ErrorCode::Unsupported => "ERR_UNSUPPORTED",
+            ErrorCode::AuthFailed => "ERR_AUTH_FAILED",
+            ErrorCode::Policy => "ERR_POLICY",
+            ErrorCode::IoOpen => "ERR_IO_OPEN",
+            ErrorCode::IoRead => "ERR_IO_READ",
+            ErrorCode::IoWrite => "ERR_IO_WRITE",
+            ErrorCode::Timeout => "ERR_TIMEOUT",
+            ErrorCode::Aborted => "ERR_ABORTED",
+            ErrorCode::Unknown => "ERR_UNKNOWN",
+        }
+    }
+}
+
+impl AsRef<str> for ErrorCode {
+    fn as_ref(&self) -> &str {
+        self.as_str()
+    }
+}
+
+/// 带错误码的错误：错误码在产生错误的地方确定，抛出的 JS 错误的 `code` 和 `message` 即这两个字段
+#[derive(Debug, Clone, PartialEq)]
+pub struct CodedError {
+    pub code: ErrorCode,
+    pub message: String,
+}
+
+impl CodedError {
+    pub fn new<M: Into<String>>(code: ErrorCode, message: M) -> Self {
+        CodedError { code, message: message.into() }
+    }
+
+    pub fn invalid_key_length<M: Into<String>>(message: M) -> Self {
+        Self::new(ErrorCode::InvalidKeyLength, message)
+    }
+
+    pub fn invalid_argument<M: Into<String>>(message: M) -> Self {
+        Self::new(ErrorCode::InvalidArgument, message)
+    }
+
+    pub fn bad_header<M: Into<String>>(message: M) -> Self {
+        Self::new(ErrorCode::BadHeader, message)
+    }
+
+    pub fn bad_format<M: Into<String>>(message: M) -> Self {
+        Self::new(ErrorCode::BadFormat, message)
+    }
+
+    pub fn truncated<M: Into<String>>(message: M) -> Self {
+        Self::new(ErrorCode::Truncated, message)
+    }
+
+    pub fn unsupported<M: Into<String>>(message: M) -> Self {
+        Self::new(ErrorCode::Unsupported, message)
+    }
+
+    pub fn auth_failed<M: Into<String>>(message: M) -> Self {
+        Self::new(ErrorCode::AuthFailed, message)
+    }
+
+    pub fn policy<M: Into<String>>(message: M) -> Self {
+        Self::new(ErrorCode::Policy, message)
+    }
+
+    pub fn io_open<M: Into<String>>(message: M) -> Self {
+        Self::new(ErrorCode::IoOpen, message)
+    }
+
+    pub fn io_read<M: Into<String>>(message: M) -> Self {
+        Self::new(ErrorCode::IoRead, message)
+    }
+
+    pub fn io_write<M: Into<String>>(message: M) -> Self {
+        Self::new(ErrorCode::IoWrite, message)
+    }
+
+    pub fn timeout<M: Into<String>>(message: M) -> Self {
+        Self::new(ErrorCode::Timeout, message)
+    }
+
+    pub fn aborted<M: Into<String>>(message: M) -> Self {
+        Self::new(ErrorCode::Aborted, message)
+    }
+
+    pub fn unknown<M: Into<String>>(message: M) -> Self {
+        Self::new(ErrorCode::Unknown, message)
+    }
+
+    /// 在错误信息前加上上下文（`上下文: 原信息`），错误码不变
+    pub fn context<C: fmt::Display>(self, context: C) -> Self {
+        CodedError { code: self.code, message: format!("{}: {}", context, self.message) }
+    }
+
+    /// io::Error 转换为带错误码的错误；Read / Write 实现中包装的 CodedError（例如解密失败）原样取出，保留原来的错误码
+    pub fn from_io(code: ErrorCode, err: io::Error) -> Self {
+        if err.get_ref().is_some_and(|inner| inner.is::<CodedError>()) {
+            return *err.into_inner().unwrap().downcast::<CodedError>().unwrap();
+        }
+        Self::new(code, err.to_string())
+    }
+
+    /// 与 from_io 相同，信息前加上上下文（`上下文: io 错误`）
+    pub fn io<C: fmt::Display>(code: ErrorCode, context: C, err: io::Error) -> Self {
+        Self::from_io(code, err).context(context)
+    }
+}
+
+impl fmt::Display for CodedError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.message)
+    }
+}
+
+impl std::error::Error for CodedError {}
+
+/// 打开或创建文件失败，what 如 "opening input file"
+pub fn open_error(what: &str, err: io::Error) -> CodedError {
+    CodedError::io(ErrorCode::IoOpen, format_args!("Error {}", what), err)
+}
+
+/// 读取或定位失败，what 如 "reading input file"
+pub fn read_error(what: &str, err: io::Error) -> CodedError {
+    CodedError::io(ErrorCode::IoRead, format_args!("Error {}", what), err)
+}
+
+/// 写入、同步或截断失败，what 如 "writing output file"
+pub fn write_error(what: &str, err: io::Error) -> CodedError {
+    CodedError::io(ErrorCode::IoWrite, format_args!("Error {}", what), err)
+}
+
+impl From<CodedError> for Error<ErrorCode> {
+    fn from(err: CodedError) -> Self {
+        Error::new(err.code, err.message)
+    }
+}
+
+impl From<Error<ErrorCode>> for CodedError {
+    fn from(err: Error<ErrorCode>) -> Self {
+        CodedError::new(err.status, err.reason)
+    }
+}
+
+/// napi 自身的错误（取得 JS 值、调用 JS 函数等）：参数类型不对时为 ERR_INVALID_ARGUMENT，其它为 ERR_UNKNOWN
+pub fn napi_error(err: Error) -> Error<ErrorCode> {
+    let code = match err.status {
+        Status::InvalidArg => ErrorCode::InvalidArgument,
+        _ => ErrorCode::Unknown,
+    };
+    Error::new(code, err.reason)
+}
+
+/// `*Async` 版本拒绝 Promise 前在主线程中创建带错误码的 JS 错误
+pub fn to_js_error(env: Env, err: Error<ErrorCode>) -> Error {
+    Error::from(JsError::from(err).into_unknown(env))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn codes_have_stable_names() {
+        let codes = [
+            (CodedError::invalid_key_length("m"), "ERR_INVALID_KEY_LENGTH"),
+            (CodedError::invalid_argument("m"), "ERR_INVALID_ARGUMENT"),
+            (CodedError::bad_header("m"), "ERR_BAD_HEADER"),
+            (CodedError::bad_format("m"), "ERR_BAD_FORMAT"),
+            (CodedError::truncated("m"), "ERR_TRUNCATED"),
+            (CodedError::unsupported("m"), "ERR_UNSUPPORTED"),
+            (CodedError::auth_failed("m"), "ERR_AUTH_FAILED"),
+            (CodedError::policy("m"), "ERR_POLICY"),
+            (CodedError::io_open("m"), "ERR_IO_OPEN"),
+            (CodedError::io_read("m"), "ERR_IO_READ"),
+            (CodedError::io_write("m"), "ERR_IO_WRITE"),
+            (CodedError::timeout("m"), "ERR_TIMEOUT"),
+            (CodedError::aborted("m"), "ERR_ABORTED"),
+            (CodedError::unknown("m"), "ERR_UNKNOWN"),
+        ];
+        for (err, name) in codes {
+            assert_eq!(err.code.as_str(), name);
+            assert_eq!(err.code.as_ref(), name);
+            assert_eq!(err.to_string(), "m");
+        }
+    }
+
+    #[test]
+    fn adds_context_and_keeps_the_code() {
+        let err = CodedError::auth_failed("Authentication failed").context("Chunk 3").context(format_args!("File {}", "a.zenc"));
+        assert_eq!(err.code, ErrorCode::AuthFailed);
+        assert_eq!(err.message, "File a.zenc: Chunk 3: Authentication failed");
+    }
+
+    #[test]
+    fn converts_io_errors() {
+        let err = open_error("opening input file", io::Error::new(io::ErrorKind::NotFound, "No such file"));
+        assert_eq!((err.code, err.message.as_str()), (ErrorCode::IoOpen, "Error opening input file: No such file"));
+        assert_eq!(read_error("reading", io::Error::other("x")).code, ErrorCode::IoRead);
+        assert_eq!(write_error("writing", io::Error::other("x")).code, ErrorCode::IoWrite);
+    }
+
+    #[test]
+    fn unwraps_coded_errors_carried_through_io() {
+        // Read / Write 实现中产生的解密错误经 io::Error 传出后保留原来的错误码
+        let wrapped = io::Error::other(CodedError::auth_failed("Chunk authentication failed"));
+        let err = CodedError::io(ErrorCode::IoRead, "Error reading input file", wrapped);
+        assert_eq!(err.code, ErrorCode::AuthFailed);
+        assert_eq!(err.message, "Error reading input file: Chunk authentication failed");
+        let plain = CodedError::from_io(ErrorCode::IoWrite, io::Error::other("disk full"));
+        assert_eq!((plain.code, plain.message.as_str()), (ErrorCode::IoWrite, "disk full"));
+    }
+
+    #[test]
+    fn converts_to_and_from_napi_errors() {
+        let napi: Error<ErrorCode> = CodedError::truncated("File is truncated").into();
+        assert_eq!((&napi.status, napi.reason.as_str()), (&ErrorCode::Truncated, "File is truncated"));
+        assert_eq!(CodedError::from(napi), CodedError::truncated("File is truncated"));
+        assert_eq!(napi_error(Error::new(Status::InvalidArg, "expected a string")).status, ErrorCode::InvalidArgument);
+        assert_eq!(napi_error(Error::new(Status::GenericFailure, "failed")).status, ErrorCode::Unknown);
+    }
+}
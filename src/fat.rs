@@ -0,0 +1,763 @@
+use std::collections::HashSet;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use crate::errors::CodedError;
+
+/// 按字节偏移随机读取的块设备（例如解密后的 VeraCrypt 卷）
+pub trait ReadAt {
+    fn read_at(&mut self, offset: u64, buffer: &mut [u8]) -> Result<(), CodedError>;
+    /// 设备的字节长度，文件系统的几何参数不能超出它
+    fn size(&self) -> u64;
+}
+
+/// 目录项长度，FAT 和 exFAT 相同
+const DIR_ENTRY_LEN: usize = 32;
+/// 读取文件内容时每次读取的最大长度
+const READ_BUFFER_LEN: usize = 1024 * 1024;
+/// 目录的最大长度（exFAT 规定为 256 MiB），防止损坏的簇链耗尽内存
+const MAX_DIRECTORY_LEN: u64 = 256 * 1024 * 1024;
+/// 目录的最大嵌套深度
+const MAX_DEPTH: usize = 64;
+/// 簇链结束；坏簇和空闲簇在链中出现时视为损坏
+const CHAIN_END: u32 = u32::MAX;
+const CHAIN_BAD: u32 = u32::MAX - 1;
+
+const ATTR_VOLUME_ID: u8 = 0x08;
+const ATTR_DIRECTORY: u8 = 0x10;
+const ATTR_LONG_NAME: u8 = 0x0f;
+/// 短文件名目录项第 12 字节的小写标志（Windows NT 及 Linux 使用）
+const LOWERCASE_BASE: u8 = 0x08;
+const LOWERCASE_EXTENSION: u8 = 0x10;
+
+const EXFAT_FILE: u8 = 0x85;
+const EXFAT_STREAM: u8 = 0xc0;
+const EXFAT_NAME: u8 = 0xc1;
+const EXFAT_NO_FAT_CHAIN: u8 = 0x02;
+const EXFAT_NAME_CHARS: usize = 15;
+
+#[derive(Clone, Copy, PartialEq)]
+enum Kind {
+    Fat12,
+    Fat16,
+    Fat32,
+    ExFat,
+}
+
+/// 文件或目录的数据位置：按 FAT 簇链，或者（exFAT 的 NoFatChain）从起始簇开始连续存放
+#[derive(Clone, Copy)]
+struct Extent {
+    first_cluster: u32,
+    contiguous: bool,
+}
+
+/// 文件系统中的一个文件或目录
+pub struct Entry {
+    /// 以 `/` 分隔的相对路径
+    pub path: String,
+    pub is_dir: bool,
+    pub size: u64,
+    pub modified: Option<SystemTime>,
+    extent: Extent,
+    /// exFAT 中已写入数据的长度，之后的内容读作 0
+    valid_size: u64,
+}
+
+impl Entry {
+    pub fn modified_ms(&self) -> Option<f64> {
+        self.modified
+            .and_then(|modified| modified.duration_since(UNIX_EPOCH).ok())
+            .map(|elapsed| elapsed.as_millis() as f64)
+    }
+}
+
+enum RootDirectory {
+    /// FAT12/16 的根目录位于 FAT 之后的固定区域
+    Fixed { offset: u64, len: u64 },
+    Cluster(Extent),
+}
+
+/// 只读的 FAT12/16/32 和 exFAT 文件系统
+pub struct FileSystem {
+    kind: Kind,
+    cluster_size: u64,
+    /// 2 号簇的字节偏移
+    heap_offset: u64,
+    cluster_count: u32,
+    /// 整个 FAT 表，结束标记统一为 CHAIN_END
+    fat: Vec<u32>,
+    root: RootDirectory,
+}
+
+fn le_u16(data: &[u8], offset: usize) -> u16 {
+    u16::from_le_bytes(data[offset..offset + 2].try_into().unwrap())
+}
+
+fn le_u32(data: &[u8], offset: usize) -> u32 {
+    u32::from_le_bytes(data[offset..offset + 4].try_into().unwrap())
+}
+
+fn le_u64(data: &[u8], offset: usize) -> u64 {
+    u64::from_le_bytes(data[offset..offset + 8].try_into().unwrap())
+}
+
+fn corrupt(what: &str) -> CodedError {
+    CodedError::bad_format(format!("Corrupt file system: {}", what))
+}
+
+/// DOS 日期和时间（FAT 与 exFAT 的格式相同，精度 2 秒，没有时区）转换为时间点
+fn dos_time(date: u16, time: u16) -> Option<SystemTime> {
+    let year = 1980 + (date >> 9) as i64;
+    let month = ((date >> 5) & 0x0f) as i64;
+    let day = (date & 0x1f) as i64;
+    if !(1..=12).contains(&month) || day == 0 {
+        return None;
+    }
+    // 公历日期到 1970-01-01 的天数（Howard Hinnant 的 days_from_civil）
+    let shifted_year = if month <= 2 { year - 1 } else { year };
+    let era = shifted_year.div_euclid(400);
+    let year_of_era = shifted_year - era * 400;
+    let day_of_year = (153 * ((month + 9) % 12) + 2) / 5 + day - 1;
+    let day_of_era = year_of_era * 365 + year_of_era / 4 - year_of_era / 100 + day_of_year;
+    let days = era * 146_097 + day_of_era - 719_468;
+    let seconds = days * 86_400 + (time >> 11) as i64 * 3600 + ((time >> 5) & 0x3f) as i64 * 60 + (time & 0x1f) as i64 * 2;
+    Some(UNIX_EPOCH + Duration::from_secs(u64::try_from(seconds).ok()?))
+}
+
+impl FileSystem {
+    /// 读取引导扇区和 FAT 表；NTFS 等其它文件系统会报错
+    pub fn open(device: &mut dyn ReadAt) -> Result<FileSystem, CodedError> {
+        let mut boot = [0u8; 512];
+        device.read_at(0, &mut boot)?;
+        match &boot[3..11] {
+            b"EXFAT   " => return Self::open_exfat(device, &boot),
+            b"NTFS    " => return Err(CodedError::unsupported("NTFS volumes are not supported, only FAT and exFAT")),
+            _ => {}
+        }
+        if le_u16(&boot, 510) != 0xaa55 {
+            return Err(CodedError::unsupported("Unsupported file system: no FAT or exFAT boot sector found"));
+        }
+
+        let bytes_per_sector = le_u16(&boot, 11) as u64;
+        let sectors_per_cluster = boot[13] as u64;
+        let reserved_sectors = le_u16(&boot, 14) as u64;
+        let fat_count = boot[16] as u64;
+        let root_entries = le_u16(&boot, 17) as u64;
+        let total_sectors = match le_u16(&boot, 19) {
+            0 => le_u32(&boot, 32) as u64,
+            sectors => sectors as u64,
+        };
+        let fat_sectors = match le_u16(&boot, 22) {
+            0 => le_u32(&boot, 36) as u64,
+            sectors => sectors as u64,
+        };
+        if !bytes_per_sector.is_power_of_two() || bytes_per_sector < 512 || !sectors_per_cluster.is_power_of_two() || fat_count == 0 || fat_sectors == 0 {
+            return Err(CodedError::unsupported("Unsupported file system: no FAT or exFAT boot sector found"));
+        }
+        let root_sectors = (root_entries * DIR_ENTRY_LEN as u64).div_ceil(bytes_per_sector);
+        let first_data_sector = reserved_sectors + fat_count * fat_sectors + root_sectors;
+        let cluster_count = total_sectors
+            .checked_sub(first_data_sector)
+            .ok_or_else(|| corrupt("data area is empty"))?
+            / sectors_per_cluster;
+        // FAT 类型只由簇数决定
+        let kind = match cluster_count {
+            0..=4084 => Kind::Fat12,
+            4085..=65524 => Kind::Fat16,
+            _ => Kind::Fat32,
+        };
+        let cluster_count = u32::try_from(cluster_count).map_err(|_| corrupt("too many clusters"))?;
+        // FAT 表位于卷内，卷不能超出设备，损坏的引导扇区因此不会导致分配过多内存
+        if total_sectors * bytes_per_sector > device.size() {
+            return Err(corrupt("volume is larger than the device"));
+        }
+
+        let entries = cluster_count as usize + 2;
+        let table_len = match kind {
+            Kind::Fat12 => entries + entries / 2 + 1,
+            Kind::Fat16 => entries * 2,
+            _ => entries * 4,
+        };
+        let mut table = vec![0u8; table_len.min((fat_sectors * bytes_per_sector) as usize)];
+        device.read_at(reserved_sectors * bytes_per_sector, &mut table)?;
+        let fat = match kind {
+            Kind::Fat12 => (0..entries)
+                .map(|cluster| {
+                    let offset = cluster + cluster / 2;
+                    let pair = table.get(offset..offset + 2).map_or(0, |bytes| le_u16(bytes, 0));
+                    let value = if cluster.is_multiple_of(2) { pair & 0x0fff } else { pair >> 4 };
+                    normalize(value as u32, 0xff7)
+                })
+                .collect(),
+            Kind::Fat16 => (0..entries)
+                .map(|cluster| normalize(table.get(cluster * 2..cluster * 2 + 2).map_or(0, |bytes| le_u16(bytes, 0)) as u32, 0xfff7))
+                .collect(),
+            _ => (0..entries)
+                .map(|cluster| normalize(table.get(cluster * 4..cluster * 4 + 4).map_or(0, |bytes| le_u32(bytes, 0)) & 0x0fff_ffff, 0x0fff_fff7))
+                .collect(),
+        };
+
+        let root = if kind == Kind::Fat32 {
+            RootDirectory::Cluster(Extent { first_cluster: le_u32(&boot, 44), contiguous: false })
+        } else {
+            RootDirectory::Fixed {
+                offset: (reserved_sectors + fat_count * fat_sectors) * bytes_per_sector,
+                len: root_entries * DIR_ENTRY_LEN as u64,
+            }
+        };
+        Ok(FileSystem {
+            kind,
+            cluster_size: sectors_per_cluster * bytes_per_sector,
+            heap_offset: first_data_sector * bytes_per_sector,
+            cluster_count,
+            fat,
+            root,
+        })
+    }
+
+    fn open_exfat(device: &mut dyn ReadAt, boot: &[u8; 512]) -> Result<FileSystem, CodedError> {
+        let sector_shift = boot[108] as u32;
+        let cluster_shift = boot[109] as u32;
+        if !(9..=12).contains(&sector_shift) || sector_shift + cluster_shift > 25 {
+            return Err(corrupt("invalid exFAT sector or cluster size"));
+        }
+        let bytes_per_sector = 1u64 << sector_shift;
+        let fat_offset = le_u32(boot, 80) as u64 * bytes_per_sector;
+        let cluster_count = le_u32(boot, 92);
+        let entries = cluster_count as usize + 2;
+        if fat_offset + entries as u64 * 4 > device.size() {
+            return Err(corrupt("FAT extends beyond the end of the device"));
+        }
+        let mut table = vec![0u8; entries * 4];
+        device.read_at(fat_offset, &mut table)?;
+        let fat = table
+            .chunks_exact(4)
+            .map(|bytes| normalize(le_u32(bytes, 0), 0xffff_fff7))
+            .collect();
+        Ok(FileSystem {
+            kind: Kind::ExFat,
+            cluster_size: 1u64 << (sector_shift + cluster_shift),
+            heap_offset: le_u32(boot, 88) as u64 * bytes_per_sector,
+            cluster_count,
+            fat,
+            root: RootDirectory::Cluster(Extent { first_cluster: le_u32(boot, 96), contiguous: false }),
+        })
+    }
+
+    pub fn kind(&self) -> &'static str {
+        match self.kind {
+            Kind::Fat12 => "FAT12",
+            Kind::Fat16 => "FAT16",
+            Kind::Fat32 => "FAT32",
+            Kind::ExFat => "exFAT",
+        }
+    }
+
+    /// 数据所在的连续簇段（起始簇, 簇数）；size 为 None 时读到簇链结束
+    fn runs(&self, extent: Extent, size: Option<u64>) -> Result<Vec<(u32, u32)>, CodedError> {
+        let needed = size.map(|size| size.div_ceil(self.cluster_size));
+        if needed == Some(0) {
+            return Ok(Vec::new());
+        }
+        let valid = |cluster: u32| cluster >= 2 && cluster - 2 < self.cluster_count;
+        if !valid(extent.first_cluster) {
+            return Err(corrupt("invalid cluster number"));
+        }
+        if extent.contiguous {
+            let count = needed.ok_or_else(|| corrupt("contiguous directory without a size"))?;
+            if extent.first_cluster as u64 - 2 + count > self.cluster_count as u64 {
+                return Err(corrupt("file extends beyond the end of the volume"));
+            }
+            return Ok(vec![(extent.first_cluster, count as u32)]);
+        }
+
+        let mut runs: Vec<(u32, u32)> = Vec::new();
+        let mut visited = HashSet::new();
+        let mut cluster = extent.first_cluster;
+        let mut total = 0u64;
+        loop {
+            if !visited.insert(cluster) {
+                return Err(corrupt("cluster chain loops"));
+            }
+            match runs.last_mut() {
+                Some((start, count)) if *start + *count == cluster => *count += 1,
+                _ => runs.push((cluster, 1)),
+            }
+            total += 1;
+            if needed == Some(total) {
+                return Ok(runs);
+            }
+            cluster = match self.fat[cluster as usize] {
+                CHAIN_END if needed.is_none() => return Ok(runs),
+                next if valid(next) => next,
+                _ => return Err(corrupt("cluster chain is shorter than the file")),
+            };
+        }
+    }
+
+    /// 按顺序读出数据，每次最多 1 MiB
+    fn read_extent(&self, device: &mut dyn ReadAt, extent: Extent, size: Option<u64>, valid_size: u64, sink: &mut dyn FnMut(&[u8]) -> Result<(), CodedError>) -> Result<u64, CodedError> {
+        let mut buffer = vec![0u8; READ_BUFFER_LEN];
+        let mut position = 0u64;
+        for (start, count) in self.runs(extent, size)? {
+            let run_offset = self.heap_offset + (start as u64 - 2) * self.cluster_size;
+            let run_len = count as u64 * self.cluster_size;
+            let run_len = size.map_or(run_len, |size| run_len.min(size - position));
+            let mut done = 0u64;
+            while done < run_len {
+                let len = (run_len - done).min(READ_BUFFER_LEN as u64) as usize;
+                let chunk = &mut buffer[..len];
+                if position >= valid_size {
+                    chunk.fill(0);
+                } else {
+                    device.read_at(run_offset + done, chunk)?;
+                    if position + len as u64 > valid_size {
+                        chunk[(valid_size - position) as usize..].fill(0);
+                    }
+                }
+                sink(chunk)?;
+                done += len as u64;
+                position += len as u64;
+            }
+        }
+        Ok(position)
+    }
+
+    /// 读取整个目录的内容
+    fn read_directory(&self, device: &mut dyn ReadAt, extent: Extent, size: Option<u64>) -> Result<Vec<u8>, CodedError> {
+        let mut data = Vec::new();
+        self.read_extent(device, extent, size, u64::MAX, &mut |chunk| {
+            if (data.len() + chunk.len()) as u64 > MAX_DIRECTORY_LEN {
+                return Err(corrupt("directory is too large"));
+            }
+            data.extend_from_slice(chunk);
+            Ok(())
+        })?;
+        Ok(data)
+    }
+
+    /// 列出全部文件和目录（深度优先，父目录在子项之前）
+    pub fn list(&self, device: &mut dyn ReadAt) -> Result<Vec<Entry>, CodedError> {
+        let root = match self.root {
+            RootDirectory::Fixed { offset, len } => {
+                let mut data = vec![0u8; len as usize];
+                device.read_at(offset, &mut data)?;
+                data
+            }
+            RootDirectory::Cluster(extent) => self.read_directory(device, extent, None)?,
+        };
+        let mut entries = Vec::new();
+        let mut visited = HashSet::new();
+        self.list_directory(device, &root, "", 0, &mut visited, &mut entries)?;
+        Ok(entries)
+    }
+
+    fn list_directory(&self, device: &mut dyn ReadAt, data: &[u8], prefix: &str, depth: usize, visited: &mut HashSet<u32>, entries: &mut Vec<Entry>) -> Result<(), CodedError> {
+        if depth > MAX_DEPTH {
+            return Err(corrupt("directories are nested too deeply"));
+        }
+        let children = match self.kind {
+            Kind::ExFat => parse_exfat_directory(data, prefix)?,
+            _ => parse_fat_directory(data, prefix, self.kind == Kind::Fat32),
+        };
+        for child in children {
+            if child.is_dir {
+                if !visited.insert(child.extent.first_cluster) {
+                    return Err(corrupt("directory loop"));
+                }
+                let size = (self.kind == Kind::ExFat).then_some(child.size);
+                let data = if child.extent.first_cluster == 0 {
+                    Vec::new()
+                } else {
+                    self.read_directory(device, child.extent, size)?
+                };
+                let prefix = format!("{}/", child.path);
+                entries.push(child);
+                self.list_directory(device, &data, &prefix, depth + 1, visited, entries)?;
+            } else {
+                entries.push(child);
+            }
+        }
+        Ok(())
+    }
+
+    /// 读取文件内容，依次交给 sink
+    pub fn read_file(&self, device: &mut dyn ReadAt, entry: &Entry, sink: &mut dyn FnMut(&[u8]) -> Result<(), CodedError>) -> Result<(), CodedError> {
+        if entry.size == 0 {
+            return Ok(());
+        }
+        self.read_extent(device, entry.extent, Some(entry.size), entry.valid_size, sink)?;
+        Ok(())
+    }
+}
+
+/// 把 FAT 表项统一为簇号、CHAIN_END 或 CHAIN_BAD
+fn normalize(value: u32, bad: u32) -> u32 {
+    match value {
+        value if value == bad => CHAIN_BAD,
+        value if value > bad => CHAIN_END,
+        value => value,
+    }
+}
+
+/// 短文件名的校验和，用于确认长文件名属于紧随其后的短文件名目录项
+fn short_name_checksum(name: &[u8]) -> u8 {
+    name.iter().fold(0u8, |sum, byte| sum.rotate_right(1).wrapping_add(*byte))
+}
+
+/// 短文件名（8.3）转换为字符串，非 ASCII 字节按 Latin-1 解释
+fn short_name(entry: &[u8]) -> String {
+    let decode = |bytes: &[u8], lowercase: bool| -> String {
+        let text: String = bytes.iter().map(|byte| char::from(*byte)).collect();
+        let text = text.trim_end_matches(' ');
+        if lowercase {
+            text.to_ascii_lowercase()
+        } else {
+            text.to_string()
+        }
+    };
+    let mut base_bytes = entry[..8].to_vec();
+    if base_bytes[0] == 0x05 {
+        base_bytes[0] = 0xe5;
+    }
+    let base = decode(&base_bytes, entry[12] & LOWERCASE_BASE != 0);
+    let extension = decode(&entry[8..11], entry[12] & LOWERCASE_EXTENSION != 0);
+    if extension.is_empty() {
+        base
+    } else {
+        format!("{}.{}", base, extension)
+    }
+}
+
+fn parse_fat_directory(data: &[u8], prefix: &str, fat32: bool) -> Vec<Entry> {
+    let mut entries = Vec::new();
+    // 正在拼接的长文件名：UTF-16 字符、预期的目录项数、已读到的目录项数和校验和
+    let mut long_name: Vec<u16> = Vec::new();
+    let mut long_parts = 0u8;
+    let mut long_seen = 0u8;
+    let mut long_checksum = 0u8;
+    for entry in data.chunks_exact(DIR_ENTRY_LEN) {
+        match entry[0] {
+            0x00 => break,
+            0xe5 => {
+                long_parts = 0;
+                continue;
+            }
+            _ => {}
+        }
+        let attributes = entry[11];
+        if attributes & 0x3f == ATTR_LONG_NAME {
+            let sequence = entry[0] & 0x1f;
+            if entry[0] & 0x40 != 0 {
+                long_parts = sequence;
+                long_seen = 0;
+                long_checksum = entry[13];
+                long_name = vec![0xffff; sequence as usize * 13];
+            }
+            if sequence == 0 || sequence > long_parts || entry[13] != long_checksum {
+                long_parts = 0;
+                continue;
+            }
+            let start = (sequence as usize - 1) * 13;
+            let units = [1, 3, 5, 7, 9, 14, 16, 18, 20, 22, 24, 28, 30].map(|offset| le_u16(entry, offset));
+            long_name[start..start + 13].copy_from_slice(&units);
+            long_seen += 1;
+            continue;
+        }
+        let has_long_name = long_parts != 0 && long_seen == long_parts && long_checksum == short_name_checksum(&entry[..11]);
+        long_parts = 0;
+        if attributes & ATTR_VOLUME_ID != 0 {
+            continue;
+        }
+        let name = if has_long_name {
+            let units: Vec<u16> = long_name.iter().copied().take_while(|unit| *unit != 0 && *unit != 0xffff).collect();
+            String::from_utf16_lossy(&units)
+        } else {
+            short_name(entry)
+        };
+        if name == "." || name == ".." {
+            continue;
+        }
+        let high = if fat32 { (le_u16(entry, 20) as u32) << 16 } else { 0 };
+        let is_dir = attributes & ATTR_DIRECTORY != 0;
+        let size = if is_dir { 0 } else { le_u32(entry, 28) as u64 };
+        entries.push(Entry {
+            path: format!("{}{}", prefix, name),
+            is_dir,
+            size,
+            modified: dos_time(le_u16(entry, 24), le_u16(entry, 22)),
+            extent: Extent { first_cluster: high | le_u16(entry, 26) as u32, contiguous: false },
+            valid_size: size,
+        });
+    }
+    entries
+}
+
+fn parse_exfat_directory(data: &[u8], prefix: &str) -> Result<Vec<Entry>, CodedError> {
+    let mut entries = Vec::new();
+    let records: Vec<&[u8]> = data.chunks_exact(DIR_ENTRY_LEN).collect();
+    let mut index = 0;
+    while index < records.len() {
+        let record = records[index];
+        index += 1;
+        match record[0] {
+            0x00 => break,
+            EXFAT_FILE => {}
+            _ => continue,
+        }
+        // 文件目录项之后依次为流扩展目录项和文件名目录项
+        let secondary = record[1] as usize;
+        let set = records
+            .get(index..index + secondary)
+            .filter(|set| secondary >= 2 && set[0][0] == EXFAT_STREAM)
+            .ok_or_else(|| corrupt("incomplete exFAT directory entry set"))?;
+        index += secondary;
+        let stream = set[0];
+        let name_len = stream[3] as usize;
+        let units: Vec<u16> = set[1..]
+            .iter()
+            .take_while(|record| record[0] == EXFAT_NAME)
+            .flat_map(|record| (0..EXFAT_NAME_CHARS).map(move |char_index| le_u16(record, 2 + char_index * 2)))
+            .take(name_len)
+            .collect();
+        if units.len() != name_len {
+            return Err(corrupt("incomplete exFAT file name"));
+        }
+        let timestamp = le_u32(record, 12);
+        let is_dir = le_u16(record, 4) as u8 & ATTR_DIRECTORY != 0;
+        entries.push(Entry {
+            path: format!("{}{}", prefix, String::from_utf16_lossy(&units)),
+            is_dir,
+            size: le_u64(stream, 24),
+            modified: dos_time((timestamp >> 16) as u16, timestamp as u16),
+            extent: Extent { first_cluster: le_u32(stream, 20), contiguous: stream[1] & EXFAT_NO_FAT_CHAIN != 0 },
+            valid_size: le_u64(stream, 8),
+        });
+    }
+    Ok(entries)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::errors::ErrorCode;
+
+    const SECTOR: usize = 512;
+
+    /// 内存中的磁盘映像，超出末尾的读取出错
+    struct Image(Vec<u8>);
+
+    impl ReadAt for Image {
+        fn read_at(&mut self, offset: u64, buffer: &mut [u8]) -> Result<(), CodedError> {
+            let start = usize::try_from(offset).map_err(|_| CodedError::truncated("offset"))?;
+            let data = self.0.get(start..start + buffer.len()).ok_or_else(|| CodedError::truncated("read beyond the end of the image"))?;
+            buffer.copy_from_slice(data);
+            Ok(())
+        }
+
+        fn size(&self) -> u64 {
+            self.0.len() as u64
+        }
+    }
+
+    fn dir_entry(name: &[u8; 11], attributes: u8, cluster: u16, size: u32) -> [u8; DIR_ENTRY_LEN] {
+        let mut entry = [0u8; DIR_ENTRY_LEN];
+        entry[..11].copy_from_slice(name);
+        entry[11] = attributes;
+        entry[22..24].copy_from_slice(&0x6000u16.to_le_bytes());
+        entry[24..26].copy_from_slice(&0x5821u16.to_le_bytes());
+        entry[26..28].copy_from_slice(&cluster.to_le_bytes());
+        entry[28..32].copy_from_slice(&size.to_le_bytes());
+        entry
+    }
+
+    /// FAT12 映像：每簇 1 个扇区，保留 1 个扇区，1 个 FAT，根目录 16 项，共 64 个扇区（61 个簇）
+    struct Fat12 {
+        image: Vec<u8>,
+    }
+
+    impl Fat12 {
+        const TOTAL_SECTORS: usize = 64;
+        const FAT_OFFSET: usize = SECTOR;
+        const ROOT_OFFSET: usize = 2 * SECTOR;
+        const HEAP_OFFSET: usize = 3 * SECTOR;
+
+        fn new() -> Self {
+            let mut image = vec![0u8; Self::TOTAL_SECTORS * SECTOR];
+            image[3..11].copy_from_slice(b"MSDOS5.0");
+            image[11..13].copy_from_slice(&(SECTOR as u16).to_le_bytes());
+            image[13] = 1;
+            image[14..16].copy_from_slice(&1u16.to_le_bytes());
+            image[16] = 1;
+            image[17..19].copy_from_slice(&16u16.to_le_bytes());
+            image[19..21].copy_from_slice(&(Self::TOTAL_SECTORS as u16).to_le_bytes());
+            image[22..24].copy_from_slice(&1u16.to_le_bytes());
+            image[510..512].copy_from_slice(&0xaa55u16.to_le_bytes());
+            let mut fat = Fat12 { image };
+            fat.set_next(0, 0xff8);
+            fat.set_next(1, 0xfff);
+            fat
+        }
+
+        fn set_next(&mut self, cluster: usize, value: u16) {
+            let offset = Self::FAT_OFFSET + cluster + cluster / 2;
+            let pair = le_u16(&self.image, offset);
+            let pair = if cluster.is_multiple_of(2) { (pair & 0xf000) | value } else { (pair & 0x000f) | (value << 4) };
+            self.image[offset..offset + 2].copy_from_slice(&pair.to_le_bytes());
+        }
+
+        fn cluster(&mut self, cluster: usize) -> &mut [u8] {
+            let offset = Self::HEAP_OFFSET + (cluster - 2) * SECTOR;
+            &mut self.image[offset..offset + SECTOR]
+        }
+
+        /// 根目录：HELLO.TXT（簇 2、3，700 字节）和 SUB 目录（簇 4），SUB 中有 A.TXT（簇 5）
+        fn sample() -> Self {
+            let mut fat = Fat12::new();
+            fat.image[Self::ROOT_OFFSET..Self::ROOT_OFFSET + 32].copy_from_slice(&dir_entry(b"HELLO   TXT", 0x20, 2, 700));
+            fat.image[Self::ROOT_OFFSET + 32..Self::ROOT_OFFSET + 64].copy_from_slice(&dir_entry(b"SUB        ", ATTR_DIRECTORY, 4, 0));
+            fat.set_next(2, 3);
+            fat.set_next(3, 0xfff);
+            fat.set_next(4, 0xfff);
+            fat.set_next(5, 0xfff);
+            fat.cluster(2).fill(b'h');
+            fat.cluster(3).fill(b'i');
+            fat.cluster(4)[..32].copy_from_slice(&dir_entry(b".          ", ATTR_DIRECTORY, 4, 0));
+            fat.cluster(4)[32..64].copy_from_slice(&dir_entry(b"A       TXT", 0x20, 5, 5));
+            fat.cluster(5)[..5].copy_from_slice(b"hello");
+            fat
+        }
+    }
+
+    /// 打开映像，列出并读出全部文件
+    fn read_all(image: Vec<u8>) -> Result<Vec<(String, Vec<u8>)>, CodedError> {
+        let mut device = Image(image);
+        let fs = FileSystem::open(&mut device)?;
+        let mut files = Vec::new();
+        for entry in fs.list(&mut device)? {
+            let mut data = Vec::new();
+            fs.read_file(&mut device, &entry, &mut |chunk| {
+                data.extend_from_slice(chunk);
+                Ok(())
+            })?;
+            files.push((entry.path, data));
+        }
+        Ok(files)
+    }
+
+    #[test]
+    fn lists_and_reads_a_fat12_volume() {
+        let mut device = Image(Fat12::sample().image);
+        let fs = FileSystem::open(&mut device).unwrap();
+        assert_eq!(fs.kind(), "FAT12");
+        let files = read_all(device.0).unwrap();
+        let paths: Vec<_> = files.iter().map(|(path, _)| path.as_str()).collect();
+        assert_eq!(paths, ["HELLO.TXT", "SUB", "SUB/A.TXT"]);
+        let mut hello = vec![b'h'; SECTOR];
+        hello.extend_from_slice(&[b'i'; 700 - SECTOR]);
+        assert_eq!(files[0].1, hello);
+        assert_eq!(files[2].1, b"hello");
+    }
+
+    #[test]
+    fn truncated_images_fail_cleanly() {
+        let image = Fat12::sample().image;
+        for len in [0, 11, 100, 511, 512, SECTOR + 1, 2 * SECTOR, 3 * SECTOR, 4 * SECTOR + 100, 6 * SECTOR, image.len() - 1] {
+            assert!(read_all(image[..len].to_vec()).is_err(), "length {}", len);
+        }
+    }
+
+    #[test]
+    fn rejects_malformed_boot_sectors() {
+        assert_eq!(read_all(vec![0u8; 4096]).err().unwrap().code, ErrorCode::Unsupported);
+
+        let mut zero_sector_size = Fat12::sample();
+        zero_sector_size.image[11..13].fill(0);
+        assert_eq!(read_all(zero_sector_size.image).err().unwrap().code, ErrorCode::Unsupported);
+
+        let mut no_data_area = Fat12::sample();
+        no_data_area.image[22..24].copy_from_slice(&1000u16.to_le_bytes());
+        assert_eq!(read_all(no_data_area.image).err().unwrap().code, ErrorCode::BadFormat);
+
+        // 声称有 2^32 - 1 个扇区：不能按引导扇区分配 FAT 表
+        let mut huge = Fat12::sample();
+        huge.image[19..21].fill(0);
+        huge.image[32..36].copy_from_slice(&u32::MAX.to_le_bytes());
+        huge.image[36..40].copy_from_slice(&u32::MAX.to_le_bytes());
+        huge.image[22..24].fill(0);
+        assert_eq!(read_all(huge.image).err().unwrap().code, ErrorCode::BadFormat);
+
+        let mut exfat = vec![0u8; 4096];
+        exfat[3..11].copy_from_slice(b"EXFAT   ");
+        exfat[108] = 8;
+        assert_eq!(read_all(exfat.clone()).err().unwrap().code, ErrorCode::BadFormat);
+        exfat[108] = 9;
+        exfat[80..84].copy_from_slice(&1u32.to_le_bytes());
+        exfat[92..96].copy_from_slice(&u32::MAX.to_le_bytes());
+        assert_eq!(read_all(exfat).err().unwrap().code, ErrorCode::BadFormat);
+
+        let mut ntfs = vec![0u8; 512];
+        ntfs[3..11].copy_from_slice(b"NTFS    ");
+        assert_eq!(read_all(ntfs).err().unwrap().code, ErrorCode::Unsupported);
+    }
+
+    #[test]
+    fn rejects_bad_cluster_chains() {
+        type Corruption = fn(&mut Fat12);
+        let cases: [(&str, Corruption); 5] = [
+            ("bad cluster", |fat| fat.set_next(2, 0xff7)),
+            ("free cluster", |fat| fat.set_next(2, 0)),
+            ("beyond the volume", |fat| fat.set_next(2, 0xff0)),
+            ("loop", |fat| fat.set_next(3, 2)),
+            ("chain ends early", |fat| fat.set_next(2, 0xfff)),
+        ];
+        for (name, corrupt_chain) in cases {
+            let mut fat = Fat12::sample();
+            corrupt_chain(&mut fat);
+            // 文件比簇链长，读到坏的表项时出错
+            fat.image[Fat12::ROOT_OFFSET + 28..Fat12::ROOT_OFFSET + 32].copy_from_slice(&5000u32.to_le_bytes());
+            assert_eq!(read_all(fat.image).err().unwrap().code, ErrorCode::BadFormat, "{}", name);
+        }
+
+        let mut invalid_start = Fat12::sample();
+        invalid_start.image[Fat12::ROOT_OFFSET + 26..Fat12::ROOT_OFFSET + 28].copy_from_slice(&1u16.to_le_bytes());
+        assert_eq!(read_all(invalid_start.image).err().unwrap().code, ErrorCode::BadFormat);
+
+        // 目录的簇链成环时同样报错，不会无限读取
+        let mut directory_loop = Fat12::sample();
+        directory_loop.set_next(4, 4);
+        assert_eq!(read_all(directory_loop.image).err().unwrap().code, ErrorCode::BadFormat);
+    }
+
+    #[test]
+    fn rejects_directory_loops() {
+        let mut fat = Fat12::sample();
+        fat.cluster(4)[64..96].copy_from_slice(&dir_entry(b"LOOP       ", ATTR_DIRECTORY, 4, 0));
+        assert_eq!(read_all(fat.image).err().unwrap().code, ErrorCode::BadFormat);
+    }
+
+    #[test]
+    fn rejects_incomplete_exfat_entry_sets() {
+        let mut file = [0u8; DIR_ENTRY_LEN];
+        file[0] = EXFAT_FILE;
+        file[1] = 2;
+        let mut stream = [0u8; DIR_ENTRY_LEN];
+        stream[0] = EXFAT_STREAM;
+        stream[3] = 20;
+        let mut name = [0u8; DIR_ENTRY_LEN];
+        name[0] = EXFAT_NAME;
+
+        // 缺少文件名目录项
+        assert!(parse_exfat_directory(&[file, stream].concat(), "").is_err());
+        // 文件名长度为 20，但只有一个文件名目录项（15 个字符）
+        assert!(parse_exfat_directory(&[file, stream, name].concat(), "").is_err());
+        // 第一个附属目录项不是流扩展目录项
+        assert!(parse_exfat_directory(&[file, name, name].concat(), "").is_err());
+        // 目录项集合在结尾被截断
+        assert!(parse_exfat_directory(&[file, stream, name][..].concat()[..70], "").is_err());
+
+        stream[3] = 5;
+        let entries = parse_exfat_directory(&[file, stream, name].concat(), "dir/").unwrap();
+        assert_eq!(entries.len(), 1);
+        assert_eq!(entries[0].path, "dir/\0\0\0\0\0");
+    }
+}
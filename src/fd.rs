@@ -0,0 +1,501 @@
+use std::fmt;
+use std::fs::{self, File, OpenOptions};
+use std::io;
+use std::path::PathBuf;
+
+use napi::bindgen_prelude::Either;
+
+use crate::errors::{CodedError, ErrorCode};
+
+/// JS 传入的输入或输出文件：路径，或已打开的文件描述符（如 `fs.openSync()` 的返回值）
+pub type PathOrFd = Either<String, i32>;
+
+/// 内部传递的输入或输出文件：路径，或调用方传入的文件描述符，经过 open()/create() 打开
+///
+/// 文件描述符不依赖文件原来的路径，因此也适用于 `O_TMPFILE` 创建或已删除的文件，输出还可以是管道和套接字。
+/// 调用方传入的字符串总是路径，即使形如 `/dev/fd/3` 也按普通路径处理。
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum Target {
+    Path(String),
+    Fd(i32),
+}
+
+impl Target {
+    /// 是否为文件描述符；这样的输出不能删除或替换，出错时保留已写出的内容
+    pub fn is_fd(&self) -> bool {
+        matches!(self, Target::Fd(_))
+    }
+
+    /// 路径，文件描述符没有路径
+    pub fn as_path(&self) -> Option<&str> {
+        match self {
+            Target::Path(path) => Some(path),
+            Target::Fd(_) => None,
+        }
+    }
+
+    /// 与 fs::metadata 相同，文件描述符取它指向的文件的信息
+    pub fn metadata(&self) -> io::Result<fs::Metadata> {
+        match self {
+            Target::Path(path) => fs::metadata(path),
+            Target::Fd(fd) => dup(*fd).map_err(io::Error::other)?.metadata(),
+        }
+    }
+}
+
+/// 用于结果和错误信息：文件描述符显示为 `/dev/fd/<fd>`，只用于显示，不会再按路径打开
+impl fmt::Display for Target {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Target::Path(path) => f.write_str(path),
+            Target::Fd(fd) => write!(f, "/dev/fd/{}", fd),
+        }
+    }
+}
+
+impl From<String> for Target {
+    fn from(path: String) -> Self {
+        Target::Path(path)
+    }
+}
+
+impl From<&String> for Target {
+    fn from(path: &String) -> Self {
+        Target::Path(path.clone())
+    }
+}
+
+impl From<&str> for Target {
+    fn from(path: &str) -> Self {
+        Target::Path(path.to_string())
+    }
+}
+
+impl From<&Target> for Target {
+    fn from(target: &Target) -> Self {
+        target.clone()
+    }
+}
+
+impl From<Target> for PathOrFd {
+    fn from(target: Target) -> Self {
+        match target {
+            Target::Path(path) => Either::A(path),
+            Target::Fd(fd) => Either::B(fd),
+        }
+    }
+}
+
+/// 转换输入文件参数；输入的文件描述符必须是普通文件，文件头记录的原始大小等需要预先知道文件长度
+pub fn input_path(file: PathOrFd) -> Result<Target, CodedError> {
+    match file {
+        Either::A(path) => Ok(Target::Path(path)),
+        Either::B(fd) => {
+            let file = dup(fd)?;
+            let metadata = file
+                .metadata()
+                .map_err(|e| CodedError::io_read(format!("Failed to get file metadata: {}", e)))?;
+            if !metadata.is_file() {
+                return Err(CodedError::invalid_argument(format!("Input file descriptor {} must refer to a regular file: this operation needs the input size or seeks in the input", fd)));
+            }
+            Ok(Target::Fd(fd))
+        }
+    }
+}
+
+/// 转换只按顺序读取一次的输入文件参数，文件描述符还可以是管道或套接字（如标准输入 0）
+pub fn stream_input_path(file: PathOrFd) -> Result<Target, CodedError> {
+    match file {
+        Either::A(path) => Ok(Target::Path(path)),
+        Either::B(fd) => {
+            dup(fd)?;
+            Ok(Target::Fd(fd))
+        }
+    }
+}
+
+/// 转换输出文件参数；输出的文件描述符可以是普通文件、管道或套接字
+pub fn output_path(file: PathOrFd) -> Result<Target, CodedError> {
+    match file {
+        Either::A(path) => Ok(Target::Path(path)),
+        Either::B(fd) => {
+            dup(fd)?;
+            Ok(Target::Fd(fd))
+        }
+    }
+}
+
+/// 写完输出文件后同步到磁盘，管道和套接字（如标准输出 1）无法同步，直接返回
+pub trait SyncOutput {
+    fn sync_output(&self) -> io::Result<()>;
+}
+
+impl SyncOutput for File {
+    fn sync_output(&self) -> io::Result<()> {
+        match self.sync_all() {
+            Err(_) if !self.metadata()?.is_file() => Ok(()),
+            result => result,
+        }
+    }
+}
+
+/// 与 File::open 相同；普通文件的描述符从开头读取，管道和套接字从当前位置按顺序读取
+///
+/// Linux 上普通文件的描述符经 `/proc/self/fd/<fd>` 重新打开，得到独立的读取位置，同一个输入可以同时打开多次（如检测 MIME 类型）；
+/// 其它平台上只能复制描述符，副本共享读取位置。
+pub fn open(target: impl Into<Target>) -> io::Result<File> {
+    let fd = match target.into() {
+        Target::Path(path) => return File::open(path),
+        Target::Fd(fd) => fd,
+    };
+    let file = dup(fd).map_err(io::Error::other)?;
+    if !file.metadata()?.is_file() {
+        return Ok(file);
+    }
+    if cfg!(target_os = "linux") {
+        return File::open(format!("/proc/self/fd/{}", fd));
+    }
+    rewind(&file)?;
+    Ok(file)
+}
+
+/// 与 File::create 相同；普通文件的描述符同样被清空并从开头写入，管道和套接字按顺序写入
+pub fn create(target: impl Into<Target>) -> io::Result<File> {
+    match target.into() {
+        Target::Fd(fd) => {
+            let file = dup(fd).map_err(io::Error::other)?;
+            if file.metadata()?.is_file() {
+                file.set_len(0)?;
+                rewind(&file)?;
+            }
+            Ok(file)
+        }
+        Target::Path(path) => File::create(path),
+    }
+}
+
+/// 以读写方式打开已写出的输出文件，用于写完后再改写其中的内容；Linux 上普通文件的描述符同样经 `/proc/self/fd/<fd>` 重新打开，
+/// 只以写方式打开的描述符也可以读取
+pub fn open_rw(target: &Target) -> io::Result<File> {
+    let fd = match target {
+        Target::Path(path) => return OpenOptions::new().read(true).write(true).open(path),
+        Target::Fd(fd) => *fd,
+    };
+    let file = dup(fd).map_err(io::Error::other)?;
+    if cfg!(target_os = "linux") && file.metadata()?.is_file() {
+        return OpenOptions::new().read(true).write(true).open(format!("/proc/self/fd/{}", fd));
+    }
+    Ok(file)
+}
+
+/// 原子写入的输出文件：数据先写入目标所在目录中的临时文件，commit() 时重命名为目标路径
+///
+/// 没有 commit() 就被丢弃（出错、超时或中止）时删除临时文件，目标路径上原有的文件保持不变，崩溃时也不会留下写了一半的目标文件。
+/// 文件描述符和已存在的非普通文件（如 `/dev/null`、命名管道）无法替换，直接写入。目标是符号链接时替换它指向的文件；
+/// 替换已存在的文件时沿用它的权限。
+pub struct AtomicOutput {
+    target: Target,
+    /// 实际写入的临时文件，直接写入或已提交时为 None
+    temp: Option<Target>,
+    /// 为 false 时 commit() 不覆盖目标路径上已有的文件
+    replace: bool,
+}
+
+impl AtomicOutput {
+    pub fn new(target: impl Into<Target>) -> Self {
+        let path = match target.into() {
+            Target::Path(path) if !fs::metadata(&path).is_ok_and(|metadata| !metadata.is_file()) => path,
+            target => return AtomicOutput { target, temp: None, replace: true },
+        };
+        let target = match fs::symlink_metadata(&path) {
+            Ok(metadata) if metadata.file_type().is_symlink() => fs::canonicalize(&path).unwrap_or_else(|_| PathBuf::from(&path)),
+            _ => PathBuf::from(&path),
+        };
+        let mut suffix = [0u8; 6];
+        rand::RngCore::fill_bytes(&mut rand::thread_rng(), &mut suffix);
+        let name = target.file_name().map_or_else(String::new, |name| name.to_string_lossy().into_owned());
+        let temp_path = target.with_file_name(format!(".{}.{}.tmp", name, hex::encode(suffix)));
+        AtomicOutput {
+            target: Target::Path(target.to_string_lossy().into_owned()),
+            temp: Some(Target::Path(temp_path.to_string_lossy().into_owned())),
+            replace: true,
+        }
+    }
+
+    /// replace 为 false 时，写入期间目标路径上出现的文件同样不会被覆盖
+    pub fn replace_existing(mut self, replace: bool) -> Self {
+        self.replace = replace;
+        self
+    }
+
+    /// 调用方应写入的文件
+    pub fn path(&self) -> &Target {
+        self.temp.as_ref().unwrap_or(&self.target)
+    }
+
+    /// 写入完成，把临时文件同步到磁盘并重命名为目标路径
+    pub fn commit(mut self) -> Result<(), CodedError> {
+        let (Some(Target::Path(temp_path)), Target::Path(path)) = (self.temp.take(), &self.target) else {
+            return Ok(());
+        };
+        // 重命名之前把数据同步到磁盘，崩溃后目标路径上要么是原来的文件，要么是完整的新文件
+        File::open(&temp_path).and_then(|file| file.sync_all()).map_err(|e| CodedError::io_write(format!("Failed to flush {}: {}", temp_path, e)))?;
+        if !self.replace {
+            return link_new(&temp_path, path);
+        }
+        #[cfg(unix)]
+        if let Ok(existing) = fs::metadata(path) {
+            let _ = fs::set_permissions(&temp_path, existing.permissions());
+        }
+        fs::rename(&temp_path, path).map_err(|e| {
+            let _ = fs::remove_file(&temp_path);
+            CodedError::io(ErrorCode::IoWrite, format_args!("Failed to replace {}", path), e)
+        })
+    }
+}
+
+/// 以硬链接代替重命名，目标已存在时原子地失败
+///
+/// 文件系统不支持硬链接时直接报错：先检查目标是否存在再重命名，检查和重命名之间出现的文件会被覆盖。
+fn link_new(temp_path: &str, path: &str) -> Result<(), CodedError> {
+    let linked = match fs::hard_link(temp_path, path) {
+        Ok(()) => Ok(()),
+        Err(e) if e.kind() == io::ErrorKind::AlreadyExists => Err(exists_error(path)),
+        Err(e) => Err(CodedError::io_open(format!(
+            "Failed to create {} without overwriting: {} - the file system does not support hard links, use overwrite: true", path, e
+        ))),
+    };
+    let _ = fs::remove_file(temp_path);
+    linked
+}
+
+/// 两个文件（包括文件描述符和符号链接）是否为同一个文件，任一方不存在时为 false
+pub fn same_file(first: &Target, second: &Target) -> bool {
+    let (Ok(first_metadata), Ok(second_metadata)) = (first.metadata(), second.metadata()) else {
+        return false;
+    };
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::MetadataExt;
+        first_metadata.dev() == second_metadata.dev() && first_metadata.ino() == second_metadata.ino()
+    }
+    #[cfg(not(unix))]
+    {
+        let _ = (first_metadata, second_metadata);
+        let canonical = |target: &Target| target.as_path().and_then(|path| fs::canonicalize(path).ok());
+        canonical(first).is_some_and(|first| canonical(second) == Some(first))
+    }
+}
+
+fn exists_error(path: &str) -> CodedError {
+    CodedError::io_open(format!("Output file already exists: {}", path))
+}
+
+/// 输出路径上已有普通文件时出错，用于不允许覆盖的输出；文件描述符和 `/dev/null` 等非普通文件不受限制
+pub fn check_no_clobber(target: &Target) -> Result<(), CodedError> {
+    match target.as_path() {
+        Some(path) if fs::metadata(path).is_ok_and(|metadata| metadata.is_file()) => Err(exists_error(path)),
+        _ => Ok(()),
+    }
+}
+
+impl Drop for AtomicOutput {
+    fn drop(&mut self) {
+        if let Some(Target::Path(temp_path)) = self.temp.take() {
+            let _ = fs::remove_file(temp_path);
+        }
+    }
+}
+
+fn rewind(mut file: &File) -> io::Result<()> {
+    use std::io::{Seek, SeekFrom};
+    file.seek(SeekFrom::Start(0)).map(|_| ())
+}
+
+/// 复制文件描述符，调用方仍拥有原来的描述符，操作结束时只关闭副本
+#[cfg(unix)]
+fn dup(fd: i32) -> Result<File, CodedError> {
+    let duplicate = unsafe { libc::fcntl(fd, libc::F_DUPFD_CLOEXEC, 0) };
+    if fd < 0 || duplicate < 0 {
+        return Err(CodedError::invalid_argument(format!("Invalid file descriptor {}: {}", fd, io::Error::last_os_error())));
+    }
+    use std::os::unix::io::FromRawFd;
+    Ok(unsafe { File::from_raw_fd(duplicate) })
+}
+
+#[cfg(not(unix))]
+fn dup(fd: i32) -> Result<File, CodedError> {
+    Err(CodedError::unsupported(format!("File descriptors are not supported on this platform: {}", fd)))
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::{Read, Write};
+
+    use super::*;
+
+    /// 测试用的临时目录，结束时删除
+    struct TempDir(PathBuf);
+
+    impl TempDir {
+        fn new(name: &str) -> Self {
+            let path = std::env::temp_dir().join(format!("zippy-fd-{}-{}", std::process::id(), name));
+            let _ = fs::remove_dir_all(&path);
+            fs::create_dir_all(&path).unwrap();
+            TempDir(path)
+        }
+
+        fn path(&self, name: &str) -> String {
+            self.0.join(name).to_string_lossy().into_owned()
+        }
+
+        fn entries(&self) -> usize {
+            fs::read_dir(&self.0).unwrap().count()
+        }
+    }
+
+    impl Drop for TempDir {
+        fn drop(&mut self) {
+            let _ = fs::remove_dir_all(&self.0);
+        }
+    }
+
+    #[cfg(unix)]
+    fn raw_fd(file: &File) -> i32 {
+        use std::os::fd::AsRawFd;
+        file.as_raw_fd()
+    }
+
+    #[test]
+    fn strings_are_always_paths() {
+        assert_eq!(input_path(Either::A("/dev/fd/3".to_string())).unwrap(), Target::Path("/dev/fd/3".to_string()));
+        assert_eq!(output_path(Either::A("/dev/fd/1".to_string())).unwrap(), Target::Path("/dev/fd/1".to_string()));
+        assert!(!Target::from("/dev/fd/0").is_fd());
+        assert_eq!(Target::Fd(3).as_path(), None);
+        assert!(matches!(PathOrFd::from(Target::Fd(3)), Either::B(3)));
+    }
+
+    #[cfg(target_os = "linux")]
+    #[test]
+    fn literal_dev_fd_path_is_replaced_atomically() {
+        let dir = TempDir::new("literal");
+        let path = dir.path("existing.bin");
+        fs::write(&path, b"original").unwrap();
+        let file = File::open(&path).unwrap();
+        // 字符串形式的 /dev/fd/N 按路径处理：写入临时文件后替换，不会就地清空调用方的文件
+        let output = AtomicOutput::new(format!("/dev/fd/{}", raw_fd(&file)));
+        assert!(output.path().as_path().is_some_and(|temp| temp.starts_with(&*dir.0.to_string_lossy())));
+        fs::write(output.path().as_path().unwrap(), b"replaced").unwrap();
+        assert_eq!(fs::read(&path).unwrap(), b"original");
+        output.commit().unwrap();
+        assert_eq!(fs::read(&path).unwrap(), b"replaced");
+        assert_eq!(dir.entries(), 1);
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn create_truncates_a_regular_file_descriptor() {
+        let dir = TempDir::new("create");
+        let path = dir.path("output.bin");
+        fs::write(&path, b"previous contents").unwrap();
+        let file = OpenOptions::new().read(true).write(true).open(&path).unwrap();
+        let target = output_path(Either::B(raw_fd(&file))).unwrap();
+        assert!(target.is_fd());
+        let output = AtomicOutput::new(&target);
+        assert_eq!(output.path(), &target);
+        create(output.path()).unwrap().write_all(b"new").unwrap();
+        output.commit().unwrap();
+        assert_eq!(fs::read(&path).unwrap(), b"new");
+        // 调用方的描述符仍然有效
+        assert!(file.metadata().is_ok());
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn open_reads_a_regular_file_descriptor_from_the_start() {
+        let dir = TempDir::new("open");
+        let path = dir.path("input.bin");
+        fs::write(&path, b"0123456789").unwrap();
+        let mut file = File::open(&path).unwrap();
+        file.read_exact(&mut [0u8; 4]).unwrap();
+        let target = input_path(Either::B(raw_fd(&file))).unwrap();
+        let mut data = Vec::new();
+        open(&target).unwrap().read_to_end(&mut data).unwrap();
+        assert_eq!(data, b"0123456789");
+        assert_eq!(target.metadata().unwrap().len(), 10);
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn input_descriptors_must_be_regular_files() {
+        let mut fds = [0; 2];
+        assert_eq!(unsafe { libc::pipe(fds.as_mut_ptr()) }, 0);
+        assert_eq!(input_path(Either::B(fds[0])).unwrap_err().code, ErrorCode::InvalidArgument);
+        assert_eq!(stream_input_path(Either::B(fds[0])).unwrap(), Target::Fd(fds[0]));
+        unsafe {
+            libc::close(fds[0]);
+            libc::close(fds[1]);
+        }
+        assert_eq!(output_path(Either::B(-1)).unwrap_err().code, ErrorCode::InvalidArgument);
+    }
+
+    #[test]
+    fn commit_replaces_the_target_and_drop_discards_the_temporary_file() {
+        let dir = TempDir::new("commit");
+        let path = dir.path("output.bin");
+        fs::write(&path, b"old").unwrap();
+
+        let output = AtomicOutput::new(&path);
+        fs::write(output.path().as_path().unwrap(), b"partial").unwrap();
+        drop(output);
+        assert_eq!(fs::read(&path).unwrap(), b"old");
+        assert_eq!(dir.entries(), 1);
+
+        let output = AtomicOutput::new(&path);
+        assert_ne!(output.path(), &Target::from(&path));
+        fs::write(output.path().as_path().unwrap(), b"new").unwrap();
+        output.commit().unwrap();
+        assert_eq!(fs::read(&path).unwrap(), b"new");
+        assert_eq!(dir.entries(), 1);
+    }
+
+    #[test]
+    fn no_clobber_commit_fails_when_the_target_appears_while_writing() {
+        let dir = TempDir::new("clobber");
+        let path = dir.path("output.bin");
+        let target = Target::from(&path);
+        check_no_clobber(&target).unwrap();
+
+        let output = AtomicOutput::new(&path).replace_existing(false);
+        fs::write(output.path().as_path().unwrap(), b"new").unwrap();
+        fs::write(&path, b"appeared").unwrap();
+        assert_eq!(output.commit().unwrap_err().code, ErrorCode::IoOpen);
+        assert_eq!(fs::read(&path).unwrap(), b"appeared");
+        assert_eq!(dir.entries(), 1);
+        assert_eq!(check_no_clobber(&target).unwrap_err().code, ErrorCode::IoOpen);
+
+        fs::remove_file(&path).unwrap();
+        let output = AtomicOutput::new(&path).replace_existing(false);
+        fs::write(output.path().as_path().unwrap(), b"new").unwrap();
+        output.commit().unwrap();
+        assert_eq!(fs::read(&path).unwrap(), b"new");
+        assert_eq!(dir.entries(), 1);
+    }
+
+    #[test]
+    fn detects_the_same_file() {
+        let dir = TempDir::new("same");
+        let first = dir.path("first.bin");
+        let second = dir.path("second.bin");
+        fs::write(&first, b"first").unwrap();
+        fs::write(&second, b"second").unwrap();
+        assert!(same_file(&Target::from(&first), &Target::from(&first)));
+        assert!(!same_file(&Target::from(&first), &Target::from(&second)));
+        assert!(!same_file(&Target::from(&first), &Target::from(&dir.path("missing.bin"))));
+        #[cfg(unix)]
+        {
+            let file = File::open(&first).unwrap();
+            assert!(same_file(&Target::Fd(raw_fd(&file)), &Target::from(&first)));
+        }
+    }
+}
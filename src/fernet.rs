@@ -0,0 +1,184 @@
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use aes::Aes128;
+use base64::engine::general_purpose::{GeneralPurpose, GeneralPurposeConfig, URL_SAFE};
+use base64::engine::DecodePaddingMode;
+use base64::{alphabet, Engine};
+use cbc::cipher::block_padding::Pkcs7;
+use cbc::cipher::{BlockDecryptMut, BlockEncryptMut, KeyIvInit};
+use hmac::{Hmac, Mac};
+use rand::RngCore;
+use sha2::Sha256;
+
+use crate::errors::CodedError;
+
+type Aes128CbcEnc = cbc::Encryptor<Aes128>;
+type Aes128CbcDec = cbc::Decryptor<Aes128>;
+type HmacSha256 = Hmac<Sha256>;
+
+/// Fernet 令牌的版本字节
+const VERSION: u8 = 0x80;
+/// 版本（1 字节）+ 时间戳（8 字节）+ IV（16 字节）
+const PREFIX_LEN: usize = 25;
+const MAC_LEN: usize = 32;
+/// 与 Python cryptography 相同：检查 TTL 时允许令牌时间比当前时间最多晚 60 秒
+const MAX_CLOCK_SKEW: u64 = 60;
+
+/// 解码时同时接受带填充和不带填充的 URL 安全 base64（Python 生成的令牌和密钥都带填充）
+const URL_SAFE_LENIENT: GeneralPurpose = GeneralPurpose::new(
+    &alphabet::URL_SAFE,
+    GeneralPurposeConfig::new().with_decode_padding_mode(DecodePaddingMode::Indifferent),
+);
+
+/// 当前 Unix 时间（秒）
+pub fn now() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map_or(0, |duration| duration.as_secs())
+}
+
+/// 生成新的 Fernet 密钥：32 个随机字节的 URL 安全 base64 编码，与 `Fernet.generate_key()` 相同
+pub fn generate_key() -> String {
+    let mut key = [0u8; 32];
+    rand::thread_rng().fill_bytes(&mut key);
+    URL_SAFE.encode(key)
+}
+
+/// 解析 Fernet 密钥，返回（签名密钥，加密密钥）
+fn parse_key(key: &str) -> Result<([u8; 16], [u8; 16]), CodedError> {
+    let decoded = URL_SAFE_LENIENT
+        .decode(key.trim())
+        .map_err(|_| CodedError::invalid_key_length("Fernet key must be 32 url-safe base64-encoded bytes"))?;
+    if decoded.len() != 32 {
+        return Err(CodedError::invalid_key_length("Fernet key must be 32 url-safe base64-encoded bytes"));
+    }
+    let mut signing = [0u8; 16];
+    let mut encryption = [0u8; 16];
+    signing.copy_from_slice(&decoded[..16]);
+    encryption.copy_from_slice(&decoded[16..]);
+    Ok((signing, encryption))
+}
+
+/// 以 timestamp 为令牌时间加密 data：版本 || 时间戳 || IV || AES-128-CBC 密文 || HMAC-SHA256，整体为 URL 安全 base64
+pub fn encrypt(key: &str, data: &[u8], timestamp: u64) -> Result<String, CodedError> {
+    let mut iv = [0u8; 16];
+    rand::thread_rng().fill_bytes(&mut iv);
+    encrypt_with_iv(key, data, timestamp, &iv)
+}
+
+fn encrypt_with_iv(key: &str, data: &[u8], timestamp: u64, iv: &[u8; 16]) -> Result<String, CodedError> {
+    let (signing, encryption) = parse_key(key)?;
+    let cipher = Aes128CbcEnc::new_from_slices(&encryption, iv)
+        .map_err(|e| CodedError::unknown(format!("AES cipher init failed: {:?}", e)))?;
+
+    let mut token = Vec::with_capacity(PREFIX_LEN + data.len() + 16 + MAC_LEN);
+    token.push(VERSION);
+    token.extend_from_slice(&timestamp.to_be_bytes());
+    token.extend_from_slice(iv);
+    token.extend_from_slice(&cipher.encrypt_padded_vec_mut::<Pkcs7>(data));
+    let mut mac = HmacSha256::new_from_slice(&signing).map_err(|e| CodedError::unknown(format!("HMAC init failed: {:?}", e)))?;
+    mac.update(&token);
+    token.extend_from_slice(&mac.finalize().into_bytes());
+    Ok(URL_SAFE.encode(token))
+}
+
+/// 验证并解密令牌；ttl 为令牌的最长有效期（秒），设置时还会拒绝时间戳比 now 晚超过 60 秒的令牌
+pub fn decrypt(key: &str, token: &str, ttl: Option<u64>, now: u64) -> Result<Vec<u8>, CodedError> {
+    let (signing, encryption) = parse_key(key)?;
+    let token = URL_SAFE_LENIENT
+        .decode(token.trim())
+        .map_err(|_| CodedError::bad_format("Invalid Fernet token: not url-safe base64"))?;
+    if token.len() < PREFIX_LEN + 16 + MAC_LEN || token[0] != VERSION {
+        return Err(CodedError::bad_format("Invalid Fernet token: bad version or length"));
+    }
+    let (signed, tag) = token.split_at(token.len() - MAC_LEN);
+    let mut mac = HmacSha256::new_from_slice(&signing).map_err(|e| CodedError::unknown(format!("HMAC init failed: {:?}", e)))?;
+    mac.update(signed);
+    mac.verify_slice(tag)
+        .map_err(|_| CodedError::auth_failed("Invalid Fernet token: signature mismatch (wrong key or modified token)"))?;
+
+    let timestamp = u64::from_be_bytes(signed[1..9].try_into().unwrap());
+    if let Some(ttl) = ttl {
+        if timestamp.saturating_add(ttl) < now {
+            return Err(CodedError::auth_failed(format!("Fernet token has expired: issued at {}, ttl {} seconds", timestamp, ttl)));
+        }
+        if now + MAX_CLOCK_SKEW < timestamp {
+            return Err(CodedError::auth_failed(format!("Fernet token timestamp {} is in the future", timestamp)));
+        }
+    }
+
+    let ciphertext = &signed[PREFIX_LEN..];
+    if !ciphertext.len().is_multiple_of(16) {
+        return Err(CodedError::bad_format("Invalid Fernet token: ciphertext is not a whole number of blocks"));
+    }
+    let cipher = Aes128CbcDec::new_from_slices(&encryption, &signed[9..PREFIX_LEN])
+        .map_err(|e| CodedError::unknown(format!("AES cipher init failed: {:?}", e)))?;
+    cipher
+        .decrypt_padded_vec_mut::<Pkcs7>(ciphertext)
+        .map_err(|_| CodedError::bad_format("Invalid Fernet token: bad padding"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::errors::ErrorCode;
+
+    // Fernet 规范（github.com/fernet/spec）中 generate.json、verify.json 和 invalid.json 的测试向量
+    const SECRET: &str = "cw_0x689RpI-jtRR7oE8h_eQsKImvJapLeSbXpwF4e4=";
+    const TOKEN: &str = "gAAAAAAdwJ6wAAECAwQFBgcICQoLDA0ODy021cpGVWKZ_eEwCGM4BLLF_5CV9dOPmrhuVUPgJobwOz7JcbmrR64jVmpU4IwqDA==";
+    /// 1985-10-26T01:20:00-07:00
+    const ISSUED_AT: u64 = 499162800;
+    const TTL: Option<u64> = Some(60);
+
+    #[test]
+    fn generate_vector() {
+        let iv: [u8; 16] = std::array::from_fn(|i| i as u8);
+        assert_eq!(encrypt_with_iv(SECRET, b"hello", ISSUED_AT, &iv).unwrap(), TOKEN);
+    }
+
+    #[test]
+    fn verify_vector() {
+        assert_eq!(decrypt(SECRET, TOKEN, TTL, ISSUED_AT + 1).unwrap(), b"hello");
+        assert_eq!(decrypt(SECRET, TOKEN.trim_end_matches('='), TTL, ISSUED_AT + 1).unwrap(), b"hello");
+    }
+
+    #[test]
+    fn invalid_vectors() {
+        let now = ISSUED_AT + 1;
+        let cases = [
+            ("incorrect mac", "gAAAAAAdwJ6xAAECAwQFBgcICQoLDA0OD3HkMATM5lFqGaerZ-fWPAl1-szkFVzXTuGb4hR8AKtwcaX1YdykQUFBQUFBQUFBQQ==", now, ErrorCode::AuthFailed),
+            ("too short", "gAAAAAAdwJ6xAAECAwQFBgcICQoLDA0OD3HkMATM5lFqGaerZ-fWPA==", now, ErrorCode::BadFormat),
+            ("invalid base64", "%%%%%%%%%%%%%AECAwQFBgcICQoLDA0OD3HkMATM5lFqGaerZ-fWPAl1-szkFVzXTuGb4hR8AKtwcaX1YdykRtfsH-p1YsUD2Q==", now, ErrorCode::BadFormat),
+            ("payload size not multiple of block size", "gAAAAAAdwJ6xAAECAwQFBgcICQoLDA0OD3HkMATM5lFqGaerZ-fWPOm73QeoCk9uGib28Xe5vz6oxq5nmxbx_v7mrfyudzUm", now, ErrorCode::BadFormat),
+            ("payload padding error", "gAAAAAAdwJ6xAAECAwQFBgcICQoLDA0ODz4LEpdELGQAad7aNEHbf-JkLPIpuiYRLQ3RtXatOYREu2FWke6CnJNYIbkuKNqOhw==", now, ErrorCode::BadFormat),
+            ("far-future TS (unacceptable clock skew)", "gAAAAAAdwStRAAECAwQFBgcICQoLDA0OD3HkMATM5lFqGaerZ-fWPAnja1xKYyhd-Y6mSkTOyTGJmw2Xc2a6kBd-iX9b_qXQcw==", now, ErrorCode::AuthFailed),
+            // 1985-10-26T01:21:31-07:00，比签发时间晚 91 秒
+            ("expired TTL", "gAAAAAAdwJ6xAAECAwQFBgcICQoLDA0OD3HkMATM5lFqGaerZ-fWPAl1-szkFVzXTuGb4hR8AKtwcaX1YdykRtfsH-p1YsUD2Q==", ISSUED_AT + 91, ErrorCode::AuthFailed),
+            ("incorrect IV (causes padding error)", "gAAAAAAdwJ6xBQECAwQFBgcICQoLDA0OD3HkMATM5lFqGaerZ-fWPAkLhFLHpGtDBRLRTZeUfWgHSv49TF2AUEZ1TIvcZjK1zQ==", now, ErrorCode::BadFormat),
+        ];
+        for (name, token, now, code) in cases {
+            assert_eq!(decrypt(SECRET, token, TTL, now).unwrap_err().code, code, "{}", name);
+        }
+    }
+
+    #[test]
+    fn ttl_and_clock_skew_only_apply_with_a_ttl() {
+        let expired = "gAAAAAAdwJ6xAAECAwQFBgcICQoLDA0OD3HkMATM5lFqGaerZ-fWPAl1-szkFVzXTuGb4hR8AKtwcaX1YdykRtfsH-p1YsUD2Q==";
+        assert_eq!(decrypt(SECRET, expired, None, ISSUED_AT + 91).unwrap(), b"");
+        // 令牌时间最多可以比当前时间晚 60 秒
+        assert_eq!(decrypt(SECRET, TOKEN, TTL, ISSUED_AT - MAX_CLOCK_SKEW).unwrap(), b"hello");
+        assert_eq!(decrypt(SECRET, TOKEN, TTL, ISSUED_AT - MAX_CLOCK_SKEW - 1).unwrap_err().code, ErrorCode::AuthFailed);
+        assert_eq!(decrypt(SECRET, TOKEN, None, ISSUED_AT - 3600).unwrap(), b"hello");
+        assert_eq!(decrypt(SECRET, TOKEN, TTL, ISSUED_AT + 60).unwrap(), b"hello");
+        assert_eq!(decrypt(SECRET, TOKEN, TTL, ISSUED_AT + 61).unwrap_err().code, ErrorCode::AuthFailed);
+    }
+
+    #[test]
+    fn round_trips_and_rejects_bad_keys() {
+        let key = generate_key();
+        let token = encrypt(&key, b"secret data", 1_700_000_000).unwrap();
+        assert_eq!(decrypt(&key, &token, None, 1_700_000_000).unwrap(), b"secret data");
+        assert_eq!(decrypt(SECRET, &token, None, 1_700_000_000).unwrap_err().code, ErrorCode::AuthFailed);
+        assert_eq!(encrypt("c2hvcnQ=", b"x", 0).unwrap_err().code, ErrorCode::InvalidKeyLength);
+    }
+}
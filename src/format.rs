@@ -0,0 +1,1420 @@
+use std::io::{BufRead, BufReader, ErrorKind, Read, Seek, SeekFrom, Write};
+use std::path::Path;
+
+use crate::age_format::{AGE_ARMOR_MAGIC, AGE_MAGIC};
+use crate::attributes::FileAttributes;
+use crate::compression::{max_compressed_len, Compression};
+use crate::crypto::{
+    header_mac, open_field, seal_field, verify_header_mac, CryptoAlgorithm, HashAlgorithm, PlaintextHasher,
+    CHUNK_CHECKSUM_LEN, CHUNK_MAC_LEN, HEADER_MAC_LEN,
+};
+use crate::errors::{CodedError, ErrorCode};
+use crate::fd::{self, Target};
+use crate::gpg::{looks_like_openpgp, PGP_ARMOR_MAGIC};
+use crate::openssl::OPENSSL_MAGIC;
+use crate::volume::VOLUME_MAGIC;
+
+/// 文件被截断的错误码
+pub const ERR_TRUNCATED: &str = "ERR_TRUNCATED";
+
+/// 分片格式（v1）的文件头标记
+pub const CHUNKED_V1_MAGIC: &[u8] = b"CHUNKS:";
+
+/// 容器格式（v2）的文件头标记
+pub const CONTAINER_MAGIC: &[u8] = b"ZIPPYENC";
+pub const CONTAINER_VERSION: u8 = 2;
+/// v2 容器内的格式修订号，新增字段或语义变化时递增
+pub const FORMAT_REVISION: u32 = 15;
+/// 写入文件头的工具版本
+pub const TOOL_VERSION: &str = concat!(env!("CARGO_PKG_NAME"), "/", env!("CARGO_PKG_VERSION"));
+
+/// 容器文件头的固定部分：魔数 + 版本 + 布局 + 算法 + 标志位 + 字段区长度
+const CONTAINER_FIXED_LEN: usize = 16;
+/// 字段区长度上限，防止损坏的文件头导致超大内存分配
+const MAX_HEADER_FIELDS_LEN: u32 = 16 * 1024 * 1024;
+
+/// 标志位：字段区之后跟随文件头认证标签
+const FLAG_HEADER_MAC: u8 = 0x01;
+/// 标志位：每个分片带认证标签，文件末尾有整个文件的 MAC
+const FLAG_CHUNK_AUTH: u8 = 0x02;
+/// 标志位：分片序号和分片总数作为关联数据参与分片认证
+const FLAG_CHUNK_SEQUENCE: u8 = 0x04;
+/// 标志位：分片帧使用定长二进制长度字段
+const FLAG_BINARY_FRAMES: u8 = 0x08;
+/// 标志位：文件 MAC 之后跟随结尾字段（分片索引等）和定长的结尾标记
+const FLAG_FOOTER: u8 = 0x10;
+/// 标志位：结尾标记之后跟随 Reed-Solomon 纠错数据
+const FLAG_PARITY: u8 = 0x20;
+/// 标志位：全零的分片以空分片帧保存，解密时还原为零
+const FLAG_SPARSE: u8 = 0x40;
+/// 标志位：分片认证只绑定分片序号而不绑定分片总数，可以在文件末尾追加分片；结尾字段不记录明文摘要
+const FLAG_APPENDABLE: u8 = 0x80;
+/// 当前版本能够识别的全部标志位
+const KNOWN_FLAGS: u8 = FLAG_HEADER_MAC
+    | FLAG_CHUNK_AUTH
+    | FLAG_CHUNK_SEQUENCE
+    | FLAG_BINARY_FRAMES
+    | FLAG_FOOTER
+    | FLAG_PARITY
+    | FLAG_SPARSE
+    | FLAG_APPENDABLE;
+
+/// 可追加的文件分片认证中代替分片总数的值：追加分片后已写出的分片仍能通过认证，截断由整个文件的 MAC 发现
+const OPEN_SEQUENCE_TOTAL: u64 = u64::MAX;
+
+/// 容器文件头字段的 TLV 标签
+const TAG_ORIGINAL_SIZE: u8 = 1;
+const TAG_CHUNK_SIZE: u8 = 2;
+const TAG_FILENAME: u8 = 3;
+const TAG_METADATA: u8 = 4;
+const TAG_CREATED_AT: u8 = 5;
+const TAG_TOOL_VERSION: u8 = 6;
+const TAG_FORMAT_REVISION: u8 = 7;
+const TAG_HASH_ALGORITHM: u8 = 8;
+const TAG_PLAINTEXT_HASH: u8 = 9;
+const TAG_COMPRESSION: u8 = 10;
+const TAG_MIME_TYPE: u8 = 11;
+const TAG_ATTRIBUTES: u8 = 12;
+
+/// 加密字段的用途标签，同时作为子密钥派生信息和关联数据
+pub const FIELD_FILENAME: &str = "filename";
+pub const FIELD_METADATA: &str = "metadata";
+pub const FIELD_DIGEST: &str = "digest";
+pub const FIELD_PLAINTEXT_HASH: &str = "plaintext hash";
+pub const FIELD_MIME_TYPE: &str = "mime type";
+pub const FIELD_ATTRIBUTES: &str = "file attributes";
+
+/// 用户元数据大小上限
+pub const MAX_METADATA_LEN: usize = 1024 * 1024;
+
+/// 用户元数据：原始字节或 JSON 值
+pub enum UserMetadata {
+    Binary(Vec<u8>),
+    Json(serde_json::Value),
+}
+
+impl UserMetadata {
+    pub fn to_bytes(&self) -> Result<Vec<u8>, CodedError> {
+        let mut bytes = Vec::new();
+        match self {
+            UserMetadata::Binary(data) => {
+                bytes.push(0);
+                bytes.extend_from_slice(data);
+            }
+            UserMetadata::Json(value) => {
+                bytes.push(1);
+                let json = serde_json::to_vec(value).map_err(|e| CodedError::invalid_argument(format!("Failed to serialize metadata: {}", e)))?;
+                bytes.extend_from_slice(&json);
+            }
+        }
+        if bytes.len() - 1 > MAX_METADATA_LEN {
+            return Err(CodedError::invalid_argument(format!("Metadata too large: {} bytes (max {})", bytes.len() - 1, MAX_METADATA_LEN)));
+        }
+        Ok(bytes)
+    }
+
+    pub fn from_bytes(bytes: &[u8]) -> Result<Self, CodedError> {
+        match bytes.split_first() {
+            Some((0, data)) => Ok(UserMetadata::Binary(data.to_vec())),
+            Some((1, json)) => serde_json::from_slice(json)
+                .map(UserMetadata::Json)
+                .map_err(|e| CodedError::bad_format(format!("Invalid metadata JSON: {}", e))),
+            _ => Err(CodedError::bad_format("Invalid metadata encoding")),
+        }
+    }
+}
+
+/// 加密文件的布局
+#[derive(Clone, Copy, PartialEq)]
+pub enum Layout {
+    Monolithic,
+    Chunked,
+}
+
+impl Layout {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Layout::Monolithic => "monolithic",
+            Layout::Chunked => "chunked",
+        }
+    }
+
+    fn id(&self) -> u8 {
+        match self {
+            Layout::Monolithic => 0,
+            Layout::Chunked => 1,
+        }
+    }
+
+    fn from_id(id: u8) -> Option<Self> {
+        match id {
+            0 => Some(Layout::Monolithic),
+            1 => Some(Layout::Chunked),
+            _ => None,
+        }
+    }
+}
+
+/// v2 容器文件头
+///
+/// 布局：`ZIPPYENC` + 版本(u8) + 布局(u8) + 算法(u8) + 标志位(u8) + 字段区长度(u32 LE) + 字段区 + 认证标签。
+/// 字段区由 TLV 组成：标签(u8) + 长度(u32 LE) + 值，读取时忽略未知标签。
+/// 认证标签是对前面所有文件头字节计算的 HMAC-SHA256，由 `FLAG_HEADER_MAC` 标志位表示存在。
+#[derive(Clone)]
+pub struct ContainerHeader {
+    pub layout: Layout,
+    pub algorithm: CryptoAlgorithm,
+    pub original_size: u64,
+    /// 分片大小，整体加密时为 0
+    pub chunk_size: u64,
+    /// 加密并认证后的原始文件名
+    pub encrypted_filename: Option<Vec<u8>>,
+    /// 加密并认证后的用户元数据
+    pub encrypted_metadata: Option<Vec<u8>>,
+    /// 加密并认证后的明文 MIME 类型
+    pub encrypted_mime_type: Option<Vec<u8>>,
+    /// 加密并认证后的文件属性（权限、时间、所有者和扩展属性）
+    pub encrypted_attributes: Option<Vec<u8>>,
+    /// 创建时间（Unix 毫秒）
+    pub created_at: Option<u64>,
+    /// 写入文件的工具及版本，例如 `encryptor/0.1.0`
+    pub tool_version: Option<String>,
+    pub format_revision: Option<u32>,
+    /// 文件头认证标签，读取的文件头总是有；新建的文件头在写出之前为 None
+    pub header_mac: Option<Vec<u8>>,
+    /// 分片是否带认证标签，且文件末尾有整个文件的 MAC；读取的分片文件总是带有
+    pub authenticated_chunks: bool,
+    /// 分片认证是否绑定分片序号和总数
+    pub sequenced_chunks: bool,
+    /// 分片帧是否使用二进制长度字段
+    pub binary_frames: bool,
+    /// 文件末尾是否有结尾字段（分片索引等）
+    pub has_footer: bool,
+    /// 文件末尾是否附加了纠错数据
+    pub has_parity: bool,
+    /// 全零的分片以空分片帧保存（稀疏文件），只用于带认证的分片文件
+    pub sparse: bool,
+    /// 可以在文件末尾追加分片（见 `FLAG_APPENDABLE`）
+    pub appendable: bool,
+    /// 明文摘要算法；整体加密时摘要加密保存在文件头中，分片文件的摘要保存在结尾字段中（未设置时为 SHA-256）
+    pub hash_algorithm: Option<HashAlgorithm>,
+    /// 加密并认证后的明文摘要（仅整体加密）
+    pub encrypted_plaintext_hash: Option<Vec<u8>>,
+    /// 加密前的压缩算法；分片文件逐个分片压缩，整体加密时压缩整个文件
+    pub compression: Option<Compression>,
+    /// 认证标签覆盖的文件头字节，读取文件头时保存，用于校验
+    authenticated_bytes: Vec<u8>,
+}
+
+fn push_field(fields: &mut Vec<u8>, tag: u8, value: &[u8]) {
+    fields.push(tag);
+    fields.extend_from_slice(&(value.len() as u32).to_le_bytes());
+    fields.extend_from_slice(value);
+}
+
+/// 解析 TLV 字段区，返回（标签，值）列表
+fn parse_fields<'a>(fields: &'a [u8], what: &str) -> Result<Vec<(u8, &'a [u8])>, CodedError> {
+    let mut parsed = Vec::new();
+    let mut rest = fields;
+    while !rest.is_empty() {
+        if rest.len() < 5 {
+            return Err(CodedError::truncated(format!("Truncated {} field", what)));
+        }
+        let tag = rest[0];
+        let len = u32::from_le_bytes([rest[1], rest[2], rest[3], rest[4]]) as usize;
+        if rest.len() < 5 + len {
+            return Err(CodedError::truncated(format!("Truncated {} field", what)));
+        }
+        parsed.push((tag, &rest[5..5 + len]));
+        rest = &rest[5 + len..];
+    }
+    Ok(parsed)
+}
+
+fn read_u64_field(value: &[u8], name: &str) -> Result<u64, CodedError> {
+    let bytes: [u8; 8] = value
+        .try_into()
+        .map_err(|_| CodedError::bad_header(format!("Invalid {} field in header", name)))?;
+    Ok(u64::from_le_bytes(bytes))
+}
+
+impl ContainerHeader {
+    pub fn new(layout: Layout, algorithm: CryptoAlgorithm, original_size: u64, chunk_size: u64) -> Self {
+        ContainerHeader {
+            layout,
+            algorithm,
+            original_size,
+            chunk_size,
+            encrypted_filename: None,
+            encrypted_metadata: None,
+            encrypted_mime_type: None,
+            encrypted_attributes: None,
+            created_at: None,
+            tool_version: None,
+            format_revision: None,
+            header_mac: None,
+            authenticated_chunks: false,
+            sequenced_chunks: false,
+            binary_frames: false,
+            has_footer: false,
+            has_parity: false,
+            sparse: false,
+            appendable: false,
+            hash_algorithm: None,
+            encrypted_plaintext_hash: None,
+            compression: None,
+            authenticated_bytes: Vec::new(),
+        }
+    }
+
+    /// 创建新文件使用的文件头，记录当前时间、工具版本和格式修订号
+    pub fn for_new_file(layout: Layout, algorithm: CryptoAlgorithm, original_size: u64, chunk_size: u64) -> Self {
+        let created_at = std::time::SystemTime::now()
+            .duration_since(std::time::UNIX_EPOCH)
+            .map(|elapsed| elapsed.as_millis() as u64)
+            .unwrap_or(0);
+        let mut header = ContainerHeader::new(layout, algorithm, original_size, chunk_size);
+        header.created_at = Some(created_at);
+        header.tool_version = Some(TOOL_VERSION.to_string());
+        header.format_revision = Some(FORMAT_REVISION);
+        header.authenticated_chunks = layout == Layout::Chunked;
+        header.sequenced_chunks = layout == Layout::Chunked;
+        header.binary_frames = layout == Layout::Chunked;
+        header.has_footer = layout == Layout::Chunked;
+        header
+    }
+
+    /// 为格式转换后的文件创建文件头，沿用原文件头中加密保存的文件名、元数据、MIME 类型、文件属性和创建时间
+    pub fn converted_from(source: &ContainerHeader, layout: Layout, original_size: u64, chunk_size: u64) -> Self {
+        let mut header = ContainerHeader::for_new_file(layout, source.algorithm.clone(), original_size, chunk_size);
+        header.encrypted_filename = source.encrypted_filename.clone();
+        header.encrypted_metadata = source.encrypted_metadata.clone();
+        header.encrypted_mime_type = source.encrypted_mime_type.clone();
+        header.encrypted_attributes = source.encrypted_attributes.clone();
+        header.created_at = source.created_at.or(header.created_at);
+        header.hash_algorithm = source.hash_algorithm;
+        header.sparse = source.sparse && layout == Layout::Chunked;
+        header.appendable = source.appendable && layout == Layout::Chunked;
+        header.compression = source.compression.map(|compression| Compression { adaptive: true, ..compression });
+        header
+    }
+
+    /// 序列化文件头，并在末尾附加使用文件密钥计算的认证标签
+    pub fn to_bytes(&self, key: &[u8]) -> Result<Vec<u8>, CodedError> {
+        let mut fields = Vec::new();
+        push_field(&mut fields, TAG_ORIGINAL_SIZE, &self.original_size.to_le_bytes());
+        if self.layout == Layout::Chunked {
+            push_field(&mut fields, TAG_CHUNK_SIZE, &self.chunk_size.to_le_bytes());
+        }
+        if let Some(name) = &self.encrypted_filename {
+            push_field(&mut fields, TAG_FILENAME, name);
+        }
+        if let Some(metadata) = &self.encrypted_metadata {
+            push_field(&mut fields, TAG_METADATA, metadata);
+        }
+        if let Some(created_at) = self.created_at {
+            push_field(&mut fields, TAG_CREATED_AT, &created_at.to_le_bytes());
+        }
+        if let Some(tool_version) = &self.tool_version {
+            push_field(&mut fields, TAG_TOOL_VERSION, tool_version.as_bytes());
+        }
+        if let Some(revision) = self.format_revision {
+            push_field(&mut fields, TAG_FORMAT_REVISION, &revision.to_le_bytes());
+        }
+        if let Some(algorithm) = self.hash_algorithm {
+            push_field(&mut fields, TAG_HASH_ALGORITHM, &[algorithm.id()]);
+        }
+        if let Some(hash) = &self.encrypted_plaintext_hash {
+            push_field(&mut fields, TAG_PLAINTEXT_HASH, hash);
+        }
+        if let Some(compression) = &self.compression {
+            push_field(&mut fields, TAG_COMPRESSION, &compression.to_bytes());
+        }
+        if let Some(mime_type) = &self.encrypted_mime_type {
+            push_field(&mut fields, TAG_MIME_TYPE, mime_type);
+        }
+        if let Some(attributes) = &self.encrypted_attributes {
+            push_field(&mut fields, TAG_ATTRIBUTES, attributes);
+        }
+
+        let mut bytes = Vec::with_capacity(CONTAINER_FIXED_LEN + fields.len() + HEADER_MAC_LEN);
+        bytes.extend_from_slice(CONTAINER_MAGIC);
+        bytes.push(CONTAINER_VERSION);
+        bytes.push(self.layout.id());
+        bytes.push(self.algorithm.id());
+        let mut flags = FLAG_HEADER_MAC;
+        if self.authenticated_chunks {
+            flags |= FLAG_CHUNK_AUTH;
+        }
+        if self.sequenced_chunks {
+            flags |= FLAG_CHUNK_SEQUENCE;
+        }
+        if self.binary_frames {
+            flags |= FLAG_BINARY_FRAMES;
+        }
+        if self.has_footer {
+            flags |= FLAG_FOOTER;
+        }
+        if self.has_parity {
+            flags |= FLAG_PARITY;
+        }
+        if self.sparse {
+            flags |= FLAG_SPARSE;
+        }
+        if self.appendable {
+            flags |= FLAG_APPENDABLE;
+        }
+        bytes.push(flags);
+        bytes.extend_from_slice(&(fields.len() as u32).to_le_bytes());
+        bytes.extend_from_slice(&fields);
+        let tag = header_mac(key, &bytes)?;
+        bytes.extend_from_slice(&tag);
+        Ok(bytes)
+    }
+
+    /// 读取容器文件头，返回文件头及其占用的字节数
+    pub fn read<R: Read>(reader: &mut R) -> Result<(Self, u64), CodedError> {
+        let mut fixed = [0u8; CONTAINER_FIXED_LEN];
+        reader
+            .read_exact(&mut fixed)
+            .map_err(|e| CodedError::io_read(format!("Error reading header: {}", e)))?;
+        if &fixed[..CONTAINER_MAGIC.len()] != CONTAINER_MAGIC {
+            return Err(CodedError::bad_header("Invalid file format - missing container header"));
+        }
+        if fixed[8] != CONTAINER_VERSION {
+            return Err(CodedError::unsupported(format!("Unsupported container version: {}", fixed[8])));
+        }
+        let layout = Layout::from_id(fixed[9]).ok_or_else(|| CodedError::bad_header(format!("Unknown layout in header: {}", fixed[9])))?;
+        let algorithm = CryptoAlgorithm::from_id(fixed[10])
+            .ok_or_else(|| CodedError::bad_header(format!("Unknown algorithm in header: {}", fixed[10])))?;
+        let flags = fixed[11];
+        // 8 个标志位目前都已定义，保留检查以免以后修改 KNOWN_FLAGS 时遗漏
+        #[allow(clippy::bad_bit_mask)]
+        if flags & !KNOWN_FLAGS != 0 {
+            return Err(CodedError::unsupported(format!("Unsupported header flags: {:#04x}", flags)));
+        }
+        // v2 文件头必须带认证标签：去掉标签并清除标志位的文件头不能被当作早期的未认证文件接受
+        if flags & FLAG_HEADER_MAC == 0 {
+            return Err(CodedError::auth_failed("Header is not authenticated"));
+        }
+        // 分片认证由格式版本决定而不由文件自己声明：v2 分片文件必须带分片认证标签和整个文件的 MAC
+        if layout == Layout::Chunked && flags & FLAG_CHUNK_AUTH == 0 {
+            return Err(CodedError::auth_failed("Chunks are not authenticated"));
+        }
+        // 分片认证以文件头认证标签为起点，只能用于分片文件；绑定分片序号依赖分片认证；二进制分片帧只用于分片文件；
+        // 结尾字段跟在文件 MAC 之后；纠错数据跟在结尾标记之后；空分片帧依赖分片认证确认其未被篡改；
+        // 可追加的文件依靠分片序号和结尾字段中的分片索引定位分片
+        let invalid = (flags & FLAG_CHUNK_AUTH != 0 && (flags & FLAG_HEADER_MAC == 0 || layout != Layout::Chunked))
+            || (flags & FLAG_CHUNK_SEQUENCE != 0 && flags & FLAG_CHUNK_AUTH == 0)
+            || (flags & FLAG_BINARY_FRAMES != 0 && layout != Layout::Chunked)
+            || (flags & FLAG_FOOTER != 0 && flags & FLAG_CHUNK_AUTH == 0)
+            || (flags & FLAG_PARITY != 0 && flags & FLAG_FOOTER == 0)
+            || (flags & FLAG_SPARSE != 0 && flags & FLAG_CHUNK_AUTH == 0)
+            || (flags & FLAG_APPENDABLE != 0 && (flags & FLAG_CHUNK_SEQUENCE == 0 || flags & FLAG_FOOTER == 0));
+        if invalid {
+            return Err(CodedError::bad_header(format!("Invalid header flags: {:#04x}", flags)));
+        }
+
+        let fields_len = u32::from_le_bytes([fixed[12], fixed[13], fixed[14], fixed[15]]);
+        if fields_len > MAX_HEADER_FIELDS_LEN {
+            return Err(CodedError::bad_header(format!("Header too large: {} bytes", fields_len)));
+        }
+        let mut fields = vec![0u8; fields_len as usize];
+        reader
+            .read_exact(&mut fields)
+            .map_err(|e| CodedError::io_read(format!("Error reading header: {}", e)))?;
+
+        let mut header = ContainerHeader::new(layout, algorithm, 0, 0);
+        header.authenticated_chunks = flags & FLAG_CHUNK_AUTH != 0;
+        header.sequenced_chunks = flags & FLAG_CHUNK_SEQUENCE != 0;
+        header.binary_frames = flags & FLAG_BINARY_FRAMES != 0;
+        header.has_footer = flags & FLAG_FOOTER != 0;
+        header.has_parity = flags & FLAG_PARITY != 0;
+        header.sparse = flags & FLAG_SPARSE != 0;
+        header.appendable = flags & FLAG_APPENDABLE != 0;
+        for (tag, value) in parse_fields(&fields, "header")? {
+            match tag {
+                TAG_ORIGINAL_SIZE => header.original_size = read_u64_field(value, "file size")?,
+                TAG_CHUNK_SIZE => header.chunk_size = read_u64_field(value, "chunk size")?,
+                TAG_FILENAME => header.encrypted_filename = Some(value.to_vec()),
+                TAG_METADATA => header.encrypted_metadata = Some(value.to_vec()),
+                TAG_MIME_TYPE => header.encrypted_mime_type = Some(value.to_vec()),
+                TAG_ATTRIBUTES => header.encrypted_attributes = Some(value.to_vec()),
+                TAG_CREATED_AT => header.created_at = Some(read_u64_field(value, "creation time")?),
+                TAG_TOOL_VERSION => header.tool_version = Some(String::from_utf8_lossy(value).into_owned()),
+                TAG_FORMAT_REVISION => {
+                    let bytes: [u8; 4] = value
+                        .try_into()
+                        .map_err(|_| CodedError::bad_header("Invalid format revision field in header"))?;
+                    header.format_revision = Some(u32::from_le_bytes(bytes));
+                }
+                TAG_HASH_ALGORITHM => {
+                    let algorithm = match value {
+                        [id] => HashAlgorithm::from_id(*id),
+                        _ => None,
+                    };
+                    header.hash_algorithm =
+                        Some(algorithm.ok_or_else(|| CodedError::bad_header("Unknown hash algorithm in header"))?);
+                }
+                TAG_PLAINTEXT_HASH => header.encrypted_plaintext_hash = Some(value.to_vec()),
+                TAG_COMPRESSION => {
+                    header.compression = Some(
+                        Compression::from_bytes(value).ok_or_else(|| CodedError::bad_header("Unknown compression in header"))?,
+                    );
+                }
+                _ => {}
+            }
+        }
+
+        if header.layout == Layout::Chunked && header.chunk_size == 0 {
+            return Err(CodedError::bad_header("Invalid chunk size in header"));
+        }
+
+        let mut header_len = CONTAINER_FIXED_LEN + fields.len();
+        if flags & FLAG_HEADER_MAC != 0 {
+            let mut tag = vec![0u8; HEADER_MAC_LEN];
+            reader
+                .read_exact(&mut tag)
+                .map_err(|e| CodedError::io_read(format!("Error reading header: {}", e)))?;
+            header.header_mac = Some(tag);
+            header_len += HEADER_MAC_LEN;
+        }
+        header.authenticated_bytes = [&fixed[..], &fields[..]].concat();
+
+        Ok((header, header_len as u64))
+    }
+
+    /// 分片认证绑定的分片总数，分片未绑定序号时为 None；可追加的文件不绑定实际的分片总数
+    pub fn chunk_sequence_total(&self, total_chunks: u64) -> Option<u64> {
+        match self.sequenced_chunks {
+            true if self.appendable => Some(OPEN_SEQUENCE_TOTAL),
+            true => Some(total_chunks),
+            false => None,
+        }
+    }
+
+    /// 使用密钥校验文件头认证标签；没有认证标签的文件头（尚未写出）不能通过校验
+    pub fn verify(&self, key: &[u8]) -> Result<(), CodedError> {
+        match &self.header_mac {
+            Some(tag) => verify_header_mac(key, &self.authenticated_bytes, tag),
+            None => Err(CodedError::auth_failed("Header is not authenticated")),
+        }
+    }
+
+    /// 使用密钥解密文件头中保存的原始文件名
+    pub fn decrypt_filename(&self, key: &[u8]) -> Result<Option<String>, CodedError> {
+        match &self.encrypted_filename {
+            Some(encrypted) => {
+                let name = open_field(key, FIELD_FILENAME, encrypted)?;
+                let name = String::from_utf8(name).map_err(|_| CodedError::bad_format("Stored filename is not valid UTF-8"))?;
+                Ok(Some(sanitize_filename(&name)?))
+            }
+            None => Ok(None),
+        }
+    }
+
+    /// 使用密钥解密文件头中保存的用户元数据
+    pub fn decrypt_metadata(&self, key: &[u8]) -> Result<Option<UserMetadata>, CodedError> {
+        match &self.encrypted_metadata {
+            Some(encrypted) => {
+                let bytes = open_field(key, FIELD_METADATA, encrypted)?;
+                Ok(Some(UserMetadata::from_bytes(&bytes)?))
+            }
+            None => Ok(None),
+        }
+    }
+
+    /// 使用密钥解密文件头中保存的明文 MIME 类型
+    pub fn decrypt_mime_type(&self, key: &[u8]) -> Result<Option<String>, CodedError> {
+        match &self.encrypted_mime_type {
+            Some(encrypted) => {
+                let mime_type = open_field(key, FIELD_MIME_TYPE, encrypted)?;
+                let mime_type = String::from_utf8(mime_type).map_err(|_| CodedError::bad_format("Stored MIME type is not valid UTF-8"))?;
+                Ok(Some(mime_type))
+            }
+            None => Ok(None),
+        }
+    }
+
+    /// 使用密钥解密文件头中保存的文件属性
+    pub fn decrypt_attributes(&self, key: &[u8]) -> Result<Option<FileAttributes>, CodedError> {
+        match &self.encrypted_attributes {
+            Some(encrypted) => {
+                let bytes = open_field(key, FIELD_ATTRIBUTES, encrypted)?;
+                Ok(Some(FileAttributes::from_bytes(&bytes)?))
+            }
+            None => Ok(None),
+        }
+    }
+
+    /// 整体加密时计算明文摘要并加密保存到文件头，未选择摘要算法时不保存
+    pub fn seal_plaintext_hash(&mut self, key: &[u8], data: &[u8]) -> Result<(), CodedError> {
+        if let Some(algorithm) = self.hash_algorithm {
+            self.seal_plaintext_digest(key, &PlaintextHasher::digest(algorithm, data))?;
+        }
+        Ok(())
+    }
+
+    /// 加密保存已经计算好的明文摘要，摘要由 hash_algorithm 指定的算法计算
+    pub fn seal_plaintext_digest(&mut self, key: &[u8], digest: &[u8]) -> Result<(), CodedError> {
+        self.encrypted_plaintext_hash = Some(seal_field(key, FIELD_PLAINTEXT_HASH, digest)?);
+        Ok(())
+    }
+
+    /// 核对解密得到的明文与文件头中保存的摘要，返回是否进行了核对
+    pub fn verify_plaintext_hash(&self, key: &[u8], data: &[u8]) -> Result<bool, CodedError> {
+        let Some(encrypted) = &self.encrypted_plaintext_hash else {
+            return Ok(false);
+        };
+        let expected = open_field(key, FIELD_PLAINTEXT_HASH, encrypted)?;
+        let algorithm = self.hash_algorithm.unwrap_or(HashAlgorithm::Sha256);
+        if PlaintextHasher::digest(algorithm, data) != expected {
+            return Err(CodedError::auth_failed("Plaintext hash mismatch - decrypted data is corrupted"));
+        }
+        Ok(true)
+    }
+}
+
+/// 只保留文件名部分，拒绝可能导致路径穿越的名称
+pub fn sanitize_filename(name: &str) -> Result<String, CodedError> {
+    let invalid = name.is_empty()
+        || name == "."
+        || name == ".."
+        || name.contains(['/', '\\', '\0']);
+    if invalid {
+        return Err(CodedError::bad_format(format!("Invalid filename: {:?}", name)));
+    }
+    Ok(name.to_string())
+}
+
+/// 取输入路径中的文件名部分
+pub fn file_name_of(path: &str) -> Result<String, CodedError> {
+    Path::new(path)
+        .file_name()
+        .and_then(|name| name.to_str())
+        .map(|name| name.to_string())
+        .ok_or_else(|| CodedError::invalid_argument(format!("Cannot determine file name of {}", path)))
+}
+
+/// v1 分片文件头：`CHUNKS:<原始大小>:<块大小>:`
+pub struct ChunkedHeaderV1 {
+    pub original_size: u64,
+    pub chunk_size: usize,
+    /// 文件头占用的字节数（第一个分片从这里开始）
+    pub header_len: u64,
+}
+
+/// 分片文件头（v1 文本格式或 v2 容器格式）
+pub struct ChunkedHeader {
+    pub original_size: u64,
+    pub chunk_size: usize,
+    /// 文件头占用的字节数（第一个分片从这里开始）
+    pub header_len: u64,
+    /// v2 容器文件头，v1 文件为 None
+    pub container: Option<ContainerHeader>,
+}
+
+impl ChunkedHeader {
+    pub fn total_chunks(&self) -> u64 {
+        if self.chunk_size == 0 {
+            return 0;
+        }
+        self.original_size.div_ceil(self.chunk_size as u64)
+    }
+
+    /// 分片帧的长度字段格式
+    pub fn frame_format(&self) -> FrameFormat {
+        match &self.container {
+            Some(container) if container.binary_frames => FrameFormat::Binary,
+            _ => FrameFormat::Text,
+        }
+    }
+
+    /// 单个分片帧的最大长度：分片大小（压缩时为压缩后的上限）加上 IV/nonce、填充和认证标签
+    pub fn max_frame_len(&self) -> usize {
+        match self.compression() {
+            Some(_) => max_compressed_len(self.chunk_size).saturating_add(MAX_CHUNK_OVERHEAD),
+            None => self.chunk_size.saturating_add(MAX_CHUNK_OVERHEAD),
+        }
+    }
+
+    /// 分片加密前使用的压缩算法
+    pub fn compression(&self) -> Option<Compression> {
+        self.container.as_ref().and_then(|container| container.compression)
+    }
+
+    /// 第 index 个分片（从 0 开始）的明文长度
+    pub fn chunk_len(&self, index: u64) -> usize {
+        let start = index.saturating_mul(self.chunk_size as u64);
+        self.original_size.saturating_sub(start).min(self.chunk_size as u64) as usize
+    }
+
+    /// 全零的分片是否以空分片帧保存
+    pub fn sparse(&self) -> bool {
+        self.container.as_ref().is_some_and(|container| container.sparse)
+    }
+
+    /// 把第 index 个分片解密得到的数据还原为明文：空分片还原为零，压缩的分片解压
+    pub fn restore_chunk(&self, index: u64, decrypted: Vec<u8>) -> Result<Vec<u8>, CodedError> {
+        let chunk_len = self.chunk_len(index);
+        match self.compression() {
+            _ if self.sparse() && decrypted.is_empty() => Ok(vec![0u8; chunk_len]),
+            Some(compression) => compression.decompress(&decrypted, chunk_len),
+            None => Ok(decrypted),
+        }
+    }
+
+    /// 分片认证绑定的分片总数，分片未绑定序号时为 None
+    pub fn chunk_sequence_total(&self) -> Option<u64> {
+        self.container
+            .as_ref()
+            .and_then(|container| container.chunk_sequence_total(self.total_chunks()))
+    }
+}
+
+impl ChunkedHeaderV1 {
+    pub fn total_chunks(&self) -> u64 {
+        if self.chunk_size == 0 {
+            return 0;
+        }
+        self.original_size.div_ceil(self.chunk_size as u64)
+    }
+}
+
+/// 读取错误：文件提前结束时返回以 ERR_TRUNCATED 开头的错误
+fn read_error(err: std::io::Error, what: &str) -> CodedError {
+    if err.kind() == ErrorKind::UnexpectedEof {
+        CodedError::truncated(format!("{}: file ends unexpectedly while reading {}", ERR_TRUNCATED, what))
+    } else {
+        CodedError::io(ErrorCode::IoRead, format_args!("Error reading {}", what), err)
+    }
+}
+
+/// 文本长度字段的最大字节数：u64 的最大位数加上结尾的 `:`
+const MAX_TEXT_LEN_FIELD: usize = 21;
+
+/// 分片加密后增加的最大字节数：AES-CBC 为 IV(16) + 填充(16) + HMAC(32)
+pub const MAX_CHUNK_OVERHEAD: usize = 64;
+
+/// 分片帧的长度字段格式
+#[derive(Clone, Copy, PartialEq)]
+pub enum FrameFormat {
+    /// v1 及早期 v2 文件：ASCII `<密文长度>:`
+    Text,
+    /// 定长 u32 LE 长度
+    Binary,
+}
+
+impl FrameFormat {
+    /// 编码分片帧的长度字段
+    pub fn encode_len(&self, len: usize) -> Result<Vec<u8>, CodedError> {
+        match self {
+            FrameFormat::Text => Ok(format!("{}:", len).into_bytes()),
+            FrameFormat::Binary => {
+                let len = u32::try_from(len).map_err(|_| CodedError::bad_format(format!("Encrypted chunk too large: {} bytes", len)))?;
+                Ok(len.to_le_bytes().to_vec())
+            }
+        }
+    }
+
+    /// 读取分片帧的长度字段，what 用于错误信息，例如 "chunk 3"
+    ///
+    /// 文本长度字段从缓冲区中一次取出到 `:` 为止，不逐字节读取；超过 u64 最大位数仍没有 `:` 时视为无效长度。
+    pub fn read_len<R: BufRead>(&self, reader: &mut R, what: &str) -> Result<usize, CodedError> {
+        match self {
+            FrameFormat::Text => {
+                let mut field = Vec::new();
+                reader
+                    .take(MAX_TEXT_LEN_FIELD as u64)
+                    .read_until(b':', &mut field)
+                    .map_err(|e| read_error(e, what))?;
+                if field.pop() != Some(b':') {
+                    return match field.len() + 1 < MAX_TEXT_LEN_FIELD {
+                        true => Err(read_error(ErrorKind::UnexpectedEof.into(), what)),
+                        false => Err(CodedError::bad_format(format!("Invalid encrypted chunk size in {}", what))),
+                    };
+                }
+                std::str::from_utf8(&field)
+                    .ok()
+                    .and_then(|digits| digits.parse().ok())
+                    .ok_or_else(|| CodedError::bad_format(format!("Invalid encrypted chunk size in {}", what)))
+            }
+            FrameFormat::Binary => {
+                let mut len = [0u8; 4];
+                reader.read_exact(&mut len).map_err(|e| read_error(e, what))?;
+                Ok(u32::from_le_bytes(len) as usize)
+            }
+        }
+    }
+
+    /// 跳过一个分片帧（不读取分片数据），返回跳过的字节数；在 BufReader 的缓冲区内移动时不重新读取文件
+    pub fn skip_frame<R: Read + Seek>(&self, reader: &mut BufReader<R>, what: &str) -> Result<u64, CodedError> {
+        let len = self.read_len(reader, what)?;
+        reader
+            .seek_relative(len as i64)
+            .map_err(|e| CodedError::io_read(format!("Error seeking to next chunk: {}", e)))?;
+        Ok((self.encode_len(len)?.len() + len) as u64)
+    }
+
+    /// 读取一个完整的分片帧，返回加密的分片数据；长度为 0 的结束帧返回空数据
+    pub fn read_frame<R: BufRead>(&self, reader: &mut R, what: &str, max_len: usize) -> Result<Vec<u8>, CodedError> {
+        let mut frame = Vec::new();
+        self.read_frame_into(reader, what, max_len, &mut frame)?;
+        Ok(frame)
+    }
+
+    /// 与 read_frame 相同，但读入调用方提供的缓冲区，复用缓冲区时不必为每个分片重新分配和清零
+    pub fn read_frame_into<R: BufRead>(&self, reader: &mut R, what: &str, max_len: usize, frame: &mut Vec<u8>) -> Result<(), CodedError> {
+        let len = self.read_len(reader, what)?;
+        if len > max_len {
+            return Err(CodedError::bad_format(format!("Invalid encrypted chunk size in {}: {} bytes", what, len)));
+        }
+        frame.resize(len, 0);
+        reader.read_exact(frame).map_err(|e| read_error(e, what))
+    }
+
+    /// 写入分片文件的结尾：长度为 0 的结束帧 + 整个文件的 MAC
+    pub fn write_footer<W: Write>(&self, writer: &mut W, file_mac: &[u8]) -> Result<(), CodedError> {
+        writer
+            .write_all(&self.encode_len(0)?)
+            .and_then(|_| writer.write_all(file_mac))
+            .map_err(|e| CodedError::io_write(format!("Failed to write file footer: {}", e)))
+    }
+
+    /// 读取最后一个分片之后的结尾，返回整个文件的 MAC；结尾缺失说明文件被截断
+    pub fn read_footer<R: BufRead>(&self, reader: &mut R) -> Result<Vec<u8>, CodedError> {
+        if self.read_len(reader, "end-of-file marker")? != 0 {
+            return Err(CodedError::bad_format("Unexpected chunk after the end of file"));
+        }
+        let mut file_mac = vec![0u8; CHUNK_MAC_LEN];
+        reader
+            .read_exact(&mut file_mac)
+            .map_err(|e| read_error(e, "end-of-file marker"))?;
+        Ok(file_mac)
+    }
+}
+
+/// 分片文件结尾的定长标记
+pub const FOOTER_MAGIC: &[u8] = b"ZIPPYEND";
+/// 结尾标记：结尾字段的起始位置(u64 LE) + `ZIPPYEND`
+const FOOTER_TRAILER_LEN: usize = 16;
+
+/// 结尾字段区长度上限，足够容纳 1MB 分片、32TB 文件的分片索引
+const MAX_FOOTER_FIELDS_LEN: u32 = 256 * 1024 * 1024;
+
+/// 结尾字段的 TLV 标签
+const FOOTER_TAG_CHUNK_INDEX: u8 = 1;
+const FOOTER_TAG_PLAINTEXT_SIZE: u8 = 2;
+const FOOTER_TAG_DIGEST: u8 = 3;
+const FOOTER_TAG_CHUNK_CHECKSUMS: u8 = 4;
+
+/// 分片文件的结尾字段，位于结束帧和文件 MAC 之后
+///
+/// 布局：字段区长度(u32 LE) + 字段区（TLV，与文件头相同）+ 字段区长度字段的位置(u64 LE) + `ZIPPYEND`。
+/// 字段区参与整个文件的 MAC 计算；文件末尾的定长标记使读取方不必扫描分片即可定位结尾字段。
+pub struct ChunkedFooter {
+    /// 每个分片帧在文件中的起始位置
+    pub chunk_offsets: Vec<u64>,
+    /// 实际加密的明文总大小
+    pub plaintext_size: Option<u64>,
+    /// 加密保存的明文摘要（默认 SHA-256，文件头可指定其它算法），明文摘要可用于确认文件内容，因此不以明文形式保存
+    pub encrypted_digest: Option<Vec<u8>>,
+    /// 每个分片明文的校验值（见 `ChunkChecksum`），与分片索引一一对应，早期文件没有
+    pub chunk_checksums: Vec<[u8; CHUNK_CHECKSUM_LEN]>,
+}
+
+impl ChunkedFooter {
+    /// 结尾字段区，同时作为文件 MAC 的输入
+    pub fn fields(&self) -> Vec<u8> {
+        let index: Vec<u8> = self.chunk_offsets.iter().flat_map(|offset| offset.to_le_bytes()).collect();
+        let mut fields = Vec::new();
+        push_field(&mut fields, FOOTER_TAG_CHUNK_INDEX, &index);
+        if let Some(size) = self.plaintext_size {
+            push_field(&mut fields, FOOTER_TAG_PLAINTEXT_SIZE, &size.to_le_bytes());
+        }
+        if let Some(digest) = &self.encrypted_digest {
+            push_field(&mut fields, FOOTER_TAG_DIGEST, digest);
+        }
+        if !self.chunk_checksums.is_empty() {
+            push_field(&mut fields, FOOTER_TAG_CHUNK_CHECKSUMS, &self.chunk_checksums.concat());
+        }
+        fields
+    }
+
+    /// 写入结尾字段，offset 为当前写入位置
+    pub fn write<W: Write>(writer: &mut W, fields: &[u8], offset: u64) -> Result<(), CodedError> {
+        let fields_len = u32::try_from(fields.len()).map_err(|_| CodedError::bad_header("File footer too large"))?;
+        writer
+            .write_all(&fields_len.to_le_bytes())
+            .and_then(|_| writer.write_all(fields))
+            .and_then(|_| writer.write_all(&offset.to_le_bytes()))
+            .and_then(|_| writer.write_all(FOOTER_MAGIC))
+            .map_err(|e| CodedError::io_write(format!("Failed to write file footer: {}", e)))
+    }
+
+    fn from_fields(fields: &[u8]) -> Result<Self, CodedError> {
+        let mut footer = ChunkedFooter {
+            chunk_offsets: Vec::new(),
+            plaintext_size: None,
+            encrypted_digest: None,
+            chunk_checksums: Vec::new(),
+        };
+        for (tag, value) in parse_fields(fields, "footer")? {
+            match tag {
+                FOOTER_TAG_CHUNK_INDEX => {
+                    if value.len() % 8 != 0 {
+                        return Err(CodedError::bad_header("Invalid chunk index in footer"));
+                    }
+                    footer.chunk_offsets = value
+                        .chunks_exact(8)
+                        .map(|entry| u64::from_le_bytes(entry.try_into().unwrap()))
+                        .collect();
+                }
+                FOOTER_TAG_PLAINTEXT_SIZE => footer.plaintext_size = Some(read_u64_field(value, "plaintext size")?),
+                FOOTER_TAG_DIGEST => footer.encrypted_digest = Some(value.to_vec()),
+                FOOTER_TAG_CHUNK_CHECKSUMS => {
+                    if value.len() % CHUNK_CHECKSUM_LEN != 0 {
+                        return Err(CodedError::bad_header("Invalid chunk checksums in footer"));
+                    }
+                    footer.chunk_checksums = value
+                        .chunks_exact(CHUNK_CHECKSUM_LEN)
+                        .map(|entry| entry.try_into().unwrap())
+                        .collect();
+                }
+                _ => {}
+            }
+        }
+        Ok(footer)
+    }
+
+    /// 使用密钥解密保存的明文 SHA-256
+    pub fn decrypt_digest(&self, key: &[u8]) -> Result<Option<Vec<u8>>, CodedError> {
+        match &self.encrypted_digest {
+            Some(encrypted) => open_field(key, FIELD_DIGEST, encrypted).map(Some),
+            None => Ok(None),
+        }
+    }
+
+    /// 核对解密得到的明文大小和 SHA-256 是否与结尾字段记录的一致
+    pub fn verify_plaintext(&self, key: &[u8], size: u64, digest: &[u8]) -> Result<(), CodedError> {
+        if let Some(expected) = self.plaintext_size {
+            if expected != size {
+                return Err(CodedError::bad_header(format!("Decrypted size {} does not match footer size {}", size, expected)));
+            }
+        }
+        if let Some(expected) = self.decrypt_digest(key)? {
+            if expected != digest {
+                return Err(CodedError::auth_failed("Plaintext digest mismatch - decrypted data is corrupted"));
+            }
+        }
+        Ok(())
+    }
+
+    /// 通过文件末尾的定长标记读取结尾字段，不校验文件 MAC
+    pub fn read_at_end<R: Read + Seek>(reader: &mut R) -> Result<Self, CodedError> {
+        seek_footer(reader)?;
+        Ok(ChunkedFooter::read(reader)?.0)
+    }
+
+    /// 顺序读取紧跟在文件 MAC 之后的结尾字段，返回结尾字段及用于校验 MAC 的字段区
+    pub fn read<R: Read>(reader: &mut R) -> Result<(Self, Vec<u8>), CodedError> {
+        let mut fields_len = [0u8; 4];
+        reader
+            .read_exact(&mut fields_len)
+            .map_err(|e| read_error(e, "file footer"))?;
+        let fields_len = u32::from_le_bytes(fields_len);
+        if fields_len > MAX_FOOTER_FIELDS_LEN {
+            return Err(CodedError::bad_header(format!("File footer too large: {} bytes", fields_len)));
+        }
+        let mut fields = vec![0u8; fields_len as usize];
+        reader.read_exact(&mut fields).map_err(|e| read_error(e, "file footer"))?;
+        let mut trailer = [0u8; FOOTER_TRAILER_LEN];
+        reader.read_exact(&mut trailer).map_err(|e| read_error(e, "file footer"))?;
+        if &trailer[8..] != FOOTER_MAGIC {
+            return Err(CodedError::bad_header("Invalid file footer"));
+        }
+        Ok((ChunkedFooter::from_fields(&fields)?, fields))
+    }
+}
+
+fn seek_error(err: std::io::Error) -> CodedError {
+    CodedError::io_read(format!("Error seeking in file: {}", err))
+}
+
+/// 根据文件末尾的定长标记跳转到结尾字段的开头，文件附加了纠错数据时结尾标记位于纠错数据之前
+fn seek_footer<R: Read + Seek>(reader: &mut R) -> Result<(), CodedError> {
+    let end = crate::parity::protected_len(reader).map_err(seek_error)?;
+    let trailer_offset = end
+        .checked_sub(FOOTER_TRAILER_LEN as u64)
+        .ok_or_else(|| CodedError::truncated(format!("{}: missing file footer", ERR_TRUNCATED)))?;
+    reader.seek(SeekFrom::Start(trailer_offset)).map_err(seek_error)?;
+    let mut trailer = [0u8; FOOTER_TRAILER_LEN];
+    reader.read_exact(&mut trailer).map_err(|e| read_error(e, "file footer"))?;
+    if &trailer[8..] != FOOTER_MAGIC {
+        return Err(CodedError::truncated(format!("{}: missing file footer", ERR_TRUNCATED)));
+    }
+    let footer_offset = u64::from_le_bytes(trailer[..8].try_into().unwrap());
+    reader.seek(SeekFrom::Start(footer_offset)).map_err(seek_error)?;
+    Ok(())
+}
+
+/// 读取结尾字段中按分片排列的定长数组（分片索引、分片校验值）的一项，只读取这一项；字段不存在时返回 None
+fn read_footer_entry<R: Read + Seek, const N: usize>(reader: &mut R, tag: u8, chunk_index: u64) -> Result<Option<[u8; N]>, CodedError> {
+    seek_footer(reader)?;
+    let mut fields_len = [0u8; 4];
+    reader.read_exact(&mut fields_len).map_err(|e| read_error(e, "file footer"))?;
+    let mut remaining = u32::from_le_bytes(fields_len) as u64;
+
+    // 逐个跳过字段，只读取目标字段中的目标项
+    while remaining >= 5 {
+        let mut field_header = [0u8; 5];
+        reader.read_exact(&mut field_header).map_err(|e| read_error(e, "file footer"))?;
+        let len = u32::from_le_bytes(field_header[1..].try_into().unwrap()) as u64;
+        if len > remaining - 5 {
+            return Err(CodedError::truncated("Truncated footer field"));
+        }
+        if field_header[0] == tag {
+            if (chunk_index + 1) * N as u64 > len {
+                return Err(CodedError::bad_format(format!("Chunk {} missing from chunk index", chunk_index)));
+            }
+            reader.seek(SeekFrom::Current((chunk_index * N as u64) as i64)).map_err(seek_error)?;
+            let mut entry = [0u8; N];
+            reader.read_exact(&mut entry).map_err(|e| read_error(e, "chunk index"))?;
+            return Ok(Some(entry));
+        }
+        reader.seek(SeekFrom::Current(len as i64)).map_err(seek_error)?;
+        remaining -= 5 + len;
+    }
+    Ok(None)
+}
+
+/// 通过文件末尾的分片索引定位分片帧的起始位置，只读取索引中的一项
+pub fn locate_chunk<R: Read + Seek>(reader: &mut R, chunk_index: u64) -> Result<u64, CodedError> {
+    read_footer_entry::<_, 8>(reader, FOOTER_TAG_CHUNK_INDEX, chunk_index)?
+        .map(u64::from_le_bytes)
+        .ok_or_else(|| CodedError::bad_header("File footer has no chunk index"))
+}
+
+/// 读取分片索引中记录的分片明文校验值，早期文件没有校验值时返回 None
+pub fn chunk_checksum<R: Read + Seek>(reader: &mut R, chunk_index: u64) -> Result<Option<[u8; CHUNK_CHECKSUM_LEN]>, CodedError> {
+    read_footer_entry(reader, FOOTER_TAG_CHUNK_CHECKSUMS, chunk_index)
+}
+
+/// 不需要密钥即可读取的文件信息
+pub struct FileInfo {
+    pub format_version: u32,
+    pub layout: Layout,
+    pub algorithm: Option<String>,
+    pub original_size: Option<u64>,
+    pub chunk_size: Option<u64>,
+    pub total_chunks: u64,
+    pub encrypted_size: u64,
+    pub kdf: Option<String>,
+    /// 文件头中是否保存了（加密的）原始文件名
+    pub has_filename: bool,
+    /// 解密后的原始文件名，需要密钥
+    pub filename: Option<String>,
+    /// 文件头中是否保存了（加密的）用户元数据
+    pub has_metadata: bool,
+    /// 解密后的用户元数据，需要密钥
+    pub metadata: Option<UserMetadata>,
+    /// 解密后的明文 MIME 类型，需要密钥
+    pub mime_type: Option<String>,
+    /// 创建时间（Unix 毫秒）
+    pub created_at: Option<u64>,
+    pub tool_version: Option<String>,
+    pub format_revision: Option<u32>,
+    /// 文件头是否带有认证标签
+    pub header_authenticated: bool,
+    /// 分片是否带有认证标签及整个文件的 MAC
+    pub chunks_authenticated: bool,
+    /// 文件末尾是否附加了纠错数据
+    pub has_parity: bool,
+    /// 明文摘要算法，文件中没有记录明文摘要时为 None
+    pub hash_algorithm: Option<HashAlgorithm>,
+    /// 加密前的压缩算法
+    pub compression: Option<Compression>,
+    /// 全零的分片是否以空分片帧保存
+    pub sparse: bool,
+    /// 是否可以追加分片
+    pub appendable: bool,
+}
+
+/// 读取以 `:` 结尾的 ASCII 字段
+fn read_field<R: BufRead>(reader: &mut R, name: &str) -> Result<String, CodedError> {
+    let mut field = Vec::new();
+    reader
+        .read_until(b':', &mut field)
+        .map_err(|e| CodedError::io_read(format!("Error reading {}: {}", name, e)))?;
+    if field.pop() != Some(b':') {
+        return Err(CodedError::truncated(format!("Unexpected end of file while reading {}", name)));
+    }
+    String::from_utf8(field).map_err(|_| CodedError::bad_header(format!("Invalid {} in header", name)))
+}
+
+/// 解析 v1 分片文件头，调用前读取位置必须位于文件开头
+pub fn read_chunked_header_v1<R: BufRead>(reader: &mut R) -> Result<ChunkedHeaderV1, CodedError> {
+    let mut magic = [0u8; 7];
+    reader
+        .read_exact(&mut magic)
+        .map_err(|e| CodedError::io_read(format!("Error reading header: {}", e)))?;
+    if magic != CHUNKED_V1_MAGIC {
+        return Err(CodedError::bad_header("Invalid file format - not a chunked file"));
+    }
+
+    let original_size_str = read_field(reader, "file size")?;
+    let chunk_size_str = read_field(reader, "chunk size")?;
+
+    let original_size: u64 = original_size_str
+        .parse()
+        .map_err(|_| CodedError::bad_header("Invalid file size in header"))?;
+    let chunk_size: usize = chunk_size_str
+        .parse()
+        .map_err(|_| CodedError::bad_header("Invalid chunk size in header"))?;
+
+    let header_len = (CHUNKED_V1_MAGIC.len() + original_size_str.len() + chunk_size_str.len() + 2) as u64;
+
+    Ok(ChunkedHeaderV1 {
+        original_size,
+        chunk_size,
+        header_len,
+    })
+}
+
+/// 读取分片文件头，自动识别 v1 和 v2 格式，调用前读取位置必须位于文件开头
+pub fn read_chunked_header<R: BufRead>(reader: &mut R) -> Result<ChunkedHeader, CodedError> {
+    let is_container = reader
+        .fill_buf()
+        .map_err(|e| CodedError::io_read(format!("Error reading header: {}", e)))?
+        .starts_with(CONTAINER_MAGIC);
+
+    if is_container {
+        let (container, header_len) = ContainerHeader::read(reader)?;
+        if container.layout != Layout::Chunked {
+            return Err(CodedError::bad_header("Invalid file format - not a chunked file"));
+        }
+        return Ok(ChunkedHeader {
+            original_size: container.original_size,
+            chunk_size: container.chunk_size as usize,
+            header_len,
+            container: Some(container),
+        });
+    }
+
+    let header = read_chunked_header_v1(reader)?;
+    Ok(ChunkedHeader {
+        original_size: header.original_size,
+        chunk_size: header.chunk_size,
+        header_len: header.header_len,
+        container: None,
+    })
+}
+
+/// 读取加密文件的头部信息；提供密钥时会解密文件头中加密保存的字段
+pub fn inspect(path: impl Into<Target>, key: Option<&[u8]>) -> Result<FileInfo, CodedError> {
+    let file = fd::open(path).map_err(|e| CodedError::io_open(format!("Failed to open input file: {}", e)))?;
+    let encrypted_size = file
+        .metadata()
+        .map_err(|e| CodedError::io_read(format!("Failed to get file metadata: {}", e)))?
+        .len();
+
+    let mut reader = BufReader::new(file);
+    let head = reader
+        .fill_buf()
+        .map_err(|e| CodedError::io_read(format!("Error reading header: {}", e)))?;
+    let is_container = head.starts_with(CONTAINER_MAGIC);
+    let starts_with_magic = head.starts_with(CHUNKED_V1_MAGIC);
+
+    if is_container {
+        let (header, _) = ContainerHeader::read(&mut reader)?;
+        let (filename, metadata, mime_type) = match key {
+            Some(key) => {
+                header.verify(key)?;
+                (header.decrypt_filename(key)?, header.decrypt_metadata(key)?, header.decrypt_mime_type(key)?)
+            }
+            None => (None, None, None),
+        };
+        let (chunk_size, total_chunks) = match header.layout {
+            Layout::Chunked => (Some(header.chunk_size), header.original_size.div_ceil(header.chunk_size)),
+            Layout::Monolithic => (None, 1),
+        };
+        // 分片文件的明文摘要在结尾字段中，结尾字段缺失或损坏时视为没有摘要
+        let has_hash = match header.layout {
+            Layout::Chunked => {
+                header.has_footer
+                    && ChunkedFooter::read_at_end(&mut reader).is_ok_and(|footer| footer.encrypted_digest.is_some())
+            }
+            Layout::Monolithic => header.encrypted_plaintext_hash.is_some(),
+        };
+        let hash_algorithm = has_hash.then(|| header.hash_algorithm.unwrap_or(HashAlgorithm::Sha256));
+        return Ok(FileInfo {
+            format_version: CONTAINER_VERSION as u32,
+            layout: header.layout,
+            algorithm: Some(header.algorithm.as_str().to_string()),
+            original_size: Some(header.original_size),
+            chunk_size,
+            total_chunks,
+            encrypted_size,
+            kdf: None,
+            has_filename: header.encrypted_filename.is_some(),
+            filename,
+            has_metadata: header.encrypted_metadata.is_some(),
+            metadata,
+            mime_type,
+            created_at: header.created_at,
+            tool_version: header.tool_version,
+            format_revision: header.format_revision,
+            header_authenticated: header.header_mac.is_some(),
+            chunks_authenticated: header.authenticated_chunks,
+            has_parity: header.has_parity,
+            hash_algorithm,
+            compression: header.compression,
+            sparse: header.sparse,
+            appendable: header.appendable,
+        });
+    }
+
+    if starts_with_magic {
+        let header = read_chunked_header_v1(&mut reader)?;
+        return Ok(FileInfo {
+            format_version: 1,
+            layout: Layout::Chunked,
+            algorithm: None,
+            original_size: Some(header.original_size),
+            chunk_size: Some(header.chunk_size as u64),
+            total_chunks: header.total_chunks(),
+            encrypted_size,
+            kdf: None,
+            has_filename: false,
+            filename: None,
+            has_metadata: false,
+            metadata: None,
+            mime_type: None,
+            created_at: None,
+            tool_version: None,
+            format_revision: None,
+            header_authenticated: false,
+            chunks_authenticated: false,
+            has_parity: false,
+            hash_algorithm: None,
+            compression: None,
+            sparse: false,
+            appendable: false,
+        });
+    }
+
+    // 整体加密的文件只有 IV/nonce + 密文，没有可读取的文件头
+    Ok(FileInfo {
+        format_version: 1,
+        layout: Layout::Monolithic,
+        algorithm: None,
+        original_size: None,
+        chunk_size: None,
+        total_chunks: 1,
+        encrypted_size,
+        kdf: None,
+        has_filename: false,
+        filename: None,
+        has_metadata: false,
+        metadata: None,
+        mime_type: None,
+        created_at: None,
+        tool_version: None,
+        format_revision: None,
+        header_authenticated: false,
+        chunks_authenticated: false,
+        has_parity: false,
+        hash_algorithm: None,
+        compression: None,
+        sparse: false,
+        appendable: false,
+    })
+}
+
+/// 文件格式嗅探结果
+#[derive(Clone, Copy, PartialEq)]
+pub enum DetectedFormat {
+    Plain,
+    ZippyMonolithic,
+    ZippyChunkedV1,
+    ZippyMonolithicV2,
+    ZippyChunkedV2,
+    ZippyVolume,
+    /// age v1 文件（age-encryption.org），二进制或 ASCII 封装
+    Age,
+    /// `openssl enc` 生成的加盐文件（`Salted__` 文件头）
+    OpensslSalted,
+    /// OpenPGP 加密消息（如 `gpg -c` 的输出），二进制或 ASCII 封装
+    OpenPgp,
+}
+
+impl DetectedFormat {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            DetectedFormat::Plain => "plain",
+            DetectedFormat::ZippyMonolithic => "zippy-monolithic",
+            DetectedFormat::ZippyChunkedV1 => "zippy-chunked-v1",
+            DetectedFormat::ZippyMonolithicV2 => "zippy-monolithic-v2",
+            DetectedFormat::ZippyChunkedV2 => "zippy-chunked-v2",
+            DetectedFormat::ZippyVolume => "zippy-volume",
+            DetectedFormat::Age => "age",
+            DetectedFormat::OpensslSalted => "openssl-salted",
+            DetectedFormat::OpenPgp => "openpgp",
+        }
+    }
+
+    pub fn is_encrypted(&self) -> bool {
+        *self != DetectedFormat::Plain
+    }
+}
+
+/// 嗅探时读取的字节数
+const SNIFF_LEN: usize = 4096;
+
+/// 常见的高熵明文格式（压缩包、图片、视频等），避免被误判为密文
+const KNOWN_SIGNATURES: &[(usize, &[u8])] = &[
+    (0, b"PK\x03\x04"),
+    (0, b"\x1f\x8b"),
+    (0, b"\x89PNG"),
+    (0, b"\xff\xd8\xff"),
+    (0, b"GIF8"),
+    (0, b"%PDF"),
+    (0, b"7z\xbc\xaf\x27\x1c"),
+    (0, b"Rar!"),
+    (0, b"\x28\xb5\x2f\xfd"),
+    (0, b"BZh"),
+    (0, b"\xfd7zXZ"),
+    (0, b"RIFF"),
+    (0, b"OggS"),
+    (0, b"fLaC"),
+    (0, b"ID3"),
+    (0, b"\x1a\x45\xdf\xa3"),
+    (4, b"ftyp"),
+];
+
+/// 计算样本的香农熵（bit/字节）
+fn shannon_entropy(sample: &[u8]) -> f64 {
+    let mut counts = [0usize; 256];
+    for &byte in sample {
+        counts[byte as usize] += 1;
+    }
+    let len = sample.len() as f64;
+    counts
+        .iter()
+        .filter(|&&count| count > 0)
+        .map(|&count| {
+            let p = count as f64 / len;
+            -p * p.log2()
+        })
+        .sum()
+}
+
+/// 根据文件开头的字节判断文件格式
+///
+/// 整体加密的文件没有文件头，只能根据长度约束和字节熵推断，结果是启发式的。
+pub fn sniff(sample: &[u8], file_size: u64) -> DetectedFormat {
+    if sample.len() >= CONTAINER_FIXED_LEN
+        && sample.starts_with(CONTAINER_MAGIC)
+        && sample[8] == CONTAINER_VERSION
+    {
+        match Layout::from_id(sample[9]) {
+            Some(Layout::Monolithic) => return DetectedFormat::ZippyMonolithicV2,
+            Some(Layout::Chunked) => return DetectedFormat::ZippyChunkedV2,
+            None => {}
+        }
+    }
+
+    if sample.starts_with(VOLUME_MAGIC) {
+        return DetectedFormat::ZippyVolume;
+    }
+
+    if sample.starts_with(AGE_MAGIC) || sample.starts_with(AGE_ARMOR_MAGIC) {
+        return DetectedFormat::Age;
+    }
+
+    if sample.starts_with(OPENSSL_MAGIC) {
+        return DetectedFormat::OpensslSalted;
+    }
+
+    if sample.starts_with(PGP_ARMOR_MAGIC) || looks_like_openpgp(sample) {
+        return DetectedFormat::OpenPgp;
+    }
+
+    if sample.starts_with(CHUNKED_V1_MAGIC) {
+        let mut reader = sample;
+        if read_chunked_header_v1(&mut reader).is_ok() {
+            return DetectedFormat::ZippyChunkedV1;
+        }
+    }
+
+    // 最短的密文：ChaCha20Poly1305 为 12 字节 nonce + 16 字节认证标签
+    if file_size < 28 || sample.is_empty() {
+        return DetectedFormat::Plain;
+    }
+
+    for (offset, signature) in KNOWN_SIGNATURES {
+        if sample.len() >= offset + signature.len() && &sample[*offset..offset + signature.len()] == *signature {
+            return DetectedFormat::Plain;
+        }
+    }
+
+    // 随机数据的熵接近样本长度所能达到的上限
+    let max_entropy = (sample.len() as f64).log2().min(8.0);
+    if shannon_entropy(sample) >= max_entropy * 0.9 {
+        DetectedFormat::ZippyMonolithic
+    } else {
+        DetectedFormat::Plain
+    }
+}
+
+/// 读取文件开头并判断格式
+pub fn detect(path: impl Into<Target>) -> Result<DetectedFormat, CodedError> {
+    let file = fd::open(path).map_err(|e| CodedError::io_open(format!("Failed to open input file: {}", e)))?;
+    let file_size = file
+        .metadata()
+        .map_err(|e| CodedError::io_read(format!("Failed to get file metadata: {}", e)))?
+        .len();
+
+    let mut sample = Vec::with_capacity(SNIFF_LEN);
+    file.take(SNIFF_LEN as u64)
+        .read_to_end(&mut sample)
+        .map_err(|e| CodedError::io_read(format!("Error reading input file: {}", e)))?;
+
+    Ok(sniff(&sample, file_size))
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::Cursor;
+
+    use super::*;
+
+    const KEY: [u8; 32] = [9; 32];
+
+    fn header_bytes(layout: Layout) -> Vec<u8> {
+        ContainerHeader::for_new_file(layout, CryptoAlgorithm::Aes, 3500, 1000).to_bytes(&KEY).unwrap()
+    }
+
+    #[test]
+    fn reads_and_verifies_header() {
+        let bytes = header_bytes(Layout::Chunked);
+        let (header, len) = ContainerHeader::read(&mut Cursor::new(&bytes)).unwrap();
+        assert_eq!(len, bytes.len() as u64);
+        assert_eq!((header.original_size, header.chunk_size), (3500, 1000));
+        header.verify(&KEY).unwrap();
+        assert_eq!(header.verify(&[8; 32]).unwrap_err().code, ErrorCode::AuthFailed);
+    }
+
+    /// 去掉认证标签并清除标志位的 v2 文件头不能被当作早期的未认证文件接受
+    #[test]
+    fn rejects_header_without_mac() {
+        for layout in [Layout::Chunked, Layout::Monolithic] {
+            let mut bytes = header_bytes(layout);
+            bytes[11] &= !FLAG_HEADER_MAC;
+            bytes.truncate(bytes.len() - HEADER_MAC_LEN);
+            assert_eq!(ContainerHeader::read(&mut Cursor::new(&bytes)).err().unwrap().code, ErrorCode::AuthFailed);
+        }
+        let unwritten = ContainerHeader::for_new_file(Layout::Monolithic, CryptoAlgorithm::Aes, 3500, 0);
+        assert_eq!(unwritten.verify(&KEY).unwrap_err().code, ErrorCode::AuthFailed);
+    }
+}
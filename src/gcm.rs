@@ -0,0 +1,290 @@
+use aes::cipher::{BlockEncrypt, InnerIvInit, KeyInit, StreamCipher};
+use aes::{Aes128, Aes192, Aes256};
+use aes_gcm::aead::consts::{U12, U16};
+use aes_gcm::aead::AeadInPlace;
+use aes_gcm::AesGcm as Gcm;
+use ctr::{Ctr32BE, CtrCore};
+use ghash::universal_hash::UniversalHash;
+use ghash::GHash;
+
+use crate::errors::CodedError;
+
+/// GCM 认证标签的长度
+pub const TAG_LEN: usize = 16;
+/// 默认的 nonce 长度（96 位）
+pub const NONCE_LEN: usize = 12;
+/// gocryptfs 文件内容使用的 nonce 长度（128 位）
+pub const LONG_NONCE_LEN: usize = 16;
+
+enum GcmCipher {
+    Aes128(Box<Gcm<Aes128, U12>>),
+    Aes192(Box<Gcm<Aes192, U12>>),
+    Aes256(Box<Gcm<Aes256, U12>>),
+    Aes256LongNonce(Box<Gcm<Aes256, U16>>),
+}
+
+/// 以与数据无关的时间比较认证标签，长度不是秘密，不同时直接返回
+fn tags_match(expected: &[u8; TAG_LEN], tag: &[u8]) -> bool {
+    if tag.len() != expected.len() {
+        return false;
+    }
+    let difference = expected
+        .iter()
+        .zip(tag)
+        .fold(0u8, |acc, (a, b)| acc | (a ^ b));
+    difference == 0
+}
+
+fn authentication_failed<E>(_: E) -> CodedError {
+    CodedError::auth_failed("AES-GCM authentication failed")
+}
+
+/// AES-GCM（NIST SP 800-38D，由 aes-gcm crate 实现），支持 128/192/256 位密钥和 128 位标签
+///
+/// 由 new 创建时 nonce 为 96 位，由 with_long_nonce 创建时为 128 位；传入的 nonce 长度与之不符时 panic。
+pub struct AesGcm {
+    cipher: GcmCipher,
+}
+
+impl AesGcm {
+    pub fn new(key: &[u8]) -> Result<Self, CodedError> {
+        let cipher = match key.len() {
+            16 => GcmCipher::Aes128(Box::new(Gcm::new_from_slice(key).unwrap())),
+            24 => GcmCipher::Aes192(Box::new(Gcm::new_from_slice(key).unwrap())),
+            32 => GcmCipher::Aes256(Box::new(Gcm::new_from_slice(key).unwrap())),
+            len => return Err(CodedError::invalid_key_length(format!("AES-GCM key must be 16, 24 or 32 bytes, got {}", len))),
+        };
+        Ok(AesGcm { cipher })
+    }
+
+    /// 使用 128 位 nonce 的 AES-256-GCM（gocryptfs 的文件内容）
+    pub fn with_long_nonce(key: &[u8]) -> Result<Self, CodedError> {
+        let cipher = Gcm::new_from_slice(key).map_err(|_| CodedError::invalid_key_length(format!("AES-GCM key must be 32 bytes, got {}", key.len())))?;
+        Ok(AesGcm { cipher: GcmCipher::Aes256LongNonce(Box::new(cipher)) })
+    }
+
+    /// 原地加密 buffer，返回认证标签
+    pub fn encrypt_in_place(&self, nonce: &[u8], aad: &[u8], buffer: &mut [u8]) -> Result<[u8; TAG_LEN], CodedError> {
+        let tag = match &self.cipher {
+            GcmCipher::Aes128(cipher) => cipher.encrypt_in_place_detached(&short_nonce(nonce).into(), aad, buffer),
+            GcmCipher::Aes192(cipher) => cipher.encrypt_in_place_detached(&short_nonce(nonce).into(), aad, buffer),
+            GcmCipher::Aes256(cipher) => cipher.encrypt_in_place_detached(&short_nonce(nonce).into(), aad, buffer),
+            GcmCipher::Aes256LongNonce(cipher) => cipher.encrypt_in_place_detached(&long_nonce(nonce).into(), aad, buffer),
+        };
+        tag.map(Into::into).map_err(|_| CodedError::invalid_argument("AES-GCM plaintext is too long"))
+    }
+
+    /// 验证认证标签后原地解密 buffer；验证失败时 buffer 保持不变
+    pub fn decrypt_in_place(&self, nonce: &[u8], aad: &[u8], buffer: &mut [u8], tag: &[u8]) -> Result<(), CodedError> {
+        let tag: [u8; TAG_LEN] = tag.try_into().map_err(authentication_failed)?;
+        let tag = tag.into();
+        match &self.cipher {
+            GcmCipher::Aes128(cipher) => cipher.decrypt_in_place_detached(&short_nonce(nonce).into(), aad, buffer, &tag),
+            GcmCipher::Aes192(cipher) => cipher.decrypt_in_place_detached(&short_nonce(nonce).into(), aad, buffer, &tag),
+            GcmCipher::Aes256(cipher) => cipher.decrypt_in_place_detached(&short_nonce(nonce).into(), aad, buffer, &tag),
+            GcmCipher::Aes256LongNonce(cipher) => cipher.decrypt_in_place_detached(&long_nonce(nonce).into(), aad, buffer, &tag),
+        }
+        .map_err(authentication_failed)
+    }
+
+    /// 加密并在密文后附加认证标签
+    pub fn seal(&self, nonce: &[u8], aad: &[u8], plaintext: &[u8]) -> Result<Vec<u8>, CodedError> {
+        let mut output = Vec::with_capacity(plaintext.len() + TAG_LEN);
+        output.extend_from_slice(plaintext);
+        let tag = self.encrypt_in_place(nonce, aad, &mut output)?;
+        output.extend_from_slice(&tag);
+        Ok(output)
+    }
+
+    /// 解密 `密文 || 认证标签`
+    pub fn open(&self, nonce: &[u8], aad: &[u8], sealed: &[u8]) -> Result<Vec<u8>, CodedError> {
+        if sealed.len() < TAG_LEN {
+            return Err(CodedError::bad_format("AES-GCM ciphertext is shorter than the authentication tag"));
+        }
+        let (ciphertext, tag) = sealed.split_at(sealed.len() - TAG_LEN);
+        let mut output = ciphertext.to_vec();
+        self.decrypt_in_place(nonce, aad, &mut output, tag)?;
+        Ok(output)
+    }
+}
+
+fn short_nonce(nonce: &[u8]) -> [u8; NONCE_LEN] {
+    nonce.try_into().expect("AES-GCM nonce must be 96 bits")
+}
+
+fn long_nonce(nonce: &[u8]) -> [u8; LONG_NONCE_LEN] {
+    nonce.try_into().expect("AES-GCM nonce must be 128 bits")
+}
+
+/// 流式 AES-256-GCM（96 位 nonce、无附加认证数据）：按顺序分多次加密或解密连续的数据，除最后一次外每次的长度必须是 16 的倍数，
+/// 最后计算或校验认证标签。aes-gcm crate 只能一次处理整条消息，这里用它所基于的 ctr 和 ghash crate 组合出相同的构造。
+///
+/// 解密时明文在校验标签之前就已输出，调用方必须在校验失败时丢弃已写出的数据。
+pub struct GcmStream {
+    ctr: Ctr32BE<Aes256>,
+    ghash: GHash,
+    /// E(K, J0)，与 GHASH 的结果异或得到认证标签
+    mask: [u8; TAG_LEN],
+    len: u64,
+}
+
+impl GcmStream {
+    pub fn new(key: &[u8], nonce: &[u8; NONCE_LEN]) -> Result<Self, CodedError> {
+        let cipher = Aes256::new_from_slice(key).map_err(|_| CodedError::invalid_key_length(format!("AES-GCM stream key must be 32 bytes, got {}", key.len())))?;
+        let mut ghash_key = [0u8; 16];
+        cipher.encrypt_block((&mut ghash_key).into());
+        let ghash = GHash::new(&ghash_key.into());
+
+        // J0 = nonce || 0x00000001，J0 加密后作为标签的掩码，数据从 J0 + 1 开始加密
+        let mut j0 = [0u8; 16];
+        j0[..NONCE_LEN].copy_from_slice(nonce);
+        j0[15] = 1;
+        let mut ctr = Ctr32BE::from_core(CtrCore::inner_iv_init(cipher, &j0.into()));
+        let mut mask = [0u8; TAG_LEN];
+        ctr.apply_keystream(&mut mask);
+        Ok(GcmStream { ctr, ghash, mask, len: 0 })
+    }
+
+    /// 原地加密下一段数据
+    pub fn encrypt(&mut self, data: &mut [u8]) {
+        debug_assert!(self.len.is_multiple_of(16), "only the last GCM stream update may be partial");
+        self.ctr.apply_keystream(data);
+        self.ghash.update_padded(data);
+        self.len += data.len() as u64;
+    }
+
+    /// 原地解密下一段数据
+    pub fn decrypt(&mut self, data: &mut [u8]) {
+        debug_assert!(self.len.is_multiple_of(16), "only the last GCM stream update may be partial");
+        self.ghash.update_padded(data);
+        self.ctr.apply_keystream(data);
+        self.len += data.len() as u64;
+    }
+
+    /// 计算已处理数据的认证标签
+    pub fn tag(&self) -> [u8; TAG_LEN] {
+        let mut ghash = self.ghash.clone();
+        let mut lengths = [0u8; 16];
+        lengths[8..].copy_from_slice(&(self.len * 8).to_be_bytes());
+        ghash.update_padded(&lengths);
+        let mut tag: [u8; TAG_LEN] = ghash.finalize().into();
+        for (byte, mask) in tag.iter_mut().zip(self.mask) {
+            *byte ^= mask;
+        }
+        tag
+    }
+
+    /// 校验认证标签
+    pub fn verify(&self, tag: &[u8]) -> Result<(), CodedError> {
+        if !tags_match(&self.tag(), tag) {
+            return Err(CodedError::auth_failed("AES-GCM authentication failed"));
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn unhex(hex: &str) -> Vec<u8> {
+        hex::decode(hex).unwrap()
+    }
+
+    const K3: &str = "feffe9928665731c6d6a8f9467308308";
+    const IV3: &str = "cafebabefacedbaddecaf888";
+    const P3: &str = "d9313225f88406e5a55909c5aff5269a86a7a9531534f7da2e4c303d8a318a721c3c0c95956809532fcf0e2449a6b525b16aedf5aa0de657ba637b391aafd255";
+    const A4: &str = "feedfacedeadbeeffeedfacedeadbeefabaddad2";
+
+    /// GCM 规范（McGrew & Viega，NIST SP 800-38D 引用）的测试用例：(密钥, nonce, 明文, 附加数据, 密文 || 标签)
+    fn nist_vectors() -> Vec<(String, &'static str, String, &'static str, String)> {
+        let c3 = "42831ec2217774244b7221b784d0d49ce3aa212f2c02a4e035c17e2329aca12e21d514b25466931c7d8f6a5aac84aa051ba30b396a0aac973d58e091473f5985";
+        let c15 = "522dc1f099567d07f47f37a32a84427d643a8cdcbfe5c0c97598a2bd2555d1aa8cb08e48590dbb3da7b08b1056828838c5f61e6393ba7a0abcc9f662898015ad";
+        let zero_iv = "000000000000000000000000";
+        vec![
+            // Test Case 1、2：AES-128，全零密钥
+            ("00".repeat(16), zero_iv, String::new(), "", "58e2fccefa7e3061367f1d57a4e7455a".to_string()),
+            ("00".repeat(16), zero_iv, "00".repeat(16), "", "0388dace60b6a392f328c2b971b2fe78ab6e47d42cec13bdf53a67b21257bddf".to_string()),
+            // Test Case 3、4：AES-128，不带和带附加数据
+            (K3.to_string(), IV3, P3.to_string(), "", format!("{}4d5c2af327cd64a62cf35abd2ba6fab4", c3)),
+            (K3.to_string(), IV3, P3[..120].to_string(), A4, format!("{}5bc94fbc3221a5db94fae95ae7121a47", &c3[..120])),
+            // Test Case 8：AES-192
+            ("00".repeat(24), zero_iv, "00".repeat(16), "", "98e7247c07f0fe411c267e4384b0f6002ff58d80033927ab8ef4d4587514f0fb".to_string()),
+            // Test Case 13 到 16：AES-256
+            ("00".repeat(32), zero_iv, String::new(), "", "530f8afbc74536b9a963b4f1c4cb738b".to_string()),
+            ("00".repeat(32), zero_iv, "00".repeat(16), "", "cea7403d4d606b6e074ec5d3baf39d18d0d1c8a799996bf0265b98b5d48ab919".to_string()),
+            (K3.repeat(2), IV3, P3.to_string(), "", format!("{}b094dac5d93471bdec1a502270e3cc6c", c15)),
+            (K3.repeat(2), IV3, P3[..120].to_string(), A4, format!("{}76fc6ece0f4e1768cddf8853bb2d551b", &c15[..120])),
+        ]
+    }
+
+    #[test]
+    fn seal_and_open_match_nist_vectors() {
+        for (key, nonce, plaintext, aad, sealed) in nist_vectors() {
+            let gcm = AesGcm::new(&unhex(&key)).unwrap();
+            let (nonce, plaintext, aad, sealed) = (unhex(nonce), unhex(&plaintext), unhex(aad), unhex(&sealed));
+            assert_eq!(gcm.seal(&nonce, &aad, &plaintext).unwrap(), sealed);
+            assert_eq!(gcm.open(&nonce, &aad, &sealed).unwrap(), plaintext);
+        }
+    }
+
+    #[test]
+    fn open_rejects_modified_ciphertext_tag_and_aad() {
+        let gcm = AesGcm::new(&unhex(K3)).unwrap();
+        let (nonce, aad) = (unhex(IV3), unhex(A4));
+        let sealed = gcm.seal(&nonce, &aad, &unhex(P3)).unwrap();
+        for position in [0, sealed.len() - 1] {
+            let mut modified = sealed.clone();
+            modified[position] ^= 1;
+            assert!(gcm.open(&nonce, &aad, &modified).is_err());
+        }
+        assert!(gcm.open(&nonce, b"other", &sealed).is_err());
+        assert!(gcm.open(&nonce, &aad, &sealed[..TAG_LEN - 1]).is_err());
+    }
+
+    #[test]
+    fn long_nonce_matches_openssl() {
+        // 由 OpenSSL（Python cryptography 的 AESGCM）以 128 位 nonce 生成
+        let gcm = AesGcm::with_long_nonce(&unhex(&K3.repeat(2))).unwrap();
+        let nonce = unhex("cafebabefacedbaddecaf888feedface");
+        let expected = unhex("3dabe8c2c442b078986cd191bd84ec31ee311189a8ad97ab7964cb95f3b90dc13164021ff694951f66f88dbfc72d41e3469c881a08e02d29b37cfeb37f69084b3da90040aba0ba1012f8efaf");
+        assert_eq!(gcm.seal(&nonce, &unhex(A4), &unhex(&P3[..120])).unwrap(), expected);
+        assert!(AesGcm::with_long_nonce(&unhex(K3)).is_err());
+    }
+
+    #[test]
+    fn stream_matches_nist_vector_in_pieces() {
+        // Test Case 15：分成 32 字节和 32 字节两段处理
+        let key = unhex(&K3.repeat(2));
+        let nonce: [u8; NONCE_LEN] = unhex(IV3).try_into().unwrap();
+        let (_, _, plaintext, _, sealed) = nist_vectors().swap_remove(7);
+        let (plaintext, sealed) = (unhex(&plaintext), unhex(&sealed));
+
+        let mut data = plaintext.clone();
+        let mut stream = GcmStream::new(&key, &nonce).unwrap();
+        let (first, second) = data.split_at_mut(32);
+        stream.encrypt(first);
+        stream.encrypt(second);
+        assert_eq!(data, sealed[..plaintext.len()]);
+        assert_eq!(stream.tag(), sealed[plaintext.len()..]);
+
+        let mut stream = GcmStream::new(&key, &nonce).unwrap();
+        let (first, second) = data.split_at_mut(48);
+        stream.decrypt(first);
+        stream.decrypt(second);
+        assert_eq!(data, plaintext);
+        assert!(stream.verify(&sealed[plaintext.len()..]).is_ok());
+        let mut tag = sealed[plaintext.len()..].to_vec();
+        tag[0] ^= 1;
+        assert!(stream.verify(&tag).is_err());
+    }
+    #[test]
+    fn tags_of_other_lengths_do_not_match() {
+        let expected = [7u8; TAG_LEN];
+        assert!(tags_match(&expected, &expected));
+        assert!(!tags_match(&expected, &expected[..TAG_LEN - 1]));
+        assert!(!tags_match(&expected, &[]));
+        // 长度相差 256 时按字节折叠长度差会得到 0
+        assert!(!tags_match(&expected, &[7u8; TAG_LEN + 256]));
+    }
+}
@@ -0,0 +1,325 @@
+use std::collections::HashSet;
+use std::fs;
+use std::path::Path;
+
+use crate::errors::CodedError;
+
+/// 目录根部自动读取的忽略文件名
+pub const IGNORE_FILE_NAME: &str = ".zippyignore";
+
+/// 路径模式，语法与 `.gitignore` 相同：`*` 和 `?` 不跨越 `/`，`**` 匹配任意层目录，`[a-z]` 匹配字符集合，
+/// 以 `/` 结尾的模式只匹配目录
+pub struct Pattern {
+    /// 原始模式，用于错误信息
+    text: String,
+    negated: bool,
+    dir_only: bool,
+    segments: Vec<String>,
+}
+
+impl Pattern {
+    /// 解析一个模式；空行和 `#` 开头的注释返回 None
+    ///
+    /// anchored 为 false 时按 `.gitignore` 的规则处理：不含 `/`（末尾的除外）的模式匹配任意层级中的名称；
+    /// 为 true 时模式总是相对于根目录。
+    pub fn parse(text: &str, anchored: bool) -> Result<Option<Self>, CodedError> {
+        let trimmed = text.trim_end_matches(['\r', '\n']);
+        if trimmed.trim().is_empty() || trimmed.starts_with('#') {
+            return Ok(None);
+        }
+        let (negated, mut pattern) = match trimmed.strip_prefix('!') {
+            Some(rest) => (true, rest),
+            None => (false, trimmed.strip_prefix('\\').filter(|rest| rest.starts_with(['#', '!'])).unwrap_or(trimmed)),
+        };
+        pattern = pattern.trim_start_matches("./");
+        let dir_only = pattern.ends_with('/');
+        pattern = pattern.trim_end_matches('/');
+        let anchored = anchored || pattern.contains('/');
+        pattern = pattern.trim_start_matches('/');
+        if pattern.is_empty() {
+            return Err(CodedError::invalid_argument(format!("Invalid path pattern: {:?}", text)));
+        }
+        let mut segments: Vec<String> = pattern.split('/').filter(|segment| !segment.is_empty()).map(str::to_string).collect();
+        for segment in &segments {
+            if segment.contains("**") && segment != "**" {
+                return Err(CodedError::invalid_argument(format!("Invalid path pattern {:?}: ** must be a whole path segment", text)));
+            }
+            if segment.contains('[') && class_end(segment.as_bytes(), segment.find('[').unwrap()).is_none() {
+                return Err(CodedError::invalid_argument(format!("Invalid path pattern {:?}: unterminated character class", text)));
+            }
+        }
+        if !anchored {
+            segments.insert(0, "**".to_string());
+        }
+        Ok(Some(Pattern { text: text.to_string(), negated, dir_only, segments }))
+    }
+
+    /// 相对路径（`/` 分隔）本身是否匹配
+    pub fn matches(&self, path: &str, is_dir: bool) -> bool {
+        if self.dir_only && !is_dir {
+            return false;
+        }
+        let parts: Vec<&str> = path.split('/').filter(|part| !part.is_empty()).collect();
+        match_segments(&self.segments, &parts)
+    }
+
+    /// 路径本身或它所在的某一层目录是否匹配
+    pub fn matches_within(&self, path: &str, is_dir: bool) -> bool {
+        ancestors(path).any(|ancestor| self.matches(ancestor, true)) || self.matches(path, is_dir)
+    }
+}
+
+/// 路径的各层上级目录，由浅到深，不含路径本身
+fn ancestors(path: &str) -> impl Iterator<Item = &str> {
+    path.match_indices('/').map(move |(index, _)| &path[..index]).filter(|ancestor| !ancestor.is_empty())
+}
+
+/// 逐段匹配路径；`**` 可以匹配任意层目录，用按位置记录结果的动态规划代替回溯，耗时为模式段数 × 路径段数
+fn match_segments(pattern: &[String], parts: &[&str]) -> bool {
+    // next[j]：pattern[i + 1..] 是否匹配 parts[j..]，从最后一个模式段开始向前计算
+    let mut next: Vec<bool> = (0..=parts.len()).map(|j| j == parts.len()).collect();
+    for segment in pattern.iter().rev() {
+        let mut current = vec![false; parts.len() + 1];
+        for j in (0..=parts.len()).rev() {
+            current[j] = if segment == "**" {
+                next[j] || (j < parts.len() && current[j + 1])
+            } else {
+                j < parts.len() && next[j + 1] && match_name(segment.as_bytes(), parts[j].as_bytes())
+            };
+        }
+        next = current;
+    }
+    next[0]
+}
+
+/// 单个路径段模式中的一个元素
+enum Token<'a> {
+    Star,
+    Any,
+    Class(&'a [u8]),
+    Byte(u8),
+}
+
+impl Token<'_> {
+    fn matches(&self, c: u8) -> bool {
+        match self {
+            Token::Star | Token::Any => true,
+            Token::Class(class) => match_class(class, c),
+            Token::Byte(byte) => *byte == c,
+        }
+    }
+}
+
+/// 把路径段模式拆成元素；有未结束的字符集合时返回 None（不匹配任何名称）
+fn tokenize(pattern: &[u8]) -> Option<Vec<Token<'_>>> {
+    let mut tokens = Vec::new();
+    let mut index = 0;
+    while index < pattern.len() {
+        match pattern[index] {
+            b'*' => tokens.push(Token::Star),
+            b'?' => tokens.push(Token::Any),
+            b'[' => {
+                let end = class_end(pattern, index)?;
+                tokens.push(Token::Class(&pattern[index + 1..end]));
+                index = end;
+            }
+            b'\\' if index + 1 < pattern.len() => {
+                index += 1;
+                tokens.push(Token::Byte(pattern[index]));
+            }
+            c => tokens.push(Token::Byte(c)),
+        }
+        index += 1;
+    }
+    Some(tokens)
+}
+
+/// 匹配单个路径段中的 `*`、`?`、`[...]` 和 `\` 转义
+///
+/// 使用双指针：遇到不匹配时回到最近一个 `*` 多吞一个字符，耗时不超过模式长度 × 名称长度。
+fn match_name(pattern: &[u8], name: &[u8]) -> bool {
+    let Some(tokens) = tokenize(pattern) else {
+        return false;
+    };
+    let (mut t, mut n) = (0, 0);
+    // 最近一个 `*` 之后的元素位置，以及这个 `*` 目前吞到的名称位置
+    let mut star: Option<(usize, usize)> = None;
+    while n < name.len() {
+        match tokens.get(t) {
+            Some(Token::Star) => {
+                star = Some((t + 1, n));
+                t += 1;
+            }
+            Some(token) if token.matches(name[n]) => {
+                t += 1;
+                n += 1;
+            }
+            _ => match star {
+                Some((after, start)) => {
+                    star = Some((after, start + 1));
+                    t = after;
+                    n = start + 1;
+                }
+                None => return false,
+            },
+        }
+    }
+    tokens[t..].iter().all(|token| matches!(token, Token::Star))
+}
+
+/// 从 start 处的 `[` 开始找到字符集合结尾的 `]`
+fn class_end(pattern: &[u8], start: usize) -> Option<usize> {
+    let mut index = start + 1;
+    if matches!(pattern.get(index), Some(b'!' | b'^')) {
+        index += 1;
+    }
+    // 紧跟在开头的 `]` 属于集合本身
+    if pattern.get(index) == Some(&b']') {
+        index += 1;
+    }
+    pattern[index.min(pattern.len())..].iter().position(|&c| c == b']').map(|offset| index + offset)
+}
+
+fn match_class(class: &[u8], c: u8) -> bool {
+    let (negated, class) = match class.first() {
+        Some(b'!' | b'^') => (true, &class[1..]),
+        _ => (false, class),
+    };
+    let mut index = 0;
+    let mut found = false;
+    while index < class.len() {
+        if index + 2 < class.len() && class[index + 1] == b'-' {
+            found |= (class[index]..=class[index + 2]).contains(&c);
+            index += 3;
+        } else {
+            found |= class[index] == c;
+            index += 1;
+        }
+    }
+    found != negated
+}
+
+/// 目录操作的路径过滤：include 选择要处理的路径（为空时选择全部），exclude 和忽略文件中的模式排除路径
+///
+/// 排除规则按顺序匹配，后面的 `!` 模式可以重新包含前面排除的路径；与 `.gitignore` 相同，目录被排除后其中的内容不能再被包含。
+#[derive(Default)]
+pub struct PathFilter {
+    include: Vec<Pattern>,
+    exclude: Vec<Pattern>,
+}
+
+impl PathFilter {
+    /// include 中的模式总是相对于根目录，exclude 中的模式按 `.gitignore` 的规则匹配
+    pub fn new(include: Option<&[String]>, exclude: Option<&[String]>) -> Result<Self, CodedError> {
+        let parse = |patterns: Option<&[String]>, anchored: bool| {
+            patterns
+                .unwrap_or_default()
+                .iter()
+                .map(|pattern| Pattern::parse(pattern, anchored))
+                .filter_map(Result::transpose)
+                .collect::<Result<Vec<_>, CodedError>>()
+        };
+        Ok(PathFilter { include: parse(include, true)?, exclude: parse(exclude, false)? })
+    }
+
+    /// 把忽略文件中的每一行加入排除规则
+    pub fn add_ignore_file(&mut self, path: &Path) -> Result<(), CodedError> {
+        let text = fs::read_to_string(path).map_err(|e| CodedError::io_read(format!("Failed to read ignore file {}: {}", path.display(), e)))?;
+        for line in text.lines() {
+            self.exclude.extend(Pattern::parse(line, false)?);
+        }
+        Ok(())
+    }
+
+    /// 路径是否被排除：路径本身或它所在的某一层目录被排除
+    pub fn excluded(&self, path: &str, is_dir: bool) -> bool {
+        let excluded = |path: &str, is_dir: bool| {
+            self.exclude.iter().rev().find(|pattern| pattern.matches(path, is_dir)).is_some_and(|pattern| !pattern.negated)
+        };
+        ancestors(path).any(|ancestor| excluded(ancestor, true)) || excluded(path, is_dir)
+    }
+
+    /// 路径是否被 include 选中：没有 include 时总是选中，路径所在的目录被选中时路径也被选中
+    pub fn included(&self, path: &str, is_dir: bool) -> bool {
+        self.include.is_empty() || self.include.iter().any(|pattern| pattern.matches_within(path, is_dir))
+    }
+
+    /// 返回没有选中任何路径的第一个 include 模式
+    pub fn unmatched_include<'a>(&self, paths: impl Iterator<Item = (&'a str, bool)> + Clone) -> Option<&str> {
+        self.include
+            .iter()
+            .find(|pattern| !paths.clone().any(|(path, is_dir)| pattern.matches_within(path, is_dir)))
+            .map(|pattern| pattern.text.as_str())
+    }
+
+    /// 按过滤条件保留目录和文件：目录在被选中或包含保留的文件时保留
+    pub fn retain<D, F>(&self, directories: &mut Vec<D>, files: &mut Vec<F>, dir_path: impl Fn(&D) -> &str, file_path: impl Fn(&F) -> &str) {
+        files.retain(|file| self.included(file_path(file), false) && !self.excluded(file_path(file), false));
+        let parents: HashSet<&str> = files.iter().flat_map(|file| ancestors(file_path(file))).collect();
+        directories.retain(|dir| {
+            let path = dir_path(dir);
+            !self.excluded(path, true) && (self.included(path, true) || parents.contains(path))
+        });
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::{Duration, Instant};
+
+    use super::*;
+
+    fn pattern(text: &str) -> Pattern {
+        Pattern::parse(text, false).unwrap().unwrap()
+    }
+
+    #[test]
+    fn matches_wildcards_within_a_segment() {
+        assert!(pattern("*.log").matches("debug.log", false));
+        assert!(pattern("*.log").matches("logs/debug.log", false));
+        assert!(!pattern("*.log").matches("debug.log.txt", false));
+        assert!(!pattern("/*.log").matches("logs/debug.log", false));
+        assert!(pattern("file?.txt").matches("file1.txt", false));
+        assert!(!pattern("file?.txt").matches("file10.txt", false));
+        assert!(pattern("data[0-9].bin").matches("data7.bin", false));
+        assert!(!pattern("data[!0-9].bin").matches("data7.bin", false));
+        assert!(pattern("data[]x].bin").matches("data].bin", false));
+        assert!(pattern("\\*.txt").matches("*.txt", false));
+        assert!(!pattern("\\*.txt").matches("a.txt", false));
+        assert!(Pattern::parse("data[0-9.bin", false).is_err());
+    }
+
+    #[test]
+    fn matches_double_star_segments() {
+        let nested = pattern("src/**/test/*.rs");
+        assert!(nested.matches("src/test/a.rs", false));
+        assert!(nested.matches("src/a/b/c/test/a.rs", false));
+        assert!(!nested.matches("lib/test/a.rs", false));
+        assert!(pattern("**/build/").matches("a/b/build", true));
+        assert!(!pattern("**/build/").matches("a/b/build", false));
+        assert!(pattern("cache").matches_within("cache/a/b.bin", false));
+        assert!(Pattern::parse("a**b", false).is_err());
+    }
+
+    #[test]
+    fn later_negation_reincludes_files() {
+        let exclude = ["*.log".to_string(), "!keep.log".to_string(), "tmp/".to_string(), "!tmp/keep.txt".to_string()];
+        let filter = PathFilter::new(None, Some(&exclude)).unwrap();
+        assert!(filter.excluded("a/debug.log", false));
+        assert!(!filter.excluded("a/keep.log", false));
+        // 与 .gitignore 相同，目录被排除后其中的文件不能重新包含
+        assert!(filter.excluded("tmp/keep.txt", false));
+        assert!(!filter.excluded("a/readme.md", false));
+    }
+
+    /// 回溯实现对这些模式需要指数时间；忽略文件来自待处理的目录，不能让它卡住工作线程
+    #[test]
+    fn pathological_patterns_finish_quickly() {
+        let started = Instant::now();
+        let name = "a".repeat(60);
+        assert!(!pattern(&format!("{}b", "*a".repeat(20))).matches(&name, false));
+        let path = vec!["a"; 60].join("/");
+        assert!(!pattern(&format!("{}b", "**/a/".repeat(20))).matches(&path, false));
+        assert!(started.elapsed() < Duration::from_secs(2));
+    }
+}
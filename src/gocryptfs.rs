@@ -0,0 +1,443 @@
+use std::fs::{self, File};
+use std::io::{BufWriter, Write};
+use std::path::{Path, PathBuf};
+
+use aes::cipher::KeyInit;
+use aes::Aes256;
+use base64::engine::general_purpose::{STANDARD, URL_SAFE_NO_PAD};
+use base64::Engine;
+use hkdf::Hkdf;
+use rand::RngCore;
+use serde_json::{json, Value};
+use sha2::{Digest, Sha256};
+
+use crate::eme;
+use crate::errors::{open_error, read_error, write_error, CodedError};
+use crate::gcm::AesGcm;
+use crate::job::JobControl;
+use crate::pipeline;
+
+/// 密文目录根部的配置文件，保存以口令加密的主密钥
+pub const CONF_NAME: &str = "gocryptfs.conf";
+/// 每个密文目录中保存该目录文件名 IV（EME 的 tweak）的文件
+pub const DIRIV_NAME: &str = "gocryptfs.diriv";
+const DIRIV_LEN: usize = 16;
+/// 加密后超过 255 字节的文件名改用 `gocryptfs.longname.<SHA-256>`，完整名称保存在同名的 `.name` 文件中
+const LONGNAME_PREFIX: &str = "gocryptfs.longname.";
+const LONGNAME_SUFFIX: &str = ".name";
+const NAME_MAX: usize = 255;
+
+pub const KEY_LEN: usize = 32;
+/// 配置文件与文件头中的格式版本
+const FORMAT_VERSION: u16 = 2;
+const FILE_ID_LEN: usize = 16;
+/// 文件内容按 4 KiB 明文分块，每块为 16 字节 nonce || 密文 || 16 字节认证标签
+const BLOCK_DATA_LEN: usize = 4096;
+const BLOCK_NONCE_LEN: usize = 16;
+/// 主密钥以 96 位 nonce 的 AES-GCM 加密
+const MASTER_KEY_NONCE_LEN: usize = 12;
+/// 与 `gocryptfs -init` 的默认值相同
+const FEATURE_FLAGS: [&str; 6] = ["HKDF", "GCMIV128", "DirIV", "EMENames", "LongNames", "Raw64"];
+pub const DEFAULT_SCRYPT_LOG_N: u8 = 16;
+const SCRYPT_R: u32 = 8;
+const SCRYPT_P: u32 = 1;
+const HKDF_INFO_CONTENT: &str = "AES-GCM file content encryption";
+const HKDF_INFO_NAMES: &str = "EME filename encryption";
+
+/// 每读取 1 MiB 检查一次超时
+const COPY_BUFFER_LEN: usize = 256 * BLOCK_DATA_LEN;
+
+/// 与 gocryptfs 的 HKDF 特性相同：以 HKDF-SHA256（无盐）从主密钥派生内容密钥和文件名密钥
+fn derive_key(key: &[u8], info: &str) -> [u8; KEY_LEN] {
+    let mut derived = [0u8; KEY_LEN];
+    Hkdf::<Sha256>::new(None, key)
+        .expand(info.as_bytes(), &mut derived)
+        .expect("32 bytes is a valid HKDF-SHA256 output length");
+    derived
+}
+
+/// 生成 blockNo（大端 64 位）|| fileID 形式的附加认证数据
+fn block_aad(block_no: u64, file_id: &[u8]) -> Vec<u8> {
+    let mut aad = block_no.to_be_bytes().to_vec();
+    aad.extend_from_slice(file_id);
+    aad
+}
+
+fn scrypt_hash(password: &str, salt: &[u8], log_n: u8) -> Result<[u8; KEY_LEN], CodedError> {
+    let params = scrypt::Params::new(log_n, SCRYPT_R, SCRYPT_P, KEY_LEN).map_err(|_| CodedError::invalid_argument(format!("Invalid scrypt cost: 2^{}", log_n)))?;
+    let mut hash = [0u8; KEY_LEN];
+    scrypt::scrypt(password.as_bytes(), salt, &params, &mut hash).map_err(|_| CodedError::unknown("scrypt key derivation failed"))?;
+    Ok(hash)
+}
+
+/// 生成 `gocryptfs.conf` 的内容：主密钥以 scrypt(口令) 经 HKDF 派生的密钥做 AES-GCM 加密
+fn conf_json(master_key: &[u8; KEY_LEN], password: &str, log_n: u8) -> Result<String, CodedError> {
+    if !(10..=28).contains(&log_n) {
+        return Err(CodedError::invalid_argument(format!("scryptLogN must be between 10 and 28, got {}", log_n)));
+    }
+    let mut salt = [0u8; KEY_LEN];
+    rand::thread_rng().fill_bytes(&mut salt);
+    let wrapping_key = derive_key(&scrypt_hash(password, &salt, log_n)?, HKDF_INFO_CONTENT);
+    let mut nonce = [0u8; MASTER_KEY_NONCE_LEN];
+    rand::thread_rng().fill_bytes(&mut nonce);
+    let mut encrypted_key = nonce.to_vec();
+    encrypted_key.extend(AesGcm::new(&wrapping_key)?.seal(&nonce, &block_aad(0, &[]), master_key)?);
+    let conf = json!({
+        "Creator": format!("encryptor v{}", env!("CARGO_PKG_VERSION")),
+        "EncryptedKey": STANDARD.encode(encrypted_key),
+        "ScryptObject": {
+            "Salt": STANDARD.encode(salt),
+            "N": 1u64 << log_n,
+            "R": SCRYPT_R,
+            "P": SCRYPT_P,
+            "KeyLen": KEY_LEN,
+        },
+        "Version": FORMAT_VERSION,
+        "FeatureFlags": FEATURE_FLAGS,
+    });
+    serde_json::to_string_pretty(&conf).map_err(|e| CodedError::unknown(format!("Failed to serialize gocryptfs config: {}", e)))
+}
+
+/// 读取 `gocryptfs.conf` 并用口令解开主密钥；只支持 `gocryptfs -init` 默认特性（HKDF、GCMIV128、EME 文件名、Raw64）的 v2 文件系统
+pub fn unlock_master_key(cipher_dir: &str, password: &str) -> Result<[u8; KEY_LEN], CodedError> {
+    let path = Path::new(cipher_dir).join(CONF_NAME);
+    let text = fs::read(&path).map_err(|e| read_error(&format!("reading {}", path.display()), e))?;
+    let conf: Value = serde_json::from_slice(&text).map_err(|e| CodedError::bad_format(format!("Invalid gocryptfs config: {}", e)))?;
+    if conf["Version"].as_u64() != Some(FORMAT_VERSION as u64) {
+        return Err(CodedError::unsupported(format!("Unsupported gocryptfs config version: {}", conf["Version"])));
+    }
+    let flags: Vec<&str> = conf["FeatureFlags"].as_array().into_iter().flatten().filter_map(Value::as_str).collect();
+    for flag in ["HKDF", "GCMIV128", "DirIV", "EMENames", "Raw64"] {
+        if !flags.contains(&flag) {
+            return Err(CodedError::unsupported(format!("Unsupported gocryptfs file system: feature flag {} is not set", flag)));
+        }
+    }
+    if let Some(flag) = flags.iter().find(|flag| !FEATURE_FLAGS.contains(flag)) {
+        return Err(CodedError::unsupported(format!("Unsupported gocryptfs feature flag: {}", flag)));
+    }
+    let scrypt_object = &conf["ScryptObject"];
+    let decode = |value: &Value, what: &str| {
+        value
+            .as_str()
+            .and_then(|text| STANDARD.decode(text).ok())
+            .ok_or_else(|| CodedError::bad_format(format!("Invalid gocryptfs config: bad {}", what)))
+    };
+    let salt = decode(&scrypt_object["Salt"], "scrypt salt")?;
+    let n = scrypt_object["N"].as_u64().unwrap_or(0);
+    if !n.is_power_of_two() || n < 2 || scrypt_object["R"].as_u64() != Some(SCRYPT_R as u64) || scrypt_object["P"].as_u64() != Some(SCRYPT_P as u64) || scrypt_object["KeyLen"].as_u64() != Some(KEY_LEN as u64) {
+        return Err(CodedError::unsupported("Unsupported gocryptfs scrypt parameters"));
+    }
+    let wrapping_key = derive_key(&scrypt_hash(password, &salt, n.trailing_zeros() as u8)?, HKDF_INFO_CONTENT);
+    let encrypted_key = decode(&conf["EncryptedKey"], "encrypted key")?;
+    if encrypted_key.len() < MASTER_KEY_NONCE_LEN {
+        return Err(CodedError::bad_format("Invalid gocryptfs config: bad encrypted key"));
+    }
+    let (nonce, sealed) = encrypted_key.split_at(MASTER_KEY_NONCE_LEN);
+    let master_key = AesGcm::new(&wrapping_key)?
+        .open(nonce, &block_aad(0, &[]), sealed)
+        .map_err(|_| CodedError::auth_failed("Failed to unlock gocryptfs master key: wrong password"))?;
+    master_key
+        .try_into()
+        .map_err(|_| CodedError::invalid_key_length("Invalid gocryptfs config: master key must be 32 bytes"))
+}
+
+/// gocryptfs 的文件名加密：每段名称以 PKCS#7 填充后用 EME 加密，tweak 为所在目录的 `gocryptfs.diriv`，
+/// 再以无填充的 URL 安全 base64 编码。同一目录中相同的名称总是得到相同的密文
+pub struct NameCipher {
+    cipher: Aes256,
+}
+
+impl NameCipher {
+    pub fn new(master_key: &[u8; KEY_LEN]) -> Self {
+        let key = derive_key(master_key, HKDF_INFO_NAMES);
+        NameCipher { cipher: Aes256::new(&key.into()) }
+    }
+
+    pub fn encrypt_name(&self, name: &str, dir_iv: &[u8; DIRIV_LEN]) -> Result<String, CodedError> {
+        if name.is_empty() || name == "." || name == ".." || name.contains('/') || name.contains('\0') {
+            return Err(CodedError::bad_format(format!("Invalid file name: {:?}", name)));
+        }
+        if name.len() > NAME_MAX {
+            return Err(CodedError::invalid_argument(format!("File name is longer than {} bytes: {}", NAME_MAX, name)));
+        }
+        let padded = eme::pad_name(name.as_bytes()).ok_or_else(|| CodedError::invalid_argument(format!("File name is too long to encrypt: {}", name)))?;
+        Ok(URL_SAFE_NO_PAD.encode(eme::transform(&self.cipher, dir_iv, &padded, eme::Direction::Encrypt)))
+    }
+
+    pub fn decrypt_name(&self, encrypted: &str, dir_iv: &[u8; DIRIV_LEN]) -> Result<String, CodedError> {
+        let invalid = || CodedError::bad_format(format!("Invalid gocryptfs encrypted name: {}", encrypted));
+        let ciphertext = URL_SAFE_NO_PAD.decode(encrypted).map_err(|_| invalid())?;
+        if ciphertext.is_empty() || ciphertext.len() % eme::BLOCK_LEN != 0 || ciphertext.len() > eme::BLOCK_LEN * eme::MAX_BLOCKS {
+            return Err(invalid());
+        }
+        let padded = eme::transform(&self.cipher, dir_iv, &ciphertext, eme::Direction::Decrypt);
+        eme::unpad_name(padded)
+            .and_then(|name| String::from_utf8(name).ok())
+            .ok_or_else(|| CodedError::auth_failed(format!("Invalid gocryptfs encrypted name: {} (wrong key?)", encrypted)))
+    }
+}
+
+/// 加密后的名称在磁盘上的文件名；过长时返回 longname 形式的文件名和应写入 `.name` 文件的完整名称
+fn stored_name(encrypted: String) -> (String, Option<String>) {
+    if encrypted.len() <= NAME_MAX {
+        return (encrypted, None);
+    }
+    let hash = URL_SAFE_NO_PAD.encode(Sha256::digest(encrypted.as_bytes()));
+    (format!("{}{}", LONGNAME_PREFIX, hash), Some(encrypted))
+}
+
+fn read_dir_iv(dir: &Path) -> Result<[u8; DIRIV_LEN], CodedError> {
+    let path = dir.join(DIRIV_NAME);
+    let iv = fs::read(&path).map_err(|e| read_error(&format!("reading {}", path.display()), e))?;
+    iv.try_into()
+        .map_err(|_| CodedError::invalid_argument(format!("Invalid {}: must be {} bytes", path.display(), DIRIV_LEN)))
+}
+
+/// 把明文相对路径转换为密文目录中的相对路径；中间的目录必须已存在于密文目录中（用于读取它们的 diriv）
+pub fn encrypt_path(cipher_dir: &str, path: &str, names: &NameCipher) -> Result<String, CodedError> {
+    let mut dir = PathBuf::from(cipher_dir);
+    let mut segments = Vec::new();
+    for segment in path.split('/').filter(|segment| !segment.is_empty()) {
+        let (stored, _) = stored_name(names.encrypt_name(segment, &read_dir_iv(&dir)?)?);
+        dir.push(&stored);
+        segments.push(stored);
+    }
+    Ok(segments.join("/"))
+}
+
+/// 把密文目录中的相对路径还原为明文路径，longname 形式的名称从对应的 `.name` 文件读取
+pub fn decrypt_path(cipher_dir: &str, path: &str, names: &NameCipher) -> Result<String, CodedError> {
+    let mut dir = PathBuf::from(cipher_dir);
+    let mut segments = Vec::new();
+    for segment in path.split('/').filter(|segment| !segment.is_empty()) {
+        let encrypted = if segment.starts_with(LONGNAME_PREFIX) {
+            let name_path = dir.join(format!("{}{}", segment, LONGNAME_SUFFIX));
+            fs::read_to_string(&name_path).map_err(|e| read_error(&format!("reading {}", name_path.display()), e))?
+        } else {
+            segment.to_string()
+        };
+        segments.push(names.decrypt_name(encrypted.trim_end(), &read_dir_iv(&dir)?)?);
+        dir.push(segment);
+    }
+    Ok(segments.join("/"))
+}
+
+pub struct GocryptfsReport {
+    pub files: u32,
+    pub directories: u32,
+    pub plaintext_size: u64,
+    pub encrypted_size: u64,
+    pub master_key: [u8; KEY_LEN],
+}
+
+#[cfg(unix)]
+fn set_mode(path: &Path, source: &fs::Metadata) {
+    let _ = fs::set_permissions(path, source.permissions());
+}
+
+#[cfg(not(unix))]
+fn set_mode(_path: &Path, _source: &fs::Metadata) {}
+
+/// 写出 `gocryptfs.diriv` 等只读的元数据文件（权限 0400，与 gocryptfs 相同）
+fn write_read_only(path: &Path, data: &[u8]) -> Result<(), CodedError> {
+    fs::write(path, data).map_err(|e| write_error(&format!("writing {}", path.display()), e))?;
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::PermissionsExt;
+        let _ = fs::set_permissions(path, fs::Permissions::from_mode(0o400));
+    }
+    Ok(())
+}
+
+struct TreeWriter<'a> {
+    names: NameCipher,
+    content: AesGcm,
+    control: &'a JobControl,
+    created: Vec<PathBuf>,
+    report: GocryptfsReport,
+}
+
+impl TreeWriter<'_> {
+    fn write_dir_iv(&mut self, dir: &Path) -> Result<[u8; DIRIV_LEN], CodedError> {
+        let mut iv = [0u8; DIRIV_LEN];
+        rand::thread_rng().fill_bytes(&mut iv);
+        let path = dir.join(DIRIV_NAME);
+        self.created.push(path.clone());
+        write_read_only(&path, &iv)?;
+        self.report.encrypted_size += DIRIV_LEN as u64;
+        Ok(iv)
+    }
+
+    /// 按名称排序加密目录中的所有项，子目录递归处理；符号链接按其指向的内容处理
+    fn encrypt_dir(&mut self, input: &Path, output: &Path) -> Result<(), CodedError> {
+        let dir_iv = self.write_dir_iv(output)?;
+        let mut children = fs::read_dir(input)
+            .map_err(|e| read_error(&format!("reading directory {}", input.display()), e))?
+            .map(|child| child.map(|child| child.path()))
+            .collect::<Result<Vec<_>, _>>()
+            .map_err(|e| read_error(&format!("reading directory {}", input.display()), e))?;
+        children.sort();
+        for child in children {
+            let name = child
+                .file_name()
+                .and_then(|name| name.to_str())
+                .ok_or_else(|| CodedError::bad_format(format!("File name is not valid UTF-8: {}", child.display())))?;
+            let (stored, long_name) = stored_name(self.names.encrypt_name(name, &dir_iv)?);
+            if let Some(long_name) = long_name {
+                let path = output.join(format!("{}{}", stored, LONGNAME_SUFFIX));
+                self.created.push(path.clone());
+                write_read_only(&path, long_name.as_bytes())?;
+                self.report.encrypted_size += long_name.len() as u64;
+            }
+            let target = output.join(&stored);
+            let metadata = fs::metadata(&child).map_err(|e| read_error(&format!("reading {}", child.display()), e))?;
+            if metadata.is_dir() {
+                fs::create_dir(&target).map_err(|e| open_error(&format!("creating directory {}", target.display()), e))?;
+                self.created.push(target.clone());
+                self.report.directories += 1;
+                self.encrypt_dir(&child, &target)?;
+            } else if metadata.is_file() {
+                self.created.push(target.clone());
+                self.encrypt_file(&child, &target)?;
+                self.report.files += 1;
+                self.control.chunk_done(self.report.files, metadata.len() as usize);
+            } else {
+                return Err(CodedError::unsupported(format!("Unsupported file type: {}", child.display())));
+            }
+            set_mode(&target, &metadata);
+        }
+        Ok(())
+    }
+
+    /// 文件内容：18 字节文件头（版本 2 和随机的 16 字节文件 ID），之后每 4 KiB 明文一块 AES-256-GCM；空文件不写文件头
+    fn encrypt_file(&mut self, input_path: &Path, output_path: &Path) -> Result<(), CodedError> {
+        let mut input = File::open(input_path).map_err(|e| open_error(&format!("opening {}", input_path.display()), e))?;
+        let output = File::create(output_path).map_err(|e| open_error(&format!("creating {}", output_path.display()), e))?;
+        let mut writer = BufWriter::new(output);
+        let mut file_id = [0u8; FILE_ID_LEN];
+        rand::thread_rng().fill_bytes(&mut file_id);
+        let mut buffer = vec![0u8; COPY_BUFFER_LEN];
+        let mut block_no = 0u64;
+        loop {
+            self.control.check()?;
+            let read = pipeline::read_full(&mut input, &mut buffer).map_err(|err| read_error("reading input file", err))?;
+            if read == 0 {
+                break;
+            }
+            if block_no == 0 {
+                writer
+                    .write_all(&FORMAT_VERSION.to_be_bytes())
+                    .and_then(|_| writer.write_all(&file_id))
+                    .map_err(|e| write_error("writing output file", e))?;
+                self.report.encrypted_size += (2 + FILE_ID_LEN) as u64;
+            }
+            for block in buffer[..read].chunks(BLOCK_DATA_LEN) {
+                let mut nonce = [0u8; BLOCK_NONCE_LEN];
+                rand::thread_rng().fill_bytes(&mut nonce);
+                let sealed = self.content.seal(&nonce, &block_aad(block_no, &file_id), block)?;
+                writer
+                    .write_all(&nonce)
+                    .and_then(|_| writer.write_all(&sealed))
+                    .map_err(|e| write_error("writing output file", e))?;
+                self.report.encrypted_size += (BLOCK_NONCE_LEN + sealed.len()) as u64;
+                block_no += 1;
+            }
+            self.report.plaintext_size += read as u64;
+            if read < buffer.len() {
+                break;
+            }
+        }
+        writer
+            .into_inner()
+            .map_err(|e| write_error("writing output file", e.into_error()))?
+            .sync_all()
+            .map_err(|e| write_error("flushing output file", e))
+    }
+}
+
+/// 把明文目录树加密为新的 gocryptfs（v2，默认特性）密文目录，可以直接用 `gocryptfs` 挂载。
+/// 输出目录必须不存在或为空；失败时删除已写入的文件
+pub fn encrypt_directory(input_dir: &str, output_dir: &str, password: &str, scrypt_log_n: u8, control: &JobControl) -> Result<GocryptfsReport, CodedError> {
+    if password.is_empty() {
+        return Err(CodedError::invalid_argument("gocryptfs password must not be empty"));
+    }
+    if !fs::metadata(input_dir).map_err(|e| read_error(&format!("reading {}", input_dir), e))?.is_dir() {
+        return Err(CodedError::invalid_argument(format!("Input is not a directory: {}", input_dir)));
+    }
+    let output = Path::new(output_dir);
+    let mut created = Vec::new();
+    match fs::read_dir(output) {
+        Ok(mut entries) => {
+            if entries.next().is_some() {
+                return Err(CodedError::invalid_argument(format!("Output directory is not empty: {}", output_dir)));
+            }
+        }
+        Err(err) if err.kind() == std::io::ErrorKind::NotFound => {
+            fs::create_dir_all(output).map_err(|e| open_error(&format!("creating directory {}", output_dir), e))?;
+            created.push(output.to_path_buf());
+        }
+        Err(err) => return Err(read_error(&format!("reading directory {}", output_dir), err)),
+    }
+
+    let mut master_key = [0u8; KEY_LEN];
+    rand::thread_rng().fill_bytes(&mut master_key);
+    let mut writer = TreeWriter {
+        names: NameCipher::new(&master_key),
+        content: AesGcm::with_long_nonce(&derive_key(&master_key, HKDF_INFO_CONTENT))?,
+        control,
+        created,
+        report: GocryptfsReport { files: 0, directories: 0, plaintext_size: 0, encrypted_size: 0, master_key },
+    };
+    let result = conf_json(&master_key, password, scrypt_log_n).and_then(|conf| {
+        let conf_path = output.join(CONF_NAME);
+        writer.created.push(conf_path.clone());
+        write_read_only(&conf_path, conf.as_bytes())?;
+        writer.encrypt_dir(Path::new(input_dir), output)
+    });
+    if let Err(err) = result {
+        crate::walk::remove_created(&writer.created);
+        return Err(err);
+    }
+    Ok(writer.report)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// 以下向量由按 gocryptfs 格式文档独立编写的 Python 实现生成（scrypt/HKDF/AES-GCM 来自 hashlib 与 cryptography，
+    /// EME 另行实现并已用 rclone 的官方文件名向量核对），口令为 `gocryptfs-test`，主密钥为 0x40..0x5f
+    const CIPHER_DIR: &str = concat!(env!("CARGO_MANIFEST_DIR"), "/testdata/gocryptfs");
+    fn master_key() -> [u8; KEY_LEN] {
+        std::array::from_fn(|index| 0x40 + index as u8)
+    }
+
+    #[test]
+    fn unlocks_independently_generated_config() {
+        assert_eq!(unlock_master_key(CIPHER_DIR, "gocryptfs-test").unwrap(), master_key());
+        assert_eq!(unlock_master_key(CIPHER_DIR, "wrong").unwrap_err().code, crate::errors::ErrorCode::AuthFailed);
+    }
+
+    #[test]
+    fn encrypts_names_like_independent_implementation() {
+        let names = NameCipher::new(&master_key());
+        assert_eq!(encrypt_path(CIPHER_DIR, "hello.txt", &names).unwrap(), "rB6mTIOth4gRnfrCNH-6BA");
+        assert_eq!(decrypt_path(CIPHER_DIR, "rB6mTIOth4gRnfrCNH-6BA", &names).unwrap(), "hello.txt");
+        let long_name = format!("long-{}.txt", "x".repeat(200));
+        let long_stored = "gocryptfs.longname.Rc4GadwgtcVkr87PKd5qliRF5a-ukCk3wm9bG-26Ces";
+        assert_eq!(encrypt_path(CIPHER_DIR, &long_name, &names).unwrap(), long_stored);
+        assert_eq!(decrypt_path(CIPHER_DIR, long_stored, &names).unwrap(), long_name);
+    }
+
+    #[test]
+    fn seals_content_blocks_like_independent_implementation() {
+        let file = hex::decode(include_str!("../testdata/gocryptfs/hello.txt.hex").trim()).unwrap();
+        let (header, block) = file.split_at(2 + FILE_ID_LEN);
+        assert_eq!(header[..2], FORMAT_VERSION.to_be_bytes());
+        let (nonce, _) = block.split_at(BLOCK_NONCE_LEN);
+        let plaintext: Vec<u8> = (0..100).map(|i| (i % 251) as u8).collect();
+        let content = AesGcm::with_long_nonce(&derive_key(&master_key(), HKDF_INFO_CONTENT)).unwrap();
+        let sealed = content.seal(nonce, &block_aad(0, &header[2..]), &plaintext).unwrap();
+        assert_eq!([nonce, &sealed[..]].concat(), block);
+    }
+}
@@ -0,0 +1,1057 @@
+use std::io::{self, BufReader, BufWriter, Read, Write};
+use std::path::Path;
+use std::time::UNIX_EPOCH;
+
+use aes::cipher::{BlockEncrypt, KeyInit};
+use aes::{Aes128, Aes192, Aes256, Block};
+use flate2::read::{DeflateDecoder, ZlibDecoder};
+use hkdf::Hkdf;
+use md5::{Digest, Md5};
+use sha1::Sha1;
+use rand::RngCore;
+use sha2::{Sha224, Sha256, Sha384, Sha512};
+
+use crate::errors::{open_error, read_error, write_error, CodedError, ErrorCode};
+use crate::fd::{self, SyncOutput, Target};
+use crate::gcm::AesGcm;
+use crate::job::JobControl;
+use crate::ocb::AesOcb;
+
+/// ASCII 封装的 OpenPGP 消息的第一行
+pub const PGP_ARMOR_MAGIC: &[u8] = b"-----BEGIN PGP MESSAGE-----";
+
+const TAG_PKESK: u8 = 1;
+const TAG_SKESK: u8 = 3;
+const TAG_ONE_PASS_SIGNATURE: u8 = 4;
+const TAG_SIGNATURE: u8 = 2;
+const TAG_COMPRESSED: u8 = 8;
+const TAG_SED: u8 = 9;
+const TAG_MARKER: u8 = 10;
+const TAG_LITERAL: u8 = 11;
+const TAG_SEIPD: u8 = 18;
+const TAG_AEAD: u8 = 20;
+
+const BLOCK_LEN: usize = 16;
+/// MDC 包：0xD3 0x14 + 20 字节 SHA-1
+const MDC_LEN: usize = 22;
+const MDC_HEADER: [u8; 2] = [0xd3, 0x14];
+
+/// 复制数据时每次读取的长度，每读完一块检查一次超时并发送 chunkDone 事件
+const COPY_BUFFER_LEN: usize = 1024 * 1024;
+
+fn invalid(message: &str) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, message.to_string())
+}
+
+/// OpenPGP 包的长度
+enum BodyLength {
+    Fixed(u64),
+    /// 分段长度（partial body length），后面还有其它分段
+    Partial(u64),
+    /// 旧格式的不定长度，一直延续到外层数据结束
+    Indeterminate,
+}
+
+/// 读取一个包头，返回（tag，长度）；None 表示数据已结束
+fn read_packet_header<R: Read>(reader: &mut R) -> io::Result<Option<(u8, BodyLength)>> {
+    let mut ctb = [0u8; 1];
+    if reader.read(&mut ctb)? == 0 {
+        return Ok(None);
+    }
+    let ctb = ctb[0];
+    if ctb & 0x80 == 0 {
+        return Err(invalid("Invalid OpenPGP packet header"));
+    }
+    if ctb & 0x40 == 0 {
+        // 旧格式包头
+        let tag = (ctb >> 2) & 0x0f;
+        let length = match ctb & 0x03 {
+            0 => BodyLength::Fixed(read_be(reader, 1)?),
+            1 => BodyLength::Fixed(read_be(reader, 2)?),
+            2 => BodyLength::Fixed(read_be(reader, 4)?),
+            _ => BodyLength::Indeterminate,
+        };
+        Ok(Some((tag, length)))
+    } else {
+        Ok(Some((ctb & 0x3f, read_new_length(reader)?)))
+    }
+}
+
+/// 读取新格式的长度字段
+fn read_new_length<R: Read>(reader: &mut R) -> io::Result<BodyLength> {
+    let first = read_be(reader, 1)?;
+    Ok(match first {
+        0..=191 => BodyLength::Fixed(first),
+        192..=223 => BodyLength::Fixed(((first - 192) << 8) + read_be(reader, 1)? + 192),
+        255 => BodyLength::Fixed(read_be(reader, 4)?),
+        _ => BodyLength::Partial(1 << (first & 0x1f)),
+    })
+}
+
+fn read_be<R: Read>(reader: &mut R, len: usize) -> io::Result<u64> {
+    let mut bytes = [0u8; 4];
+    reader.read_exact(&mut bytes[..len])?;
+    Ok(bytes[..len].iter().fold(0, |value, byte| (value << 8) | *byte as u64))
+}
+
+/// 包体读取器：按包长度读取，自动拼接分段长度的各个分段
+struct PacketBody<R: Read> {
+    inner: R,
+    /// 当前分段剩余的字节数，不定长度时为 None
+    remaining: Option<u64>,
+    more_parts: bool,
+}
+
+impl<R: Read> PacketBody<R> {
+    fn new(inner: R, length: BodyLength) -> Self {
+        match length {
+            BodyLength::Fixed(len) => PacketBody { inner, remaining: Some(len), more_parts: false },
+            BodyLength::Partial(len) => PacketBody { inner, remaining: Some(len), more_parts: true },
+            BodyLength::Indeterminate => PacketBody { inner, remaining: None, more_parts: false },
+        }
+    }
+}
+
+impl<R: Read> Read for PacketBody<R> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        loop {
+            let remaining = match self.remaining {
+                None => return self.inner.read(buf),
+                Some(remaining) => remaining,
+            };
+            if remaining == 0 {
+                if !self.more_parts {
+                    return Ok(0);
+                }
+                match read_new_length(&mut self.inner)? {
+                    BodyLength::Partial(len) => self.remaining = Some(len),
+                    BodyLength::Fixed(len) => {
+                        self.remaining = Some(len);
+                        self.more_parts = false;
+                    }
+                    BodyLength::Indeterminate => unreachable!(),
+                }
+                continue;
+            }
+            if buf.is_empty() {
+                return Ok(0);
+            }
+            let want = (buf.len() as u64).min(remaining) as usize;
+            let read = self.inner.read(&mut buf[..want])?;
+            if read == 0 {
+                return Err(io::Error::new(io::ErrorKind::UnexpectedEof, "OpenPGP packet is truncated"));
+            }
+            self.remaining = Some(remaining - read as u64);
+            return Ok(read);
+        }
+    }
+}
+
+/// 支持的对称算法（RFC 4880 9.2）
+#[derive(Clone, Copy)]
+pub enum SymmetricAlgorithm {
+    Aes128,
+    Aes192,
+    Aes256,
+}
+
+impl SymmetricAlgorithm {
+    fn from_id(id: u8) -> Result<Self, CodedError> {
+        match id {
+            7 => Ok(SymmetricAlgorithm::Aes128),
+            8 => Ok(SymmetricAlgorithm::Aes192),
+            9 => Ok(SymmetricAlgorithm::Aes256),
+            _ => Err(CodedError::unsupported(format!("Unsupported OpenPGP cipher algorithm {}, only AES is supported", id))),
+        }
+    }
+
+    pub fn parse(name: &str) -> Result<Self, CodedError> {
+        match name {
+            "aes128" => Ok(SymmetricAlgorithm::Aes128),
+            "aes192" => Ok(SymmetricAlgorithm::Aes192),
+            "aes256" => Ok(SymmetricAlgorithm::Aes256),
+            _ => Err(CodedError::unsupported(format!("Unsupported OpenPGP cipher: {}, supported: aes128, aes192, aes256", name))),
+        }
+    }
+
+    fn id(&self) -> u8 {
+        match self {
+            SymmetricAlgorithm::Aes128 => 7,
+            SymmetricAlgorithm::Aes192 => 8,
+            SymmetricAlgorithm::Aes256 => 9,
+        }
+    }
+
+    fn key_len(&self) -> usize {
+        match self {
+            SymmetricAlgorithm::Aes128 => 16,
+            SymmetricAlgorithm::Aes192 => 24,
+            SymmetricAlgorithm::Aes256 => 32,
+        }
+    }
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            SymmetricAlgorithm::Aes128 => "aes128",
+            SymmetricAlgorithm::Aes192 => "aes192",
+            SymmetricAlgorithm::Aes256 => "aes256",
+        }
+    }
+}
+
+enum BlockCipher {
+    Aes128(Box<Aes128>),
+    Aes192(Box<Aes192>),
+    Aes256(Box<Aes256>),
+}
+
+impl BlockCipher {
+    fn new(algorithm: SymmetricAlgorithm, key: &[u8]) -> Result<Self, CodedError> {
+        let error = |_| CodedError::invalid_key_length("Invalid OpenPGP session key length");
+        Ok(match algorithm {
+            SymmetricAlgorithm::Aes128 => BlockCipher::Aes128(Box::new(Aes128::new_from_slice(key).map_err(error)?)),
+            SymmetricAlgorithm::Aes192 => BlockCipher::Aes192(Box::new(Aes192::new_from_slice(key).map_err(error)?)),
+            SymmetricAlgorithm::Aes256 => BlockCipher::Aes256(Box::new(Aes256::new_from_slice(key).map_err(error)?)),
+        })
+    }
+
+    fn encrypt_block(&self, block: &mut Block) {
+        match self {
+            BlockCipher::Aes128(cipher) => cipher.encrypt_block(block),
+            BlockCipher::Aes192(cipher) => cipher.encrypt_block(block),
+            BlockCipher::Aes256(cipher) => cipher.encrypt_block(block),
+        }
+    }
+}
+
+/// OpenPGP CFB 解密（IV 全零，不做重新同步），可以分多次调用
+struct CfbDecryptor {
+    cipher: BlockCipher,
+    register: Block,
+    keystream: Block,
+    position: usize,
+}
+
+impl CfbDecryptor {
+    fn new(cipher: BlockCipher) -> Self {
+        CfbDecryptor { cipher, register: Block::default(), keystream: Block::default(), position: BLOCK_LEN }
+    }
+
+    fn decrypt(&mut self, data: &mut [u8]) {
+        for byte in data {
+            if self.position == BLOCK_LEN {
+                self.keystream = self.register;
+                self.cipher.encrypt_block(&mut self.keystream);
+                self.position = 0;
+            }
+            self.register[self.position] = *byte;
+            *byte ^= self.keystream[self.position];
+            self.position += 1;
+        }
+    }
+}
+
+/// S2K 使用的摘要算法（RFC 4880 9.4）
+#[derive(Clone, Copy)]
+enum S2kHash {
+    Md5,
+    Sha1,
+    Sha224,
+    Sha256,
+    Sha384,
+    Sha512,
+}
+
+impl S2kHash {
+    fn from_id(id: u8) -> Result<Self, CodedError> {
+        match id {
+            1 => Ok(S2kHash::Md5),
+            2 => Ok(S2kHash::Sha1),
+            8 => Ok(S2kHash::Sha256),
+            9 => Ok(S2kHash::Sha384),
+            10 => Ok(S2kHash::Sha512),
+            11 => Ok(S2kHash::Sha224),
+            _ => Err(CodedError::unsupported(format!("Unsupported OpenPGP S2K hash algorithm {}", id))),
+        }
+    }
+}
+
+/// 字符串到密钥的转换（S2K）
+struct S2k {
+    hash: S2kHash,
+    salt: Vec<u8>,
+    /// 迭代加盐时参与摘要的总字节数，其它类型为 0
+    count: usize,
+}
+
+impl S2k {
+    fn parse(data: &[u8]) -> Result<(S2k, usize), CodedError> {
+        let truncated = || CodedError::bad_format("Invalid OpenPGP S2K specifier");
+        let kind = *data.first().ok_or_else(truncated)?;
+        let hash = S2kHash::from_id(*data.get(1).ok_or_else(truncated)?)?;
+        match kind {
+            0 => Ok((S2k { hash, salt: Vec::new(), count: 0 }, 2)),
+            1 | 3 => {
+                let salt = data.get(2..10).ok_or_else(truncated)?.to_vec();
+                if kind == 1 {
+                    return Ok((S2k { hash, salt, count: 0 }, 10));
+                }
+                let coded = *data.get(10).ok_or_else(truncated)? as usize;
+                let count = (16 + (coded & 15)) << ((coded >> 4) + 6);
+                Ok((S2k { hash, salt, count }, 11))
+            }
+            _ => Err(CodedError::unsupported(format!("Unsupported OpenPGP S2K type {}", kind))),
+        }
+    }
+
+    fn derive(&self, passphrase: &[u8], key_len: usize) -> Vec<u8> {
+        fn run<D: Digest>(s2k: &S2k, passphrase: &[u8], key_len: usize) -> Vec<u8> {
+            let mut key = Vec::with_capacity(key_len);
+            let mut preload = 0;
+            while key.len() < key_len {
+                // 密钥比摘要长时，第 n 个摘要上下文先输入 n 个零字节
+                let mut hasher = D::new();
+                hasher.update(vec![0u8; preload]);
+                let total = s2k.count.max(s2k.salt.len() + passphrase.len());
+                let mut fed = 0;
+                while fed < total {
+                    for part in [s2k.salt.as_slice(), passphrase] {
+                        let take = part.len().min(total - fed);
+                        hasher.update(&part[..take]);
+                        fed += take;
+                    }
+                }
+                key.extend_from_slice(&hasher.finalize());
+                preload += 1;
+            }
+            key.truncate(key_len);
+            key
+        }
+        match self.hash {
+            S2kHash::Md5 => run::<Md5>(self, passphrase, key_len),
+            S2kHash::Sha1 => run::<Sha1>(self, passphrase, key_len),
+            S2kHash::Sha224 => run::<Sha224>(self, passphrase, key_len),
+            S2kHash::Sha256 => run::<Sha256>(self, passphrase, key_len),
+            S2kHash::Sha384 => run::<Sha384>(self, passphrase, key_len),
+            S2kHash::Sha512 => run::<Sha512>(self, passphrase, key_len),
+        }
+    }
+}
+
+/// 由 v4 SKESK 包和口令得到（会话密钥算法，会话密钥）
+fn session_key(packet: &[u8], passphrase: &[u8]) -> Result<(SymmetricAlgorithm, Vec<u8>), CodedError> {
+    match packet.first() {
+        Some(4) => {}
+        Some(version) => return Err(CodedError::unsupported(format!("Unsupported OpenPGP symmetric key packet version {}", version))),
+        None => return Err(CodedError::bad_format("Invalid OpenPGP symmetric key packet")),
+    }
+    let algorithm = SymmetricAlgorithm::from_id(*packet.get(1).ok_or_else(|| CodedError::bad_format("Invalid OpenPGP symmetric key packet"))?)?;
+    let (s2k, used) = S2k::parse(&packet[2..])?;
+    let key = s2k.derive(passphrase, algorithm.key_len());
+    let encrypted = &packet[2 + used..];
+    if encrypted.is_empty() {
+        return Ok((algorithm, key));
+    }
+
+    // 带有加密的会话密钥：第一个字节为会话密钥的算法
+    let mut decrypted = encrypted.to_vec();
+    CfbDecryptor::new(BlockCipher::new(algorithm, &key)?).decrypt(&mut decrypted);
+    let session_algorithm = SymmetricAlgorithm::from_id(decrypted[0])?;
+    if decrypted.len() - 1 != session_algorithm.key_len() {
+        return Err(CodedError::auth_failed("Invalid OpenPGP session key (wrong passphrase?)"));
+    }
+    Ok((session_algorithm, decrypted[1..].to_vec()))
+}
+
+/// SEIPD 包体的解密读取器：维护 MDC 的 SHA-1，保留末尾 22 字节，读到结尾时校验 MDC
+struct SeipdReader<R: Read> {
+    inner: R,
+    cfb: CfbDecryptor,
+    mdc: Sha1,
+    /// 已解密但尚未交给调用方的数据，末尾的 MDC_LEN 字节要等到数据结束才能确定是否属于 MDC
+    pending: Vec<u8>,
+    offset: usize,
+    verified: bool,
+}
+
+impl<R: Read> SeipdReader<R> {
+    /// prefix 为已经读取并通过快速校验的随机前缀（分组长度 + 2 字节）的明文
+    fn new(inner: R, cfb: CfbDecryptor, prefix: &[u8]) -> Self {
+        let mut mdc = Sha1::new();
+        mdc.update(prefix);
+        SeipdReader { inner, cfb, mdc, pending: Vec::new(), offset: 0, verified: false }
+    }
+}
+
+impl<R: Read> Read for SeipdReader<R> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        loop {
+            let available = self.pending.len() - self.offset;
+            if available > MDC_LEN {
+                let take = (available - MDC_LEN).min(buf.len());
+                let data = &self.pending[self.offset..self.offset + take];
+                self.mdc.update(data);
+                buf[..take].copy_from_slice(data);
+                self.offset += take;
+                return Ok(take);
+            }
+            if self.verified {
+                return Ok(0);
+            }
+
+            self.pending.drain(..self.offset);
+            self.offset = 0;
+            let start = self.pending.len();
+            self.pending.resize(start + COPY_BUFFER_LEN, 0);
+            let read = loop {
+                match self.inner.read(&mut self.pending[start..]) {
+                    Err(err) if err.kind() == io::ErrorKind::Interrupted => continue,
+                    result => break result?,
+                }
+            };
+            self.pending.truncate(start + read);
+            self.cfb.decrypt(&mut self.pending[start..]);
+            if read > 0 {
+                continue;
+            }
+
+            // 数据结束：剩下的 22 字节必须是 MDC 包，SHA-1 覆盖到 MDC 包头为止
+            if self.pending.len() != MDC_LEN || self.pending[..2] != MDC_HEADER {
+                return Err(invalid("OpenPGP integrity check failed: missing modification detection code"));
+            }
+            let mut mdc = self.mdc.clone();
+            mdc.update(MDC_HEADER);
+            if mdc.finalize()[..] != self.pending[2..] {
+                return Err(invalid("OpenPGP integrity check failed: data was modified"));
+            }
+            self.pending.clear();
+            self.verified = true;
+        }
+    }
+}
+
+/// 压缩包使用的算法（RFC 4880 9.3）
+fn compression_name(id: u8) -> Result<&'static str, CodedError> {
+    match id {
+        0 => Ok("none"),
+        1 => Ok("zip"),
+        2 => Ok("zlib"),
+        3 => Ok("bzip2"),
+        _ => Err(CodedError::unsupported(format!("Unsupported OpenPGP compression algorithm {}", id))),
+    }
+}
+
+/// 解密 OpenPGP 文件的统计
+pub struct GpgReport {
+    pub plaintext_size: u64,
+    pub encrypted_size: u64,
+    /// 字面数据包中记录的文件名，可能为空
+    pub filename: String,
+    pub cipher: &'static str,
+    pub compression: &'static str,
+}
+
+/// 判断数据是否像二进制 OpenPGP 消息：以 SKESK、PKESK 或标记包开头
+pub fn looks_like_openpgp(sample: &[u8]) -> bool {
+    let mut reader = sample;
+    match read_packet_header(&mut reader) {
+        Ok(Some((TAG_SKESK, BodyLength::Fixed(_)))) => matches!(reader.first(), Some(4) | Some(5)),
+        Ok(Some((TAG_PKESK, BodyLength::Fixed(_)))) => matches!(reader.first(), Some(3)),
+        Ok(Some((TAG_MARKER, BodyLength::Fixed(3)))) => reader.starts_with(b"PGP"),
+        _ => false,
+    }
+}
+
+/// 逐块复制字面数据，每块之间检查超时
+fn copy<R: Read, W: Write>(reader: &mut R, writer: &mut W, control: &JobControl) -> Result<u64, CodedError> {
+    let mut buffer = vec![0u8; COPY_BUFFER_LEN];
+    let mut total = 0u64;
+    let mut index = 0u32;
+    loop {
+        control.check()?;
+        let read = match reader.read(&mut buffer) {
+            Ok(0) => return Ok(total),
+            Ok(read) => read,
+            Err(err) if err.kind() == io::ErrorKind::Interrupted => continue,
+            Err(err) => return Err(read_error("reading OpenPGP data", err)),
+        };
+        writer
+            .write_all(&buffer[..read])
+            .map_err(|e| write_error("writing output file", e))?;
+        total += read as u64;
+        index += 1;
+        control.chunk_done(index, read);
+    }
+}
+
+/// 在（可能已解压的）明文包序列中找到字面数据包，把内容写入 writer；first 为已经读出的第一个包头。返回（文件名，明文长度）
+fn write_literal<R: Read, W: Write>(reader: &mut R, mut first: Option<(u8, BodyLength)>, writer: &mut W, control: &JobControl) -> Result<(String, u64), CodedError> {
+    let data_error = |e| read_error("reading OpenPGP data", e);
+    loop {
+        let (tag, length) = match first.take() {
+            Some(header) => header,
+            None => read_packet_header(reader)
+                .map_err(data_error)?
+                .ok_or_else(|| CodedError::bad_format("OpenPGP message contains no literal data"))?,
+        };
+        let mut body = PacketBody::new(&mut *reader, length);
+        match tag {
+            TAG_LITERAL => {
+                let mut head = [0u8; 2];
+                body.read_exact(&mut head).map_err(data_error)?;
+                let mut filename = vec![0u8; head[1] as usize];
+                let mut date = [0u8; 4];
+                body.read_exact(&mut filename).map_err(data_error)?;
+                body.read_exact(&mut date).map_err(data_error)?;
+                let size = copy(&mut body, writer, control)?;
+                return Ok((String::from_utf8_lossy(&filename).into_owned(), size));
+            }
+            TAG_ONE_PASS_SIGNATURE | TAG_SIGNATURE | TAG_MARKER => {
+                io::copy(&mut body, &mut io::sink()).map_err(data_error)?;
+            }
+            TAG_COMPRESSED => return Err(CodedError::unsupported("Nested OpenPGP compressed packets are not supported")),
+            _ => return Err(CodedError::bad_format(format!("Unexpected OpenPGP packet {} in encrypted data", tag))),
+        }
+    }
+}
+
+/// 解密 `gpg -c`（对称加密）生成的二进制 OpenPGP 文件：支持 AES 和带 MDC 的 SEIPD 包，以及 ZIP、ZLIB、BZip2 压缩
+///
+/// MDC 要在读完全部数据后才能校验，出错时调用方应删除已写出的输出文件。签名包会被跳过，不做校验。
+pub fn decrypt_file(input_path: &Target, output_path: &Target, passphrase: &[u8], control: &JobControl) -> Result<GpgReport, CodedError> {
+    let input = fd::open(input_path).map_err(|e| open_error("opening input file", e))?;
+    let encrypted_size = input
+        .metadata()
+        .map_err(|e| read_error("reading input file metadata", e))?
+        .len();
+    let mut reader = BufReader::new(input);
+    let input_error = |e| read_error("reading input file", e);
+
+    // 收集 SEIPD 包之前的所有 SKESK 包
+    let mut key_packets = Vec::new();
+    let (tag, length) = loop {
+        let header = read_packet_header(&mut reader).map_err(|err| {
+            if err.kind() == io::ErrorKind::InvalidData {
+                CodedError::bad_header("Not a binary OpenPGP file (ASCII-armored files must be converted with `gpg --dearmor` first)")
+            } else {
+                input_error(err)
+            }
+        })?;
+        let (tag, length) = header.ok_or_else(|| CodedError::bad_format("OpenPGP file contains no encrypted data"))?;
+        match tag {
+            TAG_SKESK | TAG_PKESK | TAG_MARKER => {
+                let mut packet = Vec::new();
+                PacketBody::new(&mut reader, length)
+                    .read_to_end(&mut packet)
+                    .map_err(input_error)?;
+                if tag == TAG_SKESK {
+                    key_packets.push(packet);
+                }
+            }
+            _ => break (tag, length),
+        }
+    };
+    match tag {
+        TAG_SEIPD => {}
+        TAG_SED => return Err(CodedError::unsupported("OpenPGP data without integrity protection (no MDC) is not supported")),
+        TAG_AEAD => return Err(CodedError::unsupported("OpenPGP AEAD encrypted data is not supported")),
+        _ => return Err(CodedError::bad_header(format!("Not an OpenPGP encrypted file: unexpected packet {} before encrypted data", tag))),
+    }
+    if key_packets.is_empty() {
+        return Err(CodedError::unsupported("OpenPGP file is not passphrase-encrypted (public-key encryption is not supported)"));
+    }
+
+    let mut body = PacketBody::new(&mut reader, length);
+    let mut head = [0u8; 1 + BLOCK_LEN + 2];
+    body.read_exact(&mut head).map_err(input_error)?;
+    if head[0] != 1 {
+        return Err(CodedError::unsupported(format!("Unsupported OpenPGP encrypted data packet version {}", head[0])));
+    }
+
+    // 用随机前缀的最后两个字节的重复做快速校验，找到口令能打开的 SKESK
+    let mut selected = None;
+    let mut last_error = None;
+    for packet in &key_packets {
+        match session_key(packet, passphrase) {
+            Ok((algorithm, key)) => {
+                let mut cfb = CfbDecryptor::new(BlockCipher::new(algorithm, &key)?);
+                let mut prefix = head[1..].to_vec();
+                cfb.decrypt(&mut prefix);
+                if prefix[BLOCK_LEN - 2..BLOCK_LEN] == prefix[BLOCK_LEN..] {
+                    selected = Some((algorithm, cfb, prefix));
+                    break;
+                }
+            }
+            Err(err) => last_error = Some(err),
+        }
+    }
+    let (algorithm, cfb, prefix) = match (selected, last_error) {
+        (Some(selected), _) => selected,
+        (None, Some(err)) if err.code == ErrorCode::Unsupported => return Err(err),
+        (None, _) => return Err(CodedError::auth_failed("OpenPGP decryption failed: wrong passphrase")),
+    };
+
+    let mut seipd = SeipdReader::new(body, cfb, &prefix);
+    let output = fd::create(output_path).map_err(|e| open_error("creating output file", e))?;
+    let mut writer = BufWriter::new(output);
+
+    let (tag, length) = read_packet_header(&mut seipd)
+        .map_err(|e| read_error("reading OpenPGP data", e))?
+        .ok_or_else(|| CodedError::bad_format("OpenPGP encrypted data is empty"))?;
+    let (compression, (filename, plaintext_size)) = if tag == TAG_COMPRESSED {
+        let mut compressed = PacketBody::new(&mut seipd, length);
+        let mut method = [0u8; 1];
+        compressed
+            .read_exact(&mut method)
+            .map_err(|e| read_error("reading OpenPGP data", e))?;
+        let compression = compression_name(method[0])?;
+        let literal = match method[0] {
+            0 => write_literal(&mut compressed, None, &mut writer, control),
+            1 => write_literal(&mut DeflateDecoder::new(compressed), None, &mut writer, control),
+            2 => write_literal(&mut ZlibDecoder::new(compressed), None, &mut writer, control),
+            _ => write_literal(&mut bzip2::read::BzDecoder::new(compressed), None, &mut writer, control),
+        }?;
+        (compression, literal)
+    } else {
+        (compression_name(0)?, write_literal(&mut seipd, Some((tag, length)), &mut writer, control)?)
+    };
+
+    // 读完剩余的数据（例如签名包），直到 MDC 校验完成
+    io::copy(&mut seipd, &mut io::sink()).map_err(|e| read_error("reading OpenPGP data", e))?;
+    if !seipd.verified {
+        return Err(CodedError::auth_failed("OpenPGP integrity check failed: missing modification detection code"));
+    }
+    writer
+        .into_inner()
+        .map_err(|e| write_error("writing output file", e.into_error()))?
+        .sync_output()
+        .map_err(|e| write_error("flushing output file", e))?;
+    Ok(GpgReport { plaintext_size, encrypted_size, filename, cipher: algorithm.as_str(), compression })
+}
+
+/// OpenPGP AEAD 消息的格式
+#[derive(Clone, Copy, PartialEq)]
+pub enum AeadProfile {
+    /// RFC 9580：v6 SKESK 和 v2 SEIPD 包，Sequoia、OpenPGP.js 6、RNP 等实现支持
+    Rfc9580,
+    /// LibrePGP（rfc4880bis）：v5 SKESK 和 OCB 加密数据包（tag 20），GnuPG 2.3 及以上版本支持
+    LibrePgp,
+}
+
+impl AeadProfile {
+    pub fn parse(name: &str) -> Result<Self, CodedError> {
+        match name {
+            "rfc9580" => Ok(AeadProfile::Rfc9580),
+            "librepgp" => Ok(AeadProfile::LibrePgp),
+            _ => Err(CodedError::unsupported(format!("Unsupported OpenPGP profile: {}, supported: rfc9580, librepgp", name))),
+        }
+    }
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            AeadProfile::Rfc9580 => "rfc9580",
+            AeadProfile::LibrePgp => "librepgp",
+        }
+    }
+}
+
+/// AEAD 算法（RFC 9580 9.6）
+#[derive(Clone, Copy, PartialEq)]
+pub enum AeadAlgorithm {
+    Ocb,
+    Gcm,
+}
+
+impl AeadAlgorithm {
+    pub fn parse(name: &str) -> Result<Self, CodedError> {
+        match name {
+            "ocb" => Ok(AeadAlgorithm::Ocb),
+            "gcm" => Ok(AeadAlgorithm::Gcm),
+            _ => Err(CodedError::unsupported(format!("Unsupported OpenPGP AEAD algorithm: {}, supported: ocb, gcm", name))),
+        }
+    }
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            AeadAlgorithm::Ocb => "ocb",
+            AeadAlgorithm::Gcm => "gcm",
+        }
+    }
+
+    fn id(&self) -> u8 {
+        match self {
+            AeadAlgorithm::Ocb => 2,
+            AeadAlgorithm::Gcm => 3,
+        }
+    }
+
+    fn nonce_len(&self) -> usize {
+        match self {
+            AeadAlgorithm::Ocb => 15,
+            AeadAlgorithm::Gcm => 12,
+        }
+    }
+}
+
+/// 写出 AEAD 加密消息的参数
+pub struct AeadSettings {
+    pub profile: AeadProfile,
+    pub cipher: SymmetricAlgorithm,
+    pub aead: AeadAlgorithm,
+}
+
+/// 新格式包头的最高两位
+const NEW_FORMAT_HEADER: u8 = 0xc0;
+/// 加密数据按 2^(CHUNK_SIZE_OCTET + 6) = 256 KiB 明文分块认证
+const CHUNK_SIZE_OCTET: u8 = 12;
+/// SEIPD v2 包中 HKDF 使用的盐的长度
+const SEIPD_SALT_LEN: usize = 32;
+/// 迭代加盐 S2K 的编码次数：65011712 字节，OpenPGP 能表示的最大值
+const S2K_CODED_COUNT: u8 = 0xff;
+const S2K_SALT_LEN: usize = 8;
+/// 写出分段长度时每个分段的长度（2^16）
+const PARTIAL_LEN_EXPONENT: u8 = 16;
+
+enum AeadCipher {
+    Ocb(Box<AesOcb>),
+    Gcm(AesGcm),
+}
+
+impl AeadCipher {
+    fn new(algorithm: AeadAlgorithm, key: &[u8]) -> Result<Self, CodedError> {
+        Ok(match algorithm {
+            AeadAlgorithm::Ocb => AeadCipher::Ocb(Box::new(AesOcb::new(key)?)),
+            AeadAlgorithm::Gcm => AeadCipher::Gcm(AesGcm::new(key)?),
+        })
+    }
+
+    fn seal(&self, nonce: &[u8], aad: &[u8], plaintext: &[u8]) -> Result<Vec<u8>, CodedError> {
+        match self {
+            AeadCipher::Ocb(cipher) => cipher.seal(nonce, aad, plaintext),
+            AeadCipher::Gcm(cipher) => cipher.seal(nonce, aad, plaintext),
+        }
+    }
+}
+
+/// 新格式的包长度
+fn new_length(len: usize) -> Vec<u8> {
+    match len {
+        0..=191 => vec![len as u8],
+        192..=8383 => vec![(((len - 192) >> 8) + 192) as u8, (len - 192) as u8],
+        _ => {
+            let mut bytes = vec![0xff];
+            bytes.extend_from_slice(&(len as u32).to_be_bytes());
+            bytes
+        }
+    }
+}
+
+/// 以分段长度写出包体：缓存满 64 KiB 后写出一个分段，结束时以普通长度写出剩余部分，不需要预先知道包的长度
+struct PartialBodyWriter<W: Write> {
+    inner: W,
+    tag: u8,
+    buffer: Vec<u8>,
+    started: bool,
+}
+
+impl<W: Write> PartialBodyWriter<W> {
+    fn new(inner: W, tag: u8) -> Self {
+        PartialBodyWriter { inner, tag, buffer: Vec::new(), started: false }
+    }
+
+    fn write_header(&mut self) -> io::Result<()> {
+        if !self.started {
+            self.inner.write_all(&[NEW_FORMAT_HEADER | self.tag])?;
+            self.started = true;
+        }
+        Ok(())
+    }
+
+    fn finish(mut self) -> io::Result<W> {
+        self.write_header()?;
+        self.inner.write_all(&new_length(self.buffer.len()))?;
+        self.inner.write_all(&self.buffer)?;
+        Ok(self.inner)
+    }
+}
+
+impl<W: Write> Write for PartialBodyWriter<W> {
+    fn write(&mut self, data: &[u8]) -> io::Result<usize> {
+        let part_len = 1usize << PARTIAL_LEN_EXPONENT;
+        self.buffer.extend_from_slice(data);
+        if self.buffer.len() > part_len {
+            let mut offset = 0;
+            while self.buffer.len() - offset > part_len {
+                self.write_header()?;
+                self.inner.write_all(&[0xe0 | PARTIAL_LEN_EXPONENT])?;
+                self.inner.write_all(&self.buffer[offset..offset + part_len])?;
+                offset += part_len;
+            }
+            self.buffer.drain(..offset);
+        }
+        Ok(data.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+/// 分块 AEAD 加密的参数：每块的 nonce 和附加认证数据由格式决定
+struct ChunkScheme {
+    cipher: AeadCipher,
+    /// nonce 的前缀（RFC 9580，后接 8 字节块序号）或起始 IV（LibrePGP，最后 8 字节与块序号异或）
+    iv: Vec<u8>,
+    /// 包的前 5 个字节形式的附加认证数据（包头、版本、算法、AEAD 算法、分块大小）
+    aad_prefix: [u8; 5],
+    /// LibrePGP 在每块的附加认证数据中加入块序号
+    index_in_aad: bool,
+}
+
+impl ChunkScheme {
+    fn nonce(&self, index: u64) -> Vec<u8> {
+        let mut nonce = self.iv.clone();
+        if self.index_in_aad {
+            let tail = nonce.len() - 8;
+            nonce[tail..].iter_mut().zip(index.to_be_bytes()).for_each(|(byte, index)| *byte ^= index);
+        } else {
+            nonce.extend_from_slice(&index.to_be_bytes());
+        }
+        nonce
+    }
+
+    fn aad(&self, index: u64, total: Option<u64>) -> Vec<u8> {
+        let mut aad = self.aad_prefix.to_vec();
+        if self.index_in_aad {
+            aad.extend_from_slice(&index.to_be_bytes());
+        }
+        if let Some(total) = total {
+            aad.extend_from_slice(&total.to_be_bytes());
+        }
+        aad
+    }
+
+    fn seal_chunk(&self, index: u64, plaintext: &[u8]) -> Result<Vec<u8>, CodedError> {
+        self.cipher.seal(&self.nonce(index), &self.aad(index, None), plaintext)
+    }
+}
+
+/// 按块加密写入的数据，每块为 `密文 || 标签`，结束时写出覆盖明文总长度的最终标签
+struct AeadChunkWriter<W: Write> {
+    inner: W,
+    scheme: ChunkScheme,
+    chunk_len: usize,
+    buffer: Vec<u8>,
+    index: u64,
+    total: u64,
+}
+
+impl<W: Write> AeadChunkWriter<W> {
+    fn seal_chunk(&mut self, len: usize) -> io::Result<()> {
+        let sealed = self
+            .scheme
+            .seal_chunk(self.index, &self.buffer[..len])
+            .map_err(io::Error::other)?;
+        self.inner.write_all(&sealed)?;
+        self.buffer.drain(..len);
+        self.index += 1;
+        self.total += len as u64;
+        Ok(())
+    }
+
+    fn finish(mut self) -> io::Result<W> {
+        if !self.buffer.is_empty() {
+            self.seal_chunk(self.buffer.len())?;
+        }
+        let tag = self
+            .scheme
+            .cipher
+            .seal(&self.scheme.nonce(self.index), &self.scheme.aad(self.index, Some(self.total)), &[])
+            .map_err(io::Error::other)?;
+        self.inner.write_all(&tag)?;
+        Ok(self.inner)
+    }
+}
+
+impl<W: Write> Write for AeadChunkWriter<W> {
+    fn write(&mut self, data: &[u8]) -> io::Result<usize> {
+        self.buffer.extend_from_slice(data);
+        while self.buffer.len() > self.chunk_len {
+            self.seal_chunk(self.chunk_len)?;
+        }
+        Ok(data.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+fn random_bytes(len: usize) -> Vec<u8> {
+    let mut bytes = vec![0u8; len];
+    rand::thread_rng().fill_bytes(&mut bytes);
+    bytes
+}
+
+/// 写出 OpenPGP AEAD 加密消息（二进制）：SKESK 包加上包含字面数据包的 AEAD 加密数据包，口令经迭代加盐的 SHA-256 S2K 派生密钥。
+/// `literal_header` 是字面数据包中明文之前的部分，会话密钥、盐和 IV 由 `random` 生成，`write_plaintext` 写入明文并返回其长度
+fn write_message<W: Write>(mut writer: W, passphrase: &[u8], settings: &AeadSettings, literal_header: &[u8], mut random: impl FnMut(usize) -> Vec<u8>, write_plaintext: impl FnOnce(&mut dyn Write) -> Result<u64, CodedError>) -> Result<(W, u64), CodedError> {
+    let (cipher, aead) = (settings.cipher, settings.aead);
+    let key_len = cipher.key_len();
+    let session_key = random(key_len);
+    let s2k = S2k { hash: S2kHash::Sha256, salt: random(S2K_SALT_LEN), count: (16 + (S2K_CODED_COUNT as usize & 15)) << ((S2K_CODED_COUNT as usize >> 4) + 6) };
+    let mut s2k_specifier = vec![3, 8];
+    s2k_specifier.extend_from_slice(&s2k.salt);
+    s2k_specifier.push(S2K_CODED_COUNT);
+    let s2k_key = s2k.derive(passphrase, key_len);
+
+    // SKESK：用 S2K 派生的密钥以 AEAD 加密会话密钥
+    let version = match settings.profile {
+        AeadProfile::Rfc9580 => 6,
+        AeadProfile::LibrePgp => 5,
+    };
+    let skesk_aad = [NEW_FORMAT_HEADER | TAG_SKESK, version, cipher.id(), aead.id()];
+    let kek = match settings.profile {
+        AeadProfile::Rfc9580 => {
+            let mut kek = vec![0u8; key_len];
+            Hkdf::<Sha256>::new(None, &s2k_key)
+                .expand(&skesk_aad, &mut kek)
+                .map_err(|_| CodedError::unknown("HKDF expansion failed"))?;
+            kek
+        }
+        AeadProfile::LibrePgp => s2k_key,
+    };
+    let skesk_iv = random(aead.nonce_len());
+    let encrypted_session_key = AeadCipher::new(aead, &kek)?.seal(&skesk_iv, &skesk_aad, &session_key)?;
+    let mut skesk = vec![version];
+    if settings.profile == AeadProfile::Rfc9580 {
+        skesk.push((3 + s2k_specifier.len() + skesk_iv.len()) as u8);
+    }
+    skesk.extend_from_slice(&[cipher.id(), aead.id()]);
+    if settings.profile == AeadProfile::Rfc9580 {
+        skesk.push(s2k_specifier.len() as u8);
+    }
+    skesk.extend_from_slice(&s2k_specifier);
+    skesk.extend_from_slice(&skesk_iv);
+    skesk.extend_from_slice(&encrypted_session_key);
+
+    // 加密数据包的头部和分块参数
+    let (data_tag, data_header, scheme) = match settings.profile {
+        AeadProfile::Rfc9580 => {
+            let aad_prefix = [NEW_FORMAT_HEADER | TAG_SEIPD, 2, cipher.id(), aead.id(), CHUNK_SIZE_OCTET];
+            let salt = random(SEIPD_SALT_LEN);
+            let mut derived = vec![0u8; key_len + aead.nonce_len() - 8];
+            Hkdf::<Sha256>::new(Some(&salt), &session_key)
+                .expand(&aad_prefix, &mut derived)
+                .map_err(|_| CodedError::unknown("HKDF expansion failed"))?;
+            let mut header = aad_prefix[1..].to_vec();
+            header.extend_from_slice(&salt);
+            let scheme = ChunkScheme { cipher: AeadCipher::new(aead, &derived[..key_len])?, iv: derived[key_len..].to_vec(), aad_prefix, index_in_aad: false };
+            (TAG_SEIPD, header, scheme)
+        }
+        AeadProfile::LibrePgp => {
+            let aad_prefix = [NEW_FORMAT_HEADER | TAG_AEAD, 1, cipher.id(), aead.id(), CHUNK_SIZE_OCTET];
+            let iv = random(aead.nonce_len());
+            let mut header = aad_prefix[1..].to_vec();
+            header.extend_from_slice(&iv);
+            let scheme = ChunkScheme { cipher: AeadCipher::new(aead, &session_key)?, iv, aad_prefix, index_in_aad: true };
+            (TAG_AEAD, header, scheme)
+        }
+    };
+
+    let output_error = |e| write_error("writing output file", e);
+    writer.write_all(&[NEW_FORMAT_HEADER | TAG_SKESK]).map_err(output_error)?;
+    writer.write_all(&new_length(skesk.len())).map_err(output_error)?;
+    writer.write_all(&skesk).map_err(output_error)?;
+
+    let mut encrypted = PartialBodyWriter::new(writer, data_tag);
+    encrypted.write_all(&data_header).map_err(output_error)?;
+    let chunks = AeadChunkWriter { inner: encrypted, scheme, chunk_len: 1 << (CHUNK_SIZE_OCTET + 6), buffer: Vec::new(), index: 0, total: 0 };
+    let mut literal = PartialBodyWriter::new(chunks, TAG_LITERAL);
+    literal.write_all(literal_header).map_err(output_error)?;
+    let plaintext_size = write_plaintext(&mut literal)?;
+    let writer = literal
+        .finish()
+        .and_then(AeadChunkWriter::finish)
+        .and_then(PartialBodyWriter::finish)
+        .map_err(output_error)?;
+    Ok((writer, plaintext_size))
+}
+
+/// 以口令加密文件，写出 OpenPGP AEAD 加密消息
+pub fn encrypt_file(input_path: &Target, output_path: &Target, passphrase: &[u8], settings: &AeadSettings, control: &JobControl) -> Result<GpgReport, CodedError> {
+    if settings.profile == AeadProfile::LibrePgp && settings.aead != AeadAlgorithm::Ocb {
+        return Err(CodedError::invalid_argument("The LibrePGP profile only supports the ocb AEAD algorithm"));
+    }
+    let mut input = fd::open(input_path).map_err(|e| open_error("opening input file", e))?;
+    let metadata = input
+        .metadata()
+        .map_err(|e| read_error("reading input file metadata", e))?;
+    let filename = input_path
+        .as_path()
+        .and_then(|path| Path::new(path).file_name())
+        .map(|name| name.to_string_lossy().into_owned())
+        .unwrap_or_default();
+    let mut literal_name = filename.as_bytes().to_vec();
+    literal_name.truncate(u8::MAX as usize);
+    let modified = metadata
+        .modified()
+        .ok()
+        .and_then(|modified| modified.duration_since(UNIX_EPOCH).ok())
+        .map_or(0, |duration| duration.as_secs().min(u32::MAX as u64) as u32);
+    let mut literal_header = vec![b'b', literal_name.len() as u8];
+    literal_header.extend_from_slice(&literal_name);
+    literal_header.extend_from_slice(&modified.to_be_bytes());
+
+    let output = fd::create(output_path).map_err(|e| open_error("creating output file", e))?;
+    let (writer, plaintext_size) = write_message(BufWriter::new(output), passphrase, settings, &literal_header, random_bytes, |mut literal| copy(&mut input, &mut literal, control))?;
+    let output = writer
+        .into_inner()
+        .map_err(|e| write_error("writing output file", e.into_error()))?;
+    output.sync_output().map_err(|e| write_error("flushing output file", e))?;
+    let encrypted_size = output
+        .metadata()
+        .map_err(|e| read_error("reading output file metadata", e))?
+        .len();
+    Ok(GpgReport { plaintext_size, encrypted_size, filename, cipher: settings.cipher.as_str(), compression: compression_name(0)? })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// 第 n 次调用得到 n * 0x11 重复的字节，使输出可以复现
+    fn fixed_random() -> impl FnMut(usize) -> Vec<u8> {
+        let mut calls = 0u8;
+        move |len| {
+            calls += 1;
+            vec![calls * 0x11; len]
+        }
+    }
+
+    /// 期望的 SHA-256 由按 RFC 9580 和 LibrePGP 草案独立编写的 Python 实现生成（S2K、HKDF、OCB/GCM 来自 hashlib 与 cryptography）；
+    /// 本机的 GnuPG 2.2 还不支持 AEAD 消息。明文为 300000 字节，跨越两个分块和多个分段长度
+    #[test]
+    fn writes_messages_like_independent_implementation() {
+        let data: Vec<u8> = (0..300000).map(|i| (i % 251) as u8).collect();
+        let mut literal_header = vec![b'b', 8];
+        literal_header.extend_from_slice(b"data.bin");
+        literal_header.extend_from_slice(&0x5f5e1000u32.to_be_bytes());
+        let cases = [
+            (AeadProfile::Rfc9580, SymmetricAlgorithm::Aes256, AeadAlgorithm::Ocb, 300199, "4094c3dcd9aabd46d14071cd0f86fbd37306beb2930b17ebf6ea43b05f9e06e4"),
+            (AeadProfile::Rfc9580, SymmetricAlgorithm::Aes128, AeadAlgorithm::Gcm, 300180, "fac3b1357e7238a330845cab9fa0dfe40021f756e9d8c4fe7f494acfd36ebd15"),
+            (AeadProfile::LibrePgp, SymmetricAlgorithm::Aes128, AeadAlgorithm::Ocb, 300164, "50ab443015709bbe7ce600d9122c8a9733a1fa8f23754bbca15e6af1ed1f86a7"),
+        ];
+        for (profile, cipher, aead, len, expected) in cases {
+            let settings = AeadSettings { profile, cipher, aead };
+            let (message, plaintext_size) = write_message(Vec::new(), b"correct horse", &settings, &literal_header, fixed_random(), |literal| {
+                literal.write_all(&data).map_err(|e| write_error("writing output file", e))?;
+                Ok(data.len() as u64)
+            })
+            .unwrap();
+            assert_eq!(plaintext_size, data.len() as u64);
+            assert_eq!(message.len(), len);
+            assert_eq!(hex::encode(Sha256::digest(&message)), expected);
+        }
+    }
+}
@@ -0,0 +1,155 @@
+use std::time::{Duration, Instant};
+
+use crate::crypto::{self, ChunkAuthenticator, CryptoAlgorithm, HashAlgorithm, PlaintextHasher};
+use crate::errors::CodedError;
+
+/// 运行时检测到的 CPU 加密加速指令
+pub struct CpuFeatures {
+    /// x86 AES-NI
+    pub aes_ni: bool,
+    /// x86 PCLMULQDQ（无进位乘法，用于 GHASH）
+    pub pclmul: bool,
+    /// ARM NEON
+    pub neon: bool,
+    /// ARMv8 AES 指令（Cryptography Extension）
+    pub arm_aes: bool,
+}
+
+pub fn cpu_features() -> CpuFeatures {
+    #[cfg(any(target_arch = "x86", target_arch = "x86_64"))]
+    {
+        CpuFeatures {
+            aes_ni: std::arch::is_x86_feature_detected!("aes"),
+            pclmul: std::arch::is_x86_feature_detected!("pclmulqdq"),
+            neon: false,
+            arm_aes: false,
+        }
+    }
+    #[cfg(target_arch = "aarch64")]
+    {
+        CpuFeatures {
+            aes_ni: false,
+            pclmul: false,
+            neon: std::arch::is_aarch64_feature_detected!("neon"),
+            arm_aes: std::arch::is_aarch64_feature_detected!("aes"),
+        }
+    }
+    #[cfg(not(any(target_arch = "x86", target_arch = "x86_64", target_arch = "aarch64")))]
+    {
+        CpuFeatures { aes_ni: false, pclmul: false, neon: false, arm_aes: false }
+    }
+}
+
+/// 本库的 AES 实现是否使用硬件指令：aes crate 在 x86 上运行时检测 AES-NI；
+/// aarch64 上只有以 `--cfg aes_armv8` 编译时（见 .cargo/config.toml）才运行时检测并使用 ARMv8 AES 指令，否则为软件实现
+pub fn aes_accelerated() -> bool {
+    let features = cpu_features();
+    features.aes_ni || (cfg!(aes_armv8) && features.arm_aes)
+}
+
+/// algorithm 为 "auto" 时选择的算法：总是首选 ChaCha20Poly1305。"aes" 是 AES-256-CBC，单块加密时没有认证，
+/// 即使有硬件加速也不作为默认；只有 allowed 不允许 ChaCha20Poly1305 而允许 AES 时才选 AES
+pub fn auto_algorithm(allowed: impl Fn(&CryptoAlgorithm) -> bool) -> CryptoAlgorithm {
+    match !allowed(&CryptoAlgorithm::Chacha20Poly1305) && allowed(&CryptoAlgorithm::Aes) {
+        true => CryptoAlgorithm::Aes,
+        false => CryptoAlgorithm::Chacha20Poly1305,
+    }
+}
+
+/// 一项吞吐量测量的结果
+pub struct Throughput {
+    pub iterations: u64,
+    /// 每秒处理的数据量，MB 按 1024 × 1024 字节计算
+    pub mb_per_sec: f64,
+}
+
+/// 测量分片帧的加密和解密吞吐量（与分片加密时每个分片的处理相同，不含文件读写和压缩），返回（加密，解密）
+pub fn benchmark_cipher(algorithm: &CryptoAlgorithm, size: usize, duration: Duration) -> Result<(Throughput, Throughput), CodedError> {
+    let key = crypto::random_bytes(32);
+    let data = crypto::random_bytes(size);
+    // 不绑定分片序号，同一个分片帧可以反复解密
+    let mut encryptor = ChunkAuthenticator::new(algorithm.clone(), &key, &[], None)?;
+    let mut frame = Vec::new();
+    let encrypt = measure(size, duration, || encryptor.encrypt_chunk(&data, &mut frame))?;
+    let mut decryptor = ChunkAuthenticator::new(algorithm.clone(), &key, &[], None)?;
+    let mut plaintext = Vec::new();
+    let decrypt = measure(size, duration, || decryptor.decrypt_chunk(&frame, &mut plaintext))?;
+    Ok((encrypt, decrypt))
+}
+
+/// 测量明文摘要的吞吐量
+pub fn benchmark_hash(algorithm: HashAlgorithm, size: usize, duration: Duration) -> Result<Throughput, CodedError> {
+    let data = crypto::random_bytes(size);
+    measure(size, duration, || {
+        PlaintextHasher::digest(algorithm, &data);
+        Ok(())
+    })
+}
+
+/// 反复执行 op（每次处理 size 字节）直到经过 duration，至少执行一次；先执行一次预热，不计入结果
+fn measure(size: usize, duration: Duration, mut op: impl FnMut() -> Result<(), CodedError>) -> Result<Throughput, CodedError> {
+    op()?;
+    let started = Instant::now();
+    let mut iterations = 0u64;
+    loop {
+        op()?;
+        iterations += 1;
+        if started.elapsed() >= duration {
+            break;
+        }
+    }
+    let seconds = started.elapsed().as_secs_f64();
+    Ok(Throughput {
+        iterations,
+        mb_per_sec: (iterations * size as u64) as f64 / (1024.0 * 1024.0) / seconds,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn auto_prefers_chacha20_poly1305() {
+        assert!(matches!(auto_algorithm(|_| true), CryptoAlgorithm::Chacha20Poly1305));
+        assert!(matches!(auto_algorithm(|algorithm| matches!(algorithm, CryptoAlgorithm::Aes)), CryptoAlgorithm::Aes));
+        // 两者都不允许时仍返回默认算法，由调用方按配置报错
+        assert!(matches!(auto_algorithm(|_| false), CryptoAlgorithm::Chacha20Poly1305));
+    }
+
+    #[test]
+    fn reports_features_for_the_current_architecture() {
+        let features = cpu_features();
+        if cfg!(any(target_arch = "x86", target_arch = "x86_64")) {
+            assert!(!features.neon && !features.arm_aes);
+        } else {
+            assert!(!features.aes_ni && !features.pclmul);
+        }
+        assert_eq!(aes_accelerated(), features.aes_ni || (cfg!(aes_armv8) && features.arm_aes));
+    }
+
+    #[test]
+    fn measures_cipher_and_hash_throughput() {
+        for algorithm in [CryptoAlgorithm::Aes, CryptoAlgorithm::Chacha20Poly1305] {
+            let (encrypt, decrypt) = benchmark_cipher(&algorithm, 4096, Duration::from_millis(5)).unwrap();
+            assert!(encrypt.iterations >= 1 && decrypt.iterations >= 1);
+            assert!(encrypt.mb_per_sec > 0.0 && decrypt.mb_per_sec.is_finite());
+        }
+        let hash = benchmark_hash(HashAlgorithm::Sha256, 4096, Duration::from_millis(5)).unwrap();
+        assert!(hash.iterations >= 1 && hash.mb_per_sec > 0.0);
+    }
+
+    #[test]
+    fn measure_runs_at_least_once_after_warming_up() {
+        let mut calls = 0;
+        let throughput = measure(1024 * 1024, Duration::ZERO, || {
+            calls += 1;
+            Ok(())
+        })
+        .unwrap();
+        assert_eq!((calls, throughput.iterations), (2, 1));
+
+        let err = measure(1, Duration::from_secs(10), || Err(CodedError::unsupported("no"))).err().unwrap();
+        assert_eq!(err.code, crate::errors::ErrorCode::Unsupported);
+    }
+}
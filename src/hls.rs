@@ -0,0 +1,196 @@
+use std::io::{BufWriter, Write};
+
+use aes::Aes128;
+use cbc::cipher::block_padding::{NoPadding, Pkcs7};
+use cbc::cipher::{BlockEncryptMut, KeyIvInit};
+
+use crate::errors::{open_error, read_error, write_error, CodedError};
+use crate::fd::{self, SyncOutput, Target};
+use crate::pipeline;
+
+type Aes128CbcEnc = cbc::Encryptor<Aes128>;
+
+/// HLS `METHOD=AES-128` 使用的密钥长度，密钥文件就是这 16 个字节
+pub const KEY_LEN: usize = 16;
+pub const IV_LEN: usize = 16;
+const BLOCK_LEN: usize = 16;
+
+/// 复制数据时每次读取的长度（分组长度的整数倍），每读完一块检查一次超时并发送 chunkDone 事件
+const COPY_BUFFER_LEN: usize = 1024 * 1024;
+
+pub struct HlsReport {
+    pub plaintext_size: u64,
+    pub encrypted_size: u64,
+}
+
+/// 检查 AES-128 密钥的长度
+pub fn key_from_slice(key: &[u8]) -> Result<[u8; KEY_LEN], CodedError> {
+    key.try_into()
+        .map_err(|_| CodedError::invalid_key_length(format!("HLS AES-128 key must be {} bytes, got {}", KEY_LEN, key.len())))
+}
+
+/// 播放列表没有 IV 属性时，播放器以分段的媒体序号（128 位大端整数）作为 IV
+pub fn iv_from_sequence(sequence: u64) -> [u8; IV_LEN] {
+    let mut iv = [0u8; IV_LEN];
+    iv[8..].copy_from_slice(&sequence.to_be_bytes());
+    iv
+}
+
+/// 生成 `#EXT-X-KEY` 标签；iv 为 None 时不写 IV 属性，播放器使用媒体序号，同一行可以用于后续所有分段
+pub fn key_line(uri: &str, iv: Option<&[u8; IV_LEN]>) -> Result<String, CodedError> {
+    if uri.is_empty() || uri.contains(['"', '\r', '\n']) {
+        return Err(CodedError::invalid_argument(format!("Invalid HLS key URI: \"{}\"", uri)));
+    }
+    let mut line = format!("#EXT-X-KEY:METHOD=AES-128,URI=\"{}\"", uri);
+    if let Some(iv) = iv {
+        line.push_str(",IV=0x");
+        line.push_str(&hex::encode_upper(iv));
+    }
+    Ok(line)
+}
+
+/// 按 HLS 规范（RFC 8216 的 AES-128 方法）加密一个 MPEG-TS 或 fMP4 分段：整个分段以 AES-128-CBC 和 PKCS#7 填充加密，
+/// 不写入任何文件头，输出可以直接由播放器或 `ffmpeg` 按 `#EXT-X-KEY` 解密
+pub fn encrypt_segment(input_path: &Target, output_path: &Target, key: &[u8; KEY_LEN], iv: &[u8; IV_LEN], check: impl Fn() -> Result<(), CodedError>, mut chunk_done: impl FnMut(u32, usize)) -> Result<HlsReport, CodedError> {
+    let mut input = fd::open(input_path).map_err(|e| open_error("opening input file", e))?;
+    let output = fd::create(output_path).map_err(|e| open_error("creating output file", e))?;
+    let mut writer = BufWriter::new(output);
+
+    // 整块部分逐段以 CBC 加密，下一段的 IV 为上一段最后一个密文分组；最后不足一块的部分连同 PKCS#7 填充一起加密
+    let mut iv = *iv;
+    let mut buffer = vec![0u8; COPY_BUFFER_LEN];
+    let mut plaintext_size = 0u64;
+    let mut index = 0u32;
+    let tail = loop {
+        check()?;
+        let read = pipeline::read_full(&mut input, &mut buffer).map_err(|err| read_error("reading input file", err))?;
+        plaintext_size += read as u64;
+        if read < buffer.len() {
+            break read;
+        }
+        let cipher = Aes128CbcEnc::new_from_slices(key, &iv)
+            .map_err(|e| CodedError::unknown(format!("AES cipher init failed: {:?}", e)))?;
+        cipher
+            .encrypt_padded_mut::<NoPadding>(&mut buffer, read)
+            .map_err(|e| CodedError::unknown(format!("AES-128-CBC encryption failed: {:?}", e)))?;
+        iv.copy_from_slice(&buffer[read - BLOCK_LEN..read]);
+        writer
+            .write_all(&buffer[..read])
+            .map_err(|e| write_error("writing output file", e))?;
+        index += 1;
+        chunk_done(index, read);
+    };
+
+    let whole = tail - tail % BLOCK_LEN;
+    if whole > 0 {
+        let cipher = Aes128CbcEnc::new_from_slices(key, &iv)
+            .map_err(|e| CodedError::unknown(format!("AES cipher init failed: {:?}", e)))?;
+        cipher
+            .encrypt_padded_mut::<NoPadding>(&mut buffer, whole)
+            .map_err(|e| CodedError::unknown(format!("AES-128-CBC encryption failed: {:?}", e)))?;
+        iv.copy_from_slice(&buffer[whole - BLOCK_LEN..whole]);
+    }
+    let cipher = Aes128CbcEnc::new_from_slices(key, &iv)
+        .map_err(|e| CodedError::unknown(format!("AES cipher init failed: {:?}", e)))?;
+    let last = cipher.encrypt_padded_vec_mut::<Pkcs7>(&buffer[whole..tail]);
+    writer
+        .write_all(&buffer[..whole])
+        .and_then(|_| writer.write_all(&last))
+        .map_err(|e| write_error("writing output file", e))?;
+    if tail > 0 {
+        index += 1;
+        chunk_done(index, tail);
+    }
+
+    let output = writer
+        .into_inner()
+        .map_err(|e| write_error("writing output file", e.into_error()))?;
+    output.sync_output().map_err(|e| write_error("flushing output file", e))?;
+    let encrypted_size = output
+        .metadata()
+        .map_err(|e| read_error("reading output file metadata", e))?
+        .len();
+    Ok(HlsReport { plaintext_size, encrypted_size })
+}
+
+/// 写出播放列表 `URI` 指向的密钥文件（16 字节原始密钥）
+pub fn write_key_file(path: &str, key: &[u8; KEY_LEN]) -> Result<(), CodedError> {
+    std::fs::write(path, key).map_err(|e| write_error("writing key file", e))
+}
+
+#[cfg(test)]
+mod tests {
+    use std::fs;
+    use std::path::PathBuf;
+
+    use super::*;
+    use crate::errors::ErrorCode;
+
+    struct TempFile(PathBuf);
+
+    impl TempFile {
+        fn new(name: &str, content: &[u8]) -> Self {
+            let path = std::env::temp_dir().join(format!("zippy-hls-{}-{}", std::process::id(), name));
+            fs::write(&path, content).unwrap();
+            TempFile(path)
+        }
+
+        fn target(&self) -> Target {
+            Target::from(&*self.0.to_string_lossy())
+        }
+    }
+
+    impl Drop for TempFile {
+        fn drop(&mut self) {
+            let _ = fs::remove_file(&self.0);
+        }
+    }
+
+    fn encrypt(data: &[u8], key: &[u8; KEY_LEN], iv: &[u8; IV_LEN]) -> (HlsReport, Vec<u8>, Vec<(u32, usize)>) {
+        let input = TempFile::new("in", data);
+        let output = TempFile::new("out", b"");
+        let mut progress = Vec::new();
+        let report = encrypt_segment(&input.target(), &output.target(), key, iv, || Ok(()), |index, bytes| progress.push((index, bytes))).unwrap();
+        (report, fs::read(&output.0).unwrap(), progress)
+    }
+
+    #[test]
+    fn matches_the_nist_cbc_vector() {
+        // NIST SP 800-38A F.2.1 CBC-AES128.Encrypt 的第一个分组，之后是一个完整的 PKCS#7 填充分组
+        let key: [u8; KEY_LEN] = hex::decode("2b7e151628aed2a6abf7158809cf4f3c").unwrap().try_into().unwrap();
+        let iv: [u8; IV_LEN] = hex::decode("000102030405060708090a0b0c0d0e0f").unwrap().try_into().unwrap();
+        let (report, encrypted, _) = encrypt(&hex::decode("6bc1bee22e409f96e93d7e117393172a").unwrap(), &key, &iv);
+        assert_eq!(hex::encode(&encrypted[..16]), "7649abac8119b246cee98e9b12e9197d");
+        assert_eq!((report.plaintext_size, report.encrypted_size), (16, 32));
+    }
+
+    #[test]
+    fn encrypts_like_one_cbc_pass() {
+        // 逐块加密的结果与一次性以 CBC 和 PKCS#7 加密整个分段相同
+        let key = [4u8; KEY_LEN];
+        let iv = iv_from_sequence(7);
+        for len in [0, 15, 16, 17, COPY_BUFFER_LEN, COPY_BUFFER_LEN + 17, 2 * COPY_BUFFER_LEN] {
+            let data: Vec<u8> = (0..len).map(|i| (i % 253) as u8).collect();
+            let (report, encrypted, progress) = encrypt(&data, &key, &iv);
+            let expected = Aes128CbcEnc::new(&key.into(), &iv.into()).encrypt_padded_vec_mut::<Pkcs7>(&data);
+            assert_eq!(encrypted, expected, "length {}", len);
+            assert_eq!(report.encrypted_size as usize, (len / BLOCK_LEN + 1) * BLOCK_LEN);
+            assert_eq!(progress.iter().map(|&(_, bytes)| bytes).sum::<usize>(), len);
+        }
+    }
+
+    #[test]
+    fn builds_key_lines() {
+        assert_eq!(iv_from_sequence(0x0102), [0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 1, 2]);
+        assert_eq!(key_line("key.bin", None).unwrap(), "#EXT-X-KEY:METHOD=AES-128,URI=\"key.bin\"");
+        assert_eq!(
+            key_line("https://example.com/k", Some(&iv_from_sequence(255))).unwrap(),
+            "#EXT-X-KEY:METHOD=AES-128,URI=\"https://example.com/k\",IV=0x000000000000000000000000000000FF"
+        );
+        for uri in ["", "a\"b", "a\nb"] {
+            assert_eq!(key_line(uri, None).unwrap_err().code, ErrorCode::InvalidArgument);
+        }
+        assert!(key_from_slice(&[0; KEY_LEN]).is_ok());
+        assert_eq!(key_from_slice(&[0; 32]).unwrap_err().code, ErrorCode::InvalidKeyLength);
+    }
+}
@@ -0,0 +1,375 @@
+use std::sync::atomic::{AtomicBool, AtomicU32, Ordering};
+use std::sync::{Arc, Condvar, Mutex};
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
+
+use napi::threadsafe_function::{ErrorStrategy, ThreadsafeFunction, ThreadsafeFunctionCallMode};
+use napi_derive::napi;
+
+use crate::errors::{CodedError, ErrorCode};
+
+/// 超时错误码
+pub const ERR_TIMEOUT: &str = "ERR_TIMEOUT";
+/// 通过 AbortSignal 取消的错误码
+pub const ERR_ABORTED: &str = "ERR_ABORTED";
+
+static NEXT_JOB_ID: AtomicU32 = AtomicU32::new(1);
+static NEXT_SUBSCRIPTION_ID: AtomicU32 = AtomicU32::new(1);
+static SUBSCRIBERS: Mutex<Vec<(u32, ThreadsafeFunction<JobEvent, ErrorStrategy::Fatal>)>> = Mutex::new(Vec::new());
+
+/// 任务生命周期事件，通过 events() 订阅
+#[napi(object)]
+#[derive(Clone)]
+pub struct JobEvent {
+    pub job_id: u32,
+    /// queued / started / chunkDone / paused / resumed / finished / failed
+    #[napi(js_name = "type")]
+    pub kind: String,
+    /// 产生事件的操作，例如 "chunkEncryptFile"
+    pub operation: String,
+    /// 事件时间，Unix 毫秒时间戳
+    pub timestamp: f64,
+    pub chunk_index: Option<u32>,
+    /// 本分块处理的明文字节数
+    pub bytes: Option<f64>,
+    pub error: Option<String>,
+}
+
+/// 注册事件订阅者，返回订阅 id
+pub fn subscribe(callback: ThreadsafeFunction<JobEvent, ErrorStrategy::Fatal>) -> u32 {
+    let id = NEXT_SUBSCRIPTION_ID.fetch_add(1, Ordering::Relaxed);
+    SUBSCRIBERS.lock().unwrap().push((id, callback));
+    id
+}
+
+/// 取消订阅，订阅不存在时返回 false
+pub fn unsubscribe(id: u32) -> bool {
+    let mut subscribers = SUBSCRIBERS.lock().unwrap();
+    let before = subscribers.len();
+    subscribers.retain(|(subscription, _)| *subscription != id);
+    subscribers.len() != before
+}
+
+pub fn now_ms() -> f64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|elapsed| elapsed.as_millis() as f64)
+        .unwrap_or(0.0)
+}
+
+/// Job.status() 的返回值
+#[napi(object)]
+pub struct JobStatus {
+    /// idle / queued / running / paused / finished / failed
+    pub state: String,
+    /// 最近一次关联的任务 id，与 events() 中的 jobId 相同
+    pub job_id: Option<u32>,
+    pub operation: Option<String>,
+    /// 已完成的分块数
+    pub chunks_done: u32,
+    /// 已处理的明文字节数
+    pub bytes_processed: f64,
+    pub error: Option<String>,
+}
+
+struct JobProgress {
+    paused: bool,
+    state: &'static str,
+    job_id: Option<u32>,
+    operation: Option<&'static str>,
+    chunks_done: u32,
+    bytes_processed: f64,
+    error: Option<String>,
+}
+
+/// Job 与正在运行的操作共享的状态
+pub struct JobState {
+    progress: Mutex<JobProgress>,
+    resumed: Condvar,
+}
+
+impl JobState {
+    /// 关联新的操作并重置进度，之后只记录该操作的进度
+    fn attach(&self, job_id: Option<u32>, operation: Option<&'static str>) {
+        let mut progress = self.progress.lock().unwrap();
+        progress.state = "queued";
+        progress.job_id = job_id;
+        progress.operation = operation;
+        progress.chunks_done = 0;
+        progress.bytes_processed = 0.0;
+        progress.error = None;
+    }
+
+    fn update<F: FnOnce(&mut JobProgress)>(&self, job_id: Option<u32>, update: F) {
+        let mut progress = self.progress.lock().unwrap();
+        if progress.job_id == job_id {
+            update(&mut progress);
+        }
+    }
+
+    /// 暂停期间阻塞当前工作线程，直到 resume() 或超过截止时间（超时由调用方随后报告），返回是否已恢复
+    ///
+    /// 阻塞的是 libuv 线程池的线程：线程池默认只有 4 个线程，同时暂停的操作达到线程数时，
+    /// 其它 `*Async` 操作以及 Node 自身的 fs、dns、zlib、crypto 异步调用都会排队等待，直到某个操作恢复。
+    fn wait_while_paused(&self, deadline: Option<Instant>) -> bool {
+        let mut progress = self.progress.lock().unwrap();
+        while progress.paused {
+            match deadline {
+                Some(deadline) => {
+                    let now = Instant::now();
+                    if now >= deadline {
+                        return false;
+                    }
+                    progress = self.resumed.wait_timeout(progress, deadline - now).unwrap().0;
+                }
+                None => progress = self.resumed.wait(progress).unwrap(),
+            }
+        }
+        true
+    }
+
+    /// 分块之间的暂停点：已暂停时先通过 emit 发送 paused 事件，等待结束后恢复时发送 resumed 事件（超时则不发送）
+    fn pause_point(&self, deadline: Option<Instant>, mut emit: impl FnMut(&str)) {
+        if !self.progress.lock().unwrap().paused {
+            return;
+        }
+        emit("paused");
+        if self.wait_while_paused(deadline) {
+            emit("resumed");
+        }
+    }
+}
+
+/// 可暂停的任务句柄，作为 `*Async` 版本的最后一个参数传入
+///
+/// pause() 之后操作在下一个分块之间停下，resume() 之后从停下的位置继续，不会重新开始文件；停下和继续时分别发送
+/// paused 和 resumed 事件。暂停的操作保持占用线程池线程，同时暂停的操作占满线程池（默认 4 个线程）后，其它异步操作都会排队。
+/// 同一个 Job 可以传给多个操作，pause() 对所有操作生效，status() 返回最近一次传入的操作的进度。
+#[napi]
+pub struct Job {
+    state: Arc<JobState>,
+}
+
+#[napi]
+impl Job {
+    #[napi(constructor)]
+    #[allow(clippy::new_without_default)]
+    pub fn new() -> Self {
+        let progress = JobProgress {
+            paused: false,
+            state: "idle",
+            job_id: None,
+            operation: None,
+            chunks_done: 0,
+            bytes_processed: 0.0,
+            error: None,
+        };
+        Job { state: Arc::new(JobState { progress: Mutex::new(progress), resumed: Condvar::new() }) }
+    }
+
+    /// 暂停关联的操作，也可以在传入操作之前调用
+    #[napi]
+    pub fn pause(&self) {
+        self.state.progress.lock().unwrap().paused = true;
+    }
+
+    #[napi]
+    pub fn resume(&self) {
+        self.state.progress.lock().unwrap().paused = false;
+        self.state.resumed.notify_all();
+    }
+
+    #[napi]
+    pub fn status(&self) -> JobStatus {
+        let progress = self.state.progress.lock().unwrap();
+        let active = progress.state == "queued" || progress.state == "running";
+        JobStatus {
+            state: if progress.paused && active { "paused" } else { progress.state }.to_string(),
+            job_id: progress.job_id,
+            operation: progress.operation.map(str::to_string),
+            chunks_done: progress.chunks_done,
+            bytes_processed: progress.bytes_processed,
+            error: progress.error.clone(),
+        }
+    }
+
+    /// 供 FileTask 关联到操作的共享状态
+    pub(crate) fn shared(&self) -> Arc<JobState> {
+        self.state.clone()
+    }
+}
+
+/// 关联到不发送事件的操作（FileTask::plain），只记录开始和结束
+pub fn plain_attached(state: &JobState) {
+    state.attach(None, None);
+}
+
+/// 不带 JobControl 的操作开始前等待暂停结束，并记录开始状态
+pub fn plain_started(state: &JobState) {
+    state.wait_while_paused(None);
+    state.update(None, |progress| progress.state = "running");
+}
+
+/// 记录不带 JobControl 的操作的结果
+pub fn plain_finished<T>(state: &JobState, result: &napi::Result<T, ErrorCode>) {
+    state.update(None, |progress| finish(progress, result));
+}
+
+fn finish<T>(progress: &mut JobProgress, result: &napi::Result<T, ErrorCode>) {
+    match result {
+        Ok(_) => progress.state = "finished",
+        Err(err) => {
+            progress.state = "failed";
+            progress.error = Some(err.reason.clone());
+        }
+    }
+}
+
+/// 单个文件操作的运行控制，在每个处理阶段/分块之间检查，并向订阅者发送生命周期事件
+pub struct JobControl {
+    id: u32,
+    operation: &'static str,
+    deadline: Option<Instant>,
+    timeout_ms: u32,
+    aborted: Option<Arc<AtomicBool>>,
+    job: Option<Arc<JobState>>,
+}
+
+impl JobControl {
+    /// 创建任务并分配 id，发送 queued 事件
+    pub fn new(operation: &'static str, timeout_ms: Option<u32>) -> Self {
+        let timeout_ms = timeout_ms.unwrap_or(0);
+        let deadline = if timeout_ms > 0 {
+            Some(Instant::now() + Duration::from_millis(timeout_ms as u64))
+        } else {
+            None
+        };
+        let id = NEXT_JOB_ID.fetch_add(1, Ordering::Relaxed);
+        let control = JobControl { id, operation, deadline, timeout_ms, aborted: None, job: None };
+        control.emit("queued", None, None, None);
+        control
+    }
+
+    /// 关联取消标志，标志被设置后 check() 返回 ERR_ABORTED
+    pub fn set_abort_flag(&mut self, aborted: Arc<AtomicBool>) {
+        self.aborted = Some(aborted);
+    }
+
+    /// 关联 Job，之后 check() 在暂停期间阻塞，进度记录到 Job.status()
+    pub fn attach_job(&mut self, job: Arc<JobState>) {
+        job.attach(Some(self.id), Some(self.operation));
+        self.job = Some(job);
+    }
+
+    /// 超过截止时间时返回以 ERR_TIMEOUT 开头的错误，已被取消时返回以 ERR_ABORTED 开头的错误
+    pub fn check(&self) -> Result<(), CodedError> {
+        if self.aborted.as_ref().is_some_and(|aborted| aborted.load(Ordering::Relaxed)) {
+            return Err(CodedError::aborted(format!("{}: operation was aborted", ERR_ABORTED)));
+        }
+        if let Some(job) = &self.job {
+            job.pause_point(self.deadline, |kind| self.emit(kind, None, None, None));
+        }
+        match self.deadline {
+            Some(deadline) if Instant::now() >= deadline => Err(CodedError::timeout(format!(
+                "{}: operation timed out after {} ms",
+                ERR_TIMEOUT, self.timeout_ms
+            ))),
+            _ => Ok(()),
+        }
+    }
+
+    pub fn started(&self) {
+        self.update_job(|progress| progress.state = "running");
+        self.emit("started", None, None, None);
+    }
+
+    pub fn chunk_done(&self, chunk_index: u32, bytes: usize) {
+        self.update_job(|progress| {
+            progress.chunks_done += 1;
+            progress.bytes_processed += bytes as f64;
+        });
+        self.emit("chunkDone", Some(chunk_index), Some(bytes as f64), None);
+    }
+
+    /// 根据操作结果发送 finished 或 failed 事件，结果原样返回
+    pub fn complete<T>(&self, result: napi::Result<T, ErrorCode>) -> napi::Result<T, ErrorCode> {
+        self.update_job(|progress| finish(progress, &result));
+        match &result {
+            Ok(_) => self.emit("finished", None, None, None),
+            Err(err) => self.emit("failed", None, None, Some(err.reason.clone())),
+        }
+        result
+    }
+
+    fn update_job<F: FnOnce(&mut JobProgress)>(&self, update: F) {
+        if let Some(job) = &self.job {
+            job.update(Some(self.id), update);
+        }
+    }
+
+    fn emit(&self, kind: &str, chunk_index: Option<u32>, bytes: Option<f64>, error: Option<String>) {
+        let subscribers = SUBSCRIBERS.lock().unwrap();
+        if subscribers.is_empty() {
+            return;
+        }
+        let event = JobEvent {
+            job_id: self.id,
+            kind: kind.to_string(),
+            operation: self.operation.to_string(),
+            timestamp: now_ms(),
+            chunk_index,
+            bytes,
+            error,
+        };
+        for (_, callback) in subscribers.iter() {
+            callback.call(event.clone(), ThreadsafeFunctionCallMode::NonBlocking);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::thread;
+
+    use super::*;
+
+    fn job_state(paused: bool) -> Arc<JobState> {
+        let job = Job::new();
+        if paused {
+            job.pause();
+        }
+        job.shared()
+    }
+
+    #[test]
+    fn running_job_passes_the_pause_point_silently() {
+        let state = job_state(false);
+        let mut events = Vec::new();
+        state.pause_point(None, |kind| events.push(kind.to_string()));
+        assert!(events.is_empty());
+    }
+
+    #[test]
+    fn paused_job_waits_for_resume_and_reports_both_events() {
+        let job = Job::new();
+        job.pause();
+        let state = job.shared();
+        let waiter = thread::spawn(move || {
+            let mut events = Vec::new();
+            state.pause_point(None, |kind| events.push(kind.to_string()));
+            events
+        });
+        thread::sleep(Duration::from_millis(50));
+        assert!(!waiter.is_finished());
+        job.resume();
+        assert_eq!(waiter.join().unwrap(), ["paused", "resumed"]);
+    }
+
+    #[test]
+    fn deadline_ends_the_pause_without_a_resumed_event() {
+        let state = job_state(true);
+        let mut events = Vec::new();
+        state.pause_point(Some(Instant::now() + Duration::from_millis(20)), |kind| events.push(kind.to_string()));
+        assert_eq!(events, ["paused"]);
+        assert!(!state.wait_while_paused(Some(Instant::now())));
+    }
+}
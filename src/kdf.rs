@@ -0,0 +1,114 @@
+use crate::errors::CodedError;
+use crate::gocryptfs;
+use crate::openssl::{self, KdfDigest};
+
+/// 盐的最短长度，与 `openssl enc` 的盐相同
+pub const MIN_SALT_LEN: usize = 8;
+/// 默认派生的密钥长度，即 aes 和 chacha20poly1305 的密钥长度
+pub const DEFAULT_KEY_LEN: usize = 32;
+/// 派生密钥的最大长度
+pub const MAX_KEY_LEN: usize = 1024;
+/// scrypt 的默认参数，与 gocryptfsEncryptDirectory() 相同
+pub const DEFAULT_SCRYPT_LOG_N: u8 = gocryptfs::DEFAULT_SCRYPT_LOG_N;
+pub const DEFAULT_SCRYPT_R: u32 = 8;
+pub const DEFAULT_SCRYPT_P: u32 = 1;
+
+/// 由口令派生密钥的算法和参数
+pub enum Kdf {
+    Scrypt { log_n: u8, r: u32, p: u32 },
+    /// PBKDF2 的默认参数与 opensslEncryptFile() 相同
+    Pbkdf2 { digest: KdfDigest, iterations: u32 },
+}
+
+impl Kdf {
+    pub fn scrypt(log_n: Option<u32>, r: Option<u32>, p: Option<u32>) -> Result<Self, CodedError> {
+        let log_n = match log_n {
+            Some(log_n) => u8::try_from(log_n).map_err(|_| CodedError::invalid_argument(format!("Invalid scrypt cost: 2^{}", log_n)))?,
+            None => DEFAULT_SCRYPT_LOG_N,
+        };
+        Ok(Kdf::Scrypt { log_n, r: r.unwrap_or(DEFAULT_SCRYPT_R), p: p.unwrap_or(DEFAULT_SCRYPT_P) })
+    }
+
+    pub fn pbkdf2(digest: Option<&str>, iterations: Option<u32>) -> Result<Self, CodedError> {
+        Ok(Kdf::Pbkdf2 {
+            digest: KdfDigest::parse(digest.unwrap_or("sha256"))?,
+            iterations: iterations.unwrap_or(openssl::DEFAULT_PBKDF2_ITERATIONS),
+        })
+    }
+
+    /// 派生 key_len 字节的密钥；相同的口令、盐和参数总是得到相同的密钥
+    pub fn derive(&self, password: &[u8], salt: &[u8], key_len: usize) -> Result<Vec<u8>, CodedError> {
+        if salt.len() < MIN_SALT_LEN {
+            return Err(CodedError::invalid_argument(format!("Salt must be at least {} bytes", MIN_SALT_LEN)));
+        }
+        if !(1..=MAX_KEY_LEN).contains(&key_len) {
+            return Err(CodedError::invalid_key_length(format!("Key length must be between 1 and {} bytes", MAX_KEY_LEN)));
+        }
+        let mut key = vec![0u8; key_len];
+        match self {
+            Kdf::Scrypt { log_n, r, p } => {
+                // Params 中的长度只用于 PHC 字符串，实际输出长度由 key 决定
+                let params = scrypt::Params::new(*log_n, *r, *p, scrypt::Params::RECOMMENDED_LEN)
+                    .map_err(|_| CodedError::invalid_argument(format!("Invalid scrypt parameters: log2(N) {}, r {}, p {}", log_n, r, p)))?;
+                scrypt::scrypt(password, salt, &params, &mut key).map_err(|_| CodedError::unknown("scrypt key derivation failed"))?;
+            }
+            Kdf::Pbkdf2 { digest, iterations } => digest.pbkdf2(password, salt, *iterations, &mut key)?,
+        }
+        Ok(key)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::errors::ErrorCode;
+
+    #[test]
+    fn matches_the_rfc_7914_scrypt_vector() {
+        let kdf = Kdf::scrypt(Some(14), Some(8), Some(1)).unwrap();
+        let key = kdf.derive(b"pleaseletmein", b"SodiumChloride", 64).unwrap();
+        assert_eq!(
+            hex::encode(key),
+            "7023bdcb3afd7348461c06cd81fd38ebfda8fbba904f8e3ea9b543f6545da1f2d5432955613f0fcf62d49705242a9af9e61e85dc0d651e40dfcf017b45575887"
+        );
+    }
+
+    #[test]
+    fn matches_pbkdf2_vectors() {
+        let password = b"passwordPASSWORDpassword";
+        let salt = b"saltSALTsaltSALTsaltSALTsaltSALTsalt";
+        let kdf = Kdf::pbkdf2(Some("SHA256"), Some(4096)).unwrap();
+        assert_eq!(
+            hex::encode(kdf.derive(password, salt, 40).unwrap()),
+            "348c89dbcbd32b2f32d814b8116e84cf2b17347ebc1800181c4e2a1fb8dd53e1c635518c7dac47e9"
+        );
+        let kdf = Kdf::pbkdf2(Some("sha512"), Some(1000)).unwrap();
+        assert_eq!(hex::encode(kdf.derive(b"password", b"saltsalt", 32).unwrap()), "43abf8c7027c6bd9d63e9d81784a006188474a8db14663d60114f5eef4e94b1b");
+        // 较长的密钥以较短的密钥开头
+        let kdf = Kdf::pbkdf2(None, Some(1)).unwrap();
+        let short = kdf.derive(b"pw", b"12345678", 16).unwrap();
+        assert_eq!(kdf.derive(b"pw", b"12345678", DEFAULT_KEY_LEN).unwrap()[..16], short);
+    }
+
+    #[test]
+    fn uses_default_parameters() {
+        assert!(matches!(Kdf::scrypt(None, None, None).unwrap(), Kdf::Scrypt { log_n: DEFAULT_SCRYPT_LOG_N, r: 8, p: 1 }));
+        assert!(matches!(Kdf::pbkdf2(None, None).unwrap(), Kdf::Pbkdf2 { digest: KdfDigest::Sha256, iterations: openssl::DEFAULT_PBKDF2_ITERATIONS }));
+    }
+
+    #[test]
+    fn rejects_invalid_parameters() {
+        let kdf = Kdf::pbkdf2(None, Some(1)).unwrap();
+        assert_eq!(kdf.derive(b"pw", b"1234567", 32).unwrap_err().code, ErrorCode::InvalidArgument);
+        assert_eq!(kdf.derive(b"pw", b"12345678", 0).unwrap_err().code, ErrorCode::InvalidKeyLength);
+        assert_eq!(kdf.derive(b"pw", b"12345678", MAX_KEY_LEN + 1).unwrap_err().code, ErrorCode::InvalidKeyLength);
+        assert_eq!(Kdf::pbkdf2(None, Some(0)).unwrap().derive(b"pw", b"12345678", 32).unwrap_err().code, ErrorCode::InvalidArgument);
+        assert_eq!(Kdf::pbkdf2(Some("sha1"), None).err().unwrap().code, ErrorCode::Unsupported);
+
+        assert_eq!(Kdf::scrypt(Some(256), None, None).err().unwrap().code, ErrorCode::InvalidArgument);
+        for (log_n, r, p) in [(64, 8, 1), (10, 0, 1), (10, 8, 0)] {
+            let kdf = Kdf::scrypt(Some(log_n), Some(r), Some(p)).unwrap();
+            assert_eq!(kdf.derive(b"pw", b"12345678", 32).unwrap_err().code, ErrorCode::InvalidArgument, "{} {} {}", log_n, r, p);
+        }
+    }
+}
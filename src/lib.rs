@@ -1,100 +1,682 @@
 #![deny(clippy::all)]
 
 use napi::bindgen_prelude::*;
+use napi::threadsafe_function::{ErrorStrategy, ThreadsafeFunction, ThreadsafeFunctionCallMode};
+use napi::{Env, JsFunction, JsObject, JsUnknown};
 use napi_derive::napi;
 use std::fs::File;
-use std::io::{Read, Write, BufReader, BufWriter, Seek};
+use std::io::{self, BufRead, Read, Write, BufReader, BufWriter, Seek, SeekFrom};
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
 use std::path::Path;
 use md5::{Md5, Digest};
 use hex::encode as hex_encode;
+use base64::engine::general_purpose::STANDARD;
+use base64::Engine;
 
+pub mod age_format;
+pub mod append;
+pub mod archive;
+pub mod attributes;
+pub mod batch;
+pub mod cenc;
+pub mod checkpoint;
+pub mod chunked;
+pub mod cms;
+pub mod compression;
+pub mod config;
 pub mod crypto;
+pub mod delta;
+pub mod direct;
+pub mod directory;
+pub mod eme;
+pub mod errors;
+pub mod fat;
+pub mod fd;
+pub mod fernet;
+pub mod format;
+pub mod gcm;
+pub mod glob;
+pub mod gocryptfs;
+pub mod gpg;
+pub mod hardware;
+pub mod hls;
+pub mod job;
+pub mod jwe;
+pub mod kdf;
+pub mod mime;
+pub mod minisign;
+pub mod mmap;
+pub mod ocb;
+pub mod openssl;
+pub mod options;
+pub mod parity;
+pub mod paseto;
+pub mod pipeline;
+pub mod plan;
+pub mod prealloc;
+pub mod rclone;
+pub mod results;
+pub mod rsa;
+pub mod runtime;
+pub mod s3crypto;
+pub mod saltpack;
+pub mod sevenz;
+pub mod shred;
+pub mod sparse;
+pub mod store;
+pub mod stream;
+pub mod task;
+pub mod template;
+pub mod tink;
+pub mod uring;
+pub mod veracrypt;
+pub mod volume;
+pub mod walk;
+pub mod watch;
+pub mod webstream;
+pub mod zip_aes;
 
-use crypto::{encrypt, decrypt, CryptoAlgorithm};
+use attributes::FileAttributes;
+use checkpoint::{CheckpointWriter, InputIdentity};
+use config::LogLevel;
+use errors::{CodedError, ErrorCode};
+use chunked::{ChunkDecoder, ChunkEncoder, ChunkOutcome, CompressionStats, TolerantChunkReader};
+use compression::{Compression, CompressionMethod};
+use crypto::{encrypt, decrypt, CryptoAlgorithm, HashAlgorithm};
+use format::{ChunkedFooter, ContainerHeader, Layout, UserMetadata};
+use job::{Job, JobControl};
+use mmap::{InputData, Mapping};
+use pipeline::{ChunkReadAhead, Prefetch, ReadAhead};
+use task::{AbortSignal, FileTask};
+use options::{AgeDecryptOptions, AgeEncryptOptions, ArchiveEncryptOptions, ArchiveExtractOptions, BatchDecryptEntry, BatchEncryptEntry, BatchOptions, BenchmarkOptions, CencEncryptOptions, CmsEncryptOptions, ConvertOptions, DecryptOptions, DecryptPolicyOptions, DirectoryDecryptOptions, DirectoryEncryptOptions, EncryptOptions, ExtractOptions, FernetDecryptOptions, GocryptfsOptions, GpgDecryptOptions, GpgEncryptOptions, HlsSegmentOptions, JsonValue, JweEncryptOptions, KdfOptions, MinisignKeyOptions, MinisignSignOptions, MinisignVerifyOptions, OpensslOptions, PasetoOptions, RcloneCryptOptions, ReaderOptions, RestoreOptions, S3DecryptOptions, S3EncryptOptions, SaltpackDecryptOptions, SaltpackEncryptOptions, SecretStreamOptions, SevenZipOptions, StoreOptions, TinkStreamingOptions, VeraCryptOptions, VerifyOptions, WatchOptions, ZipOptions};
+use results::{byte_count, AgeDecryptResult, AgeEncryptResult, AgeIdentity, AppendChunkedFileResult, ArchiveEncryptResult, ArchiveEntry, ArchiveListResult, BatchFileResult, BatchResult, BenchmarkMeasurement, BenchmarkResult, CencEncryptResult, ChunkDecryptFileResult, ChunkEncryptFileResult, ChunkReport, ChunkedFileMetadata, ChunkedFooterInfo, CmsEncryptResult, ConvertToChunkedResult, ConvertToMonolithicResult, DamagedChunk, DecryptFileResult, DecryptPolicyResult, DecryptedFile, DirectoryDecryptResult, DirectoryEncryptResult, EncryptFileResult, ExtractResult, FileSizes, GocryptfsResult, GpgDecryptResult, GpgEncryptResult, HardwareInfo, HlsSegmentResult, InspectFileResult, JweDecryptResult, MigrateFileResult, MinisignKeyPair, MinisignSignResult, MinisignVerifyResult, ModuleConfig, PasetoDecryptResult, PlannedFile, RechunkFileResult, RepairFileResult, RestoreFileResult, S3EncryptResult, SaltpackDecryptResult, SaltpackEncryptResult, SaltpackKeyPair, SecretStreamDecryptResult, SecretStreamEncryptResult, SevenZipResult, StoreFileResult, StoredFileEntry, TinkDecryptResult, TinkEncryptResult, UpdateEncryptedFileResult, VeraCryptEntry, VeraCryptExtractResult, VerifyChunkedFileResult, WarmupResult, ZipResult};
+use sparse::HoleMap;
+use uring::RingFile;
+use volume::{VolumeReader, VolumeWriter};
 use std::str::FromStr;
 
-/// 加密文件 - 适用于小到中等大小的文件
-#[napi(js_name = "encryptFile")]
-pub fn encrypt_file(algorithm: String, key: Buffer, input_path: String, output_path: String, env: Env) -> Result<Object> {
-    let algo = CryptoAlgorithm::from_str(&algorithm)
-        .map_err(|_| Error::from_reason("Invalid algorithm".to_string()))?;
+/// 解析算法名称，并检查该算法是否被配置允许；"auto" 的选择见 hardware.rs；首次调用时完成运行环境的初始化
+fn parse_algorithm(algorithm: &str) -> Result<CryptoAlgorithm, ErrorCode> {
+    runtime::ensure_ready()?;
+    if algorithm.eq_ignore_ascii_case("auto") {
+        return Ok(hardware::auto_algorithm(config::algorithm_allowed));
+    }
+    let algo = CryptoAlgorithm::from_str(algorithm)
+        .map_err(|_| Error::new(ErrorCode::InvalidArgument, "Invalid algorithm"))?;
+    if !config::algorithm_allowed(&algo) {
+        return Err(Error::new(ErrorCode::Policy, format!("Algorithm '{}' is not allowed by configuration", algo.as_str())));
+    }
+    Ok(algo)
+}
+
+/// 根据选项确定要保存到文件头的原始文件名
+fn stored_filename(options: &EncryptOptions, input_path: Option<&fd::Target>) -> Result<Option<String>, ErrorCode> {
+    if let Some(name) = &options.filename {
+        return format::sanitize_filename(name).map(Some).map_err(Error::from);
+    }
+    if options.store_filename.unwrap_or(false) {
+        return match input_path.and_then(fd::Target::as_path) {
+            Some(path) => format::file_name_of(path).map(Some).map_err(Error::from),
+            None => Err(Error::new(ErrorCode::InvalidArgument, "storeFilename requires an input path; pass filename for file descriptors")),
+        };
+    }
+    Ok(None)
+}
+
+/// 解析压缩选项，未选择压缩算法时不能指定压缩级别
+fn parse_compression(compression: &Option<String>, level: Option<i32>) -> Result<Option<Compression>, ErrorCode> {
+    match compression {
+        Some(name) => {
+            let method = CompressionMethod::from_str(name)
+                .map_err(|_| Error::new(ErrorCode::InvalidArgument, format!("Invalid compression: {}", name)))?;
+            Compression::new(method, level).map(Some).map_err(Error::from)
+        },
+        None if level.is_some() => Err(Error::new(ErrorCode::InvalidArgument, "compressionLevel requires the compression option")),
+        None => Ok(None),
+    }
+}
+
+/// 根据选项创建 v2 容器文件头，文件名和元数据加密后保存；选择旧格式输出时返回 None。input_path 为 None 表示没有输入文件（内存或流式输入）
+fn build_container_header(options: &EncryptOptions, input_path: Option<&fd::Target>, layout: Layout, algo: &CryptoAlgorithm, key: &[u8], original_size: u64, chunk_size: u64) -> Result<Option<ContainerHeader>, ErrorCode> {
+    let filename = stored_filename(options, input_path)?;
+    let metadata = match &options.metadata {
+        Some(Either::A(buffer)) => Some(UserMetadata::Binary(buffer.to_vec())),
+        Some(Either::B(json)) => Some(UserMetadata::Json(json.0.clone())),
+        None => None,
+    };
+    let hash_algorithm = match &options.plaintext_hash {
+        Some(name) => Some(HashAlgorithm::from_str(name)
+            .map_err(|_| Error::new(ErrorCode::InvalidArgument, format!("Invalid plaintext hash algorithm: {}", name)))?),
+        None => None,
+    };
+    let compression = parse_compression(&options.compression, options.compression_level)?;
+    if let Some(mime_type) = &options.mime_type {
+        mime::check(mime_type)?;
+    }
+    if options.legacy_format.unwrap_or(false) {
+        if filename.is_some() || metadata.is_some() || options.mime_type.is_some() || options.preserve_attributes.unwrap_or(false) {
+            return Err(Error::new(ErrorCode::InvalidArgument, "Stored filenames, metadata, MIME types and file attributes require the container format"));
+        }
+        if hash_algorithm.is_some() {
+            return Err(Error::new(ErrorCode::InvalidArgument, "Plaintext hashes require the container format"));
+        }
+        if compression.is_some() {
+            return Err(Error::new(ErrorCode::InvalidArgument, "Compression requires the container format"));
+        }
+        return Ok(None);
+    }
+    if options.preserve_attributes.unwrap_or(false) && input_path.is_none() {
+        return Err(Error::new(ErrorCode::InvalidArgument, "preserveAttributes requires an input file"));
+    }
     
-    // 读取整个文件内容
-    let mut file = match File::open(&input_path) {
+    let mut header = ContainerHeader::for_new_file(layout, algo.clone(), original_size, chunk_size);
+    header.hash_algorithm = hash_algorithm;
+    header.compression = compression;
+    if let Some(name) = filename {
+        let sealed = crypto::seal_field(key, format::FIELD_FILENAME, name.as_bytes())?;
+        header.encrypted_filename = Some(sealed);
+    }
+    if let Some(metadata) = metadata {
+        let bytes = metadata.to_bytes()?;
+        let sealed = crypto::seal_field(key, format::FIELD_METADATA, &bytes)?;
+        header.encrypted_metadata = Some(sealed);
+    }
+    // 未指定 MIME 类型时根据输入文件开头的字节检测
+    let mime_type = match (&options.mime_type, input_path) {
+        (Some(mime_type), _) => mime_type.as_str(),
+        (None, Some(input_path)) => mime::sniff_file(input_path)?,
+        (None, None) => mime::OCTET_STREAM,
+    };
+    let sealed = crypto::seal_field(key, format::FIELD_MIME_TYPE, mime_type.as_bytes())?;
+    header.encrypted_mime_type = Some(sealed);
+    Ok(Some(header))
+}
+
+/// preserveAttributes 时读取输入文件的属性并加密，由调用方写入文件头；在读取文件内容之前调用，保留原来的访问时间
+fn sealed_attributes(options: &EncryptOptions, input: &File, key: &[u8]) -> Result<Option<Vec<u8>>, ErrorCode> {
+    if !options.preserve_attributes.unwrap_or(false) {
+        return Ok(None);
+    }
+    let attributes = FileAttributes::capture(input)?;
+    crypto::seal_field(key, format::FIELD_ATTRIBUTES, &attributes.to_bytes()).map(Some).map_err(Error::from)
+}
+
+/// 将用户元数据转换为 JS 值：二进制为 Buffer，其它为 JSON 值
+fn metadata_to_js(metadata: Option<UserMetadata>) -> Option<Either<Buffer, serde_json::Value>> {
+    metadata.map(|metadata| match metadata {
+        UserMetadata::Binary(data) => Either::A(Buffer::from(data)),
+        UserMetadata::Json(value) => Either::B(value),
+    })
+}
+
+/// 校验容器文件头：认证标签必须有效，记录的算法必须与调用方指定的一致
+fn verify_header(header: &ContainerHeader, algo: &CryptoAlgorithm, key: &[u8]) -> Result<(), ErrorCode> {
+    header.verify(key)?;
+    if header.algorithm != *algo {
+        return Err(Error::new(ErrorCode::InvalidArgument, format!(
+            "Algorithm mismatch: file was encrypted with {}", header.algorithm.as_str()
+        )));
+    }
+    Ok(())
+}
+
+/// 确定解密输出路径：恢复原始文件名时 output_path 视为输出目录
+fn resolve_output_path(output_path: &fd::Target, filename: &Option<String>, restore_filename: bool) -> Result<fd::Target, ErrorCode> {
+    if !restore_filename {
+        return Ok(output_path.clone());
+    }
+    let Some(output_dir) = output_path.as_path() else {
+        return Err(Error::new(ErrorCode::InvalidArgument, "restoreFilename requires an output directory path, not a file descriptor"));
+    };
+    match filename {
+        Some(name) => Ok(Path::new(output_dir).join(name).to_string_lossy().into_owned().into()),
+        None => Err(Error::new(ErrorCode::InvalidArgument, "File does not contain an original filename")),
+    }
+}
+
+/// overwrite: false 时输出路径上已有文件则出错，在开始读写之前检查
+fn check_overwrite(output_path: &fd::Target, overwrite: Option<bool>) -> Result<(), ErrorCode> {
+    match overwrite {
+        Some(false) => fd::check_no_clobber(output_path).map_err(Error::from),
+        _ => Ok(()),
+    }
+}
+
+/// 原地加解密（输出与输入是同一个文件）时输出经临时文件替换输入；输出为文件描述符时只能直接写入，
+/// 边读边写会在读完之前破坏输入，因此出错
+fn check_in_place(input_path: &fd::Target, output_path: &fd::Target) -> Result<(), ErrorCode> {
+    match output_path.is_fd() && fd::same_file(input_path, output_path) {
+        true => Err(Error::new(ErrorCode::InvalidArgument, "In-place operation requires an output path, not a file descriptor for the input file")),
+        false => Ok(()),
+    }
+}
+
+/// 分卷、检查点和纠错数据等按路径打开输出文件的功能不支持文件描述符，feature 为出错时的功能名称
+fn output_file_path<'a>(output_path: &'a fd::Target, feature: &str) -> Result<&'a str, ErrorCode> {
+    output_path
+        .as_path()
+        .ok_or_else(|| Error::new(ErrorCode::InvalidArgument, format!("{} requires an output path, not a file descriptor", feature)))
+}
+
+/// shredSource 时先由 verify 重新读取并校验输出文件，通过后才粉碎输入文件
+fn shred_source(input_path: &fd::Target, output_path: &fd::Target, verify: impl FnOnce() -> Result<(), ErrorCode>) -> Result<(), ErrorCode> {
+    verify().map_err(|err| Error::new(err.status, format!("Verification of {} failed, the source was not shredded: {}", output_path, err.reason)))?;
+    match input_path.as_path() {
+        Some(input_path) => shred::shred(input_path).map_err(Error::from),
+        None => Err(Error::new(ErrorCode::InvalidArgument, "shredSource requires an input file path, not a file descriptor")),
+    }
+}
+
+/// restoreAttributes 时解密文件头中保存的文件属性，文件中没有保存时出错
+fn stored_attributes(header: Option<&ContainerHeader>, key: &[u8], restore_attributes: bool) -> Result<Option<FileAttributes>, ErrorCode> {
+    if !restore_attributes {
+        return Ok(None);
+    }
+    match header.map(|header| header.decrypt_attributes(key)).transpose()?.flatten() {
+        Some(attributes) => Ok(Some(attributes)),
+        None => Err(Error::new(ErrorCode::InvalidArgument, "File does not contain file attributes - encrypt it with preserveAttributes")),
+    }
+}
+
+/// 读取并解密整体加密的文件，返回明文、v2 容器文件头（旧格式为 None）和加密文件大小
+fn read_monolithic_file(algo: &CryptoAlgorithm, key: &[u8], input_path: &fd::Target, control: &JobControl) -> Result<(Vec<u8>, Option<ContainerHeader>, u64), ErrorCode> {
+    // 读取整个加密文件
+    let mut file = match fd::open(input_path) {
         Ok(file) => file,
-        Err(err) => return Err(Error::from_reason(format!("Failed to open input file: {}", err))),
+        Err(err) => return Err(Error::new(ErrorCode::IoOpen, format!("Failed to open encrypted file: {}", err))),
     };
     
-    // 获取文件大小
-    let file_size = match file.metadata() {
-        Ok(metadata) => metadata.len(),
-        Err(err) => return Err(Error::from_reason(format!("Failed to get file metadata: {}", err))),
+    let mut encrypted_data = Vec::new();
+    if let Err(err) = file.read_to_end(&mut encrypted_data) {
+        return Err(Error::new(ErrorCode::IoRead, format!("Failed to read encrypted file: {}", err)));
+    }
+    // 输入可能是管道（如标准输入），加密文件大小以读到的数据为准
+    let encrypted_file_size = encrypted_data.len() as u64;
+    
+    control.check()?;
+    let (decrypted, header) = decrypt_monolithic(algo, key, &encrypted_data)?;
+    control.check()?;
+    Ok((decrypted, header, encrypted_file_size))
+}
+
+/// 解密内存中的整体加密数据（与加密文件的内容相同），返回明文和 v2 容器文件头（旧格式为 None）
+fn decrypt_monolithic(algo: &CryptoAlgorithm, key: &[u8], encrypted_data: &[u8]) -> Result<(Vec<u8>, Option<ContainerHeader>), ErrorCode> {
+    // v2 容器文件带有文件头，旧格式文件整体都是 IV/nonce + 密文
+    let (payload, header) = if encrypted_data.starts_with(format::CONTAINER_MAGIC) {
+        let mut reader = encrypted_data;
+        let (header, header_len) = ContainerHeader::read(&mut reader)?;
+        if header.layout != Layout::Monolithic {
+            return Err(Error::new(ErrorCode::InvalidArgument, "Chunked file - use chunkDecryptFile instead"));
+        }
+        verify_header(&header, algo, key)?;
+        (&encrypted_data[header_len as usize..], Some(header))
+    } else {
+        (encrypted_data, None)
     };
+    config::decrypt_policy().check(header.as_ref(), algo)?;
     
-    let mut data = Vec::new();
-    if let Err(err) = file.read_to_end(&mut data) {
-        return Err(Error::from_reason(format!("Failed to read input file: {}", err)));
+    // 使用一次性解密函数解密整个数据
+    let decrypted = decrypt(algo.clone(), key, payload)
+        .map_err(|e| e.context("Decryption error"))?;
+    
+    // 加密前压缩过的文件解压为文件头记录的原始大小
+    let decrypted = match header.as_ref().and_then(|header| header.compression.map(|compression| (compression, header.original_size))) {
+        Some((compression, original_size)) => compression.decompress(&decrypted, original_size as usize)?,
+        None => decrypted,
+    };
+    
+    // v2 文件头记录了原始大小，大小不符说明密文被截断或损坏；保存了明文摘要时同时核对
+    if let Some(header) = &header {
+        if decrypted.len() as u64 != header.original_size {
+            return Err(Error::new(ErrorCode::Truncated, format!(
+                "{}: decrypted size {} does not match header size {}", format::ERR_TRUNCATED, decrypted.len(), header.original_size
+            )));
+        }
+        header.verify_plaintext_hash(key, &decrypted)?;
+    }
+    Ok((decrypted, header))
+}
+
+/// 打开分片加密的文件并读取、校验文件头，返回位于第一个分片帧的 reader
+fn open_chunked_file(algo: &CryptoAlgorithm, key: &[u8], input_path: &fd::Target) -> Result<(BufReader<File>, format::ChunkedHeader), ErrorCode> {
+    // 打开输入文件
+    let input_file = match fd::open(input_path) {
+        Ok(file) => file,
+        Err(err) => return Err(Error::new(ErrorCode::IoOpen, format!("Failed to open input file: {}", err))),
+    };
+    let mut input_file = BufReader::new(input_file);
+    let header = read_chunked_input(&mut input_file, algo, key)?;
+    Ok((input_file, header))
+}
+
+/// 读取并校验分片数据流的文件头，检查解密策略
+fn read_chunked_input<R: BufRead>(reader: &mut R, algo: &CryptoAlgorithm, key: &[u8]) -> Result<format::ChunkedHeader, ErrorCode> {
+    // 读取文件头以获取元数据
+    let header = format::read_chunked_header(reader)?;
+    check_chunked_header(&header, algo, key)?;
+    Ok(header)
+}
+
+/// 校验分片文件头并检查解密策略
+fn check_chunked_header(header: &format::ChunkedHeader, algo: &CryptoAlgorithm, key: &[u8]) -> Result<(), ErrorCode> {
+    if let Some(container) = &header.container {
+        verify_header(container, algo, key)?;
+    }
+    config::decrypt_policy().check(header.container.as_ref(), algo).map_err(Error::from)
+}
+
+/// 加密文件 - 普通文件边读边加密；管道输入、压缩和 mmap 时整体读入内存
+#[napi(js_name = "encryptFile")]
+pub fn encrypt_file(algorithm: String, key: Buffer, input_path: Either<String, i32>, output_path: Either<String, i32>, options: Option<EncryptOptions>) -> Result<EncryptFileResult, ErrorCode> {
+    let options = options.unwrap_or_default();
+    let control = JobControl::new("encryptFile", options.timeout_ms);
+    control.started();
+    let result = encrypt_file_job(algorithm, key, input_path, output_path, options, &control);
+    control.complete(result)
+}
+
+/// encryptFile() 的异步版本，在 libuv 线程池中运行并返回 Promise
+#[napi(js_name = "encryptFileAsync", ts_return_type = "Promise<EncryptFileResult>")]
+pub fn encrypt_file_async(algorithm: String, key: Buffer, input_path: Either<String, i32>, output_path: Either<String, i32>, options: Option<EncryptOptions>, signal: Option<Either<AbortSignal, ClassInstance<Job>>>) -> AsyncTask<FileTask<EncryptFileResult>> {
+    let options = options.unwrap_or_default();
+    let control = JobControl::new("encryptFile", options.timeout_ms);
+    FileTask::new(control, signal, move |control| encrypt_file_job(algorithm, key, input_path, output_path, options, control))
+}
+
+fn encrypt_file_job(algorithm: String, key: Buffer, input_path: Either<String, i32>, output_path: Either<String, i32>, options: EncryptOptions, control: &JobControl) -> Result<EncryptFileResult, ErrorCode> {
+    let input_path = fd::stream_input_path(input_path)?;
+    let output_path = fd::output_path(output_path)?;
+    let algo = parse_algorithm(&algorithm)?;
+    check_overwrite(&output_path, options.overwrite)?;
+    let shred = options.shred_source.unwrap_or(false);
+    if shred {
+        shred::check_paths(&input_path, &output_path)?;
+    }
+    
+    let mut file = match fd::open(&input_path) {
+        Ok(file) => file,
+        Err(err) => return Err(Error::new(ErrorCode::IoOpen, format!("Failed to open input file: {}", err))),
+    };
+    
+    let attributes = sealed_attributes(&options, &file, &key)?;
+    // 不压缩的普通文件边读边加密，内存占用与文件大小无关；管道的大小事先未知、压缩需要完整的数据，这两种情况和选择了 mmap 时整体读入
+    if options.compression.is_none() && !options.mmap.unwrap_or(false) && file.metadata().is_ok_and(|metadata| metadata.is_file()) {
+        return encrypt_file_streamed(&algo, &key, file, &input_path, &output_path, options, attributes, control);
     }
+    let data = match InputData::read(&mut file, options.mmap.unwrap_or(false)) {
+        Ok(data) => data,
+        Err(err) => return Err(Error::new(ErrorCode::IoRead, format!("Failed to read input file: {}", err))),
+    };
+    // 输入可能是管道（如标准输入），文件大小以读到的数据为准，MIME 类型根据已读入的数据检测
+    let file_size = data.len() as u64;
+    let options = match options.mime_type.is_none() && !options.legacy_format.unwrap_or(false) {
+        true => EncryptOptions { mime_type: Some(mime::sniff(&data).to_string()), ..options },
+        false => options,
+    };
+    
+    control.check()?;
+    
+    // 写入 v2 容器文件头（选择旧格式时没有文件头），选择了摘要算法时同时保存明文摘要
+    let (header, compression) = match build_container_header(&options, Some(&input_path), Layout::Monolithic, &algo, &key, file_size, 0)? {
+        Some(mut header) => {
+            header.encrypted_attributes = attributes;
+            header.seal_plaintext_hash(&key, &data)?;
+            (header.to_bytes(&key)?, header.compression)
+        },
+        None => (Vec::new(), None),
+    };
+    
+    // 选择了压缩算法时先压缩整个文件
+    let compressed = match compression {
+        Some(compression) => Some(compression.compress(&data)?),
+        None => None,
+    };
     
     // 使用一次性加密函数加密整个数据
-    let encrypted = encrypt(algo, &key, &data)
-        .map_err(|e| Error::from_reason(format!("Encryption error: {}", e)))?;
+    let encrypted = encrypt(algo, &key, compressed.as_ref().map_or(&data, |(compressed, _)| compressed.as_slice()))
+        .map_err(|e| e.context("Encryption error"))?;
+    
+    control.check()?;
     
     // 写入加密数据到输出文件
-    let mut output_file = match File::create(&output_path) {
+    let output = fd::AtomicOutput::new(&output_path).replace_existing(options.overwrite.unwrap_or(true));
+    let mut output_file = match fd::create(output.path()) {
         Ok(file) => file,
-        Err(err) => return Err(Error::from_reason(format!("Failed to create output file: {}", err))),
+        Err(err) => return Err(Error::new(ErrorCode::IoOpen, format!("Failed to create output file: {}", err))),
     };
     
+    if let Err(err) = output_file.write_all(&header) {
+        return Err(Error::new(ErrorCode::IoWrite, format!("Failed to write file header: {}", err)));
+    }
+    
     if let Err(err) = output_file.write_all(&encrypted) {
-        return Err(Error::from_reason(format!("Failed to write encrypted data: {}", err)));
+        return Err(Error::new(ErrorCode::IoWrite, format!("Failed to write encrypted data: {}", err)));
+    }
+    output.commit()?;
+    
+    // 解密写出的文件并与明文比较，一致后才粉碎输入文件
+    if shred {
+        shred_source(&input_path, &output_path, || {
+            let (decrypted, _, _) = read_monolithic_file(&parse_algorithm(&algorithm)?, &key, &output_path, control)?;
+            match decrypted[..] == data[..] {
+                true => Ok(()),
+                false => Err(Error::new(ErrorCode::Unknown, "decrypted output does not match the input")),
+            }
+        })?;
     }
     
     // 计算KB单位的文件大小
     let file_size_kb = (file_size as f64) / 1024.0;
     
     // 创建并返回结果对象
-    let mut result = env.create_object()?;
-    result.set("fileSize", file_size_kb)?;
-    
-    Ok(result)
+    Ok(EncryptFileResult {
+        file_size: file_size_kb,
+        file_size_bytes: byte_count(file_size),
+        compressed_size_kb: compressed.as_ref().map(|(compressed, _)| (compressed.len() as f64) / 1024.0),
+        compressed_size_bytes: compressed.as_ref().map(|(compressed, _)| byte_count(compressed.len() as u64)),
+        compression_skipped: compressed.map(|(_, applied)| !applied),
+    })
 }
 
-/// 解密文件 - 适用于小到中等大小的文件
-#[napi(js_name = "decryptFile")]
-pub fn decrypt_file(algorithm: String, key: Buffer, input_path: String, output_path: String, env: Env) -> Result<Object> {
-    let algo = CryptoAlgorithm::from_str(&algorithm)
-        .map_err(|_| Error::from_reason("Invalid algorithm".to_string()))?;
+/// 整体加密时逐段读写使用的缓冲区大小
+const STREAM_BUFFER_LEN: usize = 1024 * 1024;
+
+/// 边读边加密普通文件，输出与整体读入后加密相同；保存明文摘要时先读一遍文件计算摘要
+#[allow(clippy::too_many_arguments)]
+fn encrypt_file_streamed(algo: &CryptoAlgorithm, key: &[u8], mut file: File, input_path: &fd::Target, output_path: &fd::Target, options: EncryptOptions, attributes: Option<Vec<u8>>, control: &JobControl) -> Result<EncryptFileResult, ErrorCode> {
+    let read_error = |err: io::Error| Error::new(ErrorCode::IoRead, format!("Failed to read input file: {}", err));
+    let file_size = file.metadata().map_err(read_error)?.len();
+    let shred = options.shred_source.unwrap_or(false);
+    // MIME 类型根据文件开头的数据检测
+    let options = match options.mime_type.is_none() && !options.legacy_format.unwrap_or(false) {
+        true => {
+            let mut sample = Vec::with_capacity(mime::SNIFF_LEN);
+            (&mut file).take(mime::SNIFF_LEN as u64).read_to_end(&mut sample).map_err(read_error)?;
+            file.rewind().map_err(read_error)?;
+            EncryptOptions { mime_type: Some(mime::sniff(&sample).to_string()), ..options }
+        },
+        false => options,
+    };
     
-    // 读取整个加密文件
-    let mut file = match File::open(&input_path) {
+    let header = match build_container_header(&options, Some(input_path), Layout::Monolithic, algo, key, file_size, 0)? {
+        Some(mut header) => {
+            header.encrypted_attributes = attributes;
+            if let Some(algorithm) = header.hash_algorithm {
+                let mut hasher = crypto::PlaintextHasher::new(algorithm);
+                read_blocks(&mut file, "input file", control, |data| {
+                    hasher.update(data);
+                    Ok(())
+                })?;
+                header.seal_plaintext_digest(key, &hasher.finalize())?;
+                file.rewind().map_err(read_error)?;
+            }
+            header.to_bytes(key)?
+        },
+        None => Vec::new(),
+    };
+    
+    let output = fd::AtomicOutput::new(output_path).replace_existing(options.overwrite.unwrap_or(true));
+    let mut output_file = match fd::create(output.path()) {
         Ok(file) => file,
-        Err(err) => return Err(Error::from_reason(format!("Failed to open encrypted file: {}", err))),
+        Err(err) => return Err(Error::new(ErrorCode::IoOpen, format!("Failed to create output file: {}", err))),
     };
+    let write_error = |err: io::Error| Error::new(ErrorCode::IoWrite, format!("Failed to write encrypted data: {}", err));
+    if let Err(err) = output_file.write_all(&header) {
+        return Err(Error::new(ErrorCode::IoWrite, format!("Failed to write file header: {}", err)));
+    }
+    let (mut encryptor, prefix) = crypto::StreamEncryptor::new(algo, key)
+        .map_err(|e| e.context("Encryption error"))?;
+    output_file.write_all(&prefix).map_err(write_error)?;
     
-    // 获取加密文件大小
-    let encrypted_file_size = match file.metadata() {
-        Ok(metadata) => metadata.len(),
-        Err(err) => return Err(Error::from_reason(format!("Failed to get file metadata: {}", err))),
+    // 粉碎源文件前解密写出的文件，与加密时计算的明文摘要核对
+    let mut source_hasher = shred.then(|| crypto::PlaintextHasher::new(HashAlgorithm::Sha256));
+    let mut encrypted = Vec::with_capacity(STREAM_BUFFER_LEN + 16);
+    let bytes_read = read_blocks(&mut file, "input file", control, |data| {
+        if let Some(hasher) = &mut source_hasher {
+            hasher.update(data);
+        }
+        encrypted.clear();
+        encryptor.update(data, &mut encrypted);
+        output_file.write_all(&encrypted).map_err(write_error)
+    })?;
+    // 文件头已经记录了原始大小，加密期间文件大小变化时放弃输出
+    if bytes_read != file_size {
+        return Err(Error::new(ErrorCode::IoRead, format!("Input file changed during encryption: read {} of {} bytes", bytes_read, file_size)));
+    }
+    encrypted.clear();
+    encryptor.finalize(&mut encrypted).map_err(|e| e.context("Encryption error"))?;
+    output_file.write_all(&encrypted).map_err(write_error)?;
+    output.commit()?;
+    
+    if let Some(hasher) = source_hasher {
+        let expected = hasher.finalize();
+        shred_source(input_path, output_path, || match monolithic_file_digest(algo, key, output_path, control)? == expected {
+            true => Ok(()),
+            false => Err(Error::new(ErrorCode::Unknown, "decrypted output does not match the input")),
+        })?;
+    }
+    
+    // 创建并返回结果对象
+    Ok(EncryptFileResult {
+        file_size: (file_size as f64) / 1024.0,
+        file_size_bytes: byte_count(file_size),
+        compressed_size_kb: None,
+        compressed_size_bytes: None,
+        compression_skipped: None,
+    })
+}
+
+/// 边读边解密整体加密的文件，返回明文的 SHA-256 摘要，不把整个文件读入内存
+fn monolithic_file_digest(algo: &CryptoAlgorithm, key: &[u8], path: &fd::Target, control: &JobControl) -> Result<Vec<u8>, ErrorCode> {
+    let read_error = |err: io::Error| Error::new(ErrorCode::IoRead, format!("Failed to read encrypted file: {}", err));
+    let mut reader = match fd::open(path) {
+        Ok(file) => BufReader::new(file),
+        Err(err) => return Err(Error::new(ErrorCode::IoOpen, format!("Failed to open encrypted file: {}", err))),
+    };
+    let header = match reader.fill_buf().map_err(read_error)?.starts_with(format::CONTAINER_MAGIC) {
+        true => {
+            let (header, _) = ContainerHeader::read(&mut reader)?;
+            if header.layout != Layout::Monolithic || header.compression.is_some() {
+                return Err(Error::new(ErrorCode::BadHeader, "Not an uncompressed monolithic file"));
+            }
+            verify_header(&header, algo, key)?;
+            Some(header)
+        },
+        false => None,
     };
+    let mut prefix = vec![0u8; algo.nonce_len()];
+    reader.read_exact(&mut prefix).map_err(read_error)?;
+    let decrypt_error = |e: CodedError| e.context("Decryption error");
+    let mut decryptor = crypto::StreamDecryptor::new(algo, key, &prefix).map_err(decrypt_error)?;
     
-    let mut encrypted_data = Vec::new();
-    if let Err(err) = file.read_to_end(&mut encrypted_data) {
-        return Err(Error::from_reason(format!("Failed to read encrypted file: {}", err)));
+    let mut hasher = crypto::PlaintextHasher::new(HashAlgorithm::Sha256);
+    let mut plaintext = Vec::with_capacity(STREAM_BUFFER_LEN + 16);
+    let mut size = 0u64;
+    read_blocks(&mut reader, "encrypted file", control, |data| {
+        plaintext.clear();
+        decryptor.update(data, &mut plaintext);
+        hasher.update(&plaintext);
+        size += plaintext.len() as u64;
+        Ok(())
+    })?;
+    plaintext.clear();
+    decryptor.finalize(&mut plaintext).map_err(decrypt_error)?;
+    hasher.update(&plaintext);
+    size += plaintext.len() as u64;
+    if let Some(header) = header.filter(|header| header.original_size != size) {
+        return Err(Error::new(ErrorCode::Truncated, format!(
+            "{}: decrypted size {} does not match header size {}", format::ERR_TRUNCATED, size, header.original_size
+        )));
     }
+    Ok(hasher.finalize())
+}
+
+/// 以固定大小的缓冲区逐段读取到结尾，每段之前检查操作是否已中止；返回读取的总字节数，what 用于错误信息
+fn read_blocks<R: Read>(reader: &mut R, what: &str, control: &JobControl, mut process: impl FnMut(&[u8]) -> Result<(), ErrorCode>) -> Result<u64, ErrorCode> {
+    let mut buffer = vec![0u8; STREAM_BUFFER_LEN];
+    let mut total = 0;
+    loop {
+        control.check()?;
+        let len = pipeline::read_full(reader, &mut buffer).map_err(|err| Error::new(ErrorCode::IoRead, format!("Failed to read {}: {}", what, err)))?;
+        if len == 0 {
+            return Ok(total);
+        }
+        process(&buffer[..len])?;
+        total += len as u64;
+    }
+}
+
+/// 解密文件 - 适用于小到中等大小的文件
+#[napi(js_name = "decryptFile")]
+pub fn decrypt_file(algorithm: String, key: Buffer, input_path: Either<String, i32>, output_path: Either<String, i32>, options: Option<DecryptOptions>) -> Result<DecryptFileResult, ErrorCode> {
+    let options = options.unwrap_or_default();
+    let control = JobControl::new("decryptFile", options.timeout_ms);
+    control.started();
+    let result = decrypt_file_job(algorithm, key, input_path, output_path, options, &control);
+    control.complete(result)
+}
+
+/// decryptFile() 的异步版本，在 libuv 线程池中运行并返回 Promise
+#[napi(js_name = "decryptFileAsync", ts_return_type = "Promise<DecryptFileResult>")]
+pub fn decrypt_file_async(algorithm: String, key: Buffer, input_path: Either<String, i32>, output_path: Either<String, i32>, options: Option<DecryptOptions>, signal: Option<Either<AbortSignal, ClassInstance<Job>>>) -> AsyncTask<FileTask<DecryptFileResult>> {
+    let options = options.unwrap_or_default();
+    let control = JobControl::new("decryptFile", options.timeout_ms);
+    FileTask::new(control, signal, move |control| decrypt_file_job(algorithm, key, input_path, output_path, options, control))
+}
+
+fn decrypt_file_job(algorithm: String, key: Buffer, input_path: Either<String, i32>, output_path: Either<String, i32>, options: DecryptOptions, control: &JobControl) -> Result<DecryptFileResult, ErrorCode> {
+    let input_path = fd::stream_input_path(input_path)?;
+    let output_path = fd::output_path(output_path)?;
+    let algo = parse_algorithm(&algorithm)?;
     
-    // 使用一次性解密函数解密整个数据
-    let decrypted = decrypt(algo, &key, &encrypted_data)
-        .map_err(|e| Error::from_reason(format!("Decryption error: {}", e)))?;
+    let (decrypted, header, encrypted_file_size) = read_monolithic_file(&algo, &key, &input_path, control)?;
+    // read_monolithic_file 已核对文件头中保存的明文摘要
+    let hash_verified = header.as_ref().is_some_and(|header| header.encrypted_plaintext_hash.is_some());
+    let (filename, mime_type) = match &header {
+        Some(header) => (
+            header.decrypt_filename(&key)?,
+            header.decrypt_mime_type(&key)?,
+        ),
+        None => (None, None),
+    };
+    let output_path = resolve_output_path(&output_path, &filename, options.restore_filename.unwrap_or(false))?;
+    let attributes = stored_attributes(header.as_ref(), &key, options.restore_attributes.unwrap_or(false))?;
+    check_overwrite(&output_path, options.overwrite)?;
     
     // 写入解密数据到输出文件
-    let mut output_file = match File::create(&output_path) {
+    let output = fd::AtomicOutput::new(&output_path).replace_existing(options.overwrite.unwrap_or(true));
+    let mut output_file = match fd::create(output.path()) {
         Ok(file) => file,
-        Err(err) => return Err(Error::from_reason(format!("Failed to create output file: {}", err))),
+        Err(err) => return Err(Error::new(ErrorCode::IoOpen, format!("Failed to create output file: {}", err))),
     };
     
     if let Err(err) = output_file.write_all(&decrypted) {
-        return Err(Error::from_reason(format!("Failed to write decrypted data: {}", err)));
+        return Err(Error::new(ErrorCode::IoWrite, format!("Failed to write decrypted data: {}", err)));
+    }
+    output.commit()?;
+    if let Some(attributes) = attributes {
+        attributes.restore(&output_file);
     }
     
     // 计算KB单位的文件大小
@@ -102,92 +684,229 @@ pub fn decrypt_file(algorithm: String, key: Buffer, input_path: String, output_p
     let encrypted_size_kb = (encrypted_file_size as f64) / 1024.0;
     
     // 创建并返回结果对象
-    let mut result = env.create_object()?;
-    result.set("fileSize", file_size_kb)?;
-    result.set("encryptedSize", encrypted_size_kb)?;
-    
-    Ok(result)
+    Ok(DecryptFileResult {
+        file_size: file_size_kb,
+        file_size_bytes: byte_count(decrypted.len() as u64),
+        encrypted_size: encrypted_size_kb,
+        encrypted_size_bytes: byte_count(encrypted_file_size),
+        filename,
+        mime_type,
+        output_path: output_path.to_string(),
+        hash_verified,
+    })
 }
 
 /// 分片加密文件 - 用于超大文件，带有分片处理功能
 #[napi(js_name = "chunkEncryptFile")]
-pub fn chunk_encrypt_file(algorithm: String, key: Buffer, input_path: String, output_path: String, chunk_size_mb: u32, env: Env) -> Result<Object> {
-    let algo = CryptoAlgorithm::from_str(&algorithm)
-        .map_err(|_| Error::from_reason("Invalid algorithm".to_string()))?;
+pub fn chunk_encrypt_file(algorithm: String, key: Buffer, input_path: Either<String, i32>, output_path: Either<String, i32>, chunk_size_mb: u32, options: Option<EncryptOptions>) -> Result<ChunkEncryptFileResult, ErrorCode> {
+    let options = options.unwrap_or_default();
+    let control = JobControl::new("chunkEncryptFile", options.timeout_ms);
+    control.started();
+    let result = chunk_encrypt_file_job(algorithm, key, input_path, output_path, chunk_size_mb, options, &control);
+    control.complete(result)
+}
+
+/// chunkEncryptFile() 的异步版本，在 libuv 线程池中运行并返回 Promise
+#[napi(js_name = "chunkEncryptFileAsync", ts_return_type = "Promise<ChunkEncryptFileResult>")]
+pub fn chunk_encrypt_file_async(algorithm: String, key: Buffer, input_path: Either<String, i32>, output_path: Either<String, i32>, chunk_size_mb: u32, options: Option<EncryptOptions>, signal: Option<Either<AbortSignal, ClassInstance<Job>>>) -> AsyncTask<FileTask<ChunkEncryptFileResult>> {
+    let options = options.unwrap_or_default();
+    let control = JobControl::new("chunkEncryptFile", options.timeout_ms);
+    FileTask::new(control, signal, move |control| chunk_encrypt_file_job(algorithm, key, input_path, output_path, chunk_size_mb, options, control))
+}
+
+#[allow(clippy::too_many_arguments)]
+fn chunk_encrypt_file_job(algorithm: String, key: Buffer, input_path: Either<String, i32>, output_path: Either<String, i32>, chunk_size_mb: u32, options: EncryptOptions, control: &JobControl) -> Result<ChunkEncryptFileResult, ErrorCode> {
+    let input_path = fd::input_path(input_path)?;
+    let output_path = fd::output_path(output_path)?;
+    let algo = parse_algorithm(&algorithm)?;
+    // 可续传时已有的输出文件可能是上次中断时留下的，在确定不能继续之后再检查
+    let shred = options.shred_source.unwrap_or(false);
+    if shred {
+        if options.volume_size_mb.is_some() {
+            return Err(Error::new(ErrorCode::InvalidArgument, "shredSource cannot be combined with volumes"));
+        }
+        shred::check_paths(&input_path, &output_path)?;
+    }
+    check_in_place(&input_path, &output_path)?;
+    if options.resume.unwrap_or(false) && fd::same_file(&input_path, &output_path) {
+        return Err(Error::new(ErrorCode::InvalidArgument, "Resumable encryption requires an output path different from the input"));
+    }
+    let direct_io = options.direct_io.unwrap_or(false);
+    if direct_io && options.mmap.unwrap_or(false) {
+        return Err(Error::new(ErrorCode::InvalidArgument, "directIo cannot be combined with mmap"));
+    }
+    if direct_io && options.volume_size_mb.is_some() {
+        return Err(Error::new(ErrorCode::InvalidArgument, "directIo cannot be combined with volumes"));
+    }
+    match options.volume_size_mb {
+        Some(_) => check_overwrite(&volume::volume_path(output_file_path(&output_path, "Volumes")?, 0).into(), options.overwrite)?,
+        None if !options.resume.unwrap_or(false) => check_overwrite(&output_path, options.overwrite)?,
+        None => {},
+    }
     
     // 默认使用10MB的块大小，也可以通过参数指定
     let chunk_size = (chunk_size_mb as usize) * 1024 * 1024;
     
     // 打开输入文件
-    let input_file = match File::open(&input_path) {
+    let input_file = match fd::open(&input_path) {
         Ok(file) => file,
-        Err(err) => return Err(Error::from_reason(format!("Failed to open input file: {}", err))),
+        Err(err) => return Err(Error::new(ErrorCode::IoOpen, format!("Failed to open input file: {}", err))),
     };
     
     let file_size = match input_file.metadata() {
         Ok(metadata) => metadata.len(),
-        Err(err) => return Err(Error::from_reason(format!("Failed to get file metadata: {}", err))),
+        Err(err) => return Err(Error::new(ErrorCode::IoRead, format!("Failed to get file metadata: {}", err))),
     };
+    let attributes = sealed_attributes(&options, &input_file, &key)?;
     
-    let mut reader = BufReader::with_capacity(chunk_size, input_file);
-    
-    // 创建输出文件
-    let output_file = match File::create(&output_path) {
-        Ok(file) => file,
-        Err(err) => return Err(Error::from_reason(format!("Failed to create output file: {}", err))),
+    // 稀疏编码时先查询输入文件中的空洞，位于空洞中的分片不必读取
+    let holes = options.sparse.unwrap_or(false).then(|| HoleMap::of(&input_file, file_size));
+    // 可续传时记录输入文件的标识，检查点只用于同一个输入文件
+    let resumable = options.resume.unwrap_or(false);
+    let identity = match resumable {
+        true => Some(InputIdentity::of(&input_file, chunk_size)?),
+        false => None,
+    };
+    // 选择 mmap 时映射输入文件，映射失败时改用普通读取
+    let mapping = options.mmap.unwrap_or(false).then(|| Mapping::of(&input_file)).flatten();
+    // 选择 directIo 时绕过页缓存读写，文件描述符不受影响（不改变调用方句柄的标志）
+    let mut reader = BufReader::with_capacity(chunk_size, RingFile::new(input_file).direct_io(direct_io && !input_path.is_fd()));
+    let chunk_input = |reader| match &mapping {
+        Some(mapping) => ChunkInput::Mapped(mapping),
+        None => ChunkInput::Reader(reader, holes.as_ref()),
     };
     
-    let mut writer = BufWriter::with_capacity(chunk_size, output_file);
-    
-    // 写入分片标记和元数据（文件头）
-    let header = format!("CHUNKS:{}:{}:", file_size, chunk_size);
-    if let Err(err) = writer.write_all(header.as_bytes()) {
-        return Err(Error::from_reason(format!("Failed to write file header: {}", err)));
+    // 写入分片标记和元数据（文件头），选择旧格式时使用 v1 文本文件头
+    let mut container = build_container_header(&options, Some(&input_path), Layout::Chunked, &algo, &key, file_size, chunk_size as u64)?;
+    if let Some(container) = container.as_mut() {
+        container.encrypted_attributes = attributes;
     }
-    
-    // 计算预期的总分片数，用于后续处理
-    let _total_chunks = (file_size as f64 / chunk_size as f64).ceil() as u64;
-    
-    let mut buffer = vec![0u8; chunk_size];
-    let mut chunk_index = 0;
-    
-    loop {
-        let bytes_read = match reader.read(&mut buffer) {
-            Ok(0) => break, // 读取完毕
-            Ok(n) => n,
-            Err(err) => return Err(Error::from_reason(format!("Error reading file chunk: {}", err))),
-        };
-        
-        chunk_index += 1;
-        
-        // 只加密实际读取的数据
-        let chunk_data = &buffer[..bytes_read];
-        
-        // 加密当前块
-        let encrypted = match encrypt(algo.clone(), &key, chunk_data) {
-            Ok(data) => data,
-            Err(err) => return Err(Error::from_reason(format!("Chunk encryption error: {}", err))),
-        };
-        
-        // 写入块大小和加密后的数据
-        let size_header = format!("{}:", encrypted.len());
-        if let Err(err) = writer.write_all(size_header.as_bytes()) {
-            return Err(Error::from_reason(format!("Failed to write chunk size header: {}", err)));
+    if holes.is_some() {
+        match container.as_mut() {
+            Some(container) => container.sparse = true,
+            None => return Err(Error::new(ErrorCode::InvalidArgument, "Sparse encoding requires the container format")),
         }
-        
-        if let Err(err) = writer.write_all(&encrypted) {
-            return Err(Error::from_reason(format!("Failed to write encrypted chunk: {}", err)));
+    }
+    if options.appendable.unwrap_or(false) {
+        match container.as_mut() {
+            Some(_) if options.volume_size_mb.is_some() => {
+                return Err(Error::new(ErrorCode::InvalidArgument, "Appendable files cannot be split into volumes"));
+            },
+            Some(container) => container.appendable = true,
+            None => return Err(Error::new(ErrorCode::InvalidArgument, "Appendable files require the container format")),
         }
-        
-        // 如果没读满buffer，说明文件已经读完了
-        if bytes_read < chunk_size {
-            break;
+    }
+    if let Some(percent) = options.parity_percent {
+        parity::check_percent(percent)?;
+        match container.as_mut() {
+            Some(_) if options.volume_size_mb.is_some() => {
+                return Err(Error::new(ErrorCode::InvalidArgument, "Parity cannot be combined with volumes"));
+            },
+            Some(container) => container.has_parity = true,
+            None => return Err(Error::new(ErrorCode::InvalidArgument, "Parity requires the container format")),
+        }
+        output_file_path(&output_path, "Parity")?;
+    }
+    if resumable {
+        output_file_path(&output_path, "Resumable encryption")?;
+        if container.is_none() {
+            return Err(Error::new(ErrorCode::InvalidArgument, "Resumable encryption requires the container format"));
+        }
+        if options.volume_size_mb.is_some() {
+            return Err(Error::new(ErrorCode::InvalidArgument, "Resumable encryption cannot be combined with volumes"));
         }
     }
+    // 先写入临时文件，完成后再替换输出文件；可续传时直接写入输出文件，中止操作时保留给下次继续
+    let output = (!resumable && options.volume_size_mb.is_none())
+        .then(|| fd::AtomicOutput::new(&output_path).replace_existing(options.overwrite.unwrap_or(true)));
+    let write_path = output.as_ref().map_or(&output_path, |output| output.path());
+    
+    // 设置了分卷大小时输出切分为 <output_path>.001、.002……，出错时已写出的分卷会被删除
+    let (encoded, volumes, resumed_chunks) = match options.volume_size_mb {
+        Some(volume_size_mb) => {
+            if container.is_none() {
+                return Err(Error::new(ErrorCode::InvalidArgument, "Volumes require the container format"));
+            }
+            let volumes = VolumeWriter::create(output_file_path(&output_path, "Volumes")?, &key, volume_size_mb as u64 * 1024 * 1024)?;
+            let writer = BufWriter::with_capacity(chunk_size, volumes);
+            let encoder = ChunkEncoder::new(writer, algo, &key, container.as_ref(), file_size, chunk_size)?;
+            let (encoded, writer) = encode_chunks(chunk_input(&mut reader), encoder, chunk_size, 0, None, control)?;
+            let volumes = writer
+                .into_inner()
+                .map_err(|e| Error::new(ErrorCode::IoWrite, format!("Failed to flush output file: {}", e.error())))?;
+            (encoded, Some(volumes.finish()?), 0)
+        },
+        None => {
+            // 有检查点时从上次中断的位置继续，检查点与输入文件或选项不符时从头开始
+            let checkpoint_path = output_path.as_path();
+            let resumed = match (identity, container.as_ref(), checkpoint_path) {
+                (Some(identity), Some(container), Some(checkpoint_path)) => {
+                    checkpoint::resume(checkpoint_path, &mut reader, identity, container, &algo, &key, || control.check())?
+                },
+                _ => None,
+            };
+            let (mut encoder, mut checkpoint, offset) = match (resumed, identity, checkpoint_path) {
+                (Some(mut resumed), Some(identity), Some(checkpoint_path)) => {
+                    let checkpoint = CheckpointWriter::new(checkpoint_path, &key, identity, &resumed.output)?;
+                    if direct_io {
+                        resumed.encoder.get_mut().get_mut().set_direct_io();
+                    }
+                    (resumed.encoder, Some(checkpoint), resumed.offset)
+                },
+                _ => {
+                    if resumable {
+                        check_overwrite(&output_path, options.overwrite)?;
+                    }
+                    if let Some(checkpoint_path) = checkpoint_path {
+                        checkpoint::remove(checkpoint_path);
+                    }
+                    // 创建输出文件
+                    let output_file = match fd::create(write_path) {
+                        Ok(file) => file,
+                        Err(err) => return Err(Error::new(ErrorCode::IoOpen, format!("Failed to create output file: {}", err))),
+                    };
+                    let checkpoint = match (identity, checkpoint_path) {
+                        (Some(identity), Some(checkpoint_path)) => Some(CheckpointWriter::new(checkpoint_path, &key, identity, &output_file)?),
+                        _ => None,
+                    };
+                    let writer = BufWriter::with_capacity(chunk_size, RingFile::new(output_file).direct_io(direct_io && !output_path.is_fd()));
+                    let encoder = ChunkEncoder::new(writer, algo, &key, container.as_ref(), file_size, chunk_size)?;
+                    (encoder, checkpoint, 0)
+                },
+            };
+            // 按预计的密文大小预先分配输出文件的空间，空间不足时在加密开始前报错
+            if let Some(len) = encoder.expected_len(file_size.saturating_sub(offset)) {
+                reserve_output(encoder.get_mut().get_mut(), len)?;
+            }
+            let resumed_chunks = encoder.chunks_written();
+            let (encoded, mut writer) = encode_chunks(chunk_input(&mut reader), encoder, chunk_size, offset, checkpoint.as_mut(), control)?;
+            release_output(writer.get_mut())?;
+            (encoded, None, resumed_chunks)
+        },
+    };
+    
+    // 加密完成后在文件末尾追加纠错数据
+    let parity_size = match options.parity_percent {
+        Some(percent) => Some(parity::append_parity(output_file_path(write_path, "Parity")?, percent)?),
+        None => None,
+    };
+    if let Some(output) = output {
+        output.commit()?;
+    }
+    if let (true, Some(checkpoint_path)) = (resumable, output_path.as_path()) {
+        checkpoint::remove(checkpoint_path);
+    }
     
-    // 确保所有数据都写入磁盘
-    if let Err(err) = writer.flush() {
-        return Err(Error::from_reason(format!("Failed to flush output file: {}", err)));
+    // 重新读取并认证写出的每个分片以及整个文件的 MAC 和明文摘要，通过后才粉碎输入文件
+    if shred {
+        drop(reader);
+        shred_source(&input_path, &output_path, || {
+            let verified = verify_chunked_file_job(output_path.clone().into(), key.clone(), Some(algorithm.clone()), control)?;
+            match verified.error {
+                Some(err) => Err(Error::new(ErrorCode::AuthFailed, err)),
+                None => Ok(()),
+            }
+        })?;
     }
     
     // 计算KB单位的大小
@@ -195,389 +914,4177 @@ pub fn chunk_encrypt_file(algorithm: String, key: Buffer, input_path: String, ou
     let chunk_size_kb = (chunk_size as f64) / 1024.0;
     
     // 创建并返回结果对象
-    let mut result = env.create_object()?;
-    result.set("totalChunks", chunk_index)?;
-    result.set("fileSize", file_size_kb)?;
-    result.set("chunkSize", chunk_size_kb)?;
+    Ok(ChunkEncryptFileResult {
+        total_chunks: encoded.chunks as f64,
+        file_size: file_size_kb,
+        file_size_bytes: byte_count(file_size),
+        chunk_size: chunk_size_kb,
+        chunk_size_bytes: byte_count(chunk_size as u64),
+        volumes,
+        parity_size_kb: parity_size.map(|size| (size as f64) / 1024.0),
+        parity_size_bytes: parity_size.map(byte_count),
+        compressed_size_kb: encoded.compression.map(|stats| (stats.compressed_size as f64) / 1024.0),
+        compressed_size_bytes: encoded.compression.map(|stats| byte_count(stats.compressed_size)),
+        uncompressed_chunks: encoded.compression.map(|stats| stats.stored_chunks as f64),
+        sparse_chunks: holes.is_some().then_some(encoded.sparse_chunks as f64),
+        resumed_chunks: resumable.then_some(resumed_chunks as f64),
+    })
+}
+
+/// 为输出文件预先分配 len 字节的磁盘空间（见 prealloc.rs）
+fn reserve_output(output: &mut RingFile, len: u64) -> Result<(), ErrorCode> {
+    let file = output
+        .file()
+        .map_err(|e| Error::new(ErrorCode::IoWrite, format!("Failed to write output file: {}", e)))?;
+    prealloc::reserve(file, len).map_err(|e| Error::new(ErrorCode::IoWrite, format!("Failed to reserve disk space for output file: {}", e)))
+}
+
+/// 释放输出文件末尾之后没有用到的预先分配的空间
+fn release_output(output: &mut RingFile) -> Result<(), ErrorCode> {
+    output
+        .file()
+        .and_then(prealloc::release)
+        .map_err(|e| Error::new(ErrorCode::IoWrite, format!("Failed to write output file: {}", e)))
+}
+
+/// encode_chunks 写出的分片统计
+struct EncodedChunks {
+    chunks: u32,
+    /// 压缩统计，未启用压缩时为 None
+    compression: Option<CompressionStats>,
+    /// 以空分片帧保存的全零分片数
+    sparse_chunks: u32,
+}
+
+/// encode_chunks 的输入
+enum ChunkInput<'a, R> {
+    /// 由读取线程预读，位于 holes 中空洞的分片跳过不读
+    Reader(&'a mut R, Option<&'a HoleMap>),
+    /// 映射到内存的输入文件，直接按分片切分
+    Mapped(&'a [u8]),
+}
+
+/// 逐块读取明文交给分片写入器加密写出，最后写入文件结尾，返回分片统计和底层 writer；holes 中的空洞直接跳过不读取
+///
+/// reader 位于明文的 offset 处（继续中断的加密时不为 0）；设置了 checkpoint 时定期记录检查点，中止前也记录一次。
+#[allow(clippy::too_many_arguments)]
+fn encode_chunks<R: Read + Seek + Send, W: Write>(input: ChunkInput<'_, R>, mut encoder: ChunkEncoder<W>, chunk_size: usize, mut offset: u64, mut checkpoint: Option<&mut CheckpointWriter>, control: &JobControl) -> std::result::Result<(EncodedChunks, W), CodedError> {
+    let (reader, holes) = match input {
+        ChunkInput::Mapped(data) => {
+            for chunk in data.get(offset as usize..).unwrap_or_default().chunks(chunk_size) {
+                encode_chunk(&mut encoder, chunk, checkpoint.as_deref_mut(), control)?;
+            }
+            return finish_chunks(encoder);
+        },
+        ChunkInput::Reader(reader, holes) => (reader, holes),
+    };
     
-    Ok(result)
+    // 读取线程读取下一个分片的同时，当前线程加密并写出上一个分片
+    let read_chunk = move |buffer: &mut [u8]| {
+        let bytes_read = match holes.and_then(|holes| holes.hole_len(offset, chunk_size as u64)) {
+            Some(len) => {
+                reader.seek(SeekFrom::Current(len as i64))?;
+                buffer[..len as usize].fill(0);
+                len as usize
+            },
+            None => pipeline::read_full(reader, buffer)?,
+        };
+        offset += bytes_read as u64;
+        Ok(bytes_read)
+    };
+    std::thread::scope(|scope| {
+        let chunks = Prefetch::spawn(scope, chunk_size, read_chunk);
+        // 没读满一个分片说明文件已经读完了，读取线程随之结束
+        while let Some(buffer) = chunks.next() {
+            let buffer = buffer.map_err(|err| CodedError::io(ErrorCode::IoRead, "Error reading file chunk", err))?;
+            encode_chunk(&mut encoder, &buffer, checkpoint.as_deref_mut(), control)?;
+            chunks.recycle(buffer);
+        }
+        Ok::<_, CodedError>(())
+    })?;
+    finish_chunks(encoder)
+}
+
+/// 加密写出一个分片；操作被中止时先保存检查点
+fn encode_chunk<W: Write>(encoder: &mut ChunkEncoder<W>, data: &[u8], checkpoint: Option<&mut CheckpointWriter>, control: &JobControl) -> std::result::Result<(), CodedError> {
+    if let Err(err) = control.check() {
+        if let Some(checkpoint) = checkpoint {
+            checkpoint.save(encoder)?;
+        }
+        return Err(err);
+    }
+    encoder.write_chunk(data)?;
+    let chunk_index = encoder.chunks_written();
+    config::log(LogLevel::Debug, format!("encrypted chunk {} ({} bytes)", chunk_index, data.len()));
+    control.chunk_done(chunk_index, data.len());
+    if let Some(checkpoint) = checkpoint {
+        checkpoint.save_if_due(encoder)?;
+    }
+    Ok(())
+}
+
+fn finish_chunks<W: Write>(encoder: ChunkEncoder<W>) -> std::result::Result<(EncodedChunks, W), CodedError> {
+    // 写入结束帧、整个文件的 MAC 和结尾字段，并确保所有数据都写入磁盘
+    let encoded = EncodedChunks {
+        chunks: encoder.chunks_written(),
+        compression: encoder.compression_stats(),
+        sparse_chunks: encoder.sparse_chunks(),
+    };
+    Ok((encoded, encoder.finish()?))
 }
 
 /// 分片解密文件 - 用于超大文件，处理分片加密的文件
 #[napi(js_name = "chunkDecryptFile")]
-pub fn chunk_decrypt_file(algorithm: String, key: Buffer, input_path: String, output_path: String, env: Env) -> Result<Object> {
-    let algo = CryptoAlgorithm::from_str(&algorithm)
-        .map_err(|_| Error::from_reason("Invalid algorithm".to_string()))?;
+pub fn chunk_decrypt_file(algorithm: String, key: Buffer, input_path: Either<String, i32>, output_path: Either<String, i32>, options: Option<DecryptOptions>) -> Result<ChunkDecryptFileResult, ErrorCode> {
+    let options = options.unwrap_or_default();
+    let control = JobControl::new("chunkDecryptFile", options.timeout_ms);
+    control.started();
+    let result = chunk_decrypt_file_job(algorithm, key, input_path, output_path, options, &control);
+    control.complete(result)
+}
+
+/// chunkDecryptFile() 的异步版本，在 libuv 线程池中运行并返回 Promise
+#[napi(js_name = "chunkDecryptFileAsync", ts_return_type = "Promise<ChunkDecryptFileResult>")]
+pub fn chunk_decrypt_file_async(algorithm: String, key: Buffer, input_path: Either<String, i32>, output_path: Either<String, i32>, options: Option<DecryptOptions>, signal: Option<Either<AbortSignal, ClassInstance<Job>>>) -> AsyncTask<FileTask<ChunkDecryptFileResult>> {
+    let options = options.unwrap_or_default();
+    let control = JobControl::new("chunkDecryptFile", options.timeout_ms);
+    FileTask::new(control, signal, move |control| chunk_decrypt_file_job(algorithm, key, input_path, output_path, options, control))
+}
+
+fn chunk_decrypt_file_job(algorithm: String, key: Buffer, input_path: Either<String, i32>, output_path: Either<String, i32>, options: DecryptOptions, control: &JobControl) -> Result<ChunkDecryptFileResult, ErrorCode> {
+    let input_path = fd::stream_input_path(input_path)?;
+    let output_path = fd::output_path(output_path)?;
+    let algo = parse_algorithm(&algorithm)?;
+    check_in_place(&input_path, &output_path)?;
+    let (input_file, header) = open_chunked_file(&algo, &key, &input_path)?;
+    let direct_io = options.direct_io.unwrap_or(false);
+    if options.tolerate_errors.unwrap_or(false) {
+        if direct_io {
+            return Err(Error::new(ErrorCode::InvalidArgument, "directIo cannot be combined with tolerateErrors"));
+        }
+        return decrypt_chunked_tolerant(input_file, header, algo, &key, output_path, options, control);
+    }
+    // 文件头之后已读入缓冲区的数据先交给解密流程，其余部分由 RingFile 继续读取
+    let buffered = input_file.buffer().to_vec();
+    let input_file = RingFile::new(input_file.into_inner()).direct_io(direct_io && !input_path.is_fd());
+    let input = io::Cursor::new(buffered).chain(input_file);
+    decrypt_chunked_stream(input, header, algo, &key, output_path, options, control)
+}
+
+/// 容错解密分片文件：无法读取或解密的分片以零填充（或跳过）并记录在结果中，不中止操作
+#[allow(clippy::too_many_arguments)]
+fn decrypt_chunked_tolerant(input: BufReader<File>, header: format::ChunkedHeader, algo: CryptoAlgorithm, key: &[u8], output_path: fd::Target, options: DecryptOptions, control: &JobControl) -> Result<ChunkDecryptFileResult, ErrorCode> {
+    let original_size = header.original_size;
+    let chunk_size = header.chunk_size;
+    let sparse = header.sparse();
+    let skip_damaged = options.skip_damaged_chunks.unwrap_or(false);
     
-    // 打开输入文件
-    let mut input_file = match File::open(&input_path) {
-        Ok(file) => file,
-        Err(err) => return Err(Error::from_reason(format!("Failed to open input file: {}", err))),
+    let (filename, mime_type) = match &header.container {
+        Some(container) => (
+            container.decrypt_filename(key)?,
+            container.decrypt_mime_type(key)?,
+        ),
+        None => (None, None),
     };
+    let output_path = resolve_output_path(&output_path, &filename, options.restore_filename.unwrap_or(false))?;
+    let attributes = stored_attributes(header.container.as_ref(), key, options.restore_attributes.unwrap_or(false))?;
+    check_overwrite(&output_path, options.overwrite)?;
     
     // 创建输出文件
-    let mut output_file = match File::create(&output_path) {
+    let output = fd::AtomicOutput::new(&output_path).replace_existing(options.overwrite.unwrap_or(true));
+    let mut output_file = match fd::create(output.path()) {
         Ok(file) => file,
-        Err(err) => return Err(Error::from_reason(format!("Failed to create output file: {}", err))),
+        Err(err) => return Err(Error::new(ErrorCode::IoOpen, format!("Failed to create output file: {}", err))),
     };
     
-    // 读取文件头以获取元数据
-    let mut header = String::new();
-    let mut buffer = [0u8; 1];
+    let mut chunks = TolerantChunkReader::new(input, header, algo, key)?;
+    let mut damaged_chunks = Vec::new();
+    let mut bytes_written = 0u64;
     
     loop {
-        match input_file.read_exact(&mut buffer) {
-            Ok(_) => {
-                if buffer[0] == b':' {
-                    break;
+        control.check()?;
+        
+        let data = match chunks.next_chunk() {
+            Some((_, ChunkOutcome::Decrypted(data))) => data,
+            Some((index, ChunkOutcome::Damaged { start, len, error })) => {
+                config::log(LogLevel::Warn, format!("damaged chunk {} ({} bytes at {}): {}", index, len, start, error));
+                damaged_chunks.push(DamagedChunk {
+                    index,
+                    start: start as f64,
+                    end: (start + len) as f64,
+                    error,
+                });
+                if skip_damaged {
+                    continue;
                 }
-                header.push(buffer[0] as char);
+                vec![0u8; len as usize]
             },
-            Err(err) => return Err(Error::from_reason(format!("Error reading header: {}", err))),
+            None => break,
+        };
+        
+        // 写入解密后的数据（损坏的分片为零）
+        if let Err(err) = write_decrypted_chunk(&mut output_file, &data, sparse) {
+            return Err(Error::new(ErrorCode::IoWrite, format!("Failed to write decrypted chunk: {}", err)));
         }
+        bytes_written += data.len() as u64;
+        control.chunk_done(chunks.decoder().chunks_read(), data.len());
     }
-    
-    if !header.starts_with("CHUNKS") {
-        return Err(Error::from_reason("Invalid file format - not a chunked file".to_string()));
+    if let Err(err) = finish_sparse_output(&output_file, bytes_written, sparse) {
+        return Err(Error::new(ErrorCode::IoWrite, format!("Failed to write decrypted chunk: {}", err)));
     }
     
-    // 解析文件大小
-    let mut original_size_str = String::new();
-    loop {
-        match input_file.read_exact(&mut buffer) {
-            Ok(_) => {
-                if buffer[0] == b':' {
-                    break;
-                }
-                original_size_str.push(buffer[0] as char);
-            },
-            Err(err) => return Err(Error::from_reason(format!("Error reading file size: {}", err))),
-        }
+    // 没有损坏的分片时仍校验整个文件的 MAC 和结尾字段，失败只记录在结果中
+    let total_chunks = chunks.decoder().chunks_read();
+    let verification_error = chunks.finish();
+    output.commit()?;
+    if let Some(attributes) = attributes {
+        attributes.restore(&output_file);
     }
     
-    let original_size: u64 = match original_size_str.parse() {
-        Ok(size) => size,
-        Err(_) => return Err(Error::from_reason("Invalid file size in header".to_string())),
-    };
+    // 计算KB单位的大小
+    let original_size_kb = (original_size as f64) / 1024.0;
+    let bytes_written_kb = (bytes_written as f64) / 1024.0;
+    let chunk_size_kb = (chunk_size as f64) / 1024.0;
     
-    // 解析块大小
-    let mut chunk_size_str = String::new();
+    // 创建并返回结果对象
+    Ok(ChunkDecryptFileResult {
+        total_chunks: total_chunks as f64,
+        total_bytes_kb: bytes_written_kb,
+        total_bytes: byte_count(bytes_written),
+        original_size_kb,
+        original_size_bytes: byte_count(original_size),
+        chunk_size_kb,
+        chunk_size_bytes: byte_count(chunk_size as u64),
+        filename,
+        mime_type,
+        output_path: output_path.to_string(),
+        hash_verified: false,
+        damaged_chunks,
+        verified: verification_error.is_none(),
+        verification_error,
+        volumes: None,
+    })
+}
+
+/// 写出一个解密后的分片；稀疏文件中全零的分片不写入，只移动写入位置，在输出文件中留下空洞
+fn write_decrypted_chunk<W: Write + Seek>(output: &mut W, data: &[u8], sparse: bool) -> std::io::Result<()> {
+    if sparse && data.iter().all(|&byte| byte == 0) {
+        output.seek(SeekFrom::Current(data.len() as i64)).map(|_| ())
+    } else {
+        output.write_all(data)
+    }
+}
+
+/// 稀疏文件以空洞结尾时写入位置之后没有数据，按已解密的大小设置输出文件长度
+fn finish_sparse_output(output: &File, len: u64, sparse: bool) -> std::io::Result<()> {
+    if sparse {
+        output.set_len(len)?;
+    }
+    Ok(())
+}
+
+/// 解密已读取文件头的分片数据流并写入输出文件，单个分片文件和分卷共用
+#[allow(clippy::too_many_arguments)]
+fn decrypt_chunked_stream<R: Read + Send + 'static>(input: R, header: format::ChunkedHeader, algo: CryptoAlgorithm, key: &[u8], output_path: fd::Target, options: DecryptOptions, control: &JobControl) -> Result<ChunkDecryptFileResult, ErrorCode> {
+    let original_size = header.original_size;
+    let chunk_size = header.chunk_size;
+    let has_parity = header.container.as_ref().is_some_and(|container| container.has_parity);
+    let sparse = header.sparse();
+    
+    let (filename, mime_type) = match &header.container {
+        Some(container) => (
+            container.decrypt_filename(key)?,
+            container.decrypt_mime_type(key)?,
+        ),
+        None => (None, None),
+    };
+    let output_path = resolve_output_path(&output_path, &filename, options.restore_filename.unwrap_or(false))?;
+    let attributes = stored_attributes(header.container.as_ref(), key, options.restore_attributes.unwrap_or(false))?;
+    check_overwrite(&output_path, options.overwrite)?;
+    
+    // 创建输出文件
+    let output = fd::AtomicOutput::new(&output_path).replace_existing(options.overwrite.unwrap_or(true));
+    let mut output_file = match fd::create(output.path()) {
+        Ok(file) => RingFile::new(file).direct_io(options.direct_io.unwrap_or(false) && !output_path.is_fd()),
+        Err(err) => return Err(Error::new(ErrorCode::IoOpen, format!("Failed to create output file: {}", err))),
+    };
+    // 按文件头记录的明文大小预先分配输出文件的空间；稀疏输出保留空洞，不预先分配
+    if !sparse {
+        reserve_output(&mut output_file, original_size)?;
+    }
+    
+    // 读取线程预读下一个分片的同时，当前线程解密并写出上一个分片
+    let input = ReadAhead::new(input, chunk_size);
+    let mut decoder = ChunkDecoder::new(input, header, algo, key)?;
+    
+    // 读取并解密每个块；文件被截断、分片被篡改时丢弃已写出的部分。明文缓冲区在分片之间复用
+    let mut decrypted = Vec::new();
     loop {
-        match input_file.read_exact(&mut buffer) {
-            Ok(_) => {
-                if buffer[0] == b':' {
-                    break;
+        control.check()?;
+        
+        match decoder.next_chunk_into(&mut decrypted) {
+            Ok(true) => {},
+            Ok(false) => break,
+            Err(err) => return Err(err.into()),
+        }
+        
+        // 写入解密后的数据
+        if let Err(err) = write_decrypted_chunk(&mut output_file, &decrypted, sparse) {
+            return Err(Error::new(ErrorCode::IoWrite, format!("Failed to write decrypted chunk: {}", err)));
+        }
+        
+        let chunk_index = decoder.chunks_read();
+        config::log(LogLevel::Debug, format!("decrypted chunk {} ({} bytes)", chunk_index, decrypted.len()));
+        control.chunk_done(chunk_index, decrypted.len());
+    }
+    
+    // 校验整个文件的 MAC 以及结尾字段记录的明文大小和摘要，失败时丢弃已写出的内容
+    let total_bytes_written = decoder.bytes_decrypted();
+    let chunk_index = decoder.chunks_read();
+    // 等待进行中的写入完成
+    let output_file = match output_file.file() {
+        Ok(file) => file,
+        Err(err) => return Err(Error::new(ErrorCode::IoWrite, format!("Failed to write decrypted chunk: {}", err))),
+    };
+    if let Err(err) = finish_sparse_output(output_file, total_bytes_written, sparse).and_then(|_| prealloc::release(output_file)) {
+        return Err(Error::new(ErrorCode::IoWrite, format!("Failed to write decrypted chunk: {}", err)));
+    }
+    let (mut input_file, hash_verified) = decoder.finish()?;
+    
+    // 严格模式下，解密结果必须与文件头记录的大小一致，且文件末尾不能有多余数据（附加的纠错数据除外）
+    if config::get()?.strict {
+        if total_bytes_written != original_size {
+            return Err(Error::new(ErrorCode::BadHeader, format!(
+                "Decrypted size {} does not match header size {}", total_bytes_written, original_size
+            )));
+        }
+        let mut trailing = [0u8; 1];
+        match input_file.read(&mut trailing) {
+            Ok(_) if has_parity => {},
+            Ok(0) => {},
+            Ok(_) => return Err(Error::new(ErrorCode::BadFormat, "Unexpected trailing data after last chunk")),
+            Err(err) => return Err(Error::new(ErrorCode::IoRead, format!("Error reading input file: {}", err))),
+        }
+    }
+    output.commit()?;
+    if let Some(attributes) = attributes {
+        attributes.restore(output_file);
+    }
+    
+    // 计算KB单位的大小
+    let original_size_kb = (original_size as f64) / 1024.0;
+    let total_bytes_written_kb = (total_bytes_written as f64) / 1024.0;
+    let chunk_size_kb = (chunk_size as f64) / 1024.0;
+    
+    // 创建并返回结果对象
+    Ok(ChunkDecryptFileResult {
+        total_chunks: chunk_index as f64,
+        total_bytes_kb: total_bytes_written_kb,
+        total_bytes: byte_count(total_bytes_written),
+        original_size_kb,
+        original_size_bytes: byte_count(original_size),
+        chunk_size_kb,
+        chunk_size_bytes: byte_count(chunk_size as u64),
+        filename,
+        mime_type,
+        output_path: output_path.to_string(),
+        hash_verified,
+        damaged_chunks: Vec::new(),
+        verified: true,
+        verification_error: None,
+        volumes: None,
+    })
+}
+
+/// 分卷解密 - 校验分卷的顺序、完整性和归属后跨分卷流式解密；volumes 为分卷路径数组，或基础路径/第一个分卷的路径
+#[napi(js_name = "chunkDecryptVolumes")]
+pub fn chunk_decrypt_volumes(algorithm: String, key: Buffer, volumes: Either<String, Vec<String>>, output_path: Either<String, i32>, options: Option<DecryptOptions>) -> Result<ChunkDecryptFileResult, ErrorCode> {
+    let options = options.unwrap_or_default();
+    let control = JobControl::new("chunkDecryptVolumes", options.timeout_ms);
+    control.started();
+    let result = chunk_decrypt_volumes_job(algorithm, key, volumes, output_path, options, &control);
+    control.complete(result)
+}
+
+/// chunkDecryptVolumes() 的异步版本，在 libuv 线程池中运行并返回 Promise
+#[napi(js_name = "chunkDecryptVolumesAsync", ts_return_type = "Promise<ChunkDecryptFileResult>")]
+pub fn chunk_decrypt_volumes_async(algorithm: String, key: Buffer, volumes: Either<String, Vec<String>>, output_path: Either<String, i32>, options: Option<DecryptOptions>, signal: Option<Either<AbortSignal, ClassInstance<Job>>>) -> AsyncTask<FileTask<ChunkDecryptFileResult>> {
+    let options = options.unwrap_or_default();
+    let control = JobControl::new("chunkDecryptVolumes", options.timeout_ms);
+    FileTask::new(control, signal, move |control| chunk_decrypt_volumes_job(algorithm, key, volumes, output_path, options, control))
+}
+
+fn chunk_decrypt_volumes_job(algorithm: String, key: Buffer, volumes: Either<String, Vec<String>>, output_path: Either<String, i32>, options: DecryptOptions, control: &JobControl) -> Result<ChunkDecryptFileResult, ErrorCode> {
+    let output_path = fd::output_path(output_path)?;
+    let algo = parse_algorithm(&algorithm)?;
+    if options.tolerate_errors.unwrap_or(false) {
+        return Err(Error::new(ErrorCode::InvalidArgument, "tolerateErrors is only supported by chunkDecryptFile"));
+    }
+    if options.direct_io.unwrap_or(false) {
+        return Err(Error::new(ErrorCode::InvalidArgument, "directIo is only supported by chunkDecryptFile"));
+    }
+    let paths = match volumes {
+        Either::A(pattern) => volume::discover_volumes(&pattern)?,
+        Either::B(paths) => paths,
+    };
+    let reader = VolumeReader::open(&paths, &key)?;
+    let volume_count = reader.volume_count() as u32;
+    
+    let mut input = BufReader::new(reader);
+    let header = read_chunked_input(&mut input, &algo, &key)?;
+    let mut result = decrypt_chunked_stream(input, header, algo, &key, output_path, options, control)?;
+    result.volumes = Some(volume_count);
+    
+    Ok(result)
+}
+
+/// 整体加密文件转换为分片文件 - 在内存中解密后逐个分片重新加密写出，明文不会写入磁盘
+#[napi(js_name = "convertToChunked")]
+pub fn convert_to_chunked(algorithm: String, key: Buffer, input_path: Either<String, i32>, output_path: Either<String, i32>, chunk_size_mb: u32, options: Option<ConvertOptions>) -> Result<ConvertToChunkedResult, ErrorCode> {
+    let options = options.unwrap_or_default();
+    let control = JobControl::new("convertToChunked", options.timeout_ms);
+    control.started();
+    let result = convert_to_chunked_job(algorithm, key, input_path, output_path, chunk_size_mb, &control);
+    control.complete(result)
+}
+
+/// convertToChunked() 的异步版本，在 libuv 线程池中运行并返回 Promise
+#[napi(js_name = "convertToChunkedAsync", ts_return_type = "Promise<ConvertToChunkedResult>")]
+pub fn convert_to_chunked_async(algorithm: String, key: Buffer, input_path: Either<String, i32>, output_path: Either<String, i32>, chunk_size_mb: u32, options: Option<ConvertOptions>, signal: Option<Either<AbortSignal, ClassInstance<Job>>>) -> AsyncTask<FileTask<ConvertToChunkedResult>> {
+    let options = options.unwrap_or_default();
+    let control = JobControl::new("convertToChunked", options.timeout_ms);
+    FileTask::new(control, signal, move |control| convert_to_chunked_job(algorithm, key, input_path, output_path, chunk_size_mb, control))
+}
+
+fn convert_to_chunked_job(algorithm: String, key: Buffer, input_path: Either<String, i32>, output_path: Either<String, i32>, chunk_size_mb: u32, control: &JobControl) -> Result<ConvertToChunkedResult, ErrorCode> {
+    let input_path = fd::input_path(input_path)?;
+    let output_path = fd::output_path(output_path)?;
+    let algo = parse_algorithm(&algorithm)?;
+    let chunk_size = (chunk_size_mb as usize) * 1024 * 1024;
+    
+    let (data, source, _) = read_monolithic_file(&algo, &key, &input_path, control)?;
+    let file_size = data.len() as u64;
+    
+    // 沿用原文件头中的文件名和元数据，旧格式文件转换后使用新的容器文件头
+    let container = match &source {
+        Some(source) => ContainerHeader::converted_from(source, Layout::Chunked, file_size, chunk_size as u64),
+        None => ContainerHeader::for_new_file(Layout::Chunked, algo.clone(), file_size, chunk_size as u64),
+    };
+    
+    // 创建输出文件
+    let output = fd::AtomicOutput::new(&output_path);
+    let output_file = match fd::create(output.path()) {
+        Ok(file) => file,
+        Err(err) => return Err(Error::new(ErrorCode::IoOpen, format!("Failed to create output file: {}", err))),
+    };
+    let writer = BufWriter::with_capacity(chunk_size.max(1), output_file);
+    let mut encoder = ChunkEncoder::new(writer, algo, &key, Some(&container), file_size, chunk_size)?;
+    
+    for chunk in data.chunks(chunk_size) {
+        control.check().and_then(|_| encoder.write_chunk(chunk))?;
+        control.chunk_done(encoder.chunks_written(), chunk.len());
+    }
+    
+    let total_chunks = encoder.chunks_written();
+    encoder.finish()?;
+    output.commit()?;
+    
+    // 创建并返回结果对象
+    Ok(ConvertToChunkedResult {
+        total_chunks: total_chunks as f64,
+        file_size: (file_size as f64) / 1024.0,
+        file_size_bytes: byte_count(file_size),
+        chunk_size: (chunk_size as f64) / 1024.0,
+        chunk_size_bytes: byte_count(chunk_size as u64),
+    })
+}
+
+/// 分片文件转换为整体加密文件 - 逐个分片解密到内存后整体重新加密写出，明文不会写入磁盘
+#[napi(js_name = "convertToMonolithic")]
+pub fn convert_to_monolithic(algorithm: String, key: Buffer, input_path: Either<String, i32>, output_path: Either<String, i32>, options: Option<ConvertOptions>) -> Result<ConvertToMonolithicResult, ErrorCode> {
+    let options = options.unwrap_or_default();
+    let control = JobControl::new("convertToMonolithic", options.timeout_ms);
+    control.started();
+    let result = convert_to_monolithic_job(algorithm, key, input_path, output_path, &control);
+    control.complete(result)
+}
+
+/// convertToMonolithic() 的异步版本，在 libuv 线程池中运行并返回 Promise
+#[napi(js_name = "convertToMonolithicAsync", ts_return_type = "Promise<ConvertToMonolithicResult>")]
+pub fn convert_to_monolithic_async(algorithm: String, key: Buffer, input_path: Either<String, i32>, output_path: Either<String, i32>, options: Option<ConvertOptions>, signal: Option<Either<AbortSignal, ClassInstance<Job>>>) -> AsyncTask<FileTask<ConvertToMonolithicResult>> {
+    let options = options.unwrap_or_default();
+    let control = JobControl::new("convertToMonolithic", options.timeout_ms);
+    FileTask::new(control, signal, move |control| convert_to_monolithic_job(algorithm, key, input_path, output_path, control))
+}
+
+fn convert_to_monolithic_job(algorithm: String, key: Buffer, input_path: Either<String, i32>, output_path: Either<String, i32>, control: &JobControl) -> Result<ConvertToMonolithicResult, ErrorCode> {
+    let input_path = fd::input_path(input_path)?;
+    let output_path = fd::output_path(output_path)?;
+    let algo = parse_algorithm(&algorithm)?;
+    
+    let (input_file, header) = open_chunked_file(&algo, &key, &input_path)?;
+    let source = header.container.as_ref().map(|source| ContainerHeader::converted_from(source, Layout::Monolithic, 0, 0));
+    let mut decoder = ChunkDecoder::new(input_file, header, algo.clone(), &key)?;
+    
+    // 逐个分片解密，并在读取结尾后校验整个文件
+    let mut data = Vec::new();
+    loop {
+        control.check()?;
+        match decoder.next_chunk()? {
+            Some(chunk) => {
+                control.chunk_done(decoder.chunks_read(), chunk.len());
+                data.extend_from_slice(&chunk);
+            },
+            None => break,
+        }
+    }
+    let total_chunks = decoder.chunks_read();
+    decoder.finish()?;
+    
+    // 沿用原文件头中的文件名和元数据，旧格式文件转换后使用新的容器文件头
+    let mut container = source.unwrap_or_else(|| ContainerHeader::for_new_file(Layout::Monolithic, algo.clone(), 0, 0));
+    container.original_size = data.len() as u64;
+    container.seal_plaintext_hash(&key, &data)?;
+    let header = container.to_bytes(&key)?;
+    
+    // 原文件压缩过时整体压缩后再加密
+    let compressed = match container.compression {
+        Some(compression) => Some(compression.compress(&data)?),
+        None => None,
+    };
+    let encrypted = encrypt(algo, &key, compressed.as_ref().map_or(&data, |(compressed, _)| compressed))
+        .map_err(|e| e.context("Encryption error"))?;
+    
+    control.check()?;
+    
+    // 写入加密数据到输出文件
+    let output = fd::AtomicOutput::new(&output_path);
+    let mut output_file = match fd::create(output.path()) {
+        Ok(file) => file,
+        Err(err) => return Err(Error::new(ErrorCode::IoOpen, format!("Failed to create output file: {}", err))),
+    };
+    let written = output_file.write_all(&header).and_then(|_| output_file.write_all(&encrypted));
+    if let Err(err) = written {
+        return Err(Error::new(ErrorCode::IoWrite, format!("Failed to write encrypted data: {}", err)));
+    }
+    output.commit()?;
+    
+    // 创建并返回结果对象
+    Ok(ConvertToMonolithicResult {
+        total_chunks: total_chunks as f64,
+        file_size: (data.len() as f64) / 1024.0,
+        file_size_bytes: byte_count(data.len() as u64),
+    })
+}
+
+/// 逐个分片解密后按 chunk_size 重新加密写入 output_path，明文不落盘；全部校验通过后才替换输出文件
+///
+/// 原文件头中的文件名、元数据和创建时间会被沿用，旧格式文件输出为当前的 v2 容器格式。
+fn rewrite_chunked_file(algo: &CryptoAlgorithm, key: &[u8], input_file: BufReader<File>, header: format::ChunkedHeader, output_path: &fd::Target, chunk_size: usize, control: &JobControl) -> std::result::Result<(), CodedError> {
+    let original_size = header.original_size;
+    let container = match &header.container {
+        Some(source) => ContainerHeader::converted_from(source, Layout::Chunked, original_size, chunk_size as u64),
+        None => ContainerHeader::for_new_file(Layout::Chunked, algo.clone(), original_size, chunk_size as u64),
+    };
+    let mut decoder = ChunkDecoder::new(input_file, header, algo.clone(), key)?;
+    
+    // 创建输出文件
+    let output = fd::AtomicOutput::new(output_path);
+    let output_file = fd::create(output.path()).map_err(|e| CodedError::io(ErrorCode::IoOpen, "Failed to create output file", e))?;
+    let writer = BufWriter::with_capacity(chunk_size.max(1), output_file);
+    let mut encoder = ChunkEncoder::new(writer, algo.clone(), key, Some(&container), original_size, chunk_size)?;
+    
+    // 逐个分片解密并送入新的分片写入器，明文缓冲区在分片之间复用
+    let mut chunk = Vec::new();
+    while decoder.next_chunk_into(&mut chunk)? {
+        control.check()?;
+        encoder.write(&chunk)?;
+        control.chunk_done(decoder.chunks_read(), chunk.len());
+    }
+    if decoder.bytes_decrypted() != original_size {
+        return Err(CodedError::bad_header(format!(
+            "Decrypted size {} does not match header size {}", decoder.bytes_decrypted(), original_size
+        )));
+    }
+    decoder.finish()?;
+    encoder.finish()?;
+    output.commit()
+}
+
+/// 重新分片 - 逐个分片解密后按新的分片大小重新加密写出，明文不会写入磁盘
+#[napi(js_name = "rechunkFile")]
+pub fn rechunk_file(algorithm: String, key: Buffer, input_path: Either<String, i32>, output_path: Either<String, i32>, new_chunk_size_mb: u32, options: Option<ConvertOptions>) -> Result<RechunkFileResult, ErrorCode> {
+    let options = options.unwrap_or_default();
+    let control = JobControl::new("rechunkFile", options.timeout_ms);
+    control.started();
+    let result = rechunk_file_job(algorithm, key, input_path, output_path, new_chunk_size_mb, &control);
+    control.complete(result)
+}
+
+/// rechunkFile() 的异步版本，在 libuv 线程池中运行并返回 Promise
+#[napi(js_name = "rechunkFileAsync", ts_return_type = "Promise<RechunkFileResult>")]
+pub fn rechunk_file_async(algorithm: String, key: Buffer, input_path: Either<String, i32>, output_path: Either<String, i32>, new_chunk_size_mb: u32, options: Option<ConvertOptions>, signal: Option<Either<AbortSignal, ClassInstance<Job>>>) -> AsyncTask<FileTask<RechunkFileResult>> {
+    let options = options.unwrap_or_default();
+    let control = JobControl::new("rechunkFile", options.timeout_ms);
+    FileTask::new(control, signal, move |control| rechunk_file_job(algorithm, key, input_path, output_path, new_chunk_size_mb, control))
+}
+
+fn rechunk_file_job(algorithm: String, key: Buffer, input_path: Either<String, i32>, output_path: Either<String, i32>, new_chunk_size_mb: u32, control: &JobControl) -> Result<RechunkFileResult, ErrorCode> {
+    let input_path = fd::input_path(input_path)?;
+    let output_path = fd::output_path(output_path)?;
+    let algo = parse_algorithm(&algorithm)?;
+    let chunk_size = (new_chunk_size_mb as usize) * 1024 * 1024;
+    
+    // 边读边写，输出与输入相同时经临时文件替换输入
+    check_in_place(&input_path, &output_path)?;
+    
+    let (input_file, header) = open_chunked_file(&algo, &key, &input_path)?;
+    let original_size = header.original_size;
+    let source_chunks = header.total_chunks();
+    rewrite_chunked_file(&algo, &key, input_file, header, &output_path, chunk_size, control)?;
+    
+    // 创建并返回结果对象
+    Ok(RechunkFileResult {
+        source_chunks: source_chunks as f64,
+        total_chunks: original_size.div_ceil(chunk_size as u64) as f64,
+        file_size: (original_size as f64) / 1024.0,
+        file_size_bytes: byte_count(original_size),
+        chunk_size: (chunk_size as f64) / 1024.0,
+        chunk_size_bytes: byte_count(chunk_size as u64),
+    })
+}
+
+/// 迁移单个文件：旧的 `CHUNKS:` 文件和缺少认证等特性的早期 v2 分片文件按原分片大小改写为当前格式，并原地替换
+fn migrate_one(algo: &CryptoAlgorithm, key: &[u8], path: &str, control: &JobControl) -> Result<MigrateFileResult, ErrorCode> {
+    let target = fd::Target::from(path);
+    let (input_file, header) = open_chunked_file(algo, key, &target)?;
+    let from_version = if header.container.is_some() { 2 } else { 1 };
+    let original_size = header.original_size;
+    let chunk_size = header.chunk_size;
+    let total_chunks = header.total_chunks();
+    let up_to_date = header.container.as_ref().is_some_and(|container| {
+        container.header_mac.is_some()
+            && container.authenticated_chunks
+            && container.sequenced_chunks
+            && container.binary_frames
+            && container.has_footer
+    });
+    let size_before = std::fs::metadata(path).map(|metadata| metadata.len()).unwrap_or(0);
+    
+    let mut report = MigrateFileResult {
+        path: path.to_string(),
+        status: "skipped".to_string(),
+        from_version: Some(from_version),
+        original_size_kb: Some((original_size as f64) / 1024.0),
+        original_size_bytes: Some(byte_count(original_size)),
+        chunk_size_kb: Some((chunk_size as f64) / 1024.0),
+        chunk_size_bytes: Some(byte_count(chunk_size as u64)),
+        total_chunks: Some(total_chunks as f64),
+        encrypted_size_before_kb: Some((size_before as f64) / 1024.0),
+        encrypted_size_before_bytes: Some(byte_count(size_before)),
+        encrypted_size_after_kb: Some((size_before as f64) / 1024.0),
+        encrypted_size_after_bytes: Some(byte_count(size_before)),
+        error: None,
+    };
+    if up_to_date {
+        drop(input_file);
+        return Ok(report);
+    }
+    
+    // 先写入同目录下的临时文件，全部校验通过后再替换原文件，并保留原文件的权限
+    rewrite_chunked_file(algo, key, input_file, header, &target, chunk_size, control)?;
+    let size_after = std::fs::metadata(path).map(|metadata| metadata.len()).unwrap_or(0);
+    
+    report.status = "migrated".to_string();
+    report.encrypted_size_after_kb = Some((size_after as f64) / 1024.0);
+    report.encrypted_size_after_bytes = Some(byte_count(size_after));
+    Ok(report)
+}
+
+/// 迁移旧格式文件 - 将 v1 `CHUNKS:` 分片文件原地改写为带认证的 v2 容器格式，保留原始大小和分片大小，返回迁移报告
+#[napi(js_name = "migrateFile")]
+pub fn migrate_file(algorithm: String, key: Buffer, path: String, options: Option<ConvertOptions>) -> Result<MigrateFileResult, ErrorCode> {
+    let options = options.unwrap_or_default();
+    let control = JobControl::new("migrateFile", options.timeout_ms);
+    control.started();
+    let result = parse_algorithm(&algorithm).and_then(|algo| migrate_one(&algo, &key, &path, &control));
+    control.complete(result)
+}
+
+/// migrateFile() 的异步版本，在 libuv 线程池中运行并返回 Promise
+#[napi(js_name = "migrateFileAsync", ts_return_type = "Promise<MigrateFileResult>")]
+pub fn migrate_file_async(algorithm: String, key: Buffer, path: String, options: Option<ConvertOptions>, signal: Option<Either<AbortSignal, ClassInstance<Job>>>) -> AsyncTask<FileTask<MigrateFileResult>> {
+    let options = options.unwrap_or_default();
+    let control = JobControl::new("migrateFile", options.timeout_ms);
+    FileTask::new(control, signal, move |control| parse_algorithm(&algorithm).and_then(|algo| migrate_one(&algo, &key, &path, control)))
+}
+
+/// 批量迁移旧格式文件 - 逐个迁移，单个文件失败不影响其它文件，失败原因记录在对应的报告中
+#[napi(js_name = "migrateFiles")]
+pub fn migrate_files(algorithm: String, key: Buffer, paths: Vec<String>, options: Option<ConvertOptions>) -> Result<Vec<MigrateFileResult>, ErrorCode> {
+    let options = options.unwrap_or_default();
+    let control = JobControl::new("migrateFiles", options.timeout_ms);
+    control.started();
+    let result = migrate_files_job(algorithm, key, paths, &control);
+    control.complete(result)
+}
+
+/// migrateFiles() 的异步版本，在 libuv 线程池中运行并返回 Promise
+#[napi(js_name = "migrateFilesAsync", ts_return_type = "Promise<Array<MigrateFileResult>>")]
+pub fn migrate_files_async(algorithm: String, key: Buffer, paths: Vec<String>, options: Option<ConvertOptions>, signal: Option<Either<AbortSignal, ClassInstance<Job>>>) -> AsyncTask<FileTask<Vec<MigrateFileResult>>> {
+    let options = options.unwrap_or_default();
+    let control = JobControl::new("migrateFiles", options.timeout_ms);
+    FileTask::new(control, signal, move |control| migrate_files_job(algorithm, key, paths, control))
+}
+
+fn migrate_files_job(algorithm: String, key: Buffer, paths: Vec<String>, control: &JobControl) -> Result<Vec<MigrateFileResult>, ErrorCode> {
+    let algo = parse_algorithm(&algorithm)?;
+    let mut reports = Vec::with_capacity(paths.len());
+    for path in &paths {
+        control.check()?;
+        let report = match migrate_one(&algo, &key, path, control) {
+            Ok(report) => report,
+            Err(err) => MigrateFileResult {
+                path: path.clone(),
+                status: "failed".to_string(),
+                from_version: None,
+                original_size_kb: None,
+                original_size_bytes: None,
+                chunk_size_kb: None,
+                chunk_size_bytes: None,
+                total_chunks: None,
+                encrypted_size_before_kb: None,
+                encrypted_size_before_bytes: None,
+                encrypted_size_after_kb: None,
+                encrypted_size_after_bytes: None,
+                error: Some(err.reason),
+            },
+        };
+        reports.push(report);
+    }
+    Ok(reports)
+}
+
+/// 增量更新分片文件 - 明文修改后（大小不变）依据分片校验值只重新加密变化的分片，并更新分片索引中的校验值、明文摘要和文件 MAC
+#[napi(js_name = "updateEncryptedFile")]
+pub fn update_encrypted_file(plain_path: Either<String, i32>, encrypted_path: String, key: Buffer, options: Option<ConvertOptions>) -> Result<UpdateEncryptedFileResult, ErrorCode> {
+    let options = options.unwrap_or_default();
+    let control = JobControl::new("updateEncryptedFile", options.timeout_ms);
+    control.started();
+    let result = update_encrypted_file_job(plain_path, encrypted_path, key, &control);
+    control.complete(result)
+}
+
+/// updateEncryptedFile() 的异步版本，在 libuv 线程池中运行并返回 Promise
+#[napi(js_name = "updateEncryptedFileAsync", ts_return_type = "Promise<UpdateEncryptedFileResult>")]
+pub fn update_encrypted_file_async(plain_path: Either<String, i32>, encrypted_path: String, key: Buffer, options: Option<ConvertOptions>, signal: Option<Either<AbortSignal, ClassInstance<Job>>>) -> AsyncTask<FileTask<UpdateEncryptedFileResult>> {
+    let options = options.unwrap_or_default();
+    let control = JobControl::new("updateEncryptedFile", options.timeout_ms);
+    FileTask::new(control, signal, move |control| update_encrypted_file_job(plain_path, encrypted_path, key, control))
+}
+
+fn update_encrypted_file_job(plain_path: Either<String, i32>, encrypted_path: String, key: Buffer, control: &JobControl) -> Result<UpdateEncryptedFileResult, ErrorCode> {
+    let plain_path = fd::input_path(plain_path)?;
+    runtime::ensure_ready()?;
+    let input_file = match fd::open(&encrypted_path) {
+        Ok(file) => file,
+        Err(err) => return Err(Error::new(ErrorCode::IoOpen, format!("Failed to open input file: {}", err))),
+    };
+    let header = format::read_chunked_header(&mut BufReader::new(input_file))?;
+    let algo = match &header.container {
+        Some(container) => container.algorithm.clone(),
+        None => return Err(Error::new(ErrorCode::InvalidArgument, "Delta updates require a chunk index and chunk checksums - re-encrypt with chunkEncryptFile first")),
+    };
+    check_chunked_header(&header, &algo, &key)?;
+    
+    let report = delta::update_file(&plain_path, &encrypted_path, header, &algo, &key, || control.check(), |index, bytes| control.chunk_done(index, bytes))?;
+    
+    // 创建并返回结果对象
+    Ok(UpdateEncryptedFileResult {
+        total_chunks: report.total_chunks as f64,
+        changed_chunks: report.changed_chunks as f64,
+        rewritten_size_kb: (report.rewritten_bytes as f64) / 1024.0,
+        rewritten_size_bytes: byte_count(report.rewritten_bytes),
+        in_place: report.in_place,
+    })
+}
+
+/// 追加数据 - 在用 appendable 选项创建的分片文件末尾追加加密数据，之前的完整分片不会重写，适合持续增长的日志
+#[napi(js_name = "appendChunkedFile")]
+pub fn append_chunked_file(encrypted_path: String, key: Buffer, data: Buffer, options: Option<ConvertOptions>) -> Result<AppendChunkedFileResult, ErrorCode> {
+    let options = options.unwrap_or_default();
+    let control = JobControl::new("appendChunkedFile", options.timeout_ms);
+    control.started();
+    let result = append_chunked_file_job(encrypted_path, key, data, &control);
+    control.complete(result)
+}
+
+/// appendChunkedFile() 的异步版本，在 libuv 线程池中运行并返回 Promise
+#[napi(js_name = "appendChunkedFileAsync", ts_return_type = "Promise<AppendChunkedFileResult>")]
+pub fn append_chunked_file_async(encrypted_path: String, key: Buffer, data: Buffer, options: Option<ConvertOptions>, signal: Option<Either<AbortSignal, ClassInstance<Job>>>) -> AsyncTask<FileTask<AppendChunkedFileResult>> {
+    let options = options.unwrap_or_default();
+    let control = JobControl::new("appendChunkedFile", options.timeout_ms);
+    FileTask::new(control, signal, move |control| append_chunked_file_job(encrypted_path, key, data, control))
+}
+
+fn append_chunked_file_job(encrypted_path: String, key: Buffer, data: Buffer, control: &JobControl) -> Result<AppendChunkedFileResult, ErrorCode> {
+    let input_file = match fd::open(&encrypted_path) {
+        Ok(file) => file,
+        Err(err) => return Err(Error::new(ErrorCode::IoOpen, format!("Failed to open input file: {}", err))),
+    };
+    let header = format::read_chunked_header(&mut BufReader::new(input_file))?;
+    let algo = match &header.container {
+        Some(container) => container.algorithm.clone(),
+        None => return Err(Error::new(ErrorCode::InvalidArgument, "File is not appendable - create it with chunkEncryptFile and the appendable option")),
+    };
+    check_chunked_header(&header, &algo, &key)?;
+    
+    let report = append::append_file(&encrypted_path, header, &algo, &key, &data, || control.check(), |index, bytes| control.chunk_done(index, bytes))?;
+    
+    // 创建并返回结果对象
+    Ok(AppendChunkedFileResult {
+        total_chunks: report.total_chunks as f64,
+        written_chunks: report.written_chunks as f64,
+        appended_size_kb: (data.len() as f64) / 1024.0,
+        appended_size_bytes: byte_count(data.len() as u64),
+        file_size_kb: (report.original_size as f64) / 1024.0,
+        file_size_bytes: byte_count(report.original_size),
+    })
+}
+
+/// 校验分片文件 - 逐个分片在内存中解密和认证，不写出任何明文；返回每个分片的状态和整体结果
+#[napi(js_name = "verifyChunkedFile")]
+pub fn verify_chunked_file(path: Either<String, i32>, key: Buffer, options: Option<VerifyOptions>) -> Result<VerifyChunkedFileResult, ErrorCode> {
+    let options = options.unwrap_or_default();
+    let control = JobControl::new("verifyChunkedFile", options.timeout_ms);
+    control.started();
+    let result = verify_chunked_file_job(path, key, options.algorithm, &control);
+    control.complete(result)
+}
+
+/// verifyChunkedFile() 的异步版本，在 libuv 线程池中运行并返回 Promise
+#[napi(js_name = "verifyChunkedFileAsync", ts_return_type = "Promise<VerifyChunkedFileResult>")]
+pub fn verify_chunked_file_async(path: Either<String, i32>, key: Buffer, options: Option<VerifyOptions>, signal: Option<Either<AbortSignal, ClassInstance<Job>>>) -> AsyncTask<FileTask<VerifyChunkedFileResult>> {
+    let options = options.unwrap_or_default();
+    let control = JobControl::new("verifyChunkedFile", options.timeout_ms);
+    FileTask::new(control, signal, move |control| verify_chunked_file_job(path, key, options.algorithm, control))
+}
+
+fn verify_chunked_file_job(path: Either<String, i32>, key: Buffer, algorithm: Option<String>, control: &JobControl) -> Result<VerifyChunkedFileResult, ErrorCode> {
+    let path = fd::input_path(path)?;
+    runtime::ensure_ready()?;
+    let input_file = match fd::open(&path) {
+        Ok(file) => file,
+        Err(err) => return Err(Error::new(ErrorCode::IoOpen, format!("Failed to open input file: {}", err))),
+    };
+    let mut input_file = BufReader::new(input_file);
+    let header = format::read_chunked_header(&mut input_file)?;
+    
+    // v2 文件头记录了算法，旧格式文件需要调用方指定
+    let algo = match (&algorithm, &header.container) {
+        (Some(algorithm), _) => parse_algorithm(algorithm)?,
+        (None, Some(container)) => parse_algorithm(container.algorithm.as_str())?,
+        (None, None) => return Err(Error::new(ErrorCode::InvalidArgument, "Legacy files do not record the algorithm - pass options.algorithm")),
+    };
+    check_chunked_header(&header, &algo, &key)?;
+    let original_size = header.original_size;
+    let chunk_size = header.chunk_size;
+    
+    let mut chunks = TolerantChunkReader::new(input_file, header, algo, &key)?;
+    let mut chunk_reports = Vec::new();
+    while let Some((index, outcome)) = chunks.next_chunk() {
+        control.check()?;
+        let report = match outcome {
+            ChunkOutcome::Decrypted(data) => {
+                let start = chunks.decoder().bytes_decrypted() - data.len() as u64;
+                control.chunk_done(index + 1, data.len());
+                ChunkReport {
+                    index,
+                    status: "ok".to_string(),
+                    start: start as f64,
+                    end: chunks.decoder().bytes_decrypted() as f64,
+                    error: None,
+                }
+            },
+            ChunkOutcome::Damaged { start, len, error } => {
+                config::log(LogLevel::Warn, format!("damaged chunk {} in {}: {}", index, path, error));
+                control.chunk_done(index + 1, len as usize);
+                ChunkReport {
+                    index,
+                    status: "damaged".to_string(),
+                    start: start as f64,
+                    end: (start + len) as f64,
+                    error: Some(error),
                 }
-                chunk_size_str.push(buffer[0] as char);
             },
-            Err(err) => return Err(Error::from_reason(format!("Error reading chunk size: {}", err))),
+        };
+        chunk_reports.push(report);
+    }
+    
+    // 所有分片完好时再校验整个文件的 MAC 以及结尾字段记录的明文大小和摘要
+    let total_chunks = chunks.decoder().chunks_read();
+    let damaged_chunks = chunks.damaged_chunks();
+    let verification_error = chunks.finish();
+    
+    // 创建并返回结果对象
+    Ok(VerifyChunkedFileResult {
+        path: path.to_string(),
+        passed: verification_error.is_none(),
+        total_chunks: total_chunks as f64,
+        damaged_chunks: damaged_chunks as f64,
+        original_size_kb: (original_size as f64) / 1024.0,
+        original_size_bytes: byte_count(original_size),
+        chunk_size_kb: (chunk_size as f64) / 1024.0,
+        chunk_size_bytes: byte_count(chunk_size as u64),
+        error: verification_error,
+        chunks: chunk_reports,
+    })
+}
+
+/// 单个分片的解密 - 用于视频实时播放场景
+#[napi(js_name = "decryptSingleChunk")]
+pub fn decrypt_single_chunk(algorithm: String, key: Buffer, input_path: Either<String, i32>, chunk_index: u32) -> Result<Buffer, ErrorCode> {
+    decrypt_single_chunk_job(algorithm, key, input_path, chunk_index)
+}
+
+fn decrypt_single_chunk_job(algorithm: String, key: Buffer, input_path: Either<String, i32>, chunk_index: u32) -> Result<Buffer, ErrorCode> {
+    let input_path = fd::input_path(input_path)?;
+    let algo = parse_algorithm(&algorithm)?;
+    
+    let (mut input_file, header) = open_chunked_file(&algo, &key, &input_path)?;
+    let decrypted = read_single_chunk(&mut input_file, &header, &algo, &key, chunk_index as u64)?;
+    
+    // 将解密后的数据返回为Buffer
+    Ok(Buffer::from(decrypted))
+}
+
+/// decryptSingleChunk() 的异步版本，在 libuv 线程池中运行并返回 Promise
+#[napi(js_name = "decryptSingleChunkAsync", ts_return_type = "Promise<Buffer>")]
+pub fn decrypt_single_chunk_async(algorithm: String, key: Buffer, input_path: Either<String, i32>, chunk_index: u32, signal: Option<Either<AbortSignal, ClassInstance<Job>>>) -> AsyncTask<FileTask<Buffer>> {
+    FileTask::plain(signal, move || decrypt_single_chunk_job(algorithm, key, input_path, chunk_index))
+}
+
+/// 随机读取并解密一个分片：有分片索引时直接定位，否则逐个跳过前面的分片；只校验该分片自身的认证标签、序号和校验值
+fn read_single_chunk(input_file: &mut BufReader<File>, header: &format::ChunkedHeader, algo: &CryptoAlgorithm, key: &[u8], chunk_index: u64) -> Result<Vec<u8>, ErrorCode> {
+    let frame_format = header.frame_format();
+    if chunk_index >= header.total_chunks() {
+        return Err(Error::new(ErrorCode::InvalidArgument, format!(
+            "Chunk index {} out of range ({} chunks)", chunk_index, header.total_chunks()
+        )));
+    }
+    
+    // 有分片索引时直接定位目标分块，否则从第一个分块开始逐个跳过前面的分块
+    let has_index = header.container.as_ref().is_some_and(|container| container.has_footer);
+    let offset = match has_index {
+        true => format::locate_chunk(input_file, chunk_index)?,
+        false => header.header_len,
+    };
+    if offset < header.header_len {
+        return Err(Error::new(ErrorCode::BadHeader, "Invalid chunk index in file footer"));
+    }
+    if let Err(err) = input_file.seek(SeekFrom::Start(offset)) {
+        return Err(Error::new(ErrorCode::IoRead, format!("Error seeking to chunk: {}", err)));
+    }
+    let mut current_chunk = if has_index { chunk_index } else { 0 };
+    while current_chunk < chunk_index {
+        frame_format.skip_frame(input_file, &format!("chunk {}", current_chunk + 1))?;
+        current_chunk += 1;
+    }
+    
+    // 读取目标块的加密数据
+    let encrypted_chunk = frame_format.read_frame(input_file, &format!("chunk {}", chunk_index + 1), header.max_frame_len())?;
+    let decrypted = decrypt_chunk_frame(header, algo, key, chunk_index, &encrypted_chunk)?;
+    
+    // 分片索引记录了分片明文校验值时，核对取回的这一个分片
+    if has_index {
+        let expected = format::chunk_checksum(input_file, chunk_index)?;
+        check_chunk_checksum(key, chunk_index, &decrypted, expected)?;
+    }
+    
+    Ok(decrypted)
+}
+
+/// 解密随机读取的一个分片帧；随机访问时只能校验该分片自身的认证标签和序号
+fn decrypt_chunk_frame(header: &format::ChunkedHeader, algo: &CryptoAlgorithm, key: &[u8], chunk_index: u64, encrypted_chunk: &[u8]) -> Result<Vec<u8>, ErrorCode> {
+    // v2 分片文件总是带分片认证（读取文件头时已检查），只有 v1 文件没有
+    let decrypted = if header.container.is_some() {
+        let sequence = header.chunk_sequence_total().map(|total| (chunk_index, total));
+        crypto::decrypt_single_chunk(algo, key, encrypted_chunk, sequence)
+    } else {
+        decrypt(algo.clone(), key, encrypted_chunk)
+    };
+    let decrypted = match decrypted {
+        Ok(data) => data,
+        Err(err) => return Err(err.context("Chunk decryption error").into()),
+    };
+    header.restore_chunk(chunk_index, decrypted).map_err(Error::from)
+}
+
+/// 核对分片明文与分片索引中记录的校验值，早期文件没有校验值时跳过
+fn check_chunk_checksum(key: &[u8], chunk_index: u64, decrypted: &[u8], expected: Option<[u8; crypto::CHUNK_CHECKSUM_LEN]>) -> Result<(), ErrorCode> {
+    if expected.is_some_and(|expected| expected != crypto::ChunkChecksum::new(key).compute(decrypted)) {
+        return Err(Error::new(ErrorCode::AuthFailed, format!("Chunk checksum mismatch in chunk {}", chunk_index)));
+    }
+    Ok(())
+}
+
+/// 读取文件末尾的明文 - 通过分片索引只解密返回最后 bytes 个字节所需的最后几个分片，用于查看持续追加的加密日志的最新内容
+#[napi(js_name = "decryptTail")]
+pub fn decrypt_tail(path: Either<String, i32>, key: Buffer, bytes: u32) -> Result<Buffer, ErrorCode> {
+    decrypt_tail_job(path, key, bytes)
+}
+
+fn decrypt_tail_job(path: Either<String, i32>, key: Buffer, bytes: u32) -> Result<Buffer, ErrorCode> {
+    let path = fd::input_path(path)?;
+    let input_file = match fd::open(&path) {
+        Ok(file) => file,
+        Err(err) => return Err(Error::new(ErrorCode::IoOpen, format!("Failed to open input file: {}", err))),
+    };
+    let mut input_file = BufReader::new(input_file);
+    let header = format::read_chunked_header(&mut input_file)?;
+    let algo = match &header.container {
+        Some(container) => container.algorithm.clone(),
+        None => return Err(Error::new(ErrorCode::InvalidArgument, "Legacy files do not record the algorithm - use decryptSingleChunk")),
+    };
+    check_chunked_header(&header, &algo, &key)?;
+    
+    // 文件不足 bytes 个字节时返回全部明文
+    let start = header.original_size.saturating_sub(bytes as u64);
+    let first_chunk = start / header.chunk_size as u64;
+    let mut tail = Vec::with_capacity((header.original_size - start) as usize);
+    for chunk_index in first_chunk..header.total_chunks() {
+        let data = read_single_chunk(&mut input_file, &header, &algo, &key, chunk_index)?;
+        let skip = start.saturating_sub(chunk_index * header.chunk_size as u64) as usize;
+        tail.extend_from_slice(&data[skip.min(data.len())..]);
+    }
+    
+    Ok(Buffer::from(tail))
+}
+
+/// decryptTail() 的异步版本，在 libuv 线程池中运行并返回 Promise
+#[napi(js_name = "decryptTailAsync", ts_return_type = "Promise<Buffer>")]
+pub fn decrypt_tail_async(path: Either<String, i32>, key: Buffer, bytes: u32, signal: Option<Either<AbortSignal, ClassInstance<Job>>>) -> AsyncTask<FileTask<Buffer>> {
+    FileTask::plain(signal, move || decrypt_tail_job(path, key, bytes))
+}
+
+/// 随机读取分片加密文件的明文 - 只打开一次文件、读取一次文件头和分片索引，适合视频拖动播放等频繁的随机读取
+///
+/// 没有分片索引的文件在打开时逐个跳过分片帧建立索引。每次 read() 只解密覆盖读取范围的分片，
+/// 并保留最近解密的一个分片，连续的小块读取不会重复解密同一分片；设置 prefetch 时在后台预先解密之后的分片。
+#[napi]
+pub struct DecryptedReader {
+    state: Option<ReaderState>,
+}
+
+struct ReaderState {
+    source: Arc<ChunkSource>,
+    /// 设置了 prefetch 时在后台预先解密之后的分片
+    read_ahead: Option<ChunkReadAhead<Result<Vec<u8>, ErrorCode>>>,
+    /// 最近解密的分片序号和明文
+    cached_chunk: Option<(u64, Vec<u8>)>,
+}
+
+/// 打开的分片文件及其分片位置，可以同时在 JS 线程和预取线程中解密分片
+struct ChunkSource {
+    /// 读取分片帧时加锁，解密在锁外进行
+    input_file: Mutex<BufReader<File>>,
+    header: format::ChunkedHeader,
+    algo: CryptoAlgorithm,
+    key: Vec<u8>,
+    /// 每个分片帧在文件中的起始位置
+    chunk_offsets: Vec<u64>,
+    /// 分片索引记录的分片明文校验值，早期文件和没有分片索引的文件为空
+    chunk_checksums: Vec<[u8; crypto::CHUNK_CHECKSUM_LEN]>,
+}
+
+#[napi]
+impl DecryptedReader {
+    #[napi(constructor)]
+    pub fn new(algorithm: String, key: Buffer, input_path: Either<String, i32>, options: Option<ReaderOptions>) -> Result<Self, ErrorCode> {
+        let state = open_decrypted_reader(algorithm, key, input_path, options.unwrap_or_default())?;
+        Ok(DecryptedReader { state: Some(state) })
+    }
+
+    /// 明文总大小
+    #[napi(getter)]
+    pub fn size(&self) -> f64 {
+        self.state.as_ref().map_or(0.0, |state| state.source.header.original_size as f64)
+    }
+
+    /// 读取从 offset 开始的 length 个明文字节；超出文件末尾的部分不返回，offset 位于末尾或之后时返回空 Buffer
+    #[napi]
+    pub fn read(&mut self, offset: f64, length: u32) -> Result<Buffer, ErrorCode> {
+        self.read_range(offset, length)
+    }
+
+    /// 关闭文件，之后不能再读取；重复调用没有影响
+    #[napi]
+    pub fn close(&mut self) {
+        self.state = None;
+    }
+}
+
+impl DecryptedReader {
+    fn read_range(&mut self, offset: f64, length: u32) -> Result<Buffer, ErrorCode> {
+        let Some(state) = self.state.as_mut() else {
+            return Err(Error::new(ErrorCode::InvalidArgument, "DecryptedReader is closed"));
+        };
+        if offset < 0.0 || offset.fract() != 0.0 || offset > ((1u64 << 53) - 1) as f64 {
+            return Err(Error::new(ErrorCode::InvalidArgument, format!("Read offset must be a non-negative integer: {}", offset)));
+        }
+        let start = (offset as u64).min(state.source.header.original_size);
+        let end = start.saturating_add(length as u64).min(state.source.header.original_size);
+        let chunk_size = state.source.header.chunk_size as u64;
+        let mut data = Vec::with_capacity((end - start) as usize);
+        let mut position = start;
+        while position < end {
+            let chunk_index = position / chunk_size;
+            let chunk = state.chunk(chunk_index)?;
+            let skip = (position - chunk_index * chunk_size) as usize;
+            let take = (chunk.len().saturating_sub(skip) as u64).min(end - position) as usize;
+            if take == 0 {
+                return Err(Error::new(ErrorCode::Truncated, format!("{}: chunk {} is shorter than expected", format::ERR_TRUNCATED, chunk_index + 1)));
+            }
+            data.extend_from_slice(&chunk[skip..skip + take]);
+            position += take as u64;
+        }
+        Ok(Buffer::from(data))
+    }
+}
+
+/// 分片加密文件的句柄 - 只打开一次文件、校验一次文件头并读取分片索引，之后按序号解密分片
+///
+/// 用于代替对同一文件重复调用 decryptSingleChunk，省去每次打开文件、解析文件头和定位分片的开销。
+#[napi]
+pub struct ChunkedFileHandle {
+    state: Option<ReaderState>,
+}
+
+#[napi]
+impl ChunkedFileHandle {
+    #[napi(constructor)]
+    pub fn new(algorithm: String, key: Buffer, input_path: Either<String, i32>, options: Option<ReaderOptions>) -> Result<Self, ErrorCode> {
+        let state = open_decrypted_reader(algorithm, key, input_path, options.unwrap_or_default())?;
+        Ok(ChunkedFileHandle { state: Some(state) })
+    }
+
+    /// 明文总大小
+    #[napi(getter)]
+    pub fn size(&self) -> f64 {
+        self.state.as_ref().map_or(0.0, |state| state.source.header.original_size as f64)
+    }
+
+    /// 分片大小（字节）
+    #[napi(getter)]
+    pub fn chunk_size(&self) -> f64 {
+        self.state.as_ref().map_or(0.0, |state| state.source.header.chunk_size as f64)
+    }
+
+    /// 分片总数
+    #[napi(getter)]
+    pub fn total_chunks(&self) -> f64 {
+        self.state.as_ref().map_or(0.0, |state| state.source.header.total_chunks() as f64)
+    }
+
+    /// 解密并返回第 chunk_index 个分片（从 0 开始）；与 decryptSingleChunk 相同，只校验该分片自身的标签、序号和校验值
+    #[napi]
+    pub fn decrypt_chunk(&mut self, chunk_index: u32) -> Result<Buffer, ErrorCode> {
+        self.read_chunk(chunk_index as u64)
+    }
+
+    /// 关闭文件，之后不能再解密；重复调用没有影响
+    #[napi]
+    pub fn close(&mut self) {
+        self.state = None;
+    }
+}
+
+impl ChunkedFileHandle {
+    fn read_chunk(&mut self, chunk_index: u64) -> Result<Buffer, ErrorCode> {
+        let Some(state) = self.state.as_mut() else {
+            return Err(Error::new(ErrorCode::InvalidArgument, "ChunkedFileHandle is closed"));
+        };
+        if chunk_index >= state.source.header.total_chunks() {
+            return Err(Error::new(ErrorCode::InvalidArgument, format!(
+                "Chunk index {} out of range ({} chunks)", chunk_index, state.source.header.total_chunks()
+            )));
+        }
+        state.decrypt_chunk(chunk_index).map(Buffer::from)
+    }
+}
+
+impl ReaderState {
+    /// 取得一个分片的明文，与最近解密的分片相同时直接返回
+    fn chunk(&mut self, chunk_index: u64) -> Result<&[u8], ErrorCode> {
+        if self.cached_chunk.as_ref().is_none_or(|(cached, _)| *cached != chunk_index) {
+            let decrypted = self.decrypt_chunk(chunk_index)?;
+            self.cached_chunk = Some((chunk_index, decrypted));
+        }
+        Ok(&self.cached_chunk.as_ref().unwrap().1)
+    }
+
+    /// 解密一个分片，设置了 prefetch 时优先取用后台已解密的分片；chunk_index 必须小于分片总数
+    fn decrypt_chunk(&mut self, chunk_index: u64) -> Result<Vec<u8>, ErrorCode> {
+        match &self.read_ahead {
+            Some(read_ahead) => read_ahead.get(chunk_index),
+            None => self.source.decrypt_chunk(chunk_index),
+        }
+    }
+}
+
+impl ChunkSource {
+    /// 定位、读取并解密一个分片，核对分片索引中记录的校验值
+    fn decrypt_chunk(&self, chunk_index: u64) -> Result<Vec<u8>, ErrorCode> {
+        let offset = self.chunk_offsets[chunk_index as usize];
+        let encrypted_chunk = {
+            let mut input_file = self.input_file.lock().unwrap();
+            if let Err(err) = input_file.seek(SeekFrom::Start(offset)) {
+                return Err(Error::new(ErrorCode::IoRead, format!("Error seeking to chunk: {}", err)));
+            }
+            self.header.frame_format()
+                .read_frame(&mut *input_file, &format!("chunk {}", chunk_index + 1), self.header.max_frame_len())?
+        };
+        let decrypted = decrypt_chunk_frame(&self.header, &self.algo, &self.key, chunk_index, &encrypted_chunk)?;
+        let expected = self.chunk_checksums.get(chunk_index as usize).copied();
+        check_chunk_checksum(&self.key, chunk_index, &decrypted, expected)?;
+        Ok(decrypted)
+    }
+}
+
+/// ReaderOptions.prefetch 的上限，预取的分片都保留在内存中
+const MAX_PREFETCH: u32 = 64;
+
+/// 打开文件并校验文件头，读取分片索引；没有分片索引时逐个跳过分片帧记录各分片的位置
+fn open_decrypted_reader(algorithm: String, key: Buffer, input_path: Either<String, i32>, options: ReaderOptions) -> Result<ReaderState, ErrorCode> {
+    let prefetch = options.prefetch.unwrap_or(0);
+    if prefetch > MAX_PREFETCH {
+        return Err(Error::new(ErrorCode::InvalidArgument, format!("prefetch must be at most {}: {}", MAX_PREFETCH, prefetch)));
+    }
+    let input_path = fd::input_path(input_path)?;
+    let algo = parse_algorithm(&algorithm)?;
+    let (mut input_file, header) = open_chunked_file(&algo, &key, &input_path)?;
+    let total_chunks = header.total_chunks();
+
+    let has_index = header.container.as_ref().is_some_and(|container| container.has_footer);
+    let (chunk_offsets, chunk_checksums) = if has_index {
+        let footer = ChunkedFooter::read_at_end(&mut input_file)?;
+        if (footer.chunk_offsets.len() as u64) < total_chunks {
+            return Err(Error::new(ErrorCode::BadFormat, format!("Chunk {} missing from chunk index", footer.chunk_offsets.len())));
+        }
+        if footer.chunk_offsets.iter().any(|&offset| offset < header.header_len) {
+            return Err(Error::new(ErrorCode::BadHeader, "Invalid chunk index in file footer"));
+        }
+        (footer.chunk_offsets, footer.chunk_checksums)
+    } else {
+        let frame_format = header.frame_format();
+        let mut chunk_offsets = Vec::with_capacity(total_chunks as usize);
+        let mut offset = header.header_len;
+        for chunk_index in 0..total_chunks {
+            chunk_offsets.push(offset);
+            offset += frame_format.skip_frame(&mut input_file, &format!("chunk {}", chunk_index + 1))?;
+        }
+        (chunk_offsets, Vec::new())
+    };
+
+    let source = Arc::new(ChunkSource {
+        input_file: Mutex::new(input_file),
+        header,
+        algo,
+        key: key.to_vec(),
+        chunk_offsets,
+        chunk_checksums,
+    });
+    let read_ahead = (prefetch > 0).then(|| {
+        let source = source.clone();
+        ChunkReadAhead::new(prefetch as u64, total_chunks, move |chunk_index| source.decrypt_chunk(chunk_index))
+    });
+    Ok(ReaderState { source, read_ahead, cached_chunk: None })
+}
+
+/// 获取分片加密文件的元数据 - 用于视频播放前获取文件信息；v2 文件同时返回结尾字段，提供密钥时解密明文 SHA-256
+#[napi(js_name = "getChunkedFileMetadata")]
+pub fn get_chunked_file_metadata(input_path: Either<String, i32>, key: Option<Buffer>) -> Result<ChunkedFileMetadata, ErrorCode> {
+    let input_path = fd::input_path(input_path)?;
+    // 打开输入文件
+    let input_file = match fd::open(&input_path) {
+        Ok(file) => file,
+        Err(err) => return Err(Error::new(ErrorCode::IoOpen, format!("Failed to open input file: {}", err))),
+    };
+    let mut input_file = BufReader::new(input_file);
+    
+    // 读取文件头以获取元数据
+    let header = format::read_chunked_header(&mut input_file)?;
+    let original_size = header.original_size;
+    let chunk_size = header.chunk_size;
+    
+    // 计算总块数
+    let total_chunks = (original_size as f64 / chunk_size as f64).ceil() as u32;
+    
+    // 计算KB单位的大小
+    let original_size_kb = (original_size as f64) / 1024.0;
+    let chunk_size_kb = (chunk_size as f64) / 1024.0;
+    
+    // 读取文件末尾的结尾字段；提供密钥时先校验文件头，再解密明文摘要
+    let footer = match &header.container {
+        Some(container) if container.has_footer => {
+            if let Some(key) = &key {
+                container.verify(key)?;
+            }
+            let footer = ChunkedFooter::read_at_end(&mut input_file)?;
+            let digest = match &key {
+                Some(key) => footer.decrypt_digest(key)?,
+                None => None,
+            };
+            let hash_algorithm = container.hash_algorithm.unwrap_or(HashAlgorithm::Sha256);
+            Some(ChunkedFooterInfo {
+                indexed_chunks: footer.chunk_offsets.len() as f64,
+                has_chunk_checksums: !footer.chunk_checksums.is_empty(),
+                plaintext_size_kb: footer.plaintext_size.map(|size| (size as f64) / 1024.0),
+                plaintext_size_bytes: footer.plaintext_size.map(byte_count),
+                has_digest: footer.encrypted_digest.is_some(),
+                hash_algorithm: footer.encrypted_digest.as_ref().map(|_| hash_algorithm.as_str().to_string()),
+                digest: digest.as_ref().map(hex_encode),
+                sha256: digest.filter(|_| hash_algorithm == HashAlgorithm::Sha256).map(hex_encode),
+            })
+        },
+        _ => None,
+    };
+    
+    // 创建并返回结果对象
+    Ok(ChunkedFileMetadata {
+        total_chunks: total_chunks as f64,
+        file_size_kb: original_size_kb,
+        file_size_bytes: byte_count(original_size),
+        chunk_size_kb,
+        chunk_size_bytes: byte_count(chunk_size as u64),
+        compression: header.compression().map(|compression| compression.method.as_str().to_string()),
+        footer,
+    })
+}
+
+/// 修复文件 - 用加密时附加的纠错数据找出并恢复损坏的块，无需密钥；修复后的内容在解密时仍会经过完整的认证
+#[napi(js_name = "repairFile")]
+pub fn repair_file(path: String) -> Result<RepairFileResult, ErrorCode> {
+    repair_file_report(path)
+}
+
+/// repairFile() 的异步版本，在 libuv 线程池中运行并返回 Promise
+#[napi(js_name = "repairFileAsync", ts_return_type = "Promise<RepairFileResult>")]
+pub fn repair_file_async(path: String, signal: Option<Either<AbortSignal, ClassInstance<Job>>>) -> AsyncTask<FileTask<RepairFileResult>> {
+    FileTask::plain(signal, move || repair_file_report(path))
+}
+
+fn repair_file_report(path: String) -> Result<RepairFileResult, ErrorCode> {
+    let report = parity::repair(&path)?;
+
+    // 创建并返回结果对象
+    Ok(RepairFileResult {
+        checked_blocks: report.checked_blocks as u32,
+        damaged_blocks: report.damaged_blocks as u32,
+        repaired_blocks: report.repaired_blocks as u32,
+        unrecoverable_blocks: report.unrecoverable_blocks as u32,
+        repaired: report.unrecoverable_blocks == 0,
+    })
+}
+
+/// 存入内容寻址存储 - 文件按内容切分为分片，分片加密后以 keyed hash 为名保存，相同内容的分片只保存一次
+#[napi(js_name = "storeFile")]
+pub fn store_file(store_dir: String, algorithm: String, key: Buffer, input_path: Either<String, i32>, options: Option<StoreOptions>) -> Result<StoreFileResult, ErrorCode> {
+    let options = options.unwrap_or_default();
+    let control = JobControl::new("storeFile", options.timeout_ms);
+    control.started();
+    let result = store_file_job(store_dir, algorithm, key, input_path, options, &control);
+    control.complete(result)
+}
+
+/// storeFile() 的异步版本，在 libuv 线程池中运行并返回 Promise
+#[napi(js_name = "storeFileAsync", ts_return_type = "Promise<StoreFileResult>")]
+pub fn store_file_async(store_dir: String, algorithm: String, key: Buffer, input_path: Either<String, i32>, options: Option<StoreOptions>, signal: Option<Either<AbortSignal, ClassInstance<Job>>>) -> AsyncTask<FileTask<StoreFileResult>> {
+    let options = options.unwrap_or_default();
+    let control = JobControl::new("storeFile", options.timeout_ms);
+    FileTask::new(control, signal, move |control| store_file_job(store_dir, algorithm, key, input_path, options, control))
+}
+
+fn store_file_job(store_dir: String, algorithm: String, key: Buffer, input_path: Either<String, i32>, options: StoreOptions, control: &JobControl) -> Result<StoreFileResult, ErrorCode> {
+    let input_path = fd::input_path(input_path)?;
+    let algo = parse_algorithm(&algorithm)?;
+    let compression = parse_compression(&options.compression, options.compression_level)?;
+    let name = match options.name {
+        Some(name) => name,
+        None => match input_path.as_path() {
+            Some(path) => format::file_name_of(path)?,
+            None => return Err(Error::new(ErrorCode::InvalidArgument, "A name is required when storing a file descriptor")),
+        },
+    };
+    
+    let report = store::store_file(&store_dir, &name, &input_path, &algo, &key, compression, || control.check(), |index, bytes| control.chunk_done(index, bytes))?;
+    
+    // 创建并返回结果对象
+    Ok(StoreFileResult {
+        name,
+        file_size_kb: (report.size as f64) / 1024.0,
+        file_size_bytes: byte_count(report.size),
+        total_chunks: report.total_chunks as f64,
+        new_chunks: report.new_chunks as f64,
+        reused_chunks: report.reused_chunks as f64,
+        stored_size_kb: (report.stored_bytes as f64) / 1024.0,
+        stored_size_bytes: byte_count(report.stored_bytes),
+    })
+}
+
+/// 从内容寻址存储取回文件 - 按清单解密并核对每个分片，写入 outputPath
+#[napi(js_name = "restoreFile")]
+pub fn restore_file(store_dir: String, key: Buffer, name: String, output_path: Either<String, i32>, options: Option<RestoreOptions>) -> Result<RestoreFileResult, ErrorCode> {
+    let options = options.unwrap_or_default();
+    let control = JobControl::new("restoreFile", options.timeout_ms);
+    control.started();
+    let result = restore_file_job(store_dir, key, name, output_path, &control);
+    control.complete(result)
+}
+
+/// restoreFile() 的异步版本，在 libuv 线程池中运行并返回 Promise
+#[napi(js_name = "restoreFileAsync", ts_return_type = "Promise<RestoreFileResult>")]
+pub fn restore_file_async(store_dir: String, key: Buffer, name: String, output_path: Either<String, i32>, options: Option<RestoreOptions>, signal: Option<Either<AbortSignal, ClassInstance<Job>>>) -> AsyncTask<FileTask<RestoreFileResult>> {
+    let options = options.unwrap_or_default();
+    let control = JobControl::new("restoreFile", options.timeout_ms);
+    FileTask::new(control, signal, move |control| restore_file_job(store_dir, key, name, output_path, control))
+}
+
+fn restore_file_job(store_dir: String, key: Buffer, name: String, output_path: Either<String, i32>, control: &JobControl) -> Result<RestoreFileResult, ErrorCode> {
+    let output_path = fd::output_path(output_path)?;
+    runtime::ensure_ready()?;
+    let file = store::restore_file(&store_dir, &name, &output_path, &key, || control.check(), |index, bytes| control.chunk_done(index, bytes))?;
+    
+    // 创建并返回结果对象
+    Ok(RestoreFileResult {
+        name: file.name,
+        file_size_kb: (file.size as f64) / 1024.0,
+        file_size_bytes: byte_count(file.size),
+        total_chunks: file.total_chunks as f64,
+        output_path: output_path.to_string(),
+    })
+}
+
+/// 列出内容寻址存储中可以用该密钥解密的文件
+#[napi(js_name = "listStoredFiles")]
+pub fn list_stored_files(store_dir: String, key: Buffer) -> Result<Vec<StoredFileEntry>, ErrorCode> {
+    let files = store::list_files(&store_dir, &key)?;
+    Ok(files
+        .into_iter()
+        .map(|file| StoredFileEntry {
+            name: file.name,
+            file_size_kb: (file.size as f64) / 1024.0,
+            file_size_bytes: byte_count(file.size),
+            total_chunks: file.total_chunks as f64,
+            created_at: file.created_at as f64,
+            algorithm: file.algorithm,
+        })
+        .collect())
+}
+
+/// 以 age v1 格式加密文件 - 输出可以用标准的 age 命令行工具解密，使用口令或 X25519 接收者
+#[napi(js_name = "ageEncryptFile")]
+pub fn age_encrypt_file(input_path: Either<String, i32>, output_path: Either<String, i32>, options: AgeEncryptOptions) -> Result<AgeEncryptResult, ErrorCode> {
+    let control = JobControl::new("ageEncryptFile", options.timeout_ms);
+    control.started();
+    let result = age_encrypt_file_job(input_path, output_path, options, &control);
+    control.complete(result)
+}
+
+/// ageEncryptFile() 的异步版本，在 libuv 线程池中运行并返回 Promise
+#[napi(js_name = "ageEncryptFileAsync", ts_return_type = "Promise<AgeEncryptResult>")]
+pub fn age_encrypt_file_async(input_path: Either<String, i32>, output_path: Either<String, i32>, options: AgeEncryptOptions, signal: Option<Either<AbortSignal, ClassInstance<Job>>>) -> AsyncTask<FileTask<AgeEncryptResult>> {
+    let control = JobControl::new("ageEncryptFile", options.timeout_ms);
+    FileTask::new(control, signal, move |control| age_encrypt_file_job(input_path, output_path, options, control))
+}
+
+fn age_encrypt_file_job(input_path: Either<String, i32>, output_path: Either<String, i32>, options: AgeEncryptOptions, control: &JobControl) -> Result<AgeEncryptResult, ErrorCode> {
+    let input_path = fd::input_path(input_path)?;
+    let output_path = fd::output_path(output_path)?;
+    runtime::ensure_ready()?;
+    let armor = options.armor.unwrap_or(false);
+    let recipients = options.recipients.unwrap_or_default();
+    let output = fd::AtomicOutput::new(&output_path);
+    let report = age_format::encrypt_file(&input_path, output.path(), options.passphrase.as_deref(), &recipients, armor, control)?;
+    output.commit()?;
+    
+    // 创建并返回结果对象
+    Ok(AgeEncryptResult {
+        file_size_kb: (report.plaintext_size as f64) / 1024.0,
+        file_size_bytes: byte_count(report.plaintext_size),
+        encrypted_size_kb: (report.encrypted_size as f64) / 1024.0,
+        encrypted_size_bytes: byte_count(report.encrypted_size),
+        passphrase: report.passphrase,
+        armored: armor,
+    })
+}
+
+/// 解密 age v1 文件（二进制或 ASCII 封装）- 可以解密标准 age 命令行工具生成的文件
+#[napi(js_name = "ageDecryptFile")]
+pub fn age_decrypt_file(input_path: Either<String, i32>, output_path: Either<String, i32>, options: AgeDecryptOptions) -> Result<AgeDecryptResult, ErrorCode> {
+    let control = JobControl::new("ageDecryptFile", options.timeout_ms);
+    control.started();
+    let result = age_decrypt_file_job(input_path, output_path, options, &control);
+    control.complete(result)
+}
+
+/// ageDecryptFile() 的异步版本，在 libuv 线程池中运行并返回 Promise
+#[napi(js_name = "ageDecryptFileAsync", ts_return_type = "Promise<AgeDecryptResult>")]
+pub fn age_decrypt_file_async(input_path: Either<String, i32>, output_path: Either<String, i32>, options: AgeDecryptOptions, signal: Option<Either<AbortSignal, ClassInstance<Job>>>) -> AsyncTask<FileTask<AgeDecryptResult>> {
+    let control = JobControl::new("ageDecryptFile", options.timeout_ms);
+    FileTask::new(control, signal, move |control| age_decrypt_file_job(input_path, output_path, options, control))
+}
+
+fn age_decrypt_file_job(input_path: Either<String, i32>, output_path: Either<String, i32>, options: AgeDecryptOptions, control: &JobControl) -> Result<AgeDecryptResult, ErrorCode> {
+    let input_path = fd::input_path(input_path)?;
+    let output_path = fd::output_path(output_path)?;
+    runtime::ensure_ready()?;
+    let identities = options.identities.unwrap_or_default();
+    let output = fd::AtomicOutput::new(&output_path);
+    let report = age_format::decrypt_file(&input_path, output.path(), options.passphrase.as_deref(), &identities, control)?;
+    output.commit()?;
+    
+    // 创建并返回结果对象
+    Ok(AgeDecryptResult {
+        file_size_kb: (report.plaintext_size as f64) / 1024.0,
+        file_size_bytes: byte_count(report.plaintext_size),
+        encrypted_size_kb: (report.encrypted_size as f64) / 1024.0,
+        encrypted_size_bytes: byte_count(report.encrypted_size),
+        passphrase: report.passphrase,
+        output_path: output_path.to_string(),
+    })
+}
+
+/// 生成新的 age X25519 身份，返回 { identity, recipient }；identity 为私钥，需要妥善保存
+#[napi(js_name = "generateAgeIdentity")]
+pub fn generate_age_identity() -> Result<AgeIdentity, ErrorCode> {
+    let (identity, recipient) = age_format::generate_identity();
+    Ok(AgeIdentity {
+        identity,
+        recipient,
+    })
+}
+
+/// 由 opensslEncryptFile() / opensslDecryptFile() 的参数确定密钥派生方式
+fn openssl_kdf(options: &OpensslOptions) -> Result<openssl::Kdf, ErrorCode> {
+    let digest = openssl::KdfDigest::parse(options.digest.as_deref().unwrap_or("sha256"))?;
+    if options.pbkdf2.unwrap_or(true) {
+        let iterations = options.iterations.unwrap_or(openssl::DEFAULT_PBKDF2_ITERATIONS);
+        Ok(openssl::Kdf::Pbkdf2 { digest, iterations })
+    } else if options.iterations.is_some() {
+        Err(Error::new(ErrorCode::InvalidArgument, "iterations requires pbkdf2"))
+    } else {
+        Ok(openssl::Kdf::BytesToKey(digest))
+    }
+}
+
+/// 以 `openssl enc -aes-256-cbc -salt` 的格式加密文件 - 输出可以用 `openssl enc -d` 以相同的口令和参数解密
+#[napi(js_name = "opensslEncryptFile")]
+pub fn openssl_encrypt_file(input_path: Either<String, i32>, output_path: Either<String, i32>, passphrase: String, options: Option<OpensslOptions>) -> Result<FileSizes, ErrorCode> {
+    let options = options.unwrap_or_default();
+    let control = JobControl::new("opensslEncryptFile", options.timeout_ms);
+    control.started();
+    let result = openssl_encrypt_file_job(input_path, output_path, passphrase, options, &control);
+    control.complete(result)
+}
+
+/// opensslEncryptFile() 的异步版本，在 libuv 线程池中运行并返回 Promise
+#[napi(js_name = "opensslEncryptFileAsync", ts_return_type = "Promise<FileSizes>")]
+pub fn openssl_encrypt_file_async(input_path: Either<String, i32>, output_path: Either<String, i32>, passphrase: String, options: Option<OpensslOptions>, signal: Option<Either<AbortSignal, ClassInstance<Job>>>) -> AsyncTask<FileTask<FileSizes>> {
+    let options = options.unwrap_or_default();
+    let control = JobControl::new("opensslEncryptFile", options.timeout_ms);
+    FileTask::new(control, signal, move |control| openssl_encrypt_file_job(input_path, output_path, passphrase, options, control))
+}
+
+fn openssl_encrypt_file_job(input_path: Either<String, i32>, output_path: Either<String, i32>, passphrase: String, options: OpensslOptions, control: &JobControl) -> Result<FileSizes, ErrorCode> {
+    let input_path = fd::input_path(input_path)?;
+    let output_path = fd::output_path(output_path)?;
+    runtime::ensure_ready()?;
+    let kdf = openssl_kdf(&options)?;
+    let output = fd::AtomicOutput::new(&output_path);
+    let report = openssl::encrypt_file(&input_path, output.path(), passphrase.as_bytes(), kdf, control)?;
+    output.commit()?;
+    
+    // 创建并返回结果对象
+    Ok(FileSizes {
+        file_size_kb: (report.plaintext_size as f64) / 1024.0,
+        file_size_bytes: byte_count(report.plaintext_size),
+        encrypted_size_kb: (report.encrypted_size as f64) / 1024.0,
+        encrypted_size_bytes: byte_count(report.encrypted_size),
+    })
+}
+
+/// 解密 `openssl enc -aes-256-cbc` 生成的加盐文件 - 口令和密钥派生参数必须与加密时一致
+#[napi(js_name = "opensslDecryptFile")]
+pub fn openssl_decrypt_file(input_path: Either<String, i32>, output_path: Either<String, i32>, passphrase: String, options: Option<OpensslOptions>) -> Result<DecryptedFile, ErrorCode> {
+    let options = options.unwrap_or_default();
+    let control = JobControl::new("opensslDecryptFile", options.timeout_ms);
+    control.started();
+    let result = openssl_decrypt_file_job(input_path, output_path, passphrase, options, &control);
+    control.complete(result)
+}
+
+/// opensslDecryptFile() 的异步版本，在 libuv 线程池中运行并返回 Promise
+#[napi(js_name = "opensslDecryptFileAsync", ts_return_type = "Promise<DecryptedFile>")]
+pub fn openssl_decrypt_file_async(input_path: Either<String, i32>, output_path: Either<String, i32>, passphrase: String, options: Option<OpensslOptions>, signal: Option<Either<AbortSignal, ClassInstance<Job>>>) -> AsyncTask<FileTask<DecryptedFile>> {
+    let options = options.unwrap_or_default();
+    let control = JobControl::new("opensslDecryptFile", options.timeout_ms);
+    FileTask::new(control, signal, move |control| openssl_decrypt_file_job(input_path, output_path, passphrase, options, control))
+}
+
+fn openssl_decrypt_file_job(input_path: Either<String, i32>, output_path: Either<String, i32>, passphrase: String, options: OpensslOptions, control: &JobControl) -> Result<DecryptedFile, ErrorCode> {
+    let input_path = fd::input_path(input_path)?;
+    let output_path = fd::output_path(output_path)?;
+    runtime::ensure_ready()?;
+    config::decrypt_policy().check_unauthenticated("OpenSSL enc")?;
+    let kdf = openssl_kdf(&options)?;
+    let output = fd::AtomicOutput::new(&output_path);
+    let report = openssl::decrypt_file(&input_path, output.path(), passphrase.as_bytes(), kdf, control)?;
+    output.commit()?;
+    
+    // 创建并返回结果对象
+    Ok(DecryptedFile {
+        file_size_kb: (report.plaintext_size as f64) / 1024.0,
+        file_size_bytes: byte_count(report.plaintext_size),
+        encrypted_size_kb: (report.encrypted_size as f64) / 1024.0,
+        encrypted_size_bytes: byte_count(report.encrypted_size),
+        output_path: output_path.to_string(),
+    })
+}
+
+/// 解密 `gpg -c` 生成的 OpenPGP 对称加密文件（AES，SEIPD + MDC）- 完整性校验失败时删除输出文件
+#[napi(js_name = "gpgDecryptFile")]
+pub fn gpg_decrypt_file(input_path: Either<String, i32>, output_path: Either<String, i32>, passphrase: String, options: Option<GpgDecryptOptions>) -> Result<GpgDecryptResult, ErrorCode> {
+    let options = options.unwrap_or_default();
+    let control = JobControl::new("gpgDecryptFile", options.timeout_ms);
+    control.started();
+    let result = gpg_decrypt_file_job(input_path, output_path, passphrase, &control);
+    control.complete(result)
+}
+
+/// gpgDecryptFile() 的异步版本，在 libuv 线程池中运行并返回 Promise
+#[napi(js_name = "gpgDecryptFileAsync", ts_return_type = "Promise<GpgDecryptResult>")]
+pub fn gpg_decrypt_file_async(input_path: Either<String, i32>, output_path: Either<String, i32>, passphrase: String, options: Option<GpgDecryptOptions>, signal: Option<Either<AbortSignal, ClassInstance<Job>>>) -> AsyncTask<FileTask<GpgDecryptResult>> {
+    let options = options.unwrap_or_default();
+    let control = JobControl::new("gpgDecryptFile", options.timeout_ms);
+    FileTask::new(control, signal, move |control| gpg_decrypt_file_job(input_path, output_path, passphrase, control))
+}
+
+fn gpg_decrypt_file_job(input_path: Either<String, i32>, output_path: Either<String, i32>, passphrase: String, control: &JobControl) -> Result<GpgDecryptResult, ErrorCode> {
+    let input_path = fd::input_path(input_path)?;
+    let output_path = fd::output_path(output_path)?;
+    runtime::ensure_ready()?;
+    let output = fd::AtomicOutput::new(&output_path);
+    let report = gpg::decrypt_file(&input_path, output.path(), passphrase.as_bytes(), control)?;
+    output.commit()?;
+    
+    // 创建并返回结果对象
+    Ok(GpgDecryptResult {
+        file_size_kb: (report.plaintext_size as f64) / 1024.0,
+        file_size_bytes: byte_count(report.plaintext_size),
+        encrypted_size_kb: (report.encrypted_size as f64) / 1024.0,
+        encrypted_size_bytes: byte_count(report.encrypted_size),
+        filename: report.filename,
+        cipher: report.cipher.to_string(),
+        compression: report.compression.to_string(),
+        output_path: output_path.to_string(),
+    })
+}
+
+/// 以口令加密文件，输出 OpenPGP AEAD 加密消息 - 默认为 RFC 9580 格式（v6 SKESK + v2 SEIPD），也可以输出 GnuPG 使用的 LibrePGP OCB 格式
+#[napi(js_name = "gpgEncryptFile")]
+pub fn gpg_encrypt_file(input_path: Either<String, i32>, output_path: Either<String, i32>, passphrase: String, options: Option<GpgEncryptOptions>) -> Result<GpgEncryptResult, ErrorCode> {
+    let options = options.unwrap_or_default();
+    let control = JobControl::new("gpgEncryptFile", options.timeout_ms);
+    control.started();
+    let result = gpg_encrypt_file_job(input_path, output_path, passphrase, options, &control);
+    control.complete(result)
+}
+
+/// gpgEncryptFile() 的异步版本，在 libuv 线程池中运行并返回 Promise
+#[napi(js_name = "gpgEncryptFileAsync", ts_return_type = "Promise<GpgEncryptResult>")]
+pub fn gpg_encrypt_file_async(input_path: Either<String, i32>, output_path: Either<String, i32>, passphrase: String, options: Option<GpgEncryptOptions>, signal: Option<Either<AbortSignal, ClassInstance<Job>>>) -> AsyncTask<FileTask<GpgEncryptResult>> {
+    let options = options.unwrap_or_default();
+    let control = JobControl::new("gpgEncryptFile", options.timeout_ms);
+    FileTask::new(control, signal, move |control| gpg_encrypt_file_job(input_path, output_path, passphrase, options, control))
+}
+
+fn gpg_encrypt_file_job(input_path: Either<String, i32>, output_path: Either<String, i32>, passphrase: String, options: GpgEncryptOptions, control: &JobControl) -> Result<GpgEncryptResult, ErrorCode> {
+    let input_path = fd::input_path(input_path)?;
+    let output_path = fd::output_path(output_path)?;
+    runtime::ensure_ready()?;
+    let settings = gpg::AeadSettings {
+        profile: gpg::AeadProfile::parse(options.profile.as_deref().unwrap_or("rfc9580"))?,
+        cipher: gpg::SymmetricAlgorithm::parse(options.cipher.as_deref().unwrap_or("aes256"))?,
+        aead: gpg::AeadAlgorithm::parse(options.aead.as_deref().unwrap_or("ocb"))?,
+    };
+    let output = fd::AtomicOutput::new(&output_path);
+    let report = gpg::encrypt_file(&input_path, output.path(), passphrase.as_bytes(), &settings, control)?;
+    output.commit()?;
+    
+    // 创建并返回结果对象
+    Ok(GpgEncryptResult {
+        file_size_kb: (report.plaintext_size as f64) / 1024.0,
+        file_size_bytes: byte_count(report.plaintext_size),
+        encrypted_size_kb: (report.encrypted_size as f64) / 1024.0,
+        encrypted_size_bytes: byte_count(report.encrypted_size),
+        filename: report.filename,
+        profile: settings.profile.as_str().to_string(),
+        cipher: report.cipher.to_string(),
+        aead: settings.aead.as_str().to_string(),
+    })
+}
+
+/// 把文件和目录打包为 WinZip AES-256（AE-2）加密的 ZIP 文件 - 可以直接用 7-Zip、WinZip 等工具输入口令解压
+#[napi(js_name = "encryptToZip")]
+pub fn encrypt_to_zip(inputs: Vec<String>, output_path: Either<String, i32>, password: String, options: Option<ZipOptions>) -> Result<ZipResult, ErrorCode> {
+    let options = options.unwrap_or_default();
+    let control = JobControl::new("encryptToZip", options.timeout_ms);
+    control.started();
+    let result = encrypt_to_zip_job(inputs, output_path, password, options, &control);
+    control.complete(result)
+}
+
+/// encryptToZip() 的异步版本，在 libuv 线程池中运行并返回 Promise
+#[napi(js_name = "encryptToZipAsync", ts_return_type = "Promise<ZipResult>")]
+pub fn encrypt_to_zip_async(inputs: Vec<String>, output_path: Either<String, i32>, password: String, options: Option<ZipOptions>, signal: Option<Either<AbortSignal, ClassInstance<Job>>>) -> AsyncTask<FileTask<ZipResult>> {
+    let options = options.unwrap_or_default();
+    let control = JobControl::new("encryptToZip", options.timeout_ms);
+    FileTask::new(control, signal, move |control| encrypt_to_zip_job(inputs, output_path, password, options, control))
+}
+
+fn encrypt_to_zip_job(inputs: Vec<String>, output_path: Either<String, i32>, password: String, options: ZipOptions, control: &JobControl) -> Result<ZipResult, ErrorCode> {
+    let output_path = fd::output_path(output_path)?;
+    runtime::ensure_ready()?;
+    let output = fd::AtomicOutput::new(&output_path);
+    let report = zip_aes::encrypt_to_zip(&inputs, output.path(), &password, options.compress.unwrap_or(true), || control.check(), |index, bytes| control.chunk_done(index, bytes))?;
+    output.commit()?;
+    
+    // 创建并返回结果对象
+    Ok(ZipResult {
+        files: report.files,
+        directories: report.directories,
+        file_size_kb: (report.plaintext_size as f64) / 1024.0,
+        file_size_bytes: byte_count(report.plaintext_size),
+        encrypted_size_kb: (report.zip_size as f64) / 1024.0,
+        encrypted_size_bytes: byte_count(report.zip_size),
+    })
+}
+
+/// 把文件和目录打包为 AES-256 加密的 7z 归档（可选 LZMA2 压缩）- 文件列表同样加密，可以用 7-Zip 输入口令解压
+#[napi(js_name = "create7zArchive")]
+pub fn create_7z_archive(inputs: Vec<String>, output_path: Either<String, i32>, password: String, options: Option<SevenZipOptions>) -> Result<SevenZipResult, ErrorCode> {
+    let options = options.unwrap_or_default();
+    let control = JobControl::new("create7zArchive", options.timeout_ms);
+    control.started();
+    let result = create_7z_archive_job(inputs, output_path, password, options, &control);
+    control.complete(result)
+}
+
+/// create7zArchive() 的异步版本，在 libuv 线程池中运行并返回 Promise
+#[napi(js_name = "create7zArchiveAsync", ts_return_type = "Promise<SevenZipResult>")]
+pub fn create_7z_archive_async(inputs: Vec<String>, output_path: Either<String, i32>, password: String, options: Option<SevenZipOptions>, signal: Option<Either<AbortSignal, ClassInstance<Job>>>) -> AsyncTask<FileTask<SevenZipResult>> {
+    let options = options.unwrap_or_default();
+    let control = JobControl::new("create7zArchive", options.timeout_ms);
+    FileTask::new(control, signal, move |control| create_7z_archive_job(inputs, output_path, password, options, control))
+}
+
+fn create_7z_archive_job(inputs: Vec<String>, output_path: Either<String, i32>, password: String, options: SevenZipOptions, control: &JobControl) -> Result<SevenZipResult, ErrorCode> {
+    let output_path = fd::output_path(output_path)?;
+    runtime::ensure_ready()?;
+    let output = fd::AtomicOutput::new(&output_path);
+    let report = sevenz::create_archive(&inputs, output.path(), &password, options.compress.unwrap_or(true), || control.check(), |index, bytes| control.chunk_done(index, bytes))?;
+    output.commit()?;
+    
+    // 创建并返回结果对象
+    Ok(SevenZipResult {
+        files: report.files,
+        file_size_kb: (report.plaintext_size as f64) / 1024.0,
+        file_size_bytes: byte_count(report.plaintext_size),
+        encrypted_size_kb: (report.archive_size as f64) / 1024.0,
+        encrypted_size_bytes: byte_count(report.archive_size),
+    })
+}
+
+/// 把 7z 归档解压到目录 - 支持 AES-256 加密的归档，未加密的归档 password 传 null；失败时删除已解压的文件
+#[napi(js_name = "extract7zArchive")]
+pub fn extract_7z_archive(input_path: Either<String, i32>, output_dir: String, password: Option<String>, options: Option<ExtractOptions>) -> Result<ExtractResult, ErrorCode> {
+    let options = options.unwrap_or_default();
+    let control = JobControl::new("extract7zArchive", options.timeout_ms);
+    control.started();
+    let result = extract_7z_archive_job(input_path, output_dir, password, &control);
+    control.complete(result)
+}
+
+/// extract7zArchive() 的异步版本，在 libuv 线程池中运行并返回 Promise
+#[napi(js_name = "extract7zArchiveAsync", ts_return_type = "Promise<ExtractResult>")]
+pub fn extract_7z_archive_async(input_path: Either<String, i32>, output_dir: String, password: Option<String>, options: Option<ExtractOptions>, signal: Option<Either<AbortSignal, ClassInstance<Job>>>) -> AsyncTask<FileTask<ExtractResult>> {
+    let options = options.unwrap_or_default();
+    let control = JobControl::new("extract7zArchive", options.timeout_ms);
+    FileTask::new(control, signal, move |control| extract_7z_archive_job(input_path, output_dir, password, control))
+}
+
+fn extract_7z_archive_job(input_path: Either<String, i32>, output_dir: String, password: Option<String>, control: &JobControl) -> Result<ExtractResult, ErrorCode> {
+    let input_path = fd::input_path(input_path)?;
+    runtime::ensure_ready()?;
+    let mut created = Vec::new();
+    let report = sevenz::extract_archive(&input_path, &output_dir, password.as_deref(), || control.check(), |index, bytes| control.chunk_done(index, bytes), &mut created)
+        .map_err(|err| {
+            walk::remove_created(&created);
+            Error::from(err)
+        })?;
+    
+    // 创建并返回结果对象
+    Ok(ExtractResult {
+        files: report.files,
+        directories: report.directories,
+        file_size_kb: (report.plaintext_size as f64) / 1024.0,
+        file_size_bytes: byte_count(report.plaintext_size),
+        encrypted_size_kb: (report.archive_size as f64) / 1024.0,
+        encrypted_size_bytes: byte_count(report.archive_size),
+        output_dir,
+    })
+}
+
+/// 以 libsodium `crypto_secretstream_xchacha20poly1305` 的格式加密文件 - 输出可以直接用 libsodium（Python、Go 等）按相同的分块长度解密
+#[napi(js_name = "secretstreamEncryptFile")]
+pub fn secretstream_encrypt_file(key: Buffer, input_path: Either<String, i32>, output_path: Either<String, i32>, options: Option<SecretStreamOptions>) -> Result<SecretStreamEncryptResult, ErrorCode> {
+    let options = options.unwrap_or_default();
+    let control = JobControl::new("secretstreamEncryptFile", options.timeout_ms);
+    control.started();
+    let result = secretstream_encrypt_file_job(key, input_path, output_path, options, &control);
+    control.complete(result)
+}
+
+/// secretstreamEncryptFile() 的异步版本，在 libuv 线程池中运行并返回 Promise
+#[napi(js_name = "secretstreamEncryptFileAsync", ts_return_type = "Promise<SecretStreamEncryptResult>")]
+pub fn secretstream_encrypt_file_async(key: Buffer, input_path: Either<String, i32>, output_path: Either<String, i32>, options: Option<SecretStreamOptions>, signal: Option<Either<AbortSignal, ClassInstance<Job>>>) -> AsyncTask<FileTask<SecretStreamEncryptResult>> {
+    let options = options.unwrap_or_default();
+    let control = JobControl::new("secretstreamEncryptFile", options.timeout_ms);
+    FileTask::new(control, signal, move |control| secretstream_encrypt_file_job(key, input_path, output_path, options, control))
+}
+
+fn secretstream_encrypt_file_job(key: Buffer, input_path: Either<String, i32>, output_path: Either<String, i32>, options: SecretStreamOptions, control: &JobControl) -> Result<SecretStreamEncryptResult, ErrorCode> {
+    let input_path = fd::stream_input_path(input_path)?;
+    let output_path = fd::output_path(output_path)?;
+    runtime::ensure_ready()?;
+    let chunk_size = options.chunk_size.map_or(stream::DEFAULT_CHUNK_SIZE, |size| size as usize);
+    let output = fd::AtomicOutput::new(&output_path);
+    let report = stream::encrypt_file(&input_path, output.path(), &key, chunk_size, control)?;
+    output.commit()?;
+    
+    // 创建并返回结果对象
+    Ok(SecretStreamEncryptResult {
+        file_size_kb: (report.plaintext_size as f64) / 1024.0,
+        file_size_bytes: byte_count(report.plaintext_size),
+        encrypted_size_kb: (report.encrypted_size as f64) / 1024.0,
+        encrypted_size_bytes: byte_count(report.encrypted_size),
+        chunks: report.chunks as f64,
+    })
+}
+
+/// 解密 libsodium `crypto_secretstream_xchacha20poly1305` 格式的文件 - 分块长度必须与加密时相同，被截断或篡改时报错并删除输出文件
+#[napi(js_name = "secretstreamDecryptFile")]
+pub fn secretstream_decrypt_file(key: Buffer, input_path: Either<String, i32>, output_path: Either<String, i32>, options: Option<SecretStreamOptions>) -> Result<SecretStreamDecryptResult, ErrorCode> {
+    let options = options.unwrap_or_default();
+    let control = JobControl::new("secretstreamDecryptFile", options.timeout_ms);
+    control.started();
+    let result = secretstream_decrypt_file_job(key, input_path, output_path, options, &control);
+    control.complete(result)
+}
+
+/// secretstreamDecryptFile() 的异步版本，在 libuv 线程池中运行并返回 Promise
+#[napi(js_name = "secretstreamDecryptFileAsync", ts_return_type = "Promise<SecretStreamDecryptResult>")]
+pub fn secretstream_decrypt_file_async(key: Buffer, input_path: Either<String, i32>, output_path: Either<String, i32>, options: Option<SecretStreamOptions>, signal: Option<Either<AbortSignal, ClassInstance<Job>>>) -> AsyncTask<FileTask<SecretStreamDecryptResult>> {
+    let options = options.unwrap_or_default();
+    let control = JobControl::new("secretstreamDecryptFile", options.timeout_ms);
+    FileTask::new(control, signal, move |control| secretstream_decrypt_file_job(key, input_path, output_path, options, control))
+}
+
+fn secretstream_decrypt_file_job(key: Buffer, input_path: Either<String, i32>, output_path: Either<String, i32>, options: SecretStreamOptions, control: &JobControl) -> Result<SecretStreamDecryptResult, ErrorCode> {
+    let input_path = fd::stream_input_path(input_path)?;
+    let output_path = fd::output_path(output_path)?;
+    runtime::ensure_ready()?;
+    let chunk_size = options.chunk_size.map_or(stream::DEFAULT_CHUNK_SIZE, |size| size as usize);
+    let output = fd::AtomicOutput::new(&output_path);
+    let report = stream::decrypt_file(&input_path, output.path(), &key, chunk_size, control)?;
+    output.commit()?;
+    
+    // 创建并返回结果对象
+    Ok(SecretStreamDecryptResult {
+        file_size_kb: (report.plaintext_size as f64) / 1024.0,
+        file_size_bytes: byte_count(report.plaintext_size),
+        encrypted_size_kb: (report.encrypted_size as f64) / 1024.0,
+        encrypted_size_bytes: byte_count(report.encrypted_size),
+        chunks: report.chunks as f64,
+        output_path: output_path.to_string(),
+    })
+}
+
+/// 把 tinkEncryptFile() / tinkDecryptFile() 的参数转换为 Tink 流式 AEAD 的参数
+fn tink_params(options: &TinkStreamingOptions, key: &[u8]) -> Result<tink::StreamingParams, ErrorCode> {
+    let hkdf_hash = tink::HkdfHash::parse(options.hkdf_hash.as_deref().unwrap_or("sha256"))?;
+    let derived_key_size = options
+        .derived_key_size
+        .map_or(if key.len() >= 32 { 32 } else { 16 }, |size| size as usize);
+    let segment_size = options.segment_size.map_or(tink::DEFAULT_SEGMENT_SIZE, |size| size as usize);
+    Ok(tink::StreamingParams { derived_key_size, hkdf_hash, segment_size })
+}
+
+/// 以 Tink 流式 AEAD（AES-GCM-HKDF-STREAMING）的格式加密文件 - 输出可以用 Java 等语言的 Tink 以相同的密钥、参数和附加数据解密
+#[napi(js_name = "tinkEncryptFile")]
+pub fn tink_encrypt_file(key: Buffer, input_path: Either<String, i32>, output_path: Either<String, i32>, options: Option<TinkStreamingOptions>) -> Result<TinkEncryptResult, ErrorCode> {
+    let options = options.unwrap_or_default();
+    let control = JobControl::new("tinkEncryptFile", options.timeout_ms);
+    control.started();
+    let result = tink_encrypt_file_job(key, input_path, output_path, options, &control);
+    control.complete(result)
+}
+
+/// tinkEncryptFile() 的异步版本，在 libuv 线程池中运行并返回 Promise
+#[napi(js_name = "tinkEncryptFileAsync", ts_return_type = "Promise<TinkEncryptResult>")]
+pub fn tink_encrypt_file_async(key: Buffer, input_path: Either<String, i32>, output_path: Either<String, i32>, options: Option<TinkStreamingOptions>, signal: Option<Either<AbortSignal, ClassInstance<Job>>>) -> AsyncTask<FileTask<TinkEncryptResult>> {
+    let options = options.unwrap_or_default();
+    let control = JobControl::new("tinkEncryptFile", options.timeout_ms);
+    FileTask::new(control, signal, move |control| tink_encrypt_file_job(key, input_path, output_path, options, control))
+}
+
+fn tink_encrypt_file_job(key: Buffer, input_path: Either<String, i32>, output_path: Either<String, i32>, options: TinkStreamingOptions, control: &JobControl) -> Result<TinkEncryptResult, ErrorCode> {
+    let input_path = fd::input_path(input_path)?;
+    let output_path = fd::output_path(output_path)?;
+    runtime::ensure_ready()?;
+    let params = tink_params(&options, &key)?;
+    let associated_data = options.associated_data.as_deref().unwrap_or_default();
+    let output = fd::AtomicOutput::new(&output_path);
+    let report = tink::encrypt_file(&input_path, output.path(), &key, associated_data, &params, control)?;
+    output.commit()?;
+    
+    // 创建并返回结果对象
+    Ok(TinkEncryptResult {
+        file_size_kb: (report.plaintext_size as f64) / 1024.0,
+        file_size_bytes: byte_count(report.plaintext_size),
+        encrypted_size_kb: (report.encrypted_size as f64) / 1024.0,
+        encrypted_size_bytes: byte_count(report.encrypted_size),
+        segments: report.segments,
+    })
+}
+
+/// 解密 Tink 流式 AEAD（AES-GCM-HKDF-STREAMING）文件 - 密钥、参数和附加数据必须与加密时一致，被截断或篡改时报错并删除输出文件
+#[napi(js_name = "tinkDecryptFile")]
+pub fn tink_decrypt_file(key: Buffer, input_path: Either<String, i32>, output_path: Either<String, i32>, options: Option<TinkStreamingOptions>) -> Result<TinkDecryptResult, ErrorCode> {
+    let options = options.unwrap_or_default();
+    let control = JobControl::new("tinkDecryptFile", options.timeout_ms);
+    control.started();
+    let result = tink_decrypt_file_job(key, input_path, output_path, options, &control);
+    control.complete(result)
+}
+
+/// tinkDecryptFile() 的异步版本，在 libuv 线程池中运行并返回 Promise
+#[napi(js_name = "tinkDecryptFileAsync", ts_return_type = "Promise<TinkDecryptResult>")]
+pub fn tink_decrypt_file_async(key: Buffer, input_path: Either<String, i32>, output_path: Either<String, i32>, options: Option<TinkStreamingOptions>, signal: Option<Either<AbortSignal, ClassInstance<Job>>>) -> AsyncTask<FileTask<TinkDecryptResult>> {
+    let options = options.unwrap_or_default();
+    let control = JobControl::new("tinkDecryptFile", options.timeout_ms);
+    FileTask::new(control, signal, move |control| tink_decrypt_file_job(key, input_path, output_path, options, control))
+}
+
+fn tink_decrypt_file_job(key: Buffer, input_path: Either<String, i32>, output_path: Either<String, i32>, options: TinkStreamingOptions, control: &JobControl) -> Result<TinkDecryptResult, ErrorCode> {
+    let input_path = fd::input_path(input_path)?;
+    let output_path = fd::output_path(output_path)?;
+    runtime::ensure_ready()?;
+    let params = tink_params(&options, &key)?;
+    let associated_data = options.associated_data.as_deref().unwrap_or_default();
+    let output = fd::AtomicOutput::new(&output_path);
+    let report = tink::decrypt_file(&input_path, output.path(), &key, associated_data, &params, control)?;
+    output.commit()?;
+    
+    // 创建并返回结果对象
+    Ok(TinkDecryptResult {
+        file_size_kb: (report.plaintext_size as f64) / 1024.0,
+        file_size_bytes: byte_count(report.plaintext_size),
+        encrypted_size_kb: (report.encrypted_size as f64) / 1024.0,
+        encrypted_size_bytes: byte_count(report.encrypted_size),
+        segments: report.segments,
+        output_path: output_path.to_string(),
+    })
+}
+
+/// 生成新的 Fernet 密钥（URL 安全 base64 编码的 32 字节），与 Python 的 `Fernet.generate_key()` 相同
+#[napi(js_name = "generateFernetKey")]
+pub fn generate_fernet_key() -> String {
+    fernet::generate_key()
+}
+
+/// 生成 Fernet 令牌 - 与 Python cryptography 库的 `Fernet(key).encrypt(data)` 兼容，字符串按 UTF-8 加密
+#[napi(js_name = "fernetEncrypt")]
+pub fn fernet_encrypt(key: String, data: Either<Buffer, String>) -> Result<String, ErrorCode> {
+    let data = match &data {
+        Either::A(buffer) => buffer.as_ref(),
+        Either::B(text) => text.as_bytes(),
+    };
+    fernet::encrypt(&key, data, fernet::now()).map_err(Error::from)
+}
+
+/// 验证并解密 Fernet 令牌 - 与 Python 的 `Fernet(key).decrypt(token, ttl)` 兼容，设置 ttl 时拒绝过期的令牌
+#[napi(js_name = "fernetDecrypt")]
+pub fn fernet_decrypt(key: String, token: Either<String, Buffer>, options: Option<FernetDecryptOptions>) -> Result<Buffer, ErrorCode> {
+    let options = options.unwrap_or_default();
+    let token = match &token {
+        Either::A(text) => text.as_str(),
+        Either::B(buffer) => std::str::from_utf8(buffer)
+            .map_err(|_| Error::new(ErrorCode::BadFormat, "Invalid Fernet token: not url-safe base64"))?,
+    };
+    let plaintext = fernet::decrypt(&key, token, options.ttl.map(u64::from), fernet::now())?;
+    Ok(plaintext.into())
+}
+
+/// 把 encryptJwe() / decryptJwe() 的密钥参数转换为 JWE 密钥：Buffer 为对称密钥，对象或 JSON 字符串为 JWK
+fn jwe_key(key: Either<Buffer, JsonValue>) -> Result<jwe::JweKey, ErrorCode> {
+    match key {
+        Either::A(buffer) => Ok(jwe::JweKey::Symmetric(buffer.to_vec())),
+        Either::B(JsonValue(serde_json::Value::String(text))) => {
+            let jwk = serde_json::from_str(&text).map_err(|err| Error::new(ErrorCode::InvalidArgument, format!("Invalid JWK JSON: {}", err)))?;
+            jwe::JweKey::from_jwk(&jwk).map_err(Error::from)
+        }
+        Either::B(JsonValue(jwk)) => jwe::JweKey::from_jwk(&jwk).map_err(Error::from),
+    }
+}
+
+/// 生成 JWE 紧凑序列化令牌 - 支持 dir、A128KW/A192KW/A256KW 和 RSA-OAEP/RSA-OAEP-256 密钥管理，内容以 AES-GCM 加密
+#[napi(js_name = "encryptJwe", ts_args_type = "payload: Buffer | string, key: Buffer | object | string, options: JweEncryptOptions")]
+pub fn encrypt_jwe(payload: Either<Buffer, String>, key: Either<Buffer, JsonValue>, options: JweEncryptOptions) -> Result<String, ErrorCode> {
+    let payload = match &payload {
+        Either::A(buffer) => buffer.as_ref(),
+        Either::B(text) => text.as_bytes(),
+    };
+    let key = jwe_key(key)?;
+    let alg = jwe::KeyAlgorithm::parse(&options.alg)?;
+    let enc = jwe::ContentAlgorithm::parse(options.enc.as_deref().unwrap_or("A256GCM"))?;
+    let mut header = match options.header {
+        Some(JsonValue(serde_json::Value::Object(header))) => header,
+        Some(_) => return Err(Error::new(ErrorCode::InvalidArgument, "JWE header must be an object")),
+        None => serde_json::Map::new(),
+    };
+    for (name, value) in [("kid", options.kid), ("typ", options.typ), ("cty", options.cty)] {
+        if let Some(value) = value {
+            header.insert(name.to_string(), serde_json::Value::String(value));
+        }
+    }
+    jwe::encrypt(payload, &key, jwe::JweParams { alg, enc, header }).map_err(Error::from)
+}
+
+/// 解密 JWE 紧凑序列化令牌，返回 { plaintext, header } - 密钥类型决定可以接受的 alg，认证失败时报错
+#[napi(js_name = "decryptJwe", ts_args_type = "token: string, key: Buffer | object | string")]
+pub fn decrypt_jwe(token: String, key: Either<Buffer, JsonValue>) -> Result<JweDecryptResult, ErrorCode> {
+    let key = jwe_key(key)?;
+    let (plaintext, header) = jwe::decrypt(&token, &key)?;
+    
+    // 创建并返回结果对象
+    Ok(JweDecryptResult {
+        plaintext: Buffer::from(plaintext),
+        header: serde_json::Value::Object(header),
+    })
+}
+
+/// 可选的 Buffer 或字符串参数的字节内容，字符串按 UTF-8，未设置时为空
+fn bytes_or_text(value: &Option<Either<Buffer, String>>) -> &[u8] {
+    match value {
+        Some(Either::A(buffer)) => buffer.as_ref(),
+        Some(Either::B(text)) => text.as_bytes(),
+        None => &[],
+    }
+}
+
+/// 生成 PASETO v4.local 令牌 - XChaCha20 加密、BLAKE2b-MAC 认证，可选 footer 和隐式断言
+#[napi(js_name = "pasetoEncrypt")]
+pub fn paseto_encrypt(key: Buffer, payload: Either<Buffer, String>, options: Option<PasetoOptions>) -> Result<String, ErrorCode> {
+    let options = options.unwrap_or_default();
+    let payload = match &payload {
+        Either::A(buffer) => buffer.as_ref(),
+        Either::B(text) => text.as_bytes(),
+    };
+    paseto::encrypt(&key, payload, bytes_or_text(&options.footer), bytes_or_text(&options.implicit_assertion)).map_err(Error::from)
+}
+
+/// 验证并解密 PASETO v4.local 令牌，返回 { payload, footer } - 设置 footer 时令牌的 footer 必须与之相同
+#[napi(js_name = "pasetoDecrypt")]
+pub fn paseto_decrypt(key: Buffer, token: String, options: Option<PasetoOptions>) -> Result<PasetoDecryptResult, ErrorCode> {
+    let options = options.unwrap_or_default();
+    let expected_footer = options.footer.as_ref().map(|_| bytes_or_text(&options.footer));
+    let (payload, footer) = paseto::decrypt(&key, &token, expected_footer, bytes_or_text(&options.implicit_assertion))?;
+    
+    // 创建并返回结果对象
+    Ok(PasetoDecryptResult {
+        payload: Buffer::from(payload),
+        footer: Buffer::from(footer),
+    })
+}
+
+/// 把文件加密为 CMS / PKCS#7 EnvelopedData - 内容密钥以每个接收方 X.509 证书中的 RSA 公钥加密，输出 DER 或 PEM
+#[napi(js_name = "cmsEncryptFile", ts_args_type = "recipients: Array<Buffer | string>, inputPath: string, outputPath: string, options?: CmsEncryptOptions | undefined | null")]
+pub fn cms_encrypt_file(recipients: Vec<Either<Buffer, String>>, input_path: Either<String, i32>, output_path: Either<String, i32>, options: Option<CmsEncryptOptions>) -> Result<CmsEncryptResult, ErrorCode> {
+    let options = options.unwrap_or_default();
+    let control = JobControl::new("cmsEncryptFile", options.timeout_ms);
+    control.started();
+    let result = cms_encrypt_file_job(recipients, input_path, output_path, options, &control);
+    control.complete(result)
+}
+
+/// cmsEncryptFile() 的异步版本，在 libuv 线程池中运行并返回 Promise
+#[napi(js_name = "cmsEncryptFileAsync", ts_args_type = "recipients: Array<Buffer | string>, inputPath: string, outputPath: string, options?: CmsEncryptOptions | undefined | null, signal?: AbortSignal | Job | undefined | null", ts_return_type = "Promise<CmsEncryptResult>")]
+pub fn cms_encrypt_file_async(recipients: Vec<Either<Buffer, String>>, input_path: Either<String, i32>, output_path: Either<String, i32>, options: Option<CmsEncryptOptions>, signal: Option<Either<AbortSignal, ClassInstance<Job>>>) -> AsyncTask<FileTask<CmsEncryptResult>> {
+    let options = options.unwrap_or_default();
+    let control = JobControl::new("cmsEncryptFile", options.timeout_ms);
+    FileTask::new(control, signal, move |control| cms_encrypt_file_job(recipients, input_path, output_path, options, control))
+}
+
+fn cms_encrypt_file_job(recipients: Vec<Either<Buffer, String>>, input_path: Either<String, i32>, output_path: Either<String, i32>, options: CmsEncryptOptions, control: &JobControl) -> Result<CmsEncryptResult, ErrorCode> {
+    let input_path = fd::input_path(input_path)?;
+    let output_path = fd::output_path(output_path)?;
+    runtime::ensure_ready()?;
+    let recipients = recipients
+        .iter()
+        .map(|certificate| match certificate {
+            Either::A(buffer) => cms::Recipient::from_certificate(buffer),
+            Either::B(text) => cms::Recipient::from_certificate(text.as_bytes()),
+        })
+        .collect::<std::result::Result<Vec<_>, CodedError>>()?;
+    let transport = cms::KeyTransport::parse(options.key_transport.as_deref().unwrap_or("pkcs1"))?;
+    let cipher = cms::ContentCipher::parse(options.content_encryption.as_deref().unwrap_or("aes-256-cbc"))?;
+    let format = cms::OutputFormat::parse(options.format.as_deref().unwrap_or("der"))?;
+    let output = fd::AtomicOutput::new(&output_path);
+    let report = cms::encrypt_file(&input_path, output.path(), &recipients, transport, cipher, format, control)?;
+    output.commit()?;
+    
+    // 创建并返回结果对象
+    Ok(CmsEncryptResult {
+        file_size_kb: (report.plaintext_size as f64) / 1024.0,
+        file_size_bytes: byte_count(report.plaintext_size),
+        encrypted_size_kb: (report.encrypted_size as f64) / 1024.0,
+        encrypted_size_bytes: byte_count(report.encrypted_size),
+        recipients: recipients.len() as u32,
+    })
+}
+
+/// 生成 minisign 密钥对，返回 { publicKey, secretKey, keyId } - 公钥和私钥为 minisign 公钥文件和私钥文件的内容
+#[napi(js_name = "generateMinisignKeyPair")]
+pub fn generate_minisign_key_pair(options: Option<MinisignKeyOptions>) -> Result<MinisignKeyPair, ErrorCode> {
+    let options = options.unwrap_or_default();
+    let key_pair = minisign::generate_key_pair(options.password.as_deref())?;
+    Ok(MinisignKeyPair {
+        public_key: key_pair.public_key,
+        secret_key: key_pair.secret_key,
+        key_id: key_pair.key_id,
+    })
+}
+
+/// 以 minisign 格式为文件签名并写出 .minisig 文件 - 可以用 `minisign -V` 或 `rsign verify` 验证
+#[napi(js_name = "minisignSignFile")]
+pub fn minisign_sign_file(input_path: Either<String, i32>, secret_key: String, options: Option<MinisignSignOptions>) -> Result<MinisignSignResult, ErrorCode> {
+    let options = options.unwrap_or_default();
+    let control = JobControl::new("minisignSignFile", options.timeout_ms);
+    control.started();
+    let result = minisign_sign_file_job(input_path, secret_key, options, &control);
+    control.complete(result)
+}
+
+/// minisignSignFile() 的异步版本，在 libuv 线程池中运行并返回 Promise
+#[napi(js_name = "minisignSignFileAsync", ts_return_type = "Promise<MinisignSignResult>")]
+pub fn minisign_sign_file_async(input_path: Either<String, i32>, secret_key: String, options: Option<MinisignSignOptions>, signal: Option<Either<AbortSignal, ClassInstance<Job>>>) -> AsyncTask<FileTask<MinisignSignResult>> {
+    let options = options.unwrap_or_default();
+    let control = JobControl::new("minisignSignFile", options.timeout_ms);
+    FileTask::new(control, signal, move |control| minisign_sign_file_job(input_path, secret_key, options, control))
+}
+
+/// 签名文件路径，默认为输入路径加 `.minisig`；输入为文件描述符时没有默认路径
+fn minisign_signature_path(signature_path: Option<String>, input_path: &fd::Target) -> Result<String, ErrorCode> {
+    match (signature_path, input_path.as_path()) {
+        (Some(signature_path), _) => Ok(signature_path),
+        (None, Some(input_path)) => Ok(format!("{}.minisig", input_path)),
+        (None, None) => Err(Error::new(ErrorCode::InvalidArgument, "signaturePath is required when the input is a file descriptor")),
+    }
+}
+
+fn minisign_sign_file_job(input_path: Either<String, i32>, secret_key: String, options: MinisignSignOptions, control: &JobControl) -> Result<MinisignSignResult, ErrorCode> {
+    let input_path = fd::input_path(input_path)?;
+    runtime::ensure_ready()?;
+    let report = minisign::sign_file(
+        &input_path,
+        &secret_key,
+        options.password.as_deref(),
+        options.trusted_comment.as_deref(),
+        options.untrusted_comment.as_deref(),
+        || control.check(),
+        |index, bytes| control.chunk_done(index, bytes),
+    )?;
+    let signature_path = minisign_signature_path(options.signature_path, &input_path)?;
+    std::fs::write(&signature_path, &report.signature)
+        .map_err(|err| Error::new(ErrorCode::IoWrite, format!("Error writing signature file: {}", err)))?;
+    
+    // 创建并返回结果对象
+    Ok(MinisignSignResult {
+        signature_path,
+        signature: report.signature,
+        key_id: report.key_id,
+        trusted_comment: report.trusted_comment,
+        file_size_kb: (report.file_size as f64) / 1024.0,
+        file_size_bytes: byte_count(report.file_size),
+    })
+}
+
+/// 验证文件的 minisign 签名，返回 { valid, keyId, trustedComment, error } - 签名不匹配时 valid 为 false，格式错误或密钥 ID 不符时报错
+#[napi(js_name = "minisignVerifyFile")]
+pub fn minisign_verify_file(input_path: Either<String, i32>, public_key: String, options: Option<MinisignVerifyOptions>) -> Result<MinisignVerifyResult, ErrorCode> {
+    let options = options.unwrap_or_default();
+    let control = JobControl::new("minisignVerifyFile", options.timeout_ms);
+    control.started();
+    let result = minisign_verify_file_job(input_path, public_key, options, &control);
+    control.complete(result)
+}
+
+/// minisignVerifyFile() 的异步版本，在 libuv 线程池中运行并返回 Promise
+#[napi(js_name = "minisignVerifyFileAsync", ts_return_type = "Promise<MinisignVerifyResult>")]
+pub fn minisign_verify_file_async(input_path: Either<String, i32>, public_key: String, options: Option<MinisignVerifyOptions>, signal: Option<Either<AbortSignal, ClassInstance<Job>>>) -> AsyncTask<FileTask<MinisignVerifyResult>> {
+    let options = options.unwrap_or_default();
+    let control = JobControl::new("minisignVerifyFile", options.timeout_ms);
+    FileTask::new(control, signal, move |control| minisign_verify_file_job(input_path, public_key, options, control))
+}
+
+fn minisign_verify_file_job(input_path: Either<String, i32>, public_key: String, options: MinisignVerifyOptions, control: &JobControl) -> Result<MinisignVerifyResult, ErrorCode> {
+    let input_path = fd::input_path(input_path)?;
+    runtime::ensure_ready()?;
+    let signature_path = minisign_signature_path(options.signature_path, &input_path)?;
+    let signature = std::fs::read_to_string(&signature_path)
+        .map_err(|err| Error::new(ErrorCode::IoRead, format!("Error reading signature file: {}", err)))?;
+    let report = minisign::verify_file(&input_path, &public_key, &signature, || control.check(), |index, bytes| control.chunk_done(index, bytes))?;
+    
+    // 创建并返回结果对象
+    Ok(MinisignVerifyResult {
+        valid: report.valid,
+        key_id: report.key_id,
+        trusted_comment: report.trusted_comment,
+        error: report.error,
+    })
+}
+
+/// 生成 saltpack 使用的 Curve25519 密钥对，返回 { publicKey, secretKey }，均为 32 字节
+#[napi(js_name = "generateSaltpackKeyPair")]
+pub fn generate_saltpack_key_pair() -> Result<SaltpackKeyPair, ErrorCode> {
+    let (public_key, secret_key) = saltpack::generate_key_pair();
+    Ok(SaltpackKeyPair {
+        public_key: Buffer::from(public_key.to_vec()),
+        secret_key: Buffer::from(secret_key.to_vec()),
+    })
+}
+
+/// 以 saltpack 加密格式（v2）加密文件 - 二进制或 base62 文本封装，可以用 Keybase 时代的 saltpack 工具解密
+#[napi(js_name = "saltpackEncryptFile")]
+pub fn saltpack_encrypt_file(input_path: Either<String, i32>, output_path: Either<String, i32>, options: SaltpackEncryptOptions) -> Result<SaltpackEncryptResult, ErrorCode> {
+    let control = JobControl::new("saltpackEncryptFile", options.timeout_ms);
+    control.started();
+    let result = saltpack_encrypt_file_job(input_path, output_path, options, &control);
+    control.complete(result)
+}
+
+/// saltpackEncryptFile() 的异步版本，在 libuv 线程池中运行并返回 Promise
+#[napi(js_name = "saltpackEncryptFileAsync", ts_return_type = "Promise<SaltpackEncryptResult>")]
+pub fn saltpack_encrypt_file_async(input_path: Either<String, i32>, output_path: Either<String, i32>, options: SaltpackEncryptOptions, signal: Option<Either<AbortSignal, ClassInstance<Job>>>) -> AsyncTask<FileTask<SaltpackEncryptResult>> {
+    let control = JobControl::new("saltpackEncryptFile", options.timeout_ms);
+    FileTask::new(control, signal, move |control| saltpack_encrypt_file_job(input_path, output_path, options, control))
+}
+
+fn saltpack_encrypt_file_job(input_path: Either<String, i32>, output_path: Either<String, i32>, options: SaltpackEncryptOptions, control: &JobControl) -> Result<SaltpackEncryptResult, ErrorCode> {
+    let input_path = fd::input_path(input_path)?;
+    let output_path = fd::output_path(output_path)?;
+    runtime::ensure_ready()?;
+    let recipients = options
+        .recipients
+        .iter()
+        .map(|key| saltpack::key_from_slice(key, "recipient public key"))
+        .collect::<std::result::Result<Vec<_>, CodedError>>()?;
+    let sender = options
+        .sender_secret_key
+        .as_deref()
+        .map(|key| saltpack::key_from_slice(key, "sender secret key"))
+        .transpose()?;
+    let armor = options.armor.unwrap_or(false);
+    let hide_recipients = options.hide_recipients.unwrap_or(false);
+    let output = fd::AtomicOutput::new(&output_path);
+    let report = saltpack::encrypt_file(&input_path, output.path(), &recipients, sender.as_ref(), hide_recipients, armor, control)?;
+    output.commit()?;
+    
+    // 创建并返回结果对象
+    Ok(SaltpackEncryptResult {
+        file_size_kb: (report.plaintext_size as f64) / 1024.0,
+        file_size_bytes: byte_count(report.plaintext_size),
+        encrypted_size_kb: (report.encrypted_size as f64) / 1024.0,
+        encrypted_size_bytes: byte_count(report.encrypted_size),
+        recipients: recipients.len() as u32,
+        armored: armor,
+    })
+}
+
+/// 解密 saltpack 加密消息（v1 或 v2，二进制或 base62 文本封装），返回发送者公钥，匿名发送时为 null
+#[napi(js_name = "saltpackDecryptFile")]
+pub fn saltpack_decrypt_file(input_path: Either<String, i32>, output_path: Either<String, i32>, options: SaltpackDecryptOptions) -> Result<SaltpackDecryptResult, ErrorCode> {
+    let control = JobControl::new("saltpackDecryptFile", options.timeout_ms);
+    control.started();
+    let result = saltpack_decrypt_file_job(input_path, output_path, options, &control);
+    control.complete(result)
+}
+
+/// saltpackDecryptFile() 的异步版本，在 libuv 线程池中运行并返回 Promise
+#[napi(js_name = "saltpackDecryptFileAsync", ts_return_type = "Promise<SaltpackDecryptResult>")]
+pub fn saltpack_decrypt_file_async(input_path: Either<String, i32>, output_path: Either<String, i32>, options: SaltpackDecryptOptions, signal: Option<Either<AbortSignal, ClassInstance<Job>>>) -> AsyncTask<FileTask<SaltpackDecryptResult>> {
+    let control = JobControl::new("saltpackDecryptFile", options.timeout_ms);
+    FileTask::new(control, signal, move |control| saltpack_decrypt_file_job(input_path, output_path, options, control))
+}
+
+fn saltpack_decrypt_file_job(input_path: Either<String, i32>, output_path: Either<String, i32>, options: SaltpackDecryptOptions, control: &JobControl) -> Result<SaltpackDecryptResult, ErrorCode> {
+    let input_path = fd::input_path(input_path)?;
+    let output_path = fd::output_path(output_path)?;
+    runtime::ensure_ready()?;
+    let secret_key = saltpack::key_from_slice(&options.secret_key, "secret key")?;
+    let output = fd::AtomicOutput::new(&output_path);
+    let report = saltpack::decrypt_file(&input_path, output.path(), &secret_key, control)?;
+    output.commit()?;
+    
+    // 创建并返回结果对象
+    Ok(SaltpackDecryptResult {
+        file_size_kb: (report.plaintext_size as f64) / 1024.0,
+        file_size_bytes: byte_count(report.plaintext_size),
+        encrypted_size_kb: (report.encrypted_size as f64) / 1024.0,
+        encrypted_size_bytes: byte_count(report.encrypted_size),
+        sender: report.sender.map(|sender| Buffer::from(sender.to_vec())),
+        output_path: output_path.to_string(),
+    })
+}
+
+/// 按 HLS 的 AES-128 方法加密一个 MPEG-TS / fMP4 分段，返回播放列表中使用的 `#EXT-X-KEY` 行，可选写出密钥文件
+#[napi(js_name = "hlsEncryptSegment")]
+pub fn hls_encrypt_segment(key: Buffer, input_path: Either<String, i32>, output_path: Either<String, i32>, options: HlsSegmentOptions) -> Result<HlsSegmentResult, ErrorCode> {
+    let control = JobControl::new("hlsEncryptSegment", options.timeout_ms);
+    control.started();
+    let result = hls_encrypt_segment_job(key, input_path, output_path, options, &control);
+    control.complete(result)
+}
+
+/// hlsEncryptSegment() 的异步版本，在 libuv 线程池中运行并返回 Promise
+#[napi(js_name = "hlsEncryptSegmentAsync", ts_return_type = "Promise<HlsSegmentResult>")]
+pub fn hls_encrypt_segment_async(key: Buffer, input_path: Either<String, i32>, output_path: Either<String, i32>, options: HlsSegmentOptions, signal: Option<Either<AbortSignal, ClassInstance<Job>>>) -> AsyncTask<FileTask<HlsSegmentResult>> {
+    let control = JobControl::new("hlsEncryptSegment", options.timeout_ms);
+    FileTask::new(control, signal, move |control| hls_encrypt_segment_job(key, input_path, output_path, options, control))
+}
+
+fn hls_encrypt_segment_job(key: Buffer, input_path: Either<String, i32>, output_path: Either<String, i32>, options: HlsSegmentOptions, control: &JobControl) -> Result<HlsSegmentResult, ErrorCode> {
+    let input_path = fd::input_path(input_path)?;
+    let output_path = fd::output_path(output_path)?;
+    runtime::ensure_ready()?;
+    let key = hls::key_from_slice(&key)?;
+    let explicit_iv = match (options.sequence, options.iv.as_deref()) {
+        (Some(_), Some(_)) => return Err(Error::new(ErrorCode::InvalidArgument, "sequence and iv cannot be used together")),
+        (None, None) => return Err(Error::new(ErrorCode::InvalidArgument, "Either sequence or iv is required")),
+        (Some(_), None) => None,
+        (None, Some(iv)) => Some(<[u8; hls::IV_LEN]>::try_from(iv).map_err(|_| {
+            Error::new(ErrorCode::InvalidKeyLength, format!("HLS IV must be {} bytes, got {}", hls::IV_LEN, iv.len()))
+        })?),
+    };
+    let iv = explicit_iv.unwrap_or_else(|| hls::iv_from_sequence(options.sequence.unwrap_or_default() as u64));
+    let key_uri = match (&options.key_uri, &options.key_path) {
+        (Some(uri), _) => uri.clone(),
+        (None, Some(path)) => Path::new(path)
+            .file_name()
+            .map(|name| name.to_string_lossy().into_owned())
+            .ok_or_else(|| Error::new(ErrorCode::InvalidArgument, format!("Invalid key path: {}", path)))?,
+        (None, None) => return Err(Error::new(ErrorCode::InvalidArgument, "keyUri or keyPath is required for the #EXT-X-KEY line")),
+    };
+    let key_line = hls::key_line(&key_uri, explicit_iv.as_ref())?;
+    let output = fd::AtomicOutput::new(&output_path);
+    let report = hls::encrypt_segment(&input_path, output.path(), &key, &iv, || control.check(), |index, bytes| control.chunk_done(index, bytes))?;
+    output.commit()?;
+    if let Some(key_path) = &options.key_path {
+        hls::write_key_file(key_path, &key)?;
+    }
+    
+    // 创建并返回结果对象
+    Ok(HlsSegmentResult {
+        file_size_kb: (report.plaintext_size as f64) / 1024.0,
+        file_size_bytes: byte_count(report.plaintext_size),
+        encrypted_size_kb: (report.encrypted_size as f64) / 1024.0,
+        encrypted_size_bytes: byte_count(report.encrypted_size),
+        key_line,
+        iv: format!("0x{}", hex::encode_upper(iv)),
+        key_path: options.key_path,
+    })
+}
+
+/// 以通用加密（CENC，cenc 或 cbcs 方案）加密分片 MP4 文件 - 使用给定的 16 字节密钥和 KID，输出可以在浏览器中通过 EME Clear Key 播放
+#[napi(js_name = "cencEncryptFile")]
+pub fn cenc_encrypt_file(key: Buffer, kid: Buffer, input_path: Either<String, i32>, output_path: Either<String, i32>, options: Option<CencEncryptOptions>) -> Result<CencEncryptResult, ErrorCode> {
+    let options = options.unwrap_or_default();
+    let control = JobControl::new("cencEncryptFile", options.timeout_ms);
+    control.started();
+    let result = cenc_encrypt_file_job(key, kid, input_path, output_path, options, &control);
+    control.complete(result)
+}
+
+/// cencEncryptFile() 的异步版本，在 libuv 线程池中运行并返回 Promise
+#[napi(js_name = "cencEncryptFileAsync", ts_return_type = "Promise<CencEncryptResult>")]
+pub fn cenc_encrypt_file_async(key: Buffer, kid: Buffer, input_path: Either<String, i32>, output_path: Either<String, i32>, options: Option<CencEncryptOptions>, signal: Option<Either<AbortSignal, ClassInstance<Job>>>) -> AsyncTask<FileTask<CencEncryptResult>> {
+    let options = options.unwrap_or_default();
+    let control = JobControl::new("cencEncryptFile", options.timeout_ms);
+    FileTask::new(control, signal, move |control| cenc_encrypt_file_job(key, kid, input_path, output_path, options, control))
+}
+
+fn cenc_encrypt_file_job(key: Buffer, kid: Buffer, input_path: Either<String, i32>, output_path: Either<String, i32>, options: CencEncryptOptions, control: &JobControl) -> Result<CencEncryptResult, ErrorCode> {
+    let input_path = fd::input_path(input_path)?;
+    let output_path = fd::output_path(output_path)?;
+    runtime::ensure_ready()?;
+    let key: [u8; cenc::KEY_LEN] = key
+        .as_ref()
+        .try_into()
+        .map_err(|_| Error::new(ErrorCode::InvalidKeyLength, format!("CENC key must be {} bytes, got {}", cenc::KEY_LEN, key.len())))?;
+    let kid: [u8; cenc::KID_LEN] = kid
+        .as_ref()
+        .try_into()
+        .map_err(|_| Error::new(ErrorCode::InvalidKeyLength, format!("CENC KID must be {} bytes, got {}", cenc::KID_LEN, kid.len())))?;
+    let scheme = cenc::Scheme::parse(options.scheme.as_deref().unwrap_or("cenc"))?;
+    let protection = cenc::Protection { key: &key, kid: &kid, scheme, iv: options.iv.as_deref(), clear_key_pssh: options.clear_key_pssh.unwrap_or(true) };
+    let output = fd::AtomicOutput::new(&output_path);
+    let report = cenc::encrypt_file(&input_path, output.path(), &protection, || control.check(), |index, bytes| control.chunk_done(index, bytes))?;
+    output.commit()?;
+    
+    // 创建并返回结果对象
+    Ok(CencEncryptResult {
+        file_size_kb: (report.plaintext_size as f64) / 1024.0,
+        file_size_bytes: byte_count(report.plaintext_size),
+        encrypted_size_kb: (report.encrypted_size as f64) / 1024.0,
+        encrypted_size_bytes: byte_count(report.encrypted_size),
+        scheme: scheme.as_str().to_string(),
+        kid: hex::encode(kid),
+        tracks: report.tracks,
+        fragments: report.fragments,
+        samples: report.samples as f64,
+    })
+}
+
+/// 由 rclone crypt 配置派生密钥和文件名加密器
+fn rclone_ciphers(options: &RcloneCryptOptions) -> Result<(rclone::CryptKeys, rclone::NameCipher), ErrorCode> {
+    let keys = rclone::CryptKeys::derive(&options.password, options.salt.as_deref())?;
+    let mode = rclone::NameEncryption::parse(options.filename_encryption.as_deref().unwrap_or("standard"))?;
+    let encoding = rclone::NameEncoding::parse(options.filename_encoding.as_deref().unwrap_or("base32"))?;
+    let names = rclone::NameCipher::new(&keys, mode, encoding, options.directory_name_encryption.unwrap_or(true));
+    Ok((keys, names))
+}
+
+/// 以 rclone crypt 远端的格式加密文件 - 上传到 crypt 远端底层存储后可以直接由 rclone 读取
+#[napi(js_name = "rcloneEncryptFile")]
+pub fn rclone_encrypt_file(input_path: Either<String, i32>, output_path: Either<String, i32>, options: RcloneCryptOptions) -> Result<FileSizes, ErrorCode> {
+    let control = JobControl::new("rcloneEncryptFile", options.timeout_ms);
+    control.started();
+    let result = rclone_encrypt_file_job(input_path, output_path, options, &control);
+    control.complete(result)
+}
+
+/// rcloneEncryptFile() 的异步版本，在 libuv 线程池中运行并返回 Promise
+#[napi(js_name = "rcloneEncryptFileAsync", ts_return_type = "Promise<FileSizes>")]
+pub fn rclone_encrypt_file_async(input_path: Either<String, i32>, output_path: Either<String, i32>, options: RcloneCryptOptions, signal: Option<Either<AbortSignal, ClassInstance<Job>>>) -> AsyncTask<FileTask<FileSizes>> {
+    let control = JobControl::new("rcloneEncryptFile", options.timeout_ms);
+    FileTask::new(control, signal, move |control| rclone_encrypt_file_job(input_path, output_path, options, control))
+}
+
+fn rclone_encrypt_file_job(input_path: Either<String, i32>, output_path: Either<String, i32>, options: RcloneCryptOptions, control: &JobControl) -> Result<FileSizes, ErrorCode> {
+    let input_path = fd::input_path(input_path)?;
+    let output_path = fd::output_path(output_path)?;
+    runtime::ensure_ready()?;
+    let (keys, _) = rclone_ciphers(&options)?;
+    let output = fd::AtomicOutput::new(&output_path);
+    let report = rclone::encrypt_file(&input_path, output.path(), &keys, control)?;
+    output.commit()?;
+    
+    // 创建并返回结果对象
+    Ok(FileSizes {
+        file_size_kb: (report.plaintext_size as f64) / 1024.0,
+        file_size_bytes: byte_count(report.plaintext_size),
+        encrypted_size_kb: (report.encrypted_size as f64) / 1024.0,
+        encrypted_size_bytes: byte_count(report.encrypted_size),
+    })
+}
+
+/// 解密 rclone crypt 远端底层存储中的加密文件
+#[napi(js_name = "rcloneDecryptFile")]
+pub fn rclone_decrypt_file(input_path: Either<String, i32>, output_path: Either<String, i32>, options: RcloneCryptOptions) -> Result<DecryptedFile, ErrorCode> {
+    let control = JobControl::new("rcloneDecryptFile", options.timeout_ms);
+    control.started();
+    let result = rclone_decrypt_file_job(input_path, output_path, options, &control);
+    control.complete(result)
+}
+
+/// rcloneDecryptFile() 的异步版本，在 libuv 线程池中运行并返回 Promise
+#[napi(js_name = "rcloneDecryptFileAsync", ts_return_type = "Promise<DecryptedFile>")]
+pub fn rclone_decrypt_file_async(input_path: Either<String, i32>, output_path: Either<String, i32>, options: RcloneCryptOptions, signal: Option<Either<AbortSignal, ClassInstance<Job>>>) -> AsyncTask<FileTask<DecryptedFile>> {
+    let control = JobControl::new("rcloneDecryptFile", options.timeout_ms);
+    FileTask::new(control, signal, move |control| rclone_decrypt_file_job(input_path, output_path, options, control))
+}
+
+fn rclone_decrypt_file_job(input_path: Either<String, i32>, output_path: Either<String, i32>, options: RcloneCryptOptions, control: &JobControl) -> Result<DecryptedFile, ErrorCode> {
+    let input_path = fd::input_path(input_path)?;
+    let output_path = fd::output_path(output_path)?;
+    runtime::ensure_ready()?;
+    let (keys, _) = rclone_ciphers(&options)?;
+    let output = fd::AtomicOutput::new(&output_path);
+    let report = rclone::decrypt_file(&input_path, output.path(), &keys, control)?;
+    output.commit()?;
+    
+    // 创建并返回结果对象
+    Ok(DecryptedFile {
+        file_size_kb: (report.plaintext_size as f64) / 1024.0,
+        file_size_bytes: byte_count(report.plaintext_size),
+        encrypted_size_kb: (report.encrypted_size as f64) / 1024.0,
+        encrypted_size_bytes: byte_count(report.encrypted_size),
+        output_path: output_path.to_string(),
+    })
+}
+
+/// 按 rclone crypt 的文件名加密规则加密以 `/` 分隔的相对路径，返回底层存储中的路径
+#[napi(js_name = "rcloneEncryptName")]
+pub fn rclone_encrypt_name(path: String, options: RcloneCryptOptions) -> Result<String, ErrorCode> {
+    let (_, names) = rclone_ciphers(&options)?;
+    names.encrypt_path(&path).map_err(Error::from)
+}
+
+/// 把 rclone crypt 底层存储中的路径还原为原始路径
+#[napi(js_name = "rcloneDecryptName")]
+pub fn rclone_decrypt_name(path: String, options: RcloneCryptOptions) -> Result<String, ErrorCode> {
+    let (_, names) = rclone_ciphers(&options)?;
+    names.decrypt_path(&path).map_err(Error::from)
+}
+
+/// 把明文目录树加密为 gocryptfs 密文目录（v2 格式，`gocryptfs -init` 的默认特性），可以直接用 gocryptfs 挂载读取
+#[napi(js_name = "gocryptfsEncryptDirectory")]
+pub fn gocryptfs_encrypt_directory(input_dir: String, output_dir: String, options: GocryptfsOptions) -> Result<GocryptfsResult, ErrorCode> {
+    let control = JobControl::new("gocryptfsEncryptDirectory", options.timeout_ms);
+    control.started();
+    let result = gocryptfs_encrypt_directory_job(input_dir, output_dir, options, &control);
+    control.complete(result)
+}
+
+/// gocryptfsEncryptDirectory() 的异步版本，在 libuv 线程池中运行并返回 Promise
+#[napi(js_name = "gocryptfsEncryptDirectoryAsync", ts_return_type = "Promise<GocryptfsResult>")]
+pub fn gocryptfs_encrypt_directory_async(input_dir: String, output_dir: String, options: GocryptfsOptions, signal: Option<Either<AbortSignal, ClassInstance<Job>>>) -> AsyncTask<FileTask<GocryptfsResult>> {
+    let control = JobControl::new("gocryptfsEncryptDirectory", options.timeout_ms);
+    FileTask::new(control, signal, move |control| gocryptfs_encrypt_directory_job(input_dir, output_dir, options, control))
+}
+
+fn gocryptfs_encrypt_directory_job(input_dir: String, output_dir: String, options: GocryptfsOptions, control: &JobControl) -> Result<GocryptfsResult, ErrorCode> {
+    runtime::ensure_ready()?;
+    let password = options
+        .password
+        .ok_or_else(|| Error::new(ErrorCode::InvalidArgument, "password is required to create a gocryptfs file system"))?;
+    let log_n = options.scrypt_log_n.map_or(gocryptfs::DEFAULT_SCRYPT_LOG_N, |log_n| log_n.min(u8::MAX as u32) as u8);
+    let report = gocryptfs::encrypt_directory(&input_dir, &output_dir, &password, log_n, control)?;
+    
+    // 创建并返回结果对象
+    Ok(GocryptfsResult {
+        files: report.files,
+        directories: report.directories,
+        file_size_kb: (report.plaintext_size as f64) / 1024.0,
+        file_size_bytes: byte_count(report.plaintext_size),
+        encrypted_size_kb: (report.encrypted_size as f64) / 1024.0,
+        encrypted_size_bytes: byte_count(report.encrypted_size),
+        master_key: Buffer::from(report.master_key.to_vec()),
+    })
+}
+
+/// 取得 gocryptfs 文件名加密器：优先使用 masterKey，否则用口令解开密文目录中的 gocryptfs.conf
+fn gocryptfs_names(cipher_dir: &str, options: &GocryptfsOptions) -> Result<gocryptfs::NameCipher, ErrorCode> {
+    let master_key = match (&options.master_key, &options.password) {
+        (Some(key), _) => <[u8; gocryptfs::KEY_LEN]>::try_from(key.as_ref())
+            .map_err(|_| Error::new(ErrorCode::InvalidKeyLength, format!("gocryptfs master key must be {} bytes, got {}", gocryptfs::KEY_LEN, key.len())))?,
+        (None, Some(password)) => gocryptfs::unlock_master_key(cipher_dir, password)?,
+        (None, None) => return Err(Error::new(ErrorCode::InvalidArgument, "password or masterKey is required")),
+    };
+    Ok(gocryptfs::NameCipher::new(&master_key))
+}
+
+/// 计算明文相对路径在 gocryptfs 密文目录中对应的路径（同一目录中相同的名称总是得到相同的密文）
+#[napi(js_name = "gocryptfsEncryptPath")]
+pub fn gocryptfs_encrypt_path(cipher_dir: String, path: String, options: GocryptfsOptions) -> Result<String, ErrorCode> {
+    let names = gocryptfs_names(&cipher_dir, &options)?;
+    gocryptfs::encrypt_path(&cipher_dir, &path, &names).map_err(Error::from)
+}
+
+/// 把 gocryptfs 密文目录中的相对路径还原为明文路径
+#[napi(js_name = "gocryptfsDecryptPath")]
+pub fn gocryptfs_decrypt_path(cipher_dir: String, path: String, options: GocryptfsOptions) -> Result<String, ErrorCode> {
+    let names = gocryptfs_names(&cipher_dir, &options)?;
+    gocryptfs::decrypt_path(&cipher_dir, &path, &names).map_err(Error::from)
+}
+
+/// 把 s3EncryptFile() / s3DecryptFile() 的主密钥参数转换为 S3 主密钥：Buffer 为 AES 密钥，对象或 JSON 字符串为 RSA JWK
+fn s3_master_key(key: Either<Buffer, JsonValue>) -> Result<s3crypto::MasterKey, ErrorCode> {
+    match jwe_key(key)? {
+        jwe::JweKey::Symmetric(key) => Ok(s3crypto::MasterKey::Aes(key)),
+        jwe::JweKey::Rsa(key) => Ok(s3crypto::MasterKey::Rsa(key)),
+    }
+}
+
+/// 按 AWS S3 加密客户端 V2/V3 的信封格式加密文件 - 内容以 AES-256-GCM 加密，返回的 metadata 作为对象的用户元数据上传后可以由官方 SDK 解密
+#[napi(js_name = "s3EncryptFile", ts_args_type = "inputPath: string, outputPath: string, key: Buffer | object | string, options?: S3EncryptOptions | undefined | null")]
+pub fn s3_encrypt_file(input_path: Either<String, i32>, output_path: Either<String, i32>, key: Either<Buffer, JsonValue>, options: Option<S3EncryptOptions>) -> Result<S3EncryptResult, ErrorCode> {
+    let options = options.unwrap_or_default();
+    let control = JobControl::new("s3EncryptFile", options.timeout_ms);
+    control.started();
+    let result = s3_encrypt_file_job(input_path, output_path, key, options, &control);
+    control.complete(result)
+}
+
+/// s3EncryptFile() 的异步版本，在 libuv 线程池中运行并返回 Promise
+#[napi(js_name = "s3EncryptFileAsync", ts_args_type = "inputPath: string, outputPath: string, key: Buffer | object | string, options?: S3EncryptOptions | undefined | null, signal?: AbortSignal | Job | undefined | null", ts_return_type = "Promise<S3EncryptResult>")]
+pub fn s3_encrypt_file_async(input_path: Either<String, i32>, output_path: Either<String, i32>, key: Either<Buffer, JsonValue>, options: Option<S3EncryptOptions>, signal: Option<Either<AbortSignal, ClassInstance<Job>>>) -> AsyncTask<FileTask<S3EncryptResult>> {
+    let options = options.unwrap_or_default();
+    let control = JobControl::new("s3EncryptFile", options.timeout_ms);
+    FileTask::new(control, signal, move |control| s3_encrypt_file_job(input_path, output_path, key, options, control))
+}
+
+fn s3_encrypt_file_job(input_path: Either<String, i32>, output_path: Either<String, i32>, key: Either<Buffer, JsonValue>, options: S3EncryptOptions, control: &JobControl) -> Result<S3EncryptResult, ErrorCode> {
+    let input_path = fd::input_path(input_path)?;
+    let output_path = fd::output_path(output_path)?;
+    runtime::ensure_ready()?;
+    let master_key = s3_master_key(key)?;
+    let material_description = s3crypto::material_description_json(options.material_description.as_ref());
+    let output = fd::AtomicOutput::new(&output_path);
+    let report = s3crypto::encrypt_file(&input_path, output.path(), &master_key, material_description, control)?;
+    let metadata: serde_json::Map<String, serde_json::Value> = report
+        .metadata
+        .into_iter()
+        .map(|(name, value)| (name.to_string(), serde_json::Value::String(value)))
+        .collect();
+    if let Some(instruction_path) = &options.instruction_path {
+        let json = serde_json::to_string(&metadata).map_err(|err| Error::new(ErrorCode::Unknown, err.to_string()))?;
+        std::fs::write(instruction_path, json)
+            .map_err(|err| Error::new(ErrorCode::IoWrite, format!("Error writing instruction file: {}", err)))?;
+    }
+    output.commit()?;
+    
+    // 创建并返回结果对象
+    Ok(S3EncryptResult {
+        file_size_kb: (report.plaintext_size as f64) / 1024.0,
+        file_size_bytes: byte_count(report.plaintext_size),
+        encrypted_size_kb: (report.encrypted_size as f64) / 1024.0,
+        encrypted_size_bytes: byte_count(report.encrypted_size),
+        metadata: serde_json::Value::Object(metadata),
+    })
+}
+
+/// 解密 AWS S3 加密客户端 V2/V3 加密的对象 - metadata 为对象的用户元数据（可以带 x-amz-meta- 前缀）或指令文件的内容，不支持 KMS 包装的数据密钥
+#[napi(js_name = "s3DecryptFile", ts_args_type = "inputPath: string, outputPath: string, key: Buffer | object | string, metadata: Record<string, string>, options?: S3DecryptOptions | undefined | null")]
+pub fn s3_decrypt_file(input_path: Either<String, i32>, output_path: Either<String, i32>, key: Either<Buffer, JsonValue>, metadata: HashMap<String, String>, options: Option<S3DecryptOptions>) -> Result<DecryptedFile, ErrorCode> {
+    let options = options.unwrap_or_default();
+    let control = JobControl::new("s3DecryptFile", options.timeout_ms);
+    control.started();
+    let result = s3_decrypt_file_job(input_path, output_path, key, metadata, &control);
+    control.complete(result)
+}
+
+/// s3DecryptFile() 的异步版本，在 libuv 线程池中运行并返回 Promise
+#[napi(js_name = "s3DecryptFileAsync", ts_args_type = "inputPath: string, outputPath: string, key: Buffer | object | string, metadata: Record<string, string>, options?: S3DecryptOptions | undefined | null, signal?: AbortSignal | Job | undefined | null", ts_return_type = "Promise<DecryptedFile>")]
+pub fn s3_decrypt_file_async(input_path: Either<String, i32>, output_path: Either<String, i32>, key: Either<Buffer, JsonValue>, metadata: HashMap<String, String>, options: Option<S3DecryptOptions>, signal: Option<Either<AbortSignal, ClassInstance<Job>>>) -> AsyncTask<FileTask<DecryptedFile>> {
+    let options = options.unwrap_or_default();
+    let control = JobControl::new("s3DecryptFile", options.timeout_ms);
+    FileTask::new(control, signal, move |control| s3_decrypt_file_job(input_path, output_path, key, metadata, control))
+}
+
+fn s3_decrypt_file_job(input_path: Either<String, i32>, output_path: Either<String, i32>, key: Either<Buffer, JsonValue>, metadata: HashMap<String, String>, control: &JobControl) -> Result<DecryptedFile, ErrorCode> {
+    let input_path = fd::input_path(input_path)?;
+    let output_path = fd::output_path(output_path)?;
+    runtime::ensure_ready()?;
+    let master_key = s3_master_key(key)?;
+    let output = fd::AtomicOutput::new(&output_path);
+    let report = s3crypto::decrypt_file(&input_path, output.path(), &master_key, &metadata, control)?;
+    output.commit()?;
+    
+    // 创建并返回结果对象
+    Ok(DecryptedFile {
+        file_size_kb: (report.plaintext_size as f64) / 1024.0,
+        file_size_bytes: byte_count(report.plaintext_size),
+        encrypted_size_kb: (report.encrypted_size as f64) / 1024.0,
+        encrypted_size_bytes: byte_count(report.encrypted_size),
+        output_path: output_path.to_string(),
+    })
+}
+
+fn veracrypt_hash(options: &VeraCryptOptions) -> Result<Option<veracrypt::HeaderHash>, ErrorCode> {
+    options
+        .hash
+        .as_deref()
+        .map(veracrypt::HeaderHash::parse)
+        .transpose()
+        .map_err(Error::from)
+}
+
+/// 列出 VeraCrypt 文件容器中的文件和目录 - 只读打开标准卷或隐藏卷，支持 AES 加密、SHA-512 / SHA-256 卷头和 FAT / exFAT 文件系统
+#[napi(js_name = "veracryptListFiles")]
+pub fn veracrypt_list_files(container_path: Either<String, i32>, password: String, options: Option<VeraCryptOptions>) -> Result<Vec<VeraCryptEntry>, ErrorCode> {
+    veracrypt_list_files_job(container_path, password, options)
+}
+
+fn veracrypt_list_files_job(container_path: Either<String, i32>, password: String, options: Option<VeraCryptOptions>) -> Result<Vec<VeraCryptEntry>, ErrorCode> {
+    let container_path = fd::input_path(container_path)?;
+    let options = options.unwrap_or_default();
+    let hash = veracrypt_hash(&options)?;
+    let listing = veracrypt::list_volume(&container_path, password.as_bytes(), options.pim.unwrap_or(0), hash)?;
+    Ok(listing
+        .entries
+        .into_iter()
+        .map(|file| VeraCryptEntry {
+            path: file.path,
+            is_directory: file.is_dir,
+            file_size_kb: (file.size as f64) / 1024.0,
+            file_size_bytes: byte_count(file.size),
+            modified: file.modified_ms,
+        })
+        .collect())
+}
+
+/// veracryptListFiles() 的异步版本，在 libuv 线程池中运行并返回 Promise
+#[napi(js_name = "veracryptListFilesAsync", ts_return_type = "Promise<Array<VeraCryptEntry>>")]
+pub fn veracrypt_list_files_async(container_path: Either<String, i32>, password: String, options: Option<VeraCryptOptions>, signal: Option<Either<AbortSignal, ClassInstance<Job>>>) -> AsyncTask<FileTask<Vec<VeraCryptEntry>>> {
+    FileTask::plain(signal, move || veracrypt_list_files_job(container_path, password, options))
+}
+
+/// 把 VeraCrypt 文件容器中的文件解压到目录 - 用于把旧的 VeraCrypt 保险库迁移为本库的格式，容器本身不会被修改
+#[napi(js_name = "veracryptExtractFiles")]
+pub fn veracrypt_extract_files(container_path: Either<String, i32>, output_dir: String, password: String, options: Option<VeraCryptOptions>) -> Result<VeraCryptExtractResult, ErrorCode> {
+    let options = options.unwrap_or_default();
+    let control = JobControl::new("veracryptExtractFiles", options.timeout_ms);
+    control.started();
+    let result = veracrypt_extract_files_job(container_path, output_dir, password, options, &control);
+    control.complete(result)
+}
+
+/// veracryptExtractFiles() 的异步版本，在 libuv 线程池中运行并返回 Promise
+#[napi(js_name = "veracryptExtractFilesAsync", ts_return_type = "Promise<VeraCryptExtractResult>")]
+pub fn veracrypt_extract_files_async(container_path: Either<String, i32>, output_dir: String, password: String, options: Option<VeraCryptOptions>, signal: Option<Either<AbortSignal, ClassInstance<Job>>>) -> AsyncTask<FileTask<VeraCryptExtractResult>> {
+    let options = options.unwrap_or_default();
+    let control = JobControl::new("veracryptExtractFiles", options.timeout_ms);
+    FileTask::new(control, signal, move |control| veracrypt_extract_files_job(container_path, output_dir, password, options, control))
+}
+
+fn veracrypt_extract_files_job(container_path: Either<String, i32>, output_dir: String, password: String, options: VeraCryptOptions, control: &JobControl) -> Result<VeraCryptExtractResult, ErrorCode> {
+    let container_path = fd::input_path(container_path)?;
+    runtime::ensure_ready()?;
+    let hash = veracrypt_hash(&options)?;
+    let mut created = Vec::new();
+    let report = veracrypt::extract_volume(&container_path, &output_dir, password.as_bytes(), options.pim.unwrap_or(0), hash, control, &mut created)
+        .map_err(|err| {
+            walk::remove_created(&created);
+            Error::from(err)
+        })?;
+    
+    // 创建并返回结果对象
+    Ok(VeraCryptExtractResult {
+        files: report.files,
+        directories: report.directories,
+        file_size_kb: (report.plaintext_size as f64) / 1024.0,
+        file_size_bytes: byte_count(report.plaintext_size),
+        encrypted_size_kb: (report.container_size as f64) / 1024.0,
+        encrypted_size_bytes: byte_count(report.container_size),
+        filesystem: report.filesystem.to_string(),
+        hash: report.hash.to_string(),
+        hidden_volume: report.hidden,
+        output_dir,
+    })
+}
+
+/// 生成随机字节 - 使用与加密相同的随机数生成器，适合在本库之上构建自定义协议时生成密钥、盐值等
+#[napi(js_name = "randomBytes")]
+pub fn random_bytes(size: u32) -> Result<Buffer, ErrorCode> {
+    if size > MAX_RANDOM_BYTES {
+        return Err(Error::new(ErrorCode::InvalidArgument, format!("size must be at most {} bytes", MAX_RANDOM_BYTES)));
+    }
+    Ok(crypto::random_bytes(size as usize).into())
+}
+
+/// randomBytes 一次最多生成的字节数，与 Node 的 crypto.randomBytes 相同
+const MAX_RANDOM_BYTES: u32 = i32::MAX as u32;
+
+/// 生成该算法一次加密所需长度的随机 IV / nonce：aes 为 16 字节，chacha20poly1305 为 12 字节
+#[napi(js_name = "generateNonce")]
+pub fn generate_nonce(algorithm: String) -> Result<Buffer, ErrorCode> {
+    let algo = parse_algorithm(&algorithm)?;
+    Ok(crypto::random_bytes(algo.nonce_len()).into())
+}
+
+/// 由口令派生密钥 - 与使用口令的文件接口相同的 scrypt / PBKDF2，派生一次后可以在多次调用和会话之间复用
+///
+/// 相同的口令、盐和参数总是得到相同的密钥；字符串按 UTF-8 处理。scrypt 的默认参数需要约 64 MiB 内存，耗时较长时请使用 deriveKeyAsync()。
+#[napi(js_name = "deriveKey")]
+pub fn derive_key(password: Either<Buffer, String>, salt: Either<Buffer, String>, options: Option<KdfOptions>) -> Result<Buffer, ErrorCode> {
+    derive_key_job(password_bytes(password), password_bytes(salt), options.unwrap_or_default())
+}
+
+/// deriveKey() 的异步版本，在 libuv 线程池中运行并返回 Promise
+#[napi(js_name = "deriveKeyAsync", ts_return_type = "Promise<Buffer>")]
+pub fn derive_key_async(password: Either<Buffer, String>, salt: Either<Buffer, String>, options: Option<KdfOptions>, signal: Option<Either<AbortSignal, ClassInstance<Job>>>) -> AsyncTask<FileTask<Buffer>> {
+    // Buffer 不能在线程间共享，先复制为 Vec
+    let (password, salt) = (password_bytes(password), password_bytes(salt));
+    let options = options.unwrap_or_default();
+    FileTask::plain(signal, move || derive_key_job(password, salt, options))
+}
+
+/// 口令或盐的字节内容，字符串按 UTF-8
+fn password_bytes(value: Either<Buffer, String>) -> Vec<u8> {
+    match value {
+        Either::A(buffer) => buffer.to_vec(),
+        Either::B(text) => text.into_bytes(),
+    }
+}
+
+fn derive_key_job(password: Vec<u8>, salt: Vec<u8>, options: KdfOptions) -> Result<Buffer, ErrorCode> {
+    let kdf = match options.algorithm.as_deref().unwrap_or("scrypt") {
+        "scrypt" if options.iterations.is_some() || options.digest.is_some() => Err(CodedError::invalid_argument("iterations and digest require algorithm pbkdf2")),
+        "scrypt" => kdf::Kdf::scrypt(options.log_n, options.r, options.p),
+        "pbkdf2" if options.log_n.is_some() || options.r.is_some() || options.p.is_some() => Err(CodedError::invalid_argument("logN, r and p require algorithm scrypt")),
+        "pbkdf2" => kdf::Kdf::pbkdf2(options.digest.as_deref(), options.iterations),
+        other => Err(CodedError::unsupported(format!("Unsupported key derivation algorithm: {}, supported: scrypt, pbkdf2", other))),
+    }?;
+    let key_len = options.key_length.map_or(kdf::DEFAULT_KEY_LEN, |len| len as usize);
+    let key = kdf.derive(&password, &salt, key_len)?;
+    Ok(key.into())
+}
+
+/// 加密字符串 - 按 UTF-8 加密，返回 base64（默认）或 hex 编码的密文，适合配置项和数据库字段；密文与 encryptFile() 写出的文件内容格式相同
+#[napi(js_name = "encryptString")]
+pub fn encrypt_string(algorithm: String, key: Buffer, text: String, #[napi(ts_arg_type = "'base64' | 'hex'")] encoding: Option<String>) -> Result<String, ErrorCode> {
+    let algo = parse_algorithm(&algorithm)?;
+    let encoding = StringEncoding::parse(encoding.as_deref())?;
+    let header = ContainerHeader::for_new_file(Layout::Monolithic, algo.clone(), text.len() as u64, 0);
+    let mut data = header.to_bytes(&key)?;
+    let encrypted = encrypt(algo, &key, text.as_bytes())
+        .map_err(|e| e.context("Encryption error"))?;
+    data.extend_from_slice(&encrypted);
+    Ok(encoding.encode(&data))
+}
+
+/// 解密 encryptString() 生成的密文，encoding 须与加密时相同；明文必须是有效的 UTF-8
+#[napi(js_name = "decryptString")]
+pub fn decrypt_string(algorithm: String, key: Buffer, ciphertext: String, #[napi(ts_arg_type = "'base64' | 'hex'")] encoding: Option<String>) -> Result<String, ErrorCode> {
+    let algo = parse_algorithm(&algorithm)?;
+    let data = StringEncoding::parse(encoding.as_deref()).and_then(|encoding| encoding.decode(&ciphertext))?;
+    let (decrypted, _) = decrypt_monolithic(&algo, &key, &data)?;
+    String::from_utf8(decrypted).map_err(|_| Error::new(ErrorCode::BadFormat, "Decrypted data is not valid UTF-8"))
+}
+
+/// encryptString() / decryptString() 的密文编码
+enum StringEncoding {
+    Base64,
+    Hex,
+}
+
+impl StringEncoding {
+    fn parse(encoding: Option<&str>) -> Result<Self, ErrorCode> {
+        match encoding.unwrap_or("base64") {
+            "base64" => Ok(StringEncoding::Base64),
+            "hex" => Ok(StringEncoding::Hex),
+            other => Err(Error::new(ErrorCode::InvalidArgument, format!("Invalid encoding: {} (expected base64 or hex)", other))),
+        }
+    }
+
+    fn encode(&self, data: &[u8]) -> String {
+        match self {
+            StringEncoding::Base64 => STANDARD.encode(data),
+            StringEncoding::Hex => hex_encode(data),
+        }
+    }
+
+    fn decode(&self, text: &str) -> Result<Vec<u8>, ErrorCode> {
+        match self {
+            StringEncoding::Base64 => STANDARD.decode(text.trim())
+                .map_err(|err| Error::new(ErrorCode::BadFormat, format!("Invalid base64 ciphertext: {}", err))),
+            StringEncoding::Hex => hex::decode(text.trim())
+                .map_err(|err| Error::new(ErrorCode::BadFormat, format!("Invalid hex ciphertext: {}", err))),
+        }
+    }
+}
+
+/// 创建加密用的 TransformStream（WHATWG Web Streams），可用于 fetch 的请求体和响应体
+///
+/// 输出与 chunkEncryptFile() 写出的文件相同；size 为明文的总字节数（如 Content-Length），写入的数据总量必须与之相同。
+/// options 与 chunkEncryptFile() 相同，但只涉及输出文件的选项（分卷、纠错数据、稀疏、可追加、续传）不适用；未指定 MIME 类型时根据第一段数据检测。
+#[napi(js_name = "createEncryptStream", ts_return_type = "TransformStream<Uint8Array, Uint8Array>")]
+pub fn create_encrypt_stream(env: Env, algorithm: String, key: Buffer, size: f64, chunk_size_mb: u32, options: Option<EncryptOptions>) -> Result<JsObject, ErrorCode> {
+    let algo = parse_algorithm(&algorithm)?;
+    let (container, sniff_mime_type, size, chunk_size) = sized_container_header(options.unwrap_or_default(), &algo, &key, size, chunk_size_mb)?;
+    let transform = webstream::EncryptTransform::new(Vec::new(), algo, &key, container, sniff_mime_type, size, chunk_size);
+    webstream::create(env, transform)
+}
+
+/// 为预先声明明文大小、没有输入文件的加密（加密流和 ChunkedWriter）构造文件头，
+/// 返回文件头、是否需要根据第一段数据检测 MIME 类型、明文大小和分片大小
+fn sized_container_header(options: EncryptOptions, algo: &CryptoAlgorithm, key: &[u8], size: f64, chunk_size_mb: u32) -> Result<(Option<ContainerHeader>, bool, u64, usize), ErrorCode> {
+    if size < 0.0 || size.fract() != 0.0 || size > ((1u64 << 53) - 1) as f64 {
+        return Err(Error::new(ErrorCode::InvalidArgument, format!("Declared size must be a non-negative integer: {}", size)));
+    }
+    if options.volume_size_mb.is_some() || options.parity_percent.is_some() || options.sparse.unwrap_or(false)
+        || options.appendable.unwrap_or(false) || options.resume.unwrap_or(false)
+    {
+        return Err(Error::new(ErrorCode::Unsupported, "Volumes, parity, sparse, appendable and resumable output are not supported by encryption streams or ChunkedWriter"));
+    }
+    if options.store_filename.unwrap_or(false) && options.filename.is_none() {
+        return Err(Error::new(ErrorCode::InvalidArgument, "Encryption streams and ChunkedWriter require filename instead of storeFilename"));
+    }
+    // 没有输入文件可供检测 MIME 类型，文件头先以占位值构造，收到第一段数据时再检测
+    let sniff_mime_type = options.mime_type.is_none() && !options.legacy_format.unwrap_or(false);
+    let options = match sniff_mime_type {
+        true => EncryptOptions { mime_type: Some(mime::OCTET_STREAM.to_string()), ..options },
+        false => options,
+    };
+    if chunk_size_mb == 0 {
+        return Err(Error::new(ErrorCode::InvalidArgument, "Chunk size must be greater than 0"));
+    }
+    let chunk_size = (chunk_size_mb as usize) * 1024 * 1024;
+    let container = build_container_header(&options, None, Layout::Chunked, algo, key, size as u64, chunk_size as u64)?;
+    Ok((container, sniff_mime_type, size as u64, chunk_size))
+}
+
+/// 创建解密用的 TransformStream，输入为 chunkEncryptFile() 或 createEncryptStream() 的输出
+///
+/// 每个分片收齐后立即解密输出，输入结束时校验整个文件的 MAC 和明文摘要；出错时两端的流都会收到带错误码的错误。
+#[napi(js_name = "createDecryptStream", ts_return_type = "TransformStream<Uint8Array, Uint8Array>")]
+pub fn create_decrypt_stream(env: Env, algorithm: String, key: Buffer) -> Result<JsObject, ErrorCode> {
+    let algo = parse_algorithm(&algorithm)?;
+    let (check_algo, check_key) = (algo.clone(), key.to_vec());
+    let check_header = Box::new(move |header: &format::ChunkedHeader| {
+        check_chunked_header(header, &check_algo, &check_key).map_err(CodedError::from)
+    });
+    webstream::create(env, webstream::DecryptTransform::new(algo, &key, check_header))
+}
+
+/// 从 JS 数据源加密到 JS 接收端 - 数据来自 gRPC 流或自定义存储驱动时不必经过中间文件
+///
+/// source 为异步可迭代对象、可迭代对象，或每次返回一段明文（可以返回 Promise，结束时返回 null）的函数；
+/// sink 对每段密文调用一次，返回 Promise 时等它完成后再读取更多明文。输出以及 size 和 options 的要求与 createEncryptStream() 相同。
+#[napi(
+    js_name = "encryptFromSource",
+    ts_args_type = "algorithm: string, key: Buffer, source: AsyncIterable<Uint8Array> | Iterable<Uint8Array> | (() => Uint8Array | null | undefined | Promise<Uint8Array | null | undefined>), sink: (chunk: Buffer) => void | Promise<void>, size: number, chunkSizeMb: number, options?: EncryptOptions | undefined | null",
+    ts_return_type = "Promise<void>"
+)]
+#[allow(clippy::too_many_arguments)]
+pub fn encrypt_from_source(env: Env, algorithm: String, key: Buffer, source: JsUnknown, sink: JsFunction, size: f64, chunk_size_mb: u32, options: Option<EncryptOptions>) -> Result<JsObject, ErrorCode> {
+    let algo = parse_algorithm(&algorithm)?;
+    let (container, sniff_mime_type, size, chunk_size) = sized_container_header(options.unwrap_or_default(), &algo, &key, size, chunk_size_mb)?;
+    let transform = webstream::EncryptTransform::new(Vec::new(), algo, &key, container, sniff_mime_type, size, chunk_size);
+    webstream::pipe(env, transform, source, sink)
+}
+
+/// 从 JS 数据源解密到 JS 接收端，source 和 sink 同 encryptFromSource()，解密和校验同 createDecryptStream()
+#[napi(
+    js_name = "decryptFromSource",
+    ts_args_type = "algorithm: string, key: Buffer, source: AsyncIterable<Uint8Array> | Iterable<Uint8Array> | (() => Uint8Array | null | undefined | Promise<Uint8Array | null | undefined>), sink: (chunk: Buffer) => void | Promise<void>",
+    ts_return_type = "Promise<void>"
+)]
+pub fn decrypt_from_source(env: Env, algorithm: String, key: Buffer, source: JsUnknown, sink: JsFunction) -> Result<JsObject, ErrorCode> {
+    let algo = parse_algorithm(&algorithm)?;
+    let (check_algo, check_key) = (algo.clone(), key.to_vec());
+    let check_header = Box::new(move |header: &format::ChunkedHeader| {
+        check_chunked_header(header, &check_algo, &check_key).map_err(CodedError::from)
+    });
+    webstream::pipe(env, webstream::DecryptTransform::new(algo, &key, check_header), source, sink)
+}
+
+/// 分段写入的分片加密文件 - 数据陆续到达时（如 HTTP 上传）边收边加密，不必先缓存到临时文件再调用 chunkEncryptFile
+///
+/// 与 createEncryptStream() 相同，文件头记录原始大小，构造时必须声明明文的总长度（如 Content-Length）；
+/// 写入的数据超出声明的大小或 finish() 时不足都会出错。数据先写入临时文件，finish() 成功后才替换输出文件，
+/// 出错或调用 abort() 后删除临时文件。
+#[napi]
+pub struct ChunkedWriter {
+    output: Option<fd::AtomicOutput>,
+    size: u64,
+    chunk_size: usize,
+    encryptor: Option<webstream::EncryptTransform<BufWriter<File>>>,
+}
+
+#[napi]
+impl ChunkedWriter {
+    #[napi(constructor)]
+    pub fn new(algorithm: String, key: Buffer, output_path: Either<String, i32>, size: f64, chunk_size_mb: u32, options: Option<EncryptOptions>) -> Result<Self, ErrorCode> {
+        create_chunked_writer(algorithm, key, output_path, size, chunk_size_mb, options.unwrap_or_default())
+    }
+
+    /// 加密并写出一段数据，凑满一个分片后立即写入输出文件
+    #[napi]
+    pub fn write(&mut self, data: Buffer) -> Result<(), ErrorCode> {
+        let encryptor = self.encryptor()?;
+        if let Err(err) = encryptor.write(&data) {
+            return Err(self.fail(err));
         }
+        Ok(())
     }
-    
-    let chunk_size: usize = match chunk_size_str.parse() {
-        Ok(size) => size,
-        Err(_) => return Err(Error::from_reason("Invalid chunk size in header".to_string())),
+
+    /// 写入最后一个分片和文件结尾并关闭文件，之后不能再写入
+    #[napi]
+    pub fn finish(&mut self) -> Result<ChunkEncryptFileResult, ErrorCode> {
+        let (size, chunk_size) = (self.size, self.chunk_size);
+        let encryptor = self.encryptor()?;
+        let result = encryptor.take_encoder().and_then(|encoder| finish_chunked_writer(encoder, size, chunk_size));
+        self.encryptor = None;
+        let output = self.output.take();
+        let result = result.and_then(|result| output.map_or(Ok(()), fd::AtomicOutput::commit).map(|_| result));
+        result.map_err(|err| self.fail(err))
+    }
+
+    /// 放弃写入，关闭并删除未完成的输出文件；已经结束时没有影响
+    #[napi]
+    pub fn abort(&mut self) {
+        self.encryptor = None;
+        self.output = None;
+    }
+}
+
+impl ChunkedWriter {
+    fn encryptor(&mut self) -> Result<&mut webstream::EncryptTransform<BufWriter<File>>, ErrorCode> {
+        self.encryptor.as_mut().ok_or_else(|| Error::new(ErrorCode::InvalidArgument, "ChunkedWriter is closed"))
+    }
+
+    /// 出错后关闭并删除未完成的输出文件
+    fn fail(&mut self, err: CodedError) -> Error<ErrorCode> {
+        self.encryptor = None;
+        self.output = None;
+        err.into()
+    }
+}
+
+fn create_chunked_writer(algorithm: String, key: Buffer, output_path: Either<String, i32>, size: f64, chunk_size_mb: u32, options: EncryptOptions) -> Result<ChunkedWriter, ErrorCode> {
+    let output_path = fd::output_path(output_path)?;
+    let algo = parse_algorithm(&algorithm)?;
+    let (container, sniff_mime_type, size, chunk_size) = sized_container_header(options, &algo, &key, size, chunk_size_mb)?;
+    let output = fd::AtomicOutput::new(&output_path);
+    let output_file = match fd::create(output.path()) {
+        Ok(file) => file,
+        Err(err) => return Err(Error::new(ErrorCode::IoOpen, format!("Failed to create output file: {}", err))),
     };
-    
-    let mut total_bytes_written = 0;
-    let mut chunk_index = 0;
-    
-    // 读取并解密每个块
-    while total_bytes_written < original_size {
-        // 读取块大小
-        let mut chunk_enc_size_str = String::new();
-        loop {
-            match input_file.read_exact(&mut buffer) {
-                Ok(_) => {
-                    if buffer[0] == b':' {
-                        break;
-                    }
-                    chunk_enc_size_str.push(buffer[0] as char);
-                },
-                Err(err) => return Err(Error::from_reason(format!("Error reading encrypted chunk size: {}", err))),
-            }
-        }
-        
-        let encrypted_chunk_size: usize = match chunk_enc_size_str.parse() {
-            Ok(size) => size,
-            Err(_) => return Err(Error::from_reason("Invalid encrypted chunk size".to_string())),
+    let writer = BufWriter::with_capacity(chunk_size, output_file);
+    Ok(ChunkedWriter {
+        output: Some(output),
+        size,
+        chunk_size,
+        encryptor: Some(webstream::EncryptTransform::new(writer, algo, &key, container, sniff_mime_type, size, chunk_size)),
+    })
+}
+
+/// 写入文件结尾，返回与 chunkEncryptFile() 相同的统计
+fn finish_chunked_writer(mut encoder: ChunkEncoder<BufWriter<File>>, file_size: u64, chunk_size: usize) -> std::result::Result<ChunkEncryptFileResult, CodedError> {
+    encoder.write_pending()?;
+    let total_chunks = encoder.chunks_written();
+    let compression = encoder.compression_stats();
+    encoder.finish()?;
+    let chunk_size = chunk_size as u64;
+    Ok(ChunkEncryptFileResult {
+        total_chunks: total_chunks as f64,
+        file_size: (file_size as f64) / 1024.0,
+        file_size_bytes: byte_count(file_size),
+        chunk_size: (chunk_size as f64) / 1024.0,
+        chunk_size_bytes: byte_count(chunk_size),
+        volumes: None,
+        parity_size_kb: None,
+        parity_size_bytes: None,
+        compressed_size_kb: compression.map(|stats| (stats.compressed_size as f64) / 1024.0),
+        compressed_size_bytes: compression.map(|stats| byte_count(stats.compressed_size)),
+        uncompressed_chunks: compression.map(|stats| stats.stored_chunks as f64),
+        sparse_chunks: None,
+        resumed_chunks: None,
+    })
+}
+
+/// 批量加密文件 - 在 Rust 中以多个线程并行加密，单个文件失败不影响其它文件，失败原因记录在对应的结果中
+#[napi(js_name = "encryptFiles")]
+pub fn encrypt_files(algorithm: String, key: Buffer, entries: Vec<BatchEncryptEntry>, options: Option<BatchOptions>) -> Result<BatchResult, ErrorCode> {
+    let options = options.unwrap_or_default();
+    let control = JobControl::new("encryptFiles", options.timeout_ms);
+    control.started();
+    let result = encrypt_files_job(algorithm, key, entries, options, &control);
+    control.complete(result)
+}
+
+/// encryptFiles() 的异步版本，在 libuv 线程池中运行并返回 Promise
+#[napi(js_name = "encryptFilesAsync", ts_return_type = "Promise<BatchResult>")]
+pub fn encrypt_files_async(algorithm: String, key: Buffer, entries: Vec<BatchEncryptEntry>, options: Option<BatchOptions>, signal: Option<Either<AbortSignal, ClassInstance<Job>>>) -> AsyncTask<FileTask<BatchResult>> {
+    let options = options.unwrap_or_default();
+    let control = JobControl::new("encryptFiles", options.timeout_ms);
+    FileTask::new(control, signal, move |control| encrypt_files_job(algorithm, key, entries, options, control))
+}
+
+fn encrypt_files_job(algorithm: String, key: Buffer, entries: Vec<BatchEncryptEntry>, options: BatchOptions, control: &JobControl) -> Result<BatchResult, ErrorCode> {
+    parse_algorithm(&algorithm)?;
+    if options.chunk_size_mb == Some(0) {
+        return Err(Error::new(ErrorCode::InvalidArgument, "Chunk size must be greater than 0"));
+    }
+    let template = batch_template(&options)?;
+    if options.dry_run.unwrap_or(false) {
+        let chunk_size = options.chunk_size_mb.map(|chunk_size_mb| chunk_size_mb as u64 * 1024 * 1024);
+        return Ok(plan_batch(entries.into_iter().enumerate().map(|(index, entry)| {
+            let output_path = batch_output_path(entry.output_path, template.as_ref(), &entry.input_path, index);
+            let planned = output_path.as_ref().map_err(Clone::clone).and_then(|output_path| {
+                check_overwrite(&output_path.into(), entry.options.as_ref().and_then(|options| options.overwrite))?;
+                let size = plan_input_size(&entry.input_path)?;
+                Ok(plan::PlanEntry {
+                    input_path: entry.input_path.clone(),
+                    output_path: output_path.clone(),
+                    size: Some(size),
+                    chunks: chunk_size.map(|chunk_size| size.div_ceil(chunk_size)),
+                })
+            });
+            (entry.input_path, output_path.unwrap_or_default(), planned)
+        })));
+    }
+    // Buffer 不能在线程间共享：密钥复制为 Vec，每个条目由处理它的线程取走
+    let key = key.to_vec();
+    let entries: Vec<_> = entries.into_iter().map(|entry| Mutex::new(Some(entry))).collect();
+    run_batch(entries.len(), options.concurrency, control, |index| {
+        let entry = entries[index].lock().unwrap().take().unwrap();
+        let input_path = entry.input_path.clone();
+        let output_path = match batch_output_path(entry.output_path, template.as_ref(), &input_path, index) {
+            Ok(output_path) => output_path,
+            Err(err) => return batch_file_result(input_path, String::new(), Err(err)),
         };
-        
-        // 读取加密的块数据
-        let mut encrypted_chunk = vec![0u8; encrypted_chunk_size];
-        if let Err(err) = input_file.read_exact(&mut encrypted_chunk) {
-            return Err(Error::from_reason(format!("Error reading encrypted chunk: {}", err)));
-        }
-        
-        // 解密当前块
-        let decrypted = match decrypt(algo.clone(), &key, &encrypted_chunk) {
-            Ok(data) => data,
-            Err(err) => return Err(Error::from_reason(format!("Chunk decryption error: {}", err))),
+        let file_options = EncryptOptions { timeout_ms: None, ..entry.options.unwrap_or_default() };
+        let key = Buffer::from(key.clone());
+        let result = match options.chunk_size_mb {
+            Some(chunk_size_mb) => chunk_encrypt_file_job(algorithm.clone(), key, Either::A(entry.input_path), Either::A(output_path.clone()), chunk_size_mb, file_options, control)
+                .map(|result| result.file_size_bytes),
+            None => encrypt_file_job(algorithm.clone(), key, Either::A(entry.input_path), Either::A(output_path.clone()), file_options, control)
+                .map(|result| result.file_size_bytes),
         };
-        
-        chunk_index += 1;
-        
-        // 写入解密后的数据
-        if let Err(err) = output_file.write_all(&decrypted) {
-            return Err(Error::from_reason(format!("Failed to write decrypted chunk: {}", err)));
+        batch_file_result(input_path, output_path, result)
+    })
+}
+
+/// 批量解密文件 - 在 Rust 中以多个线程并行解密，根据文件头自动选择整体或分片解密；单个文件失败不影响其它文件
+#[napi(js_name = "decryptFiles")]
+pub fn decrypt_files(algorithm: String, key: Buffer, entries: Vec<BatchDecryptEntry>, options: Option<BatchOptions>) -> Result<BatchResult, ErrorCode> {
+    let options = options.unwrap_or_default();
+    let control = JobControl::new("decryptFiles", options.timeout_ms);
+    control.started();
+    let result = decrypt_files_job(algorithm, key, entries, options, &control);
+    control.complete(result)
+}
+
+/// decryptFiles() 的异步版本，在 libuv 线程池中运行并返回 Promise
+#[napi(js_name = "decryptFilesAsync", ts_return_type = "Promise<BatchResult>")]
+pub fn decrypt_files_async(algorithm: String, key: Buffer, entries: Vec<BatchDecryptEntry>, options: Option<BatchOptions>, signal: Option<Either<AbortSignal, ClassInstance<Job>>>) -> AsyncTask<FileTask<BatchResult>> {
+    let options = options.unwrap_or_default();
+    let control = JobControl::new("decryptFiles", options.timeout_ms);
+    FileTask::new(control, signal, move |control| decrypt_files_job(algorithm, key, entries, options, control))
+}
+
+fn decrypt_files_job(algorithm: String, key: Buffer, entries: Vec<BatchDecryptEntry>, options: BatchOptions, control: &JobControl) -> Result<BatchResult, ErrorCode> {
+    parse_algorithm(&algorithm)?;
+    let template = batch_template(&options)?;
+    if options.dry_run.unwrap_or(false) {
+        return Ok(plan_batch(entries.into_iter().enumerate().map(|(index, entry)| {
+            let file_options = entry.options.unwrap_or_default();
+            let output_path = batch_output_path(entry.output_path, template.as_ref(), &entry.input_path, index);
+            let planned = output_path.as_ref().map_err(Clone::clone).and_then(|output_path| {
+                // 只读取文件头：原始文件名、明文大小和分片数都记录在其中
+                let info = format::detect(&entry.input_path).map_err(Error::from).and_then(|detected| match detected {
+                    format::DetectedFormat::ZippyMonolithic
+                    | format::DetectedFormat::ZippyMonolithicV2
+                    | format::DetectedFormat::ZippyChunkedV1
+                    | format::DetectedFormat::ZippyChunkedV2 => format::inspect(&entry.input_path, Some(&key[..])).map_err(Error::from),
+                    other => Err(Error::new(ErrorCode::Unsupported, format!("Unsupported input format for decryptFiles: {}", other.as_str()))),
+                })?;
+                let output_path = resolve_output_path(&output_path.into(), &info.filename, file_options.restore_filename.unwrap_or(false))?;
+                check_overwrite(&output_path, file_options.overwrite)?;
+                Ok(plan::PlanEntry {
+                    input_path: entry.input_path.clone(),
+                    output_path: output_path.to_string(),
+                    size: info.original_size,
+                    chunks: (info.layout == format::Layout::Chunked).then_some(info.total_chunks),
+                })
+            });
+            (entry.input_path, output_path.unwrap_or_default(), planned)
+        })));
+    }
+    // Buffer 不能在线程间共享：密钥复制为 Vec，每个条目由处理它的线程取走
+    let key = key.to_vec();
+    let entries: Vec<_> = entries.into_iter().map(|entry| Mutex::new(Some(entry))).collect();
+    run_batch(entries.len(), options.concurrency, control, |index| {
+        let entry = entries[index].lock().unwrap().take().unwrap();
+        let input_path = entry.input_path.clone();
+        let output_path = match batch_output_path(entry.output_path, template.as_ref(), &input_path, index) {
+            Ok(output_path) => output_path,
+            Err(err) => return batch_file_result(input_path, String::new(), Err(err)),
+        };
+        let file_options = DecryptOptions { timeout_ms: None, ..entry.options.unwrap_or_default() };
+        let key = Buffer::from(key.clone());
+        let result = format::detect(&entry.input_path).map_err(Error::from).and_then(|detected| match detected {
+            format::DetectedFormat::ZippyMonolithic | format::DetectedFormat::ZippyMonolithicV2 => {
+                decrypt_file_job(algorithm.clone(), key, Either::A(entry.input_path), Either::A(output_path.clone()), file_options, control)
+                    .map(|result| (result.output_path, result.file_size_bytes))
+            },
+            format::DetectedFormat::ZippyChunkedV1 | format::DetectedFormat::ZippyChunkedV2 => {
+                chunk_decrypt_file_job(algorithm.clone(), key, Either::A(entry.input_path), Either::A(output_path.clone()), file_options, control)
+                    .map(|result| (result.output_path, result.total_bytes))
+            },
+            other => Err(Error::new(ErrorCode::Unsupported, format!("Unsupported input format for decryptFiles: {}", other.as_str()))),
+        });
+        match result {
+            Ok((output_path, size)) => batch_file_result(input_path, output_path, Ok(size)),
+            Err(err) => batch_file_result(input_path, output_path, Err(err)),
         }
-        
-        total_bytes_written += decrypted.len() as u64;
-        
-        // 检查是否达到了原始文件大小
-        if total_bytes_written >= original_size {
-            break;
+    })
+}
+
+fn batch_template(options: &BatchOptions) -> Result<Option<template::PathTemplate>, ErrorCode> {
+    options.output_template.as_deref().map(template::PathTemplate::parse).transpose().map_err(Error::from)
+}
+
+/// 条目没有 outputPath 时按 outputTemplate 生成输出路径
+fn batch_output_path(output_path: Option<String>, template: Option<&template::PathTemplate>, input_path: &str, index: usize) -> Result<String, ErrorCode> {
+    match (output_path, template) {
+        (Some(output_path), _) => Ok(output_path),
+        (None, Some(template)) => template.render(input_path, index, input_path).map_err(Error::from),
+        (None, None) => Err(Error::new(ErrorCode::InvalidArgument, "Output path is required when outputTemplate is not set")),
+    }
+}
+
+/// 并行处理一批文件并汇总结果；process 返回每个文件的结果，超时或取消时整批失败
+fn run_batch(count: usize, concurrency: Option<u32>, control: &JobControl, process: impl Fn(usize) -> BatchFileResult + Sync) -> Result<BatchResult, ErrorCode> {
+    let started = std::time::Instant::now();
+    let concurrency = concurrency.filter(|&concurrency| concurrency > 0).map_or_else(batch::default_concurrency, |concurrency| concurrency as usize);
+    let files = batch::run(count, concurrency, || control.check(), process)?;
+    let mut total_size = 0u64;
+    let mut succeeded = 0;
+    for file in &files {
+        if let Some(size) = &file.file_size_bytes {
+            succeeded += 1;
+            total_size += match size {
+                Either::A(size) => *size as u64,
+                Either::B(size) => size.get_u64().1,
+            };
         }
     }
-    
-    // 计算KB单位的大小
-    let original_size_kb = (original_size as f64) / 1024.0;
-    let total_bytes_written_kb = (total_bytes_written as f64) / 1024.0;
-    let chunk_size_kb = (chunk_size as f64) / 1024.0;
-    
-    // 创建并返回结果对象
-    let mut result = env.create_object()?;
-    result.set("totalChunks", chunk_index)?;
-    result.set("totalBytesKB", total_bytes_written_kb)?;
-    result.set("originalSizeKB", original_size_kb)?;
-    result.set("chunkSizeKB", chunk_size_kb)?;
-    
-    Ok(result)
+    Ok(BatchResult {
+        succeeded,
+        failed: files.len() as u32 - succeeded,
+        total_size_kb: (total_size as f64) / 1024.0,
+        total_size_bytes: byte_count(total_size),
+        elapsed_ms: started.elapsed().as_secs_f64() * 1000.0,
+        files,
+        plan: None,
+    })
 }
 
-/// 单个分片的解密 - 用于视频实时播放场景
-#[napi(js_name = "decryptSingleChunk")]
-pub fn decrypt_single_chunk(algorithm: String, key: Buffer, input_path: String, chunk_index: u32) -> Result<Buffer> {
-    let algo = CryptoAlgorithm::from_str(&algorithm)
-        .map_err(|_| Error::from_reason("Invalid algorithm".to_string()))?;
-    
-    // 打开输入文件
-    let mut input_file = match File::open(&input_path) {
-        Ok(file) => file,
-        Err(err) => return Err(Error::from_reason(format!("Failed to open input file: {}", err))),
-    };
-    
-    // 读取文件头以获取元数据
-    let mut header = String::new();
-    let mut buffer = [0u8; 1];
-    
-    loop {
-        match input_file.read_exact(&mut buffer) {
-            Ok(_) => {
-                if buffer[0] == b':' {
-                    break;
-                }
-                header.push(buffer[0] as char);
+/// dryRun：汇总每个文件的计划，成功规划的文件状态为 planned 并记录在 plan 中
+fn plan_batch(entries: impl Iterator<Item = (String, String, Result<plan::PlanEntry, ErrorCode>)>) -> BatchResult {
+    let started = std::time::Instant::now();
+    let mut files = Vec::new();
+    let mut planned = Vec::new();
+    for (input_path, output_path, result) in entries {
+        files.push(match &result {
+            Ok(entry) => BatchFileResult {
+                input_path,
+                output_path: entry.output_path.clone(),
+                status: "planned".to_string(),
+                file_size_bytes: entry.size.map(byte_count),
+                error: None,
             },
-            Err(err) => return Err(Error::from_reason(format!("Error reading header: {}", err))),
-        }
+            Err(err) => BatchFileResult { input_path, output_path, status: "failed".to_string(), file_size_bytes: None, error: Some(err.reason.clone()) },
+        });
+        planned.extend(result.ok());
     }
-    
-    if !header.starts_with("CHUNKS") {
-        return Err(Error::from_reason("Invalid file format - not a chunked file".to_string()));
+    let total_size = planned.iter().filter_map(|entry| entry.size).sum::<u64>();
+    BatchResult {
+        succeeded: planned.len() as u32,
+        failed: (files.len() - planned.len()) as u32,
+        total_size_kb: (total_size as f64) / 1024.0,
+        total_size_bytes: byte_count(total_size),
+        elapsed_ms: started.elapsed().as_secs_f64() * 1000.0,
+        files,
+        plan: Some(planned_files(&planned)),
+    }
+}
+
+/// dryRun 时读取输入文件的大小，不读取内容
+fn plan_input_size(input_path: &str) -> Result<u64, ErrorCode> {
+    let metadata = fd::open(input_path)
+        .and_then(|file| file.metadata())
+        .map_err(|e| Error::new(ErrorCode::IoOpen, format!("Failed to open input file: {}", e)))?;
+    if !metadata.is_file() {
+        return Err(Error::new(ErrorCode::InvalidArgument, format!("Input path must refer to a regular file: {}", input_path)));
+    }
+    Ok(metadata.len())
+}
+
+fn planned_files(entries: &[plan::PlanEntry]) -> Vec<PlannedFile> {
+    entries
+        .iter()
+        .zip(plan::collisions(entries))
+        .map(|(entry, collision)| PlannedFile {
+            input_path: entry.input_path.clone(),
+            output_path: entry.output_path.clone(),
+            file_size_kb: entry.size.map(|size| (size as f64) / 1024.0),
+            file_size_bytes: entry.size.map(byte_count),
+            total_chunks: entry.chunks.map(|chunks| chunks as f64),
+            exists: plan::exists(&entry.output_path),
+            collision,
+        })
+        .collect()
+}
+
+fn batch_file_result(input_path: String, output_path: String, result: Result<Either<f64, BigInt>, ErrorCode>) -> BatchFileResult {
+    match result {
+        Ok(size) => BatchFileResult { input_path, output_path, status: "ok".to_string(), file_size_bytes: Some(size), error: None },
+        Err(err) => BatchFileResult { input_path, output_path, status: "failed".to_string(), file_size_bytes: None, error: Some(err.reason) },
+    }
+}
+
+/// 加密整个目录 - 递归地把每个文件加密为目标目录中同一相对路径下的 `<name>.enc` 分片文件，并写入加密清单
+///
+/// 清单记录每个文件的相对路径、大小、权限、修改时间和明文 SHA-256。目标目录必须为空或不存在；出错时删除已写入的文件。
+#[napi(js_name = "encryptDirectory")]
+pub fn encrypt_directory(input_dir: String, output_dir: String, key: Buffer, options: Option<DirectoryEncryptOptions>) -> Result<DirectoryEncryptResult, ErrorCode> {
+    let options = options.unwrap_or_default();
+    let control = JobControl::new("encryptDirectory", options.timeout_ms);
+    control.started();
+    let result = encrypt_directory_job(input_dir, output_dir, key, options, &control);
+    control.complete(result)
+}
+
+/// encryptDirectory() 的异步版本，在 libuv 线程池中运行并返回 Promise
+#[napi(js_name = "encryptDirectoryAsync", ts_return_type = "Promise<DirectoryEncryptResult>")]
+pub fn encrypt_directory_async(input_dir: String, output_dir: String, key: Buffer, options: Option<DirectoryEncryptOptions>, signal: Option<Either<AbortSignal, ClassInstance<Job>>>) -> AsyncTask<FileTask<DirectoryEncryptResult>> {
+    let options = options.unwrap_or_default();
+    let control = JobControl::new("encryptDirectory", options.timeout_ms);
+    FileTask::new(control, signal, move |control| encrypt_directory_job(input_dir, output_dir, key, options, control))
+}
+
+fn encrypt_directory_job(input_dir: String, output_dir: String, key: Buffer, options: DirectoryEncryptOptions, control: &JobControl) -> Result<DirectoryEncryptResult, ErrorCode> {
+    let algorithm = options.algorithm.unwrap_or_else(|| "aes".to_string());
+    let algo = parse_algorithm(&algorithm)?;
+    let chunk_size_mb = options.chunk_size_mb.unwrap_or(10);
+    if chunk_size_mb == 0 {
+        return Err(Error::new(ErrorCode::InvalidArgument, "Chunk size must be greater than 0"));
     }
+    let dry_run = options.dry_run.unwrap_or(false);
+    let template = options.output_template.as_deref().map(template::PathTemplate::parse).transpose()?;
+    let concurrency = options.concurrency.filter(|&concurrency| concurrency > 0).map_or_else(batch::default_concurrency, |concurrency| concurrency as usize);
+    let (tree, created_root) = directory::source_filter(&input_dir, options.include.as_deref(), options.exclude.as_deref(), options.ignore_files.as_deref())
+        .and_then(|filter| directory::prepare(&input_dir, &output_dir, &filter, dry_run))?;
     
-    // 解析文件大小
-    let mut original_size_str = String::new();
-    loop {
-        match input_file.read_exact(&mut buffer) {
-            Ok(_) => {
-                if buffer[0] == b':' {
-                    break;
-                }
-                original_size_str.push(buffer[0] as char);
-            },
-            Err(err) => return Err(Error::from_reason(format!("Error reading file size: {}", err))),
-        }
+    // 加密文件名时目标目录中的路径由每一段名称分别加密得到，清单记录了它们与原始路径的对应关系
+    let names = options.encrypt_filenames.unwrap_or(false).then(|| directory::NameEncryptor::new(&key));
+    let stored_path = |name: &str| names.as_ref().map_or_else(|| name.to_string(), |names| names.encrypt_path(name));
+    let mut created: Vec<_> = created_root.into_iter().collect();
+    let dir_paths: Vec<_> = tree.directories.iter().map(|dir| directory::output_path(&output_dir, &stored_path(&dir.name))).collect();
+    // 模板中的 {hash} 需要读取每个文件，和加密一样并行计算
+    let encrypted_paths = match &template {
+        Some(template) => batch::run(tree.files.len(), concurrency, || control.check(), |index| {
+            let file = &tree.files[index];
+            template.render_relative(&stored_path(&file.name), index, &file.path.to_string_lossy())
+        })
+        .and_then(|paths| paths.into_iter().collect::<std::result::Result<Vec<_>, CodedError>>())
+        .and_then(|paths| {
+            let names: Vec<_> = tree.files.iter().map(|file| file.name.as_str()).collect();
+            template.check_distinct(&names, &paths, &[directory::MANIFEST_NAME])?;
+            Ok(paths)
+        }),
+        None => Ok(tree.files.iter().map(|file| format!("{}{}", stored_path(&file.name), directory::ENCRYPTED_SUFFIX)).collect()),
+    };
+    let encrypted_paths = match encrypted_paths {
+        Ok(encrypted_paths) => encrypted_paths,
+        Err(err) => {
+            walk::remove_created(&created);
+            return Err(err.into());
+        },
+    };
+    let file_paths: Vec<_> = encrypted_paths.iter().map(|name| directory::output_path(&output_dir, name)).collect();
+    let file_size = tree.files.iter().map(|file| file.size).sum::<u64>();
+    if dry_run {
+        let chunk_size = chunk_size_mb as u64 * 1024 * 1024;
+        let planned: Vec<_> = tree
+            .files
+            .iter()
+            .zip(&file_paths)
+            .map(|(file, path)| plan::PlanEntry {
+                input_path: file.path.to_string_lossy().into_owned(),
+                output_path: path.to_string_lossy().into_owned(),
+                size: Some(file.size),
+                chunks: Some(file.size.div_ceil(chunk_size)),
+            })
+            .collect();
+        return Ok(DirectoryEncryptResult {
+            files: tree.files.len() as u32,
+            directories: tree.directories.len() as u32,
+            file_size_kb: (file_size as f64) / 1024.0,
+            file_size_bytes: byte_count(file_size),
+            encrypted_size_kb: 0.0,
+            encrypted_size_bytes: byte_count(0),
+            manifest_path: Path::new(&output_dir).join(directory::MANIFEST_NAME).to_string_lossy().into_owned(),
+            plan: Some(planned_files(&planned)),
+        });
     }
     
-    // 解析块大小
-    let mut chunk_size_str = String::new();
-    loop {
-        match input_file.read_exact(&mut buffer) {
-            Ok(_) => {
-                if buffer[0] == b':' {
-                    break;
-                }
-                chunk_size_str.push(buffer[0] as char);
-            },
-            Err(err) => return Err(Error::from_reason(format!("Error reading chunk size: {}", err))),
+    // 出错时按创建的相反顺序删除：先删除文件，再从最深的目录开始删除
+    let result = (|| {
+        for path in dir_paths.iter().map(|dir| dir.as_path()).chain(file_paths.iter().filter_map(|path| path.parent())) {
+            walk::create_dirs(path, &mut created)?;
         }
+        created.extend(file_paths.iter().cloned());
+        let key = key.to_vec();
+        let files = batch::run(tree.files.len(), concurrency, || control.check(), |index| {
+            let file = &tree.files[index];
+            let output_path = file_paths[index].to_string_lossy().into_owned();
+            let file_options = EncryptOptions {
+                compression: options.compression.clone(),
+                compression_level: options.compression_level,
+                ..EncryptOptions::default()
+            };
+            let input_path = file.path.to_string_lossy().into_owned();
+            chunk_encrypt_file_job(algorithm.clone(), Buffer::from(key.clone()), Either::A(input_path), Either::A(output_path.clone()), chunk_size_mb, file_options, control)
+                .map_err(|err| CodedError::from(err).context(&file.name))?;
+            // 分片文件的结尾字段加密保存了明文 SHA-256，解开后记录到清单
+            let digest = fd::open(&output_path)
+                .map_err(|e| CodedError::io(ErrorCode::IoOpen, format_args!("Failed to open {}", output_path), e))
+                .and_then(|output| ChunkedFooter::read_at_end(&mut BufReader::new(output)))
+                .and_then(|footer| footer.decrypt_digest(&key))?
+                .ok_or_else(|| CodedError::bad_format(format!("{}: encrypted file has no plaintext digest", file.name)))?;
+            let encrypted_size = std::fs::metadata(&output_path).map_err(|e| CodedError::io(ErrorCode::IoRead, "Failed to get file metadata", e))?.len();
+            let manifest_file = directory::ManifestFile {
+                path: file.name.clone(),
+                encrypted_path: encrypted_paths[index].clone(),
+                size: file.size,
+                mode: file.mode,
+                modified: file.modified,
+                sha256: hex_encode(digest),
+            };
+            Ok::<_, CodedError>((manifest_file, encrypted_size))
+        })?;
+        let (files, encrypted_sizes): (Vec<_>, Vec<_>) = files.into_iter().collect::<std::result::Result<Vec<_>, CodedError>>()?.into_iter().unzip();
+        let manifest = directory::Manifest::new(algo.as_str(), &tree.directories, files);
+        let manifest_path = directory::write_manifest(&output_dir, &key, &manifest)?;
+        Ok::<_, CodedError>((manifest.files, encrypted_sizes.into_iter().sum::<u64>(), manifest_path))
+    })();
+    let (files, encrypted_size, manifest_path) = match result {
+        Ok(result) => result,
+        Err(err) => {
+            walk::remove_created(&created);
+            return Err(err.into());
+        },
+    };
+    
+    // 计算KB单位的大小
+    Ok(DirectoryEncryptResult {
+        files: files.len() as u32,
+        directories: tree.directories.len() as u32,
+        file_size_kb: (file_size as f64) / 1024.0,
+        file_size_bytes: byte_count(file_size),
+        encrypted_size_kb: (encrypted_size as f64) / 1024.0,
+        encrypted_size_bytes: byte_count(encrypted_size),
+        manifest_path: manifest_path.to_string_lossy().into_owned(),
+        plan: None,
+    })
+}
+
+/// 解密整个目录 - 读取 encryptDirectory() 写入的加密清单，还原整个目录树或 include 选中的路径
+///
+/// 每个文件解密前核对清单中的明文 SHA-256，解密时校验分片 MAC 和结尾字段，并还原权限和修改时间。目标目录可以已存在；出错时删除本次创建的文件和目录。
+#[napi(js_name = "decryptDirectory")]
+pub fn decrypt_directory(input_dir: String, output_dir: String, key: Buffer, options: Option<DirectoryDecryptOptions>) -> Result<DirectoryDecryptResult, ErrorCode> {
+    let options = options.unwrap_or_default();
+    let control = JobControl::new("decryptDirectory", options.timeout_ms);
+    control.started();
+    let result = decrypt_directory_job(input_dir, output_dir, key, options, &control);
+    control.complete(result)
+}
+
+/// decryptDirectory() 的异步版本，在 libuv 线程池中运行并返回 Promise
+#[napi(js_name = "decryptDirectoryAsync", ts_return_type = "Promise<DirectoryDecryptResult>")]
+pub fn decrypt_directory_async(input_dir: String, output_dir: String, key: Buffer, options: Option<DirectoryDecryptOptions>, signal: Option<Either<AbortSignal, ClassInstance<Job>>>) -> AsyncTask<FileTask<DirectoryDecryptResult>> {
+    let options = options.unwrap_or_default();
+    let control = JobControl::new("decryptDirectory", options.timeout_ms);
+    FileTask::new(control, signal, move |control| decrypt_directory_job(input_dir, output_dir, key, options, control))
+}
+
+fn decrypt_directory_job(input_dir: String, output_dir: String, key: Buffer, options: DirectoryDecryptOptions, control: &JobControl) -> Result<DirectoryDecryptResult, ErrorCode> {
+    let manifest = directory::read_manifest(&input_dir, &key)
+        .and_then(|manifest| manifest.select(&glob::PathFilter::new(options.include.as_deref(), options.exclude.as_deref())?))?;
+    let output_root = Path::new(&output_dir);
+    let input_root = Path::new(&input_dir);
+    let directories = manifest
+        .directories
+        .iter()
+        .map(|dir| walk::safe_join(output_root, &dir.path))
+        .collect::<std::result::Result<Vec<_>, CodedError>>()?;
+    let inputs = manifest
+        .files
+        .iter()
+        .map(|file| walk::safe_join(input_root, &file.encrypted_path))
+        .collect::<std::result::Result<Vec<_>, CodedError>>()?;
+    // 模板中的 {hash} 需要读取每个加密文件，并行计算
+    let concurrency = options.concurrency.filter(|&concurrency| concurrency > 0).map_or_else(batch::default_concurrency, |concurrency| concurrency as usize);
+    let relative_paths = match options.output_template.as_deref().map(template::PathTemplate::parse).transpose()? {
+        Some(template) => batch::run(inputs.len(), concurrency, || control.check(), |index| template.render_relative(&manifest.files[index].path, index, &inputs[index].to_string_lossy()))
+            .and_then(|paths| paths.into_iter().collect::<std::result::Result<Vec<_>, CodedError>>())
+            .and_then(|paths| {
+                let names: Vec<_> = manifest.files.iter().map(|file| file.path.as_str()).collect();
+                template.check_distinct(&names, &paths, &[])?;
+                Ok(paths)
+            })?,
+        None => manifest.files.iter().map(|file| file.path.clone()).collect(),
+    };
+    let files = inputs
+        .into_iter()
+        .zip(&relative_paths)
+        .map(|(input, path)| Ok((input, walk::safe_join(output_root, path)?)))
+        .collect::<std::result::Result<Vec<_>, CodedError>>()?;
+    let file_size = manifest.files.iter().map(|file| file.size).sum::<u64>();
+    if options.dry_run.unwrap_or(false) {
+        // 分片数从每个加密文件的文件头读取，缺失或损坏的文件在这里就会报错
+        let planned = manifest
+            .files
+            .iter()
+            .zip(&files)
+            .map(|(file, (input_path, output_path))| {
+                let input_path = input_path.to_string_lossy().into_owned();
+                let info = format::inspect(&input_path, None).map_err(|err| err.context(&file.path))?;
+                Ok(plan::PlanEntry {
+                    input_path,
+                    output_path: output_path.to_string_lossy().into_owned(),
+                    size: Some(file.size),
+                    chunks: Some(info.total_chunks),
+                })
+            })
+            .collect::<std::result::Result<Vec<_>, CodedError>>()?;
+        return Ok(DirectoryDecryptResult {
+            files: manifest.files.len() as u32,
+            directories: manifest.directories.len() as u32,
+            file_size_kb: (file_size as f64) / 1024.0,
+            file_size_bytes: byte_count(file_size),
+            plan: Some(planned_files(&planned)),
+        });
     }
     
-    // 跳过前面的分块，找到目标分块
-    let mut current_chunk = 0;
-    while current_chunk < chunk_index {
-        // 读取块大小
-        let mut chunk_enc_size_str = String::new();
-        loop {
-            match input_file.read_exact(&mut buffer) {
-                Ok(_) => {
-                    if buffer[0] == b':' {
-                        break;
-                    }
-                    chunk_enc_size_str.push(buffer[0] as char);
-                },
-                Err(err) => return Err(Error::from_reason(format!("Error reading encrypted chunk size: {}", err))),
-            }
-        }
-        
-        let encrypted_chunk_size: usize = match chunk_enc_size_str.parse() {
-            Ok(size) => size,
-            Err(_) => return Err(Error::from_reason("Invalid encrypted chunk size".to_string())),
-        };
-        
-        // 跳过这个块
-        if let Err(err) = input_file.seek(std::io::SeekFrom::Current(encrypted_chunk_size as i64)) {
-            return Err(Error::from_reason(format!("Error seeking to next chunk: {}", err)));
+    // 出错时按创建的相反顺序删除：先删除本次新建的文件，再从最深的目录开始删除
+    let mut created = Vec::new();
+    let result = (|| {
+        for path in directories.iter().map(|dir| dir.as_path()).chain(files.iter().filter_map(|(_, output)| output.parent())) {
+            walk::create_dirs(path, &mut created)?;
         }
-        
-        current_chunk += 1;
+        created.extend(files.iter().filter(|(_, output)| !output.exists()).map(|(_, output)| output.clone()));
+        let key = key.to_vec();
+        let results = batch::run(files.len(), concurrency, || control.check(), |index| {
+            let file = &manifest.files[index];
+            let (input_path, output_path) = &files[index];
+            let input_path = input_path.to_string_lossy().into_owned();
+            // 先核对结尾字段中的明文摘要，发现被替换或移动的加密文件
+            let digest = fd::open(&input_path)
+                .map_err(|e| CodedError::io(ErrorCode::IoOpen, format_args!("Failed to open {}", input_path), e))
+                .and_then(|input| ChunkedFooter::read_at_end(&mut BufReader::new(input)))
+                .and_then(|footer| footer.decrypt_digest(&key))?;
+            if digest.map(hex_encode).as_deref() != Some(file.sha256.as_str()) {
+                return Err(CodedError::auth_failed(format!("{}: digest mismatch with the directory manifest", file.path)));
+            }
+            let output_path = output_path.to_string_lossy().into_owned();
+            let result = chunk_decrypt_file_job(manifest.algorithm.clone(), Buffer::from(key.clone()), Either::A(input_path), Either::A(output_path.clone()), DecryptOptions::default(), control)
+                .map_err(|err| CodedError::from(err).context(&file.path))?;
+            if !result.hash_verified || std::fs::metadata(&output_path).map_err(|e| CodedError::io(ErrorCode::IoRead, "Failed to get file metadata", e))?.len() != file.size {
+                return Err(CodedError::auth_failed(format!("{}: decrypted file does not match the directory manifest", file.path)));
+            }
+            directory::restore_metadata(Path::new(&output_path), file.mode, file.modified);
+            Ok(())
+        })?;
+        results.into_iter().collect::<std::result::Result<Vec<_>, CodedError>>()
+    })();
+    if let Err(err) = result {
+        walk::remove_created(&created);
+        return Err(err.into());
+    }
+    // 目录的权限和修改时间在写入文件之后从最深处开始还原
+    for (dir, path) in manifest.directories.iter().zip(&directories).rev() {
+        directory::restore_metadata(path, dir.mode, dir.modified);
     }
     
-    // 读取目标块大小
-    let mut chunk_enc_size_str = String::new();
-    loop {
-        match input_file.read_exact(&mut buffer) {
-            Ok(_) => {
-                if buffer[0] == b':' {
-                    break;
-                }
-                chunk_enc_size_str.push(buffer[0] as char);
-            },
-            Err(err) => return Err(Error::from_reason(format!("Error reading target chunk size: {}", err))),
-        }
+    // 计算KB单位的大小
+    Ok(DirectoryDecryptResult {
+        files: manifest.files.len() as u32,
+        directories: manifest.directories.len() as u32,
+        file_size_kb: (file_size as f64) / 1024.0,
+        file_size_bytes: byte_count(file_size),
+        plan: None,
+    })
+}
+
+/// 加密为单个归档 - 把目录打包为 tar 并以流的方式分片加密为一个文件（通常以 `.zenc` 结尾），不会像 encryptDirectory() 那样暴露文件数量和大小
+///
+/// tar 的第一个条目是记录目录和文件的清单，解密后的明文是普通的 tar 文件。出错时删除未完成的归档。
+#[napi(js_name = "encryptArchive")]
+pub fn encrypt_archive(input_dir: String, output_path: String, key: Buffer, options: Option<ArchiveEncryptOptions>) -> Result<ArchiveEncryptResult, ErrorCode> {
+    let options = options.unwrap_or_default();
+    let control = JobControl::new("encryptArchive", options.timeout_ms);
+    control.started();
+    let result = encrypt_archive_job(input_dir, output_path, key, options, &control);
+    control.complete(result)
+}
+
+/// encryptArchive() 的异步版本，在 libuv 线程池中运行并返回 Promise
+#[napi(js_name = "encryptArchiveAsync", ts_return_type = "Promise<ArchiveEncryptResult>")]
+pub fn encrypt_archive_async(input_dir: String, output_path: String, key: Buffer, options: Option<ArchiveEncryptOptions>, signal: Option<Either<AbortSignal, ClassInstance<Job>>>) -> AsyncTask<FileTask<ArchiveEncryptResult>> {
+    let options = options.unwrap_or_default();
+    let control = JobControl::new("encryptArchive", options.timeout_ms);
+    FileTask::new(control, signal, move |control| encrypt_archive_job(input_dir, output_path, key, options, control))
+}
+
+fn encrypt_archive_job(input_dir: String, output_path: String, key: Buffer, options: ArchiveEncryptOptions, control: &JobControl) -> Result<ArchiveEncryptResult, ErrorCode> {
+    let algorithm = options.algorithm.clone().unwrap_or_else(|| "aes".to_string());
+    let algo = parse_algorithm(&algorithm)?;
+    let chunk_size_mb = options.chunk_size_mb.unwrap_or(10);
+    if chunk_size_mb == 0 {
+        return Err(Error::new(ErrorCode::InvalidArgument, "Chunk size must be greater than 0"));
     }
+    let chunk_size = (chunk_size_mb as usize) * 1024 * 1024;
+    let filter = directory::source_filter(&input_dir, options.include.as_deref(), options.exclude.as_deref(), options.ignore_files.as_deref())?;
+    let input = std::fs::canonicalize(&input_dir).map_err(|e| Error::new(ErrorCode::IoRead, format!("Error reading {}: {}", input_dir, e)))?;
+    let tree = directory::collect_tree(&input, &filter)?;
+    archive::check_output(&input, &output_path)?;
+    let (manifest, manifest_json, size) = archive::plan(&tree)?;
     
-    let encrypted_chunk_size: usize = match chunk_enc_size_str.parse() {
-        Ok(size) => size,
-        Err(_) => return Err(Error::from_reason("Invalid encrypted chunk size".to_string())),
+    // 归档总是使用容器格式，文件头记录 tar 的总大小和 MIME 类型
+    let file_options = EncryptOptions {
+        compression: options.compression,
+        compression_level: options.compression_level,
+        mime_type: Some(archive::MIME_TYPE.to_string()),
+        ..EncryptOptions::default()
     };
+    let container = build_container_header(&file_options, None, Layout::Chunked, &algo, &key, size, chunk_size as u64)?;
+    let output = fd::AtomicOutput::new(&output_path);
+    let output_file = match fd::create(output.path()) {
+        Ok(file) => file,
+        Err(err) => return Err(Error::new(ErrorCode::IoOpen, format!("Failed to create output file: {}", err))),
+    };
+    let result = ChunkEncoder::new(BufWriter::with_capacity(chunk_size, output_file), algo, &key, container.as_ref(), size, chunk_size)
+        .and_then(|mut encoder| {
+            archive::write(&tree, &manifest_json, &mut encoder, control)?;
+            finish_chunked_writer(encoder, size, chunk_size)
+        });
+    let result = result?;
+    output.commit()?;
+    let archive_size = std::fs::metadata(&output_path).map_err(|e| CodedError::io(ErrorCode::IoRead, "Failed to get file metadata", e))?.len();
     
-    // 读取加密的块数据
-    let mut encrypted_chunk = vec![0u8; encrypted_chunk_size];
-    if let Err(err) = input_file.read_exact(&mut encrypted_chunk) {
-        return Err(Error::from_reason(format!("Error reading encrypted chunk: {}", err)));
-    }
-    
-    // 解密当前块
-    let decrypted = match decrypt(algo, &key, &encrypted_chunk) {
-        Ok(data) => data,
-        Err(err) => return Err(Error::from_reason(format!("Chunk decryption error: {}", err))),
+    // 计算KB单位的大小
+    let file_size = manifest.files.iter().map(|file| file.size).sum::<u64>();
+    Ok(ArchiveEncryptResult {
+        files: manifest.files.len() as u32,
+        directories: manifest.directories.len() as u32,
+        file_size_kb: (file_size as f64) / 1024.0,
+        file_size_bytes: byte_count(file_size),
+        archive_size_kb: (archive_size as f64) / 1024.0,
+        archive_size_bytes: byte_count(archive_size),
+        total_chunks: result.total_chunks,
+    })
+}
+
+/// 解开加密归档 - 流式解密 encryptArchive() 的输出并解开整个目录树或 include 选中的路径，还原权限和修改时间
+///
+/// 算法使用文件头中记录的算法。读完归档后校验整个文件的 MAC 和明文摘要，失败时删除本次创建的文件和目录。
+#[napi(js_name = "extractArchive")]
+pub fn extract_archive(input_path: String, output_dir: String, key: Buffer, options: Option<ArchiveExtractOptions>) -> Result<DirectoryDecryptResult, ErrorCode> {
+    let options = options.unwrap_or_default();
+    let control = JobControl::new("extractArchive", options.timeout_ms);
+    control.started();
+    let result = extract_archive_job(input_path, output_dir, key, options, &control);
+    control.complete(result)
+}
+
+/// extractArchive() 的异步版本，在 libuv 线程池中运行并返回 Promise
+#[napi(js_name = "extractArchiveAsync", ts_return_type = "Promise<DirectoryDecryptResult>")]
+pub fn extract_archive_async(input_path: String, output_dir: String, key: Buffer, options: Option<ArchiveExtractOptions>, signal: Option<Either<AbortSignal, ClassInstance<Job>>>) -> AsyncTask<FileTask<DirectoryDecryptResult>> {
+    let options = options.unwrap_or_default();
+    let control = JobControl::new("extractArchive", options.timeout_ms);
+    FileTask::new(control, signal, move |control| extract_archive_job(input_path, output_dir, key, options, control))
+}
+
+fn extract_archive_job(input_path: String, output_dir: String, key: Buffer, options: ArchiveExtractOptions, control: &JobControl) -> Result<DirectoryDecryptResult, ErrorCode> {
+    let filter = glob::PathFilter::new(options.include.as_deref(), options.exclude.as_deref())?;
+    let (mut reader, _) = open_archive(&input_path, &key)?;
+    let mut created = Vec::new();
+    let result = reader
+        .entries()
+        .map_err(|e| Error::new(ErrorCode::IoRead, format!("Error reading archive: {}", e)))
+        .and_then(|mut entries| {
+            let manifest = archive::read_manifest(&mut entries)?.select(&filter)?;
+            archive::extract(&mut entries, &manifest, &output_dir, &mut created, || control.check())?;
+            Ok(manifest)
+        })
+        .and_then(|manifest| {
+            // 读完剩余的明文后校验整个文件的 MAC 和结尾字段
+            reader.into_inner().into_decoder()?.finish()?;
+            Ok(manifest)
+        });
+    let manifest = match result {
+        Ok(manifest) => manifest,
+        Err(err) => {
+            walk::remove_created(&created);
+            return Err(err);
+        },
     };
     
-    // 将解密后的数据返回为Buffer
-    Ok(Buffer::from(decrypted))
+    // 计算KB单位的大小
+    let file_size = manifest.files.iter().map(|file| file.size).sum::<u64>();
+    Ok(DirectoryDecryptResult {
+        files: manifest.files.len() as u32,
+        directories: manifest.directories.len() as u32,
+        file_size_kb: (file_size as f64) / 1024.0,
+        file_size_bytes: byte_count(file_size),
+        plan: None,
+    })
 }
 
-/// 获取分片加密文件的元数据 - 用于视频播放前获取文件信息
-#[napi(js_name = "getChunkedFileMetadata")]
-pub fn get_chunked_file_metadata(input_path: String, env: Env) -> Result<Object> {
-    // 打开输入文件
-    let mut input_file = match File::open(&input_path) {
+/// 逐片解密 encryptArchive() 创建的文件的 tar 读取器
+type ArchiveReader = tar::Archive<chunked::DecodedReader<BufReader<File>>>;
+
+/// 打开 encryptArchive() 创建的文件：校验文件头并确认明文是归档，返回逐片解密的 tar 读取器和算法
+fn open_archive(input_path: &str, key: &[u8]) -> Result<(ArchiveReader, CryptoAlgorithm), ErrorCode> {
+    let input_file = match fd::open(input_path) {
         Ok(file) => file,
-        Err(err) => return Err(Error::from_reason(format!("Failed to open input file: {}", err))),
+        Err(err) => return Err(Error::new(ErrorCode::IoOpen, format!("Failed to open input file: {}", err))),
     };
+    let mut input_file = BufReader::new(input_file);
+    let header = format::read_chunked_header(&mut input_file)?;
+    let not_archive = || Error::new(ErrorCode::BadHeader, "Not an encrypted archive - create it with encryptArchive");
+    let algo = match &header.container {
+        Some(container) => container.algorithm.clone(),
+        None => return Err(not_archive()),
+    };
+    check_chunked_header(&header, &algo, key)?;
+    let mime_type = header.container.as_ref().map(|container| container.decrypt_mime_type(key)).transpose()?.flatten();
+    if mime_type.as_deref() != Some(archive::MIME_TYPE) {
+        return Err(not_archive());
+    }
     
-    // 读取文件头以获取元数据
-    let mut header = String::new();
-    let mut buffer = [0u8; 1];
+    let decoder = ChunkDecoder::new(input_file, header, algo.clone(), key)?;
+    Ok((tar::Archive::new(chunked::DecodedReader::new(decoder)), algo))
+}
+
+/// 列出归档内容 - 只解密清单，不解密文件数据；支持 encryptArchive 创建的文件和 encryptDirectory 创建的目录
+#[napi(js_name = "listArchive")]
+pub fn list_archive(path: String, key: Buffer) -> Result<ArchiveListResult, ErrorCode> {
+    let is_dir = std::fs::metadata(&path).map_err(|e| Error::new(ErrorCode::IoRead, format!("Error reading {}: {}", path, e)))?.is_dir();
+    // (路径, 是否目录, 大小, 权限, 修改时间)
+    let (format, algorithm, created_at, mut entries) = if is_dir {
+        let manifest = directory::read_manifest(&path, &key)?;
+        let entries = manifest
+            .directories
+            .into_iter()
+            .map(|dir| (dir.path, true, 0, dir.mode, dir.modified))
+            .chain(manifest.files.into_iter().map(|file| (file.path, false, file.size, file.mode, file.modified)))
+            .collect::<Vec<_>>();
+        ("directory", manifest.algorithm, manifest.created_at, entries)
+    } else {
+        // 清单是 tar 的第一个条目，只需解密开头的分片
+        let (mut reader, algo) = open_archive(&path, &key)?;
+        let mut entries = reader.entries().map_err(|e| Error::new(ErrorCode::IoRead, format!("Error reading archive: {}", e)))?;
+        let manifest = archive::read_manifest(&mut entries)?;
+        let entries = manifest
+            .directories
+            .into_iter()
+            .map(|dir| (dir.path, true, 0, dir.mode, dir.modified))
+            .chain(manifest.files.into_iter().map(|file| (file.path, false, file.size, file.mode, file.modified)))
+            .collect::<Vec<_>>();
+        ("archive", algo.as_str().to_string(), manifest.created_at, entries)
+    };
+    entries.sort_by(|a, b| (!a.1, &a.0).cmp(&(!b.1, &b.0)));
     
-    loop {
-        match input_file.read_exact(&mut buffer) {
-            Ok(_) => {
-                if buffer[0] == b':' {
-                    break;
-                }
-                header.push(buffer[0] as char);
-            },
-            Err(err) => return Err(Error::from_reason(format!("Error reading header: {}", err))),
-        }
+    // 计算KB单位的大小
+    let directories = entries.iter().filter(|entry| entry.1).count() as u32;
+    let file_size = entries.iter().map(|entry| entry.2).sum::<u64>();
+    Ok(ArchiveListResult {
+        format: format.to_string(),
+        algorithm,
+        created_at: created_at as f64,
+        files: entries.len() as u32 - directories,
+        directories,
+        file_size_kb: (file_size as f64) / 1024.0,
+        file_size_bytes: byte_count(file_size),
+        entries: entries
+            .into_iter()
+            .map(|(path, is_dir, size, mode, modified)| ArchiveEntry {
+                path,
+                kind: if is_dir { "directory" } else { "file" }.to_string(),
+                size_kb: (size as f64) / 1024.0,
+                size_bytes: byte_count(size),
+                mode,
+                modified: (modified as f64) * 1000.0,
+            })
+            .collect(),
+    })
+}
+
+/// watchAndEncrypt() 返回的监视器
+#[napi]
+pub struct Watcher {
+    stop: std::sync::Arc<watch::StopSignal>,
+}
+
+#[napi]
+impl Watcher {
+    /// 停止监视；正在加密的文件完成后监视线程退出，之后不再调用回调
+    #[napi]
+    pub fn close(&self) {
+        self.stop.stop();
     }
-    
-    if !header.starts_with("CHUNKS") {
-        return Err(Error::from_reason("Invalid file format - not a chunked file".to_string()));
+
+    #[napi(getter)]
+    pub fn closed(&self) -> bool {
+        self.stop.is_stopped()
+    }
+}
+
+/// 监视目录并自动加密 - 定期扫描 src_dir，把新出现或修改过的文件加密为 dest_dir 中同一相对路径下的 `<name>.enc` 分片文件
+///
+/// 文件的大小和修改时间在 debounceMs 内不再变化才会被加密；每个文件加密成功或失败后调用 callback。返回的 Watcher 在 close() 之前保持进程运行。
+#[napi(js_name = "watchAndEncrypt", ts_args_type = "srcDir: string, destDir: string, key: Buffer, options: WatchOptions | undefined | null, callback: (event: WatchEvent) => void")]
+pub fn watch_and_encrypt(src_dir: String, dest_dir: String, key: Buffer, options: Option<WatchOptions>, callback: JsFunction) -> Result<Watcher, ErrorCode> {
+    let options = options.unwrap_or_default();
+    let algorithm = options.algorithm.clone().unwrap_or_else(|| "aes".to_string());
+    parse_algorithm(&algorithm)?;
+    let chunk_size_mb = options.chunk_size_mb.unwrap_or(10);
+    if chunk_size_mb == 0 {
+        return Err(Error::new(ErrorCode::InvalidArgument, "Chunk size must be greater than 0"));
+    }
+    if key.len() != 32 {
+        return Err(Error::new(ErrorCode::InvalidKeyLength, "Key must be 32 bytes (256 bits)"));
     }
+    let debounce = std::time::Duration::from_millis(options.debounce_ms.unwrap_or(1000) as u64);
+    let interval = std::time::Duration::from_millis(options.interval_ms.unwrap_or(500).max(1) as u64);
+    let mut tracker = directory::source_filter(&src_dir, options.include.as_deref(), options.exclude.as_deref(), options.ignore_files.as_deref())
+        .and_then(|filter| watch::Tracker::new(&src_dir, &dest_dir, filter, debounce, !options.ignore_existing.unwrap_or(false)))?;
+    let callback: ThreadsafeFunction<watch::WatchEvent, ErrorStrategy::Fatal> =
+        callback.create_threadsafe_function(0, |ctx| Ok(vec![ctx.value])).map_err(errors::napi_error)?;
     
-    // 解析文件大小
-    let mut original_size_str = String::new();
-    loop {
-        match input_file.read_exact(&mut buffer) {
-            Ok(_) => {
-                if buffer[0] == b':' {
+    let stop = std::sync::Arc::new(watch::StopSignal::default());
+    let watcher = Watcher { stop: stop.clone() };
+    let key = key.to_vec();
+    std::thread::spawn(move || {
+        let event = |kind: &str, path: String, output_path: Option<String>, file_size_bytes: Option<Either<f64, BigInt>>, error: Option<String>| watch::WatchEvent {
+            kind: kind.to_string(),
+            path,
+            output_path,
+            file_size_bytes,
+            error,
+            timestamp: job::now_ms(),
+        };
+        while !stop.wait(interval) {
+            let ready = match tracker.poll() {
+                Ok(ready) => ready,
+                Err(err) => {
+                    callback.call(event("failed", String::new(), None, None, Some(err.message)), ThreadsafeFunctionCallMode::NonBlocking);
+                    continue;
+                }
+            };
+            for watch::Ready { name, path: input_path, snapshot } in ready {
+                if stop.is_stopped() {
                     break;
                 }
-                original_size_str.push(buffer[0] as char);
-            },
-            Err(err) => return Err(Error::from_reason(format!("Error reading file size: {}", err))),
+                let output_path = directory::output_path(&dest_dir, &format!("{}{}", name, directory::ENCRYPTED_SUFFIX)).to_string_lossy().into_owned();
+                let file_options = EncryptOptions {
+                    compression: options.compression.clone(),
+                    compression_level: options.compression_level,
+                    ..EncryptOptions::default()
+                };
+                let control = JobControl::new("watchAndEncrypt", None);
+                control.started();
+                let result = Path::new(&output_path)
+                    .parent()
+                    .map_or(Ok(()), std::fs::create_dir_all)
+                    .map_err(|e| Error::new(ErrorCode::IoOpen, format!("Failed to create directory for {}: {}", output_path, e)))
+                    .and_then(|_| chunk_encrypt_file_job(algorithm.clone(), Buffer::from(key.clone()), Either::A(input_path.to_string_lossy().into_owned()), Either::A(output_path.clone()), chunk_size_mb, file_options, &control))
+                    .and_then(|result| {
+                        // 加密期间文件又被写入时，输出可能混合了新旧内容：删除输出，文件稳定后重新加密；
+                        // 这也是删除源文件之前的检查，变化后的内容还没有加密，不能删除
+                        if watch::Snapshot::of(&input_path) != Some(snapshot) {
+                            let _ = std::fs::remove_file(&output_path);
+                            tracker.requeue(&name);
+                            return Err(Error::new(ErrorCode::IoRead, format!("{} changed while it was being encrypted - it will be encrypted again", input_path.display())));
+                        }
+                        if options.remove_source.unwrap_or(false) {
+                            std::fs::remove_file(&input_path).map_err(|e| Error::new(ErrorCode::IoWrite, format!("Failed to remove {}: {}", input_path.display(), e)))?;
+                            tracker.forget(&name);
+                        }
+                        Ok(result)
+                    });
+                let watch_event = match control.complete(result) {
+                    Ok(result) => event("encrypted", name, Some(output_path), Some(result.file_size_bytes), None),
+                    Err(err) => event("failed", name, Some(output_path), None, Some(err.reason)),
+                };
+                callback.call(watch_event, ThreadsafeFunctionCallMode::NonBlocking);
+            }
         }
-    }
+    });
+    Ok(watcher)
+}
+
+/// 查看加密文件信息 - 无需密钥即可读取文件头中的元数据，支持分片和整体加密的文件；提供密钥时还会解密保存的原始文件名和用户元数据
+#[napi(js_name = "inspectFile")]
+pub fn inspect_file(input_path: Either<String, i32>, key: Option<Buffer>) -> Result<InspectFileResult, ErrorCode> {
+    let input_path = fd::input_path(input_path)?;
+    let info = format::inspect(&input_path, key.as_deref())?;
+
+    // 计算KB单位的大小
+    let original_size_kb = info.original_size.map(|size| (size as f64) / 1024.0);
+    let chunk_size_kb = info.chunk_size.map(|size| (size as f64) / 1024.0);
+    let encrypted_size_kb = (info.encrypted_size as f64) / 1024.0;
+
+    // 创建并返回结果对象，文件中未记录的字段为 null
+    Ok(InspectFileResult {
+        format_version: info.format_version,
+        layout: info.layout.as_str().to_string(),
+        chunked: info.layout == format::Layout::Chunked,
+        algorithm: info.algorithm,
+        file_size_kb: original_size_kb,
+        file_size_bytes: info.original_size.map(byte_count),
+        encrypted_size_kb,
+        encrypted_size_bytes: byte_count(info.encrypted_size),
+        chunk_size_kb,
+        chunk_size_bytes: info.chunk_size.map(byte_count),
+        total_chunks: info.total_chunks as f64,
+        kdf: info.kdf,
+        has_filename: info.has_filename,
+        filename: info.filename,
+        has_metadata: info.has_metadata,
+        metadata: metadata_to_js(info.metadata),
+        mime_type: info.mime_type,
+        created_at: info.created_at.map(|ms| ms as f64),
+        tool_version: info.tool_version,
+        format_revision: info.format_revision,
+        header_authenticated: info.header_authenticated,
+        chunks_authenticated: info.chunks_authenticated,
+        has_parity: info.has_parity,
+        hash_algorithm: info.hash_algorithm.map(|algorithm| algorithm.as_str().to_string()),
+        compression: info.compression.map(|compression| compression.method.as_str().to_string()),
+        sparse: info.sparse,
+        appendable: info.appendable,
+    })
+}
+
+/// 检测文件格式 - 只读取文件开头的少量字节，返回 "plain"、"zippy-monolithic"、"zippy-chunked-v1" 等
+#[napi(js_name = "detectFormat")]
+pub fn detect_format(input_path: Either<String, i32>) -> Result<String, ErrorCode> {
+    let input_path = fd::input_path(input_path)?;
+    let detected = format::detect(&input_path)?;
+    Ok(detected.as_str().to_string())
+}
+
+/// 判断文件是否为本库加密的文件
+#[napi(js_name = "isEncrypted")]
+pub fn is_encrypted(input_path: Either<String, i32>) -> Result<bool, ErrorCode> {
+    let input_path = fd::input_path(input_path)?;
+    let detected = format::detect(&input_path)?;
+    Ok(detected.is_encrypted())
+}
+
+/// 预热 - 提前完成配置读取和算法自检，避免第一次加密时的额外延迟
+#[napi]
+pub fn warmup() -> Result<WarmupResult, ErrorCode> {
+    let already_ready = runtime::is_ready();
+    let started = std::time::Instant::now();
+    runtime::ensure_ready()?;
+    let duration_ms = started.elapsed().as_secs_f64() * 1000.0;
     
-    let original_size: u64 = match original_size_str.parse() {
-        Ok(size) => size,
-        Err(_) => return Err(Error::from_reason("Invalid file size in header".to_string())),
-    };
+    Ok(WarmupResult {
+        already_warm: already_ready,
+        duration_ms,
+    })
+}
+
+/// 获取硬件加速信息 - CPU 支持的加密指令，以及 algorithm 为 "auto" 时选择的算法
+#[napi(js_name = "getHardwareInfo")]
+pub fn get_hardware_info() -> HardwareInfo {
+    let features = hardware::cpu_features();
+    let auto_algorithm = hardware::auto_algorithm(config::algorithm_allowed);
     
-    // 解析块大小
-    let mut chunk_size_str = String::new();
-    loop {
-        match input_file.read_exact(&mut buffer) {
-            Ok(_) => {
-                if buffer[0] == b':' {
-                    break;
-                }
-                chunk_size_str.push(buffer[0] as char);
-            },
-            Err(err) => return Err(Error::from_reason(format!("Error reading chunk size: {}", err))),
-        }
+    HardwareInfo {
+        arch: std::env::consts::ARCH.to_string(),
+        aes_ni: features.aes_ni,
+        pclmul: features.pclmul,
+        neon: features.neon,
+        arm_aes: features.arm_aes,
+        aes_accelerated: hardware::aes_accelerated(),
+        auto_algorithm: auto_algorithm.as_str().to_string(),
     }
-    
-    let chunk_size: usize = match chunk_size_str.parse() {
-        Ok(size) => size,
-        Err(_) => return Err(Error::from_reason("Invalid chunk size in header".to_string())),
+}
+
+/// 测量本机上各加密算法和摘要算法的吞吐量 - 用于应用启动时按设备选择默认算法
+///
+/// 同步版本在测量期间阻塞 JS 线程，默认设置下约 1 秒，请优先使用 benchmarkAlgorithmsAsync()。
+#[napi(js_name = "benchmarkAlgorithms")]
+pub fn benchmark_algorithms(options: Option<BenchmarkOptions>) -> Result<BenchmarkResult, ErrorCode> {
+    benchmark_algorithms_job(options.unwrap_or_default())
+}
+
+/// benchmarkAlgorithms() 的异步版本，在 libuv 线程池中运行并返回 Promise
+#[napi(js_name = "benchmarkAlgorithmsAsync", ts_return_type = "Promise<BenchmarkResult>")]
+pub fn benchmark_algorithms_async(options: Option<BenchmarkOptions>, signal: Option<Either<AbortSignal, ClassInstance<Job>>>) -> AsyncTask<FileTask<BenchmarkResult>> {
+    let options = options.unwrap_or_default();
+    FileTask::plain(signal, move || benchmark_algorithms_job(options))
+}
+
+/// 单项测量数据大小的上限（KB）
+const MAX_BENCHMARK_SIZE_KB: u32 = 64 * 1024;
+/// 单项测量时间的上限（毫秒）
+const MAX_BENCHMARK_DURATION_MS: u32 = 10_000;
+
+fn benchmark_algorithms_job(options: BenchmarkOptions) -> Result<BenchmarkResult, ErrorCode> {
+    let started = std::time::Instant::now();
+    let sizes_kb = options.sizes_kb.unwrap_or_else(|| vec![64, 1024]);
+    if sizes_kb.is_empty() || sizes_kb.iter().any(|&size| size == 0 || size > MAX_BENCHMARK_SIZE_KB) {
+        return Err(Error::new(ErrorCode::InvalidArgument, format!("sizesKb must be a non-empty list of sizes from 1 to {} KB", MAX_BENCHMARK_SIZE_KB)));
+    }
+    let duration_ms = options.duration_ms.unwrap_or(100);
+    if duration_ms == 0 || duration_ms > MAX_BENCHMARK_DURATION_MS {
+        return Err(Error::new(ErrorCode::InvalidArgument, format!("durationMs must be from 1 to {}: {}", MAX_BENCHMARK_DURATION_MS, duration_ms)));
+    }
+    let duration = std::time::Duration::from_millis(duration_ms as u64);
+    let algorithms = match &options.algorithms {
+        Some(names) => names.iter().map(|name| parse_algorithm(name)).collect::<Result<Vec<_>, ErrorCode>>()?,
+        None => {
+            runtime::ensure_ready()?;
+            [CryptoAlgorithm::Aes, CryptoAlgorithm::Chacha20Poly1305].into_iter()
+                .filter(config::algorithm_allowed)
+                .collect()
+        },
+    };
+    let hashes = match &options.hashes {
+        Some(names) => names.iter()
+            .map(|name| HashAlgorithm::from_str(name).map_err(|_| Error::new(ErrorCode::InvalidArgument, format!("Invalid plaintext hash algorithm: {}", name))))
+            .collect::<Result<Vec<_>, ErrorCode>>()?,
+        None => vec![HashAlgorithm::Sha256, HashAlgorithm::Blake3],
     };
     
-    // 计算总块数
-    let total_chunks = (original_size as f64 / chunk_size as f64).ceil() as u32;
+    let mut measurements = Vec::new();
+    let mut record = |algorithm: &str, operation: &str, size_kb: u32, throughput: hardware::Throughput| {
+        measurements.push(BenchmarkMeasurement {
+            algorithm: algorithm.to_string(),
+            operation: operation.to_string(),
+            size_kb,
+            iterations: throughput.iterations as f64,
+            mb_per_sec: throughput.mb_per_sec,
+        });
+        throughput.mb_per_sec
+    };
+    // 按各项测量的平均吞吐量比较，选出最快的算法
+    let mut fastest_algorithm: Option<(&str, f64)> = None;
+    for algo in &algorithms {
+        let mut total = 0.0;
+        for &size_kb in &sizes_kb {
+            let (encrypt, decrypt) = hardware::benchmark_cipher(algo, size_kb as usize * 1024, duration)?;
+            total += record(algo.as_str(), "encrypt", size_kb, encrypt);
+            total += record(algo.as_str(), "decrypt", size_kb, decrypt);
+        }
+        let average = total / (2 * sizes_kb.len()) as f64;
+        if fastest_algorithm.is_none_or(|(_, fastest)| average > fastest) {
+            fastest_algorithm = Some((algo.as_str(), average));
+        }
+    }
+    let mut fastest_hash: Option<(&str, f64)> = None;
+    for &hash in &hashes {
+        let mut total = 0.0;
+        for &size_kb in &sizes_kb {
+            let throughput = hardware::benchmark_hash(hash, size_kb as usize * 1024, duration)?;
+            total += record(hash.as_str(), "hash", size_kb, throughput);
+        }
+        let average = total / sizes_kb.len() as f64;
+        if fastest_hash.is_none_or(|(_, fastest)| average > fastest) {
+            fastest_hash = Some((hash.as_str(), average));
+        }
+    }
     
-    // 计算KB单位的大小
-    let original_size_kb = (original_size as f64) / 1024.0;
-    let chunk_size_kb = (chunk_size as f64) / 1024.0;
+    Ok(BenchmarkResult {
+        arch: std::env::consts::ARCH.to_string(),
+        aes_accelerated: hardware::aes_accelerated(),
+        measurements,
+        fastest_algorithm: fastest_algorithm.map(|(name, _)| name.to_string()),
+        fastest_hash: fastest_hash.map(|(name, _)| name.to_string()),
+        duration_ms: started.elapsed().as_secs_f64() * 1000.0,
+    })
+}
+
+/// 订阅所有任务的生命周期事件（queued、started、chunkDone、finished、failed），返回订阅 id；订阅不会阻止进程退出
+#[napi(ts_args_type = "callback: (event: JobEvent) => void")]
+pub fn events(callback: JsFunction, env: Env) -> Result<u32, ErrorCode> {
+    let mut tsfn: ThreadsafeFunction<job::JobEvent, ErrorStrategy::Fatal> =
+        callback.create_threadsafe_function(0, |ctx| Ok(vec![ctx.value])).map_err(errors::napi_error)?;
+    tsfn.unref(&env).map_err(errors::napi_error)?;
+    Ok(job::subscribe(tsfn))
+}
+
+/// 取消事件订阅，订阅不存在时返回 false
+#[napi(js_name = "unsubscribeEvents")]
+pub fn unsubscribe_events(subscription_id: u32) -> bool {
+    job::unsubscribe(subscription_id)
+}
+
+/// 获取当前生效的模块配置 - 来自配置文件和 ZIPPY_* 环境变量
+#[napi(js_name = "getConfig")]
+pub fn get_config() -> Result<ModuleConfig, ErrorCode> {
+    let config = config::get()?;
+    let allowed: Vec<String> = config.allowed_algorithms.iter().map(|algo| algo.as_str().to_string()).collect();
+    let policy = config::decrypt_policy();
     
-    // 创建并返回结果对象
-    let mut result = env.create_object()?;
-    result.set("totalChunks", total_chunks)?;
-    result.set("fileSizeKB", original_size_kb)?;
-    result.set("chunkSizeKB", chunk_size_kb)?;
+    Ok(ModuleConfig {
+        threads: config.threads as u32,
+        strict: config.strict,
+        log_level: config.log_level.as_str().to_string(),
+        allowed_algorithms: allowed,
+        reject_legacy_formats: policy.reject_legacy_formats,
+        require_authentication: policy.require_authentication,
+    })
+}
+
+/// 收紧本进程的解密策略 - 只能开启限制，已开启的限制（包括配置中的）不能关闭，返回生效的策略
+#[napi(js_name = "setDecryptPolicy")]
+pub fn set_decrypt_policy(policy: DecryptPolicyOptions) -> Result<DecryptPolicyResult, ErrorCode> {
+    let effective = config::tighten_decrypt_policy(config::DecryptPolicy {
+        reject_legacy_formats: policy.reject_legacy_formats.unwrap_or(false),
+        require_authentication: policy.require_authentication.unwrap_or(false),
+    });
     
-    Ok(result)
+    Ok(DecryptPolicyResult {
+        reject_legacy_formats: effective.reject_legacy_formats,
+        require_authentication: effective.require_authentication,
+    })
 }
 
 /// 获取文件大小通用函数，用于测试文件操作
 #[napi(js_name = "getFileSize")]
-pub fn get_file_size(file_path: String) -> Result<f64> {
-    let path = Path::new(&file_path);
-    
-    if !path.exists() {
-        return Err(Error::from_reason(format!("File not found: {}", file_path)));
-    }
+pub fn get_file_size(file_path: Either<String, i32>) -> Result<f64, ErrorCode> {
+    let file_path = fd::input_path(file_path)?;
     
-    match std::fs::metadata(&file_path) {
+    match file_path.metadata() {
+        Err(err) if err.kind() == io::ErrorKind::NotFound => {
+            Err(Error::new(ErrorCode::IoOpen, format!("File not found: {}", file_path)))
+        },
         Ok(metadata) => {
             let size_kb = (metadata.len() as f64) / 1024.0;
             Ok(size_kb)
         },
-        Err(err) => Err(Error::from_reason(format!("Failed to get file size: {}", err))),
+        Err(err) => Err(Error::new(ErrorCode::IoRead, format!("Failed to get file size: {}", err))),
     }
 }
 
 /// 计算文件的MD5哈希值
 #[napi(js_name = "computeFileMd5")]
-pub fn compute_file_md5(file_path: String) -> Result<String> {
+pub fn compute_file_md5(file_path: Either<String, i32>) -> Result<String, ErrorCode> {
+    compute_file_md5_job(file_path)
+}
+
+fn compute_file_md5_job(file_path: Either<String, i32>) -> Result<String, ErrorCode> {
+    let file_path = fd::input_path(file_path)?;
     // 打开文件
-    let file = match File::open(&file_path) {
+    let file = match fd::open(&file_path) {
         Ok(file) => file,
-        Err(err) => return Err(Error::from_reason(format!("Failed to open file: {}", err))),
+        Err(err) => return Err(Error::new(ErrorCode::IoOpen, format!("Failed to open file: {}", err))),
     };
     
     // 创建带缓冲的读取器，提高读取效率
@@ -594,7 +5101,7 @@ pub fn compute_file_md5(file_path: String) -> Result<String> {
             Ok(n) => {
                 hasher.update(&buffer[..n]);
             },
-            Err(err) => return Err(Error::from_reason(format!("Failed to read file: {}", err))),
+            Err(err) => return Err(Error::new(ErrorCode::IoRead, format!("Failed to read file: {}", err))),
         }
     }
     
@@ -604,3 +5111,9 @@ pub fn compute_file_md5(file_path: String) -> Result<String> {
     
     Ok(hex_hash)
 }
+
+/// computeFileMd5() 的异步版本，在 libuv 线程池中运行并返回 Promise
+#[napi(js_name = "computeFileMd5Async", ts_return_type = "Promise<string>")]
+pub fn compute_file_md5_async(file_path: Either<String, i32>, signal: Option<Either<AbortSignal, ClassInstance<Job>>>) -> AsyncTask<FileTask<String>> {
+    FileTask::plain(signal, move || compute_file_md5_job(file_path))
+}
@@ -0,0 +1,102 @@
+use std::io::Read;
+
+use crate::errors::CodedError;
+use crate::fd;
+
+/// 检测 MIME 类型时读取的明文开头的长度
+pub const SNIFF_LEN: usize = 8 * 1024;
+
+/// 无法识别的二进制内容
+pub const OCTET_STREAM: &str = "application/octet-stream";
+
+/// 根据明文开头的字节检测 MIME 类型：按文件签名识别常见的图片、音视频、压缩包和文档格式，
+/// 其余不含 NUL 字节的 UTF-8 内容视为 `text/plain`，空文件和无法识别的内容为 `application/octet-stream`
+pub fn sniff(sample: &[u8]) -> &'static str {
+    let sample = &sample[..sample.len().min(SNIFF_LEN)];
+    if let Some(kind) = infer::get(sample) {
+        return kind.mime_type();
+    }
+    if sample.is_empty() || sample.contains(&0) {
+        return OCTET_STREAM;
+    }
+    // 采样可能在多字节字符中间截断，末尾不完整的字符不算错误
+    match std::str::from_utf8(sample) {
+        Ok(_) => "text/plain",
+        Err(err) if err.error_len().is_none() => "text/plain",
+        Err(_) => OCTET_STREAM,
+    }
+}
+
+/// 读取文件开头的字节检测 MIME 类型
+pub fn sniff_file(path: &fd::Target) -> Result<&'static str, CodedError> {
+    let mut sample = Vec::with_capacity(SNIFF_LEN);
+    fd::open(path)
+        .and_then(|file| file.take(SNIFF_LEN as u64).read_to_end(&mut sample))
+        .map_err(|e| CodedError::io_read(format!("Error reading input file: {}", e)))?;
+    Ok(sniff(&sample))
+}
+
+/// 检查用户指定的 MIME 类型：`类型/子类型`，只允许可见的 ASCII 字符
+pub fn check(mime_type: &str) -> Result<(), CodedError> {
+    let valid = mime_type.len() <= 255
+        && mime_type.bytes().all(|byte| byte.is_ascii_graphic() || byte == b' ')
+        && mime_type
+            .split_once('/')
+            .is_some_and(|(kind, subtype)| !kind.is_empty() && !subtype.is_empty());
+    match valid {
+        true => Ok(()),
+        false => Err(CodedError::invalid_argument(format!("Invalid MIME type: {:?}", mime_type))),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::fs;
+
+    use super::*;
+    use crate::errors::ErrorCode;
+
+    #[test]
+    fn sniffs_signatures_and_text() {
+        assert_eq!(sniff(b"\x89PNG\r\n\x1a\n\0\0\0\rIHDR"), "image/png");
+        assert_eq!(sniff(b"%PDF-1.7\n"), "application/pdf");
+        assert_eq!(sniff(b"PK\x03\x04\x14\0\0\0\x08\0"), "application/zip");
+        assert_eq!(sniff(b"\x1f\x8b\x08\0\0\0\0\0"), "application/gzip");
+        assert_eq!(sniff("héllo wörld\n".as_bytes()), "text/plain");
+        assert_eq!(sniff(b""), OCTET_STREAM);
+        assert_eq!(sniff(b"text\0with nul"), OCTET_STREAM);
+        assert_eq!(sniff(b"\xff\xfe\xfd invalid"), OCTET_STREAM);
+    }
+
+    #[test]
+    fn allows_a_character_cut_at_the_sample_end() {
+        // 采样在两字节字符中间截断
+        let mut text = "a".repeat(SNIFF_LEN - 1).into_bytes();
+        text.extend_from_slice("é".as_bytes());
+        assert_eq!(sniff(&text), "text/plain");
+        assert_eq!(sniff(&text[..text.len() - 1]), "text/plain");
+        // 截断之外的内容不影响结果
+        text.extend_from_slice(&[0, 0xff]);
+        assert_eq!(sniff(&text), "text/plain");
+    }
+
+    #[test]
+    fn sniffs_files() {
+        let path = std::env::temp_dir().join(format!("zippy-mime-{}-file", std::process::id()));
+        fs::write(&path, b"%PDF-1.4\n%...").unwrap();
+        let target = fd::Target::from(&*path.to_string_lossy());
+        assert_eq!(sniff_file(&target).unwrap(), "application/pdf");
+        fs::remove_file(&path).unwrap();
+        assert_eq!(sniff_file(&target).unwrap_err().code, ErrorCode::IoRead);
+    }
+
+    #[test]
+    fn checks_user_mime_types() {
+        for valid in ["text/plain", "application/vnd.ms-excel", "text/plain; charset=utf-8"] {
+            assert!(check(valid).is_ok(), "{}", valid);
+        }
+        for invalid in ["", "text", "/plain", "text/", "text/plain\n", "tëxt/plain", &format!("a/{}", "b".repeat(300))] {
+            assert_eq!(check(invalid).unwrap_err().code, ErrorCode::InvalidArgument, "{}", invalid);
+        }
+    }
+}
@@ -0,0 +1,472 @@
+use std::io::Read;
+use std::path::Path;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use base64::engine::general_purpose::STANDARD;
+use base64::Engine;
+use blake2::digest::consts::U32;
+use blake2::{Blake2b, Blake2b512, Digest};
+use ed25519_dalek::{Signature, Signer, SigningKey, VerifyingKey};
+use rand::RngCore;
+
+use crate::errors::{open_error, read_error, CodedError};
+use crate::fd::{self, Target};
+
+/// 签名算法：Ed25519
+const SIG_ALG: &[u8; 2] = b"Ed";
+/// 先以 BLAKE2b-512 计算文件摘要再签名（minisign 0.8 起的默认方式）
+const SIG_ALG_PREHASHED: &[u8; 2] = b"ED";
+/// 私钥加密使用的密钥派生算法：scrypt（crypto_pwhash_scryptsalsa208sha256）
+const KDF_ALG: &[u8; 2] = b"Sc";
+/// 私钥未加密
+const KDF_NONE: &[u8; 2] = &[0, 0];
+/// 私钥校验和算法：BLAKE2b-256
+const CHK_ALG: &[u8; 2] = b"B2";
+
+/// minisign 生成加密私钥时的默认 scrypt 参数（opslimit、memlimit）
+const KDF_OPSLIMIT: u64 = 33_554_432;
+const KDF_MEMLIMIT: u64 = 1_073_741_824;
+/// 拒绝需要超过 4 GiB 内存的 scrypt 参数
+const MAX_KDF_LOG_N: u8 = 22;
+
+const KEY_ID_LEN: usize = 8;
+const SALT_LEN: usize = 32;
+/// 私钥文件中被加密的部分：密钥 ID（8）+ 私钥（种子 32 + 公钥 32）+ 校验和（32）
+const KEYNUM_SK_LEN: usize = KEY_ID_LEN + 64 + 32;
+/// 私钥文件的解码长度
+const SECRET_KEY_LEN: usize = 6 + SALT_LEN + 16 + KEYNUM_SK_LEN;
+
+const UNTRUSTED_PREFIX: &str = "untrusted comment: ";
+const TRUSTED_PREFIX: &str = "trusted comment: ";
+
+/// 计算摘要时每次读取的长度，每读完一块检查一次超时并发送 chunkDone 事件
+const READ_BUFFER_LEN: usize = 1024 * 1024;
+/// 不预先计算摘要的旧格式签名需要把整个文件读入内存，限制文件大小
+const MAX_LEGACY_FILE_SIZE: u64 = 1024 * 1024 * 1024;
+
+/// minisign 显示的密钥 ID：按小端读取的 64 位整数的大写十六进制
+fn key_id_hex(key_id: &[u8; KEY_ID_LEN]) -> String {
+    format!("{:016X}", u64::from_le_bytes(*key_id))
+}
+
+/// 取出 minisign 文件中第 index 个非空行并解码 base64
+fn base64_line(lines: &[&str], index: usize, what: &str) -> Result<Vec<u8>, CodedError> {
+    let line = lines.get(index).ok_or_else(|| CodedError::bad_format(format!("Invalid minisign {}: missing data", what)))?;
+    STANDARD
+        .decode(line.trim())
+        .map_err(|_| CodedError::bad_format(format!("Invalid minisign {}: bad base64", what)))
+}
+
+fn non_empty_lines(text: &str) -> Vec<&str> {
+    text.lines().map(|line| line.trim_end_matches('\r')).filter(|line| !line.trim().is_empty()).collect()
+}
+
+/// 按 libsodium 的 pickparams 把 opslimit / memlimit 换算为 scrypt 的 (log N, r, p)
+fn scrypt_params(opslimit: u64, memlimit: u64) -> Result<scrypt::Params, CodedError> {
+    let opslimit = opslimit.max(32768);
+    let r = 8u64;
+    let (log_n, p) = if opslimit < memlimit / 32 {
+        let max_n = opslimit / (r * 4);
+        ((1..63).find(|&log_n| 1u64 << log_n > max_n / 2).unwrap_or(63), 1)
+    } else {
+        let max_n = memlimit / (r * 128);
+        let log_n = (1..63).find(|&log_n| 1u64 << log_n > max_n / 2).unwrap_or(63);
+        let max_rp = ((opslimit / 4) / (1u64 << log_n)).min(0x3fff_ffff);
+        (log_n, (max_rp / r).max(1))
+    };
+    if log_n > MAX_KDF_LOG_N as u64 {
+        return Err(CodedError::unsupported("minisign secret key uses scrypt parameters that need too much memory"));
+    }
+    scrypt::Params::new(log_n as u8, r as u32, p as u32, scrypt::Params::RECOMMENDED_LEN)
+        .map_err(|_| CodedError::invalid_argument("Invalid scrypt parameters in minisign secret key"))
+}
+
+/// 用口令派生的 scrypt 输出异或私钥部分（加密和解密相同）
+fn apply_kdf(keynum_sk: &mut [u8], password: &[u8], salt: &[u8], opslimit: u64, memlimit: u64) -> Result<(), CodedError> {
+    let params = scrypt_params(opslimit, memlimit)?;
+    let mut stream = vec![0u8; KEYNUM_SK_LEN];
+    scrypt::scrypt(password, salt, &params, &mut stream).map_err(|_| CodedError::unknown("scrypt key derivation failed"))?;
+    keynum_sk.iter_mut().zip(stream).for_each(|(byte, mask)| *byte ^= mask);
+    Ok(())
+}
+
+/// BLAKE2b-256(签名算法 || 密钥 ID || 私钥)
+fn checksum(key_id: &[u8], secret: &[u8]) -> [u8; 32] {
+    let mut hasher = Blake2b::<U32>::new();
+    hasher.update(SIG_ALG);
+    hasher.update(key_id);
+    hasher.update(secret);
+    hasher.finalize().into()
+}
+
+/// 新生成的密钥对，内容为 minisign 的公钥文件和私钥文件
+pub struct KeyPair {
+    pub key_id: String,
+    pub public_key: String,
+    pub secret_key: String,
+}
+
+/// 生成 minisign 密钥对；password 为 None 时私钥不加密（与 `minisign -G -W` 相同）
+pub fn generate_key_pair(password: Option<&str>) -> Result<KeyPair, CodedError> {
+    let mut seed = [0u8; 32];
+    let mut key_id = [0u8; KEY_ID_LEN];
+    rand::thread_rng().fill_bytes(&mut seed);
+    rand::thread_rng().fill_bytes(&mut key_id);
+    let signing_key = SigningKey::from_bytes(&seed);
+    let public_key = signing_key.verifying_key().to_bytes();
+
+    let mut keynum_sk = Vec::with_capacity(KEYNUM_SK_LEN);
+    keynum_sk.extend_from_slice(&key_id);
+    keynum_sk.extend_from_slice(&seed);
+    keynum_sk.extend_from_slice(&public_key);
+    let sum = checksum(&key_id, &keynum_sk[KEY_ID_LEN..]);
+    keynum_sk.extend_from_slice(&sum);
+
+    let mut salt = [0u8; SALT_LEN];
+    let (kdf_alg, opslimit, memlimit) = match password {
+        Some(password) => {
+            rand::thread_rng().fill_bytes(&mut salt);
+            apply_kdf(&mut keynum_sk, password.as_bytes(), &salt, KDF_OPSLIMIT, KDF_MEMLIMIT)?;
+            (KDF_ALG, KDF_OPSLIMIT, KDF_MEMLIMIT)
+        }
+        None => (KDF_NONE, 0, 0),
+    };
+    let mut secret = Vec::with_capacity(SECRET_KEY_LEN);
+    secret.extend_from_slice(SIG_ALG);
+    secret.extend_from_slice(kdf_alg);
+    secret.extend_from_slice(CHK_ALG);
+    secret.extend_from_slice(&salt);
+    secret.extend_from_slice(&opslimit.to_le_bytes());
+    secret.extend_from_slice(&memlimit.to_le_bytes());
+    secret.extend_from_slice(&keynum_sk);
+
+    let mut public = Vec::with_capacity(2 + KEY_ID_LEN + 32);
+    public.extend_from_slice(SIG_ALG);
+    public.extend_from_slice(&key_id);
+    public.extend_from_slice(&public_key);
+
+    let id = key_id_hex(&key_id);
+    let secret_comment = if password.is_some() { "minisign encrypted secret key" } else { "minisign secret key" };
+    Ok(KeyPair {
+        public_key: format!("{}minisign public key {}\n{}\n", UNTRUSTED_PREFIX, id, STANDARD.encode(public)),
+        secret_key: format!("{}{}\n{}\n", UNTRUSTED_PREFIX, secret_comment, STANDARD.encode(secret)),
+        key_id: id,
+    })
+}
+
+/// 读取 minisign 私钥文件的内容，加密的私钥需要口令
+fn parse_secret_key(text: &str, password: Option<&str>) -> Result<([u8; KEY_ID_LEN], SigningKey), CodedError> {
+    let lines = non_empty_lines(text);
+    let data_line = if lines.first().is_some_and(|line| line.starts_with(UNTRUSTED_PREFIX)) { 1 } else { 0 };
+    let data = base64_line(&lines, data_line, "secret key")?;
+    if data.len() != SECRET_KEY_LEN || &data[..2] != SIG_ALG || &data[4..6] != CHK_ALG {
+        return Err(CodedError::bad_format("Invalid minisign secret key"));
+    }
+    let salt = &data[6..6 + SALT_LEN];
+    let opslimit = u64::from_le_bytes(data[38..46].try_into().unwrap());
+    let memlimit = u64::from_le_bytes(data[46..54].try_into().unwrap());
+    let mut keynum_sk = data[54..].to_vec();
+    match (&data[2..4], password) {
+        (kdf, _) if kdf == KDF_NONE => {}
+        (kdf, Some(password)) if kdf == KDF_ALG => apply_kdf(&mut keynum_sk, password.as_bytes(), salt, opslimit, memlimit)?,
+        (kdf, None) if kdf == KDF_ALG => return Err(CodedError::invalid_argument("minisign secret key is encrypted: options.password is required")),
+        _ => return Err(CodedError::unsupported("Unsupported minisign secret key encryption")),
+    }
+
+    let key_id: [u8; KEY_ID_LEN] = keynum_sk[..KEY_ID_LEN].try_into().unwrap();
+    let secret = &keynum_sk[KEY_ID_LEN..KEY_ID_LEN + 64];
+    if checksum(&key_id, secret) != keynum_sk[KEY_ID_LEN + 64..] {
+        return Err(CodedError::auth_failed("Wrong password for the minisign secret key"));
+    }
+    let signing_key = SigningKey::from_bytes(secret[..32].try_into().unwrap());
+    if signing_key.verifying_key().to_bytes() != secret[32..] {
+        return Err(CodedError::bad_format("Invalid minisign secret key: public key does not match"));
+    }
+    Ok((key_id, signing_key))
+}
+
+/// 读取 minisign 公钥：公钥文件的内容，或只有 base64 的一行（`minisign -P` 的参数）
+fn parse_public_key(text: &str) -> Result<([u8; KEY_ID_LEN], VerifyingKey), CodedError> {
+    let lines = non_empty_lines(text);
+    let data_line = if lines.first().is_some_and(|line| line.starts_with(UNTRUSTED_PREFIX)) { 1 } else { 0 };
+    let data = base64_line(&lines, data_line, "public key")?;
+    if data.len() != 2 + KEY_ID_LEN + 32 || &data[..2] != SIG_ALG {
+        return Err(CodedError::bad_format("Invalid minisign public key"));
+    }
+    let key = VerifyingKey::from_bytes(data[10..].try_into().unwrap())
+        .map_err(|_| CodedError::bad_format("Invalid minisign public key"))?;
+    Ok((data[2..10].try_into().unwrap(), key))
+}
+
+/// 流式计算文件的 BLAKE2b-512 摘要
+fn hash_file(path: &Target, check: impl Fn() -> Result<(), CodedError>, mut chunk_done: impl FnMut(u32, usize)) -> Result<(Vec<u8>, u64), CodedError> {
+    let mut input = fd::open(path).map_err(|e| open_error("opening input file", e))?;
+    let mut hasher = Blake2b512::new();
+    let mut buffer = vec![0u8; READ_BUFFER_LEN];
+    let mut size = 0u64;
+    let mut pending = 0usize;
+    let mut index = 0u32;
+    loop {
+        check()?;
+        let read = match input.read(&mut buffer) {
+            Ok(0) => break,
+            Ok(read) => read,
+            Err(err) if err.kind() == std::io::ErrorKind::Interrupted => continue,
+            Err(err) => return Err(read_error("reading input file", err)),
+        };
+        hasher.update(&buffer[..read]);
+        size += read as u64;
+        pending += read;
+        if pending >= READ_BUFFER_LEN {
+            index += 1;
+            chunk_done(index, pending);
+            pending = 0;
+        }
+    }
+    Ok((hasher.finalize().to_vec(), size))
+}
+
+/// 签名结果
+pub struct SignReport {
+    /// .minisig 文件的内容
+    pub signature: String,
+    pub key_id: String,
+    pub trusted_comment: String,
+    pub file_size: u64,
+}
+
+/// 以 minisign 的格式（预先计算 BLAKE2b-512 摘要）为文件签名，输出可以用 `minisign -V` 或 `rsign verify` 验证
+///
+/// trusted_comment 和签名一起被全局签名保护；不设置时与 minisign 相同，为时间戳、文件名和 hashed 标记。
+/// check 在每次读取前检查超时和取消，chunk_done 报告已计算摘要的数据量。
+pub fn sign_file(input_path: &Target, secret_key: &str, password: Option<&str>, trusted_comment: Option<&str>, untrusted_comment: Option<&str>, check: impl Fn() -> Result<(), CodedError>, chunk_done: impl FnMut(u32, usize)) -> Result<SignReport, CodedError> {
+    let (key_id, signing_key) = parse_secret_key(secret_key, password)?;
+    let trusted_comment = match trusted_comment {
+        Some(comment) => comment.to_string(),
+        None => {
+            let name = input_path.as_path().and_then(|path| Path::new(path).file_name()).map_or_else(String::new, |name| name.to_string_lossy().into_owned());
+            let timestamp = SystemTime::now().duration_since(UNIX_EPOCH).map_or(0, |duration| duration.as_secs());
+            format!("timestamp:{}\tfile:{}\thashed", timestamp, name)
+        }
+    };
+    if trusted_comment.contains(['\r', '\n']) {
+        return Err(CodedError::invalid_argument("minisign trusted comment must be a single line"));
+    }
+    let untrusted_comment = untrusted_comment.unwrap_or("signature from minisign secret key");
+    if untrusted_comment.contains(['\r', '\n']) {
+        return Err(CodedError::invalid_argument("minisign untrusted comment must be a single line"));
+    }
+
+    let (digest, file_size) = hash_file(input_path, check, chunk_done)?;
+    let signature = signing_key.sign(&digest).to_bytes();
+    let mut global_message = signature.to_vec();
+    global_message.extend_from_slice(trusted_comment.as_bytes());
+    let global_signature = signing_key.sign(&global_message).to_bytes();
+
+    let mut signature_line = Vec::with_capacity(2 + KEY_ID_LEN + 64);
+    signature_line.extend_from_slice(SIG_ALG_PREHASHED);
+    signature_line.extend_from_slice(&key_id);
+    signature_line.extend_from_slice(&signature);
+    Ok(SignReport {
+        signature: format!(
+            "{}{}\n{}\n{}{}\n{}\n",
+            UNTRUSTED_PREFIX,
+            untrusted_comment,
+            STANDARD.encode(signature_line),
+            TRUSTED_PREFIX,
+            trusted_comment,
+            STANDARD.encode(global_signature)
+        ),
+        key_id: key_id_hex(&key_id),
+        trusted_comment,
+        file_size,
+    })
+}
+
+/// 验证结果；签名不匹配时 valid 为 false，error 为原因
+pub struct VerifyReport {
+    pub valid: bool,
+    pub key_id: String,
+    pub trusted_comment: String,
+    pub error: Option<String>,
+}
+
+/// 验证 minisign 签名（.minisig 文件的内容），同时支持预先计算摘要的 ED 签名和旧的 Ed 签名
+pub fn verify_file(input_path: &Target, public_key: &str, signature: &str, check: impl Fn() -> Result<(), CodedError>, chunk_done: impl FnMut(u32, usize)) -> Result<VerifyReport, CodedError> {
+    let (public_key_id, verifying_key) = parse_public_key(public_key)?;
+    let lines = non_empty_lines(signature);
+    if lines.len() < 4 || !lines[0].starts_with(UNTRUSTED_PREFIX) {
+        return Err(CodedError::bad_format("Invalid minisign signature: expected four lines"));
+    }
+    let signature_line = base64_line(&lines, 1, "signature")?;
+    let trusted_comment = lines[2]
+        .strip_prefix(TRUSTED_PREFIX)
+        .ok_or_else(|| CodedError::bad_format("Invalid minisign signature: missing trusted comment"))?
+        .to_string();
+    let global_signature = base64_line(&lines, 3, "signature")?;
+    if signature_line.len() != 2 + KEY_ID_LEN + 64 || global_signature.len() != 64 {
+        return Err(CodedError::bad_format("Invalid minisign signature"));
+    }
+    let prehashed = match &signature_line[..2] {
+        alg if alg == SIG_ALG_PREHASHED => true,
+        alg if alg == SIG_ALG => false,
+        _ => return Err(CodedError::unsupported("Unsupported minisign signature algorithm")),
+    };
+    let key_id: [u8; KEY_ID_LEN] = signature_line[2..10].try_into().unwrap();
+    if key_id != public_key_id {
+        return Err(CodedError::auth_failed(format!(
+            "minisign signature key ID {} does not match the public key ID {}",
+            key_id_hex(&key_id),
+            key_id_hex(&public_key_id)
+        )));
+    }
+
+    let signature = Signature::from_bytes(signature_line[10..].try_into().unwrap());
+    let message = if prehashed {
+        hash_file(input_path, check, chunk_done)?.0
+    } else {
+        let mut input = fd::open(input_path).map_err(|e| open_error("opening input file", e))?;
+        let size = input.metadata().map_err(|e| read_error("reading input file metadata", e))?.len();
+        if size > MAX_LEGACY_FILE_SIZE {
+            return Err(CodedError::unsupported("File is too large to verify a legacy (non-prehashed) minisign signature"));
+        }
+        let mut message = Vec::new();
+        input.read_to_end(&mut message).map_err(|e| read_error("reading input file", e))?;
+        message
+    };
+    let mut report = VerifyReport { valid: false, key_id: key_id_hex(&key_id), trusted_comment, error: None };
+    if verifying_key.verify_strict(&message, &signature).is_err() {
+        report.error = Some("Signature verification failed: the file or signature was modified".to_string());
+        return Ok(report);
+    }
+    let mut global_message = signature.to_bytes().to_vec();
+    global_message.extend_from_slice(report.trusted_comment.as_bytes());
+    let global_signature = Signature::from_bytes(global_signature[..].try_into().unwrap());
+    if verifying_key.verify_strict(&global_message, &global_signature).is_err() {
+        report.error = Some("Comment signature verification failed: the trusted comment was modified".to_string());
+        return Ok(report);
+    }
+    report.valid = true;
+    Ok(report)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::fs;
+    use std::path::PathBuf;
+
+    use super::*;
+    use crate::errors::ErrorCode;
+
+    // 本环境中没有 minisign 命令。以下签名取自 minisign-verify 的测试，由 minisign 对内容为 "test" 的文件生成；
+    // 加密私钥由 libsodium 的 crypto_pwhash_scryptsalsa208sha256（minisign 加密私钥时调用的函数）按 minisign 的布局生成，
+    // 种子为 0..32，密钥 ID 为 11 22 33 44 55 66 77 88，口令为 "correct horse"。
+    const MINISIGN_PUBLIC_KEY: &str = "RWQf6LRCGA9i53mlYecO4IzT51TGPpvWucNSCh1CBM0QTaLn73Y7GFO3";
+    const LEGACY_SIGNATURE: &str = "untrusted comment: signature from minisign secret key
+RWQf6LRCGA9i59SLOFxz6NxvASXDJeRtuZykwQepbDEGt87ig1BNpWaVWuNrm73YiIiJbq71Wi+dP9eKL8OC351vwIasSSbXxwA=
+trusted comment: timestamp:1555779966\tfile:test
+QtKMXWyYcwdpZAlPF7tE2ENJkRd1ujvKjlj1m9RtHTBnZPa5WKU5uWRs5GoP5M/VqE81QFuMKI5k/SfNQUaOAA==
+";
+    const PREHASHED_SIGNATURE: &str = "untrusted comment: signature from minisign secret key
+RUQf6LRCGA9i559r3g7V1qNyJDApGip8MfqcadIgT9CuhV3EMhHoN1mGTkUidF/z7SrlQgXdy8ofjb7bNJJylDOocrCo8KLzZwo=
+trusted comment: timestamp:1556193335\tfile:test
+y/rUw2y8/hOUYjZU71eHp/Wo1KZ40fGy2VJEDl34XMJM+TX48Ss/17u3IvIfbVR1FkZZSNCisQbuQY+bHwhEBg==
+";
+    const PASSWORD: &str = "correct horse";
+    /// opslimit 65536、memlimit 64 MiB：N = 2^11, r = 8, p = 1
+    const SECRET_KEY_P1: &str = "untrusted comment: minisign encrypted secret key
+RWRTY0IyZGVmZ2hpamtsbW5vcHFyc3R1dnd4eXp7fH1+f4CBgoMAAAEAAAAAAAAAAAQAAAAAbTK2hTxmGbexevqJ437HTv/QRBF27wLH40s8oCJy8JabM5ipemDZX8/1Va/ofKYKkMdmppHTUT1BrwQrb48gJk5VrKtg65cufiGV0E+0CvbBIuQDnm1ztpQzk3ccmaEE0hFEXAMghlI=
+";
+    /// opslimit 1048576、memlimit 16 MiB：N = 2^14, r = 8, p = 2
+    const SECRET_KEY_P2: &str = "untrusted comment: minisign encrypted secret key
+RWRTY0IyZGVmZ2hpamtsbW5vcHFyc3R1dnd4eXp7fH1+f4CBgoMAABAAAAAAAAAAAAEAAAAA6rFCWTx4ZMkG348qe7Sy8hDu6qE4/Wf56my53vKIMuJbGcGa/0guMEGwqotKBVebilOQi9CI8Py5F5Vx/NfjuAeVmZmAPSqAMwKnizQv+1j7U7wltSI1oSwv+4Px9uYX2ZL3uBq8c5U=
+";
+    const PUBLIC_KEY: &str = "RWQRIjNEVWZ3iAOhB7/zzhC+HXDdGOdLwJln5NYwm6UNXx3chmQSVTG4";
+    /// 以上私钥对 "test" 的 ED 签名，trusted comment 为 "timestamp:0\tfile:test"（Ed25519 签名是确定的）
+    const SIGNATURE: &str = "untrusted comment: signature from minisign secret key
+RUQRIjNEVWZ3iO7bfJOJZsqfCwXF+z0sQ1VXJkH6b3IX+R7jgIZiK0baMSQj5407he+UPkHYBXBmR29ayQO7Zj5oIfpnYdZXcAQ=
+trusted comment: timestamp:0\tfile:test
+2SgyJztf3Ate5cIkAxuq2jA71UZNGlxcEuFTP0SH/BNCX77t7yocPkFHd9vcjVDpDAa3J01XoagYmaxYkj07BQ==
+";
+
+    /// 测试用的输入文件，结束时删除
+    struct TempFile(PathBuf);
+
+    impl TempFile {
+        fn new(name: &str, content: &[u8]) -> Self {
+            let path = std::env::temp_dir().join(format!("zippy-minisign-{}-{}", std::process::id(), name));
+            fs::write(&path, content).unwrap();
+            TempFile(path)
+        }
+
+        fn target(&self) -> Target {
+            Target::from(&*self.0.to_string_lossy())
+        }
+    }
+
+    impl Drop for TempFile {
+        fn drop(&mut self) {
+            let _ = fs::remove_file(&self.0);
+        }
+    }
+
+    fn verify(file: &TempFile, public_key: &str, signature: &str) -> VerifyReport {
+        verify_file(&file.target(), public_key, signature, || Ok(()), |_, _| {}).unwrap()
+    }
+
+    #[test]
+    fn picks_scrypt_parameters_like_libsodium() {
+        let cases = [(KDF_OPSLIMIT, KDF_MEMLIMIT, 20, 1), (65536, 67108864, 11, 1), (1048576, 16777216, 14, 2)];
+        for (opslimit, memlimit, log_n, p) in cases {
+            let params = scrypt_params(opslimit, memlimit).unwrap();
+            assert_eq!((params.log_n(), params.r(), params.p()), (log_n, 8, p), "opslimit {} memlimit {}", opslimit, memlimit);
+        }
+        assert_eq!(scrypt_params(1 << 40, 1 << 40).err().unwrap().code, ErrorCode::Unsupported);
+    }
+
+    #[test]
+    fn verifies_signatures_made_by_minisign() {
+        let file = TempFile::new("reference", b"test");
+        for signature in [LEGACY_SIGNATURE, PREHASHED_SIGNATURE] {
+            let report = verify(&file, MINISIGN_PUBLIC_KEY, signature);
+            assert!(report.valid, "{:?}", report.error);
+            assert_eq!(report.key_id, "E7620F1842B4E81F");
+        }
+
+        let modified = TempFile::new("modified", b"Test");
+        assert!(!verify(&modified, MINISIGN_PUBLIC_KEY, PREHASHED_SIGNATURE).valid);
+        let forged = PREHASHED_SIGNATURE.replace("file:test", "file:evil");
+        assert!(verify(&file, MINISIGN_PUBLIC_KEY, &forged).error.unwrap().contains("trusted comment"));
+        let other_key = verify_file(&file.target(), PUBLIC_KEY, PREHASHED_SIGNATURE, || Ok(()), |_, _| {});
+        assert_eq!(other_key.err().unwrap().code, ErrorCode::AuthFailed);
+    }
+
+    #[test]
+    fn decrypts_scrypt_encrypted_secret_keys() {
+        let file = TempFile::new("sign", b"test");
+        for secret_key in [SECRET_KEY_P1, SECRET_KEY_P2] {
+            let (key_id, signing_key) = parse_secret_key(secret_key, Some(PASSWORD)).unwrap();
+            assert_eq!(key_id_hex(&key_id), "8877665544332211");
+            assert_eq!(signing_key.to_bytes(), std::array::from_fn::<u8, 32, _>(|i| i as u8));
+        }
+
+        let report = sign_file(&file.target(), SECRET_KEY_P1, Some(PASSWORD), Some("timestamp:0\tfile:test"), None, || Ok(()), |_, _| {}).unwrap();
+        assert_eq!(report.signature, SIGNATURE);
+        assert_eq!(report.file_size, 4);
+        assert!(verify(&file, PUBLIC_KEY, SIGNATURE).valid);
+
+        assert_eq!(parse_secret_key(SECRET_KEY_P1, Some("wrong")).err().unwrap().code, ErrorCode::AuthFailed);
+        assert_eq!(parse_secret_key(SECRET_KEY_P1, None).err().unwrap().code, ErrorCode::InvalidArgument);
+    }
+
+    #[test]
+    fn signs_with_an_unencrypted_generated_key() {
+        let file = TempFile::new("generated", b"some file contents");
+        let pair = generate_key_pair(None).unwrap();
+        let report = sign_file(&file.target(), &pair.secret_key, None, None, Some("comment"), || Ok(()), |_, _| {}).unwrap();
+        assert!(report.trusted_comment.ends_with("\thashed"));
+        let verified = verify(&file, &pair.public_key, &report.signature);
+        assert!(verified.valid);
+        assert_eq!(verified.key_id, pair.key_id);
+    }
+}
@@ -0,0 +1,128 @@
+use std::fs::File;
+use std::io::{self, Read, Seek};
+use std::ops::Deref;
+
+use memmap2::Mmap;
+
+/// 以只读方式映射到内存的文件内容（由 memmap2 实现），从映射时文件的读取位置开始
+///
+/// 映射期间文件被其它进程截断时，访问被截掉的部分会使进程收到 SIGBUS，因此只用于操作期间不会被修改的输入。
+pub struct Mapping {
+    map: Mmap,
+    offset: usize,
+}
+
+impl Mapping {
+    /// 映射普通文件；不是普通文件、文件为空或映射失败时返回 None，由调用方改用普通读取
+    pub fn of(file: &File) -> Option<Mapping> {
+        let metadata = file.metadata().ok()?;
+        let offset = usize::try_from((&*file).stream_position().ok()?).ok()?;
+        if !metadata.is_file() || offset as u64 >= metadata.len() {
+            return None;
+        }
+        // 安全性：映射为只读，调用方保证操作期间文件不被截断或修改（见上文）
+        let map = unsafe { Mmap::map(file) }.ok()?;
+        if offset >= map.len() {
+            return None;
+        }
+        // 按顺序读取，提示内核提前读入后面的页
+        #[cfg(unix)]
+        let _ = map.advise(memmap2::Advice::Sequential);
+        Some(Mapping { map, offset })
+    }
+}
+
+impl Deref for Mapping {
+    type Target = [u8];
+
+    fn deref(&self) -> &[u8] {
+        &self.map[self.offset..]
+    }
+}
+
+/// 整个读入的输入：能映射时使用映射，否则读入内存
+pub enum InputData {
+    Mapped(Mapping),
+    Read(Vec<u8>),
+}
+
+impl InputData {
+    /// use_mmap 为 true 时先尝试映射，失败时退回普通读取
+    pub fn read(file: &mut File, use_mmap: bool) -> io::Result<InputData> {
+        if let Some(mapping) = use_mmap.then(|| Mapping::of(file)).flatten() {
+            return Ok(InputData::Mapped(mapping));
+        }
+        let mut data = Vec::new();
+        file.read_to_end(&mut data)?;
+        Ok(InputData::Read(data))
+    }
+}
+
+impl Deref for InputData {
+    type Target = [u8];
+
+    fn deref(&self) -> &[u8] {
+        match self {
+            InputData::Mapped(mapping) => mapping,
+            InputData::Read(data) => data,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::{SeekFrom, Write};
+    use std::path::PathBuf;
+
+    /// 测试用的临时文件，结束时删除
+    struct TempFile(PathBuf);
+
+    impl TempFile {
+        fn new(name: &str, contents: &[u8]) -> Self {
+            let path = std::env::temp_dir().join(format!("zippy-mmap-{}-{}", std::process::id(), name));
+            File::create(&path).unwrap().write_all(contents).unwrap();
+            TempFile(path)
+        }
+
+        fn open(&self) -> File {
+            File::open(&self.0).unwrap()
+        }
+    }
+
+    impl Drop for TempFile {
+        fn drop(&mut self) {
+            let _ = std::fs::remove_file(&self.0);
+        }
+    }
+
+    #[test]
+    fn maps_from_current_position() {
+        let temp = TempFile::new("position", b"hello, mapped world");
+        let mut file = temp.open();
+        assert_eq!(&*Mapping::of(&file).unwrap(), b"hello, mapped world");
+        file.seek(SeekFrom::Start(7)).unwrap();
+        assert_eq!(&*Mapping::of(&file).unwrap(), b"mapped world");
+        let data = InputData::read(&mut file, true).unwrap();
+        assert!(matches!(data, InputData::Mapped(_)));
+        assert_eq!(&*data, b"mapped world");
+    }
+
+    #[test]
+    fn falls_back_to_reading() {
+        let temp = TempFile::new("empty", b"");
+        let mut file = temp.open();
+        assert!(Mapping::of(&file).is_none());
+        assert!(matches!(InputData::read(&mut file, true).unwrap(), InputData::Read(data) if data.is_empty()));
+
+        let temp = TempFile::new("end", b"abc");
+        let mut file = temp.open();
+        file.seek(SeekFrom::End(0)).unwrap();
+        assert!(Mapping::of(&file).is_none());
+
+        file.seek(SeekFrom::Start(1)).unwrap();
+        let data = InputData::read(&mut file, false).unwrap();
+        assert!(matches!(data, InputData::Read(_)));
+        assert_eq!(&*data, b"bc");
+    }
+}
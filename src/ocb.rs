@@ -0,0 +1,163 @@
+use crate::crypto::AesBlockCipher;
+use crate::errors::CodedError;
+
+/// OCB 认证标签的长度（只支持 128 位标签）
+pub const TAG_LEN: usize = 16;
+/// nonce 最长 120 位，OpenPGP 使用 15 字节
+pub const MAX_NONCE_LEN: usize = 15;
+/// 预先计算的 L_i 个数，足够处理 2^32 个分组
+const L_TABLE_LEN: usize = 32;
+
+/// 加密一个以大端整数表示的分组
+fn encrypt(cipher: &AesBlockCipher, block: u128) -> u128 {
+    let mut bytes = block.to_be_bytes();
+    cipher.encrypt_block(&mut bytes);
+    u128::from_be_bytes(bytes)
+}
+
+/// GF(2^128) 中乘以 2（大端，按 x^128 + x^7 + x^2 + x + 1 约简）
+fn double(block: u128) -> u128 {
+    (block << 1) ^ if block >> 127 == 1 { 0x87 } else { 0 }
+}
+
+/// 把不足一个分组的数据按 `数据 || 1 || 0...` 填充为一个分组
+fn pad_block(data: &[u8]) -> u128 {
+    let mut block = [0u8; 16];
+    block[..data.len()].copy_from_slice(data);
+    block[data.len()] = 0x80;
+    u128::from_be_bytes(block)
+}
+
+fn read_block(data: &[u8]) -> u128 {
+    u128::from_be_bytes(data.try_into().unwrap())
+}
+
+/// AES-OCB（RFC 7253，OCB3），支持 128/192/256 位密钥、1 到 15 字节的 nonce 和 128 位标签；只实现加密
+pub struct AesOcb {
+    cipher: AesBlockCipher,
+    l_star: u128,
+    l_dollar: u128,
+    l: [u128; L_TABLE_LEN],
+}
+
+impl AesOcb {
+    pub fn new(key: &[u8]) -> Result<Self, CodedError> {
+        let cipher = AesBlockCipher::new(key).ok_or_else(|| CodedError::invalid_key_length(format!("AES-OCB key must be 16, 24 or 32 bytes, got {}", key.len())))?;
+        let l_star = encrypt(&cipher, 0);
+        let l_dollar = double(l_star);
+        let mut l = [0u128; L_TABLE_LEN];
+        l[0] = double(l_dollar);
+        for index in 1..L_TABLE_LEN {
+            l[index] = double(l[index - 1]);
+        }
+        Ok(AesOcb { cipher, l_star, l_dollar, l })
+    }
+
+    /// 第 i 个分组（从 1 开始）的偏移增量 L_{ntz(i)}
+    fn l_at(&self, index: u64) -> u128 {
+        self.l[index.trailing_zeros() as usize]
+    }
+
+    /// 由 nonce 得到初始偏移 Offset_0
+    fn initial_offset(&self, nonce: &[u8]) -> u128 {
+        // Nonce = 0^7（128 位标签）|| 0* || 1 || N，共 128 位
+        let mut block = [0u8; 16];
+        block[16 - nonce.len()..].copy_from_slice(nonce);
+        block[15 - nonce.len()] |= 1;
+        let full = u128::from_be_bytes(block);
+        let bottom = (full & 0x3f) as u32;
+        let ktop = encrypt(&self.cipher, full & !0x3f);
+        // Stretch = Ktop || (Ktop[1..64] xor Ktop[9..72])，取第 bottom 位开始的 128 位
+        let stretch_tail = ((ktop >> 64) as u64) ^ ((ktop >> 56) as u64);
+        if bottom == 0 {
+            ktop
+        } else {
+            (ktop << bottom) | (stretch_tail >> (64 - bottom)) as u128
+        }
+    }
+
+    /// 附加认证数据的摘要 HASH(K, A)
+    fn hash(&self, aad: &[u8]) -> u128 {
+        let mut sum = 0u128;
+        let mut offset = 0u128;
+        let mut blocks = aad.chunks_exact(16);
+        for (index, block) in (&mut blocks).enumerate() {
+            offset ^= self.l_at(index as u64 + 1);
+            sum ^= encrypt(&self.cipher, read_block(block) ^ offset);
+        }
+        let rest = blocks.remainder();
+        if !rest.is_empty() {
+            offset ^= self.l_star;
+            sum ^= encrypt(&self.cipher, pad_block(rest) ^ offset);
+        }
+        sum
+    }
+
+    /// 加密并在密文后附加认证标签
+    pub fn seal(&self, nonce: &[u8], aad: &[u8], plaintext: &[u8]) -> Result<Vec<u8>, CodedError> {
+        if nonce.is_empty() || nonce.len() > MAX_NONCE_LEN {
+            return Err(CodedError::invalid_argument(format!("AES-OCB nonce must be 1 to {} bytes, got {}", MAX_NONCE_LEN, nonce.len())));
+        }
+        let mut output = Vec::with_capacity(plaintext.len() + TAG_LEN);
+        let mut offset = self.initial_offset(nonce);
+        let mut checksum = 0u128;
+        let mut blocks = plaintext.chunks_exact(16);
+        for (index, block) in (&mut blocks).enumerate() {
+            let block = read_block(block);
+            offset ^= self.l_at(index as u64 + 1);
+            output.extend_from_slice(&(offset ^ encrypt(&self.cipher, block ^ offset)).to_be_bytes());
+            checksum ^= block;
+        }
+        let rest = blocks.remainder();
+        if !rest.is_empty() {
+            offset ^= self.l_star;
+            let pad = encrypt(&self.cipher, offset).to_be_bytes();
+            output.extend(rest.iter().zip(pad).map(|(byte, pad)| byte ^ pad));
+            checksum ^= pad_block(rest);
+        }
+        let tag = encrypt(&self.cipher, checksum ^ offset ^ self.l_dollar) ^ self.hash(aad);
+        output.extend_from_slice(&tag.to_be_bytes());
+        Ok(output)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// RFC 7253 附录 A 的示例（K = 000102...0F，A 与 P 相同）
+    #[test]
+    fn matches_rfc7253_sample_results() {
+        let ocb = AesOcb::new(&(0..16).collect::<Vec<u8>>()).unwrap();
+        let samples = [
+            (0, "785407BFFFC8AD9EDCC5520AC9111EE6"),
+            (8, "6820B3657B6F615A5725BDA0D3B4EB3A257C9AF1F8F03009"),
+            (16, "C050A7E919AA5643BFF595B66ACC106CF6F36BC1053EA4812AB98C31120932FC"),
+            (24, "1591E0EC9E6FC5A83475F939906EB53E5E93E9CFEEEC495F95B89CFEA9E27AC5E3EC223396F16AD3"),
+            (32, "571D535B60B277188BE5147170A9A22CDB9EF96F538354AF0E6E5D7F6F640AF81713D65F5D9422DBD4694329D30A23A6"),
+            (40, "9FFD50F147694CDE9654EC6E7CE7D40ACD5419AB0F4CD109F77A722A525E68F9EB8F90266C3ABC8C71C16FFBDE76825481F23FA8B1BEF99E"),
+        ];
+        for (index, (len, expected)) in samples.into_iter().enumerate() {
+            let nonce = hex::decode(format!("BBAA998877665544332211{:02X}", index)).unwrap();
+            let data: Vec<u8> = (0..len).collect();
+            assert_eq!(ocb.seal(&nonce, &data, &data).unwrap(), hex::decode(expected).unwrap());
+        }
+    }
+
+    /// RFC 7253 附录 A 中覆盖各种长度的迭代测试（128 位标签）
+    #[test]
+    fn matches_rfc7253_iterated_result() {
+        let mut key = [0u8; 16];
+        key[15] = 128;
+        let ocb = AesOcb::new(&key).unwrap();
+        let nonce = |n: u32| [[0u8; 8].as_slice(), &n.to_be_bytes()].concat();
+        let mut ciphertext = Vec::new();
+        for index in 0..128u32 {
+            let s = vec![0u8; index as usize];
+            ciphertext.extend(ocb.seal(&nonce(3 * index + 1), &s, &s).unwrap());
+            ciphertext.extend(ocb.seal(&nonce(3 * index + 2), &[], &s).unwrap());
+            ciphertext.extend(ocb.seal(&nonce(3 * index + 3), &s, &[]).unwrap());
+        }
+        assert_eq!(ocb.seal(&nonce(385), &ciphertext, &[]).unwrap(), hex::decode("67E944D23256C5E0B6C61FA22FDF1EA2").unwrap());
+    }
+}
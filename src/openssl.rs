@@ -0,0 +1,305 @@
+use std::io::{BufWriter, Read, Write};
+
+use aes::Aes256;
+use cbc::cipher::block_padding::{NoPadding, Pkcs7};
+use cbc::cipher::{BlockDecryptMut, BlockEncryptMut, KeyIvInit};
+use md5::{Digest, Md5};
+use rand::RngCore;
+use sha2::{Sha256, Sha512};
+
+use crate::errors::{open_error, read_error, write_error, CodedError};
+use crate::fd::{self, SyncOutput, Target};
+use crate::job::JobControl;
+use crate::pipeline;
+
+type Aes256CbcEnc = cbc::Encryptor<Aes256>;
+type Aes256CbcDec = cbc::Decryptor<Aes256>;
+
+/// `openssl enc` 加盐输出的文件头：8 字节魔数 + 8 字节盐
+pub const OPENSSL_MAGIC: &[u8] = b"Salted__";
+const SALT_LEN: usize = 8;
+const KEY_LEN: usize = 32;
+const IV_LEN: usize = 16;
+const BLOCK_LEN: usize = 16;
+
+/// `openssl enc -pbkdf2` 默认的迭代次数
+pub const DEFAULT_PBKDF2_ITERATIONS: u32 = 10000;
+
+/// 复制数据时每次读取的长度（分组长度的整数倍），每读完一块检查一次超时并发送 chunkDone 事件
+const COPY_BUFFER_LEN: usize = 1024 * 1024;
+
+/// 派生密钥使用的摘要算法，对应 `openssl enc -md`
+#[derive(Clone, Copy, PartialEq)]
+pub enum KdfDigest {
+    Md5,
+    Sha256,
+    Sha512,
+}
+
+impl KdfDigest {
+    pub fn parse(name: &str) -> Result<Self, CodedError> {
+        match name.to_ascii_lowercase().as_str() {
+            "md5" => Ok(KdfDigest::Md5),
+            "sha256" => Ok(KdfDigest::Sha256),
+            "sha512" => Ok(KdfDigest::Sha512),
+            _ => Err(CodedError::unsupported(format!("Unsupported OpenSSL digest: {}, supported: md5, sha256, sha512", name))),
+        }
+    }
+
+    /// 以该摘要算法的 HMAC 运行 PBKDF2，填满 output
+    pub fn pbkdf2(&self, passphrase: &[u8], salt: &[u8], iterations: u32, output: &mut [u8]) -> Result<(), CodedError> {
+        if iterations == 0 {
+            return Err(CodedError::invalid_argument("PBKDF2 iterations must be at least 1"));
+        }
+        match self {
+            KdfDigest::Md5 => pbkdf2::pbkdf2_hmac::<Md5>(passphrase, salt, iterations, output),
+            KdfDigest::Sha256 => pbkdf2::pbkdf2_hmac::<Sha256>(passphrase, salt, iterations, output),
+            KdfDigest::Sha512 => pbkdf2::pbkdf2_hmac::<Sha512>(passphrase, salt, iterations, output),
+        }
+        Ok(())
+    }
+
+    fn hash(&self, parts: &[&[u8]]) -> Vec<u8> {
+        fn run<D: Digest>(parts: &[&[u8]]) -> Vec<u8> {
+            let mut hasher = D::new();
+            for part in parts {
+                hasher.update(part);
+            }
+            hasher.finalize().to_vec()
+        }
+        match self {
+            KdfDigest::Md5 => run::<Md5>(parts),
+            KdfDigest::Sha256 => run::<Sha256>(parts),
+            KdfDigest::Sha512 => run::<Sha512>(parts),
+        }
+    }
+}
+
+/// 由口令派生密钥和 IV 的方式：`EVP_BytesToKey`（`openssl enc` 不带 `-pbkdf2` 时）或 PBKDF2（`-pbkdf2 -iter`）
+#[derive(Clone, Copy)]
+pub enum Kdf {
+    BytesToKey(KdfDigest),
+    Pbkdf2 { digest: KdfDigest, iterations: u32 },
+}
+
+impl Kdf {
+    /// 派生 AES-256 密钥和 IV
+    fn derive(&self, passphrase: &[u8], salt: &[u8]) -> Result<([u8; KEY_LEN], [u8; IV_LEN]), CodedError> {
+        let mut material = [0u8; KEY_LEN + IV_LEN];
+        match *self {
+            Kdf::BytesToKey(digest) => {
+                // D_i = H(D_{i-1} || 口令 || 盐)，迭代次数固定为 1，与 openssl enc 相同
+                let mut filled = 0;
+                let mut previous = Vec::new();
+                while filled < material.len() {
+                    previous = digest.hash(&[&previous, passphrase, salt]);
+                    let take = previous.len().min(material.len() - filled);
+                    material[filled..filled + take].copy_from_slice(&previous[..take]);
+                    filled += take;
+                }
+            }
+            Kdf::Pbkdf2 { digest, iterations } => digest.pbkdf2(passphrase, salt, iterations, &mut material)?,
+        }
+        let mut key = [0u8; KEY_LEN];
+        let mut iv = [0u8; IV_LEN];
+        key.copy_from_slice(&material[..KEY_LEN]);
+        iv.copy_from_slice(&material[KEY_LEN..]);
+        Ok((key, iv))
+    }
+}
+
+/// 加密或解密 OpenSSL 文件的统计
+pub struct OpensslReport {
+    pub plaintext_size: u64,
+    pub encrypted_size: u64,
+}
+
+/// 以 `openssl enc -aes-256-cbc -salt` 的格式加密文件，输出可以用相同参数的 `openssl enc -d` 解密
+pub fn encrypt_file(input_path: &Target, output_path: &Target, passphrase: &[u8], kdf: Kdf, control: &JobControl) -> Result<OpensslReport, CodedError> {
+    let mut salt = [0u8; SALT_LEN];
+    rand::thread_rng().fill_bytes(&mut salt);
+    let (key, mut iv) = kdf.derive(passphrase, &salt)?;
+
+    let mut input = fd::open(input_path).map_err(|e| open_error("opening input file", e))?;
+    let output = fd::create(output_path).map_err(|e| open_error("creating output file", e))?;
+    let mut writer = BufWriter::new(output);
+    writer
+        .write_all(OPENSSL_MAGIC)
+        .and_then(|_| writer.write_all(&salt))
+        .map_err(|e| write_error("writing output file", e))?;
+
+    // 整块部分逐段以 CBC 加密，下一段的 IV 为上一段最后一个密文分组；最后不足一块的部分连同 PKCS#7 填充一起加密
+    let mut buffer = vec![0u8; COPY_BUFFER_LEN];
+    let mut plaintext_size = 0u64;
+    let mut index = 0u32;
+    let tail = loop {
+        control.check()?;
+        let read = pipeline::read_full(&mut input, &mut buffer).map_err(|err| read_error("reading input file", err))?;
+        plaintext_size += read as u64;
+        if read < buffer.len() {
+            break read;
+        }
+        let cipher = Aes256CbcEnc::new_from_slices(&key, &iv)
+            .map_err(|e| CodedError::unknown(format!("AES cipher init failed: {:?}", e)))?;
+        cipher
+            .encrypt_padded_mut::<NoPadding>(&mut buffer, read)
+            .map_err(|e| CodedError::unknown(format!("AES-256-CBC encryption failed: {:?}", e)))?;
+        iv.copy_from_slice(&buffer[read - BLOCK_LEN..read]);
+        writer
+            .write_all(&buffer[..read])
+            .map_err(|e| write_error("writing output file", e))?;
+        index += 1;
+        control.chunk_done(index, read);
+    };
+
+    let whole = tail - tail % BLOCK_LEN;
+    if whole > 0 {
+        let cipher = Aes256CbcEnc::new_from_slices(&key, &iv)
+            .map_err(|e| CodedError::unknown(format!("AES cipher init failed: {:?}", e)))?;
+        cipher
+            .encrypt_padded_mut::<NoPadding>(&mut buffer, whole)
+            .map_err(|e| CodedError::unknown(format!("AES-256-CBC encryption failed: {:?}", e)))?;
+        iv.copy_from_slice(&buffer[whole - BLOCK_LEN..whole]);
+    }
+    let cipher = Aes256CbcEnc::new_from_slices(&key, &iv)
+        .map_err(|e| CodedError::unknown(format!("AES cipher init failed: {:?}", e)))?;
+    let last = cipher.encrypt_padded_vec_mut::<Pkcs7>(&buffer[whole..tail]);
+    writer
+        .write_all(&buffer[..whole])
+        .and_then(|_| writer.write_all(&last))
+        .map_err(|e| write_error("writing output file", e))?;
+    if tail > 0 {
+        index += 1;
+        control.chunk_done(index, tail);
+    }
+
+    let output = writer
+        .into_inner()
+        .map_err(|e| write_error("writing output file", e.into_error()))?;
+    output.sync_output().map_err(|e| write_error("flushing output file", e))?;
+    let encrypted_size = output
+        .metadata()
+        .map_err(|e| read_error("reading output file metadata", e))?
+        .len();
+    Ok(OpensslReport { plaintext_size, encrypted_size })
+}
+
+/// 解密 `Salted__` 文件头之后长度为 body_len 的密文并写入 writer，返回明文长度
+fn decrypt_body<R: Read, W: Write>(input: &mut R, writer: &mut W, body_len: u64, key: &[u8; KEY_LEN], mut iv: [u8; IV_LEN], check: impl Fn() -> Result<(), CodedError>, mut chunk_done: impl FnMut(u32, usize)) -> Result<u64, CodedError> {
+    // 最后一个分组留到最后解密并去除填充，之前的分组逐段解密，下一段的 IV 为上一段最后一个密文分组
+    let mut buffer = vec![0u8; COPY_BUFFER_LEN];
+    let mut remaining = body_len;
+    let mut plaintext_size = 0u64;
+    let mut index = 0u32;
+    while remaining > BLOCK_LEN as u64 {
+        check()?;
+        let want = (remaining - BLOCK_LEN as u64).min(buffer.len() as u64) as usize;
+        if pipeline::read_full(input, &mut buffer[..want]).map_err(|err| read_error("reading input file", err))? < want {
+            return Err(CodedError::truncated("Invalid OpenSSL file: unexpected end of file"));
+        }
+        let mut next_iv = [0u8; IV_LEN];
+        next_iv.copy_from_slice(&buffer[want - BLOCK_LEN..want]);
+        let cipher = Aes256CbcDec::new_from_slices(key, &iv)
+            .map_err(|e| CodedError::unknown(format!("AES cipher init failed: {:?}", e)))?;
+        cipher
+            .decrypt_padded_mut::<NoPadding>(&mut buffer[..want])
+            .map_err(|e| CodedError::auth_failed(format!("AES-256-CBC decryption failed: {:?}", e)))?;
+        iv = next_iv;
+        writer
+            .write_all(&buffer[..want])
+            .map_err(|e| write_error("writing output file", e))?;
+        remaining -= want as u64;
+        plaintext_size += want as u64;
+        index += 1;
+        chunk_done(index, want);
+    }
+
+    let mut last = [0u8; BLOCK_LEN];
+    if pipeline::read_full(input, &mut last).map_err(|err| read_error("reading input file", err))? < BLOCK_LEN {
+        return Err(CodedError::truncated("Invalid OpenSSL file: unexpected end of file"));
+    }
+    let cipher = Aes256CbcDec::new_from_slices(key, &iv)
+        .map_err(|e| CodedError::unknown(format!("AES cipher init failed: {:?}", e)))?;
+    let last = cipher
+        .decrypt_padded_vec_mut::<Pkcs7>(&last)
+        .map_err(|_| CodedError::auth_failed("OpenSSL decryption failed: bad decrypt (wrong passphrase or key derivation options)"))?;
+    writer
+        .write_all(&last)
+        .map_err(|e| write_error("writing output file", e))?;
+    plaintext_size += last.len() as u64;
+    Ok(plaintext_size)
+}
+
+/// 解密 `openssl enc -aes-256-cbc` 生成的加盐文件，kdf 必须与加密时的 `-md`、`-pbkdf2`、`-iter` 参数一致
+///
+/// 该格式没有认证：口令或参数错误通常表现为填充错误，但也可能解出错误的数据而不报错。
+pub fn decrypt_file(input_path: &Target, output_path: &Target, passphrase: &[u8], kdf: Kdf, control: &JobControl) -> Result<OpensslReport, CodedError> {
+    let mut input = fd::open(input_path).map_err(|e| open_error("opening input file", e))?;
+    let encrypted_size = input
+        .metadata()
+        .map_err(|e| read_error("reading input file metadata", e))?
+        .len();
+    let mut header = [0u8; OPENSSL_MAGIC.len() + SALT_LEN];
+    if pipeline::read_full(&mut input, &mut header).map_err(|err| read_error("reading input file", err))? < header.len() || !header.starts_with(OPENSSL_MAGIC) {
+        return Err(CodedError::bad_header("Not an OpenSSL salted file: missing Salted__ header"));
+    }
+    let body_len = encrypted_size - header.len() as u64;
+    if body_len == 0 || !body_len.is_multiple_of(BLOCK_LEN as u64) {
+        return Err(CodedError::bad_format(format!("Invalid OpenSSL file: ciphertext length {} is not a positive multiple of {}", body_len, BLOCK_LEN)));
+    }
+    let (key, iv) = kdf.derive(passphrase, &header[OPENSSL_MAGIC.len()..])?;
+
+    let output = fd::create(output_path).map_err(|e| open_error("creating output file", e))?;
+    let mut writer = BufWriter::new(output);
+    let plaintext_size = decrypt_body(&mut input, &mut writer, body_len, &key, iv, || control.check(), |index, len| control.chunk_done(index, len))?;
+
+    writer
+        .into_inner()
+        .map_err(|e| write_error("writing output file", e.into_error()))?
+        .sync_output()
+        .map_err(|e| write_error("flushing output file", e))?;
+    Ok(OpensslReport { plaintext_size, encrypted_size })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const PASSPHRASE: &[u8] = b"correct-horse";
+    const PLAINTEXT: &[u8] = b"zippy-encryptor OpenSSL enc interop test, more than three AES blocks long.\n";
+
+    fn decrypt(hex_file: &str, kdf: Kdf) -> Result<Vec<u8>, CodedError> {
+        let file = hex::decode(hex_file.trim()).unwrap();
+        assert!(file.starts_with(OPENSSL_MAGIC));
+        let (key, iv) = kdf.derive(PASSPHRASE, &file[OPENSSL_MAGIC.len()..OPENSSL_MAGIC.len() + SALT_LEN])?;
+        let body = &file[OPENSSL_MAGIC.len() + SALT_LEN..];
+        let mut plaintext = Vec::new();
+        decrypt_body(&mut &body[..], &mut plaintext, body.len() as u64, &key, iv, || Ok(()), |_, _| {})?;
+        Ok(plaintext)
+    }
+
+    #[test]
+    fn derives_openssl_enc_keys() {
+        // openssl enc -aes-256-cbc -pass pass:correct-horse -S 0102030405060708 -P，分别加 -md md5 / -pbkdf2 -iter 10000 -md sha256 / -pbkdf2 -iter 1000 -md sha512
+        let salt = hex::decode("0102030405060708").unwrap();
+        for (kdf, key, iv) in [
+            (Kdf::BytesToKey(KdfDigest::Md5), "e6e1d613d61fc644b1ddf76ebb33f5ea71e6c67ba50bf04f44a0d415c093b5b5", "9a24a62dc6709d3596873ca55444b3df"),
+            (Kdf::Pbkdf2 { digest: KdfDigest::Sha256, iterations: 10000 }, "31454b2be07bbc93a1743cc34737df75f1d6c2f7d381682eafe26c3a0854309e", "34e19d1fdeb2376a3ad2b1b18f362b93"),
+            (Kdf::Pbkdf2 { digest: KdfDigest::Sha512, iterations: 1000 }, "121829de50096432857719fe17be74052fc5d5a211835a4116c5d581f45b5274", "88c83423f79233731f402fd37a08a4f7"),
+        ] {
+            let (derived_key, derived_iv) = kdf.derive(PASSPHRASE, &salt).unwrap();
+            assert_eq!(hex::encode(derived_key), key);
+            assert_eq!(hex::encode(derived_iv), iv);
+        }
+    }
+
+    #[test]
+    fn decrypts_openssl_enc_files() {
+        // 由 OpenSSL 3 的 openssl enc -aes-256-cbc -salt 加密
+        let pbkdf2 = Kdf::Pbkdf2 { digest: KdfDigest::Sha256, iterations: DEFAULT_PBKDF2_ITERATIONS };
+        assert_eq!(decrypt(include_str!("../testdata/openssl/pbkdf2-sha256.hex"), pbkdf2).unwrap(), PLAINTEXT);
+        assert_eq!(decrypt(include_str!("../testdata/openssl/bytestokey-md5.hex"), Kdf::BytesToKey(KdfDigest::Md5)).unwrap(), PLAINTEXT);
+        let err = decrypt(include_str!("../testdata/openssl/pbkdf2-sha256.hex"), Kdf::BytesToKey(KdfDigest::Sha256)).unwrap_err();
+        assert_eq!(err.code, crate::errors::ErrorCode::AuthFailed);
+    }
+}
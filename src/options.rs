@@ -0,0 +1,664 @@
+use std::collections::HashMap;
+use napi::bindgen_prelude::*;
+use napi_derive::napi;
+
+/// 任意 JSON 值，用于接收 Buffer 以外的元数据
+pub struct JsonValue(pub serde_json::Value);
+
+impl TypeName for JsonValue {
+    fn type_name() -> &'static str {
+        "any"
+    }
+
+    fn value_type() -> ValueType {
+        ValueType::Unknown
+    }
+}
+
+impl FromNapiValue for JsonValue {
+    unsafe fn from_napi_value(env: sys::napi_env, napi_val: sys::napi_value) -> Result<Self> {
+        serde_json::Value::from_napi_value(env, napi_val).map(JsonValue)
+    }
+}
+
+impl ToNapiValue for JsonValue {
+    unsafe fn to_napi_value(env: sys::napi_env, val: Self) -> Result<sys::napi_value> {
+        serde_json::Value::to_napi_value(env, val.0)
+    }
+}
+
+impl ValidateNapiValue for JsonValue {
+    unsafe fn validate(_env: sys::napi_env, _napi_val: sys::napi_value) -> Result<sys::napi_value> {
+        Ok(std::ptr::null_mut())
+    }
+}
+
+/// 加密操作的可选参数
+#[napi(object)]
+#[derive(Default)]
+pub struct EncryptOptions {
+    /// 超时时间（毫秒），超时后中止操作并删除未完成的输出文件，0 或不设置表示不限制
+    pub timeout_ms: Option<u32>,
+    /// 为 false 时输出路径上已有文件则出错，不覆盖（默认为 true）
+    pub overwrite: Option<bool>,
+    /// 将输入文件名加密保存到文件头中
+    pub store_filename: Option<bool>,
+    /// 指定要保存的原始文件名（代替输入文件名），设置后隐含 storeFilename
+    pub filename: Option<String>,
+    /// 加密保存到文件头中的用户元数据，Buffer 原样保存，其它值按 JSON 保存
+    #[napi(ts_type = "Buffer | any")]
+    pub metadata: Option<Either<Buffer, JsonValue>>,
+    /// 明文的 MIME 类型，代替根据文件开头的字节自动检测的结果；加密保存到文件头中
+    pub mime_type: Option<String>,
+    /// 将输入文件的权限、修改和访问时间、所有者以及扩展属性加密保存到文件头中，解密时以 restoreAttributes 还原
+    pub preserve_attributes: Option<bool>,
+    /// 加密完成并校验输出文件后，以随机数据覆盖并删除输入文件；不能与文件描述符和分卷同时使用
+    pub shred_source: Option<bool>,
+    /// 输出旧格式（无容器文件头），供旧版本读取；不能与文件名和元数据选项同时使用
+    pub legacy_format: Option<bool>,
+    /// 明文摘要算法（"sha256" 或 "blake3"），摘要加密保存在文件中，解密时自动核对
+    #[napi(ts_type = "'sha256' | 'blake3'")]
+    pub plaintext_hash: Option<String>,
+    /// 加密前压缩明文（"zstd"、"gzip" 或 "lz4"），分片文件逐个分片压缩，算法记录在文件头中，解密时自动解压
+    #[napi(ts_type = "'zstd' | 'gzip' | 'lz4'")]
+    pub compression: Option<String>,
+    /// 压缩级别：zstd 为 1-22（默认 3），gzip 为 0-9（默认 6），lz4 不支持
+    pub compression_level: Option<i32>,
+    /// （仅 chunkEncryptFile）分卷大小（MB），输出切分为 `<outputPath>.001`、`.002`……，每个分卷不超过该大小
+    pub volume_size_mb: Option<u32>,
+    /// （仅 chunkEncryptFile）在文件末尾附加 Reed-Solomon 纠错数据，数值为冗余百分比（1-100），损坏的块可用 repairFile 恢复
+    pub parity_percent: Option<u32>,
+    /// （仅 chunkEncryptFile）全零的分片以空分片帧保存，跳过输入文件中的空洞，解密时在输出文件中还原空洞；全零区域的位置不加密
+    pub sparse: Option<bool>,
+    /// （仅 chunkEncryptFile）输出可追加的分片文件，之后可用 appendChunkedFile 在末尾追加数据；不记录明文摘要
+    pub appendable: Option<bool>,
+    /// （仅 chunkEncryptFile）可续传：加密过程中定期在 `<outputPath>.checkpoint` 记录检查点，中断后以相同参数重试时从最后一个完整的分片继续
+    pub resume: Option<bool>,
+    /// （仅 encryptFile 和 chunkEncryptFile）把输入文件映射到内存后直接读取，省去 read() 的复制；映射失败时改用普通读取。操作期间输入文件不能被截断
+    pub mmap: Option<bool>,
+    /// （仅 chunkEncryptFile）绕过页缓存读写输入和输出文件（O_DIRECT / F_NOCACHE / FILE_FLAG_NO_BUFFERING），文件系统不支持时改用普通读写；不能与 mmap 和分卷同时使用
+    pub direct_io: Option<bool>,
+}
+
+/// 解密操作的可选参数
+#[napi(object)]
+#[derive(Default)]
+pub struct DecryptOptions {
+    /// 超时时间（毫秒），超时后中止操作并删除未完成的输出文件，0 或不设置表示不限制
+    pub timeout_ms: Option<u32>,
+    /// 为 false 时输出路径上已有文件则出错，不覆盖（默认为 true）
+    pub overwrite: Option<bool>,
+    /// 恢复文件头中保存的原始文件名，此时输出路径视为目录
+    pub restore_filename: Option<bool>,
+    /// 把文件头中保存的文件属性（见 EncryptOptions.preserveAttributes）还原到输出文件；文件中没有保存时出错
+    pub restore_attributes: Option<bool>,
+    /// （仅 chunkDecryptFile）容错解密：无法读取或解密的分片不中止操作，而是以零填充并在结果中报告
+    pub tolerate_errors: Option<bool>,
+    /// （配合 tolerateErrors）跳过损坏的分片而不是以零填充，输出中不保留其位置
+    pub skip_damaged_chunks: Option<bool>,
+    /// （仅 chunkDecryptFile）绕过页缓存读写输入和输出文件，文件系统不支持时改用普通读写；不能与 tolerateErrors 同时使用
+    pub direct_io: Option<bool>,
+}
+
+/// 格式转换和重新分片的可选参数
+#[napi(object)]
+#[derive(Default)]
+pub struct ConvertOptions {
+    /// 超时时间（毫秒），超时后中止操作并删除未完成的输出文件，0 或不设置表示不限制
+    pub timeout_ms: Option<u32>,
+}
+
+/// verifyChunkedFile() 的可选参数
+#[napi(object)]
+#[derive(Default)]
+pub struct VerifyOptions {
+    /// 超时时间（毫秒），超时后中止校验，0 或不设置表示不限制
+    pub timeout_ms: Option<u32>,
+    /// 加密算法，v2 文件默认使用文件头中记录的算法，旧格式文件必须指定
+    pub algorithm: Option<String>,
+}
+
+/// DecryptedReader 和 ChunkedFileHandle 的可选参数
+#[napi(object)]
+#[derive(Default)]
+pub struct ReaderOptions {
+    /// 取用一个分片后在后台线程中预先解密之后的几个分片，0 或不设置表示不预取；最多 64
+    pub prefetch: Option<u32>,
+}
+
+/// storeFile() 的可选参数
+#[napi(object)]
+#[derive(Default)]
+pub struct StoreOptions {
+    /// 超时时间（毫秒），超时后中止操作，0 或不设置表示不限制；已写入的对象会保留，供下次复用
+    pub timeout_ms: Option<u32>,
+    /// 清单中记录的文件名称，默认为输入文件名；同名文件会被替换
+    pub name: Option<String>,
+    /// 对象加密前的压缩算法（"zstd"、"gzip" 或 "lz4"）
+    #[napi(ts_type = "'zstd' | 'gzip' | 'lz4'")]
+    pub compression: Option<String>,
+    /// 压缩级别，与 EncryptOptions.compressionLevel 相同
+    pub compression_level: Option<i32>,
+}
+
+/// restoreFile() 的可选参数
+#[napi(object)]
+#[derive(Default)]
+pub struct RestoreOptions {
+    /// 超时时间（毫秒），超时后中止操作并删除未完成的输出文件，0 或不设置表示不限制
+    pub timeout_ms: Option<u32>,
+}
+
+/// ageEncryptFile() 的参数，passphrase 和 recipients 二选一
+#[napi(object)]
+#[derive(Default)]
+pub struct AgeEncryptOptions {
+    /// 超时时间（毫秒），超时后中止操作并删除未完成的输出文件，0 或不设置表示不限制
+    pub timeout_ms: Option<u32>,
+    /// 口令，使用 scrypt 派生文件密钥
+    pub passphrase: Option<String>,
+    /// X25519 接收者（`age1...`），任意一个接收者的身份都能解密
+    pub recipients: Option<Vec<String>>,
+    /// 输出 ASCII 封装（PEM 风格）的文本文件
+    pub armor: Option<bool>,
+}
+
+/// ageDecryptFile() 的参数，口令加密的文件需要 passphrase，其它文件需要 identities
+#[napi(object)]
+#[derive(Default)]
+pub struct AgeDecryptOptions {
+    /// 超时时间（毫秒），超时后中止操作并删除未完成的输出文件，0 或不设置表示不限制
+    pub timeout_ms: Option<u32>,
+    /// 口令
+    pub passphrase: Option<String>,
+    /// X25519 身份（`AGE-SECRET-KEY-1...`），也可以是身份文件的内容
+    pub identities: Option<Vec<String>>,
+}
+
+/// opensslEncryptFile() / opensslDecryptFile() 的参数，解密时必须与加密时的 `openssl enc` 参数一致
+#[napi(object)]
+#[derive(Default)]
+pub struct OpensslOptions {
+    /// 超时时间（毫秒），超时后中止操作并删除未完成的输出文件，0 或不设置表示不限制
+    pub timeout_ms: Option<u32>,
+    /// 使用 PBKDF2 派生密钥（`-pbkdf2`），默认为 true；false 时使用 `EVP_BytesToKey`
+    pub pbkdf2: Option<bool>,
+    /// PBKDF2 迭代次数（`-iter`），默认为 10000
+    pub iterations: Option<u32>,
+    /// 派生密钥的摘要算法（`-md`）：md5、sha256 或 sha512，默认为 sha256
+    pub digest: Option<String>,
+}
+
+/// deriveKey() 的参数，默认值与使用口令的文件接口相同
+#[napi(object)]
+#[derive(Default)]
+pub struct KdfOptions {
+    /// 派生算法："scrypt"（默认）或 "pbkdf2"
+    #[napi(ts_type = "'scrypt' | 'pbkdf2'")]
+    pub algorithm: Option<String>,
+    /// 派生的密钥长度（字节），默认为 32
+    pub key_length: Option<u32>,
+    /// scrypt 的成本参数 log2(N)，默认为 16，与 gocryptfsEncryptDirectory() 相同
+    pub log_n: Option<u32>,
+    /// scrypt 的块大小参数 r，默认为 8
+    pub r: Option<u32>,
+    /// scrypt 的并行度参数 p，默认为 1
+    pub p: Option<u32>,
+    /// PBKDF2 迭代次数，默认为 10000，与 opensslEncryptFile() 相同
+    pub iterations: Option<u32>,
+    /// PBKDF2 的摘要算法：md5、sha256 或 sha512，默认为 sha256
+    pub digest: Option<String>,
+}
+
+/// gpgDecryptFile() 的参数
+#[napi(object)]
+#[derive(Default)]
+pub struct GpgDecryptOptions {
+    /// 超时时间（毫秒），超时后中止操作并删除未完成的输出文件，0 或不设置表示不限制
+    pub timeout_ms: Option<u32>,
+}
+
+/// gpgEncryptFile() 的参数
+#[napi(object)]
+#[derive(Default)]
+pub struct GpgEncryptOptions {
+    /// 超时时间（毫秒），超时后中止操作并删除未完成的输出文件，0 或不设置表示不限制
+    pub timeout_ms: Option<u32>,
+    /// 消息格式：rfc9580（默认，v6 SKESK + v2 SEIPD，Sequoia 等实现可以解密）或 librepgp（OCB 加密数据包，GnuPG 2.3 及以上版本可以解密）
+    pub profile: Option<String>,
+    /// 对称算法：aes128、aes192 或 aes256（默认）
+    pub cipher: Option<String>,
+    /// AEAD 算法：ocb（默认）或 gcm（只用于 rfc9580）
+    pub aead: Option<String>,
+}
+
+/// encryptToZip() 的参数
+#[napi(object)]
+#[derive(Default)]
+pub struct ZipOptions {
+    /// 超时时间（毫秒），超时后中止操作并删除未完成的输出文件，0 或不设置表示不限制
+    pub timeout_ms: Option<u32>,
+    /// 加密前用 Deflate 压缩，默认为 true；内容已经压缩时可以设为 false
+    pub compress: Option<bool>,
+}
+
+/// create7zArchive() 的参数
+#[napi(object)]
+#[derive(Default)]
+pub struct SevenZipOptions {
+    /// 超时时间（毫秒），超时后中止操作并删除未完成的输出文件，0 或不设置表示不限制
+    pub timeout_ms: Option<u32>,
+    /// 加密前用 LZMA2 压缩，默认为 true；内容已经压缩时可以设为 false
+    pub compress: Option<bool>,
+}
+
+/// extract7zArchive() 的参数
+#[napi(object)]
+#[derive(Default)]
+pub struct ExtractOptions {
+    /// 超时时间（毫秒），超时后中止操作并删除已解压的文件，0 或不设置表示不限制
+    pub timeout_ms: Option<u32>,
+}
+
+/// secretstreamEncryptFile() / secretstreamDecryptFile() 的参数
+#[napi(object)]
+#[derive(Default)]
+pub struct SecretStreamOptions {
+    /// 超时时间（毫秒），超时后中止操作并删除未完成的输出文件，0 或不设置表示不限制
+    pub timeout_ms: Option<u32>,
+    /// 每条消息的明文长度（字节），默认为 4096（与 libsodium 文档中的示例相同）；解密时必须与加密时相同
+    pub chunk_size: Option<u32>,
+}
+
+/// tinkEncryptFile() / tinkDecryptFile() 的参数，必须与 Tink 密钥的 AesGcmHkdfStreamingParams 一致
+#[napi(object)]
+#[derive(Default)]
+pub struct TinkStreamingOptions {
+    /// 超时时间（毫秒），超时后中止操作并删除未完成的输出文件，0 或不设置表示不限制
+    pub timeout_ms: Option<u32>,
+    /// 附加数据（Tink 的 associatedData），加密和解密时必须相同，默认为空
+    pub associated_data: Option<Buffer>,
+    /// 密文分段长度（ciphertext_segment_size），默认为 4096，对应 AES128/AES256_GCM_HKDF_4KB 模板
+    pub segment_size: Option<u32>,
+    /// 派生密钥长度（derived_key_size），16 或 32，默认为密钥长度不小于 32 字节时 32，否则 16
+    pub derived_key_size: Option<u32>,
+    /// HKDF 摘要算法（hkdf_hash_type）："sha1"、"sha256"（默认）或 "sha512"
+    pub hkdf_hash: Option<String>,
+}
+
+/// fernetDecrypt() 的参数
+#[napi(object)]
+#[derive(Default)]
+pub struct FernetDecryptOptions {
+    /// 令牌的最长有效期（秒），超过时报错；不设置表示不检查令牌时间，与 Python 的 `decrypt(token, ttl)` 相同
+    pub ttl: Option<u32>,
+}
+
+/// pasetoEncrypt() / pasetoDecrypt() 的参数
+#[napi(object)]
+#[derive(Default)]
+pub struct PasetoOptions {
+    /// 加密时附在令牌末尾的 footer（明文，受认证保护）；解密时为期望的 footer，令牌的 footer 不同时报错
+    #[napi(ts_type = "Buffer | string")]
+    pub footer: Option<Either<Buffer, String>>,
+    /// 隐式断言，不写入令牌，解密时必须提供与加密时相同的值
+    #[napi(ts_type = "Buffer | string")]
+    pub implicit_assertion: Option<Either<Buffer, String>>,
+}
+
+/// encryptJwe() 的参数
+#[napi(object)]
+#[derive(Default)]
+pub struct JweEncryptOptions {
+    /// 密钥管理算法："dir"、"A128KW"、"A192KW"、"A256KW"、"RSA-OAEP" 或 "RSA-OAEP-256"
+    pub alg: String,
+    /// 内容加密算法："A128GCM"、"A192GCM" 或 "A256GCM"（默认）
+    pub enc: Option<String>,
+    /// 写入受保护头部的密钥 ID
+    pub kid: Option<String>,
+    /// 写入受保护头部的 typ，例如 "JWT"
+    pub typ: Option<String>,
+    /// 写入受保护头部的 cty，嵌套 JWT 时为 "JWT"
+    pub cty: Option<String>,
+    /// 其它写入受保护头部的参数
+    #[napi(ts_type = "Record<string, any>")]
+    pub header: Option<JsonValue>,
+}
+
+/// cmsEncryptFile() 的参数
+#[napi(object)]
+#[derive(Default)]
+pub struct CmsEncryptOptions {
+    /// 超时时间（毫秒），超时后中止操作并删除未完成的输出文件，0 或不设置表示不限制
+    pub timeout_ms: Option<u32>,
+    /// 内容密钥的传输方式：pkcs1（默认，与 `openssl cms -encrypt` 相同）、oaep-sha1 或 oaep-sha256
+    pub key_transport: Option<String>,
+    /// 内容加密算法：aes-128-cbc、aes-192-cbc 或 aes-256-cbc（默认）
+    pub content_encryption: Option<String>,
+    /// 输出编码：der（默认）或 pem
+    pub format: Option<String>,
+}
+
+/// generateMinisignKeyPair() 的参数
+#[napi(object)]
+#[derive(Default)]
+pub struct MinisignKeyOptions {
+    /// 加密私钥的口令（scrypt，与 minisign 默认参数相同，约需 1 GiB 内存）；不设置时私钥不加密，与 `minisign -G -W` 相同
+    pub password: Option<String>,
+}
+
+/// minisignSignFile() 的参数
+#[napi(object)]
+#[derive(Default)]
+pub struct MinisignSignOptions {
+    /// 超时时间（毫秒），超时后中止签名，0 或不设置表示不限制
+    pub timeout_ms: Option<u32>,
+    /// 加密私钥的口令
+    pub password: Option<String>,
+    /// 受签名保护的注释，默认为时间戳和文件名
+    pub trusted_comment: Option<String>,
+    /// 不受保护的注释，默认为 "signature from minisign secret key"
+    pub untrusted_comment: Option<String>,
+    /// 签名文件的路径，默认为输入文件路径加 ".minisig"；输入为文件描述符时必须指定
+    pub signature_path: Option<String>,
+}
+
+/// minisignVerifyFile() 的参数
+#[napi(object)]
+#[derive(Default)]
+pub struct MinisignVerifyOptions {
+    /// 超时时间（毫秒），超时后中止验证，0 或不设置表示不限制
+    pub timeout_ms: Option<u32>,
+    /// 签名文件的路径，默认为输入文件路径加 ".minisig"；输入为文件描述符时必须指定
+    pub signature_path: Option<String>,
+}
+
+/// saltpackEncryptFile() 的参数
+#[napi(object)]
+#[derive(Default)]
+pub struct SaltpackEncryptOptions {
+    /// 超时时间（毫秒），超时后中止操作并删除未完成的输出文件，0 或不设置表示不限制
+    pub timeout_ms: Option<u32>,
+    /// 接收者的 Curve25519 公钥（32 字节），任意一个接收者的私钥都能解密
+    pub recipients: Vec<Buffer>,
+    /// 发送者的 Curve25519 私钥（32 字节），接收者解密时可以得知发送者公钥；不设置时匿名发送
+    pub sender_secret_key: Option<Buffer>,
+    /// 不在头部写出接收者公钥，解密方需要逐个尝试
+    pub hide_recipients: Option<bool>,
+    /// 输出 base62 文本封装（`BEGIN SALTPACK ENCRYPTED MESSAGE.`），默认为二进制
+    pub armor: Option<bool>,
+}
+
+/// saltpackDecryptFile() 的参数
+#[napi(object)]
+#[derive(Default)]
+pub struct SaltpackDecryptOptions {
+    /// 超时时间（毫秒），超时后中止操作并删除未完成的输出文件，0 或不设置表示不限制
+    pub timeout_ms: Option<u32>,
+    /// 接收者的 Curve25519 私钥（32 字节）
+    pub secret_key: Buffer,
+}
+
+/// hlsEncryptSegment() 的参数，sequence 和 iv 二选一
+#[napi(object)]
+#[derive(Default)]
+pub struct HlsSegmentOptions {
+    /// 超时时间（毫秒），超时后中止操作并删除未完成的输出文件，0 或不设置表示不限制
+    pub timeout_ms: Option<u32>,
+    /// 分段的媒体序号（`#EXT-X-MEDIA-SEQUENCE` 加分段在播放列表中的位置），作为 IV 使用，`#EXT-X-KEY` 不写 IV 属性
+    pub sequence: Option<u32>,
+    /// 显式的 16 字节 IV，写入 `#EXT-X-KEY` 的 IV 属性
+    pub iv: Option<Buffer>,
+    /// `#EXT-X-KEY` 的 URI 属性，默认为 keyPath 的文件名
+    pub key_uri: Option<String>,
+    /// 写出密钥文件（16 字节原始密钥）的路径，不设置时不写密钥文件
+    pub key_path: Option<String>,
+}
+
+/// cencEncryptFile() 的参数
+#[napi(object)]
+#[derive(Default)]
+pub struct CencEncryptOptions {
+    /// 超时时间（毫秒），超时后中止操作并删除未完成的输出文件，0 或不设置表示不限制
+    pub timeout_ms: Option<u32>,
+    /// 保护方案：cenc（AES-CTR，默认）或 cbcs（AES-CBC 模式加密，Safari / FairPlay 兼容的方案）
+    pub scheme: Option<String>,
+    /// cenc 的起始样本 IV（8 字节）或 cbcs 的常量 IV（16 字节），默认随机生成
+    pub iv: Option<Buffer>,
+    /// 在 moov 中加入 W3C Clear Key 的 pssh，默认为 true
+    pub clear_key_pssh: Option<bool>,
+}
+
+/// rclone crypt 相关函数的参数，与 rclone.conf 中 crypt 远端的配置项对应
+#[napi(object)]
+#[derive(Default)]
+pub struct RcloneCryptOptions {
+    /// 超时时间（毫秒），超时后中止操作并删除未完成的输出文件，0 或不设置表示不限制；只用于文件加解密
+    pub timeout_ms: Option<u32>,
+    /// 口令，对应 `password`（rclone.conf 中保存的是混淆后的值，需要先用 `rclone reveal` 还原）
+    pub password: String,
+    /// 盐口令，对应 `password2`，不设置时使用 rclone 的默认盐
+    pub salt: Option<String>,
+    /// 文件名加密方式：standard（默认）、obfuscate 或 off，对应 `filename_encryption`
+    pub filename_encryption: Option<String>,
+    /// 是否加密目录名，默认为 true，对应 `directory_name_encryption`
+    pub directory_name_encryption: Option<bool>,
+    /// 标准文件名加密的编码：base32（默认）或 base64，对应 `filename_encoding`
+    pub filename_encoding: Option<String>,
+}
+
+/// gocryptfs 相关函数的参数
+#[napi(object)]
+#[derive(Default)]
+pub struct GocryptfsOptions {
+    /// 超时时间（毫秒），超时后中止操作并删除已写入的文件，0 或不设置表示不限制；只用于加密目录
+    pub timeout_ms: Option<u32>,
+    /// 文件系统口令；加密目录时必须设置，路径转换时可以用 masterKey 代替
+    pub password: Option<String>,
+    /// 32 字节主密钥（gocryptfsEncryptDirectory() 的返回值），设置后路径转换不再读取 gocryptfs.conf 和运行 scrypt
+    pub master_key: Option<Buffer>,
+    /// 加密目录时 scrypt 的成本参数 log2(N)，默认为 16，与 `gocryptfs -init -scryptn` 相同
+    pub scrypt_log_n: Option<u32>,
+}
+
+/// s3EncryptFile() 的参数
+#[napi(object)]
+#[derive(Default)]
+pub struct S3EncryptOptions {
+    /// 超时时间（毫秒），超时后中止操作并删除未完成的输出文件，0 或不设置表示不限制
+    pub timeout_ms: Option<u32>,
+    /// 材料描述（x-amz-matdesc），解密时官方客户端用它选择主密钥，默认为空对象
+    pub material_description: Option<HashMap<String, String>>,
+    /// 指令文件路径，设置后把加密元数据以 JSON 写入该文件（对应 S3 上的 `<key>.instruction` 对象）
+    pub instruction_path: Option<String>,
+}
+
+/// s3DecryptFile() 的参数
+#[napi(object)]
+#[derive(Default)]
+pub struct S3DecryptOptions {
+    /// 超时时间（毫秒），超时后中止操作并删除未完成的输出文件，0 或不设置表示不限制
+    pub timeout_ms: Option<u32>,
+}
+
+/// veracryptListFiles() / veracryptExtractFiles() 的参数
+#[napi(object)]
+#[derive(Default)]
+pub struct VeraCryptOptions {
+    /// 超时时间（毫秒），超时后中止操作并删除已解压的文件，0 或不设置表示不限制；只用于解压
+    pub timeout_ms: Option<u32>,
+    /// 创建卷时设置的 PIM，0 或不设置表示使用默认迭代次数
+    pub pim: Option<u32>,
+    /// 卷头密钥派生的哈希算法：sha512 或 sha256，不设置时依次尝试
+    pub hash: Option<String>,
+}
+
+/// encryptFiles() 中的一个文件
+#[napi(object)]
+pub struct BatchEncryptEntry {
+    pub input_path: String,
+    /// 不设置时按 BatchOptions.outputTemplate 生成
+    pub output_path: Option<String>,
+    /// 该文件的加密选项，其中的 timeoutMs 不起作用，整批的超时由 BatchOptions.timeoutMs 指定
+    pub options: Option<EncryptOptions>,
+}
+
+/// decryptFiles() 中的一个文件
+#[napi(object)]
+pub struct BatchDecryptEntry {
+    pub input_path: String,
+    /// 不设置时按 BatchOptions.outputTemplate 生成；restoreFilename 时为输出目录
+    pub output_path: Option<String>,
+    /// 该文件的解密选项，其中的 timeoutMs 不起作用，整批的超时由 BatchOptions.timeoutMs 指定
+    pub options: Option<DecryptOptions>,
+}
+
+/// encryptFiles() / decryptFiles() 的可选参数
+#[napi(object)]
+#[derive(Default)]
+pub struct BatchOptions {
+    /// 整批的超时时间（毫秒），超时后不再开始新的文件并以 ERR_TIMEOUT 失败，0 或不设置表示不限制
+    pub timeout_ms: Option<u32>,
+    /// 同时处理的文件数，默认为 CPU 核数
+    pub concurrency: Option<u32>,
+    /// （仅 encryptFiles）设置后以该分片大小（MB）输出分片文件（同 chunkEncryptFile），否则整体加密（同 encryptFile）
+    pub chunk_size_mb: Option<u32>,
+    /// 只计算每个文件的输出路径、大小、分片数和冲突并在 plan 中返回，不加解密也不写入任何文件，默认为 false
+    pub dry_run: Option<bool>,
+    /// 没有 outputPath 的条目的输出路径模板，例如 `{dir}/{name}.{hash8}.zenc`；占位符见 README
+    pub output_template: Option<String>,
+}
+
+/// encryptDirectory() 的可选参数
+#[napi(object)]
+#[derive(Default)]
+pub struct DirectoryEncryptOptions {
+    /// 超时时间（毫秒），超时后中止操作并删除已写入的文件，0 或不设置表示不限制
+    pub timeout_ms: Option<u32>,
+    /// 加密算法（"aes" 或 "chacha20poly1305"），默认为 "aes"
+    pub algorithm: Option<String>,
+    /// 每个文件的分片大小（MB），默认为 10
+    pub chunk_size_mb: Option<u32>,
+    /// 加密前压缩每个文件（"zstd"、"gzip" 或 "lz4"），同 chunkEncryptFile
+    #[napi(ts_type = "'zstd' | 'gzip' | 'lz4'")]
+    pub compression: Option<String>,
+    pub compression_level: Option<i32>,
+    /// 同时加密的文件数，默认为 CPU 核数
+    pub concurrency: Option<u32>,
+    /// 只加密匹配这些模式的路径（相对于源目录，`**` 匹配任意层目录），目录匹配时加密其下的所有内容；不设置时加密全部
+    pub include: Option<Vec<String>>,
+    /// 跳过匹配这些模式的路径，语法与 `.gitignore` 相同；被跳过的目录不再遍历
+    pub exclude: Option<Vec<String>>,
+    /// 额外的忽略文件，每行一个 exclude 模式；源目录根部的 `.zippyignore` 总是会读取
+    pub ignore_files: Option<Vec<String>>,
+    /// 加密文件名和目录名，目标目录中的名称不再透露原始名称，默认为 false
+    pub encrypt_filenames: Option<bool>,
+    /// 只检查目录并在 plan 中返回将要写出的文件，不创建目标目录也不写入任何文件，默认为 false
+    pub dry_run: Option<bool>,
+    /// 每个加密文件在目标目录中的相对路径模板，默认为 `{dir}/{name}.enc`；`{hash}` 为明文的摘要
+    pub output_template: Option<String>,
+}
+
+/// decryptDirectory() 的可选参数
+#[napi(object)]
+#[derive(Default)]
+pub struct DirectoryDecryptOptions {
+    /// 超时时间（毫秒），超时后中止操作并删除已写入的文件，0 或不设置表示不限制
+    pub timeout_ms: Option<u32>,
+    /// 只还原匹配这些模式的路径（相对于目录根部，`**` 匹配任意层目录），目录匹配时还原其下的所有内容；不设置时还原整个目录树
+    pub include: Option<Vec<String>>,
+    /// 跳过匹配这些模式的路径，语法与 `.gitignore` 相同
+    pub exclude: Option<Vec<String>>,
+    /// 同时解密的文件数，默认为 CPU 核数
+    pub concurrency: Option<u32>,
+    /// 只读取清单并在 plan 中返回将要还原的文件，不解密也不写入任何文件，默认为 false
+    pub dry_run: Option<bool>,
+    /// 每个还原的文件在目标目录中的相对路径模板，默认为 `{dir}/{name}`；`{dir}` 和 `{name}` 取自原始路径，`{hash}` 为加密文件的摘要
+    pub output_template: Option<String>,
+}
+
+/// encryptArchive() 的可选参数
+#[napi(object)]
+#[derive(Default)]
+pub struct ArchiveEncryptOptions {
+    /// 超时时间（毫秒），超时后中止操作并删除未完成的归档，0 或不设置表示不限制
+    pub timeout_ms: Option<u32>,
+    /// 加密算法（"aes" 或 "chacha20poly1305"），默认为 "aes"
+    pub algorithm: Option<String>,
+    /// 分片大小（MB），默认为 10
+    pub chunk_size_mb: Option<u32>,
+    /// 加密前压缩（"zstd"、"gzip" 或 "lz4"），同 chunkEncryptFile
+    #[napi(ts_type = "'zstd' | 'gzip' | 'lz4'")]
+    pub compression: Option<String>,
+    pub compression_level: Option<i32>,
+    /// 只打包匹配这些模式的路径，同 encryptDirectory
+    pub include: Option<Vec<String>>,
+    /// 跳过匹配这些模式的路径，同 encryptDirectory
+    pub exclude: Option<Vec<String>>,
+    /// 额外的忽略文件，同 encryptDirectory；源目录根部的 `.zippyignore` 总是会读取
+    pub ignore_files: Option<Vec<String>>,
+}
+
+/// extractArchive() 的可选参数
+#[napi(object)]
+#[derive(Default)]
+pub struct ArchiveExtractOptions {
+    /// 超时时间（毫秒），超时后中止操作并删除已写入的文件，0 或不设置表示不限制
+    pub timeout_ms: Option<u32>,
+    /// 只解开匹配这些模式的路径，同 decryptDirectory
+    pub include: Option<Vec<String>>,
+    /// 跳过匹配这些模式的路径，同 decryptDirectory
+    pub exclude: Option<Vec<String>>,
+}
+
+/// watchAndEncrypt() 的可选参数
+#[napi(object)]
+#[derive(Default)]
+pub struct WatchOptions {
+    /// 加密算法（"aes" 或 "chacha20poly1305"），默认为 "aes"
+    pub algorithm: Option<String>,
+    /// 每个文件的分片大小（MB），默认为 10
+    pub chunk_size_mb: Option<u32>,
+    /// 加密前压缩每个文件（"zstd"、"gzip" 或 "lz4"），同 chunkEncryptFile
+    #[napi(ts_type = "'zstd' | 'gzip' | 'lz4'")]
+    pub compression: Option<String>,
+    pub compression_level: Option<i32>,
+    /// 只加密匹配这些模式的路径，同 encryptDirectory
+    pub include: Option<Vec<String>>,
+    /// 跳过匹配这些模式的路径，同 encryptDirectory
+    pub exclude: Option<Vec<String>>,
+    /// 额外的忽略文件，同 encryptDirectory；监视目录根部的 `.zippyignore` 总是会读取
+    pub ignore_files: Option<Vec<String>>,
+    /// 文件大小和修改时间保持不变多久（毫秒）后才加密，默认为 1000
+    pub debounce_ms: Option<u32>,
+    /// 扫描目录的间隔（毫秒），默认为 500
+    pub interval_ms: Option<u32>,
+    /// 不加密开始监视时已经存在的文件，直到它们被修改
+    pub ignore_existing: Option<bool>,
+    /// 加密成功后删除源文件
+    pub remove_source: Option<bool>,
+}
+
+/// setDecryptPolicy() 的参数，未设置或为 false 的项保持不变
+#[napi(object)]
+#[derive(Default)]
+pub struct DecryptPolicyOptions {
+    /// 拒绝没有容器文件头的旧格式
+    pub reject_legacy_formats: Option<bool>,
+    /// 拒绝任何未认证的内容（旧格式、未认证的文件头或分片、AES-CBC 整体加密文件）
+    pub require_authentication: Option<bool>,
+}
+
+/// benchmarkAlgorithms() 的可选参数
+#[napi(object)]
+#[derive(Default)]
+pub struct BenchmarkOptions {
+    /// 测量使用的数据大小（KB），每个大小分别测量，默认为 [64, 1024]，每个最大 65536
+    pub sizes_kb: Option<Vec<u32>>,
+    /// 每项测量持续的时间（毫秒），默认为 100，最大 10000
+    pub duration_ms: Option<u32>,
+    /// 测量的加密算法，默认为配置允许的全部算法（"aes" 和 "chacha20poly1305"）
+    pub algorithms: Option<Vec<String>>,
+    /// 测量的摘要算法，默认为 ["sha256", "blake3"]，传空数组时不测量摘要
+    pub hashes: Option<Vec<String>>,
+}
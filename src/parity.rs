@@ -0,0 +1,462 @@
+use std::fs::{File, OpenOptions};
+use std::io::{BufWriter, ErrorKind, Read, Seek, SeekFrom, Write};
+
+use reed_solomon_erasure::galois_8::ReedSolomon;
+use sha2::{Digest, Sha256};
+
+use crate::errors::{open_error, read_error, write_error, CodedError};
+use crate::fd;
+
+/// 纠错数据结尾的定长标记
+pub const PARITY_MAGIC: &[u8] = b"ZIPPYPAR";
+/// 结尾标记：纠错索引的起始位置(u64 LE) + `ZIPPYPAR`
+const PARITY_TRAILER_LEN: usize = 16;
+/// 纠错块大小，受保护区域按此大小切块
+const PARITY_BLOCK_SIZE: usize = 64 * 1024;
+/// GF(2^8) 上的 Reed-Solomon 编码每组最多 256 个块（数据块 + 校验块）
+const MAX_SHARDS: usize = 256;
+/// 纠错索引的固定部分：受保护长度(u64) + 块大小(u32) + 每组数据块数(u32) + 每组校验块数(u32) + 组数(u32)
+const INDEX_FIXED_LEN: usize = 24;
+const BLOCK_HASH_LEN: usize = 32;
+
+/// 纠错数据的分组参数
+///
+/// 受保护区域（文件头到结尾标记为止的整个加密文件）切成 N 个块，块 i 属于第 i % groups 组的第 i / groups 个位置，
+/// 连续损坏的一段数据因此分散到不同的组中。超出 N 的位置视为全零块，不写入文件。
+struct ParityLayout {
+    protected_len: u64,
+    block_size: usize,
+    data_shards: usize,
+    parity_shards: usize,
+    groups: usize,
+}
+
+impl ParityLayout {
+    /// 根据冗余百分比确定每组的数据块数和校验块数
+    fn new(protected_len: u64, percent: u32) -> Result<Self, CodedError> {
+        check_percent(percent)?;
+        let percent = percent as usize;
+        let blocks = protected_len.div_ceil(PARITY_BLOCK_SIZE as u64).max(1) as usize;
+        let mut data_shards = (MAX_SHARDS * 100 / (100 + percent)).min(blocks);
+        let parity_for = |data_shards: usize| (data_shards * percent).div_ceil(100);
+        while data_shards + parity_for(data_shards) > MAX_SHARDS {
+            data_shards -= 1;
+        }
+        Ok(ParityLayout {
+            protected_len,
+            block_size: PARITY_BLOCK_SIZE,
+            data_shards,
+            parity_shards: parity_for(data_shards),
+            groups: blocks.div_ceil(data_shards),
+        })
+    }
+
+    fn data_blocks(&self) -> usize {
+        self.protected_len.div_ceil(self.block_size as u64) as usize
+    }
+
+    fn parity_blocks(&self) -> usize {
+        self.groups * self.parity_shards
+    }
+
+    /// 第 group 组第 position 个位置对应的数据块序号
+    fn block_index(&self, group: usize, position: usize) -> usize {
+        position * self.groups + group
+    }
+
+    /// 数据块 index 在文件中的位置和实际长度（最后一个块可能不满）
+    fn data_block(&self, index: usize) -> (u64, usize) {
+        let offset = index as u64 * self.block_size as u64;
+        (offset, (self.protected_len - offset).min(self.block_size as u64) as usize)
+    }
+
+    /// 第 group 组第 parity 个校验块在文件中的位置，校验块按组依次写在受保护区域之后
+    fn parity_block(&self, group: usize, parity: usize) -> u64 {
+        self.protected_len + ((group * self.parity_shards + parity) * self.block_size) as u64
+    }
+
+    fn index_len(&self) -> usize {
+        INDEX_FIXED_LEN + (self.data_blocks() + self.parity_blocks()) * BLOCK_HASH_LEN + BLOCK_HASH_LEN
+    }
+
+    fn codec(&self) -> Result<ReedSolomon, CodedError> {
+        ReedSolomon::new(self.data_shards, self.parity_shards)
+            .map_err(|e| CodedError::bad_format(format!("Invalid parity layout: {:?}", e)))
+    }
+}
+
+/// 检查冗余百分比是否在 1-100 之间
+pub fn check_percent(percent: u32) -> Result<(), CodedError> {
+    if !(1..=100).contains(&percent) {
+        return Err(CodedError::invalid_argument(format!("Parity percent must be between 1 and 100, got {}", percent)));
+    }
+    Ok(())
+}
+
+/// 纠错修复的结果
+pub struct RepairReport {
+    /// 检查的块数（数据块 + 校验块）
+    pub checked_blocks: usize,
+    /// 校验值不符的块数
+    pub damaged_blocks: usize,
+    /// 已从校验块恢复并写回的块数
+    pub repaired_blocks: usize,
+    /// 损坏过多、无法恢复的块数
+    pub unrecoverable_blocks: usize,
+}
+
+fn block_hash(data: &[u8]) -> [u8; BLOCK_HASH_LEN] {
+    Sha256::digest(data).into()
+}
+
+/// 读取一个块，不足块大小的部分补零
+fn read_block(file: &mut File, offset: u64, len: usize, block_size: usize) -> std::io::Result<Vec<u8>> {
+    let mut block = vec![0u8; block_size];
+    file.seek(SeekFrom::Start(offset))?;
+    file.read_exact(&mut block[..len])?;
+    Ok(block)
+}
+
+/// 在已完成的加密文件末尾追加 Reed-Solomon 校验块和纠错索引，返回追加的字节数
+///
+/// 布局：受保护区域 + 校验块 + 纠错索引（分组参数、每个数据块和校验块的 SHA-256、索引自身的 SHA-256）+ 结尾标记。
+/// 块的 SHA-256 用于找出损坏的块，不需要密钥；解密时仍由分片认证和文件 MAC 保证数据未被篡改。
+pub fn append_parity(path: &str, percent: u32) -> Result<u64, CodedError> {
+    let mut file = fd::open(path).map_err(|e| open_error("opening file for parity data", e))?;
+    let protected_len = file.metadata().map_err(|e| read_error("reading file for parity data", e))?.len();
+    let layout = ParityLayout::new(protected_len, percent)?;
+    let codec = layout.codec()?;
+
+    let output = OpenOptions::new()
+        .append(true)
+        .open(path)
+        .map_err(|e| open_error("opening file for parity data", e))?;
+    let mut writer = BufWriter::with_capacity(layout.block_size, output);
+
+    let mut data_hashes = vec![[0u8; BLOCK_HASH_LEN]; layout.data_blocks()];
+    let mut parity_hashes = Vec::with_capacity(layout.parity_blocks());
+    for group in 0..layout.groups {
+        let mut shards = Vec::with_capacity(layout.data_shards + layout.parity_shards);
+        for position in 0..layout.data_shards {
+            let index = layout.block_index(group, position);
+            if index >= layout.data_blocks() {
+                shards.push(vec![0u8; layout.block_size]);
+                continue;
+            }
+            let (offset, len) = layout.data_block(index);
+            let block = read_block(&mut file, offset, len, layout.block_size).map_err(|e| read_error("reading parity data", e))?;
+            data_hashes[index] = block_hash(&block[..len]);
+            shards.push(block);
+        }
+        shards.resize(layout.data_shards + layout.parity_shards, vec![0u8; layout.block_size]);
+        codec
+            .encode(&mut shards)
+            .map_err(|e| CodedError::unknown(format!("Parity encoding error: {:?}", e)))?;
+        for shard in &shards[layout.data_shards..] {
+            writer.write_all(shard).map_err(|e| write_error("writing parity data", e))?;
+            parity_hashes.push(block_hash(shard));
+        }
+    }
+
+    let mut index = Vec::with_capacity(layout.index_len());
+    index.extend_from_slice(&layout.protected_len.to_le_bytes());
+    index.extend_from_slice(&(layout.block_size as u32).to_le_bytes());
+    index.extend_from_slice(&(layout.data_shards as u32).to_le_bytes());
+    index.extend_from_slice(&(layout.parity_shards as u32).to_le_bytes());
+    index.extend_from_slice(&(layout.groups as u32).to_le_bytes());
+    for hash in data_hashes.iter().chain(parity_hashes.iter()) {
+        index.extend_from_slice(hash);
+    }
+    let index_hash = block_hash(&index);
+    index.extend_from_slice(&index_hash);
+
+    let index_offset = layout.parity_block(layout.groups, 0);
+    writer
+        .write_all(&index)
+        .and_then(|_| writer.write_all(&index_offset.to_le_bytes()))
+        .and_then(|_| writer.write_all(PARITY_MAGIC))
+        .and_then(|_| writer.flush())
+        .map_err(|e| write_error("writing parity data", e))?;
+    Ok(index_offset - protected_len + (index.len() + PARITY_TRAILER_LEN) as u64)
+}
+
+/// 读取文件末尾的纠错结尾标记，返回纠错索引的位置；文件没有纠错数据时返回 None
+fn find_index<R: Read + Seek>(reader: &mut R) -> std::io::Result<Option<u64>> {
+    if reader.seek(SeekFrom::End(0))? < PARITY_TRAILER_LEN as u64 {
+        return Ok(None);
+    }
+    reader.seek(SeekFrom::End(-(PARITY_TRAILER_LEN as i64)))?;
+    let mut trailer = [0u8; PARITY_TRAILER_LEN];
+    reader.read_exact(&mut trailer)?;
+    if &trailer[8..] != PARITY_MAGIC {
+        return Ok(None);
+    }
+    Ok(Some(u64::from_le_bytes(trailer[..8].try_into().unwrap())))
+}
+
+/// 受保护区域（不含纠错数据）的长度；文件没有纠错数据时就是文件长度
+pub fn protected_len<R: Read + Seek>(reader: &mut R) -> std::io::Result<u64> {
+    match find_index(reader)? {
+        Some(index_offset) => {
+            reader.seek(SeekFrom::Start(index_offset))?;
+            let mut len = [0u8; 8];
+            reader.read_exact(&mut len)?;
+            Ok(u64::from_le_bytes(len))
+        }
+        None => reader.seek(SeekFrom::End(0)),
+    }
+}
+
+/// 读取并校验纠错索引，返回分组参数以及每个数据块和校验块的 SHA-256
+fn read_index(file: &mut File) -> Result<(ParityLayout, Vec<u8>), CodedError> {
+    let damaged = || CodedError::bad_format("Parity index is damaged - the file cannot be repaired");
+    let index_offset = find_index(file)
+        .map_err(|e| read_error("reading parity data", e))?
+        .ok_or_else(|| CodedError::unsupported("File has no parity data"))?;
+    let file_len = file.seek(SeekFrom::End(0)).map_err(|e| read_error("reading parity data", e))?;
+
+    let mut fixed = [0u8; INDEX_FIXED_LEN];
+    file.seek(SeekFrom::Start(index_offset)).map_err(|e| read_error("reading parity data", e))?;
+    file.read_exact(&mut fixed).map_err(|_| damaged())?;
+    let field = |at: usize| u32::from_le_bytes(fixed[at..at + 4].try_into().unwrap()) as usize;
+    let layout = ParityLayout {
+        protected_len: u64::from_le_bytes(fixed[..8].try_into().unwrap()),
+        block_size: field(8),
+        data_shards: field(12),
+        parity_shards: field(16),
+        groups: field(20),
+    };
+    let consistent = layout.block_size > 0
+        && layout.data_shards > 0
+        && layout.parity_shards > 0
+        && layout.data_shards + layout.parity_shards <= MAX_SHARDS
+        && layout.groups == layout.data_blocks().max(1).div_ceil(layout.data_shards)
+        && layout.parity_block(layout.groups, 0) == index_offset
+        && index_offset + (layout.index_len() + PARITY_TRAILER_LEN) as u64 == file_len;
+    if !consistent {
+        return Err(damaged());
+    }
+
+    let mut index = vec![0u8; layout.index_len()];
+    file.seek(SeekFrom::Start(index_offset)).map_err(|e| read_error("reading parity data", e))?;
+    file.read_exact(&mut index).map_err(|_| damaged())?;
+    let (body, index_hash) = index.split_at(index.len() - BLOCK_HASH_LEN);
+    if block_hash(body) != index_hash {
+        return Err(damaged());
+    }
+    let hashes = body[INDEX_FIXED_LEN..].to_vec();
+    Ok((layout, hashes))
+}
+
+/// 生成文件现有纠错数据时使用的冗余百分比，用于内容改变后按相同的冗余重新生成
+pub fn parity_percent(path: &str) -> Result<u32, CodedError> {
+    let mut file = fd::open(path).map_err(|e| open_error("opening file for parity data", e))?;
+    let (layout, _) = read_index(&mut file)?;
+    (1..=100)
+        .find(|&percent| {
+            ParityLayout::new(layout.protected_len, percent).is_ok_and(|candidate| {
+                candidate.data_shards == layout.data_shards && candidate.parity_shards == layout.parity_shards
+            })
+        })
+        .ok_or_else(|| CodedError::unsupported("Unsupported parity layout"))
+}
+
+/// 去掉文件末尾的纠错数据，受保护区域的内容改变后纠错数据随之失效
+pub fn remove_parity(path: &str) -> Result<(), CodedError> {
+    let mut file = OpenOptions::new()
+        .read(true)
+        .write(true)
+        .open(path)
+        .map_err(|e| open_error("opening file for parity data", e))?;
+    let protected_len = protected_len(&mut file).map_err(|e| read_error("reading parity data", e))?;
+    file.set_len(protected_len).map_err(|e| write_error("removing parity data", e))
+}
+
+/// 根据纠错索引找出损坏的块，能恢复的用校验块重建后写回原位置，不需要密钥
+///
+/// 每组中损坏的数据块和校验块总数不超过该组的校验块数时可以完全恢复。
+pub fn repair(path: &str) -> Result<RepairReport, CodedError> {
+    let mut file = OpenOptions::new()
+        .read(true)
+        .write(true)
+        .open(path)
+        .map_err(|e| open_error("opening file for parity data", e))?;
+    let (layout, hashes) = read_index(&mut file)?;
+    let codec = layout.codec()?;
+    let hash_of = |block: usize| &hashes[block * BLOCK_HASH_LEN..(block + 1) * BLOCK_HASH_LEN];
+
+    let mut report = RepairReport {
+        checked_blocks: layout.data_blocks() + layout.parity_blocks(),
+        damaged_blocks: 0,
+        repaired_blocks: 0,
+        unrecoverable_blocks: 0,
+    };
+    for group in 0..layout.groups {
+        // 读取本组的所有块，校验值不符或读取失败的块作为缺失块
+        let mut shards: Vec<Option<Vec<u8>>> = Vec::with_capacity(layout.data_shards + layout.parity_shards);
+        let mut damaged = Vec::new();
+        for position in 0..layout.data_shards + layout.parity_shards {
+            let (offset, len, hash_index) = if position < layout.data_shards {
+                let index = layout.block_index(group, position);
+                if index >= layout.data_blocks() {
+                    shards.push(Some(vec![0u8; layout.block_size]));
+                    continue;
+                }
+                let (offset, len) = layout.data_block(index);
+                (offset, len, index)
+            } else {
+                let parity = position - layout.data_shards;
+                let hash_index = layout.data_blocks() + group * layout.parity_shards + parity;
+                (layout.parity_block(group, parity), layout.block_size, hash_index)
+            };
+            let block = match read_block(&mut file, offset, len, layout.block_size) {
+                Ok(block) => Some(block).filter(|block| block_hash(&block[..len]) == hash_of(hash_index)),
+                Err(err) if err.kind() == ErrorKind::UnexpectedEof => None,
+                Err(err) => return Err(read_error("reading parity data", err)),
+            };
+            if block.is_none() {
+                damaged.push((position, offset, len));
+            }
+            shards.push(block);
+        }
+
+        report.damaged_blocks += damaged.len();
+        if damaged.is_empty() {
+            continue;
+        }
+        if damaged.len() > layout.parity_shards {
+            report.unrecoverable_blocks += damaged.len();
+            continue;
+        }
+        codec
+            .reconstruct(&mut shards)
+            .map_err(|e| CodedError::bad_format(format!("Parity reconstruction error: {:?}", e)))?;
+        for (position, offset, len) in damaged {
+            let block = shards[position].as_ref().unwrap();
+            file.seek(SeekFrom::Start(offset))
+                .and_then(|_| file.write_all(&block[..len]))
+                .map_err(|e| write_error("writing repaired parity data", e))?;
+            report.repaired_blocks += 1;
+        }
+    }
+    file.flush().map_err(|e| write_error("writing repaired parity data", e))?;
+    file.sync_all().map_err(|e| write_error("writing repaired parity data", e))?;
+    Ok(report)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::fs;
+
+    use super::*;
+    use crate::errors::ErrorCode;
+
+    /// 五个数据块，最后一个不满
+    const FILE_LEN: usize = 4 * PARITY_BLOCK_SIZE + 1000;
+
+    struct TempFile(String);
+
+    impl TempFile {
+        fn new(name: &str) -> (Self, Vec<u8>) {
+            let path = std::env::temp_dir().join(format!("zippy-parity-{}-{}", std::process::id(), name)).to_string_lossy().into_owned();
+            let data: Vec<u8> = (0..FILE_LEN).map(|i| (i * 7 % 251) as u8).collect();
+            fs::write(&path, &data).unwrap();
+            (TempFile(path), data)
+        }
+
+        fn damage(&self, offsets: &[usize]) {
+            let mut data = fs::read(&self.0).unwrap();
+            for &offset in offsets {
+                data[offset] ^= 0x55;
+            }
+            fs::write(&self.0, data).unwrap();
+        }
+    }
+
+    impl Drop for TempFile {
+        fn drop(&mut self) {
+            let _ = fs::remove_file(&self.0);
+        }
+    }
+
+    #[test]
+    fn lays_out_groups_within_the_shard_limit() {
+        for (blocks, percent) in [(1, 1), (5, 25), (300, 10), (1000, 100), (100_000, 1)] {
+            let layout = ParityLayout::new(blocks as u64 * PARITY_BLOCK_SIZE as u64, percent).unwrap();
+            assert!(layout.data_shards + layout.parity_shards <= MAX_SHARDS);
+            assert!(layout.parity_shards * 100 >= layout.data_shards * percent as usize);
+            assert!(layout.groups * layout.data_shards >= blocks);
+            assert!((layout.groups - 1) * layout.data_shards < blocks);
+        }
+        // 相邻的块属于不同的组
+        let layout = ParityLayout::new(1000 * PARITY_BLOCK_SIZE as u64, 10).unwrap();
+        assert_eq!((layout.block_index(0, 0), layout.block_index(1, 0), layout.block_index(0, 1)), (0, 1, layout.groups));
+        assert_eq!(ParityLayout::new(0, 10).unwrap().groups, 1);
+        for percent in [0, 101] {
+            assert_eq!(ParityLayout::new(100, percent).err().unwrap().code, ErrorCode::InvalidArgument);
+        }
+    }
+
+    #[test]
+    fn appends_and_removes_parity() {
+        let (file, data) = TempFile::new("append");
+        let added = append_parity(&file.0, 25).unwrap();
+        assert_eq!(fs::metadata(&file.0).unwrap().len(), FILE_LEN as u64 + added);
+        assert_eq!(protected_len(&mut File::open(&file.0).unwrap()).unwrap(), FILE_LEN as u64);
+
+        // 找回的百分比产生相同的分组参数
+        let percent = parity_percent(&file.0).unwrap();
+        let (original, found) = (ParityLayout::new(FILE_LEN as u64, 25).unwrap(), ParityLayout::new(FILE_LEN as u64, percent).unwrap());
+        assert_eq!((original.data_shards, original.parity_shards), (found.data_shards, found.parity_shards));
+
+        let report = repair(&file.0).unwrap();
+        assert_eq!((report.checked_blocks, report.damaged_blocks), (5 + original.parity_blocks(), 0));
+
+        remove_parity(&file.0).unwrap();
+        assert_eq!(fs::read(&file.0).unwrap(), data);
+        assert_eq!(protected_len(&mut File::open(&file.0).unwrap()).unwrap(), FILE_LEN as u64);
+        assert_eq!(repair(&file.0).err().unwrap().code, ErrorCode::Unsupported);
+    }
+
+    #[test]
+    fn repairs_damaged_data_and_parity_blocks() {
+        let (file, data) = TempFile::new("repair");
+        append_parity(&file.0, 25).unwrap();
+        let with_parity = fs::read(&file.0).unwrap();
+        // 两个校验块可以恢复一个数据块和一个校验块（包括最后一个不满的数据块）
+        file.damage(&[4 * PARITY_BLOCK_SIZE + 999, FILE_LEN + 10]);
+        let report = repair(&file.0).unwrap();
+        assert_eq!((report.damaged_blocks, report.repaired_blocks, report.unrecoverable_blocks), (2, 2, 0));
+        assert_eq!(fs::read(&file.0).unwrap(), with_parity);
+        assert_eq!(fs::read(&file.0).unwrap()[..FILE_LEN], data);
+    }
+
+    #[test]
+    fn reports_unrecoverable_damage() {
+        let (file, _) = TempFile::new("unrecoverable");
+        append_parity(&file.0, 25).unwrap();
+        file.damage(&[0, PARITY_BLOCK_SIZE, 2 * PARITY_BLOCK_SIZE]);
+        let damaged = fs::read(&file.0).unwrap();
+        let report = repair(&file.0).unwrap();
+        assert_eq!((report.damaged_blocks, report.repaired_blocks, report.unrecoverable_blocks), (3, 0, 3));
+        assert_eq!(fs::read(&file.0).unwrap(), damaged);
+    }
+
+    #[test]
+    fn rejects_a_damaged_index() {
+        let (file, _) = TempFile::new("index");
+        append_parity(&file.0, 25).unwrap();
+        let len = fs::metadata(&file.0).unwrap().len() as usize;
+        // 改动块的哈希：索引自身的 SHA-256 不符
+        file.damage(&[len - PARITY_TRAILER_LEN - BLOCK_HASH_LEN - 1]);
+        assert_eq!(repair(&file.0).err().unwrap().code, ErrorCode::BadFormat);
+
+        // 结尾标记指向错误的位置
+        let (file, _) = TempFile::new("offset");
+        append_parity(&file.0, 25).unwrap();
+        let len = fs::metadata(&file.0).unwrap().len() as usize;
+        file.damage(&[len - PARITY_TRAILER_LEN]);
+        assert_eq!(repair(&file.0).err().unwrap().code, ErrorCode::BadFormat);
+        assert_eq!(parity_percent(&file.0).err().unwrap().code, ErrorCode::BadFormat);
+    }
+}
@@ -0,0 +1,425 @@
+use std::collections::{HashMap, VecDeque};
+use std::io::{self, BufRead, Read};
+use std::sync::mpsc::{self, Receiver, SyncSender};
+use std::sync::{Arc, Condvar, Mutex};
+use std::thread::{self, JoinHandle, Scope};
+
+/// 双缓冲流水线的读取端：后台线程填充一个缓冲区的同时，当前线程处理另一个缓冲区
+///
+/// 只有两个缓冲区在两个线程之间轮换，处理完的缓冲区通过 recycle 交还读取线程复用。
+/// 每次填充一个完整的缓冲区，读到的数据不足一个缓冲区时视为读取完毕。
+pub struct Prefetch {
+    filled: Receiver<io::Result<Vec<u8>>>,
+    recycled: SyncSender<Vec<u8>>,
+}
+
+impl Prefetch {
+    /// 在 scope 中启动读取线程；fill 把下一块数据写入缓冲区并返回写入的字节数
+    pub fn spawn<'scope, F>(scope: &'scope Scope<'scope, '_>, buffer_len: usize, fill: F) -> Self
+    where
+        F: FnMut(&mut [u8]) -> io::Result<usize> + Send + 'scope,
+    {
+        let (prefetch, worker) = Self::channels(buffer_len, fill);
+        scope.spawn(worker);
+        prefetch
+    }
+
+    /// 取得下一块数据，读取完毕后返回 None
+    pub fn next(&self) -> Option<io::Result<Vec<u8>>> {
+        self.filled.recv().ok()
+    }
+
+    /// 把处理完的缓冲区交还读取线程
+    pub fn recycle(&self, buffer: Vec<u8>) {
+        let _ = self.recycled.try_send(buffer);
+    }
+
+    fn channels<F>(buffer_len: usize, mut fill: F) -> (Self, impl FnOnce() + Send)
+    where
+        F: FnMut(&mut [u8]) -> io::Result<usize> + Send,
+    {
+        // 容量为 0：填充好的缓冲区要等处理线程取走后才开始填充下一个
+        let (filled_sender, filled) = mpsc::sync_channel(0);
+        let (recycled, recycled_receiver) = mpsc::sync_channel::<Vec<u8>>(2);
+        let worker = move || {
+            for allocated in 0.. {
+                let mut buffer = match allocated < 2 {
+                    true => Vec::new(),
+                    // 处理线程已经放弃读取
+                    false => match recycled_receiver.recv() {
+                        Ok(buffer) => buffer,
+                        Err(_) => return,
+                    },
+                };
+                buffer.resize(buffer_len, 0);
+                let len = match fill(&mut buffer) {
+                    Ok(0) => return,
+                    Ok(len) => len,
+                    Err(err) => {
+                        let _ = filled_sender.send(Err(err));
+                        return;
+                    },
+                };
+                buffer.truncate(len);
+                if filled_sender.send(Ok(buffer)).is_err() || len < buffer_len {
+                    return;
+                }
+            }
+        };
+        (Prefetch { filled, recycled }, worker)
+    }
+}
+
+/// 在后台线程中预读的 reader，用于按顺序读取的解密流程
+///
+/// 读取出错后之后的读取都返回结尾；丢弃时等待读取线程结束。
+pub struct ReadAhead {
+    prefetch: Option<Prefetch>,
+    worker: Option<JoinHandle<()>>,
+    current: Vec<u8>,
+    position: usize,
+}
+
+impl ReadAhead {
+    pub fn new<R: Read + Send + 'static>(mut reader: R, buffer_len: usize) -> Self {
+        let (prefetch, worker) = Prefetch::channels(buffer_len, move |buffer| read_full(&mut reader, buffer));
+        ReadAhead {
+            prefetch: Some(prefetch),
+            worker: Some(thread::spawn(worker)),
+            current: Vec::new(),
+            position: 0,
+        }
+    }
+}
+
+impl Read for ReadAhead {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        let available = self.fill_buf()?;
+        let len = available.len().min(buf.len());
+        buf[..len].copy_from_slice(&available[..len]);
+        self.consume(len);
+        Ok(len)
+    }
+}
+
+impl BufRead for ReadAhead {
+    fn fill_buf(&mut self) -> io::Result<&[u8]> {
+        if self.position >= self.current.len() {
+            if let Some(prefetch) = &self.prefetch {
+                if let Some(next) = prefetch.next() {
+                    let previous = std::mem::replace(&mut self.current, next?);
+                    if previous.capacity() > 0 {
+                        prefetch.recycle(previous);
+                    }
+                    self.position = 0;
+                }
+            }
+        }
+        Ok(&self.current[self.position..])
+    }
+
+    fn consume(&mut self, amount: usize) {
+        self.position = (self.position + amount).min(self.current.len());
+    }
+}
+
+impl Drop for ReadAhead {
+    fn drop(&mut self) {
+        // 先关闭通道，读取线程完成当前的读取后退出
+        self.prefetch = None;
+        if let Some(worker) = self.worker.take() {
+            let _ = worker.join();
+        }
+    }
+}
+
+/// 按序号取用分片时在后台线程中预先准备后面的分片，用于随机读取的类按顺序播放时不必等待解密
+///
+/// 每次 get(n) 之后后台线程依次准备 n+1 到 n+depth，已准备好的结果最多保留 depth 个。
+/// 跳转到其它位置时丢弃尚未开始的预取；目标不是后台线程正在准备的分片时直接在当前线程中读取，不等待排在前面的预取。
+pub struct ChunkReadAhead<T> {
+    shared: Arc<ReadAheadShared<T>>,
+    worker: Option<JoinHandle<()>>,
+    depth: u64,
+    count: u64,
+}
+
+struct ReadAheadShared<T> {
+    state: Mutex<ReadAheadState<T>>,
+    changed: Condvar,
+    fetch: Box<dyn Fn(u64) -> T + Send + Sync>,
+}
+
+struct ReadAheadState<T> {
+    /// 等待后台线程准备的分片序号
+    queue: VecDeque<u64>,
+    /// 后台线程正在准备的分片序号
+    in_flight: Option<u64>,
+    ready: HashMap<u64, T>,
+    closed: bool,
+}
+
+impl<T: Send + 'static> ChunkReadAhead<T> {
+    /// count 为分片总数，fetch 读取一个分片，可能同时在当前线程和后台线程中调用
+    pub fn new(depth: u64, count: u64, fetch: impl Fn(u64) -> T + Send + Sync + 'static) -> Self {
+        let shared = Arc::new(ReadAheadShared {
+            state: Mutex::new(ReadAheadState { queue: VecDeque::new(), in_flight: None, ready: HashMap::new(), closed: false }),
+            changed: Condvar::new(),
+            fetch: Box::new(fetch),
+        });
+        let worker = {
+            let shared = shared.clone();
+            thread::spawn(move || shared.run())
+        };
+        ChunkReadAhead { shared, worker: Some(worker), depth, count }
+    }
+
+    /// 取得一个分片：已准备好时直接返回，后台线程正在准备时等待完成，否则在当前线程中读取；随后安排预取之后的分片
+    pub fn get(&self, index: u64) -> T {
+        let mut state = self.shared.state.lock().unwrap();
+        while state.in_flight == Some(index) {
+            state = self.shared.changed.wait(state).unwrap();
+        }
+        let ready = state.ready.remove(&index);
+        let last = index.saturating_add(self.depth).min(self.count.saturating_sub(1));
+        state.ready.retain(|&ready_index, _| ready_index > index && ready_index <= last);
+        state.queue.clear();
+        for next in index + 1..=last {
+            if !state.ready.contains_key(&next) && state.in_flight != Some(next) {
+                state.queue.push_back(next);
+            }
+        }
+        drop(state);
+        self.shared.changed.notify_all();
+        ready.unwrap_or_else(|| (self.shared.fetch)(index))
+    }
+}
+
+impl<T> ReadAheadShared<T> {
+    fn run(&self) {
+        let mut state = self.state.lock().unwrap();
+        loop {
+            if state.closed {
+                return;
+            }
+            let Some(index) = state.queue.pop_front() else {
+                state = self.changed.wait(state).unwrap();
+                continue;
+            };
+            state.in_flight = Some(index);
+            drop(state);
+            let item = (self.fetch)(index);
+            state = self.state.lock().unwrap();
+            state.in_flight = None;
+            state.ready.insert(index, item);
+            self.changed.notify_all();
+        }
+    }
+}
+
+impl<T> Drop for ChunkReadAhead<T> {
+    fn drop(&mut self) {
+        // 后台线程准备完当前的分片后退出
+        self.shared.state.lock().unwrap().closed = true;
+        self.shared.changed.notify_all();
+        if let Some(worker) = self.worker.take() {
+            let _ = worker.join();
+        }
+    }
+}
+
+/// 读满 buffer，遇到结尾时返回实际读取的字节数
+pub fn read_full<R: Read>(reader: &mut R, buffer: &mut [u8]) -> io::Result<usize> {
+    let mut filled = 0;
+    while filled < buffer.len() {
+        match reader.read(&mut buffer[filled..]) {
+            Ok(0) => break,
+            Ok(n) => filled += n,
+            Err(err) if err.kind() == io::ErrorKind::Interrupted => {},
+            Err(err) => return Err(err),
+        }
+    }
+    Ok(filled)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::Cursor;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+
+    fn sample(len: usize) -> Vec<u8> {
+        (0..len).map(|i| (i * 7 + i / 251) as u8).collect()
+    }
+
+    #[test]
+    fn prefetch_yields_full_buffers_then_the_remainder() {
+        let data = sample(2500);
+        let mut chunks = Vec::new();
+        thread::scope(|scope| {
+            let mut reader = Cursor::new(&data);
+            let prefetch = Prefetch::spawn(scope, 1000, move |buffer| read_full(&mut reader, buffer));
+            while let Some(chunk) = prefetch.next() {
+                let chunk = chunk.unwrap();
+                chunks.push(chunk.clone());
+                prefetch.recycle(chunk);
+            }
+        });
+        assert_eq!(chunks.iter().map(Vec::len).collect::<Vec<_>>(), [1000, 1000, 500]);
+        assert_eq!(chunks.concat(), data);
+    }
+
+    #[test]
+    fn prefetch_stops_after_an_exact_multiple() {
+        let data = sample(2000);
+        let mut lens = Vec::new();
+        thread::scope(|scope| {
+            let mut reader = Cursor::new(&data);
+            let prefetch = Prefetch::spawn(scope, 1000, move |buffer| read_full(&mut reader, buffer));
+            while let Some(chunk) = prefetch.next() {
+                let chunk = chunk.unwrap();
+                lens.push(chunk.len());
+                prefetch.recycle(chunk);
+            }
+        });
+        assert_eq!(lens, [1000, 1000]);
+    }
+
+    #[test]
+    fn prefetch_reports_read_errors_and_stops() {
+        thread::scope(|scope| {
+            let mut calls = 0;
+            let prefetch = Prefetch::spawn(scope, 16, move |buffer| {
+                calls += 1;
+                match calls {
+                    1 => Ok(buffer.len()),
+                    _ => Err(io::Error::other("broken")),
+                }
+            });
+            let first = prefetch.next().unwrap().unwrap();
+            prefetch.recycle(first);
+            assert_eq!(prefetch.next().unwrap().unwrap_err().to_string(), "broken");
+            assert!(prefetch.next().is_none());
+        });
+    }
+
+    #[test]
+    fn prefetch_worker_exits_when_the_consumer_gives_up() {
+        let data = sample(10_000);
+        thread::scope(|scope| {
+            let mut reader = Cursor::new(&data);
+            let prefetch = Prefetch::spawn(scope, 100, move |buffer| read_full(&mut reader, buffer));
+            assert_eq!(prefetch.next().unwrap().unwrap().len(), 100);
+            // 丢弃后读取线程退出，scope 才能结束
+            drop(prefetch);
+        });
+    }
+
+    #[test]
+    fn read_ahead_reads_the_whole_stream() {
+        let data = sample(10_007);
+        let mut reader = ReadAhead::new(Cursor::new(data.clone()), 1024);
+        let mut output = Vec::new();
+        let mut buffer = [0; 333];
+        loop {
+            let len = reader.read(&mut buffer).unwrap();
+            if len == 0 {
+                break;
+            }
+            output.extend_from_slice(&buffer[..len]);
+        }
+        assert_eq!(output, data);
+    }
+
+    #[test]
+    fn read_ahead_supports_buf_read() {
+        let data = b"first line\nsecond line\nlast".to_vec();
+        let reader = ReadAhead::new(Cursor::new(data), 4);
+        let lines = reader.lines().collect::<io::Result<Vec<_>>>().unwrap();
+        assert_eq!(lines, ["first line", "second line", "last"]);
+    }
+
+    #[test]
+    fn read_ahead_can_be_dropped_early() {
+        let mut reader = ReadAhead::new(Cursor::new(sample(100_000)), 64);
+        let mut buffer = [0; 10];
+        reader.read_exact(&mut buffer).unwrap();
+        drop(reader);
+    }
+
+    #[test]
+    fn chunk_read_ahead_returns_each_chunk() {
+        let read_ahead = ChunkReadAhead::new(2, 10, |index| index * 100);
+        for index in 0..10 {
+            assert_eq!(read_ahead.get(index), index * 100);
+        }
+        // 跳转到前面的位置
+        assert_eq!(read_ahead.get(3), 300);
+        assert_eq!(read_ahead.get(9), 900);
+    }
+
+    #[test]
+    fn chunk_read_ahead_prefetches_the_following_chunks() {
+        let fetched = Arc::new(Mutex::new(Vec::new()));
+        let wait_for = |count: usize| {
+            for _ in 0..1000 {
+                if fetched.lock().unwrap().len() >= count {
+                    break;
+                }
+                thread::sleep(std::time::Duration::from_millis(1));
+            }
+            let mut indexes = fetched.lock().unwrap().clone();
+            indexes.sort();
+            indexes
+        };
+        let read_ahead = {
+            let fetched = fetched.clone();
+            ChunkReadAhead::new(3, 5, move |index| fetched.lock().unwrap().push(index))
+        };
+        read_ahead.get(0);
+        assert_eq!(wait_for(4), [0, 1, 2, 3]);
+        // 已准备好的分片不再读取；预取不超过最后一个分片
+        read_ahead.get(1);
+        read_ahead.get(2);
+        assert_eq!(wait_for(5), [0, 1, 2, 3, 4]);
+        thread::sleep(std::time::Duration::from_millis(20));
+        drop(read_ahead);
+        assert_eq!(fetched.lock().unwrap().len(), 5);
+    }
+
+    #[test]
+    fn chunk_read_ahead_fetches_each_chunk_once_when_read_in_order() {
+        let calls = Arc::new(AtomicUsize::new(0));
+        let read_ahead = {
+            let calls = calls.clone();
+            ChunkReadAhead::new(4, 50, move |index| {
+                calls.fetch_add(1, Ordering::SeqCst);
+                index
+            })
+        };
+        for index in 0..50 {
+            assert_eq!(read_ahead.get(index), index);
+        }
+        drop(read_ahead);
+        assert!(calls.load(Ordering::SeqCst) <= 50 + 4);
+    }
+
+    #[test]
+    fn read_full_fills_across_short_reads() {
+        struct Trickle<'a>(&'a [u8]);
+        impl Read for Trickle<'_> {
+            fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+                let len = self.0.len().min(buf.len()).min(3);
+                buf[..len].copy_from_slice(&self.0[..len]);
+                self.0 = &self.0[len..];
+                Ok(len)
+            }
+        }
+        let data = sample(20);
+        let mut buffer = [0; 16];
+        assert_eq!(read_full(&mut Trickle(&data), &mut buffer).unwrap(), 16);
+        assert_eq!(buffer, data[..16]);
+        let mut buffer = [0; 32];
+        assert_eq!(read_full(&mut Trickle(&data), &mut buffer).unwrap(), 20);
+    }
+}
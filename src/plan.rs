@@ -0,0 +1,127 @@
+use std::collections::HashMap;
+use std::path::{self, Path, PathBuf};
+
+/// dryRun 中计划处理的一个文件
+pub struct PlanEntry {
+    pub input_path: String,
+    pub output_path: String,
+    /// 明文大小，旧格式的整体加密文件没有记录时为 None
+    pub size: Option<u64>,
+    /// 分片数，整体加密或解密时为 None
+    pub chunks: Option<u64>,
+}
+
+/// 找出计划中会互相冲突的输出路径，返回与 entries 一一对应的说明
+///
+/// 冲突包括：与另一个文件的输出路径相同、是另一个文件的输入路径、只有大小写不同（在不区分大小写的文件系统上是同一个文件）。
+/// 输出与自身输入相同是原地操作，不算冲突。
+pub fn collisions(entries: &[PlanEntry]) -> Vec<Option<String>> {
+    let outputs: Vec<_> = entries.iter().map(|entry| normalize(&entry.output_path)).collect();
+    let mut by_output: HashMap<_, Vec<_>> = HashMap::new();
+    let mut by_folded: HashMap<_, Vec<_>> = HashMap::new();
+    let mut by_input: HashMap<_, Vec<_>> = HashMap::new();
+    for (index, entry) in entries.iter().enumerate() {
+        by_output.entry(outputs[index].clone()).or_default().push(index);
+        by_folded.entry(fold_case(&outputs[index])).or_default().push(index);
+        by_input.entry(normalize(&entry.input_path)).or_default().push(index);
+    }
+    outputs
+        .iter()
+        .enumerate()
+        .map(|(index, output)| {
+            // 同一组中除自身以外的第一个文件
+            let other = |found: Option<&Vec<usize>>| found.and_then(|group| group.iter().find(|&&other| other != index)).map(|&other| &entries[other].input_path);
+            if let Some(input) = other(by_output.get(output)) {
+                return Some(format!("Same output path as {}", input));
+            }
+            if let Some(input) = other(by_input.get(output)) {
+                return Some(format!("Output path is the input of {}", input));
+            }
+            other(by_folded.get(&fold_case(output))).map(|input| format!("Output path differs only in case from the output of {}", input))
+        })
+        .collect()
+}
+
+/// 输出路径上是否已有文件（包括指向不存在目标的符号链接）
+pub fn exists(path: &str) -> bool {
+    Path::new(path).symlink_metadata().is_ok()
+}
+
+/// 转换为绝对路径并去掉 `.` 段，不访问文件系统，因此不解析符号链接和 `..`
+fn normalize(path: &str) -> PathBuf {
+    path::absolute(path).unwrap_or_else(|_| PathBuf::from(path)).components().collect()
+}
+
+fn fold_case(path: &Path) -> String {
+    path.to_string_lossy().to_lowercase()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn entry(input: &str, output: &str) -> PlanEntry {
+        PlanEntry { input_path: input.into(), output_path: output.into(), size: Some(1), chunks: None }
+    }
+
+    #[test]
+    fn distinct_outputs_do_not_collide() {
+        let entries = [entry("/data/a.txt", "/out/a.txt.enc"), entry("/data/b.txt", "/out/b.txt.enc")];
+        assert_eq!(collisions(&entries), [None, None]);
+    }
+
+    #[test]
+    fn reports_shared_output_paths() {
+        let entries = [entry("/data/a.txt", "/out/x.enc"), entry("/data/b.txt", "/out/./x.enc"), entry("/data/c.txt", "/out/c.enc")];
+        let found = collisions(&entries);
+        assert_eq!(found[0].as_deref(), Some("Same output path as /data/b.txt"));
+        assert_eq!(found[1].as_deref(), Some("Same output path as /data/a.txt"));
+        assert_eq!(found[2], None);
+    }
+
+    #[test]
+    fn reports_outputs_that_overwrite_another_input() {
+        let entries = [entry("/data/a", "/data/b"), entry("/data/b", "/data/c")];
+        let found = collisions(&entries);
+        assert_eq!(found[0].as_deref(), Some("Output path is the input of /data/b"));
+        assert_eq!(found[1], None);
+    }
+
+    #[test]
+    fn in_place_operations_do_not_collide() {
+        let entries = [entry("/data/a", "/data/a"), entry("/data/b", "/data/b")];
+        assert_eq!(collisions(&entries), [None, None]);
+    }
+
+    #[test]
+    fn reports_outputs_differing_only_in_case() {
+        let entries = [entry("/data/a", "/out/Report.enc"), entry("/data/b", "/out/report.enc")];
+        let found = collisions(&entries);
+        assert_eq!(found[0].as_deref(), Some("Output path differs only in case from the output of /data/b"));
+        assert_eq!(found[1].as_deref(), Some("Output path differs only in case from the output of /data/a"));
+    }
+
+    #[test]
+    fn relative_paths_are_resolved_against_the_working_directory() {
+        let absolute = path::absolute("x.enc").unwrap();
+        let entries = [entry("a", "x.enc"), entry("b", &absolute.to_string_lossy())];
+        assert!(collisions(&entries).iter().all(Option::is_some));
+    }
+
+    #[test]
+    fn detects_existing_files_and_dangling_links() {
+        let dir = std::env::temp_dir().join(format!("zippy-plan-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let file = dir.join("file");
+        std::fs::write(&file, b"x").unwrap();
+        assert!(exists(&file.to_string_lossy()));
+        assert!(!exists(&dir.join("missing").to_string_lossy()));
+        #[cfg(unix)]
+        {
+            let link = dir.join("link");
+            std::os::unix::fs::symlink(dir.join("missing"), &link).unwrap();
+            assert!(exists(&link.to_string_lossy()));
+        }
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+}
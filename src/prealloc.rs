@@ -0,0 +1,226 @@
+use std::fs::File;
+use std::io;
+
+/// 为即将写入的文件预先分配 len 字节的磁盘空间，不改变文件大小
+///
+/// 预先分配的空间尽量连续，减少长时间写入产生的碎片；空间不足时立即返回错误，而不是写到一半才失败。
+/// 不是普通文件，或者文件系统、平台不支持预先分配时不做任何事。
+pub fn reserve(file: &File, len: u64) -> io::Result<()> {
+    let metadata = file.metadata()?;
+    if !metadata.is_file() || len <= metadata.len() {
+        return Ok(());
+    }
+    match platform::reserve(file, metadata.len(), len) {
+        Err(err) if platform::is_out_of_space(&err) => Err(err),
+        _ => Ok(()),
+    }
+}
+
+/// 释放文件末尾之后没有用到的预先分配的空间
+pub fn release(file: &File) -> io::Result<()> {
+    let metadata = file.metadata()?;
+    if metadata.is_file() {
+        file.set_len(metadata.len())?;
+    }
+    Ok(())
+}
+
+#[cfg(any(target_os = "linux", target_os = "android"))]
+mod platform {
+    use std::fs::File;
+    use std::io;
+    use std::os::unix::io::AsRawFd;
+
+    pub fn reserve(file: &File, _current: u64, len: u64) -> io::Result<()> {
+        let len = libc::off_t::try_from(len).map_err(|_| io::Error::from(io::ErrorKind::InvalidInput))?;
+        loop {
+            // FALLOC_FL_KEEP_SIZE：只分配空间，文件大小不变
+            if unsafe { libc::fallocate(file.as_raw_fd(), libc::FALLOC_FL_KEEP_SIZE, 0, len) } == 0 {
+                return Ok(());
+            }
+            let err = io::Error::last_os_error();
+            if err.kind() != io::ErrorKind::Interrupted {
+                return Err(err);
+            }
+        }
+    }
+
+    pub fn is_out_of_space(err: &io::Error) -> bool {
+        matches!(err.raw_os_error(), Some(libc::ENOSPC | libc::EDQUOT | libc::EFBIG))
+    }
+}
+
+#[cfg(target_os = "macos")]
+mod platform {
+    use std::fs::File;
+    use std::io;
+    use std::os::unix::io::AsRawFd;
+
+    /// 从文件末尾起分配不足的部分，先尝试连续分配
+    pub fn reserve(file: &File, current: u64, len: u64) -> io::Result<()> {
+        let length = libc::off_t::try_from(len - current).map_err(|_| io::Error::from(io::ErrorKind::InvalidInput))?;
+        for flags in [libc::F_ALLOCATECONTIG | libc::F_ALLOCATEALL, libc::F_ALLOCATEALL] {
+            let mut store = libc::fstore_t {
+                fst_flags: flags,
+                fst_posmode: libc::F_PEOFPOSMODE,
+                fst_offset: 0,
+                fst_length: length,
+                fst_bytesalloc: 0,
+            };
+            if unsafe { libc::fcntl(file.as_raw_fd(), libc::F_PREALLOCATE, &mut store as *mut libc::fstore_t) } != -1 {
+                return Ok(());
+            }
+        }
+        Err(io::Error::last_os_error())
+    }
+
+    pub fn is_out_of_space(err: &io::Error) -> bool {
+        matches!(err.raw_os_error(), Some(libc::ENOSPC | libc::EDQUOT | libc::EFBIG))
+    }
+}
+
+#[cfg(windows)]
+mod platform {
+    use std::fs::File;
+    use std::io;
+    use std::os::windows::io::{AsRawHandle, RawHandle};
+
+    const FILE_ALLOCATION_INFO: u32 = 5;
+    const ERROR_HANDLE_DISK_FULL: i32 = 39;
+    const ERROR_DISK_FULL: i32 = 112;
+
+    #[repr(C)]
+    struct FileAllocationInfo {
+        allocation_size: i64,
+    }
+
+    #[link(name = "kernel32")]
+    extern "system" {
+        fn SetFileInformationByHandle(file: RawHandle, class: u32, info: *const FileAllocationInfo, size: u32) -> i32;
+    }
+
+    /// 设置文件的分配大小，文件大小（EOF）不变
+    pub fn reserve(file: &File, _current: u64, len: u64) -> io::Result<()> {
+        let info = FileAllocationInfo {
+            allocation_size: i64::try_from(len).map_err(|_| io::Error::from(io::ErrorKind::InvalidInput))?,
+        };
+        let size = std::mem::size_of::<FileAllocationInfo>() as u32;
+        if unsafe { SetFileInformationByHandle(file.as_raw_handle(), FILE_ALLOCATION_INFO, &info, size) } == 0 {
+            return Err(io::Error::last_os_error());
+        }
+        Ok(())
+    }
+
+    pub fn is_out_of_space(err: &io::Error) -> bool {
+        matches!(err.raw_os_error(), Some(ERROR_HANDLE_DISK_FULL | ERROR_DISK_FULL))
+    }
+}
+
+#[cfg(not(any(target_os = "linux", target_os = "android", target_os = "macos", windows)))]
+mod platform {
+    use std::fs::File;
+    use std::io;
+
+    pub fn reserve(_file: &File, _current: u64, _len: u64) -> io::Result<()> {
+        Err(io::Error::from(io::ErrorKind::Unsupported))
+    }
+
+    pub fn is_out_of_space(_err: &io::Error) -> bool {
+        false
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::Write;
+    use std::path::PathBuf;
+
+    struct TempFile(PathBuf);
+
+    impl TempFile {
+        fn new(name: &str) -> Self {
+            TempFile(std::env::temp_dir().join(format!("zippy-prealloc-{}-{}", std::process::id(), name)))
+        }
+    }
+
+    impl Drop for TempFile {
+        fn drop(&mut self) {
+            let _ = std::fs::remove_file(&self.0);
+        }
+    }
+
+    #[test]
+    fn reserving_keeps_the_file_size() {
+        let path = TempFile::new("keep");
+        let mut file = File::create(&path.0).unwrap();
+        file.write_all(b"header").unwrap();
+        reserve(&file, 1 << 20).unwrap();
+        assert_eq!(file.metadata().unwrap().len(), 6);
+        file.write_all(&[7; 1000]).unwrap();
+        release(&file).unwrap();
+        assert_eq!(std::fs::read(&path.0).unwrap().len(), 1006);
+    }
+
+    #[cfg(target_os = "linux")]
+    #[test]
+    fn allocates_the_reserved_blocks() {
+        use std::os::unix::fs::MetadataExt;
+        let path = TempFile::new("blocks");
+        let file = File::create(&path.0).unwrap();
+        // 文件系统不支持预先分配时跳过
+        if platform::reserve(&file, 0, 4096).is_err() {
+            return;
+        }
+        reserve(&file, 2 << 20).unwrap();
+        assert!(file.metadata().unwrap().blocks() * 512 >= 2 << 20);
+        assert_eq!(file.metadata().unwrap().len(), 0);
+    }
+
+    #[test]
+    fn releasing_frees_unused_space() {
+        let path = TempFile::new("release");
+        let mut file = File::create(&path.0).unwrap();
+        reserve(&file, 4 << 20).unwrap();
+        file.write_all(&[1; 4096]).unwrap();
+        release(&file).unwrap();
+        assert_eq!(file.metadata().unwrap().len(), 4096);
+        #[cfg(target_os = "linux")]
+        {
+            use std::os::unix::fs::MetadataExt;
+            assert!(file.metadata().unwrap().blocks() * 512 < 4 << 20);
+        }
+    }
+
+    #[test]
+    fn smaller_reservations_do_nothing() {
+        let path = TempFile::new("smaller");
+        let mut file = File::create(&path.0).unwrap();
+        file.write_all(&[0; 100]).unwrap();
+        reserve(&file, 10).unwrap();
+        reserve(&file, 100).unwrap();
+        assert_eq!(file.metadata().unwrap().len(), 100);
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn ignores_files_that_are_not_regular() {
+        let file = std::fs::OpenOptions::new().write(true).open("/dev/null").unwrap();
+        reserve(&file, 1 << 40).unwrap();
+        release(&file).unwrap();
+    }
+
+    #[cfg(target_os = "linux")]
+    #[test]
+    fn reports_reservations_the_disk_cannot_hold() {
+        let path = TempFile::new("full");
+        let file = File::create(&path.0).unwrap();
+        // 文件系统不支持预先分配时跳过
+        if platform::reserve(&file, 0, 4096).is_err() {
+            return;
+        }
+        let err = reserve(&file, 1 << 62).unwrap_err();
+        assert!(platform::is_out_of_space(&err), "{}", err);
+        assert_eq!(file.metadata().unwrap().len(), 0);
+    }
+}
@@ -0,0 +1,937 @@
+use napi::bindgen_prelude::*;
+use napi_derive::napi;
+
+// 各操作的返回值。只在 Rust 中构造后返回给 JS，未记录或不适用的字段为 null，字段在所有调用中都存在。
+// 大小以 `*Bytes` 字段给出精确字节数，`*KB` / fileSize 等以 KB 为单位的浮点字段只用于显示
+
+/// 把字节数转换为 JS 值：不超过 Number.MAX_SAFE_INTEGER 时为 number，否则为 bigint，保证精确
+pub fn byte_count(bytes: u64) -> Either<f64, BigInt> {
+    const MAX_SAFE_INTEGER: u64 = (1 << 53) - 1;
+    if bytes <= MAX_SAFE_INTEGER {
+        Either::A(bytes as f64)
+    } else {
+        Either::B(BigInt::from(bytes))
+    }
+}
+
+/// encryptFile() 的结果
+#[napi(object, object_from_js = false, use_nullable = true)]
+pub struct EncryptFileResult {
+    /// 明文大小（KB）
+    pub file_size: f64,
+    /// 明文大小（字节）
+    pub file_size_bytes: Either<f64, BigInt>,
+    /// 压缩后的大小（KB），未启用压缩时为 null
+    #[napi(js_name = "compressedSizeKB")]
+    pub compressed_size_kb: Option<f64>,
+    /// 压缩后的大小（字节），未启用压缩时为 null
+    pub compressed_size_bytes: Option<Either<f64, BigInt>>,
+    /// 压缩没有减小体积、按原样保存时为 true，未启用压缩时为 null
+    pub compression_skipped: Option<bool>,
+}
+
+/// decryptFile() 的结果
+#[napi(object, object_from_js = false, use_nullable = true)]
+pub struct DecryptFileResult {
+    /// 明文大小（KB）
+    pub file_size: f64,
+    /// 明文大小（字节）
+    pub file_size_bytes: Either<f64, BigInt>,
+    /// 加密文件大小（KB）
+    pub encrypted_size: f64,
+    /// 加密文件大小（字节）
+    pub encrypted_size_bytes: Either<f64, BigInt>,
+    /// 文件头中保存的原始文件名
+    pub filename: Option<String>,
+    pub mime_type: Option<String>,
+    /// 实际写入的输出路径（restoreFilename 时可能与传入的不同）
+    pub output_path: String,
+    /// 是否已核对文件头中保存的明文摘要
+    pub hash_verified: bool,
+}
+
+/// chunkEncryptFile() 的结果
+#[napi(object, object_from_js = false, use_nullable = true)]
+pub struct ChunkEncryptFileResult {
+    pub total_chunks: f64,
+    /// 明文大小（KB）
+    pub file_size: f64,
+    /// 明文大小（字节）
+    pub file_size_bytes: Either<f64, BigInt>,
+    /// 分片大小（KB）
+    pub chunk_size: f64,
+    /// 分片大小（字节）
+    pub chunk_size_bytes: Either<f64, BigInt>,
+    /// 写出的分卷路径，未设置 volumeSizeMb 时为 null
+    pub volumes: Option<Vec<String>>,
+    #[napi(js_name = "paritySizeKB")]
+    pub parity_size_kb: Option<f64>,
+    pub parity_size_bytes: Option<Either<f64, BigInt>>,
+    #[napi(js_name = "compressedSizeKB")]
+    pub compressed_size_kb: Option<f64>,
+    pub compressed_size_bytes: Option<Either<f64, BigInt>>,
+    /// 压缩无效、按原样保存的分片数
+    pub uncompressed_chunks: Option<f64>,
+    /// 以空分片帧保存的全零分片数，未启用 sparse 时为 null
+    pub sparse_chunks: Option<f64>,
+    /// 从检查点继续时跳过的分片数，未启用 resumable 时为 null
+    pub resumed_chunks: Option<f64>,
+}
+
+/// 容错解密时无法解密的分片
+#[napi(object, object_from_js = false, use_nullable = true)]
+pub struct DamagedChunk {
+    pub index: u32,
+    /// 分片在明文中的起止位置（字节）
+    pub start: f64,
+    pub end: f64,
+    pub error: String,
+}
+
+/// chunkDecryptFile() / chunkDecryptVolumes() 的结果
+#[napi(object, object_from_js = false, use_nullable = true)]
+pub struct ChunkDecryptFileResult {
+    pub total_chunks: f64,
+    /// 写出的明文大小（KB）
+    #[napi(js_name = "totalBytesKB")]
+    pub total_bytes_kb: f64,
+    /// 写出的明文大小（字节）
+    pub total_bytes: Either<f64, BigInt>,
+    /// 文件头记录的明文大小（KB）
+    #[napi(js_name = "originalSizeKB")]
+    pub original_size_kb: f64,
+    /// 文件头记录的明文大小（字节）
+    pub original_size_bytes: Either<f64, BigInt>,
+    #[napi(js_name = "chunkSizeKB")]
+    pub chunk_size_kb: f64,
+    pub chunk_size_bytes: Either<f64, BigInt>,
+    pub filename: Option<String>,
+    pub mime_type: Option<String>,
+    pub output_path: String,
+    /// 是否已核对结尾字段中的明文摘要，tolerateErrors 时为 false
+    pub hash_verified: bool,
+    /// tolerateErrors 时跳过或以零填充的分片，其他情况为空数组
+    pub damaged_chunks: Vec<DamagedChunk>,
+    /// 整个文件的 MAC 和结尾字段是否校验通过，只有 tolerateErrors 时可能为 false
+    pub verified: bool,
+    pub verification_error: Option<String>,
+    /// chunkDecryptVolumes() 读取的分卷数，其他情况为 null
+    pub volumes: Option<u32>,
+}
+
+/// convertToChunked() 的结果
+#[napi(object, object_from_js = false, use_nullable = true)]
+pub struct ConvertToChunkedResult {
+    pub total_chunks: f64,
+    /// 明文大小（KB）
+    pub file_size: f64,
+    /// 明文大小（字节）
+    pub file_size_bytes: Either<f64, BigInt>,
+    /// 分片大小（KB）
+    pub chunk_size: f64,
+    /// 分片大小（字节）
+    pub chunk_size_bytes: Either<f64, BigInt>,
+}
+
+/// convertToMonolithic() 的结果
+#[napi(object, object_from_js = false, use_nullable = true)]
+pub struct ConvertToMonolithicResult {
+    /// 源文件的分片数
+    pub total_chunks: f64,
+    /// 明文大小（KB）
+    pub file_size: f64,
+    /// 明文大小（字节）
+    pub file_size_bytes: Either<f64, BigInt>,
+}
+
+/// rechunkFile() 的结果
+#[napi(object, object_from_js = false, use_nullable = true)]
+pub struct RechunkFileResult {
+    pub source_chunks: f64,
+    pub total_chunks: f64,
+    /// 明文大小（KB）
+    pub file_size: f64,
+    /// 明文大小（字节）
+    pub file_size_bytes: Either<f64, BigInt>,
+    /// 新的分片大小（KB）
+    pub chunk_size: f64,
+    /// 新的分片大小（字节）
+    pub chunk_size_bytes: Either<f64, BigInt>,
+}
+
+/// encryptFiles() / decryptFiles() 中每个文件的结果
+#[napi(object, object_from_js = false, use_nullable = true)]
+pub struct BatchFileResult {
+    pub input_path: String,
+    /// 实际写出的文件；解密时使用 restoreFilename 为恢复文件名后的路径
+    pub output_path: String,
+    /// ok / failed，dryRun 时为 planned / failed
+    pub status: String,
+    /// 明文大小（字节），失败时为 null
+    pub file_size_bytes: Option<Either<f64, BigInt>>,
+    pub error: Option<String>,
+}
+
+/// encryptFiles() / decryptFiles() 的结果
+#[napi(object, object_from_js = false, use_nullable = true)]
+pub struct BatchResult {
+    pub succeeded: u32,
+    pub failed: u32,
+    /// 成功处理的明文总大小（KB）
+    #[napi(js_name = "totalSizeKB")]
+    pub total_size_kb: f64,
+    /// 成功处理的明文总大小（字节）
+    pub total_size_bytes: Either<f64, BigInt>,
+    /// 整批耗时（毫秒）
+    pub elapsed_ms: f64,
+    /// 与输入顺序相同的每个文件的结果
+    pub files: Vec<BatchFileResult>,
+    /// dryRun 时计划处理的文件，否则为 null
+    pub plan: Option<Vec<PlannedFile>>,
+}
+
+/// dryRun 时计划处理的一个文件，不读写文件内容
+#[napi(object, object_from_js = false, use_nullable = true)]
+pub struct PlannedFile {
+    pub input_path: String,
+    /// 将要写出的文件；解密时使用 restoreFilename 为恢复文件名后的路径
+    pub output_path: String,
+    /// 明文大小（KB），旧格式的整体加密文件没有记录时为 null
+    #[napi(js_name = "fileSizeKB")]
+    pub file_size_kb: Option<f64>,
+    /// 明文大小（字节）
+    pub file_size_bytes: Option<Either<f64, BigInt>>,
+    /// 分片数，整体加密或解密时为 null
+    pub total_chunks: Option<f64>,
+    /// 输出路径上已有文件，执行时会被替换
+    pub exists: bool,
+    /// 与同一批中其它文件的冲突：输出路径相同、输出路径是另一个文件的输入，或只有大小写不同；没有冲突时为 null
+    pub collision: Option<String>,
+}
+
+/// migrateFile() / migrateFiles() 中每个文件的结果；失败时只有 path、status 和 error
+#[napi(object, object_from_js = false, use_nullable = true)]
+pub struct MigrateFileResult {
+    pub path: String,
+    /// migrated / skipped / failed
+    pub status: String,
+    /// 迁移前的格式版本
+    pub from_version: Option<u32>,
+    #[napi(js_name = "originalSizeKB")]
+    pub original_size_kb: Option<f64>,
+    pub original_size_bytes: Option<Either<f64, BigInt>>,
+    #[napi(js_name = "chunkSizeKB")]
+    pub chunk_size_kb: Option<f64>,
+    pub chunk_size_bytes: Option<Either<f64, BigInt>>,
+    pub total_chunks: Option<f64>,
+    #[napi(js_name = "encryptedSizeBeforeKB")]
+    pub encrypted_size_before_kb: Option<f64>,
+    pub encrypted_size_before_bytes: Option<Either<f64, BigInt>>,
+    #[napi(js_name = "encryptedSizeAfterKB")]
+    pub encrypted_size_after_kb: Option<f64>,
+    pub encrypted_size_after_bytes: Option<Either<f64, BigInt>>,
+    pub error: Option<String>,
+}
+
+/// updateEncryptedFile() 的结果
+#[napi(object, object_from_js = false, use_nullable = true)]
+pub struct UpdateEncryptedFileResult {
+    pub total_chunks: f64,
+    pub changed_chunks: f64,
+    #[napi(js_name = "rewrittenSizeKB")]
+    pub rewritten_size_kb: f64,
+    pub rewritten_size_bytes: Either<f64, BigInt>,
+    /// 是否原地改写了变化的分片（否则重写了整个文件）
+    pub in_place: bool,
+}
+
+/// appendChunkedFile() 的结果
+#[napi(object, object_from_js = false, use_nullable = true)]
+pub struct AppendChunkedFileResult {
+    pub total_chunks: f64,
+    /// 追加时写入（或重写）的分片数
+    pub written_chunks: f64,
+    #[napi(js_name = "appendedSizeKB")]
+    pub appended_size_kb: f64,
+    pub appended_size_bytes: Either<f64, BigInt>,
+    /// 追加后的明文大小（KB）
+    #[napi(js_name = "fileSizeKB")]
+    pub file_size_kb: f64,
+    /// 追加后的明文大小（字节）
+    pub file_size_bytes: Either<f64, BigInt>,
+}
+
+/// verifyChunkedFile() 中每个分片的结果
+#[napi(object, object_from_js = false, use_nullable = true)]
+pub struct ChunkReport {
+    pub index: u32,
+    /// ok / damaged
+    pub status: String,
+    /// 分片在明文中的起止位置（字节）
+    pub start: f64,
+    pub end: f64,
+    pub error: Option<String>,
+}
+
+/// verifyChunkedFile() 的结果
+#[napi(object, object_from_js = false, use_nullable = true)]
+pub struct VerifyChunkedFileResult {
+    pub path: String,
+    pub passed: bool,
+    pub total_chunks: f64,
+    pub damaged_chunks: f64,
+    #[napi(js_name = "originalSizeKB")]
+    pub original_size_kb: f64,
+    pub original_size_bytes: Either<f64, BigInt>,
+    #[napi(js_name = "chunkSizeKB")]
+    pub chunk_size_kb: f64,
+    pub chunk_size_bytes: Either<f64, BigInt>,
+    pub error: Option<String>,
+    pub chunks: Vec<ChunkReport>,
+}
+
+/// getChunkedFileMetadata() 中的结尾字段信息
+#[napi(object, object_from_js = false, use_nullable = true)]
+pub struct ChunkedFooterInfo {
+    pub indexed_chunks: f64,
+    pub has_chunk_checksums: bool,
+    #[napi(js_name = "plaintextSizeKB")]
+    pub plaintext_size_kb: Option<f64>,
+    pub plaintext_size_bytes: Option<Either<f64, BigInt>>,
+    pub has_digest: bool,
+    pub hash_algorithm: Option<String>,
+    /// 明文摘要（十六进制），需要提供密钥
+    pub digest: Option<String>,
+    /// 摘要算法为 SHA-256 时与 digest 相同
+    pub sha256: Option<String>,
+}
+
+/// getChunkedFileMetadata() 的结果
+#[napi(object, object_from_js = false, use_nullable = true)]
+pub struct ChunkedFileMetadata {
+    pub total_chunks: f64,
+    #[napi(js_name = "fileSizeKB")]
+    pub file_size_kb: f64,
+    pub file_size_bytes: Either<f64, BigInt>,
+    #[napi(js_name = "chunkSizeKB")]
+    pub chunk_size_kb: f64,
+    pub chunk_size_bytes: Either<f64, BigInt>,
+    pub compression: Option<String>,
+    /// 文件没有结尾字段时为 null
+    pub footer: Option<ChunkedFooterInfo>,
+}
+
+/// repairFile() 的结果
+#[napi(object, object_from_js = false, use_nullable = true)]
+pub struct RepairFileResult {
+    pub checked_blocks: u32,
+    pub damaged_blocks: u32,
+    pub repaired_blocks: u32,
+    pub unrecoverable_blocks: u32,
+    /// 所有损坏的块都已修复
+    pub repaired: bool,
+}
+
+/// storeFile() 的结果
+#[napi(object, object_from_js = false, use_nullable = true)]
+pub struct StoreFileResult {
+    pub name: String,
+    #[napi(js_name = "fileSizeKB")]
+    pub file_size_kb: f64,
+    pub file_size_bytes: Either<f64, BigInt>,
+    pub total_chunks: f64,
+    /// 新写入存储的分片数
+    pub new_chunks: f64,
+    /// 存储中已有、直接复用的分片数
+    pub reused_chunks: f64,
+    #[napi(js_name = "storedSizeKB")]
+    pub stored_size_kb: f64,
+    pub stored_size_bytes: Either<f64, BigInt>,
+}
+
+/// restoreFile() 的结果
+#[napi(object, object_from_js = false, use_nullable = true)]
+pub struct RestoreFileResult {
+    pub name: String,
+    #[napi(js_name = "fileSizeKB")]
+    pub file_size_kb: f64,
+    pub file_size_bytes: Either<f64, BigInt>,
+    pub total_chunks: f64,
+    pub output_path: String,
+}
+
+/// listStoredFiles() 中的一个文件
+#[napi(object, object_from_js = false, use_nullable = true)]
+pub struct StoredFileEntry {
+    pub name: String,
+    #[napi(js_name = "fileSizeKB")]
+    pub file_size_kb: f64,
+    pub file_size_bytes: Either<f64, BigInt>,
+    pub total_chunks: f64,
+    /// 保存时间，Unix 毫秒时间戳
+    pub created_at: f64,
+    pub algorithm: String,
+}
+
+/// 只报告明文和密文大小的操作的结果（opensslEncryptFile、rcloneEncryptFile）
+#[napi(object, object_from_js = false, use_nullable = true)]
+pub struct FileSizes {
+    #[napi(js_name = "fileSizeKB")]
+    pub file_size_kb: f64,
+    pub file_size_bytes: Either<f64, BigInt>,
+    #[napi(js_name = "encryptedSizeKB")]
+    pub encrypted_size_kb: f64,
+    pub encrypted_size_bytes: Either<f64, BigInt>,
+}
+
+/// 只报告大小和输出路径的解密操作的结果（opensslDecryptFile、rcloneDecryptFile、s3DecryptFile）
+#[napi(object, object_from_js = false, use_nullable = true)]
+pub struct DecryptedFile {
+    #[napi(js_name = "fileSizeKB")]
+    pub file_size_kb: f64,
+    pub file_size_bytes: Either<f64, BigInt>,
+    #[napi(js_name = "encryptedSizeKB")]
+    pub encrypted_size_kb: f64,
+    pub encrypted_size_bytes: Either<f64, BigInt>,
+    pub output_path: String,
+}
+
+/// ageEncryptFile() 的结果
+#[napi(object, object_from_js = false, use_nullable = true)]
+pub struct AgeEncryptResult {
+    #[napi(js_name = "fileSizeKB")]
+    pub file_size_kb: f64,
+    pub file_size_bytes: Either<f64, BigInt>,
+    #[napi(js_name = "encryptedSizeKB")]
+    pub encrypted_size_kb: f64,
+    pub encrypted_size_bytes: Either<f64, BigInt>,
+    /// 是否使用口令（scrypt）加密
+    pub passphrase: bool,
+    pub armored: bool,
+}
+
+/// ageDecryptFile() 的结果
+#[napi(object, object_from_js = false, use_nullable = true)]
+pub struct AgeDecryptResult {
+    #[napi(js_name = "fileSizeKB")]
+    pub file_size_kb: f64,
+    pub file_size_bytes: Either<f64, BigInt>,
+    #[napi(js_name = "encryptedSizeKB")]
+    pub encrypted_size_kb: f64,
+    pub encrypted_size_bytes: Either<f64, BigInt>,
+    pub passphrase: bool,
+    pub output_path: String,
+}
+
+/// generateAgeIdentity() 的结果
+#[napi(object, object_from_js = false, use_nullable = true)]
+pub struct AgeIdentity {
+    /// AGE-SECRET-KEY-1... 私钥
+    pub identity: String,
+    /// age1... 公钥
+    pub recipient: String,
+}
+
+/// gpgDecryptFile() 的结果
+#[napi(object, object_from_js = false, use_nullable = true)]
+pub struct GpgDecryptResult {
+    #[napi(js_name = "fileSizeKB")]
+    pub file_size_kb: f64,
+    pub file_size_bytes: Either<f64, BigInt>,
+    #[napi(js_name = "encryptedSizeKB")]
+    pub encrypted_size_kb: f64,
+    pub encrypted_size_bytes: Either<f64, BigInt>,
+    /// 字面数据包中的文件名
+    pub filename: String,
+    pub cipher: String,
+    pub compression: String,
+    pub output_path: String,
+}
+
+/// gpgEncryptFile() 的结果
+#[napi(object, object_from_js = false, use_nullable = true)]
+pub struct GpgEncryptResult {
+    #[napi(js_name = "fileSizeKB")]
+    pub file_size_kb: f64,
+    pub file_size_bytes: Either<f64, BigInt>,
+    #[napi(js_name = "encryptedSizeKB")]
+    pub encrypted_size_kb: f64,
+    pub encrypted_size_bytes: Either<f64, BigInt>,
+    pub filename: String,
+    pub profile: String,
+    pub cipher: String,
+    pub aead: String,
+}
+
+/// encryptToZip() 的结果
+#[napi(object, object_from_js = false, use_nullable = true)]
+pub struct ZipResult {
+    pub files: u32,
+    pub directories: u32,
+    #[napi(js_name = "fileSizeKB")]
+    pub file_size_kb: f64,
+    pub file_size_bytes: Either<f64, BigInt>,
+    #[napi(js_name = "encryptedSizeKB")]
+    pub encrypted_size_kb: f64,
+    pub encrypted_size_bytes: Either<f64, BigInt>,
+}
+
+/// create7zArchive() 的结果
+#[napi(object, object_from_js = false, use_nullable = true)]
+pub struct SevenZipResult {
+    pub files: u32,
+    #[napi(js_name = "fileSizeKB")]
+    pub file_size_kb: f64,
+    pub file_size_bytes: Either<f64, BigInt>,
+    #[napi(js_name = "encryptedSizeKB")]
+    pub encrypted_size_kb: f64,
+    pub encrypted_size_bytes: Either<f64, BigInt>,
+}
+
+/// extract7zArchive() 的结果
+#[napi(object, object_from_js = false, use_nullable = true)]
+pub struct ExtractResult {
+    pub files: u32,
+    pub directories: u32,
+    #[napi(js_name = "fileSizeKB")]
+    pub file_size_kb: f64,
+    pub file_size_bytes: Either<f64, BigInt>,
+    #[napi(js_name = "encryptedSizeKB")]
+    pub encrypted_size_kb: f64,
+    pub encrypted_size_bytes: Either<f64, BigInt>,
+    pub output_dir: String,
+}
+
+/// secretstreamEncryptFile() 的结果
+#[napi(object, object_from_js = false, use_nullable = true)]
+pub struct SecretStreamEncryptResult {
+    #[napi(js_name = "fileSizeKB")]
+    pub file_size_kb: f64,
+    pub file_size_bytes: Either<f64, BigInt>,
+    #[napi(js_name = "encryptedSizeKB")]
+    pub encrypted_size_kb: f64,
+    pub encrypted_size_bytes: Either<f64, BigInt>,
+    pub chunks: f64,
+}
+
+/// secretstreamDecryptFile() 的结果
+#[napi(object, object_from_js = false, use_nullable = true)]
+pub struct SecretStreamDecryptResult {
+    #[napi(js_name = "fileSizeKB")]
+    pub file_size_kb: f64,
+    pub file_size_bytes: Either<f64, BigInt>,
+    #[napi(js_name = "encryptedSizeKB")]
+    pub encrypted_size_kb: f64,
+    pub encrypted_size_bytes: Either<f64, BigInt>,
+    pub chunks: f64,
+    pub output_path: String,
+}
+
+/// tinkEncryptFile() 的结果
+#[napi(object, object_from_js = false, use_nullable = true)]
+pub struct TinkEncryptResult {
+    #[napi(js_name = "fileSizeKB")]
+    pub file_size_kb: f64,
+    pub file_size_bytes: Either<f64, BigInt>,
+    #[napi(js_name = "encryptedSizeKB")]
+    pub encrypted_size_kb: f64,
+    pub encrypted_size_bytes: Either<f64, BigInt>,
+    pub segments: u32,
+}
+
+/// tinkDecryptFile() 的结果
+#[napi(object, object_from_js = false, use_nullable = true)]
+pub struct TinkDecryptResult {
+    #[napi(js_name = "fileSizeKB")]
+    pub file_size_kb: f64,
+    pub file_size_bytes: Either<f64, BigInt>,
+    #[napi(js_name = "encryptedSizeKB")]
+    pub encrypted_size_kb: f64,
+    pub encrypted_size_bytes: Either<f64, BigInt>,
+    pub segments: u32,
+    pub output_path: String,
+}
+
+/// decryptJwe() 的结果
+#[napi(object, object_from_js = false, use_nullable = true)]
+pub struct JweDecryptResult {
+    pub plaintext: Buffer,
+    /// 受保护的头部
+    #[napi(ts_type = "Record<string, unknown>")]
+    pub header: serde_json::Value,
+}
+
+/// pasetoDecrypt() 的结果
+#[napi(object, object_from_js = false, use_nullable = true)]
+pub struct PasetoDecryptResult {
+    pub payload: Buffer,
+    /// 没有 footer 时为空 Buffer
+    pub footer: Buffer,
+}
+
+/// cmsEncryptFile() 的结果
+#[napi(object, object_from_js = false, use_nullable = true)]
+pub struct CmsEncryptResult {
+    #[napi(js_name = "fileSizeKB")]
+    pub file_size_kb: f64,
+    pub file_size_bytes: Either<f64, BigInt>,
+    #[napi(js_name = "encryptedSizeKB")]
+    pub encrypted_size_kb: f64,
+    pub encrypted_size_bytes: Either<f64, BigInt>,
+    pub recipients: u32,
+}
+
+/// generateMinisignKeyPair() 的结果
+#[napi(object, object_from_js = false, use_nullable = true)]
+pub struct MinisignKeyPair {
+    pub public_key: String,
+    pub secret_key: String,
+    /// 十六进制密钥 id
+    pub key_id: String,
+}
+
+/// minisignSignFile() 的结果
+#[napi(object, object_from_js = false, use_nullable = true)]
+pub struct MinisignSignResult {
+    pub signature_path: String,
+    /// .minisig 文件的内容
+    pub signature: String,
+    pub key_id: String,
+    pub trusted_comment: String,
+    #[napi(js_name = "fileSizeKB")]
+    pub file_size_kb: f64,
+    pub file_size_bytes: Either<f64, BigInt>,
+}
+
+/// minisignVerifyFile() 的结果
+#[napi(object, object_from_js = false, use_nullable = true)]
+pub struct MinisignVerifyResult {
+    pub valid: bool,
+    pub key_id: String,
+    pub trusted_comment: String,
+    /// 验证失败的原因
+    pub error: Option<String>,
+}
+
+/// generateSaltpackKeyPair() 的结果
+#[napi(object, object_from_js = false, use_nullable = true)]
+pub struct SaltpackKeyPair {
+    pub public_key: Buffer,
+    pub secret_key: Buffer,
+}
+
+/// saltpackEncryptFile() 的结果
+#[napi(object, object_from_js = false, use_nullable = true)]
+pub struct SaltpackEncryptResult {
+    #[napi(js_name = "fileSizeKB")]
+    pub file_size_kb: f64,
+    pub file_size_bytes: Either<f64, BigInt>,
+    #[napi(js_name = "encryptedSizeKB")]
+    pub encrypted_size_kb: f64,
+    pub encrypted_size_bytes: Either<f64, BigInt>,
+    pub recipients: u32,
+    pub armored: bool,
+}
+
+/// saltpackDecryptFile() 的结果
+#[napi(object, object_from_js = false, use_nullable = true)]
+pub struct SaltpackDecryptResult {
+    #[napi(js_name = "fileSizeKB")]
+    pub file_size_kb: f64,
+    pub file_size_bytes: Either<f64, BigInt>,
+    #[napi(js_name = "encryptedSizeKB")]
+    pub encrypted_size_kb: f64,
+    pub encrypted_size_bytes: Either<f64, BigInt>,
+    /// 发送者公钥，匿名发送时为 null
+    pub sender: Option<Buffer>,
+    pub output_path: String,
+}
+
+/// hlsEncryptSegment() 的结果
+#[napi(object, object_from_js = false, use_nullable = true)]
+pub struct HlsSegmentResult {
+    #[napi(js_name = "fileSizeKB")]
+    pub file_size_kb: f64,
+    pub file_size_bytes: Either<f64, BigInt>,
+    #[napi(js_name = "encryptedSizeKB")]
+    pub encrypted_size_kb: f64,
+    pub encrypted_size_bytes: Either<f64, BigInt>,
+    /// 可直接写入播放列表的 #EXT-X-KEY 行
+    pub key_line: String,
+    /// 0x 开头的十六进制 IV
+    pub iv: String,
+    pub key_path: Option<String>,
+}
+
+/// cencEncryptFile() 的结果
+#[napi(object, object_from_js = false, use_nullable = true)]
+pub struct CencEncryptResult {
+    #[napi(js_name = "fileSizeKB")]
+    pub file_size_kb: f64,
+    pub file_size_bytes: Either<f64, BigInt>,
+    #[napi(js_name = "encryptedSizeKB")]
+    pub encrypted_size_kb: f64,
+    pub encrypted_size_bytes: Either<f64, BigInt>,
+    /// cenc / cbcs
+    pub scheme: String,
+    /// 十六进制 KID
+    pub kid: String,
+    pub tracks: u32,
+    pub fragments: u32,
+    pub samples: f64,
+}
+
+/// gocryptfsEncryptDirectory() 的结果
+#[napi(object, object_from_js = false, use_nullable = true)]
+pub struct GocryptfsResult {
+    pub files: u32,
+    pub directories: u32,
+    #[napi(js_name = "fileSizeKB")]
+    pub file_size_kb: f64,
+    pub file_size_bytes: Either<f64, BigInt>,
+    #[napi(js_name = "encryptedSizeKB")]
+    pub encrypted_size_kb: f64,
+    pub encrypted_size_bytes: Either<f64, BigInt>,
+    /// 解密 gocryptfs.conf 得到的主密钥，可用于 gocryptfsEncryptPath() 等
+    pub master_key: Buffer,
+}
+
+/// encryptDirectory() 的结果
+#[napi(object, object_from_js = false, use_nullable = true)]
+pub struct DirectoryEncryptResult {
+    pub files: u32,
+    pub directories: u32,
+    #[napi(js_name = "fileSizeKB")]
+    pub file_size_kb: f64,
+    pub file_size_bytes: Either<f64, BigInt>,
+    /// 加密后的总大小（KB），dryRun 时为 0
+    #[napi(js_name = "encryptedSizeKB")]
+    pub encrypted_size_kb: f64,
+    pub encrypted_size_bytes: Either<f64, BigInt>,
+    /// 加密清单的路径
+    pub manifest_path: String,
+    /// dryRun 时计划加密的文件，否则为 null
+    pub plan: Option<Vec<PlannedFile>>,
+}
+
+/// decryptDirectory() 的结果
+#[napi(object, object_from_js = false, use_nullable = true)]
+pub struct DirectoryDecryptResult {
+    /// 还原的文件数
+    pub files: u32,
+    /// 还原的目录数
+    pub directories: u32,
+    #[napi(js_name = "fileSizeKB")]
+    pub file_size_kb: f64,
+    pub file_size_bytes: Either<f64, BigInt>,
+    /// dryRun 时计划还原的文件，否则为 null
+    pub plan: Option<Vec<PlannedFile>>,
+}
+
+/// encryptArchive() 的结果
+#[napi(object, object_from_js = false, use_nullable = true)]
+pub struct ArchiveEncryptResult {
+    pub files: u32,
+    pub directories: u32,
+    #[napi(js_name = "fileSizeKB")]
+    pub file_size_kb: f64,
+    pub file_size_bytes: Either<f64, BigInt>,
+    /// 加密后的归档大小（KB）
+    #[napi(js_name = "archiveSizeKB")]
+    pub archive_size_kb: f64,
+    pub archive_size_bytes: Either<f64, BigInt>,
+    pub total_chunks: f64,
+}
+
+/// listArchive() 返回的一个条目
+#[napi(object, object_from_js = false, use_nullable = true)]
+pub struct ArchiveEntry {
+    /// 相对于归档根部的路径，使用 `/` 分隔
+    pub path: String,
+    /// file / directory
+    #[napi(js_name = "type")]
+    pub kind: String,
+    /// 明文大小，目录为 0
+    #[napi(js_name = "sizeKB")]
+    pub size_kb: f64,
+    pub size_bytes: Either<f64, BigInt>,
+    pub mode: u32,
+    /// 修改时间，Unix 毫秒时间戳
+    pub modified: f64,
+}
+
+/// listArchive() 的结果
+#[napi(object, object_from_js = false, use_nullable = true)]
+pub struct ArchiveListResult {
+    /// archive（encryptArchive 创建的文件）/ directory（encryptDirectory 创建的目录）
+    pub format: String,
+    pub algorithm: String,
+    /// 创建时间，Unix 毫秒时间戳
+    pub created_at: f64,
+    /// 先列出目录再列出文件，各自按路径排序
+    pub entries: Vec<ArchiveEntry>,
+    pub files: u32,
+    pub directories: u32,
+    #[napi(js_name = "fileSizeKB")]
+    pub file_size_kb: f64,
+    pub file_size_bytes: Either<f64, BigInt>,
+}
+
+/// s3EncryptFile() 的结果
+#[napi(object, object_from_js = false, use_nullable = true)]
+pub struct S3EncryptResult {
+    #[napi(js_name = "fileSizeKB")]
+    pub file_size_kb: f64,
+    pub file_size_bytes: Either<f64, BigInt>,
+    #[napi(js_name = "encryptedSizeKB")]
+    pub encrypted_size_kb: f64,
+    pub encrypted_size_bytes: Either<f64, BigInt>,
+    /// 上传时需要设置的 x-amz-meta-* 对象元数据（写入指令文件时同样返回）
+    #[napi(ts_type = "Record<string, string>")]
+    pub metadata: serde_json::Value,
+}
+
+/// veracryptListFiles() 中的一个条目
+#[napi(object, object_from_js = false, use_nullable = true)]
+pub struct VeraCryptEntry {
+    pub path: String,
+    pub is_directory: bool,
+    #[napi(js_name = "fileSizeKB")]
+    pub file_size_kb: f64,
+    pub file_size_bytes: Either<f64, BigInt>,
+    /// 修改时间，Unix 毫秒时间戳
+    pub modified: Option<f64>,
+}
+
+/// veracryptExtractFiles() 的结果
+#[napi(object, object_from_js = false, use_nullable = true)]
+pub struct VeraCryptExtractResult {
+    pub files: u32,
+    pub directories: u32,
+    #[napi(js_name = "fileSizeKB")]
+    pub file_size_kb: f64,
+    pub file_size_bytes: Either<f64, BigInt>,
+    #[napi(js_name = "encryptedSizeKB")]
+    pub encrypted_size_kb: f64,
+    pub encrypted_size_bytes: Either<f64, BigInt>,
+    /// FAT12 / FAT16 / FAT32 / exFAT
+    pub filesystem: String,
+    pub hash: String,
+    pub hidden_volume: bool,
+    pub output_dir: String,
+}
+
+/// inspectFile() 的结果，文件中未记录的字段为 null
+#[napi(object, object_from_js = false, use_nullable = true)]
+pub struct InspectFileResult {
+    pub format_version: u32,
+    /// monolithic / chunked
+    pub layout: String,
+    pub chunked: bool,
+    pub algorithm: Option<String>,
+    #[napi(js_name = "fileSizeKB")]
+    pub file_size_kb: Option<f64>,
+    pub file_size_bytes: Option<Either<f64, BigInt>>,
+    #[napi(js_name = "encryptedSizeKB")]
+    pub encrypted_size_kb: f64,
+    pub encrypted_size_bytes: Either<f64, BigInt>,
+    #[napi(js_name = "chunkSizeKB")]
+    pub chunk_size_kb: Option<f64>,
+    pub chunk_size_bytes: Option<Either<f64, BigInt>>,
+    pub total_chunks: f64,
+    pub kdf: Option<String>,
+    pub has_filename: bool,
+    /// 需要提供密钥
+    pub filename: Option<String>,
+    pub has_metadata: bool,
+    /// 需要提供密钥，保存时为 Buffer 的返回 Buffer，否则为 JSON 值
+    pub metadata: Option<Either<Buffer, serde_json::Value>>,
+    pub mime_type: Option<String>,
+    /// 加密时间，Unix 毫秒时间戳
+    pub created_at: Option<f64>,
+    pub tool_version: Option<String>,
+    pub format_revision: Option<u32>,
+    pub header_authenticated: bool,
+    pub chunks_authenticated: bool,
+    pub has_parity: bool,
+    pub hash_algorithm: Option<String>,
+    pub compression: Option<String>,
+    pub sparse: bool,
+    pub appendable: bool,
+}
+
+/// warmup() 的结果
+#[napi(object, object_from_js = false, use_nullable = true)]
+pub struct WarmupResult {
+    /// 调用前是否已完成初始化
+    pub already_warm: bool,
+    pub duration_ms: f64,
+}
+
+/// getHardwareInfo() 的结果
+#[napi(object, object_from_js = false, use_nullable = true)]
+pub struct HardwareInfo {
+    /// CPU 架构，如 "x86_64"、"aarch64"
+    pub arch: String,
+    /// x86 AES-NI 指令
+    pub aes_ni: bool,
+    /// x86 PCLMULQDQ 指令
+    pub pclmul: bool,
+    /// ARM NEON 指令
+    pub neon: bool,
+    /// ARMv8 AES 指令
+    pub arm_aes: bool,
+    /// 本库的 AES 实现是否使用硬件指令
+    pub aes_accelerated: bool,
+    /// algorithm 为 "auto" 时选择的算法
+    pub auto_algorithm: String,
+}
+
+/// benchmarkAlgorithms() 中的一项测量
+#[napi(object, object_from_js = false, use_nullable = true)]
+pub struct BenchmarkMeasurement {
+    /// "aes"、"chacha20poly1305"、"sha256" 或 "blake3"
+    pub algorithm: String,
+    /// "encrypt"、"decrypt" 或 "hash"
+    pub operation: String,
+    /// 每次处理的数据大小（KB）
+    pub size_kb: u32,
+    /// 计时期间执行的次数
+    pub iterations: f64,
+    /// 吞吐量，MB 按 1024 × 1024 字节计算
+    pub mb_per_sec: f64,
+}
+
+/// benchmarkAlgorithms() 的结果
+#[napi(object, object_from_js = false, use_nullable = true)]
+pub struct BenchmarkResult {
+    /// CPU 架构，如 "x86_64"、"aarch64"
+    pub arch: String,
+    /// 本库的 AES 实现是否使用硬件指令
+    pub aes_accelerated: bool,
+    pub measurements: Vec<BenchmarkMeasurement>,
+    /// 测量的加密算法中加密和解密平均吞吐量最高的算法，没有测量加密算法时为 null
+    pub fastest_algorithm: Option<String>,
+    /// 测量的摘要算法中吞吐量最高的算法，没有测量摘要算法时为 null
+    pub fastest_hash: Option<String>,
+    /// 整个测量耗费的时间（毫秒）
+    pub duration_ms: f64,
+}
+
+/// getConfig() 的结果
+#[napi(object, object_from_js = false, use_nullable = true)]
+pub struct ModuleConfig {
+    pub threads: u32,
+    pub strict: bool,
+    pub log_level: String,
+    pub allowed_algorithms: Vec<String>,
+    pub reject_legacy_formats: bool,
+    pub require_authentication: bool,
+}
+
+/// setDecryptPolicy() 返回的生效策略
+#[napi(object, object_from_js = false, use_nullable = true)]
+pub struct DecryptPolicyResult {
+    pub reject_legacy_formats: bool,
+    pub require_authentication: bool,
+}
@@ -0,0 +1,171 @@
+use ::rsa::{BigUint, Oaep, Pkcs1v15Encrypt, RsaPrivateKey, RsaPublicKey};
+use base64::engine::general_purpose::URL_SAFE_NO_PAD;
+use base64::Engine;
+use sha1::Sha1;
+use sha2::Sha256;
+
+use crate::errors::CodedError;
+
+/// 接受的最小模数长度
+const MIN_MODULUS_BITS: usize = 2048;
+/// 公钥接受的最大模数长度，限制运算时间；私钥受 rsa crate 的限制最大为 4096 位
+const MAX_MODULUS_BITS: usize = 8192;
+
+/// OAEP 使用的摘要算法（同时用于 MGF1）
+#[derive(Clone, Copy, PartialEq)]
+pub enum OaepHash {
+    Sha1,
+    Sha256,
+}
+
+impl OaepHash {
+    fn padding(&self) -> Oaep {
+        match self {
+            OaepHash::Sha1 => Oaep::new::<Sha1>(),
+            OaepHash::Sha256 => Oaep::new::<Sha256>(),
+        }
+    }
+}
+
+/// RSA 密钥（由 rsa crate 实现），从 JWK 或证书中的公钥读取；只有公钥时只能加密
+pub struct RsaKey {
+    public: RsaPublicKey,
+    private: Option<RsaPrivateKey>,
+}
+
+fn jwk_integer(jwk: &serde_json::Value, name: &str) -> Result<Option<BigUint>, CodedError> {
+    match jwk.get(name) {
+        None => Ok(None),
+        Some(value) => {
+            let encoded = value
+                .as_str()
+                .ok_or_else(|| CodedError::invalid_argument(format!("Invalid RSA JWK: \"{}\" must be a string", name)))?;
+            let bytes = URL_SAFE_NO_PAD
+                .decode(encoded.trim_end_matches('='))
+                .map_err(|_| CodedError::invalid_argument(format!("Invalid RSA JWK: \"{}\" is not base64url", name)))?;
+            Ok(Some(BigUint::from_bytes_be(&bytes)))
+        }
+    }
+}
+
+fn check_modulus_size(n: &BigUint) -> Result<(), CodedError> {
+    let bits = n.bits();
+    if !(MIN_MODULUS_BITS..=MAX_MODULUS_BITS).contains(&bits) {
+        return Err(CodedError::invalid_key_length(format!("RSA key must be between {} and {} bits, got {}", MIN_MODULUS_BITS, MAX_MODULUS_BITS, bits)));
+    }
+    Ok(())
+}
+
+impl RsaKey {
+    /// 读取 `kty` 为 `RSA` 的 JWK（n、e，私钥还需要 d，有 p 和 q 时直接使用，否则由 d 恢复）
+    pub fn from_jwk(jwk: &serde_json::Value) -> Result<Self, CodedError> {
+        let n = jwk_integer(jwk, "n")?.ok_or_else(|| CodedError::invalid_argument("Invalid RSA JWK: missing \"n\""))?;
+        let e = jwk_integer(jwk, "e")?.ok_or_else(|| CodedError::invalid_argument("Invalid RSA JWK: missing \"e\""))?;
+        let Some(d) = jwk_integer(jwk, "d")? else {
+            return Self::from_public_components(n, e);
+        };
+        check_modulus_size(&n)?;
+        let primes = match (jwk_integer(jwk, "p")?, jwk_integer(jwk, "q")?) {
+            (Some(p), Some(q)) => vec![p, q],
+            _ => Vec::new(),
+        };
+        let private = RsaPrivateKey::from_components(n, e, d, primes).map_err(|err| CodedError::invalid_argument(format!("Invalid RSA JWK: {}", err)))?;
+        Ok(RsaKey { public: private.to_public_key(), private: Some(private) })
+    }
+
+    /// 由大端字节的 n 和 e 构造公钥
+    pub fn from_public_bytes(n: &[u8], e: &[u8]) -> Result<Self, CodedError> {
+        Self::from_public_components(BigUint::from_bytes_be(n), BigUint::from_bytes_be(e))
+    }
+
+    fn from_public_components(n: BigUint, e: BigUint) -> Result<Self, CodedError> {
+        check_modulus_size(&n)?;
+        let public = RsaPublicKey::new_with_max_size(n, e, MAX_MODULUS_BITS).map_err(|_| CodedError::bad_format("Invalid RSA key: bad modulus or exponent"))?;
+        Ok(RsaKey { public, private: None })
+    }
+
+    pub fn is_private(&self) -> bool {
+        self.private.is_some()
+    }
+
+    /// RSAES-OAEP 加密（RFC 8017），标签为空
+    pub fn oaep_encrypt(&self, hash: OaepHash, message: &[u8]) -> Result<Vec<u8>, CodedError> {
+        self.public
+            .encrypt(&mut rand::thread_rng(), hash.padding(), message)
+            .map_err(|_| CodedError::invalid_argument("Message is too long for RSA-OAEP with this key"))
+    }
+
+    /// RSAES-PKCS1-v1_5 加密（RFC 8017），用于只支持这种填充的 CMS / S/MIME 接收方
+    pub fn pkcs1_encrypt(&self, message: &[u8]) -> Result<Vec<u8>, CodedError> {
+        self.public
+            .encrypt(&mut rand::thread_rng(), Pkcs1v15Encrypt, message)
+            .map_err(|_| CodedError::invalid_argument("Message is too long for RSA PKCS#1 v1.5 with this key"))
+    }
+
+    /// RSAES-OAEP 解密，私钥运算使用 CRT 和随机盲化；所有失败都返回同一个错误，避免泄露失败的原因
+    pub fn oaep_decrypt(&self, hash: OaepHash, ciphertext: &[u8]) -> Result<Vec<u8>, CodedError> {
+        let private = self.private.as_ref().ok_or_else(|| CodedError::invalid_argument("RSA key has no private exponent \"d\""))?;
+        private
+            .decrypt_blinded(&mut rand::thread_rng(), hash.padding(), ciphertext)
+            .map_err(|_| CodedError::auth_failed("RSA-OAEP decryption failed"))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const MESSAGE: &[u8] = b"zippy-encryptor RSA-OAEP test";
+
+    fn test_key() -> RsaKey {
+        let jwk: serde_json::Value = serde_json::from_str(include_str!("../testdata/rsa/key.jwk")).unwrap();
+        RsaKey::from_jwk(&jwk).unwrap()
+    }
+
+    #[test]
+    fn decrypts_openssl_oaep_ciphertexts() {
+        // 由 OpenSSL（Python cryptography）以同一密钥加密
+        let key = test_key();
+        for (hash, ciphertext) in [(OaepHash::Sha1, include_str!("../testdata/rsa/oaep-sha1.hex")), (OaepHash::Sha256, include_str!("../testdata/rsa/oaep-sha256.hex"))] {
+            let ciphertext = hex::decode(ciphertext.trim()).unwrap();
+            assert_eq!(key.oaep_decrypt(hash, &ciphertext).unwrap(), MESSAGE);
+            let mut modified = ciphertext.clone();
+            modified[100] ^= 1;
+            assert!(key.oaep_decrypt(hash, &modified).is_err());
+        }
+        assert!(key.oaep_decrypt(OaepHash::Sha256, &hex::decode(include_str!("../testdata/rsa/oaep-sha1.hex").trim()).unwrap()).is_err());
+    }
+
+    #[test]
+    fn recovers_primes_when_jwk_has_only_d() {
+        let mut jwk: serde_json::Value = serde_json::from_str(include_str!("../testdata/rsa/key.jwk")).unwrap();
+        for name in ["p", "q", "dp", "dq", "qi"] {
+            jwk.as_object_mut().unwrap().remove(name);
+        }
+        let key = RsaKey::from_jwk(&jwk).unwrap();
+        let ciphertext = test_key().oaep_encrypt(OaepHash::Sha256, MESSAGE).unwrap();
+        assert_eq!(key.oaep_decrypt(OaepHash::Sha256, &ciphertext).unwrap(), MESSAGE);
+    }
+
+    #[test]
+    fn public_key_encrypts_but_cannot_decrypt() {
+        let jwk: serde_json::Value = serde_json::from_str(include_str!("../testdata/rsa/key.jwk")).unwrap();
+        let public = RsaKey::from_jwk(&serde_json::json!({ "kty": "RSA", "n": jwk["n"], "e": jwk["e"] })).unwrap();
+        assert!(!public.is_private());
+        let ciphertext = public.oaep_encrypt(OaepHash::Sha1, MESSAGE).unwrap();
+        assert!(public.oaep_decrypt(OaepHash::Sha1, &ciphertext).is_err());
+        assert_eq!(test_key().oaep_decrypt(OaepHash::Sha1, &ciphertext).unwrap(), MESSAGE);
+        assert!(public.oaep_encrypt(OaepHash::Sha256, &[0u8; 256]).is_err());
+    }
+
+    #[test]
+    fn rejects_empty_and_undersized_moduli() {
+        for n in ["", "AA", "AAAA", "AQAB"] {
+            let jwk = serde_json::json!({ "kty": "RSA", "n": n, "e": "AQAB" });
+            assert!(RsaKey::from_jwk(&jwk).is_err(), "n = {:?}", n);
+            let jwk = serde_json::json!({ "kty": "RSA", "n": n, "e": "AQAB", "d": "AQAB" });
+            assert!(RsaKey::from_jwk(&jwk).is_err(), "n = {:?}", n);
+        }
+        assert!(RsaKey::from_public_bytes(&[], &[1, 0, 1]).is_err());
+    }
+}
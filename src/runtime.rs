@@ -0,0 +1,32 @@
+use std::sync::OnceLock;
+use std::time::Instant;
+
+use crate::config;
+use crate::crypto;
+use crate::errors::CodedError;
+
+/// 加密后端自检结果，首次使用时运行一次
+static SELF_TEST: OnceLock<Result<(), CodedError>> = OnceLock::new();
+
+/// 确保运行环境已初始化：读取配置并运行算法自检，配置有误时返回配置错误
+///
+/// 模块加载时不做任何初始化，只有第一次执行加密操作（或调用 `warmup()`）时才会付出这部分开销。
+pub fn ensure_ready() -> Result<(), CodedError> {
+    config::get()?;
+    SELF_TEST
+        .get_or_init(|| {
+            let started = Instant::now();
+            let result = crypto::self_test();
+            config::log(
+                config::LogLevel::Debug,
+                format!("algorithm self-test finished in {:?}", started.elapsed()),
+            );
+            result
+        })
+        .clone()
+}
+
+/// 是否已经完成初始化
+pub fn is_ready() -> bool {
+    SELF_TEST.get().is_some()
+}
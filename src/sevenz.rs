@@ -0,0 +1,517 @@
+use std::fs::{self, File};
+use std::io::{self, BufWriter, Read, Seek, SeekFrom, Write};
+use std::path::{Path, PathBuf};
+use std::time::SystemTime;
+
+use aes::Aes256;
+use cbc::cipher::block_padding::NoPadding;
+use cbc::cipher::{BlockEncryptMut, KeyIvInit};
+use sha2::{Digest, Sha256};
+use sevenz_rust::{AesEncoderOptions, Password, SevenZArchiveEntry, SevenZMethod, SevenZReader, SevenZWriter};
+
+use crate::errors::{open_error, read_error, write_error, CodedError, ErrorCode};
+use crate::fd::{self, Target};
+use crate::walk::{self, safe_join};
+
+/// Windows 属性中表示高 16 位为 Unix 文件类型和权限的标志（p7zip / 7-Zip 的约定）
+const ATTRIBUTE_UNIX_EXTENSION: u32 = 0x8000;
+
+/// 7z 起始头的长度：签名、版本、起始头 CRC、下一头部的偏移、长度和 CRC
+const SIGNATURE_HEADER_LEN: u64 = 32;
+
+// 7z 头部中使用的属性 ID
+const K_END: u8 = 0x00;
+const K_HEADER: u8 = 0x01;
+const K_PACK_INFO: u8 = 0x06;
+const K_UNPACK_INFO: u8 = 0x07;
+const K_SIZE: u8 = 0x09;
+const K_CRC: u8 = 0x0A;
+const K_FOLDER: u8 = 0x0B;
+const K_CODERS_UNPACK_SIZE: u8 = 0x0C;
+const K_ENCODED_HEADER: u8 = 0x17;
+
+/// 7zAES（AES-256 + SHA-256 派生密钥）的方法 ID
+const AES_METHOD_ID: [u8; 4] = [0x06, 0xF1, 0x07, 0x01];
+
+/// 每处理这么多字节检查一次超时并发送 chunkDone 事件
+const PROGRESS_INTERVAL: usize = 1024 * 1024;
+
+/// 把 sevenz-rust 的错误转换为错误信息
+fn archive_error(err: sevenz_rust::Error) -> CodedError {
+    match err {
+        sevenz_rust::Error::PasswordRequired => CodedError::invalid_argument("7z archive is encrypted - provide the password"),
+        sevenz_rust::Error::MaybeBadPassword(_) => CodedError::auth_failed("7z decryption failed: wrong password"),
+        sevenz_rust::Error::ChecksumVerificationFailed => {
+            CodedError::auth_failed("7z checksum verification failed: wrong password or corrupted archive")
+        }
+        err => CodedError::bad_format(format!("7z archive error: {}", err)),
+    }
+}
+
+/// 在读写数据的同时检查超时并发送 chunkDone 事件；超时后读写返回 I/O 错误，原因保存在 stopped 中
+struct Progress<'a, T> {
+    inner: T,
+    check: &'a dyn Fn() -> Result<(), CodedError>,
+    chunk_done: &'a mut dyn FnMut(u32, usize),
+    index: &'a mut u32,
+    pending: usize,
+    stopped: Option<CodedError>,
+}
+
+impl<'a, T> Progress<'a, T> {
+    fn new(inner: T, check: &'a dyn Fn() -> Result<(), CodedError>, chunk_done: &'a mut dyn FnMut(u32, usize), index: &'a mut u32) -> Self {
+        Progress { inner, check, chunk_done, index, pending: 0, stopped: None }
+    }
+
+    fn advance(&mut self, bytes: usize) -> io::Result<()> {
+        self.pending += bytes;
+        if self.pending >= PROGRESS_INTERVAL {
+            *self.index += 1;
+            (self.chunk_done)(*self.index, self.pending);
+            self.pending = 0;
+            if let Err(err) = (self.check)() {
+                self.stopped = Some(err.clone());
+                return Err(io::Error::other(err));
+            }
+        }
+        Ok(())
+    }
+}
+
+impl<T: Read> Read for Progress<'_, T> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        let read = self.inner.read(buf)?;
+        self.advance(read)?;
+        Ok(read)
+    }
+}
+
+impl<T: Write> Write for Progress<'_, T> {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        let written = self.inner.write(buf)?;
+        self.advance(written)?;
+        Ok(written)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.inner.flush()
+    }
+}
+
+/// 创建或解压 7z 归档的统计；创建归档时不写入目录条目，directories 为 0
+pub struct SevenZReport {
+    pub files: u32,
+    pub directories: u32,
+    pub plaintext_size: u64,
+    pub archive_size: u64,
+}
+
+/// 把文件和目录写入以 AES-256 加密的 7z 归档，compress 为 true 时先用 LZMA2 压缩
+///
+/// 文件列表（文件名、大小、时间）也会加密，没有口令无法列出内容。每个文件单独压缩，不使用固实压缩。
+/// 只写入文件条目，目录由文件路径隐含：sevenz-rust 写出的目录条目会被错误地标记为删除项（anti-item），
+/// 7-Zip 解压时会删除对应的目录，因此空目录不会写入归档。
+pub fn create_archive(inputs: &[String], output_path: &Target, password: &str, compress: bool, check: impl Fn() -> Result<(), CodedError>, mut chunk_done: impl FnMut(u32, usize)) -> Result<SevenZReport, CodedError> {
+    if password.is_empty() {
+        return Err(CodedError::invalid_argument("7z password must not be empty"));
+    }
+    let entries = walk::collect(inputs, "7z archive")?;
+
+    let output = fd::create(output_path).map_err(|e| open_error("creating output file", e))?;
+    let mut writer = SevenZWriter::new(output).map_err(archive_error)?;
+    let aes = AesEncoderOptions::new(Password::from(password));
+    let mut methods = vec![aes.clone().into()];
+    if compress {
+        methods.push(SevenZMethod::LZMA2.into());
+    }
+    writer.set_content_methods(methods);
+    writer.set_encrypt_header(true);
+
+    let mut index = 0u32;
+    let mut report = SevenZReport { files: 0, directories: 0, plaintext_size: 0, archive_size: 0 };
+    for entry in &entries {
+        check()?;
+        if entry.is_dir {
+            continue;
+        }
+        let mut archive_entry = SevenZArchiveEntry::from_path(&entry.path, entry.name.clone());
+        archive_entry.has_windows_attributes = true;
+        archive_entry.windows_attributes = ((0o100000 | entry.mode) << 16) | ATTRIBUTE_UNIX_EXTENSION;
+        let input = File::open(&entry.path).map_err(|e| open_error(&format!("opening {}", entry.path.display()), e))?;
+        let mut progress = Progress::new(input, &check, &mut chunk_done, &mut index);
+        let written = writer.push_archive_entry(archive_entry, Some(&mut progress));
+        if let Some(err) = progress.stopped {
+            return Err(err);
+        }
+        report.plaintext_size += written.map_err(archive_error)?.size;
+        report.files += 1;
+    }
+
+    writer
+        .finish()
+        .map_err(|e| write_error("writing output file", e))?;
+    let mut output = fd::open_rw(output_path).map_err(|e| open_error("opening output file", e))?;
+    encrypt_plain_header(&mut output, &aes).map_err(|e| write_error("encrypting archive header", e))?;
+    output.sync_all().map_err(|e| write_error("flushing output file", e))?;
+    report.archive_size = output
+        .metadata()
+        .map_err(|e| read_error("reading output file metadata", e))?
+        .len();
+    Ok(report)
+}
+
+/// 按 7z 的变长格式写入整数：首字节高位的 1 的个数表示后面还有几个字节（小端）
+fn write_number(out: &mut Vec<u8>, value: u64) {
+    let mut first = 0u8;
+    let mut mask = 0x80u8;
+    let mut extra = 0;
+    while extra < 8 {
+        if value < 1u64 << (7 * (extra + 1)) {
+            first |= (value >> (8 * extra)) as u8;
+            break;
+        }
+        first |= mask;
+        mask >>= 1;
+        extra += 1;
+    }
+    out.push(first);
+    out.extend_from_slice(&value.to_le_bytes()[..extra]);
+}
+
+/// 7zAES 的密钥：对 盐 || 口令（UTF-16LE）|| 计数器 重复 2^num_cycles_power 次计算 SHA-256
+fn aes_key(aes: &AesEncoderOptions) -> [u8; 32] {
+    let mut sha = Sha256::new();
+    for round in 0..1u64 << aes.num_cycles_power {
+        sha.update(aes.salt);
+        sha.update(aes.password.as_slice());
+        sha.update(round.to_le_bytes());
+    }
+    sha.finalize().into()
+}
+
+/// sevenz-rust 在 LZMA 压缩不能让头部变小时（文件很少的归档）会直接写出未加密的头部，泄露文件名。
+/// 这种情况下把头部改为只用 7zAES 加密（不压缩）的编码头部，并更新起始头。
+fn encrypt_plain_header(output: &mut File, aes: &AesEncoderOptions) -> io::Result<()> {
+    let mut start = [0u8; SIGNATURE_HEADER_LEN as usize];
+    output.seek(SeekFrom::Start(0))?;
+    output.read_exact(&mut start)?;
+    let offset = u64::from_le_bytes(start[12..20].try_into().unwrap());
+    let size = u64::from_le_bytes(start[20..28].try_into().unwrap());
+    let header_pos = SIGNATURE_HEADER_LEN + offset;
+    let mut raw = vec![0u8; size as usize];
+    output.seek(SeekFrom::Start(header_pos))?;
+    output.read_exact(&mut raw)?;
+    if raw.first() != Some(&K_HEADER) {
+        return Ok(());
+    }
+
+    // 7zAES 按分组用 0 补齐，解密后按头部中记录的原始长度截断
+    let mut packed = raw.clone();
+    packed.resize(raw.len().div_ceil(16) * 16, 0);
+    let cipher = cbc::Encryptor::<Aes256>::new_from_slices(&aes_key(aes), &aes.iv).map_err(io::Error::other)?;
+    let len = packed.len();
+    cipher.encrypt_padded_mut::<NoPadding>(&mut packed, len).map_err(|e| io::Error::other(format!("{:?}", e)))?;
+
+    let mut header = vec![K_ENCODED_HEADER, K_PACK_INFO];
+    write_number(&mut header, offset);
+    write_number(&mut header, 1);
+    header.push(K_SIZE);
+    write_number(&mut header, packed.len() as u64);
+    header.push(K_END);
+    header.extend_from_slice(&[K_UNPACK_INFO, K_FOLDER]);
+    write_number(&mut header, 1);
+    header.push(0);
+    // 一个编码器：单输入单输出，方法 ID 4 字节，带属性
+    write_number(&mut header, 1);
+    header.push(0x20 | AES_METHOD_ID.len() as u8);
+    header.extend_from_slice(&AES_METHOD_ID);
+    let properties = aes.properties();
+    write_number(&mut header, properties.len() as u64);
+    header.extend_from_slice(&properties);
+    header.push(K_CODERS_UNPACK_SIZE);
+    write_number(&mut header, raw.len() as u64);
+    header.extend_from_slice(&[K_CRC, 1]);
+    header.extend_from_slice(&crc32fast::hash(&raw).to_le_bytes());
+    header.extend_from_slice(&[K_END, K_END]);
+
+    output.seek(SeekFrom::Start(header_pos))?;
+    output.write_all(&packed)?;
+    output.write_all(&header)?;
+    output.set_len(header_pos + (packed.len() + header.len()) as u64)?;
+
+    start[12..20].copy_from_slice(&(offset + packed.len() as u64).to_le_bytes());
+    start[20..28].copy_from_slice(&(header.len() as u64).to_le_bytes());
+    start[28..32].copy_from_slice(&crc32fast::hash(&header).to_le_bytes());
+    let start_crc = crc32fast::hash(&start[12..]);
+    start[8..12].copy_from_slice(&start_crc.to_le_bytes());
+    output.seek(SeekFrom::Start(0))?;
+    output.write_all(&start)
+}
+
+/// 把 7z 归档解压到 output_dir（不存在时创建），支持 AES-256 加密和 LZMA/LZMA2 等常见压缩方法
+///
+/// 条目名称中的绝对路径和 `..` 会被拒绝。出错时删除本次已写出的文件，返回值 created 为本次创建的所有路径。
+pub fn extract_archive(input_path: &Target, output_dir: &str, password: Option<&str>, check: impl Fn() -> Result<(), CodedError>, mut chunk_done: impl FnMut(u32, usize), created: &mut Vec<PathBuf>) -> Result<SevenZReport, CodedError> {
+    let input = fd::open(input_path).map_err(|e| open_error("opening input file", e))?;
+    let archive_size = input
+        .metadata()
+        .map_err(|e| read_error("reading input file metadata", e))?
+        .len();
+    let password = password.map(Password::from).unwrap_or_else(Password::empty);
+    let mut reader = SevenZReader::new(input, archive_size, password).map_err(archive_error)?;
+    let dest = Path::new(output_dir);
+    walk::create_dirs(dest, created)?;
+
+    let mut index = 0u32;
+    let mut report = SevenZReport { files: 0, directories: 0, plaintext_size: 0, archive_size };
+    let mut failure = None;
+    let result = reader.for_each_entries(|entry, data| {
+        let outcome = extract_entry(entry, data, dest, &check, &mut chunk_done, &mut index, created);
+        match outcome {
+            Ok(size) => {
+                if entry.is_directory() {
+                    report.directories += 1;
+                } else if !entry.is_anti_item() {
+                    report.files += 1;
+                    report.plaintext_size += size;
+                }
+                Ok(true)
+            }
+            Err(err) => {
+                failure = Some(err);
+                Ok(false)
+            }
+        }
+    });
+    if let Some(err) = failure {
+        return Err(err);
+    }
+    result.map_err(archive_error)?;
+    Ok(report)
+}
+
+/// 解压一个条目，返回写出的字节数
+fn extract_entry(entry: &SevenZArchiveEntry, data: &mut dyn Read, dest: &Path, check: &dyn Fn() -> Result<(), CodedError>, chunk_done: &mut dyn FnMut(u32, usize), index: &mut u32, created: &mut Vec<PathBuf>) -> Result<u64, CodedError> {
+    check()?;
+    if entry.is_anti_item() {
+        return Ok(0);
+    }
+    let path = safe_join(dest, entry.name())?;
+    let unix_mode = (entry.has_windows_attributes && entry.windows_attributes & ATTRIBUTE_UNIX_EXTENSION != 0)
+        .then_some((entry.windows_attributes >> 16) & 0o777);
+
+    if entry.is_directory() {
+        walk::create_dirs(&path, created)?;
+        set_permissions(&path, unix_mode);
+        return Ok(0);
+    }
+
+    if let Some(parent) = path.parent() {
+        walk::create_dirs(parent, created)?;
+    }
+    let file = File::create(&path).map_err(|e| open_error(&format!("creating {}", path.display()), e))?;
+    created.push(path.clone());
+    let mut writer = Progress::new(BufWriter::new(file), check, chunk_done, index);
+    let copied = io::copy(data, &mut writer);
+    if let Some(err) = writer.stopped {
+        return Err(err);
+    }
+    let size = copied.map_err(|e| match e.get_ref().and_then(|inner| inner.downcast_ref::<sevenz_rust::Error>()) {
+        Some(sevenz_rust::Error::ChecksumVerificationFailed) => archive_error(sevenz_rust::Error::ChecksumVerificationFailed),
+        _ => CodedError::io(ErrorCode::IoRead, format_args!("Error extracting {}", entry.name()), e),
+    })?;
+    let file = writer
+        .inner
+        .into_inner()
+        .map_err(|e| write_error(&format!("writing {}", path.display()), e.into_error()))?;
+    if entry.has_last_modified_date {
+        let _ = file.set_modified(SystemTime::from(entry.last_modified_date()));
+    }
+    drop(file);
+    set_permissions(&path, unix_mode);
+    Ok(size)
+}
+
+#[cfg(unix)]
+fn set_permissions(path: &Path, mode: Option<u32>) {
+    use std::os::unix::fs::PermissionsExt;
+    if let Some(mode) = mode {
+        let _ = fs::set_permissions(path, fs::Permissions::from_mode(mode));
+    }
+}
+
+#[cfg(not(unix))]
+fn set_permissions(_path: &Path, _mode: Option<u32>) {}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const PASSWORD: &str = "correct horse";
+
+    struct TempDir(PathBuf);
+
+    impl TempDir {
+        fn new(name: &str) -> Self {
+            let path = std::env::temp_dir().join(format!("zippy-sevenz-{}-{}", std::process::id(), name));
+            let _ = fs::remove_dir_all(&path);
+            fs::create_dir_all(&path).unwrap();
+            TempDir(path)
+        }
+
+        fn path(&self, name: &str) -> String {
+            self.0.join(name).to_string_lossy().into_owned()
+        }
+
+        fn target(&self, name: &str) -> Target {
+            Target::from(&*self.path(name))
+        }
+
+        fn write(&self, name: &str, data: &[u8]) {
+            let path = self.0.join(name);
+            fs::create_dir_all(path.parent().unwrap()).unwrap();
+            fs::write(path, data).unwrap();
+        }
+    }
+
+    impl Drop for TempDir {
+        fn drop(&mut self) {
+            let _ = fs::remove_dir_all(&self.0);
+        }
+    }
+
+    fn create(dir: &TempDir, inputs: &[&str], password: &str, compress: bool) -> Result<SevenZReport, CodedError> {
+        let inputs: Vec<_> = inputs.iter().map(|input| dir.path(input)).collect();
+        create_archive(&inputs, &dir.target("archive.7z"), password, compress, || Ok(()), |_, _| {})
+    }
+
+    fn extract(dir: &TempDir, output: &str, password: Option<&str>) -> (Result<SevenZReport, CodedError>, Vec<PathBuf>) {
+        let mut created = Vec::new();
+        let result = extract_archive(&dir.target("archive.7z"), &dir.path(output), password, || Ok(()), |_, _| {}, &mut created);
+        (result, created)
+    }
+
+    /// 起始头指向的头部
+    fn next_header(archive: &[u8]) -> &[u8] {
+        let offset = u64::from_le_bytes(archive[12..20].try_into().unwrap()) as usize;
+        let size = u64::from_le_bytes(archive[20..28].try_into().unwrap()) as usize;
+        let start = SIGNATURE_HEADER_LEN as usize + offset;
+        &archive[start..start + size]
+    }
+
+    fn utf16(name: &str) -> Vec<u8> {
+        name.encode_utf16().flat_map(u16::to_le_bytes).collect()
+    }
+
+    fn contains(haystack: &[u8], needle: &[u8]) -> bool {
+        haystack.windows(needle.len()).any(|window| window == needle)
+    }
+
+    #[test]
+    fn round_trips_files_and_directories() {
+        for compress in [true, false] {
+            let dir = TempDir::new(&format!("round-trip-{}", compress));
+            dir.write("in/docs/readme.txt", &b"readme text ".repeat(1000));
+            dir.write("in/docs/nested/data.bin", &(0..5000u32).map(|i| (i * 31) as u8).collect::<Vec<_>>());
+            dir.write("in/single.txt", b"single");
+            let report = create(&dir, &["in/docs", "in/single.txt"], PASSWORD, compress).unwrap();
+            assert_eq!((report.files, report.directories), (3, 0));
+            assert_eq!(report.plaintext_size, 12000 + 5000 + 6);
+            assert_eq!(report.archive_size, fs::metadata(dir.path("archive.7z")).unwrap().len());
+            assert_eq!(compress, report.archive_size < 12000);
+
+            let (extracted, _) = extract(&dir, "out", Some(PASSWORD));
+            let extracted = extracted.unwrap();
+            assert_eq!((extracted.files, extracted.plaintext_size), (3, report.plaintext_size));
+            for name in ["docs/readme.txt", "docs/nested/data.bin", "single.txt"] {
+                let source = if name == "single.txt" { "in/single.txt".to_string() } else { format!("in/{}", name) };
+                assert_eq!(fs::read(dir.0.join("out").join(name)).unwrap(), fs::read(dir.0.join(source)).unwrap(), "{}", name);
+            }
+        }
+    }
+
+    #[test]
+    fn encrypts_the_file_list() {
+        let dir = TempDir::new("header");
+        dir.write("secret-name.txt", b"x");
+        create(&dir, &["secret-name.txt"], PASSWORD, true).unwrap();
+        let archive = fs::read(dir.path("archive.7z")).unwrap();
+        assert_eq!(next_header(&archive)[0], K_ENCODED_HEADER);
+        assert!(!contains(&archive, &utf16("secret-name")));
+        // 没有口令无法列出内容
+        let (result, _) = extract(&dir, "out", None);
+        assert_eq!(result.err().unwrap().code, ErrorCode::InvalidArgument);
+    }
+
+    #[test]
+    fn rejects_wrong_and_empty_passwords() {
+        let dir = TempDir::new("password");
+        dir.write("a.txt", &b"content ".repeat(100));
+        assert_eq!(create(&dir, &["a.txt"], "", true).err().unwrap().code, ErrorCode::InvalidArgument);
+        create(&dir, &["a.txt"], PASSWORD, true).unwrap();
+        let (result, _) = extract(&dir, "out", Some("wrong password"));
+        assert_eq!(result.err().unwrap().code, ErrorCode::AuthFailed);
+        assert!(!dir.0.join("out/a.txt").exists());
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn restores_unix_permissions() {
+        use std::os::unix::fs::PermissionsExt;
+        let dir = TempDir::new("mode");
+        dir.write("run.sh", b"#!/bin/sh\n");
+        fs::set_permissions(dir.0.join("run.sh"), fs::Permissions::from_mode(0o741)).unwrap();
+        create(&dir, &["run.sh"], PASSWORD, false).unwrap();
+        extract(&dir, "out", Some(PASSWORD)).0.unwrap();
+        assert_eq!(fs::metadata(dir.0.join("out/run.sh")).unwrap().permissions().mode() & 0o777, 0o741);
+    }
+
+    #[test]
+    fn rejects_entries_outside_the_output_directory() {
+        let dir = TempDir::new("unsafe");
+        dir.write("evil.txt", b"evil");
+        let mut writer = SevenZWriter::new(File::create(dir.path("archive.7z")).unwrap()).unwrap();
+        let entry = SevenZArchiveEntry::from_path(dir.0.join("evil.txt"), "../evil.txt".to_string());
+        writer.push_archive_entry(entry, Some(File::open(dir.0.join("evil.txt")).unwrap())).unwrap();
+        writer.finish().unwrap();
+
+        let (result, created) = extract(&dir, "out/inner", None);
+        assert_eq!(result.err().unwrap().code, ErrorCode::BadFormat);
+        assert!(!dir.0.join("out/evil.txt").exists());
+        walk::remove_created(&created);
+        assert!(!dir.0.join("out").exists());
+    }
+
+    #[test]
+    fn reports_progress_and_stops_when_cancelled() {
+        let dir = TempDir::new("progress");
+        dir.write("big.bin", &vec![7u8; 3 * PROGRESS_INTERVAL + 10]);
+        let mut progress = Vec::new();
+        let inputs = [dir.path("big.bin")];
+        create_archive(&inputs, &dir.target("archive.7z"), PASSWORD, true, || Ok(()), |index, bytes| progress.push((index, bytes))).unwrap();
+        assert_eq!(progress, [(1, PROGRESS_INTERVAL), (2, PROGRESS_INTERVAL), (3, PROGRESS_INTERVAL)]);
+
+        let cancelled = || Err(CodedError::aborted("aborted"));
+        let err = create_archive(&inputs, &dir.target("cancelled.7z"), PASSWORD, true, cancelled, |_, _| {}).err().unwrap();
+        assert_eq!(err.code, ErrorCode::Aborted);
+        let mut created = Vec::new();
+        let err = extract_archive(&dir.target("archive.7z"), &dir.path("out"), Some(PASSWORD), cancelled, |_, _| {}, &mut created).err().unwrap();
+        assert_eq!(err.code, ErrorCode::Aborted);
+    }
+
+    #[test]
+    fn encodes_7z_numbers() {
+        let encode = |value| {
+            let mut out = Vec::new();
+            write_number(&mut out, value);
+            out
+        };
+        assert_eq!(encode(0), [0x00]);
+        assert_eq!(encode(0x7F), [0x7F]);
+        assert_eq!(encode(0x80), [0x80, 0x80]);
+        assert_eq!(encode(0x3FFF), [0xBF, 0xFF]);
+        assert_eq!(encode(0x4000), [0xC0, 0x00, 0x40]);
+        assert_eq!(encode(0x12_3456), [0xD2, 0x56, 0x34]);
+        assert_eq!(encode(u64::MAX), [[0xFF].as_slice(), &u64::MAX.to_le_bytes()].concat());
+    }
+}
@@ -0,0 +1,176 @@
+use std::fs::{self, File, OpenOptions};
+use std::io::{self, Seek, SeekFrom, Write};
+use std::path::Path;
+
+use rand::RngCore;
+
+use crate::errors::CodedError;
+use crate::fd::Target;
+
+/// 每次覆盖写入的块大小
+const BLOCK_SIZE: usize = 1024 * 1024;
+
+/// shredSource 的前提检查：输入和输出都必须是路径，输入必须是只有一个链接的普通文件（不能是符号链接），且输出不能替换输入本身
+pub fn check_paths(input_path: &Target, output_path: &Target) -> Result<(), CodedError> {
+    let (Target::Path(input_path), Target::Path(output_path)) = (input_path, output_path) else {
+        return Err(CodedError::invalid_argument("shredSource requires input and output file paths, not file descriptors"));
+    };
+    let metadata = fs::symlink_metadata(input_path).map_err(|e| CodedError::io_open(format!("Failed to open input file: {}", e)))?;
+    check_file(input_path, &metadata)?;
+    let input = fs::canonicalize(input_path).map_err(|e| CodedError::io_open(format!("Failed to open input file: {}", e)))?;
+    if fs::canonicalize(output_path).is_ok_and(|output| output == input) {
+        return Err(CodedError::invalid_argument("Output path must not be the input file when using shredSource"));
+    }
+    Ok(())
+}
+
+/// 只粉碎只有一个链接的普通文件：覆盖符号链接会破坏链接指向的文件而只删除链接本身，
+/// 覆盖有多个硬链接的文件会破坏其它名称仍在引用的数据
+fn check_file(path: &str, metadata: &fs::Metadata) -> Result<(), CodedError> {
+    if metadata.file_type().is_symlink() {
+        return Err(CodedError::invalid_argument(format!("shredSource refuses to follow the symbolic link {}", path)));
+    }
+    if !metadata.is_file() {
+        return Err(CodedError::invalid_argument(format!("shredSource requires a regular input file: {}", path)));
+    }
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::MetadataExt;
+        if metadata.nlink() > 1 {
+            return Err(CodedError::invalid_argument(format!("shredSource refuses to shred {}, which has {} hard links", path, metadata.nlink())));
+        }
+    }
+    Ok(())
+}
+
+/// 以随机数据覆盖文件内容并同步到磁盘，再改为随机名称后删除
+///
+/// 以 O_NOFOLLOW 打开，并在打开的文件上重新检查类型和链接数，检查之后路径被换成符号链接或新增硬链接时同样拒绝。
+/// 开始覆盖后不再响应超时和取消，避免留下覆盖了一半的源文件。只能覆盖文件系统分配给文件的当前数据块：
+/// SSD 的磨损均衡、写时复制文件系统（Btrfs、ZFS、APFS）、快照、备份和日志中可能仍有明文副本，这些情况下请依赖全盘加密。
+pub fn shred(path: &str) -> Result<(), CodedError> {
+    let shred_error = |e: io::Error| CodedError::io_write(format!("Failed to shred {}: {}", path, e));
+    let mut options = OpenOptions::new();
+    options.write(true);
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::OpenOptionsExt;
+        options.custom_flags(libc::O_NOFOLLOW);
+    }
+    let mut file = match options.open(path) {
+        Ok(file) => file,
+        // 最后一级是符号链接时 O_NOFOLLOW 打开失败（ELOOP）
+        Err(_) if fs::symlink_metadata(path).is_ok_and(|metadata| metadata.file_type().is_symlink()) => {
+            return Err(CodedError::invalid_argument(format!("shredSource refuses to follow the symbolic link {}", path)));
+        }
+        Err(err) => return Err(shred_error(err)),
+    };
+    let metadata = file.metadata().map_err(shred_error)?;
+    check_file(path, &metadata)?;
+    let len = metadata.len();
+    overwrite(&mut file, len)?;
+    file.sync_all().map_err(shred_error)?;
+    drop(file);
+
+    // 目录项中的文件名同样会泄露信息，删除前先改为随机名称
+    let mut suffix = [0u8; 12];
+    rand::thread_rng().fill_bytes(&mut suffix);
+    let renamed = Path::new(path).with_file_name(format!(".{}", hex::encode(suffix)));
+    let removed = match fs::rename(path, &renamed) {
+        Ok(()) => fs::remove_file(&renamed),
+        Err(_) => fs::remove_file(path),
+    };
+    removed.map_err(shred_error)
+}
+
+fn overwrite(file: &mut File, len: u64) -> Result<(), CodedError> {
+    let write_error = |e: io::Error| CodedError::io_write(format!("Failed to overwrite source file: {}", e));
+    file.seek(SeekFrom::Start(0)).map_err(write_error)?;
+    let mut block = vec![0u8; BLOCK_SIZE];
+    let mut written = 0u64;
+    while written < len {
+        let size = (len - written).min(BLOCK_SIZE as u64) as usize;
+        rand::thread_rng().fill_bytes(&mut block[..size]);
+        file.write_all(&block[..size]).map_err(write_error)?;
+        written += size as u64;
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use std::path::PathBuf;
+
+    use super::*;
+    use crate::errors::ErrorCode;
+
+    /// 测试用的临时目录，结束时删除
+    struct TempDir(PathBuf);
+
+    impl TempDir {
+        fn new(name: &str) -> Self {
+            let path = std::env::temp_dir().join(format!("zippy-shred-{}-{}", std::process::id(), name));
+            let _ = fs::remove_dir_all(&path);
+            fs::create_dir_all(&path).unwrap();
+            TempDir(path)
+        }
+
+        fn file(&self, name: &str, content: &[u8]) -> String {
+            let path = self.0.join(name);
+            fs::write(&path, content).unwrap();
+            path.to_string_lossy().into_owned()
+        }
+    }
+
+    impl Drop for TempDir {
+        fn drop(&mut self) {
+            let _ = fs::remove_dir_all(&self.0);
+        }
+    }
+
+    #[test]
+    fn shreds_and_removes_a_regular_file() {
+        let dir = TempDir::new("regular");
+        let path = dir.file("secret.txt", &vec![b'x'; BLOCK_SIZE + 10]);
+        check_paths(&Target::from(&path), &Target::from(&*dir.0.join("secret.txt.enc").to_string_lossy())).unwrap();
+        shred(&path).unwrap();
+        assert_eq!(fs::read_dir(&dir.0).unwrap().count(), 0);
+    }
+
+    #[test]
+    fn rejects_same_output_and_file_descriptors() {
+        let dir = TempDir::new("paths");
+        let path = dir.file("secret.txt", b"secret");
+        assert_eq!(check_paths(&Target::from(&path), &Target::from(&path)).unwrap_err().code, ErrorCode::InvalidArgument);
+        assert_eq!(check_paths(&Target::Fd(0), &Target::from(&path)).unwrap_err().code, ErrorCode::InvalidArgument);
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn refuses_symbolic_links() {
+        let dir = TempDir::new("symlink");
+        let target = dir.file("target.txt", b"keep me");
+        let link = dir.0.join("link.txt");
+        std::os::unix::fs::symlink(&target, &link).unwrap();
+        let link = link.to_string_lossy().into_owned();
+
+        assert_eq!(check_paths(&Target::from(&link), &Target::Path(format!("{}.enc", link))).unwrap_err().code, ErrorCode::InvalidArgument);
+        assert_eq!(shred(&link).unwrap_err().code, ErrorCode::InvalidArgument);
+        assert_eq!(fs::read(&target).unwrap(), b"keep me");
+        assert!(fs::symlink_metadata(&link).is_ok());
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn refuses_files_with_other_hard_links() {
+        let dir = TempDir::new("hardlink");
+        let path = dir.file("a.txt", b"shared");
+        let other = dir.0.join("b.txt");
+        fs::hard_link(&path, &other).unwrap();
+
+        assert_eq!(check_paths(&Target::from(&path), &Target::Path(format!("{}.enc", path))).unwrap_err().code, ErrorCode::InvalidArgument);
+        assert_eq!(shred(&path).unwrap_err().code, ErrorCode::InvalidArgument);
+        assert_eq!(fs::read(&other).unwrap(), b"shared");
+        assert_eq!(fs::read(&path).unwrap(), b"shared");
+    }
+}
@@ -0,0 +1,201 @@
+use std::fs::File;
+
+/// 稀疏文件中数据区的分布，用于加密时跳过空洞而不必读取
+///
+/// 通过 `SEEK_DATA`/`SEEK_HOLE` 查询，不支持的平台和文件系统把整个文件视为数据区。
+pub struct HoleMap {
+    /// 按位置排列的数据区 [起始位置, 结束位置)
+    data_ranges: Vec<(u64, u64)>,
+    len: u64,
+}
+
+impl HoleMap {
+    /// 查询文件的数据区分布，查询结束后文件的读取位置回到开头
+    pub fn of(file: &File, len: u64) -> HoleMap {
+        let data_ranges = data_ranges(file, len).unwrap_or_else(|| vec![(0, len)]);
+        HoleMap { data_ranges, len }
+    }
+
+    /// 从 offset 开始最多 max_len 字节的区域完全位于空洞中时返回该区域的长度（到文件末尾为止）
+    pub fn hole_len(&self, offset: u64, max_len: u64) -> Option<u64> {
+        let end = offset.saturating_add(max_len).min(self.len);
+        if end <= offset {
+            return None;
+        }
+        let overlaps = self
+            .data_ranges
+            .iter()
+            .any(|&(start, data_end)| start < end && data_end > offset);
+        (!overlaps).then_some(end - offset)
+    }
+}
+
+#[cfg(any(target_os = "linux", target_os = "android", target_os = "freebsd"))]
+fn data_ranges(file: &File, len: u64) -> Option<Vec<(u64, u64)>> {
+    use std::os::unix::io::AsRawFd;
+
+    let fd = file.as_raw_fd();
+    let mut ranges = Vec::new();
+    let mut position = 0u64;
+    // SEEK_DATA 找到 position 之后的下一个数据区，之后没有数据时返回 ENXIO；SEEK_HOLE 找到数据区的结尾
+    let result = (|| {
+        while position < len {
+            let start = unsafe { libc::lseek(fd, position as libc::off_t, libc::SEEK_DATA) };
+            if start < 0 {
+                return match std::io::Error::last_os_error().raw_os_error() {
+                    Some(libc::ENXIO) => Some(()),
+                    _ => None,
+                };
+            }
+            let end = unsafe { libc::lseek(fd, start, libc::SEEK_HOLE) };
+            if end < 0 {
+                return None;
+            }
+            ranges.push((start as u64, (end as u64).min(len)));
+            position = end as u64;
+        }
+        Some(())
+    })();
+    let rewound = unsafe { libc::lseek(fd, 0, libc::SEEK_SET) } == 0;
+    result.filter(|_| rewound).map(|_| ranges)
+}
+
+#[cfg(not(any(target_os = "linux", target_os = "android", target_os = "freebsd")))]
+fn data_ranges(_file: &File, _len: u64) -> Option<Vec<(u64, u64)>> {
+    None
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::chunked::{ChunkDecoder, ChunkEncoder};
+    use crate::crypto::CryptoAlgorithm;
+    use crate::format::{read_chunked_header, ContainerHeader, Layout};
+    use std::io::{Cursor, Read, Seek, SeekFrom, Write};
+    use std::path::PathBuf;
+
+    const KEY: [u8; 32] = [3; 32];
+    const CHUNK_SIZE: usize = 1000;
+
+    struct TempFile(PathBuf);
+
+    impl Drop for TempFile {
+        fn drop(&mut self) {
+            let _ = std::fs::remove_file(&self.0);
+        }
+    }
+
+    fn map(data_ranges: Vec<(u64, u64)>, len: u64) -> HoleMap {
+        HoleMap { data_ranges, len }
+    }
+
+    #[test]
+    fn finds_regions_entirely_within_holes() {
+        let holes = map(vec![(4096, 8192), (16384, 20000)], 20000);
+        assert_eq!(holes.hole_len(0, 4096), Some(4096));
+        assert_eq!(holes.hole_len(8192, 8192), Some(8192));
+        assert_eq!(holes.hole_len(0, 4097), None);
+        assert_eq!(holes.hole_len(8191, 10), None);
+        assert_eq!(holes.hole_len(12000, 5000), None);
+    }
+
+    #[test]
+    fn stops_at_the_end_of_the_file() {
+        let holes = map(vec![(0, 100)], 10000);
+        assert_eq!(holes.hole_len(8192, 4096), Some(1808));
+        assert_eq!(holes.hole_len(10000, 4096), None);
+        assert_eq!(holes.hole_len(20000, 4096), None);
+        assert_eq!(holes.hole_len(100, u64::MAX), Some(9900));
+    }
+
+    #[test]
+    fn a_file_without_holes_has_no_hole_regions() {
+        let holes = map(vec![(0, 5000)], 5000);
+        assert!((0..5000).step_by(1000).all(|offset| holes.hole_len(offset, 1000).is_none()));
+    }
+
+    #[test]
+    fn maps_the_holes_of_a_sparse_file() {
+        let path = TempFile(std::env::temp_dir().join(format!("zippy-sparse-{}", std::process::id())));
+        let len = 4 << 20;
+        let mut file = std::fs::OpenOptions::new().read(true).write(true).create(true).truncate(true).open(&path.0).unwrap();
+        file.set_len(len).unwrap();
+        file.seek(SeekFrom::Start(1 << 20)).unwrap();
+        file.write_all(&[0xAB; 4096]).unwrap();
+        file.sync_all().unwrap();
+
+        let holes = HoleMap::of(&file, len);
+        // 数据区永远不会被当作空洞
+        assert_eq!(holes.hole_len(1 << 20, 4096), None);
+        assert_eq!(holes.hole_len((1 << 20) - 1, 2), None);
+        // 支持查询空洞的文件系统上，没有写入的区域是空洞
+        if data_ranges(&file, len).is_some_and(|ranges| ranges.len() == 1) {
+            assert_eq!(holes.hole_len(0, 1 << 20), Some(1 << 20));
+            assert_eq!(holes.hole_len(2 << 20, 4 << 20), Some(2 << 20));
+        }
+        // 查询结束后回到文件开头
+        let mut first = [1; 16];
+        file.read_exact(&mut first).unwrap();
+        assert_eq!(first, [0; 16]);
+    }
+
+    #[test]
+    fn an_empty_file_has_no_regions() {
+        let path = TempFile(std::env::temp_dir().join(format!("zippy-sparse-empty-{}", std::process::id())));
+        let file = File::create(&path.0).unwrap();
+        let holes = HoleMap::of(&file, 0);
+        assert_eq!(holes.hole_len(0, 4096), None);
+    }
+
+    /// 以稀疏编码加密，返回加密后的文件和以空分片帧保存的分片数
+    fn encrypt_sparse(data: &[u8], sparse: bool) -> (Vec<u8>, u32) {
+        let mut container = ContainerHeader::for_new_file(Layout::Chunked, CryptoAlgorithm::Aes, data.len() as u64, CHUNK_SIZE as u64);
+        container.sparse = sparse;
+        let mut encoder = ChunkEncoder::new(Vec::new(), CryptoAlgorithm::Aes, &KEY, Some(&container), data.len() as u64, CHUNK_SIZE).unwrap();
+        for chunk in data.chunks(CHUNK_SIZE) {
+            encoder.write_chunk(chunk).unwrap();
+        }
+        let sparse_chunks = encoder.sparse_chunks();
+        (encoder.finish().unwrap(), sparse_chunks)
+    }
+
+    fn decrypt(file: &[u8]) -> Vec<u8> {
+        let mut reader = Cursor::new(file);
+        let header = read_chunked_header(&mut reader).unwrap();
+        header.container.as_ref().unwrap().verify(&KEY).unwrap();
+        let mut decoder = ChunkDecoder::new(reader, header, CryptoAlgorithm::Aes, &KEY).unwrap();
+        let mut plaintext = Vec::new();
+        while let Some(chunk) = decoder.next_chunk().unwrap() {
+            plaintext.extend_from_slice(&chunk);
+        }
+        decoder.finish().unwrap();
+        plaintext
+    }
+
+    #[test]
+    fn stores_zero_chunks_as_empty_frames() {
+        let mut data = vec![0u8; 5500];
+        data[1000..2000].fill(9);
+        data[5499] = 1;
+        let (sparse_file, sparse_chunks) = encrypt_sparse(&data, true);
+        let (dense_file, dense_chunks) = encrypt_sparse(&data, false);
+        assert_eq!(sparse_chunks, 4);
+        assert_eq!(dense_chunks, 0);
+        // 空分片帧不含分片数据，只剩加密和认证的开销
+        assert!(sparse_file.len() + 3 * CHUNK_SIZE < dense_file.len());
+        assert_eq!(decrypt(&sparse_file), data);
+        assert_eq!(decrypt(&dense_file), data);
+    }
+
+    #[test]
+    fn a_short_zero_last_chunk_is_restored_to_its_length() {
+        let data = vec![0u8; 2300];
+        let (file, sparse_chunks) = encrypt_sparse(&data, true);
+        assert_eq!(sparse_chunks, 3);
+        let mut reader = Cursor::new(&file);
+        let header = read_chunked_header(&mut reader).unwrap();
+        assert!(header.sparse());
+        assert_eq!(header.restore_chunk(2, Vec::new()).unwrap().len(), 300);
+        assert_eq!(decrypt(&file), data);
+    }
+}
@@ -0,0 +1,483 @@
+use std::fs::{self, File};
+use std::io::{BufWriter, ErrorKind, Write};
+use std::path::{Path, PathBuf};
+
+use fastcdc::v2020::StreamCDC;
+use rand::RngCore;
+use serde_json::{json, Value};
+use sha2::{Digest, Sha256};
+
+use crate::compression::{Compression, CompressionMethod};
+use crate::config::{self, LogLevel};
+use crate::crypto::{object_id, open_field, open_object, seal_field, seal_object, CryptoAlgorithm, OBJECT_ID_LEN};
+use crate::errors::{CodedError, ErrorCode};
+use crate::fd::{self, Target};
+
+/// 对象文件开头的魔数
+const OBJECT_MAGIC: &[u8] = b"ZIPPYOBJ";
+/// 清单文件开头的魔数
+const MANIFEST_MAGIC: &[u8] = b"ZIPPYMAN";
+const STORE_VERSION: u8 = 1;
+/// 对象文件头长度：魔数(8) + 版本(u8) + 算法(u8) + 压缩算法(u8，0 表示未压缩)
+const OBJECT_HEADER_LEN: usize = 11;
+/// 清单加密使用的字段用途
+const FIELD_MANIFEST: &str = "store manifest";
+
+/// 内容定义分片（FastCDC）的最小、平均和最大分片大小：内容插入或删除后，后面的分片边界仍能对齐，未变化的分片可以复用
+const MIN_CHUNK_SIZE: u32 = 256 * 1024;
+const AVG_CHUNK_SIZE: u32 = 1024 * 1024;
+const MAX_CHUNK_SIZE: u32 = 4 * 1024 * 1024;
+
+/// 存入文件的统计
+pub struct StoreReport {
+    pub size: u64,
+    pub total_chunks: u32,
+    /// 新写入的对象数
+    pub new_chunks: u32,
+    /// 复用已有对象的分片数（包括同一文件内重复的分片）
+    pub reused_chunks: u32,
+    /// 新写入对象占用的字节数
+    pub stored_bytes: u64,
+}
+
+/// 清单中记录的文件
+pub struct StoredFile {
+    pub name: String,
+    pub size: u64,
+    pub total_chunks: u32,
+    pub created_at: u64,
+    pub algorithm: String,
+}
+
+/// 清单中的一个分片：对象 id 和明文长度
+struct ManifestChunk {
+    id: [u8; OBJECT_ID_LEN],
+    len: usize,
+}
+
+fn objects_dir(store_dir: &str) -> PathBuf {
+    Path::new(store_dir).join("objects")
+}
+
+fn manifests_dir(store_dir: &str) -> PathBuf {
+    Path::new(store_dir).join("manifests")
+}
+
+/// 对象文件路径：`objects/<id 前两位>/<id 其余部分>`
+fn object_path(store_dir: &str, id: &[u8]) -> PathBuf {
+    let id = hex::encode(id);
+    objects_dir(store_dir).join(&id[..2]).join(&id[2..])
+}
+
+/// 清单文件路径，文件名为文件名称的 keyed hash，不暴露文件名
+fn manifest_path(store_dir: &str, key: &[u8], name: &str) -> PathBuf {
+    let id = object_id(key, format!("manifest\0{}", name).as_bytes());
+    manifests_dir(store_dir).join(hex::encode(id))
+}
+
+/// 先写入同目录下的临时文件再重命名，中途失败不会留下不完整的对象或清单
+pub fn write_atomically(path: &Path, data: &[u8]) -> Result<(), CodedError> {
+    let parent = path.parent().ok_or_else(|| CodedError::bad_format(format!("Invalid store path {}", path.display())))?;
+    fs::create_dir_all(parent).map_err(|e| CodedError::io_open(format!("Failed to create {}: {}", parent.display(), e)))?;
+    let mut suffix = [0u8; 8];
+    rand::rngs::ThreadRng::default().fill_bytes(&mut suffix);
+    let tmp_path = path.with_extension(format!("tmp-{}", hex::encode(suffix)));
+    let written = File::create(&tmp_path)
+        .and_then(|mut file| file.write_all(data).and_then(|_| file.sync_all()))
+        .and_then(|_| fs::rename(&tmp_path, path));
+    if let Err(err) = written {
+        let _ = fs::remove_file(&tmp_path);
+        return Err(CodedError::io_write(format!("Failed to write {}: {}", path.display(), err)));
+    }
+    Ok(())
+}
+
+/// 把文件按内容切分为分片存入 store_dir：相同内容的分片（相同的对象 id）只保存一次，最后写入名为 name 的清单
+///
+/// 同名清单会被替换；不再被任何清单引用的对象不会自动删除。
+#[allow(clippy::too_many_arguments)]
+pub fn store_file(store_dir: &str, name: &str, input_path: &Target, algorithm: &CryptoAlgorithm, key: &[u8], compression: Option<Compression>, check: impl Fn() -> Result<(), CodedError>, mut chunk_done: impl FnMut(u32, usize)) -> Result<StoreReport, CodedError> {
+    let input = fd::open(input_path).map_err(|e| CodedError::io_open(format!("Failed to open input file: {}", e)))?;
+    let mut report = StoreReport { size: 0, total_chunks: 0, new_chunks: 0, reused_chunks: 0, stored_bytes: 0 };
+    let mut hasher = Sha256::new();
+    let mut chunks = Vec::new();
+
+    for chunk in StreamCDC::new(input, MIN_CHUNK_SIZE, AVG_CHUNK_SIZE, MAX_CHUNK_SIZE) {
+        check()?;
+        let chunk = chunk.map_err(|e| CodedError::io_read(format!("Error reading input file: {}", e)))?;
+        let data = chunk.data;
+        hasher.update(&data);
+        let id = object_id(key, &data);
+        let path = object_path(store_dir, &id);
+
+        if path.is_file() {
+            report.reused_chunks += 1;
+        } else {
+            let payload = match &compression {
+                Some(compression) => compression.compress(&data)?.0,
+                None => data.clone(),
+            };
+            let mut object = Vec::with_capacity(OBJECT_HEADER_LEN + payload.len() + 64);
+            object.extend_from_slice(OBJECT_MAGIC);
+            object.push(STORE_VERSION);
+            object.push(algorithm.id());
+            object.push(compression.map_or(0, |compression| compression.method.id()));
+            object.extend_from_slice(&seal_object(algorithm, key, &id, &payload)?);
+            write_atomically(&path, &object)?;
+            report.new_chunks += 1;
+            report.stored_bytes += object.len() as u64;
+        }
+
+        report.size += data.len() as u64;
+        report.total_chunks += 1;
+        chunks.push(json!([hex::encode(id), data.len()]));
+        config::log(LogLevel::Debug, format!("stored chunk {} ({} bytes)", report.total_chunks, data.len()));
+        chunk_done(report.total_chunks, data.len());
+    }
+
+    let created_at = std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .map(|elapsed| elapsed.as_millis() as u64)
+        .unwrap_or(0);
+    let manifest = json!({
+        "name": name,
+        "size": report.size,
+        "createdAt": created_at,
+        "algorithm": algorithm.as_str(),
+        "sha256": hex::encode(hasher.finalize()),
+        "chunks": chunks,
+    });
+    let sealed = seal_field(key, FIELD_MANIFEST, manifest.to_string().as_bytes())?;
+    write_atomically(&manifest_path(store_dir, key, name), &[MANIFEST_MAGIC, &[STORE_VERSION], &sealed].concat())?;
+    Ok(report)
+}
+
+/// 读取并解密清单文件
+fn read_manifest(path: &Path, key: &[u8]) -> Result<Value, CodedError> {
+    let bytes = fs::read(path).map_err(|e| CodedError::io_read(format!("Failed to read manifest {}: {}", path.display(), e)))?;
+    if !bytes.starts_with(MANIFEST_MAGIC) || bytes.len() <= MANIFEST_MAGIC.len() {
+        return Err(CodedError::bad_header(format!("{} is not a manifest", path.display())));
+    }
+    if bytes[MANIFEST_MAGIC.len()] != STORE_VERSION {
+        return Err(CodedError::unsupported(format!("Unsupported manifest version {}", bytes[MANIFEST_MAGIC.len()])));
+    }
+    let json = open_field(key, FIELD_MANIFEST, &bytes[MANIFEST_MAGIC.len() + 1..])?;
+    serde_json::from_slice(&json).map_err(|e| CodedError::bad_format(format!("Invalid manifest: {}", e)))
+}
+
+fn manifest_chunks(manifest: &Value) -> Result<Vec<ManifestChunk>, CodedError> {
+    let invalid = || CodedError::bad_format("Invalid chunk list in manifest");
+    let chunks = manifest["chunks"].as_array().ok_or_else(invalid)?;
+    chunks
+        .iter()
+        .map(|chunk| {
+            let id = chunk[0].as_str().and_then(|id| hex::decode(id).ok()).ok_or_else(invalid)?;
+            Ok(ManifestChunk {
+                id: id.try_into().map_err(|_| invalid())?,
+                len: chunk[1].as_u64().ok_or_else(invalid)? as usize,
+            })
+        })
+        .collect()
+}
+
+/// 读取、解密一个对象并核对内容：解压后的明文必须与对象 id 和清单记录的长度一致
+fn read_object(store_dir: &str, key: &[u8], chunk: &ManifestChunk) -> Result<Vec<u8>, CodedError> {
+    let path = object_path(store_dir, &chunk.id);
+    let object = fs::read(&path).map_err(|e| match e.kind() {
+        ErrorKind::NotFound => CodedError::io_open(format!("Chunk object {} is missing from the store", hex::encode(chunk.id))),
+        _ => CodedError::io(ErrorCode::IoRead, format_args!("Failed to read {}", path.display()), e),
+    })?;
+    if object.len() < OBJECT_HEADER_LEN || &object[..8] != OBJECT_MAGIC || object[8] != STORE_VERSION {
+        return Err(CodedError::bad_header(format!("{} is not a chunk object", path.display())));
+    }
+    let algorithm = CryptoAlgorithm::from_id(object[9])
+        .ok_or_else(|| CodedError::bad_format(format!("Unknown algorithm in chunk object {}", path.display())))?;
+    if !config::algorithm_allowed(&algorithm) {
+        return Err(CodedError::policy(format!("Algorithm '{}' is not allowed by configuration", algorithm.as_str())));
+    }
+    let payload = open_object(&algorithm, key, &chunk.id, &object[OBJECT_HEADER_LEN..])
+        .map_err(|e| e.context(format_args!("Chunk object {}", hex::encode(chunk.id))))?;
+    let data = match object[10] {
+        0 => payload,
+        id => {
+            let method = CompressionMethod::from_id(id)
+                .ok_or_else(|| CodedError::bad_format(format!("Unknown compression in chunk object {}", path.display())))?;
+            Compression::new(method, None)?.decompress(&payload, chunk.len)?
+        }
+    };
+    if data.len() != chunk.len || object_id(key, &data) != chunk.id {
+        return Err(CodedError::auth_failed(format!("Chunk object {} does not match its content", hex::encode(chunk.id))));
+    }
+    Ok(data)
+}
+
+/// 按清单从 store_dir 取回名为 name 的文件写入 output_path，核对每个分片和整个文件的 SHA-256；全部通过后才替换输出文件
+pub fn restore_file(store_dir: &str, name: &str, output_path: &Target, key: &[u8], check: impl Fn() -> Result<(), CodedError>, mut chunk_done: impl FnMut(u32, usize)) -> Result<StoredFile, CodedError> {
+    let path = manifest_path(store_dir, key, name);
+    if !path.is_file() {
+        return Err(CodedError::invalid_argument(format!("No file named {} in store", name)));
+    }
+    let manifest = read_manifest(&path, key)?;
+    let chunks = manifest_chunks(&manifest)?;
+    let stored = stored_file(&manifest)?;
+
+    let output = fd::AtomicOutput::new(output_path);
+    let output_file = fd::create(output.path()).map_err(|e| CodedError::io_open(format!("Failed to create output file: {}", e)))?;
+    let mut writer = BufWriter::new(output_file);
+    let mut hasher = Sha256::new();
+    for (index, chunk) in chunks.iter().enumerate() {
+        check()?;
+        let data = read_object(store_dir, key, chunk)?;
+        hasher.update(&data);
+        writer
+            .write_all(&data)
+            .map_err(|e| CodedError::io_write(format!("Failed to write restored chunk: {}", e)))?;
+        chunk_done(index as u32 + 1, data.len());
+    }
+    if manifest["sha256"].as_str() != Some(hex::encode(hasher.finalize()).as_str()) {
+        return Err(CodedError::auth_failed("Restored file does not match the SHA-256 in its manifest"));
+    }
+    writer.flush().map_err(|e| CodedError::io_write(format!("Failed to flush output file: {}", e)))?;
+    drop(writer);
+    output.commit()?;
+    Ok(stored)
+}
+
+fn stored_file(manifest: &Value) -> Result<StoredFile, CodedError> {
+    let invalid = || CodedError::bad_format("Invalid manifest");
+    Ok(StoredFile {
+        name: manifest["name"].as_str().ok_or_else(invalid)?.to_string(),
+        size: manifest["size"].as_u64().ok_or_else(invalid)?,
+        total_chunks: manifest["chunks"].as_array().ok_or_else(invalid)?.len() as u32,
+        created_at: manifest["createdAt"].as_u64().unwrap_or(0),
+        algorithm: manifest["algorithm"].as_str().unwrap_or_default().to_string(),
+    })
+}
+
+/// 列出 store_dir 中可以用 key 解密的清单；用其它密钥写入的清单被跳过
+pub fn list_files(store_dir: &str, key: &[u8]) -> Result<Vec<StoredFile>, CodedError> {
+    let entries = match fs::read_dir(manifests_dir(store_dir)) {
+        Ok(entries) => entries,
+        Err(err) if err.kind() == ErrorKind::NotFound => return Ok(Vec::new()),
+        Err(err) => return Err(CodedError::io_read(format!("Failed to read store: {}", err))),
+    };
+    let mut files = Vec::new();
+    for entry in entries {
+        let path = entry.map_err(|e| CodedError::io_read(format!("Failed to read store: {}", e)))?.path();
+        if path.extension().is_some() {
+            continue;
+        }
+        match read_manifest(&path, key).and_then(|manifest| stored_file(&manifest)) {
+            Ok(file) => files.push(file),
+            Err(err) => config::log(LogLevel::Debug, format!("skipping manifest {}: {}", path.display(), err.message)),
+        }
+    }
+    files.sort_by(|a, b| a.name.cmp(&b.name));
+    Ok(files)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const KEY: [u8; 32] = [5; 32];
+
+    struct TempDir(PathBuf);
+
+    impl TempDir {
+        fn new(name: &str) -> Self {
+            let path = std::env::temp_dir().join(format!("zippy-store-{}-{}", std::process::id(), name));
+            let _ = fs::remove_dir_all(&path);
+            fs::create_dir_all(&path).unwrap();
+            TempDir(path)
+        }
+
+        fn store(&self) -> String {
+            self.0.join("store").to_string_lossy().into_owned()
+        }
+
+        /// 写入目录中的文件，返回对应的 Target
+        fn file(&self, name: &str, data: &[u8]) -> Target {
+            let path = self.0.join(name);
+            fs::write(&path, data).unwrap();
+            self.target(name)
+        }
+
+        fn target(&self, name: &str) -> Target {
+            Target::from(&*self.0.join(name).to_string_lossy())
+        }
+    }
+
+    impl Drop for TempDir {
+        fn drop(&mut self) {
+            let _ = fs::remove_dir_all(&self.0);
+        }
+    }
+
+    /// 不可压缩的伪随机数据，足够切分为多个内容定义分片
+    fn random(len: usize, seed: u64) -> Vec<u8> {
+        let mut state = seed.wrapping_mul(0x9E37_79B9_7F4A_7C15) | 1;
+        (0..len)
+            .map(|_| {
+                state ^= state << 13;
+                state ^= state >> 7;
+                state ^= state << 17;
+                state as u8
+            })
+            .collect()
+    }
+
+    fn store(dir: &TempDir, name: &str, data: &[u8], compression: Option<Compression>) -> Result<StoreReport, CodedError> {
+        let input = dir.file(&format!("{}.in", name), data);
+        store_file(&dir.store(), name, &input, &CryptoAlgorithm::Aes, &KEY, compression, || Ok(()), |_, _| {})
+    }
+
+    fn restore(dir: &TempDir, name: &str, key: &[u8]) -> Result<Vec<u8>, CodedError> {
+        let output = dir.target(&format!("{}.out", name));
+        restore_file(&dir.store(), name, &output, key, || Ok(()), |_, _| {})?;
+        Ok(fs::read(dir.0.join(format!("{}.out", name))).unwrap())
+    }
+
+    /// 清单中引用的对象文件
+    fn object_files(dir: &TempDir, name: &str) -> Vec<PathBuf> {
+        let manifest = read_manifest(&manifest_path(&dir.store(), &KEY, name), &KEY).unwrap();
+        manifest_chunks(&manifest).unwrap().iter().map(|chunk| object_path(&dir.store(), &chunk.id)).collect()
+    }
+
+    #[test]
+    fn stores_and_restores_a_file() {
+        let dir = TempDir::new("round-trip");
+        let data = random(3_000_000, 1);
+        let mut progress = Vec::new();
+        let input = dir.file("a.in", &data);
+        let report = store_file(&dir.store(), "a", &input, &CryptoAlgorithm::Aes, &KEY, None, || Ok(()), |index, bytes| progress.push((index, bytes))).unwrap();
+        assert_eq!(report.size, data.len() as u64);
+        assert!(report.total_chunks > 1);
+        assert_eq!(report.new_chunks, report.total_chunks);
+        assert_eq!(report.reused_chunks, 0);
+        assert_eq!(progress.len() as u32, report.total_chunks);
+        assert_eq!(progress.iter().map(|&(_, bytes)| bytes).sum::<usize>(), data.len());
+        assert_eq!(restore(&dir, "a", &KEY).unwrap(), data);
+    }
+
+    #[test]
+    fn reuses_unchanged_chunks() {
+        let dir = TempDir::new("dedup");
+        let data = random(4_000_000, 2);
+        let first = store(&dir, "v1", &data, None).unwrap();
+        let again = store(&dir, "v1-copy", &data, None).unwrap();
+        assert_eq!(again.new_chunks, 0);
+        assert_eq!(again.reused_chunks, first.total_chunks);
+        assert_eq!(again.stored_bytes, 0);
+
+        // 中间插入数据后，插入位置之后的分片边界重新对齐
+        let mut edited = data.clone();
+        edited.splice(2_000_000..2_000_000, b"inserted".iter().copied());
+        let edit = store(&dir, "v2", &edited, None).unwrap();
+        assert!(edit.reused_chunks >= first.total_chunks - 2, "{} of {}", edit.reused_chunks, first.total_chunks);
+        assert!(edit.new_chunks <= 2);
+        assert_eq!(restore(&dir, "v1", &KEY).unwrap(), data);
+        assert_eq!(restore(&dir, "v2", &KEY).unwrap(), edited);
+    }
+
+    #[test]
+    fn compresses_stored_objects() {
+        let dir = TempDir::new("compression");
+        let data = b"compressible text ".repeat(100_000);
+        let compression = Compression::new(CompressionMethod::Zstd, None).unwrap();
+        let report = store(&dir, "text", &data, Some(compression)).unwrap();
+        assert!(report.stored_bytes < data.len() as u64 / 10);
+        assert_eq!(restore(&dir, "text", &KEY).unwrap(), data);
+    }
+
+    #[test]
+    fn replaces_manifests_and_lists_files_by_name() {
+        let dir = TempDir::new("list");
+        assert!(list_files(&dir.store(), &KEY).unwrap().is_empty());
+        store(&dir, "b", b"second", None).unwrap();
+        store(&dir, "a", b"first", None).unwrap();
+        store(&dir, "b", b"second, replaced", None).unwrap();
+        let files = list_files(&dir.store(), &KEY).unwrap();
+        assert_eq!(files.iter().map(|file| file.name.as_str()).collect::<Vec<_>>(), ["a", "b"]);
+        assert_eq!(files[1].size, 16);
+        assert_eq!(files[1].total_chunks, 1);
+        assert_eq!(files[1].algorithm, "aes");
+        assert_eq!(restore(&dir, "b", &KEY).unwrap(), b"second, replaced");
+        // 用其它密钥写入的清单被跳过
+        assert!(list_files(&dir.store(), &[6; 32]).unwrap().is_empty());
+    }
+
+    #[test]
+    fn an_empty_file_has_no_chunks() {
+        let dir = TempDir::new("empty");
+        let report = store(&dir, "empty", b"", None).unwrap();
+        assert_eq!((report.size, report.total_chunks), (0, 0));
+        assert_eq!(restore(&dir, "empty", &KEY).unwrap(), b"");
+    }
+
+    #[test]
+    fn rejects_unknown_names_and_other_keys() {
+        let dir = TempDir::new("unknown");
+        store(&dir, "a", b"data", None).unwrap();
+        assert_eq!(restore(&dir, "missing", &KEY).unwrap_err().code, ErrorCode::InvalidArgument);
+        // 清单文件名取决于密钥，其它密钥找不到清单
+        assert_eq!(restore(&dir, "a", &[6; 32]).unwrap_err().code, ErrorCode::InvalidArgument);
+    }
+
+    #[test]
+    fn detects_tampered_and_missing_objects() {
+        let dir = TempDir::new("tamper");
+        let data = random(1_000_000, 3);
+        store(&dir, "a", &data, None).unwrap();
+        let objects = object_files(&dir, "a");
+
+        let mut object = fs::read(&objects[0]).unwrap();
+        let last = object.len() - 1;
+        object[last] ^= 1;
+        fs::write(&objects[0], &object).unwrap();
+        assert_eq!(restore(&dir, "a", &KEY).unwrap_err().code, ErrorCode::AuthFailed);
+        // 校验失败时不留下输出文件
+        assert!(!dir.0.join("a.out").exists());
+
+        fs::remove_file(&objects[0]).unwrap();
+        assert_eq!(restore(&dir, "a", &KEY).unwrap_err().code, ErrorCode::IoOpen);
+    }
+
+    #[test]
+    fn detects_objects_swapped_between_ids() {
+        let dir = TempDir::new("swap");
+        store(&dir, "a", b"first file", None).unwrap();
+        store(&dir, "b", b"second file", None).unwrap();
+        let (a, b) = (object_files(&dir, "a"), object_files(&dir, "b"));
+        fs::copy(&b[0], &a[0]).unwrap();
+        assert_eq!(restore(&dir, "a", &KEY).unwrap_err().code, ErrorCode::AuthFailed);
+    }
+
+    #[test]
+    fn rejects_damaged_manifests() {
+        let dir = TempDir::new("manifest");
+        store(&dir, "a", b"data", None).unwrap();
+        let path = manifest_path(&dir.store(), &KEY, "a");
+        let mut manifest = fs::read(&path).unwrap();
+        manifest[MANIFEST_MAGIC.len()] = 9;
+        fs::write(&path, &manifest).unwrap();
+        assert_eq!(restore(&dir, "a", &KEY).unwrap_err().code, ErrorCode::Unsupported);
+        fs::write(&path, b"not a manifest").unwrap();
+        assert_eq!(restore(&dir, "a", &KEY).unwrap_err().code, ErrorCode::BadHeader);
+    }
+
+    #[test]
+    fn stops_when_cancelled() {
+        let dir = TempDir::new("cancel");
+        let input = dir.file("a.in", &random(100_000, 4));
+        let cancelled = || Err(CodedError::aborted("aborted"));
+        let err = store_file(&dir.store(), "a", &input, &CryptoAlgorithm::Aes, &KEY, None, cancelled, |_, _| {}).err().unwrap();
+        assert_eq!(err.code, ErrorCode::Aborted);
+        assert!(list_files(&dir.store(), &KEY).unwrap().is_empty());
+
+        store(&dir, "a", b"data", None).unwrap();
+        let output = dir.target("a.out");
+        let err = restore_file(&dir.store(), "a", &output, &KEY, cancelled, |_, _| {}).err().unwrap();
+        assert_eq!(err.code, ErrorCode::Aborted);
+    }
+}
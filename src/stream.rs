@@ -1,254 +1,337 @@
-use aes::Aes256;
-use block_modes::{BlockMode, Cbc};
-use block_padding::Pkcs7;
-use chacha20poly1305::{ChaCha20Poly1305, Key, Nonce};
-use chacha20poly1305::aead::{Aead, NewAead};
+use std::io::{BufWriter, Write};
+
+use chacha20::cipher::consts::U10;
+use chacha20::cipher::{KeyIvInit, StreamCipher, StreamCipherSeek};
+use chacha20::{hchacha, ChaCha20};
+use poly1305::universal_hash::KeyInit;
+use poly1305::{Poly1305, Tag};
 use rand::RngCore;
 
-use crate::crypto::CryptoAlgorithm;
+use crate::errors::{open_error, read_error, write_error, CodedError};
+use crate::fd::{self, SyncOutput, Target};
+use crate::job::JobControl;
+use crate::pipeline;
 
-type Aes256Cbc = Cbc<Aes256, Pkcs7>;
+/// 流的头部长度：前 16 字节用于 HChaCha20 派生子密钥，后 8 字节为初始 nonce
+pub const HEADER_BYTES: usize = 24;
+/// 每条消息的额外长度：1 字节加密的标签 + 16 字节 Poly1305 认证码
+pub const ABYTES: usize = 17;
 
-pub struct EncryptionStream {
-    algorithm: CryptoAlgorithm,
-    key: Vec<u8>,
-    iv_or_nonce: Vec<u8>,
-    buffer: Vec<u8>,
-    block_size: usize,
-    header_written: bool,
-}
+/// 普通消息
+pub const TAG_MESSAGE: u8 = 0;
+/// 标记一组消息的结束，不影响加密状态
+pub const TAG_PUSH: u8 = 1;
+/// 处理完这条消息后更换密钥
+pub const TAG_REKEY: u8 = 2;
+/// 流的最后一条消息
+pub const TAG_FINAL: u8 = TAG_PUSH | TAG_REKEY;
+
+/// 文件加密时每条消息的默认明文长度，与 libsodium 文档中加密文件的示例相同
+pub const DEFAULT_CHUNK_SIZE: usize = 4096;
+/// 每条消息的最大明文长度，限制解密时的内存占用
+pub const MAX_CHUNK_SIZE: usize = 64 * 1024 * 1024;
+
+/// 每处理这么多字节发送一次 chunkDone 事件
+const PROGRESS_INTERVAL: usize = 1024 * 1024;
 
-pub struct DecryptionStream {
-    algorithm: CryptoAlgorithm,
-    key: Vec<u8>,
-    iv_or_nonce: Option<Vec<u8>>,
-    buffer: Vec<u8>, // 用于收集加密数据的缓冲区
-    block_size: usize,
+/// libsodium `crypto_secretstream_xchacha20poly1305` 的加密状态，推送（加密）和拉取（解密）共用
+///
+/// 每条消息使用 ChaCha20-Poly1305 加密，nonce 由 32 位计数器和 64 位内部 nonce 组成；
+/// 每条消息之后把认证码的前 8 字节异或进内部 nonce 并递增计数器，因此消息不能被重排、删除或截断而不被发现。
+pub struct SecretStream {
+    key: [u8; 32],
+    nonce: [u8; 12],
 }
 
-impl EncryptionStream {
-    pub fn new(algorithm: CryptoAlgorithm, key: &[u8]) -> Result<Self, String> {
-        if key.len() != 32 {
-            return Err(format!("Key must be 32 bytes (256 bits)"));
-        }
+impl SecretStream {
+    fn new(key: &[u8; 32], header: &[u8; HEADER_BYTES]) -> Self {
+        let subkey = hchacha::<U10>(&(*key).into(), header[..16].into());
+        let mut nonce = [0u8; 12];
+        nonce[4..].copy_from_slice(&header[16..]);
+        let mut stream = SecretStream { key: subkey.into(), nonce };
+        stream.reset_counter();
+        stream
+    }
 
-        let (iv_or_nonce, block_size) = match algorithm {
-            CryptoAlgorithm::Aes => {
-                let mut iv = vec![0u8; 16];
-                let mut rng = rand::rngs::ThreadRng::default();
-                rng.fill_bytes(&mut iv);
-                (iv, 16)
-            },
-            CryptoAlgorithm::Chacha20Poly1305 => {
-                let mut nonce = vec![0u8; 12];
-                let mut rng = rand::rngs::ThreadRng::default();
-                rng.fill_bytes(&mut nonce);
-                (nonce, 64) // ChaCha20Poly1305 doesn't have a block size, but we'll use 64 bytes for buffer size
-            }
-        };
-
-        Ok(EncryptionStream {
-            algorithm,
-            key: key.to_vec(),
-            iv_or_nonce,
-            buffer: Vec::new(),
-            block_size,
-            header_written: false,
-        })
-    }
-
-    pub fn process(&mut self, data: &[u8], output: &mut Vec<u8>) -> Result<(), String> {
-        // Write IV/nonce as header if not already done
-        if !self.header_written {
-            output.extend_from_slice(&self.iv_or_nonce);
-            self.header_written = true;
+    /// 开始加密，返回状态和需要放在流开头的头部（对应 `crypto_secretstream_xchacha20poly1305_init_push`）
+    pub fn init_push(key: &[u8; 32]) -> (Self, [u8; HEADER_BYTES]) {
+        let mut header = [0u8; HEADER_BYTES];
+        rand::thread_rng().fill_bytes(&mut header);
+        (SecretStream::new(key, &header), header)
+    }
+
+    /// 用流开头的头部开始解密（对应 `crypto_secretstream_xchacha20poly1305_init_pull`）
+    pub fn init_pull(key: &[u8; 32], header: &[u8; HEADER_BYTES]) -> Self {
+        SecretStream::new(key, header)
+    }
+
+    fn reset_counter(&mut self) {
+        self.nonce[..4].copy_from_slice(&1u32.to_le_bytes());
+    }
+
+    /// 用当前密钥和 nonce 从第 block 个 64 字节分组开始的 ChaCha20 密钥流异或 data
+    fn apply_keystream(&self, data: &mut [u8], block: u64) {
+        let mut cipher = ChaCha20::new(&self.key.into(), &self.nonce.into());
+        cipher.seek(block * 64);
+        cipher.apply_keystream(data);
+    }
+
+    /// 计算消息的认证码：Poly1305 的密钥为第 0 个分组的密钥流，输入依次为附加数据、标签分组、密文和两个长度
+    fn authenticate(&self, ad: &[u8], block: &[u8; 64], ciphertext: &[u8]) -> Tag {
+        let mut poly_key = [0u8; 32];
+        self.apply_keystream(&mut poly_key, 0);
+        let mut input = Vec::with_capacity(ad.len() + ciphertext.len() + 112);
+        input.extend_from_slice(ad);
+        input.resize(input.len() + (0x10usize.wrapping_sub(ad.len()) & 0xf), 0);
+        input.extend_from_slice(block);
+        input.extend_from_slice(ciphertext);
+        // libsodium 在密文后填充 (0x10 - 64 + mlen) & 0xf 个零字节（并不是补齐到 16 字节），必须照搬才能互通
+        input.resize(input.len() + (ciphertext.len().wrapping_add(0x10).wrapping_sub(64) & 0xf), 0);
+        input.extend_from_slice(&(ad.len() as u64).to_le_bytes());
+        input.extend_from_slice(&((64 + ciphertext.len()) as u64).to_le_bytes());
+        Poly1305::new(&poly_key.into()).compute_unpadded(&input)
+    }
+
+    /// 处理完一条消息后更新内部 nonce 和计数器，需要时更换密钥
+    fn advance(&mut self, mac: &[u8], tag: u8) {
+        for (nonce, mac) in self.nonce[4..].iter_mut().zip(mac) {
+            *nonce ^= mac;
         }
+        let counter = u32::from_le_bytes(self.nonce[..4].try_into().unwrap()).wrapping_add(1);
+        self.nonce[..4].copy_from_slice(&counter.to_le_bytes());
+        if tag & TAG_REKEY != 0 || counter == 0 {
+            self.rekey();
+        }
+    }
 
-        self.buffer.extend_from_slice(data);
-
-        match self.algorithm {
-            CryptoAlgorithm::Aes => {
-                // 对于AES-CBC，我们需要保留一个块的大小作为填充，直到finalize
-                let process_len = if self.buffer.len() > self.block_size {
-                    self.buffer.len() - (self.buffer.len() % self.block_size)
-                } else {
-                    0
-                };
-                
-                if process_len > 0 {
-                    let cipher = Aes256Cbc::new_from_slices(&self.key, &self.iv_or_nonce)
-                        .map_err(|e| format!("AES cipher init failed: {:?}", e))?;
-                    
-                    let chunk = &self.buffer[0..process_len];
-                    let encrypted = cipher.encrypt_vec(chunk);
-                    output.extend_from_slice(&encrypted);
-                    
-                    self.buffer.drain(0..process_len);
-                }
-            },
-            CryptoAlgorithm::Chacha20Poly1305 => {
-                if !self.buffer.is_empty() {
-                    let cipher = ChaCha20Poly1305::new(Key::from_slice(&self.key));
-                    let encrypted = cipher.encrypt(Nonce::from_slice(&self.iv_or_nonce), &*self.buffer)
-                        .map_err(|e| format!("ChaCha20Poly1305 encrypt failed: {:?}", e))?;
-                    
-                    output.extend_from_slice(&encrypted);
-                    self.buffer.clear();
-                }
-            }
+    /// 更换密钥（对应 `crypto_secretstream_xchacha20poly1305_rekey`），推送端和拉取端必须在同一位置调用
+    pub fn rekey(&mut self) {
+        let mut material = [0u8; 40];
+        material[..32].copy_from_slice(&self.key);
+        material[32..].copy_from_slice(&self.nonce[4..]);
+        self.apply_keystream(&mut material, 0);
+        self.key.copy_from_slice(&material[..32]);
+        self.nonce[4..].copy_from_slice(&material[32..]);
+        self.reset_counter();
+    }
+
+    /// 加密一条消息，返回 `message.len() + ABYTES` 字节的密文
+    pub fn push(&mut self, message: &[u8], ad: &[u8], tag: u8) -> Vec<u8> {
+        let mut block = [0u8; 64];
+        block[0] = tag;
+        self.apply_keystream(&mut block, 1);
+
+        let mut output = Vec::with_capacity(message.len() + ABYTES);
+        output.push(block[0]);
+        output.extend_from_slice(message);
+        self.apply_keystream(&mut output[1..], 2);
+        let mac = self.authenticate(ad, &block, &output[1..]);
+        output.extend_from_slice(&mac);
+        self.advance(&mac, tag);
+        output
+    }
+
+    /// 验证并解密一条消息，返回明文和标签；验证失败时状态不变
+    pub fn pull(&mut self, chunk: &[u8], ad: &[u8]) -> Result<(Vec<u8>, u8), CodedError> {
+        if chunk.len() < ABYTES {
+            return Err(CodedError::truncated(format!("secretstream message is too short: {} bytes", chunk.len())));
         }
+        let (body, mac) = chunk.split_at(chunk.len() - 16);
+        let mut block = [0u8; 64];
+        block[0] = body[0];
+        self.apply_keystream(&mut block, 1);
+        let tag = block[0];
+        block[0] = body[0];
 
-        Ok(())
-    }
-
-    pub fn finalize(&mut self, output: &mut Vec<u8>) -> Result<(), String> {
-        // Process any remaining data in the buffer
-        if !self.buffer.is_empty() {
-            match self.algorithm {
-                CryptoAlgorithm::Aes => {
-                    let cipher = Aes256Cbc::new_from_slices(&self.key, &self.iv_or_nonce)
-                        .map_err(|e| format!("AES cipher init failed: {:?}", e))?;
-                    
-                    let encrypted = cipher.encrypt_vec(&self.buffer);
-                    output.extend_from_slice(&encrypted);
-                },
-                CryptoAlgorithm::Chacha20Poly1305 => {
-                    let cipher = ChaCha20Poly1305::new(Key::from_slice(&self.key));
-                    let encrypted = cipher.encrypt(Nonce::from_slice(&self.iv_or_nonce), &*self.buffer)
-                        .map_err(|e| format!("ChaCha20Poly1305 encrypt failed: {:?}", e))?;
-                    
-                    output.extend_from_slice(&encrypted);
-                }
-            }
-            self.buffer.clear();
+        if !tags_equal(&self.authenticate(ad, &block, &body[1..]), mac) {
+            return Err(CodedError::auth_failed("secretstream authentication failed: wrong key, chunk size or corrupted data"));
         }
-        
-        Ok(())
+        let mut message = body[1..].to_vec();
+        self.apply_keystream(&mut message, 2);
+        self.advance(mac, tag);
+        Ok((message, tag))
     }
 }
 
-impl DecryptionStream {
-    pub fn new(algorithm: CryptoAlgorithm, key: &[u8]) -> Result<Self, String> {
-        if key.len() != 32 {
-            return Err(format!("Key must be 32 bytes (256 bits)"));
+/// 加密或解密 secretstream 文件的统计
+pub struct StreamReport {
+    pub plaintext_size: u64,
+    pub encrypted_size: u64,
+    pub chunks: u32,
+}
+
+fn stream_key(key: &[u8]) -> Result<&[u8; 32], CodedError> {
+    key.try_into().map_err(|_| CodedError::invalid_key_length("Key must be 32 bytes (256 bits)"))
+}
+
+fn check_chunk_size(chunk_size: usize) -> Result<(), CodedError> {
+    if chunk_size == 0 || chunk_size > MAX_CHUNK_SIZE {
+        return Err(CodedError::invalid_argument(format!("secretstream chunk size must be between 1 and {} bytes", MAX_CHUNK_SIZE)));
+    }
+    Ok(())
+}
+
+/// 累计处理的字节数，每满 PROGRESS_INTERVAL 发送一次 chunkDone 事件
+struct Progress {
+    index: u32,
+    pending: usize,
+}
+
+impl Progress {
+    fn advance(&mut self, control: &JobControl, bytes: usize) {
+        self.pending += bytes;
+        if self.pending >= PROGRESS_INTERVAL {
+            self.index += 1;
+            control.chunk_done(self.index, self.pending);
+            self.pending = 0;
         }
+    }
+}
 
-        let block_size = match algorithm {
-            CryptoAlgorithm::Aes => 16,
-            CryptoAlgorithm::Chacha20Poly1305 => 64,
-        };
-
-        Ok(DecryptionStream {
-            algorithm,
-            key: key.to_vec(),
-            iv_or_nonce: None,
-            buffer: Vec::new(),
-            block_size,
-        })
-    }
-
-    pub fn process(&mut self, data: &[u8], output: &mut Vec<u8>) -> Result<(), String> {
-        // 添加新数据到缓冲区
-        self.buffer.extend_from_slice(data);
-
-        // 提取IV/nonce（如果尚未完成）
-        if self.iv_or_nonce.is_none() {
-            let header_size = match self.algorithm {
-                CryptoAlgorithm::Aes => 16,
-                CryptoAlgorithm::Chacha20Poly1305 => 12,
-            };
-
-            if self.buffer.len() < header_size {
-                // 尚未接收到足够的数据来提取头部
-                return Ok(());
-            }
+/// 以 libsodium 文档中加密文件的方式加密：24 字节头部，之后每 chunk_size 字节明文为一条消息，最后一条消息标记为 TAG_FINAL
+///
+/// 空文件输出一条空的 TAG_FINAL 消息。解密端（包括直接使用 libsodium 的程序）必须使用相同的 chunk_size。
+pub fn encrypt_file(input_path: &Target, output_path: &Target, key: &[u8], chunk_size: usize, control: &JobControl) -> Result<StreamReport, CodedError> {
+    let key = stream_key(key)?;
+    check_chunk_size(chunk_size)?;
+    let mut input = fd::open(input_path).map_err(|e| open_error("opening input file", e))?;
+    let output = fd::create(output_path).map_err(|e| open_error("creating output file", e))?;
+    let mut writer = BufWriter::new(output);
 
-            self.iv_or_nonce = Some(self.buffer[..header_size].to_vec());
-            self.buffer = self.buffer[header_size..].to_vec();
+    let (mut stream, header) = SecretStream::init_push(key);
+    writer
+        .write_all(&header)
+        .map_err(|e| write_error("writing output file", e))?;
+
+    // 预读下一条消息，以便把最后一条有数据的消息标记为 TAG_FINAL
+    let mut current = vec![0u8; chunk_size];
+    let mut next = vec![0u8; chunk_size];
+    let mut len = pipeline::read_full(&mut input, &mut current).map_err(|err| read_error("reading input file", err))?;
+    // 输出可能是管道（如标准输出），加密后的大小按写出的数据计算
+    let mut report = StreamReport { plaintext_size: 0, encrypted_size: HEADER_BYTES as u64, chunks: 0 };
+    let mut progress = Progress { index: 0, pending: 0 };
+    loop {
+        control.check()?;
+        let next_len = if len == chunk_size { pipeline::read_full(&mut input, &mut next).map_err(|err| read_error("reading input file", err))? } else { 0 };
+        let tag = if next_len == 0 { TAG_FINAL } else { TAG_MESSAGE };
+        writer
+            .write_all(&stream.push(&current[..len], &[], tag))
+            .map_err(|e| write_error("writing output file", e))?;
+        report.plaintext_size += len as u64;
+        report.encrypted_size += (len + ABYTES) as u64;
+        report.chunks += 1;
+        progress.advance(control, len);
+        if tag == TAG_FINAL {
+            break;
         }
+        std::mem::swap(&mut current, &mut next);
+        len = next_len;
+    }
+
+    let output = writer
+        .into_inner()
+        .map_err(|e| write_error("writing output file", e.into_error()))?;
+    output.sync_output().map_err(|e| write_error("flushing output file", e))?;
+    Ok(report)
+}
 
-        match self.algorithm {
-            CryptoAlgorithm::Aes => {
-                // 对于AES-CBC，流式解密是不安全的，因为它依赖于PKCS7填充
-                // 这种填充只能在最后一个块中应用，因此我们需要等待所有数据
-                // 在流式处理中，我们只是简单地收集所有数据，在finalize中一次性解密
-            },
-            CryptoAlgorithm::Chacha20Poly1305 => {
-                // 尝试解密ChaCha20Poly1305加密的数据
-                if !self.buffer.is_empty() && self.iv_or_nonce.is_some() {
-                    let iv_or_nonce = self.iv_or_nonce.as_ref().unwrap();
-                    let cipher = ChaCha20Poly1305::new(Key::from_slice(&self.key));
-                    
-                    match cipher.decrypt(Nonce::from_slice(iv_or_nonce), &*self.buffer) {
-                        Ok(decrypted) => {
-                            output.extend_from_slice(&decrypted);
-                            self.buffer.clear();
-                        },
-                        Err(_) => {
-                            // 如果解密失败，可能是因为没有收到完整的消息，我们继续收集数据
-                        }
-                    }
-                }
+/// 解密 secretstream 文件，chunk_size 必须与加密时相同；缺少 TAG_FINAL 消息（被截断）或其后还有数据时报错
+pub fn decrypt_file(input_path: &Target, output_path: &Target, key: &[u8], chunk_size: usize, control: &JobControl) -> Result<StreamReport, CodedError> {
+    let key = stream_key(key)?;
+    check_chunk_size(chunk_size)?;
+    let mut input = fd::open(input_path).map_err(|e| open_error("opening input file", e))?;
+    let mut header = [0u8; HEADER_BYTES];
+    if pipeline::read_full(&mut input, &mut header).map_err(|err| read_error("reading input file", err))? < HEADER_BYTES {
+        return Err(CodedError::bad_header("Not a secretstream file: missing the 24-byte header"));
+    }
+    let mut stream = SecretStream::init_pull(key, &header);
+
+    let output = fd::create(output_path).map_err(|e| open_error("creating output file", e))?;
+    let mut writer = BufWriter::new(output);
+    let mut buffer = vec![0u8; chunk_size + ABYTES];
+    // 输入可能是管道（如标准输入），加密文件的大小按读到的数据计算
+    let mut report = StreamReport { plaintext_size: 0, encrypted_size: HEADER_BYTES as u64, chunks: 0 };
+    let mut progress = Progress { index: 0, pending: 0 };
+    loop {
+        control.check()?;
+        let read = pipeline::read_full(&mut input, &mut buffer).map_err(|err| read_error("reading input file", err))?;
+        if read == 0 {
+            return Err(CodedError::truncated("secretstream data is truncated: the final message is missing"));
+        }
+        report.encrypted_size += read as u64;
+        let (message, tag) = stream.pull(&buffer[..read], &[])?;
+        writer
+            .write_all(&message)
+            .map_err(|e| write_error("writing output file", e))?;
+        report.plaintext_size += message.len() as u64;
+        report.chunks += 1;
+        progress.advance(control, message.len());
+        if tag == TAG_FINAL {
+            if pipeline::read_full(&mut input, &mut buffer[..1]).map_err(|err| read_error("reading input file", err))? > 0 {
+                return Err(CodedError::bad_format("Unexpected data after the final secretstream message"));
             }
+            break;
         }
+    }
+
+    writer
+        .into_inner()
+        .map_err(|e| write_error("writing output file", e.into_error()))?
+        .sync_output()
+        .map_err(|e| write_error("flushing output file", e))?;
+    Ok(report)
+}
+
+/// 以常数时间比较认证码，比较时间与第一个不同字节的位置无关
+fn tags_equal(computed: &[u8], expected: &[u8]) -> bool {
+    computed.len() == expected.len() && computed.iter().zip(expected).fold(0u8, |diff, (a, b)| diff | (a ^ b)) == 0
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// 密钥为 00 01 02 ... 1f
+    fn key() -> [u8; 32] {
+        std::array::from_fn(|i| i as u8)
+    }
+
+    /// 与 testdata/secretstream/messages.hex 中各条消息对应的（明文，附加数据，标签）
+    fn messages() -> Vec<(Vec<u8>, &'static [u8], u8)> {
+        vec![
+            (b"zippy-encryptor secretstream interop".to_vec(), b"", TAG_MESSAGE),
+            (vec![b'x'; 100], b"associated data", TAG_PUSH),
+            (b"after push, rekey next".to_vec(), b"", TAG_REKEY),
+            (Vec::new(), b"", TAG_MESSAGE),
+            (b"last message".to_vec(), b"", TAG_FINAL),
+        ]
+    }
 
-        Ok(())
+    /// 由 libsodium 1.0.18 的 crypto_secretstream_xchacha20poly1305_push 生成：第一行为头部，之后每行一条消息
+    fn libsodium_stream() -> ([u8; HEADER_BYTES], Vec<Vec<u8>>) {
+        let mut lines = include_str!("../testdata/secretstream/messages.hex").lines().map(|line| hex::decode(line).unwrap());
+        let header = lines.next().unwrap().try_into().unwrap();
+        (header, lines.collect())
     }
 
-    pub fn finalize(&mut self, output: &mut Vec<u8>) -> Result<(), String> {
-        // 确保我们有IV/nonce
-        if self.iv_or_nonce.is_none() {
-            return Err("No IV/nonce found in the encrypted data".to_string());
+    #[test]
+    fn pulls_libsodium_messages() {
+        let (header, chunks) = libsodium_stream();
+        let mut stream = SecretStream::init_pull(&key(), &header);
+        for ((message, ad, tag), chunk) in messages().into_iter().zip(&chunks) {
+            assert_eq!(stream.pull(chunk, ad).unwrap(), (message, tag));
         }
 
-        let iv_or_nonce = self.iv_or_nonce.as_ref().unwrap();
-
-        match self.algorithm {
-            CryptoAlgorithm::Aes => {
-                // AES-CBC解密要求数据长度是块大小的倍数
-                if self.buffer.len() % self.block_size != 0 {
-                    return Err(format!(
-                        "Invalid AES encrypted data length: {}. Must be multiple of block size {}",
-                        self.buffer.len(), self.block_size
-                    ));
-                }
-
-                // 一次性解密所有数据
-                if !self.buffer.is_empty() {
-                    let cipher = match Aes256Cbc::new_from_slices(&self.key, iv_or_nonce) {
-                        Ok(c) => c,
-                        Err(e) => return Err(format!("AES cipher init failed: {:?}", e)),
-                    };
-                    
-                    let decrypted = match cipher.decrypt_vec(&self.buffer) {
-                        Ok(d) => d,
-                        Err(e) => {
-                            // 提供更详细的错误诊断
-                            return Err(format!("AES decrypt failed: {:?}. 这可能是由于加密和解密过程不匹配导致的。", e));
-                        }
-                    };
-                    
-                    output.extend_from_slice(&decrypted);
-                }
-            },
-            CryptoAlgorithm::Chacha20Poly1305 => {
-                // 如果还有未解密的数据，尝试解密
-                if !self.buffer.is_empty() {
-                    let cipher = ChaCha20Poly1305::new(Key::from_slice(&self.key));
-                    
-                    let decrypted = match cipher.decrypt(Nonce::from_slice(iv_or_nonce), &*self.buffer) {
-                        Ok(d) => d,
-                        Err(e) => return Err(format!("ChaCha20Poly1305 decrypt failed: {:?}", e)),
-                    };
-                    
-                    output.extend_from_slice(&decrypted);
-                }
-            }
+        let mut stream = SecretStream::init_pull(&key(), &header);
+        assert_eq!(stream.pull(&chunks[1], b"").unwrap_err().code, crate::errors::ErrorCode::AuthFailed);
+    }
+
+    #[test]
+    fn pushes_the_same_bytes_as_libsodium() {
+        let (header, chunks) = libsodium_stream();
+        let mut stream = SecretStream::new(&key(), &header);
+        for ((message, ad, tag), chunk) in messages().into_iter().zip(&chunks) {
+            assert_eq!(&stream.push(&message, ad, tag), chunk);
         }
-        
-        // 清理缓冲区
-        self.buffer.clear();
-        
-        Ok(())
     }
-} 
\ No newline at end of file
+}
@@ -0,0 +1,209 @@
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+
+use napi::bindgen_prelude::*;
+use napi::{Env, JsFunction, JsObject, JsUnknown, Ref, Task};
+
+use crate::errors::{self, CodedError, ErrorCode};
+use crate::job::{self, Job, JobControl, JobState, ERR_ABORTED};
+
+/// `*Async` 版本的最后一个参数可以是标准的 AbortSignal，也可以是可暂停的 Job
+pub type Signal = Option<Either<AbortSignal, ClassInstance<Job>>>;
+
+/// 标准的 AbortSignal
+///
+/// 不使用 napi 自带的 AbortSignal：它只能取消尚未开始的任务，任务运行中取消时 Promise 会立即被拒绝，
+/// 而工作线程仍会把文件写完。这里在 signal 上注册 abort 监听器设置取消标志，由 JobControl::check()
+/// 在分块之间检查，操作按超时相同的路径清理输出文件后以 ERR_ABORTED 拒绝 Promise。
+/// 同一个 signal 可以传给多个操作；监听器以 `{ once: true }` 注册，操作结束后移除，长期存在的 signal 不会累积监听器。
+pub struct AbortSignal {
+    aborted: Arc<AtomicBool>,
+    /// signal 已经取消时不注册监听器
+    listener: Option<AbortListener>,
+}
+
+/// 注册在 signal 上的 abort 监听器，操作结束后在 JS 线程中移除
+pub struct AbortListener {
+    signal: Ref<()>,
+    listener: Ref<()>,
+}
+
+impl AbortListener {
+    /// 从 signal 上移除监听器并释放两个引用
+    fn remove(mut self, env: Env) -> Result<()> {
+        let signal: JsObject = env.get_reference_value(&self.signal)?;
+        let listener: JsFunction = env.get_reference_value(&self.listener)?;
+        let removed = signal
+            .get_named_property::<JsFunction>("removeEventListener")
+            .and_then(|remove| remove.call(Some(&signal), &[env.create_string("abort")?.into_unknown(), listener.into_unknown()]));
+        self.signal.unref(env)?;
+        self.listener.unref(env)?;
+        removed.map(|_| ())
+    }
+}
+
+impl TypeName for AbortSignal {
+    fn type_name() -> &'static str {
+        "AbortSignal"
+    }
+
+    fn value_type() -> ValueType {
+        ValueType::Object
+    }
+}
+
+impl ValidateNapiValue for AbortSignal {}
+
+impl FromNapiValue for AbortSignal {
+    unsafe fn from_napi_value(env: sys::napi_env, napi_val: sys::napi_value) -> Result<Self> {
+        let env = Env::from_raw(env);
+        let signal = JsObject::from_napi_value(env.raw(), napi_val)?;
+        let aborted = Arc::new(AtomicBool::new(
+            signal.get_named_property::<JsUnknown>("aborted")?.coerce_to_bool()?.get_value()?,
+        ));
+        let add_event_listener = signal.get_named_property::<JsUnknown>("addEventListener")?;
+        if add_event_listener.get_type()? != ValueType::Function {
+            return Err(Error::new(Status::InvalidArg, "signal must be an AbortSignal".to_string()));
+        }
+        if aborted.load(Ordering::Relaxed) {
+            return Ok(AbortSignal { aborted, listener: None });
+        }
+        let flag = aborted.clone();
+        let listener = env.create_function_from_closure("onabort", move |ctx| {
+            flag.store(true, Ordering::Relaxed);
+            ctx.env.get_undefined()
+        })?;
+        let listener = AbortListener { signal: env.create_reference(signal)?, listener: env.create_reference(listener)? };
+        let mut options = env.create_object()?;
+        options.set_named_property("once", env.get_boolean(true)?)?;
+        let add_event_listener: JsFunction = add_event_listener.cast();
+        let registered = add_event_listener.call(
+            Some(&env.get_reference_value::<JsObject>(&listener.signal)?),
+            &[
+                env.create_string("abort")?.into_unknown(),
+                env.get_reference_value::<JsFunction>(&listener.listener)?.into_unknown(),
+                options.into_unknown(),
+            ],
+        );
+        if let Err(err) = registered {
+            listener.remove(env)?;
+            return Err(err);
+        }
+        Ok(AbortSignal { aborted, listener: Some(listener) })
+    }
+}
+
+/// 在工作线程中运行的操作，参数为该操作的 JobControl
+type ControlledJob<T> = Box<dyn FnOnce(&JobControl) -> Result<T, ErrorCode> + Send>;
+
+/// 在 libuv 线程池中运行的文件操作，用于各操作的 `*Async` 版本
+///
+/// 带 JobControl 的任务在调用时创建（发送 queued 事件），在工作线程中开始执行并发送 started、finished / failed 事件；
+/// 同步版本不发送事件的操作（例如 computeFileMD5）以 FileTask::plain 创建，同样不发送事件。
+/// 传入 AbortSignal 或 Job 时关联到 JobControl，开始执行前和各分块之间都会检查取消和暂停。
+pub struct FileTask<T> {
+    job: Option<FileJob<T>>,
+    /// compute 只能返回 Status 错误，带错误码的错误保存在这里，reject 时再转换为 JS 错误
+    failure: Option<Error<ErrorCode>>,
+    /// 传入 AbortSignal 时注册的监听器，任务结束后移除
+    abort_listener: Option<AbortListener>,
+}
+
+enum FileJob<T> {
+    Controlled(JobControl, ControlledJob<T>),
+    Plain(PlainSignal, Box<dyn FnOnce() -> Result<T, ErrorCode> + Send>),
+}
+
+enum PlainSignal {
+    None,
+    Aborted(Arc<AtomicBool>),
+    Job(Arc<JobState>),
+}
+
+impl<T: ToNapiValue + TypeName + Send + 'static> FileTask<T> {
+    pub fn new<F>(mut control: JobControl, signal: Signal, job: F) -> AsyncTask<FileTask<T>>
+    where
+        F: FnOnce(&JobControl) -> Result<T, ErrorCode> + Send + 'static,
+    {
+        let mut abort_listener = None;
+        match signal {
+            Some(Either::A(signal)) => {
+                control.set_abort_flag(signal.aborted);
+                abort_listener = signal.listener;
+            }
+            Some(Either::B(job)) => control.attach_job(job.shared()),
+            None => {}
+        }
+        AsyncTask::new(FileTask { job: Some(FileJob::Controlled(control, Box::new(job))), failure: None, abort_listener })
+    }
+
+    /// 不带 JobControl 的任务只在开始执行前检查 signal / 等待暂停结束，运行中取消或暂停不会中断
+    pub fn plain<F>(signal: Signal, job: F) -> AsyncTask<FileTask<T>>
+    where
+        F: FnOnce() -> Result<T, ErrorCode> + Send + 'static,
+    {
+        let mut abort_listener = None;
+        let signal = match signal {
+            Some(Either::A(signal)) => {
+                abort_listener = signal.listener;
+                PlainSignal::Aborted(signal.aborted)
+            }
+            Some(Either::B(job)) => {
+                let state = job.shared();
+                job::plain_attached(&state);
+                PlainSignal::Job(state)
+            }
+            None => PlainSignal::None,
+        };
+        AsyncTask::new(FileTask { job: Some(FileJob::Plain(signal, Box::new(job))), failure: None, abort_listener })
+    }
+}
+
+impl<T: ToNapiValue + TypeName + Send + 'static> Task for FileTask<T> {
+    type Output = T;
+    type JsValue = T;
+
+    fn compute(&mut self) -> Result<T> {
+        let result = match self.job.take() {
+            Some(FileJob::Controlled(control, job)) => {
+                control.started();
+                let result = control.check().map_err(Error::from).and_then(|_| job(&control));
+                control.complete(result)
+            }
+            Some(FileJob::Plain(PlainSignal::Aborted(aborted), _)) if aborted.load(Ordering::Relaxed) => {
+                Err(CodedError::aborted(format!("{}: operation was aborted", ERR_ABORTED)).into())
+            }
+            Some(FileJob::Plain(PlainSignal::Job(state), job)) => {
+                job::plain_started(&state);
+                let result = job();
+                job::plain_finished(&state, &result);
+                result
+            }
+            Some(FileJob::Plain(_, job)) => job(),
+            None => Err(CodedError::unknown("File task has already run").into()),
+        };
+        result.map_err(|err| {
+            let reason = err.reason.clone();
+            self.failure = Some(err);
+            Error::from_reason(reason)
+        })
+    }
+
+    fn resolve(&mut self, _env: Env, output: T) -> Result<T> {
+        Ok(output)
+    }
+
+    fn reject(&mut self, env: Env, err: Error) -> Result<T> {
+        match self.failure.take() {
+            Some(failure) => Err(errors::to_js_error(env, failure)),
+            None => Err(err),
+        }
+    }
+
+    fn finally(&mut self, env: Env) -> Result<()> {
+        match self.abort_listener.take() {
+            Some(listener) => listener.remove(env),
+            None => Ok(()),
+        }
+    }
+}
@@ -0,0 +1,219 @@
+use std::collections::HashMap;
+use std::io;
+use std::path::Path;
+
+use crate::errors::CodedError;
+use crate::fd;
+
+/// 输出路径模板，例如 `{dir}/{name}.{hash8}.zenc`
+///
+/// 占位符：`{dir}` 输入所在的目录（没有目录时为 `.`），`{name}` 文件名，`{stem}` 去掉最后一个扩展名的文件名，
+/// `{ext}` 最后一个扩展名（不含点），`{index}` 在本次调用中的序号（从 0 开始），`{hash}` 输入文件内容的 BLAKE3 摘要（十六进制），
+/// `{hashN}` 摘要的前 N 位。`{{` 和 `}}` 表示花括号本身。
+pub struct PathTemplate {
+    source: String,
+    parts: Vec<Part>,
+}
+
+enum Part {
+    Text(String),
+    Dir,
+    Name,
+    Stem,
+    Ext,
+    Index,
+    Hash(usize),
+}
+
+impl PathTemplate {
+    pub fn parse(template: &str) -> Result<Self, CodedError> {
+        let invalid = |reason: &str| CodedError::invalid_argument(format!("Invalid output template {:?}: {}", template, reason));
+        let mut parts = Vec::new();
+        let mut text = String::new();
+        let mut chars = template.chars();
+        while let Some(c) = chars.next() {
+            match c {
+                '{' if chars.as_str().starts_with('{') => {
+                    chars.next();
+                    text.push('{');
+                }
+                '}' if chars.as_str().starts_with('}') => {
+                    chars.next();
+                    text.push('}');
+                }
+                '{' => {
+                    let (name, rest) = chars.as_str().split_once('}').ok_or_else(|| invalid("unclosed placeholder"))?;
+                    let part = match name {
+                        "dir" => Part::Dir,
+                        "name" => Part::Name,
+                        "stem" => Part::Stem,
+                        "ext" => Part::Ext,
+                        "index" => Part::Index,
+                        "hash" => Part::Hash(blake3::OUT_LEN * 2),
+                        _ => match name.strip_prefix("hash").and_then(|len| len.parse::<usize>().ok()) {
+                            Some(len) if (1..=blake3::OUT_LEN * 2).contains(&len) => Part::Hash(len),
+                            _ => return Err(invalid(&format!("unknown placeholder {{{}}}", name))),
+                        },
+                    };
+                    parts.extend((!text.is_empty()).then(|| Part::Text(std::mem::take(&mut text))));
+                    parts.push(part);
+                    chars = rest.chars();
+                }
+                '}' => return Err(invalid("unmatched }")),
+                c => text.push(c),
+            }
+        }
+        parts.extend((!text.is_empty()).then_some(Part::Text(text)));
+        if parts.is_empty() {
+            return Err(invalid("template is empty"));
+        }
+        Ok(PathTemplate { source: template.to_string(), parts })
+    }
+
+    /// 以 path 的目录和文件名展开模板；`{hash}` 读取 content_path 的内容计算
+    pub fn render(&self, path: &str, index: usize, content_path: &str) -> Result<String, CodedError> {
+        let path = Path::new(path);
+        let name = path.file_name().map(|name| name.to_string_lossy()).unwrap_or_default();
+        let (stem, ext) = match name.rsplit_once('.') {
+            Some((stem, ext)) if !stem.is_empty() => (stem, ext),
+            _ => (name.as_ref(), ""),
+        };
+        let hash = match self.parts.iter().any(|part| matches!(part, Part::Hash(_))) {
+            true => Some(hash_file(content_path)?),
+            false => None,
+        };
+        let mut rendered = String::new();
+        for part in &self.parts {
+            match part {
+                Part::Text(text) => rendered.push_str(text),
+                Part::Dir => match path.parent().filter(|dir| !dir.as_os_str().is_empty()) {
+                    Some(dir) => rendered.push_str(&dir.to_string_lossy()),
+                    None => rendered.push('.'),
+                },
+                Part::Name => rendered.push_str(&name),
+                Part::Stem => rendered.push_str(stem),
+                Part::Ext => rendered.push_str(ext),
+                Part::Index => rendered.push_str(&index.to_string()),
+                Part::Hash(len) => rendered.push_str(&hash.as_deref().unwrap_or_default()[..*len]),
+            }
+        }
+        Ok(rendered)
+    }
+
+    /// 展开为目标目录中的相对路径（以 `/` 分隔），去掉空段和 `.` 段；不能为空、不能包含 `..`
+    pub fn render_relative(&self, path: &str, index: usize, content_path: &str) -> Result<String, CodedError> {
+        let rendered = self.render(path, index, content_path)?;
+        let segments: Vec<_> = rendered.split('/').filter(|segment| !segment.is_empty() && *segment != ".").collect();
+        if segments.contains(&"..") {
+            return Err(CodedError::invalid_argument(format!("Invalid output template {:?}: {} is outside the output directory", self.source, rendered)));
+        }
+        if segments.is_empty() {
+            return Err(CodedError::invalid_argument(format!("Invalid output template {:?}: empty path for {}", self.source, path)));
+        }
+        Ok(segments.join("/"))
+    }
+
+    /// 检查展开后的路径互不相同且不是 reserved 中的名称；names 为与 paths 一一对应的输入名称
+    pub fn check_distinct(&self, names: &[&str], paths: &[String], reserved: &[&str]) -> Result<(), CodedError> {
+        let mut seen = HashMap::new();
+        for (name, path) in names.iter().zip(paths) {
+            if reserved.contains(&path.as_str()) {
+                return Err(CodedError::invalid_argument(format!("Invalid output template {:?}: {} maps to the reserved name {}", self.source, name, path)));
+            }
+            if let Some(other) = seen.insert(path.as_str(), name) {
+                return Err(CodedError::invalid_argument(format!("Invalid output template {:?}: {} and {} both map to {}", self.source, other, name, path)));
+            }
+        }
+        Ok(())
+    }
+}
+
+fn hash_file(path: &str) -> Result<String, CodedError> {
+    let mut file = fd::open(path).map_err(|e| CodedError::io_open(format!("Failed to open input file: {}", e)))?;
+    let mut hasher = blake3::Hasher::new();
+    io::copy(&mut file, &mut hasher).map_err(|e| CodedError::io_read(format!("Failed to read input file: {}", e)))?;
+    Ok(hasher.finalize().to_hex().to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::errors::ErrorCode;
+
+    fn render(template: &str, path: &str, index: usize) -> String {
+        PathTemplate::parse(template).unwrap().render(path, index, "/nonexistent").unwrap()
+    }
+
+    #[test]
+    fn expands_path_placeholders() {
+        assert_eq!(render("{dir}/{stem}-{index}.{ext}.zenc", "/data/report.final.pdf", 3), "/data/report.final-3.pdf.zenc");
+        assert_eq!(render("{name}.enc", "notes.txt", 0), "notes.txt.enc");
+        assert_eq!(render("{dir}/{name}", "notes.txt", 0), "./notes.txt");
+        assert_eq!(render("out/{stem}", "sub/dir/archive.tar.gz", 0), "out/archive.tar");
+    }
+
+    #[test]
+    fn names_without_extensions() {
+        assert_eq!(render("{stem}|{ext}", "/data/README", 0), "README|");
+        // 以点开头的文件名整个作为 stem
+        assert_eq!(render("{stem}|{ext}", "/data/.bashrc", 0), ".bashrc|");
+        assert_eq!(render("{stem}|{ext}", "/data/file.", 0), "file|");
+    }
+
+    #[test]
+    fn escapes_braces() {
+        assert_eq!(render("{{{name}}}", "a.txt", 0), "{a.txt}");
+        assert_eq!(render("}}x{{", "a.txt", 0), "}x{");
+    }
+
+    #[test]
+    fn expands_content_hashes() {
+        let path = std::env::temp_dir().join(format!("zippy-template-{}", std::process::id()));
+        std::fs::write(&path, b"abc").unwrap();
+        let content_path = path.to_string_lossy();
+        let expected = blake3::hash(b"abc").to_hex().to_string();
+        let template = PathTemplate::parse("{hash8}/{hash}/{hash64}").unwrap();
+        let rendered = template.render("a.txt", 0, &content_path).unwrap();
+        assert_eq!(rendered, format!("{}/{}/{}", &expected[..8], expected, expected));
+        std::fs::remove_file(&path).unwrap();
+        // 只有含 {hash} 的模板才读取文件
+        assert_eq!(template.render("a.txt", 0, &content_path).unwrap_err().code, ErrorCode::IoOpen);
+        assert!(PathTemplate::parse("{name}").unwrap().render("a.txt", 0, &content_path).is_ok());
+    }
+
+    #[test]
+    fn rejects_invalid_templates() {
+        for template in ["", "{", "{name", "}", "a}b", "{size}", "{hash0}", "{hash65}", "{hashx}", "{ name }"] {
+            assert_eq!(PathTemplate::parse(template).err().unwrap().code, ErrorCode::InvalidArgument, "{:?}", template);
+        }
+    }
+
+    #[test]
+    fn renders_relative_paths_inside_the_output_directory() {
+        let template = PathTemplate::parse("./{stem}//{index}/./{name}").unwrap();
+        assert_eq!(template.render_relative("a.txt", 2, "").unwrap(), "a/2/a.txt");
+        let escaping = PathTemplate::parse("../{name}").unwrap();
+        assert_eq!(escaping.render_relative("a.txt", 0, "").unwrap_err().code, ErrorCode::InvalidArgument);
+        let empty = PathTemplate::parse("{ext}").unwrap();
+        assert_eq!(empty.render_relative("README", 0, "").unwrap_err().code, ErrorCode::InvalidArgument);
+        let dot = PathTemplate::parse("./.").unwrap();
+        assert!(dot.render_relative("a.txt", 0, "").is_err());
+    }
+
+    #[test]
+    fn checks_rendered_paths_are_distinct() {
+        let template = PathTemplate::parse("{stem}.zenc").unwrap();
+        let names = ["a.txt", "b.txt", "a.md"];
+        let distinct = ["a.zenc".to_string(), "b.zenc".to_string(), "a2.zenc".to_string()];
+        template.check_distinct(&names, &distinct, &["manifest.json"]).unwrap();
+
+        let clashing = ["a.zenc".to_string(), "b.zenc".to_string(), "a.zenc".to_string()];
+        let err = template.check_distinct(&names, &clashing, &[]).unwrap_err();
+        assert_eq!(err.code, ErrorCode::InvalidArgument);
+        assert!(err.message.contains("a.txt and a.md both map to a.zenc"), "{}", err.message);
+
+        let reserved = ["manifest.json".to_string()];
+        let err = template.check_distinct(&names[..1], &reserved, &["manifest.json"]).unwrap_err();
+        assert!(err.message.contains("reserved name manifest.json"), "{}", err.message);
+    }
+}
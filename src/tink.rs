@@ -0,0 +1,303 @@
+use std::io::{BufWriter, Read, Write};
+
+use hkdf::Hkdf;
+use rand::RngCore;
+use sha1::Sha1;
+use sha2::{Sha256, Sha512};
+
+use crate::errors::{open_error, read_error, write_error, CodedError};
+use crate::fd::{self, SyncOutput, Target};
+use crate::gcm::{AesGcm, NONCE_LEN, TAG_LEN};
+use crate::job::JobControl;
+use crate::pipeline;
+
+/// 每个分段 nonce 的前缀长度，nonce = 前缀 || 分段序号（4 字节大端）|| 是否最后一段（1 字节）
+const NONCE_PREFIX_LEN: usize = 7;
+
+/// 默认的密文分段长度，对应 Tink 的 AES256_GCM_HKDF_4KB 模板
+pub const DEFAULT_SEGMENT_SIZE: usize = 4096;
+/// 最大的密文分段长度，限制内存占用
+pub const MAX_SEGMENT_SIZE: usize = 64 * 1024 * 1024;
+
+/// 每处理这么多字节发送一次 chunkDone 事件
+const PROGRESS_INTERVAL: usize = 1024 * 1024;
+
+/// 派生分段密钥时 HKDF 使用的摘要算法，对应 Tink 参数中的 hkdf_hash_type
+#[derive(Clone, Copy)]
+pub enum HkdfHash {
+    Sha1,
+    Sha256,
+    Sha512,
+}
+
+impl HkdfHash {
+    pub fn parse(name: &str) -> Result<Self, CodedError> {
+        match name.to_ascii_lowercase().as_str() {
+            "sha1" => Ok(HkdfHash::Sha1),
+            "sha256" => Ok(HkdfHash::Sha256),
+            "sha512" => Ok(HkdfHash::Sha512),
+            _ => Err(CodedError::unsupported(format!("Unsupported Tink HKDF hash: {}, supported: sha1, sha256, sha512", name))),
+        }
+    }
+}
+
+/// AES-GCM-HKDF-STREAMING 的参数，必须与 Tink 密钥集中 AesGcmHkdfStreamingParams 的设置一致
+pub struct StreamingParams {
+    /// 分段密钥的长度，16 或 32 字节
+    pub derived_key_size: usize,
+    pub hkdf_hash: HkdfHash,
+    /// 密文分段长度（包括每段 16 字节的认证标签；第一段还包括文件头）
+    pub segment_size: usize,
+}
+
+impl StreamingParams {
+    /// 文件头长度：1 字节头部长度 + 盐 + nonce 前缀
+    fn header_len(&self) -> usize {
+        1 + self.derived_key_size + NONCE_PREFIX_LEN
+    }
+
+    /// 第 index 段密文的完整长度
+    fn ciphertext_segment_len(&self, index: u32) -> usize {
+        if index == 0 {
+            self.segment_size - self.header_len()
+        } else {
+            self.segment_size
+        }
+    }
+
+    fn validate(&self, key: &[u8]) -> Result<(), CodedError> {
+        if self.derived_key_size != 16 && self.derived_key_size != 32 {
+            return Err(CodedError::invalid_key_length(format!("Tink derived key size must be 16 or 32 bytes, got {}", self.derived_key_size)));
+        }
+        if key.len() < 16 || key.len() < self.derived_key_size {
+            return Err(CodedError::invalid_key_length(format!("Tink streaming key must be at least {} bytes, got {}", self.derived_key_size.max(16), key.len())));
+        }
+        if self.segment_size <= self.header_len() + TAG_LEN || self.segment_size > MAX_SEGMENT_SIZE {
+            return Err(CodedError::invalid_argument(format!(
+                "Tink ciphertext segment size must be between {} and {} bytes",
+                self.header_len() + TAG_LEN + 1,
+                MAX_SEGMENT_SIZE
+            )));
+        }
+        Ok(())
+    }
+
+    /// 用 HKDF(主密钥, 盐, 附加数据) 派生本文件的分段密钥
+    fn derive_key(&self, key: &[u8], salt: &[u8], associated_data: &[u8]) -> Result<AesGcm, CodedError> {
+        let mut derived = vec![0u8; self.derived_key_size];
+        let expanded = match self.hkdf_hash {
+            HkdfHash::Sha1 => Hkdf::<Sha1>::new(Some(salt), key).expand(associated_data, &mut derived),
+            HkdfHash::Sha256 => Hkdf::<Sha256>::new(Some(salt), key).expand(associated_data, &mut derived),
+            HkdfHash::Sha512 => Hkdf::<Sha512>::new(Some(salt), key).expand(associated_data, &mut derived),
+        };
+        expanded.map_err(|_| CodedError::unknown("Tink HKDF expansion failed"))?;
+        AesGcm::new(&derived)
+    }
+}
+
+fn segment_nonce(prefix: &[u8], index: u32, last: bool) -> [u8; NONCE_LEN] {
+    let mut nonce = [0u8; NONCE_LEN];
+    nonce[..NONCE_PREFIX_LEN].copy_from_slice(prefix);
+    nonce[NONCE_PREFIX_LEN..NONCE_LEN - 1].copy_from_slice(&index.to_be_bytes());
+    nonce[NONCE_LEN - 1] = last as u8;
+    nonce
+}
+
+/// 加密或解密 Tink 流式 AEAD 文件的统计
+pub struct TinkReport {
+    pub plaintext_size: u64,
+    pub encrypted_size: u64,
+    pub segments: u32,
+}
+
+/// 累计处理的字节数，每满 PROGRESS_INTERVAL 发送一次 chunkDone 事件
+struct Progress {
+    index: u32,
+    pending: usize,
+}
+
+impl Progress {
+    fn advance(&mut self, bytes: usize, chunk_done: impl FnOnce(u32, usize)) {
+        self.pending += bytes;
+        if self.pending >= PROGRESS_INTERVAL {
+            self.index += 1;
+            chunk_done(self.index, self.pending);
+            self.pending = 0;
+        }
+    }
+}
+
+/// 以 Tink 流式 AEAD（AES-GCM-HKDF-STREAMING）的格式加密文件，输出可以用 Tink 的 newDecryptingStream 以相同的密钥和附加数据解密
+///
+/// 文件头为 头部长度 || 盐 || nonce 前缀，之后每段明文单独以 AES-GCM 加密；最后一段的 nonce 带有结束标志，因此截断可以被发现。
+pub fn encrypt_file(input_path: &Target, output_path: &Target, key: &[u8], associated_data: &[u8], params: &StreamingParams, control: &JobControl) -> Result<TinkReport, CodedError> {
+    params.validate(key)?;
+    let mut salt = vec![0u8; params.derived_key_size];
+    let mut prefix = [0u8; NONCE_PREFIX_LEN];
+    rand::thread_rng().fill_bytes(&mut salt);
+    rand::thread_rng().fill_bytes(&mut prefix);
+    let cipher = params.derive_key(key, &salt, associated_data)?;
+
+    let mut input = fd::open(input_path).map_err(|e| open_error("opening input file", e))?;
+    let output = fd::create(output_path).map_err(|e| open_error("creating output file", e))?;
+    let mut writer = BufWriter::new(output);
+    writer
+        .write_all(&[params.header_len() as u8])
+        .and_then(|_| writer.write_all(&salt))
+        .and_then(|_| writer.write_all(&prefix))
+        .map_err(|e| write_error("writing output file", e))?;
+
+    // 预读下一段，以便给最后一段（可能恰好是满的，也可能为空）设置结束标志
+    let mut current = vec![0u8; params.segment_size - TAG_LEN];
+    let mut next = vec![0u8; params.segment_size - TAG_LEN];
+    let mut index = 0u32;
+    let mut want = params.ciphertext_segment_len(0) - TAG_LEN;
+    let mut len = pipeline::read_full(&mut input, &mut current[..want]).map_err(|err| read_error("reading input file", err))?;
+    let mut report = TinkReport { plaintext_size: 0, encrypted_size: 0, segments: 0 };
+    let mut progress = Progress { index: 0, pending: 0 };
+    loop {
+        control.check()?;
+        let next_want = params.ciphertext_segment_len(1) - TAG_LEN;
+        let next_len = if len == want { pipeline::read_full(&mut input, &mut next[..next_want]).map_err(|err| read_error("reading input file", err))? } else { 0 };
+        let last = next_len == 0;
+        if !last && index == u32::MAX {
+            return Err(CodedError::invalid_argument("Input is too large for the Tink segment size"));
+        }
+        let tag = cipher.encrypt_in_place(&segment_nonce(&prefix, index, last), &[], &mut current[..len])?;
+        writer
+            .write_all(&current[..len])
+            .and_then(|_| writer.write_all(&tag))
+            .map_err(|e| write_error("writing output file", e))?;
+        report.plaintext_size += len as u64;
+        report.segments += 1;
+        progress.advance(len, |index, bytes| control.chunk_done(index, bytes));
+        if last {
+            break;
+        }
+        std::mem::swap(&mut current, &mut next);
+        len = next_len;
+        want = next_want;
+        index += 1;
+    }
+
+    let output = writer
+        .into_inner()
+        .map_err(|e| write_error("writing output file", e.into_error()))?;
+    output.sync_output().map_err(|e| write_error("flushing output file", e))?;
+    report.encrypted_size = output
+        .metadata()
+        .map_err(|e| read_error("reading output file metadata", e))?
+        .len();
+    Ok(report)
+}
+
+/// 解密文件头之后的各段密文并写入 writer，返回明文长度和分段数
+fn decrypt_segments<R: Read, W: Write>(input: &mut R, writer: &mut W, cipher: &AesGcm, prefix: &[u8], params: &StreamingParams, check: impl Fn() -> Result<(), CodedError>, mut chunk_done: impl FnMut(u32, usize)) -> Result<(u64, u32), CodedError> {
+    let mut current = vec![0u8; params.segment_size];
+    let mut next = vec![0u8; params.segment_size];
+    let mut index = 0u32;
+    let mut want = params.ciphertext_segment_len(0);
+    let mut len = pipeline::read_full(input, &mut current[..want]).map_err(|err| read_error("reading input file", err))?;
+    let (mut plaintext_size, mut segments) = (0u64, 0u32);
+    let mut progress = Progress { index: 0, pending: 0 };
+    loop {
+        check()?;
+        if len < TAG_LEN {
+            return Err(CodedError::truncated("Tink streaming ciphertext is truncated"));
+        }
+        let next_want = params.ciphertext_segment_len(1);
+        let next_len = if len == want { pipeline::read_full(input, &mut next[..next_want]).map_err(|err| read_error("reading input file", err))? } else { 0 };
+        let last = next_len == 0;
+        if !last && index == u32::MAX {
+            return Err(CodedError::bad_format("Tink streaming ciphertext has too many segments"));
+        }
+        let (body, tag) = current[..len].split_at_mut(len - TAG_LEN);
+        cipher
+            .decrypt_in_place(&segment_nonce(prefix, index, last), &[], body, tag)
+            .map_err(|_| CodedError::auth_failed(format!("Tink segment {} authentication failed: wrong key, associated data, parameters or corrupted data", index)))?;
+        writer
+            .write_all(body)
+            .map_err(|e| write_error("writing output file", e))?;
+        plaintext_size += body.len() as u64;
+        segments += 1;
+        progress.advance(body.len(), &mut chunk_done);
+        if last {
+            break;
+        }
+        std::mem::swap(&mut current, &mut next);
+        len = next_len;
+        want = next_want;
+        index += 1;
+    }
+
+    Ok((plaintext_size, segments))
+}
+
+/// 解密 Tink 流式 AEAD（AES-GCM-HKDF-STREAMING）文件，密钥、附加数据和参数必须与加密时一致
+pub fn decrypt_file(input_path: &Target, output_path: &Target, key: &[u8], associated_data: &[u8], params: &StreamingParams, control: &JobControl) -> Result<TinkReport, CodedError> {
+    params.validate(key)?;
+    let mut input = fd::open(input_path).map_err(|e| open_error("opening input file", e))?;
+    let encrypted_size = input
+        .metadata()
+        .map_err(|e| read_error("reading input file metadata", e))?
+        .len();
+    let mut header = vec![0u8; params.header_len()];
+    if pipeline::read_full(&mut input, &mut header).map_err(|err| read_error("reading input file", err))? < header.len() {
+        return Err(CodedError::truncated("Not a Tink streaming ciphertext: header is truncated"));
+    }
+    if header[0] as usize != params.header_len() {
+        return Err(CodedError::bad_header(format!(
+            "Not a Tink streaming ciphertext for these parameters: header length {} does not match derived key size {}",
+            header[0], params.derived_key_size
+        )));
+    }
+    let prefix = &header[1 + params.derived_key_size..];
+    let cipher = params.derive_key(key, &header[1..1 + params.derived_key_size], associated_data)?;
+
+    let output = fd::create(output_path).map_err(|e| open_error("creating output file", e))?;
+    let mut writer = BufWriter::new(output);
+    let (plaintext_size, segments) = decrypt_segments(&mut input, &mut writer, &cipher, prefix, params, || control.check(), |index, bytes| control.chunk_done(index, bytes))?;
+
+    writer
+        .into_inner()
+        .map_err(|e| write_error("writing output file", e.into_error()))?
+        .sync_output()
+        .map_err(|e| write_error("flushing output file", e))?;
+    Ok(TinkReport { plaintext_size, encrypted_size, segments })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const KEY: &str = "000102030405060708090a0b0c0d0e0f00112233445566778899aabbccddeeff";
+    const ASSOCIATED_DATA: &[u8] = b"aad";
+
+    fn params() -> StreamingParams {
+        StreamingParams { derived_key_size: 16, hkdf_hash: HkdfHash::Sha256, segment_size: 64 }
+    }
+
+    fn decrypt(file: &[u8], associated_data: &[u8]) -> Result<(Vec<u8>, u32), CodedError> {
+        let params = params();
+        let (header, mut body) = file.split_at(params.header_len());
+        assert_eq!(header[0] as usize, params.header_len());
+        let cipher = params.derive_key(&hex::decode(KEY).unwrap(), &header[1..1 + params.derived_key_size], associated_data)?;
+        let mut plaintext = Vec::new();
+        let (size, segments) = decrypt_segments(&mut body, &mut plaintext, &cipher, &header[1 + params.derived_key_size..], &params, || Ok(()), |_, _| {})?;
+        assert_eq!(size, plaintext.len() as u64);
+        Ok((plaintext, segments))
+    }
+
+    #[test]
+    fn decrypts_wire_format_vector() {
+        // Tink 本身无法在测试环境中运行，该向量由按 Tink 流式 AEAD 线格式独立编写的 Python 实现（cryptography 的 HKDF 和 AESGCM）生成：
+        // AES128_GCM_HKDF_SHA256，密文分段 64 字节，明文为 i % 251（i = 0..150），共 4 段
+        let file = hex::decode(include_str!("../testdata/tink/aes128-gcm-hkdf-sha256-64.hex").trim()).unwrap();
+        let expected: Vec<u8> = (0..150).map(|i| (i % 251) as u8).collect();
+        assert_eq!(decrypt(&file, ASSOCIATED_DATA).unwrap(), (expected, 4));
+
+        assert_eq!(decrypt(&file, b"other").unwrap_err().code, crate::errors::ErrorCode::AuthFailed);
+        assert_eq!(decrypt(&file[..file.len() - 1], ASSOCIATED_DATA).unwrap_err().code, crate::errors::ErrorCode::AuthFailed);
+        assert_eq!(decrypt(&file[..params().segment_size * 2], ASSOCIATED_DATA).unwrap_err().code, crate::errors::ErrorCode::AuthFailed);
+    }
+}
@@ -0,0 +1,415 @@
+use std::fs::File;
+use std::io::{self, Read, Seek, SeekFrom, Write};
+
+use crate::config::{self, LogLevel};
+use crate::direct::DirectIo;
+
+/// 分片加解密读写的文件
+///
+/// 启用 `io-uring` 特性时，在 Linux 上通过 io_uring 读写普通文件：一次大的读取拆成多段同时提交，
+/// 写入提交后立即返回，最多同时进行 QUEUE_DEPTH 个请求，flush 时等待全部完成，NVMe 磁盘不再受限于单个请求的延迟。
+/// 未启用该特性、不是普通文件（管道等）或内核不支持 io_uring 时直接读写 File。
+/// 开启直接 I/O（directIo）后改为绕过页缓存读写，不再使用 io_uring。
+pub struct RingFile {
+    file: File,
+    #[cfg(all(target_os = "linux", feature = "io-uring"))]
+    queue: Option<ring::Queue>,
+    direct: Option<DirectIo>,
+}
+
+impl RingFile {
+    /// 从 file 当前的读写位置开始读写
+    pub fn new(file: File) -> RingFile {
+        #[cfg(all(target_os = "linux", feature = "io-uring"))]
+        {
+            let queue = match file.metadata() {
+                Ok(metadata) if metadata.is_file() => ring::Queue::new(&file).ok(),
+                _ => None,
+            };
+            RingFile { file, queue, direct: None }
+        }
+        #[cfg(not(all(target_os = "linux", feature = "io-uring")))]
+        RingFile { file, direct: None }
+    }
+
+    /// enabled 为 true 时开启直接 I/O，见 set_direct_io()
+    pub fn direct_io(mut self, enabled: bool) -> RingFile {
+        if enabled {
+            self.set_direct_io();
+        }
+        self
+    }
+
+    /// 从当前位置开始绕过页缓存读写；不是普通文件或文件系统不支持时继续使用普通读写
+    pub fn set_direct_io(&mut self) {
+        if self.direct.is_some() || !self.file.metadata().is_ok_and(|metadata| metadata.is_file()) {
+            return;
+        }
+        let result = self.flush().and_then(|_| DirectIo::new(&self.file));
+        match result {
+            Ok(direct) => {
+                #[cfg(all(target_os = "linux", feature = "io-uring"))]
+                {
+                    self.queue = None;
+                }
+                self.direct = Some(direct);
+            },
+            Err(err) => config::log(LogLevel::Debug, format!("direct I/O unavailable, using buffered I/O: {}", err)),
+        }
+    }
+
+    /// 等待进行中的写入完成后返回底层文件，文件的读写位置与 RingFile 一致
+    pub fn file(&mut self) -> io::Result<&File> {
+        self.flush()?;
+        Ok(&self.file)
+    }
+}
+
+impl Read for RingFile {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        if let Some(direct) = self.direct.as_mut() {
+            return direct.read(&self.file, buf);
+        }
+        #[cfg(all(target_os = "linux", feature = "io-uring"))]
+        if let Some(queue) = self.queue.as_mut() {
+            return queue.read(buf);
+        }
+        self.file.read(buf)
+    }
+}
+
+impl Write for RingFile {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        if let Some(direct) = self.direct.as_mut() {
+            return direct.write(&self.file, buf);
+        }
+        #[cfg(all(target_os = "linux", feature = "io-uring"))]
+        if let Some(queue) = self.queue.as_mut() {
+            return queue.write(buf);
+        }
+        self.file.write(buf)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        if let Some(direct) = self.direct.as_mut() {
+            return direct.flush(&self.file);
+        }
+        #[cfg(all(target_os = "linux", feature = "io-uring"))]
+        if let Some(queue) = self.queue.as_mut() {
+            return queue.flush();
+        }
+        self.file.flush()
+    }
+}
+
+impl Seek for RingFile {
+    fn seek(&mut self, pos: SeekFrom) -> io::Result<u64> {
+        if let Some(direct) = self.direct.as_mut() {
+            return direct.seek(&self.file, pos);
+        }
+        #[cfg(all(target_os = "linux", feature = "io-uring"))]
+        if let Some(queue) = self.queue.as_mut() {
+            return queue.seek(pos);
+        }
+        self.file.seek(pos)
+    }
+}
+
+impl Drop for RingFile {
+    fn drop(&mut self) {
+        // 直接 I/O 缓冲区中还没有写出的数据
+        if let Some(direct) = self.direct.as_mut() {
+            let _ = direct.flush(&self.file);
+        }
+    }
+}
+
+#[cfg(all(target_os = "linux", feature = "io-uring"))]
+mod ring {
+    use std::fs::File;
+    use std::io::{self, Seek, SeekFrom};
+    use std::os::unix::fs::FileExt;
+    use std::os::unix::io::AsRawFd;
+
+    use io_uring::{opcode, types, IoUring};
+
+    /// 同时进行的请求数
+    const QUEUE_DEPTH: u32 = 8;
+    /// 读取拆分后每段的最小长度
+    const MIN_READ_SEGMENT: usize = 128 * 1024;
+    /// 单个写入请求的最大长度，进行中的写入最多占用 QUEUE_DEPTH 倍的内存
+    const MAX_WRITE_SEGMENT: usize = 1024 * 1024;
+
+    /// 提交给内核的一个请求及其缓冲区；收到完成事件之前内核仍可能读写缓冲区，不能释放
+    struct Request {
+        data: Vec<u8>,
+        offset: u64,
+        write: bool,
+        /// 读取完成后记录结果（读到的字节数或负的 errno）
+        result: Option<i32>,
+    }
+
+    /// 一个文件的请求队列（由 io-uring crate 实现），记录读写位置和进行中的请求
+    ///
+    /// 读取先读入队列自己的缓冲区再复制给调用方，因此无论是否出错，调用方的缓冲区都不会在返回后被内核写入。
+    pub struct Queue {
+        ring: IoUring,
+        /// 文件的副本，进行中的请求完成之前一直有效
+        file: File,
+        offset: u64,
+        /// 按 user_data 编号的请求
+        requests: Vec<Option<Request>>,
+        /// 已放入提交队列、还没有收到完成事件的请求数
+        in_flight: u32,
+        /// 已完成的写入出错时记录下来，下次写入或 flush 时返回
+        error: Option<io::Error>,
+        /// 等待完成事件失败后为 true，之后不再提交请求
+        broken: bool,
+    }
+
+    impl Queue {
+        pub fn new(file: &File) -> io::Result<Queue> {
+            let offset = (&*file).stream_position()?;
+            Ok(Queue {
+                ring: IoUring::new(QUEUE_DEPTH)?,
+                file: file.try_clone()?,
+                offset,
+                requests: (0..QUEUE_DEPTH).map(|_| None).collect(),
+                in_flight: 0,
+                error: None,
+                broken: false,
+            })
+        }
+
+        /// 把 buf 拆成最多 QUEUE_DEPTH 段同时读取，返回从当前位置开始连续读到的字节数
+        pub fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+            self.wait_writes()?;
+            if buf.is_empty() {
+                return Ok(0);
+            }
+            let segment = buf.len().div_ceil(QUEUE_DEPTH as usize).max(MIN_READ_SEGMENT);
+            let mut slots = Vec::new();
+            for (index, part) in buf.chunks(segment).enumerate() {
+                let offset = self.offset + (index * segment) as u64;
+                slots.push(self.push(Request { data: vec![0; part.len()], offset, write: false, result: None })?);
+            }
+            while self.in_flight > 0 {
+                self.enter(1)?;
+            }
+            let requests: Vec<_> = slots.iter().map(|&slot| self.requests[slot].take().expect("a completed read")).collect();
+            let mut total = 0;
+            for request in requests {
+                let res = request.result.unwrap_or_default();
+                if res < 0 {
+                    return Err(io::Error::from_raw_os_error(-res));
+                }
+                let len = res as usize;
+                buf[total..total + len].copy_from_slice(&request.data[..len]);
+                total += len;
+                if len < request.data.len() {
+                    break;
+                }
+            }
+            self.offset += total as u64;
+            Ok(total)
+        }
+
+        /// 复制 buf 的开头（最多 MAX_WRITE_SEGMENT 字节）后提交写入，不等待完成
+        pub fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+            if let Some(err) = self.error.take() {
+                return Err(err);
+            }
+            if buf.is_empty() {
+                return Ok(0);
+            }
+            while self.in_flight == QUEUE_DEPTH {
+                self.enter(1)?;
+            }
+            let data = buf[..buf.len().min(MAX_WRITE_SEGMENT)].to_vec();
+            let len = data.len();
+            self.push(Request { data, offset: self.offset, write: true, result: None })?;
+            self.offset += len as u64;
+            self.enter(0)?;
+            Ok(len)
+        }
+
+        /// 等待所有写入完成，并把文件的读写位置移到当前位置
+        pub fn flush(&mut self) -> io::Result<()> {
+            self.wait_writes()?;
+            (&self.file).seek(SeekFrom::Start(self.offset))?;
+            Ok(())
+        }
+
+        pub fn seek(&mut self, pos: SeekFrom) -> io::Result<u64> {
+            let offset = match pos {
+                SeekFrom::Start(offset) => Some(offset),
+                SeekFrom::Current(delta) => self.offset.checked_add_signed(delta),
+                SeekFrom::End(delta) => {
+                    self.wait_writes()?;
+                    self.file.metadata()?.len().checked_add_signed(delta)
+                },
+            };
+            self.offset = offset.ok_or_else(|| io::Error::new(io::ErrorKind::InvalidInput, "invalid seek to a negative or overflowing position"))?;
+            Ok(self.offset)
+        }
+
+        fn wait_writes(&mut self) -> io::Result<()> {
+            while self.in_flight > 0 {
+                self.enter(1)?;
+            }
+            match self.error.take() {
+                Some(err) => Err(err),
+                None => Ok(()),
+            }
+        }
+
+        /// 把请求放入提交队列，返回其编号；调用方保证进行中的请求不超过 QUEUE_DEPTH
+        fn push(&mut self, request: Request) -> io::Result<usize> {
+            if self.broken {
+                return Err(io::Error::other("io_uring queue failed"));
+            }
+            let slot = self.requests.iter().position(Option::is_none).expect("a free request slot");
+            let request = self.requests[slot].insert(request);
+            let fd = types::Fd(self.file.as_raw_fd());
+            let len = request.data.len() as u32;
+            let entry = match request.write {
+                true => opcode::Write::new(fd, request.data.as_ptr(), len).offset(request.offset).build(),
+                false => opcode::Read::new(fd, request.data.as_mut_ptr(), len).offset(request.offset).build(),
+            };
+            // 安全性：缓冲区由 requests 持有，收到完成事件之前不会被释放或移动（见 reap() 和 Drop）
+            if unsafe { self.ring.submission().push(&entry.user_data(slot as u64)) }.is_err() {
+                self.requests[slot] = None;
+                return Err(io::Error::other("io_uring submission queue is full"));
+            }
+            self.in_flight += 1;
+            Ok(slot)
+        }
+
+        /// 提交放入的请求并等待至少 want 个完成事件，然后处理所有已完成的请求
+        ///
+        /// 被信号中断或内核暂时无法处理时重试；其它错误使队列不再可用，进行中的请求由 Drop 处理。
+        fn enter(&mut self, want: usize) -> io::Result<()> {
+            if self.broken {
+                return Err(io::Error::other("io_uring queue failed"));
+            }
+            loop {
+                match self.ring.submit_and_wait(want) {
+                    Ok(_) => break,
+                    Err(err) if matches!(err.raw_os_error(), Some(libc::EINTR | libc::EAGAIN | libc::EBUSY)) => self.reap(),
+                    Err(err) => {
+                        self.broken = true;
+                        return Err(err);
+                    },
+                }
+            }
+            self.reap();
+            Ok(())
+        }
+
+        /// 处理完成队列：写入不完整时同步写出剩余部分并释放缓冲区，读取的结果留给 read() 取走
+        fn reap(&mut self) {
+            let completions: Vec<_> = self.ring.completion().map(|cqe| (cqe.user_data() as usize, cqe.result())).collect();
+            for (slot, res) in completions {
+                let Some(request) = self.requests[slot].as_mut() else {
+                    continue;
+                };
+                self.in_flight -= 1;
+                if !request.write {
+                    request.result = Some(res);
+                    continue;
+                }
+                let Some(Request { data, offset, .. }) = self.requests[slot].take() else {
+                    continue;
+                };
+                let result = match res {
+                    res if res < 0 => Err(io::Error::from_raw_os_error(-res)),
+                    res => self.file.write_all_at(&data[res as usize..], offset + res as u64),
+                };
+                if let Err(err) = result {
+                    self.error.get_or_insert(err);
+                }
+            }
+        }
+    }
+
+    impl Drop for Queue {
+        fn drop(&mut self) {
+            // 内核仍可能读写进行中请求的缓冲区，必须等到全部完成后才能释放
+            while self.in_flight > 0 {
+                if self.enter(1).is_err() {
+                    // 无法确认请求已经完成：泄漏缓冲区，不让内核写入已释放的内存
+                    std::mem::forget(std::mem::take(&mut self.requests));
+                    return;
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::path::PathBuf;
+
+    /// 测试用的临时文件，结束时删除
+    struct TempFile(PathBuf);
+
+    impl TempFile {
+        fn new(name: &str) -> Self {
+            TempFile(std::env::temp_dir().join(format!("zippy-uring-{}-{}", std::process::id(), name)))
+        }
+
+        fn create(&self) -> RingFile {
+            RingFile::new(File::options().read(true).write(true).create(true).truncate(true).open(&self.0).unwrap())
+        }
+    }
+
+    impl Drop for TempFile {
+        fn drop(&mut self) {
+            let _ = std::fs::remove_file(&self.0);
+        }
+    }
+
+    fn pattern(len: usize) -> Vec<u8> {
+        (0..len).map(|index| (index % 251) as u8).collect()
+    }
+
+    #[test]
+    fn writes_and_reads_back() {
+        let temp = TempFile::new("roundtrip");
+        let data = pattern(5 * 1024 * 1024 + 123);
+        let mut file = temp.create();
+        file.write_all(&data).unwrap();
+        file.flush().unwrap();
+        assert_eq!(file.seek(SeekFrom::End(0)).unwrap(), data.len() as u64);
+        assert_eq!(file.seek(SeekFrom::Start(100)).unwrap(), 100);
+        let mut read = Vec::new();
+        file.read_to_end(&mut read).unwrap();
+        assert_eq!(read, &data[100..]);
+        // 底层文件的读写位置与 RingFile 一致
+        assert_eq!(file.file().unwrap().stream_position().unwrap(), data.len() as u64);
+    }
+
+    #[test]
+    fn read_waits_for_pending_writes() {
+        let temp = TempFile::new("pending");
+        let data = pattern(3 * 1024 * 1024);
+        let mut file = temp.create();
+        file.write_all(&data).unwrap();
+        file.seek(SeekFrom::Start(0)).unwrap();
+        let mut read = vec![0u8; data.len()];
+        file.read_exact(&mut read).unwrap();
+        assert_eq!(read, data);
+        assert_eq!(file.read(&mut [0u8; 16]).unwrap(), 0);
+    }
+
+    #[test]
+    fn drop_completes_pending_writes() {
+        let temp = TempFile::new("drop");
+        let data = pattern(9 * 1024 * 1024);
+        let mut file = temp.create();
+        file.write_all(&data).unwrap();
+        drop(file);
+        assert_eq!(std::fs::read(&temp.0).unwrap(), data);
+    }
+}
@@ -0,0 +1,464 @@
+use std::fs::File;
+use std::io::{ErrorKind, Read, Seek, SeekFrom, Write};
+use std::path::Path;
+
+use rand::RngCore;
+
+use crate::crypto::{verify_volume_mac, volume_mac};
+use crate::errors::{CodedError, ErrorCode};
+use crate::format::ERR_TRUNCATED;
+
+/// 分卷文件开头的魔数
+pub const VOLUME_MAGIC: &[u8] = b"ZIPPYVOL";
+/// 分卷头版本
+const VOLUME_VERSION: u8 = 1;
+/// 标志位：最后一个分卷
+const VOLUME_FLAG_LAST: u8 = 0x01;
+/// 分卷头长度：魔数(8) + 版本(u8) + 标志位(u8) + 保留(u16) + 分卷序号(u32 LE) + 分卷组 id(16) + 认证标签(32)
+pub const VOLUME_HEADER_LEN: usize = 64;
+const VOLUME_SET_ID_LEN: usize = 16;
+
+/// 分卷头，每个分卷文件开头一个
+///
+/// 分卷序号、是否为最后一个分卷以及同一组分卷共享的随机 id 都由 HMAC-SHA256 认证，
+/// 读取时可以发现顺序错误、缺失或混入其它分卷组的分卷。
+pub struct VolumeHeader {
+    pub set_id: [u8; VOLUME_SET_ID_LEN],
+    /// 从 0 开始的分卷序号
+    pub index: u32,
+    pub last: bool,
+}
+
+impl VolumeHeader {
+    pub fn to_bytes(&self, key: &[u8]) -> Result<Vec<u8>, CodedError> {
+        let mut bytes = Vec::with_capacity(VOLUME_HEADER_LEN);
+        bytes.extend_from_slice(VOLUME_MAGIC);
+        bytes.push(VOLUME_VERSION);
+        bytes.push(if self.last { VOLUME_FLAG_LAST } else { 0 });
+        bytes.extend_from_slice(&[0u8; 2]);
+        bytes.extend_from_slice(&self.index.to_le_bytes());
+        bytes.extend_from_slice(&self.set_id);
+        let tag = volume_mac(key, &bytes)?;
+        bytes.extend_from_slice(&tag);
+        debug_assert_eq!(bytes.len(), VOLUME_HEADER_LEN);
+        Ok(bytes)
+    }
+
+    /// 读取并认证分卷头，what 用于错误信息
+    pub fn read<R: Read>(reader: &mut R, key: &[u8], what: &str) -> Result<Self, CodedError> {
+        let mut bytes = [0u8; VOLUME_HEADER_LEN];
+        reader.read_exact(&mut bytes).map_err(|e| match e.kind() {
+            ErrorKind::UnexpectedEof => CodedError::truncated(format!("{}: {} ends before its volume header", ERR_TRUNCATED, what)),
+            _ => CodedError::io(ErrorCode::IoRead, format_args!("Error reading {}", what), e),
+        })?;
+        if &bytes[..8] != VOLUME_MAGIC {
+            return Err(CodedError::bad_header(format!("{} is not a volume file", what)));
+        }
+        if bytes[8] != VOLUME_VERSION {
+            return Err(CodedError::unsupported(format!("Unsupported volume version {} in {}", bytes[8], what)));
+        }
+        verify_volume_mac(key, &bytes[..VOLUME_HEADER_LEN - 32], &bytes[VOLUME_HEADER_LEN - 32..])
+            .map_err(|e| e.context(what))?;
+        Ok(VolumeHeader {
+            set_id: bytes[16..32].try_into().unwrap(),
+            index: u32::from_le_bytes(bytes[12..16].try_into().unwrap()),
+            last: bytes[9] & VOLUME_FLAG_LAST != 0,
+        })
+    }
+}
+
+/// 第 index 个分卷（从 0 开始）的文件路径：`<base>.001`、`<base>.002`……
+pub fn volume_path(base_path: &str, index: u32) -> String {
+    format!("{}.{:03}", base_path, index + 1)
+}
+
+/// 根据分卷路径或基础路径找到一组分卷：`file.zenc` 或 `file.zenc.001` 都会依次查找 `file.zenc.001`、`.002`……
+pub fn discover_volumes(pattern: &str) -> Result<Vec<String>, CodedError> {
+    let base_path = pattern.strip_suffix(".001").unwrap_or(pattern);
+    let paths: Vec<String> = (0..)
+        .map(|index| volume_path(base_path, index))
+        .take_while(|path| Path::new(path).is_file())
+        .collect();
+    if paths.is_empty() {
+        return Err(CodedError::io_open(format!("No volumes found for {}", pattern)));
+    }
+    Ok(paths)
+}
+
+/// 把输出按固定大小切分到多个分卷文件中，每个分卷文件（含分卷头）不超过 volume_size 字节
+///
+/// 未调用 finish 就被丢弃时（操作出错或中止）删除已经创建的所有分卷。
+pub struct VolumeWriter {
+    base_path: String,
+    key: Vec<u8>,
+    set_id: [u8; VOLUME_SET_ID_LEN],
+    /// 每个分卷可写入的数据字节数
+    payload_size: u64,
+    current: Option<File>,
+    written_in_current: u64,
+    paths: Vec<String>,
+    finished: bool,
+}
+
+impl VolumeWriter {
+    pub fn create(base_path: &str, key: &[u8], volume_size: u64) -> Result<Self, CodedError> {
+        if volume_size <= VOLUME_HEADER_LEN as u64 {
+            return Err(CodedError::invalid_argument(format!("Volume size must be larger than {} bytes", VOLUME_HEADER_LEN)));
+        }
+        let mut set_id = [0u8; VOLUME_SET_ID_LEN];
+        rand::rngs::ThreadRng::default().fill_bytes(&mut set_id);
+        Ok(VolumeWriter {
+            base_path: base_path.to_string(),
+            key: key.to_vec(),
+            set_id,
+            payload_size: volume_size - VOLUME_HEADER_LEN as u64,
+            current: None,
+            written_in_current: 0,
+            paths: Vec::new(),
+            finished: false,
+        })
+    }
+
+    fn header(&self, index: u32, last: bool) -> std::io::Result<Vec<u8>> {
+        VolumeHeader { set_id: self.set_id, index, last }
+            .to_bytes(&self.key)
+            .map_err(std::io::Error::other)
+    }
+
+    /// 创建下一个分卷文件并写入分卷头（最后一个分卷的标志在 finish 时补写）
+    fn open_next(&mut self) -> std::io::Result<()> {
+        let index = self.paths.len() as u32;
+        let path = volume_path(&self.base_path, index);
+        let mut file = File::create(&path)?;
+        self.paths.push(path);
+        file.write_all(&self.header(index, false)?)?;
+        self.current = Some(file);
+        self.written_in_current = 0;
+        Ok(())
+    }
+
+    /// 把最后一个分卷标记为结束，返回所有分卷文件的路径
+    pub fn finish(mut self) -> Result<Vec<String>, CodedError> {
+        let finished = (|| -> std::io::Result<()> {
+            if self.current.is_none() {
+                self.open_next()?;
+            }
+            let index = self.paths.len() as u32 - 1;
+            let header = self.header(index, true)?;
+            let file = self.current.as_mut().unwrap();
+            file.seek(SeekFrom::Start(0))?;
+            file.write_all(&header)?;
+            file.flush()
+        })();
+        finished.map_err(|e| CodedError::io_write(format!("Failed to finish volume: {}", e)))?;
+        self.finished = true;
+        Ok(std::mem::take(&mut self.paths))
+    }
+}
+
+impl Write for VolumeWriter {
+    fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+        if buf.is_empty() {
+            return Ok(0);
+        }
+        if self.current.is_none() || self.written_in_current >= self.payload_size {
+            self.open_next()?;
+        }
+        let room = (self.payload_size - self.written_in_current).min(buf.len() as u64) as usize;
+        let written = self.current.as_mut().unwrap().write(&buf[..room])?;
+        self.written_in_current += written as u64;
+        Ok(written)
+    }
+
+    fn flush(&mut self) -> std::io::Result<()> {
+        match &mut self.current {
+            Some(file) => file.flush(),
+            None => Ok(()),
+        }
+    }
+}
+
+impl Drop for VolumeWriter {
+    fn drop(&mut self) {
+        if !self.finished {
+            for path in &self.paths {
+                let _ = std::fs::remove_file(path);
+            }
+        }
+    }
+}
+
+/// 按顺序读取一组分卷中的数据（跳过分卷头），读取方看到的是完整的分片文件
+pub struct VolumeReader {
+    key: Vec<u8>,
+    set_id: [u8; VOLUME_SET_ID_LEN],
+    /// 按分卷序号排好的路径
+    paths: Vec<String>,
+    current: Option<File>,
+    next: usize,
+}
+
+impl VolumeReader {
+    /// 打开并校验一组分卷：按分卷头中的序号排序，要求所有分卷属于同一组、序号连续且以最后一个分卷结束
+    pub fn open(paths: &[String], key: &[u8]) -> Result<Self, CodedError> {
+        if paths.is_empty() {
+            return Err(CodedError::invalid_argument("No volumes given"));
+        }
+        let mut volumes = Vec::with_capacity(paths.len());
+        for path in paths {
+            let mut file = File::open(path).map_err(|e| CodedError::io_open(format!("Failed to open volume {}: {}", path, e)))?;
+            volumes.push((VolumeHeader::read(&mut file, key, path)?, path.clone()));
+        }
+        volumes.sort_by_key(|(header, _)| header.index);
+
+        let set_id = volumes[0].0.set_id;
+        for (position, (header, path)) in volumes.iter().enumerate() {
+            if header.set_id != set_id {
+                return Err(CodedError::invalid_argument(format!("Volume {} belongs to a different volume set", path)));
+            }
+            if (header.index as usize) < position {
+                return Err(CodedError::invalid_argument(format!("Duplicate volume {} in {}", header.index + 1, path)));
+            }
+            if header.index as usize != position {
+                return Err(CodedError::truncated(format!(
+                    "{}: volume {} is missing (found volume {} in {})",
+                    ERR_TRUNCATED, position + 1, header.index + 1, path
+                )));
+            }
+            if header.last && position + 1 != volumes.len() {
+                return Err(CodedError::bad_format(format!("Unexpected volume after the last volume {}", path)));
+            }
+        }
+        if !volumes[volumes.len() - 1].0.last {
+            return Err(CodedError::truncated(format!(
+                "{}: the last volume is missing (have {} volumes)", ERR_TRUNCATED, volumes.len()
+            )));
+        }
+
+        Ok(VolumeReader {
+            key: key.to_vec(),
+            set_id,
+            paths: volumes.into_iter().map(|(_, path)| path).collect(),
+            current: None,
+            next: 0,
+        })
+    }
+
+    pub fn volume_count(&self) -> usize {
+        self.paths.len()
+    }
+
+    /// 打开下一个分卷，重新校验分卷头后停在数据开头；没有更多分卷时返回 false
+    fn open_next(&mut self) -> std::io::Result<bool> {
+        let Some(path) = self.paths.get(self.next) else {
+            self.current = None;
+            return Ok(false);
+        };
+        let mut file = File::open(path)?;
+        let header = VolumeHeader::read(&mut file, &self.key, path).map_err(std::io::Error::other)?;
+        if header.index as usize != self.next || header.set_id != self.set_id {
+            return Err(std::io::Error::other(format!("Volume {} changed while reading", path)));
+        }
+        self.current = Some(file);
+        self.next += 1;
+        Ok(true)
+    }
+}
+
+impl Read for VolumeReader {
+    fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
+        if buf.is_empty() {
+            return Ok(0);
+        }
+        loop {
+            if let Some(file) = &mut self.current {
+                let read = file.read(buf)?;
+                if read > 0 {
+                    return Ok(read);
+                }
+            }
+            if !self.open_next()? {
+                return Ok(0);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::path::PathBuf;
+
+    const KEY: [u8; 32] = [4; 32];
+    const VOLUME_SIZE: u64 = VOLUME_HEADER_LEN as u64 + 300;
+
+    struct TempDir(PathBuf);
+
+    impl TempDir {
+        fn new(name: &str) -> Self {
+            let path = std::env::temp_dir().join(format!("zippy-volume-{}-{}", std::process::id(), name));
+            let _ = std::fs::remove_dir_all(&path);
+            std::fs::create_dir_all(&path).unwrap();
+            TempDir(path)
+        }
+
+        fn base(&self) -> String {
+            self.0.join("out.zenc").to_string_lossy().into_owned()
+        }
+    }
+
+    impl Drop for TempDir {
+        fn drop(&mut self) {
+            let _ = std::fs::remove_dir_all(&self.0);
+        }
+    }
+
+    fn sample(len: usize) -> Vec<u8> {
+        (0..len).map(|i| (i % 253) as u8).collect()
+    }
+
+    fn write_volumes(base: &str, data: &[u8]) -> Vec<String> {
+        let mut writer = VolumeWriter::create(base, &KEY, VOLUME_SIZE).unwrap();
+        writer.write_all(data).unwrap();
+        writer.finish().unwrap()
+    }
+
+    fn read_volumes(paths: &[String], key: &[u8]) -> Result<Vec<u8>, CodedError> {
+        let mut reader = VolumeReader::open(paths, key)?;
+        let mut data = Vec::new();
+        reader.read_to_end(&mut data).map_err(|e| CodedError::io_read(e.to_string()))?;
+        Ok(data)
+    }
+
+    #[test]
+    fn splits_output_into_volumes_of_the_given_size() {
+        let dir = TempDir::new("split");
+        let data = sample(1000);
+        let paths = write_volumes(&dir.base(), &data);
+        assert_eq!(paths, (0..4).map(|index| volume_path(&dir.base(), index)).collect::<Vec<_>>());
+        assert!(paths[0].ends_with("out.zenc.001") && paths[3].ends_with("out.zenc.004"));
+        let sizes: Vec<_> = paths.iter().map(|path| std::fs::metadata(path).unwrap().len()).collect();
+        assert_eq!(sizes, [VOLUME_SIZE, VOLUME_SIZE, VOLUME_SIZE, VOLUME_HEADER_LEN as u64 + 100]);
+
+        let headers: Vec<_> = paths.iter().map(|path| VolumeHeader::read(&mut File::open(path).unwrap(), &KEY, path).unwrap()).collect();
+        assert_eq!(headers.iter().map(|header| header.index).collect::<Vec<_>>(), [0, 1, 2, 3]);
+        assert_eq!(headers.iter().map(|header| header.last).collect::<Vec<_>>(), [false, false, false, true]);
+        assert!(headers.iter().all(|header| header.set_id == headers[0].set_id));
+
+        let mut reader = VolumeReader::open(&paths, &KEY).unwrap();
+        assert_eq!(reader.volume_count(), 4);
+        let mut read = Vec::new();
+        reader.read_to_end(&mut read).unwrap();
+        assert_eq!(read, data);
+    }
+
+    #[test]
+    fn an_exact_multiple_does_not_add_an_empty_volume() {
+        let dir = TempDir::new("exact");
+        let data = sample(600);
+        let paths = write_volumes(&dir.base(), &data);
+        assert_eq!(paths.len(), 2);
+        assert_eq!(read_volumes(&paths, &KEY).unwrap(), data);
+    }
+
+    #[test]
+    fn empty_output_has_one_volume() {
+        let dir = TempDir::new("empty");
+        let paths = write_volumes(&dir.base(), b"");
+        assert_eq!(paths.len(), 1);
+        assert_eq!(std::fs::metadata(&paths[0]).unwrap().len(), VOLUME_HEADER_LEN as u64);
+        assert_eq!(read_volumes(&paths, &KEY).unwrap(), b"");
+    }
+
+    #[test]
+    fn rejects_volume_sizes_without_room_for_data() {
+        let dir = TempDir::new("size");
+        let err = VolumeWriter::create(&dir.base(), &KEY, VOLUME_HEADER_LEN as u64).err().unwrap();
+        assert_eq!(err.code, ErrorCode::InvalidArgument);
+        assert!(VolumeWriter::create(&dir.base(), &KEY, VOLUME_HEADER_LEN as u64 + 1).is_ok());
+    }
+
+    #[test]
+    fn removes_volumes_when_not_finished() {
+        let dir = TempDir::new("abandon");
+        let mut writer = VolumeWriter::create(&dir.base(), &KEY, VOLUME_SIZE).unwrap();
+        writer.write_all(&sample(700)).unwrap();
+        assert!(Path::new(&volume_path(&dir.base(), 2)).is_file());
+        drop(writer);
+        assert_eq!(std::fs::read_dir(&dir.0).unwrap().count(), 0);
+    }
+
+    #[test]
+    fn discovers_volumes_from_either_path() {
+        let dir = TempDir::new("discover");
+        let paths = write_volumes(&dir.base(), &sample(1000));
+        assert_eq!(discover_volumes(&dir.base()).unwrap(), paths);
+        assert_eq!(discover_volumes(&paths[0]).unwrap(), paths);
+        assert_eq!(discover_volumes(&format!("{}.other", dir.base())).unwrap_err().code, ErrorCode::IoOpen);
+    }
+
+    #[test]
+    fn reads_volumes_given_in_any_order() {
+        let dir = TempDir::new("order");
+        let data = sample(1000);
+        let mut paths = write_volumes(&dir.base(), &data);
+        paths.reverse();
+        paths.swap(0, 1);
+        assert_eq!(read_volumes(&paths, &KEY).unwrap(), data);
+    }
+
+    #[test]
+    fn detects_missing_and_duplicate_volumes() {
+        let dir = TempDir::new("missing");
+        let paths = write_volumes(&dir.base(), &sample(1000));
+        let without_middle = [paths[0].clone(), paths[2].clone(), paths[3].clone()];
+        assert_eq!(read_volumes(&without_middle, &KEY).unwrap_err().code, ErrorCode::Truncated);
+        assert_eq!(read_volumes(&paths[..3], &KEY).unwrap_err().code, ErrorCode::Truncated);
+        assert_eq!(read_volumes(&paths[1..], &KEY).unwrap_err().code, ErrorCode::Truncated);
+        let duplicated = [paths[0].clone(), paths[1].clone(), paths[1].clone(), paths[2].clone(), paths[3].clone()];
+        assert_eq!(read_volumes(&duplicated, &KEY).unwrap_err().code, ErrorCode::InvalidArgument);
+        assert_eq!(read_volumes(&[], &KEY).unwrap_err().code, ErrorCode::InvalidArgument);
+    }
+
+    #[test]
+    fn detects_volumes_from_another_set() {
+        let dir = TempDir::new("mixed");
+        let first = write_volumes(&dir.base(), &sample(1000));
+        let other_base = dir.0.join("other.zenc").to_string_lossy().into_owned();
+        let second = write_volumes(&other_base, &sample(1000));
+        let mixed = [first[0].clone(), second[1].clone(), first[2].clone(), first[3].clone()];
+        assert_eq!(read_volumes(&mixed, &KEY).unwrap_err().code, ErrorCode::InvalidArgument);
+    }
+
+    #[test]
+    fn authenticates_volume_headers() {
+        let dir = TempDir::new("tamper");
+        let paths = write_volumes(&dir.base(), &sample(1000));
+        assert_eq!(read_volumes(&paths, &[9; 32]).unwrap_err().code, ErrorCode::AuthFailed);
+
+        // 把中间的分卷改标为最后一个分卷
+        let mut volume = std::fs::read(&paths[1]).unwrap();
+        volume[9] |= VOLUME_FLAG_LAST;
+        std::fs::write(&paths[1], &volume).unwrap();
+        assert_eq!(read_volumes(&paths, &KEY).unwrap_err().code, ErrorCode::AuthFailed);
+
+        volume[..8].copy_from_slice(b"NOTAVOLM");
+        std::fs::write(&paths[1], &volume).unwrap();
+        assert_eq!(read_volumes(&paths, &KEY).unwrap_err().code, ErrorCode::BadHeader);
+
+        std::fs::write(&paths[1], &volume[..20]).unwrap();
+        assert_eq!(read_volumes(&paths, &KEY).unwrap_err().code, ErrorCode::Truncated);
+    }
+
+    #[test]
+    fn detects_volumes_replaced_while_reading() {
+        let dir = TempDir::new("replace");
+        let paths = write_volumes(&dir.base(), &sample(1000));
+        let mut reader = VolumeReader::open(&paths, &KEY).unwrap();
+        let mut first = [0; 300];
+        reader.read_exact(&mut first).unwrap();
+        std::fs::copy(&paths[2], &paths[1]).unwrap();
+        let mut rest = Vec::new();
+        assert!(reader.read_to_end(&mut rest).is_err());
+    }
+}
@@ -0,0 +1,274 @@
+use std::collections::HashSet;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::time::UNIX_EPOCH;
+
+use crate::errors::{open_error, read_error, CodedError};
+
+/// 打包到归档中的一项：普通文件或目录
+pub struct InputEntry {
+    /// 归档中的名称，使用 `/` 分隔，目录以 `/` 结尾
+    pub name: String,
+    pub path: PathBuf,
+    pub is_dir: bool,
+    pub size: u64,
+    /// Unix 权限位（不含文件类型）
+    pub mode: u32,
+    /// 修改时间（Unix 秒）
+    pub modified: u64,
+}
+
+impl InputEntry {
+    fn new(name: String, path: &Path, metadata: &fs::Metadata) -> Self {
+        #[cfg(unix)]
+        let mode = std::os::unix::fs::PermissionsExt::mode(&metadata.permissions()) & 0o777;
+        #[cfg(not(unix))]
+        let mode = if metadata.is_dir() { 0o755 } else { 0o644 };
+        InputEntry {
+            name,
+            path: path.to_path_buf(),
+            is_dir: metadata.is_dir(),
+            size: if metadata.is_dir() { 0 } else { metadata.len() },
+            mode,
+            modified: metadata
+                .modified()
+                .ok()
+                .and_then(|modified| modified.duration_since(UNIX_EPOCH).ok())
+                .map_or(0, |duration| duration.as_secs()),
+        }
+    }
+}
+
+/// 收集要打包的输入：文件直接加入，目录递归加入（名称保留目录本身的名称，子项按名称排序）；
+/// 符号链接按其指向的内容处理。archive 为归档的名称，用于错误信息
+pub fn collect(inputs: &[String], archive: &str) -> Result<Vec<InputEntry>, CodedError> {
+    collect_filtered(inputs, archive, &|_, _| false)
+}
+
+/// 同 collect()，但跳过 skip 返回 true 的条目；参数为条目名称（目录不含结尾的 `/`）和是否为目录，被跳过的目录不再遍历
+pub fn collect_filtered(inputs: &[String], archive: &str, skip: &dyn Fn(&str, bool) -> bool) -> Result<Vec<InputEntry>, CodedError> {
+    fn walk(dir: &Path, prefix: &str, entries: &mut Vec<InputEntry>, skip: &dyn Fn(&str, bool) -> bool) -> Result<(), CodedError> {
+        let mut children = fs::read_dir(dir)
+            .map_err(|e| read_error(&format!("reading directory {}", dir.display()), e))?
+            .map(|child| child.map(|child| child.path()))
+            .collect::<Result<Vec<_>, _>>()
+            .map_err(|e| read_error(&format!("reading directory {}", dir.display()), e))?;
+        children.sort();
+        for child in children {
+            let metadata = fs::metadata(&child).map_err(|e| read_error(&format!("reading {}", child.display()), e))?;
+            let name = format!("{}{}", prefix, child.file_name().unwrap_or_default().to_string_lossy());
+            if skip(&name, metadata.is_dir()) {
+                continue;
+            }
+            if metadata.is_dir() {
+                entries.push(InputEntry::new(format!("{}/", name), &child, &metadata));
+                walk(&child, &format!("{}/", name), entries, skip)?;
+            } else {
+                entries.push(InputEntry::new(name, &child, &metadata));
+            }
+        }
+        Ok(())
+    }
+
+    if inputs.is_empty() {
+        return Err(CodedError::invalid_argument(format!("No input files for the {}", archive)));
+    }
+    let mut entries = Vec::new();
+    for input in inputs {
+        let path = Path::new(input);
+        let metadata = fs::metadata(path).map_err(|e| read_error(&format!("reading {}", input), e))?;
+        let name = path
+            .file_name()
+            .map(|name| name.to_string_lossy().into_owned())
+            .ok_or_else(|| CodedError::invalid_argument(format!("Cannot add {} to the {}: path has no file name", input, archive)))?;
+        if metadata.is_dir() {
+            entries.push(InputEntry::new(format!("{}/", name), path, &metadata));
+            walk(path, &format!("{}/", name), &mut entries, skip)?;
+        } else {
+            entries.push(InputEntry::new(name, path, &metadata));
+        }
+    }
+
+    let mut names = HashSet::new();
+    for entry in &entries {
+        if !names.insert(entry.name.as_str()) {
+            return Err(CodedError::invalid_argument(format!("Duplicate name in the {}: {}", archive, entry.name)));
+        }
+    }
+    Ok(entries)
+}
+
+/// 把归档中的条目名称转换为 dest 下的相对路径；拒绝绝对路径和 `..`，防止写到目标目录之外
+pub fn safe_join(dest: &Path, name: &str) -> Result<PathBuf, CodedError> {
+    let mut path = dest.to_path_buf();
+    let mut depth = 0;
+    for part in name.split(['/', '\\']) {
+        match part {
+            "" | "." => continue,
+            ".." => return Err(CodedError::bad_format(format!("Unsafe path in archive: {}", name))),
+            _ if part.contains(':') => return Err(CodedError::bad_format(format!("Unsafe path in archive: {}", name))),
+            _ => {
+                path.push(part);
+                depth += 1;
+            }
+        }
+    }
+    if depth == 0 || name.starts_with('/') || name.starts_with('\\') {
+        return Err(CodedError::bad_format(format!("Unsafe path in archive: {}", name)));
+    }
+    Ok(path)
+}
+
+/// 逐级创建目录，把新创建的每一级记录到 created 中
+pub fn create_dirs(path: &Path, created: &mut Vec<PathBuf>) -> Result<(), CodedError> {
+    let missing: Vec<&Path> = path.ancestors().take_while(|dir| !dir.as_os_str().is_empty() && !dir.is_dir()).collect();
+    for dir in missing.into_iter().rev() {
+        fs::create_dir(dir).map_err(|e| open_error(&format!("creating {}", dir.display()), e))?;
+        created.push(dir.to_path_buf());
+    }
+    Ok(())
+}
+
+/// 解压或写出目录失败时删除已创建的文件和目录（按创建的相反顺序，目录只在为空时删除）
+pub fn remove_created(created: &[PathBuf]) {
+    for path in created.iter().rev() {
+        if path.is_dir() {
+            let _ = fs::remove_dir(path);
+        } else {
+            let _ = fs::remove_file(path);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::errors::ErrorCode;
+
+    struct TempDir(PathBuf);
+
+    impl TempDir {
+        fn new(name: &str) -> Self {
+            let path = std::env::temp_dir().join(format!("zippy-walk-{}-{}", std::process::id(), name));
+            let _ = fs::remove_dir_all(&path);
+            fs::create_dir_all(&path).unwrap();
+            TempDir(path)
+        }
+
+        fn path(&self, name: &str) -> String {
+            self.0.join(name).to_string_lossy().into_owned()
+        }
+
+        fn write(&self, name: &str, data: &[u8]) {
+            let path = self.0.join(name);
+            fs::create_dir_all(path.parent().unwrap()).unwrap();
+            fs::write(path, data).unwrap();
+        }
+    }
+
+    impl Drop for TempDir {
+        fn drop(&mut self) {
+            let _ = fs::remove_dir_all(&self.0);
+        }
+    }
+
+    fn names(entries: &[InputEntry]) -> Vec<&str> {
+        entries.iter().map(|entry| entry.name.as_str()).collect()
+    }
+
+    #[test]
+    fn collects_files_and_directories_in_name_order() {
+        let dir = TempDir::new("collect");
+        dir.write("docs/b.txt", b"bb");
+        dir.write("docs/a.txt", b"a");
+        dir.write("docs/sub/c.txt", b"ccc");
+        fs::create_dir_all(dir.0.join("docs/empty")).unwrap();
+        dir.write("single.bin", b"single");
+
+        let entries = collect(&[dir.path("docs"), dir.path("single.bin")], "archive").unwrap();
+        assert_eq!(names(&entries), ["docs/", "docs/a.txt", "docs/b.txt", "docs/empty/", "docs/sub/", "docs/sub/c.txt", "single.bin"]);
+        assert!(entries[0].is_dir && entries[0].size == 0);
+        assert_eq!(entries[2].size, 2);
+        assert_eq!(entries[6].path, dir.0.join("single.bin"));
+        assert!(entries.iter().all(|entry| entry.modified > 0));
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn records_permissions_and_follows_symlinks() {
+        use std::os::unix::fs::PermissionsExt;
+        let dir = TempDir::new("mode");
+        dir.write("data/script.sh", b"#!/bin/sh");
+        fs::set_permissions(dir.0.join("data/script.sh"), fs::Permissions::from_mode(0o750)).unwrap();
+        std::os::unix::fs::symlink(dir.0.join("data/script.sh"), dir.0.join("data/link")).unwrap();
+        let entries = collect(&[dir.path("data")], "archive").unwrap();
+        assert_eq!(names(&entries), ["data/", "data/link", "data/script.sh"]);
+        assert_eq!(entries[1].mode, 0o750);
+        assert_eq!(entries[1].size, 9);
+        assert!(!entries[1].is_dir);
+    }
+
+    #[test]
+    fn skips_filtered_entries_without_walking_them() {
+        let dir = TempDir::new("filter");
+        dir.write("root/keep.txt", b"k");
+        dir.write("root/skip.log", b"s");
+        dir.write("root/cache/inner.txt", b"i");
+        let skip = |name: &str, is_dir: bool| name.ends_with(".log") || (is_dir && name == "root/cache");
+        let entries = collect_filtered(&[dir.path("root")], "archive", &skip).unwrap();
+        assert_eq!(names(&entries), ["root/", "root/keep.txt"]);
+    }
+
+    #[test]
+    fn rejects_missing_duplicate_and_unnamed_inputs() {
+        let dir = TempDir::new("reject");
+        dir.write("a/same.txt", b"1");
+        dir.write("b/same.txt", b"2");
+        assert_eq!(collect(&[], "archive").err().unwrap().code, ErrorCode::InvalidArgument);
+        let duplicate = collect(&[dir.path("a/same.txt"), dir.path("b/same.txt")], "archive").err().unwrap();
+        assert_eq!(duplicate.code, ErrorCode::InvalidArgument);
+        assert!(duplicate.message.contains("same.txt"));
+        assert!(collect(&[dir.path("missing")], "archive").is_err());
+        assert_eq!(collect(&["/".to_string()], "archive").err().unwrap().code, ErrorCode::InvalidArgument);
+    }
+
+    #[test]
+    fn joins_only_safe_names() {
+        let dest = Path::new("/out");
+        assert_eq!(safe_join(dest, "a/b.txt").unwrap(), Path::new("/out/a/b.txt"));
+        assert_eq!(safe_join(dest, "./a//b/").unwrap(), Path::new("/out/a/b"));
+        assert_eq!(safe_join(dest, "dir\\file").unwrap(), Path::new("/out/dir/file"));
+        for name in ["", ".", "/", "/etc/passwd", "\\windows", "../x", "a/../../x", "a\\..\\x", "C:/x", "a/b:c"] {
+            assert_eq!(safe_join(dest, name).unwrap_err().code, ErrorCode::BadFormat, "{:?}", name);
+        }
+    }
+
+    #[test]
+    fn creates_and_removes_directories() {
+        let dir = TempDir::new("dirs");
+        fs::create_dir(dir.0.join("existing")).unwrap();
+        let mut created = Vec::new();
+        let deep = dir.0.join("existing/a/b/c");
+        create_dirs(&deep, &mut created).unwrap();
+        assert!(deep.is_dir());
+        assert_eq!(created, [dir.0.join("existing/a"), dir.0.join("existing/a/b"), deep.clone()]);
+        fs::write(deep.join("file"), b"x").unwrap();
+        created.push(deep.join("file"));
+
+        remove_created(&created);
+        assert!(!dir.0.join("existing/a").exists());
+        assert!(dir.0.join("existing").is_dir());
+    }
+
+    #[test]
+    fn keeps_directories_that_are_not_empty() {
+        let dir = TempDir::new("keep");
+        let mut created = Vec::new();
+        create_dirs(&dir.0.join("a/b"), &mut created).unwrap();
+        // 不是本次创建的文件
+        fs::write(dir.0.join("a/other"), b"x").unwrap();
+        remove_created(&created);
+        assert!(!dir.0.join("a/b").exists());
+        assert!(dir.0.join("a/other").is_file());
+    }
+}
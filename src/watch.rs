@@ -0,0 +1,306 @@
+use std::collections::HashMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::{Condvar, Mutex};
+use std::time::{Duration, Instant, SystemTime};
+
+use napi::bindgen_prelude::{BigInt, Either};
+use napi_derive::napi;
+
+use crate::errors::CodedError;
+use crate::glob::PathFilter;
+use crate::walk;
+
+/// watchAndEncrypt() 回调收到的事件
+#[napi(object)]
+#[derive(Clone)]
+pub struct WatchEvent {
+    /// encrypted / failed
+    #[napi(js_name = "type")]
+    pub kind: String,
+    /// 相对于监视目录的路径，扫描目录失败时为空字符串
+    pub path: String,
+    pub output_path: Option<String>,
+    /// 加密的明文大小（字节）
+    pub file_size_bytes: Option<Either<f64, BigInt>>,
+    pub error: Option<String>,
+    /// 事件时间，Unix 毫秒时间戳
+    pub timestamp: f64,
+}
+
+/// 文件的大小和修改时间，用来判断文件是否变化
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub struct Snapshot {
+    pub size: u64,
+    pub modified: Option<SystemTime>,
+}
+
+impl Snapshot {
+    /// 文件当前的状态，文件不存在或无法读取时返回 None
+    pub fn of(path: &Path) -> Option<Self> {
+        let metadata = fs::metadata(path).ok()?;
+        Some(Snapshot { size: metadata.len(), modified: metadata.modified().ok() })
+    }
+}
+
+/// 已经稳定、可以处理的文件
+pub struct Ready {
+    /// 相对于监视目录的路径
+    pub name: String,
+    pub path: PathBuf,
+    /// 判断稳定时的状态；处理期间和删除源文件之前都要与它比较，确认文件没有继续被写入
+    pub snapshot: Snapshot,
+}
+
+/// 监视目录中的一个文件
+struct Tracked {
+    snapshot: Snapshot,
+    /// 最近一次发现大小或修改时间变化的时间
+    changed_at: Instant,
+    /// 变化后还没有处理
+    pending: bool,
+}
+
+/// 轮询监视目录：文件新出现或大小、修改时间变化后，在 debounce 时间内不再变化才视为写入完成
+pub struct Tracker {
+    src_dir: String,
+    filter: PathFilter,
+    debounce: Duration,
+    files: HashMap<String, Tracked>,
+}
+
+impl Tracker {
+    /// 检查目录并记录现有文件；process_existing 为 false 时现有文件不会被处理，直到它们再次变化
+    pub fn new(src_dir: &str, dest_dir: &str, filter: PathFilter, debounce: Duration, process_existing: bool) -> Result<Self, CodedError> {
+        if !fs::metadata(src_dir).map_err(|e| CodedError::io_read(format!("Error reading {}: {}", src_dir, e)))?.is_dir() {
+            return Err(CodedError::invalid_argument(format!("Input is not a directory: {}", src_dir)));
+        }
+        let src = fs::canonicalize(src_dir).map_err(|e| CodedError::io_read(format!("Error reading {}: {}", src_dir, e)))?;
+        let mut created = Vec::new();
+        walk::create_dirs(Path::new(dest_dir), &mut created)?;
+        if fs::canonicalize(dest_dir).is_ok_and(|dest| dest.starts_with(&src)) {
+            walk::remove_created(&created);
+            return Err(CodedError::invalid_argument(format!("Output directory {} must not be inside the watched directory", dest_dir)));
+        }
+        let mut tracker = Tracker { src_dir: src_dir.to_string(), filter, debounce, files: HashMap::new() };
+        tracker.scan()?;
+        if !process_existing {
+            tracker.files.values_mut().for_each(|file| file.pending = false);
+        }
+        Ok(tracker)
+    }
+
+    /// 扫描一次目录，返回已经稳定、需要处理的文件
+    ///
+    /// 每次都重新遍历整个目录（网络共享目录上没有可靠的文件通知），文件在 debounce 时间内连续几次扫描都没有变化才算稳定；
+    /// 写入中途停顿超过 debounce 的文件仍可能被当作稳定，调用方在处理后用 snapshot 再次确认。
+    pub fn poll(&mut self) -> Result<Vec<Ready>, CodedError> {
+        self.scan()?;
+        let now = Instant::now();
+        let mut ready: Vec<_> = self
+            .files
+            .iter_mut()
+            .filter(|(_, file)| file.pending && now.duration_since(file.changed_at) >= self.debounce)
+            .map(|(name, file)| {
+                file.pending = false;
+                Ready { name: name.clone(), path: Path::new(&self.src_dir).join(name), snapshot: file.snapshot }
+            })
+            .collect();
+        ready.sort_by(|a, b| a.name.cmp(&b.name));
+        Ok(ready)
+    }
+
+    /// 处理失败的文件在再次变化之前不会重试；文件被删除后不再跟踪
+    fn scan(&mut self) -> Result<(), CodedError> {
+        // collect() 的名称以监视目录本身的名称开头，去掉这一层得到相对路径
+        let relative = |name: &str| name.split_once('/').map_or_else(String::new, |(_, name)| name.to_string());
+        let skip = |name: &str, is_dir: bool| self.filter.excluded(&relative(name), is_dir);
+        let mut directories = Vec::new();
+        let mut files: Vec<_> = walk::collect_filtered(std::slice::from_ref(&self.src_dir), "watched directory", &skip)?
+            .into_iter()
+            .filter(|entry| !entry.is_dir)
+            .map(|entry| (relative(&entry.name), entry.path))
+            .collect();
+        self.filter.retain(&mut directories, &mut files, |dir: &String| dir.as_str(), |(name, _)| name.as_str());
+
+        let now = Instant::now();
+        let mut seen = HashMap::with_capacity(files.len());
+        for (name, path) in files {
+            // 文件可能在扫描期间被删除
+            let Ok(metadata) = fs::metadata(&path) else {
+                continue;
+            };
+            let snapshot = Snapshot { size: metadata.len(), modified: metadata.modified().ok() };
+            let tracked = match self.files.remove(&name) {
+                Some(file) if file.snapshot == snapshot => file,
+                _ => Tracked { snapshot, changed_at: now, pending: true },
+            };
+            seen.insert(name, tracked);
+        }
+        self.files = seen;
+        Ok(())
+    }
+
+    /// 处理后删除了源文件时不再跟踪
+    pub fn forget(&mut self, name: &str) {
+        self.files.remove(name);
+    }
+
+    /// 文件在处理期间又发生了变化：重新开始计时，稳定后再处理一次
+    pub fn requeue(&mut self, name: &str) {
+        if let Some(file) = self.files.get_mut(name) {
+            file.changed_at = Instant::now();
+            file.pending = true;
+        }
+    }
+}
+
+/// 停止监视的信号，可以随时唤醒等待中的监视线程
+#[derive(Default)]
+pub struct StopSignal {
+    stopped: Mutex<bool>,
+    wake: Condvar,
+}
+
+impl StopSignal {
+    pub fn stop(&self) {
+        *self.stopped.lock().unwrap() = true;
+        self.wake.notify_all();
+    }
+
+    pub fn is_stopped(&self) -> bool {
+        *self.stopped.lock().unwrap()
+    }
+
+    /// 等待 interval 或直到停止，已停止时返回 true
+    pub fn wait(&self, interval: Duration) -> bool {
+        let stopped = self.stopped.lock().unwrap();
+        let (stopped, _) = self.wake.wait_timeout_while(stopped, interval, |stopped| !*stopped).unwrap();
+        *stopped
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::Write;
+    use std::thread;
+
+    use super::*;
+    use crate::errors::ErrorCode;
+
+    /// 测试用的临时目录，结束时删除
+    struct TempDir(PathBuf);
+
+    impl TempDir {
+        fn new(name: &str) -> Self {
+            let path = std::env::temp_dir().join(format!("zippy-watch-{}-{}", std::process::id(), name));
+            let _ = fs::remove_dir_all(&path);
+            fs::create_dir_all(path.join("src")).unwrap();
+            TempDir(path)
+        }
+
+        fn src(&self) -> String {
+            self.0.join("src").to_string_lossy().into_owned()
+        }
+
+        fn dest(&self) -> String {
+            self.0.join("dest").to_string_lossy().into_owned()
+        }
+    }
+
+    impl Drop for TempDir {
+        fn drop(&mut self) {
+            let _ = fs::remove_dir_all(&self.0);
+        }
+    }
+
+    fn tracker(dir: &TempDir, debounce: Duration, process_existing: bool) -> Tracker {
+        Tracker::new(&dir.src(), &dir.dest(), PathFilter::new(None, None).unwrap(), debounce, process_existing).unwrap()
+    }
+
+    fn names(ready: Vec<Ready>) -> Vec<String> {
+        ready.into_iter().map(|ready| ready.name).collect()
+    }
+
+    #[test]
+    fn waits_until_files_stop_changing() {
+        let dir = TempDir::new("debounce");
+        let mut tracker = tracker(&dir, Duration::from_millis(150), true);
+        let path = Path::new(&dir.src()).join("a.txt");
+        fs::write(&path, b"first").unwrap();
+        assert!(tracker.poll().unwrap().is_empty());
+
+        // 还在写入的文件每次变化都重新计时
+        thread::sleep(Duration::from_millis(100));
+        fs::write(&path, b"first and more").unwrap();
+        thread::sleep(Duration::from_millis(100));
+        assert!(tracker.poll().unwrap().is_empty());
+
+        thread::sleep(Duration::from_millis(200));
+        let ready = tracker.poll().unwrap();
+        assert_eq!(ready.len(), 1);
+        assert_eq!(ready[0].name, "a.txt");
+        assert_eq!(Some(ready[0].snapshot), Snapshot::of(&path));
+        assert_eq!(ready[0].snapshot.size, 14);
+        // 处理过的文件在再次变化之前不会重复返回
+        assert!(tracker.poll().unwrap().is_empty());
+    }
+
+    #[test]
+    fn existing_files_wait_for_a_change_when_ignored() {
+        let dir = TempDir::new("existing");
+        fs::create_dir_all(Path::new(&dir.src()).join("sub")).unwrap();
+        fs::write(Path::new(&dir.src()).join("sub/old.txt"), b"old").unwrap();
+        assert_eq!(names(tracker(&dir, Duration::ZERO, true).poll().unwrap()), ["sub/old.txt"]);
+
+        let mut tracker = tracker(&dir, Duration::ZERO, false);
+        assert!(tracker.poll().unwrap().is_empty());
+        fs::write(Path::new(&dir.src()).join("sub/old.txt"), b"changed").unwrap();
+        assert_eq!(names(tracker.poll().unwrap()), ["sub/old.txt"]);
+    }
+
+    #[test]
+    fn requeued_files_are_processed_again() {
+        let dir = TempDir::new("requeue");
+        let mut tracker = tracker(&dir, Duration::ZERO, true);
+        fs::write(Path::new(&dir.src()).join("a.txt"), b"data").unwrap();
+        assert_eq!(names(tracker.poll().unwrap()), ["a.txt"]);
+        tracker.requeue("a.txt");
+        assert_eq!(names(tracker.poll().unwrap()), ["a.txt"]);
+        assert!(tracker.poll().unwrap().is_empty());
+    }
+
+    #[test]
+    fn snapshot_detects_writes() {
+        let dir = TempDir::new("snapshot");
+        let path = Path::new(&dir.src()).join("a.txt");
+        fs::write(&path, b"data").unwrap();
+        let before = Snapshot::of(&path).unwrap();
+        fs::OpenOptions::new().append(true).open(&path).unwrap().write_all(b" more").unwrap();
+        assert_ne!(Snapshot::of(&path), Some(before));
+        fs::remove_file(&path).unwrap();
+        assert_eq!(Snapshot::of(&path), None);
+    }
+
+    #[test]
+    fn rejects_output_inside_the_watched_directory() {
+        let dir = TempDir::new("inside");
+        let dest = Path::new(&dir.src()).join("out");
+        let err = Tracker::new(&dir.src(), &dest.to_string_lossy(), PathFilter::new(None, None).unwrap(), Duration::ZERO, true).err().unwrap();
+        assert_eq!(err.code, ErrorCode::InvalidArgument);
+        assert!(!dest.exists());
+    }
+
+    #[test]
+    fn stop_signal_wakes_the_waiter() {
+        let signal = std::sync::Arc::new(StopSignal::default());
+        assert!(!signal.wait(Duration::from_millis(1)));
+        let waiter = {
+            let signal = signal.clone();
+            thread::spawn(move || signal.wait(Duration::from_secs(30)))
+        };
+        signal.stop();
+        assert!(waiter.join().unwrap());
+        assert!(signal.is_stopped());
+    }
+}
@@ -0,0 +1,607 @@
+use std::cell::RefCell;
+use std::io::{BufRead, Read, Write};
+use std::rc::Rc;
+
+use napi::bindgen_prelude::{FromNapiValue, Uint8Array};
+use napi::{CallContext, Env, Error, JsFunction, JsObject, JsUnknown, NapiRaw, Status, ValueType};
+
+use crate::chunked::{ChunkDecoder, ChunkEncoder};
+use crate::crypto::{seal_field, CryptoAlgorithm};
+use crate::errors::{self, CodedError, ErrorCode};
+use crate::format::{self, ChunkedHeader, ContainerHeader, CONTAINER_MAGIC, ERR_TRUNCATED, FIELD_MIME_TYPE};
+use crate::mime;
+
+/// 包装为 WHATWG TransformStream 的加解密状态机：每次输入一段数据，返回这段数据产生的输出
+pub trait StreamTransform {
+    fn transform(&mut self, chunk: &[u8]) -> Result<Vec<Vec<u8>>, CodedError>;
+
+    /// 输入结束，返回剩余的输出
+    fn flush(&mut self) -> Result<Vec<Vec<u8>>, CodedError>;
+}
+
+/// 加密为分片文件格式，输出与 chunkEncryptFile 相同；也用于 ChunkedWriter 直接写入输出文件
+///
+/// 文件头记录原始大小、分片认证绑定分片总数，因此必须预先知道明文的总长度。
+pub struct EncryptTransform<W: Write> {
+    algorithm: CryptoAlgorithm,
+    key: Vec<u8>,
+    container: Option<ContainerHeader>,
+    /// 未指定 MIME 类型时根据第一段数据检测
+    sniff_mime_type: bool,
+    size: u64,
+    chunk_size: usize,
+    received: u64,
+    /// 写入文件头之前的 writer，之后由 encoder 持有
+    writer: Option<W>,
+    encoder: Option<ChunkEncoder<W>>,
+}
+
+impl<W: Write> EncryptTransform<W> {
+    #[allow(clippy::too_many_arguments)]
+    pub fn new(writer: W, algorithm: CryptoAlgorithm, key: &[u8], container: Option<ContainerHeader>, sniff_mime_type: bool, size: u64, chunk_size: usize) -> Self {
+        EncryptTransform {
+            algorithm,
+            key: key.to_vec(),
+            container,
+            sniff_mime_type,
+            size,
+            chunk_size,
+            received: 0,
+            writer: Some(writer),
+            encoder: None,
+        }
+    }
+
+    /// 收到第一段数据（或空输入结束）时写入文件头
+    fn encoder(&mut self, sample: &[u8]) -> Result<&mut ChunkEncoder<W>, CodedError> {
+        if self.encoder.is_none() {
+            if let Some(container) = self.container.as_mut().filter(|_| self.sniff_mime_type) {
+                let mime_type = mime::sniff(sample);
+                container.encrypted_mime_type = Some(seal_field(&self.key, FIELD_MIME_TYPE, mime_type.as_bytes())?);
+            }
+            let writer = self.writer.take().ok_or_else(|| CodedError::invalid_argument("Stream already finished"))?;
+            let encoder = ChunkEncoder::new(writer, self.algorithm.clone(), &self.key, self.container.as_ref(), self.size, self.chunk_size)?;
+            self.encoder = Some(encoder);
+        }
+        Ok(self.encoder.as_mut().unwrap())
+    }
+
+    /// 加密一段明文，写出其中已凑满的分片
+    pub fn write(&mut self, chunk: &[u8]) -> Result<&mut ChunkEncoder<W>, CodedError> {
+        self.received += chunk.len() as u64;
+        if self.received > self.size {
+            return Err(CodedError::invalid_argument(format!("Stream data exceeds the declared size of {} bytes", self.size)));
+        }
+        let encoder = self.encoder(chunk)?;
+        encoder.write(chunk)?;
+        Ok(encoder)
+    }
+
+    /// 输入结束，确认收到了声明的全部明文，返回分片写入器，由调用方写入文件结尾
+    pub fn take_encoder(&mut self) -> Result<ChunkEncoder<W>, CodedError> {
+        if self.received != self.size {
+            return Err(CodedError::truncated(format!("{}: stream ended after {} of {} bytes", ERR_TRUNCATED, self.received, self.size)));
+        }
+        self.encoder(&[])?;
+        Ok(self.encoder.take().unwrap())
+    }
+}
+
+impl StreamTransform for EncryptTransform<Vec<u8>> {
+    fn transform(&mut self, chunk: &[u8]) -> Result<Vec<Vec<u8>>, CodedError> {
+        let encoder = self.write(chunk)?;
+        Ok(vec![std::mem::take(encoder.get_mut())])
+    }
+
+    fn flush(&mut self) -> Result<Vec<Vec<u8>>, CodedError> {
+        Ok(vec![self.take_encoder()?.finish()?])
+    }
+}
+
+/// 校验文件头并检查解密策略
+pub type HeaderCheck = Box<dyn Fn(&ChunkedHeader) -> Result<(), CodedError>>;
+
+/// 解密分片文件格式的数据流：收齐文件头后逐个解密完整到达的分片帧，输入结束时校验结尾
+pub struct DecryptTransform {
+    algorithm: CryptoAlgorithm,
+    key: Vec<u8>,
+    check_header: HeaderCheck,
+    state: DecryptState,
+}
+
+enum DecryptState {
+    Header(StreamBuffer),
+    Chunks(Box<ChunkDecoder<StreamBuffer>>),
+    Finished,
+}
+
+impl DecryptTransform {
+    pub fn new(algorithm: CryptoAlgorithm, key: &[u8], check_header: HeaderCheck) -> Self {
+        DecryptTransform {
+            algorithm,
+            key: key.to_vec(),
+            check_header,
+            state: DecryptState::Header(StreamBuffer::default()),
+        }
+    }
+}
+
+impl StreamTransform for DecryptTransform {
+    fn transform(&mut self, chunk: &[u8]) -> Result<Vec<Vec<u8>>, CodedError> {
+        let mut output = Vec::new();
+        if let DecryptState::Header(buffer) = &mut self.state {
+            buffer.push(chunk);
+            // 根据开头的魔数区分 v1 和 v2 文件头，至少要收到魔数的长度
+            if buffer.available().len() < CONTAINER_MAGIC.len() {
+                return Ok(Vec::new());
+            }
+            let Some((header, header_len)) = probe(buffer.fill_buf().unwrap(), |reader| format::read_chunked_header(reader))? else {
+                return Ok(Vec::new());
+            };
+            (self.check_header)(&header)?;
+            let mut buffer = std::mem::take(buffer);
+            buffer.consume(header_len);
+            self.state = DecryptState::Chunks(Box::new(ChunkDecoder::new(buffer, header, self.algorithm.clone(), &self.key)?));
+        } else if let DecryptState::Chunks(decoder) = &mut self.state {
+            // 缓冲区中没有剩余数据时，直接在传入的数据上解密其中完整的分片帧，只缓存末尾不完整的部分
+            let used = match decoder.get_ref().available().is_empty() {
+                true => decrypt_frames(decoder, chunk, &mut output)?,
+                false => 0,
+            };
+            decoder.get_mut().push(&chunk[used..]);
+        }
+
+        let DecryptState::Chunks(decoder) = &mut self.state else {
+            return Err(CodedError::invalid_argument("Stream already finished"));
+        };
+        while decoder.bytes_decrypted() < decoder.header().original_size && frame_ready(decoder)? {
+            if let Some(decrypted) = decoder.next_chunk()? {
+                output.push(decrypted);
+            }
+        }
+        Ok(output)
+    }
+
+    fn flush(&mut self) -> Result<Vec<Vec<u8>>, CodedError> {
+        let decoder = match std::mem::replace(&mut self.state, DecryptState::Finished) {
+            DecryptState::Header(_) => return Err(CodedError::truncated(format!("{}: stream ends unexpectedly while reading header", ERR_TRUNCATED))),
+            DecryptState::Chunks(decoder) => decoder,
+            DecryptState::Finished => return Err(CodedError::invalid_argument("Stream already finished")),
+        };
+        if decoder.bytes_decrypted() < decoder.header().original_size {
+            return Err(CodedError::truncated(format!(
+                "{}: stream ended after {} of {} bytes",
+                ERR_TRUNCATED, decoder.bytes_decrypted(), decoder.header().original_size
+            )));
+        }
+        let (mut rest, _) = decoder.finish()?;
+        if !rest.fill_buf().unwrap().is_empty() {
+            return Err(CodedError::bad_format("Unexpected data after the end of the encrypted stream"));
+        }
+        Ok(Vec::new())
+    }
+}
+
+/// 下一个分片帧是否已完整到达
+fn frame_ready(decoder: &ChunkDecoder<StreamBuffer>) -> Result<bool, CodedError> {
+    let mut reader = Probe { remaining: decoder.get_ref().available(), exhausted: false };
+    match decoder.header().frame_format().read_len(&mut reader, "chunk") {
+        Ok(len) => Ok(reader.remaining.len() >= len),
+        Err(_) if reader.exhausted => Ok(false),
+        Err(err) => Err(err),
+    }
+}
+
+/// 解密 data 开头连续的完整分片帧，返回用掉的字节数
+///
+/// 遇到不完整的帧、结束帧或超长的帧时停止，交给缓冲区按原来的方式等待更多数据或报错。
+fn decrypt_frames(decoder: &mut ChunkDecoder<StreamBuffer>, data: &[u8], output: &mut Vec<Vec<u8>>) -> Result<usize, CodedError> {
+    let mut used = 0;
+    while decoder.bytes_decrypted() < decoder.header().original_size {
+        let what = format!("chunk {}", decoder.chunks_read() + 1);
+        let Some((len, len_field)) = probe(&data[used..], |reader| decoder.header().frame_format().read_len(reader, &what))? else {
+            break;
+        };
+        if len == 0 || len > decoder.header().max_frame_len() || data.len() - used - len_field < len {
+            break;
+        }
+        let end = used + len_field + len;
+        output.push(decoder.decrypt_frame(&data[used + len_field..end])?);
+        used = end;
+    }
+    Ok(used)
+}
+
+/// 在已到达的数据上尝试解析，返回结果和读取的字节数；因数据不足而失败时返回 None，等待更多数据
+fn probe<T>(data: &[u8], parse: impl FnOnce(&mut Probe) -> Result<T, CodedError>) -> Result<Option<(T, usize)>, CodedError> {
+    let mut reader = Probe { remaining: data, exhausted: false };
+    match parse(&mut reader) {
+        Ok(value) => Ok(Some((value, data.len() - reader.remaining.len()))),
+        Err(_) if reader.exhausted => Ok(None),
+        Err(err) => Err(err),
+    }
+}
+
+/// 记录是否读到了已到达数据末尾的 reader
+struct Probe<'a> {
+    remaining: &'a [u8],
+    exhausted: bool,
+}
+
+impl Read for Probe<'_> {
+    fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
+        if self.remaining.len() < buf.len() {
+            self.exhausted = true;
+        }
+        self.remaining.read(buf)
+    }
+}
+
+impl BufRead for Probe<'_> {
+    fn fill_buf(&mut self) -> std::io::Result<&[u8]> {
+        if self.remaining.is_empty() {
+            self.exhausted = true;
+        }
+        Ok(self.remaining)
+    }
+
+    fn consume(&mut self, amount: usize) {
+        self.remaining = &self.remaining[amount..];
+    }
+}
+
+/// 已到达但尚未读取的输入数据
+#[derive(Default)]
+struct StreamBuffer {
+    data: Vec<u8>,
+    position: usize,
+}
+
+impl StreamBuffer {
+    fn push(&mut self, chunk: &[u8]) {
+        self.data.drain(..self.position);
+        self.position = 0;
+        self.data.extend_from_slice(chunk);
+    }
+
+    fn available(&self) -> &[u8] {
+        &self.data[self.position..]
+    }
+}
+
+impl Read for StreamBuffer {
+    fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
+        let read = self.available().read(buf)?;
+        self.position += read;
+        Ok(read)
+    }
+}
+
+impl BufRead for StreamBuffer {
+    fn fill_buf(&mut self) -> std::io::Result<&[u8]> {
+        Ok(self.available())
+    }
+
+    fn consume(&mut self, amount: usize) {
+        self.position += amount;
+    }
+}
+
+/// 以全局的 TransformStream 构造函数创建转换流，transform/flush 回调在主线程中同步运行状态机
+///
+/// 出错时抛出带错误码的 JS 错误，TransformStream 随之进入错误状态，两端的流都会收到该错误。
+pub fn create<T: StreamTransform + 'static>(env: Env, transform: T) -> napi::Result<JsObject, ErrorCode> {
+    let constructor = env
+        .get_global()
+        .and_then(|global| global.get_named_property::<JsUnknown>("TransformStream"))
+        .map_err(errors::napi_error)?;
+    if constructor.get_type().map_err(errors::napi_error)? != ValueType::Function {
+        return Err(CodedError::unsupported("TransformStream is not available (requires Node.js 18 or later)").into());
+    }
+    transform_stream(env, unsafe { constructor.cast::<JsFunction>() }, transform).map_err(errors::napi_error)
+}
+
+fn transform_stream<T: StreamTransform + 'static>(env: Env, constructor: JsFunction, transform: T) -> napi::Result<JsObject> {
+    let state = Rc::new(RefCell::new(transform));
+    let mut transformer = env.create_object()?;
+    let transform_state = state.clone();
+    let transform = env.create_function_from_closure("transform", move |ctx| {
+        let chunk = ctx.get::<JsUnknown>(0)?;
+        let chunk = unsafe { Uint8Array::from_napi_value(ctx.env.raw(), chunk.raw()) }
+            .map_err(|_| js_error(&ctx, CodedError::invalid_argument("Stream chunks must be Uint8Array or Buffer")))?;
+        let output = transform_state.borrow_mut().transform(&chunk).map_err(|err| js_error(&ctx, err))?;
+        enqueue(&ctx, output)
+    })?;
+    let flush = env.create_function_from_closure("flush", move |ctx| {
+        let output = state.borrow_mut().flush().map_err(|err| js_error(&ctx, err))?;
+        enqueue(&ctx, output)
+    })?;
+    transformer.set_named_property("transform", transform)?;
+    transformer.set_named_property("flush", flush)?;
+    constructor.new_instance(&[transformer])
+}
+
+/// 将输出依次交给 controller.enqueue()，controller 为回调的最后一个参数
+fn enqueue(ctx: &CallContext, output: Vec<Vec<u8>>) -> napi::Result<JsUnknown> {
+    let controller = ctx.get::<JsObject>(ctx.length - 1)?;
+    let enqueue = controller.get_named_property::<JsFunction>("enqueue")?;
+    for data in output.into_iter().filter(|data| !data.is_empty()) {
+        let buffer = ctx.env.create_buffer_with_data(data)?.into_raw();
+        enqueue.call(Some(&controller), &[buffer])?;
+    }
+    Ok(ctx.env.get_undefined()?.into_unknown())
+}
+
+fn js_error(ctx: &CallContext, err: CodedError) -> Error {
+    errors::to_js_error(*ctx.env, err.into())
+}
+
+/// 从 JS 数据源读取、经 transform 转换后交给 JS 接收端，返回 pipeTo() 的 Promise
+///
+/// source 为异步可迭代对象、可迭代对象，或每次返回一段数据（可以是 Promise，结束时返回 null / undefined）的函数；
+/// sink 对每段输出调用一次，返回 Promise 时等它完成后再读取更多数据，由此把背压传回数据源。
+pub fn pipe<T: StreamTransform + 'static>(env: Env, transform: T, source: JsUnknown, sink: JsFunction) -> napi::Result<JsObject, ErrorCode> {
+    let readable = source_stream(env, source).map_err(errors::napi_error)?;
+    let transform = create(env, transform)?;
+    let writable = sink_stream(env, sink).map_err(errors::napi_error)?;
+    (|| {
+        let piped = readable.get_named_property::<JsFunction>("pipeThrough")?.call(Some(&readable), &[transform])?.coerce_to_object()?;
+        piped.get_named_property::<JsFunction>("pipeTo")?.call(Some(&piped), &[writable])?.coerce_to_object()
+    })()
+    .map_err(errors::napi_error)
+}
+
+/// 以数据源构造 ReadableStream；数据源保存在 underlying source 对象上，pull/cancel 回调通过 this 取得
+fn source_stream(env: Env, source: JsUnknown) -> napi::Result<JsObject> {
+    let global = env.get_global()?;
+    let constructor = global.get_named_property::<JsFunction>("ReadableStream")?;
+    let mut underlying = env.create_object()?;
+    match source.get_type()? {
+        ValueType::Function => underlying.set_named_property("read", source)?,
+        ValueType::Object => underlying.set_named_property("iterator", iterator(env, source.coerce_to_object()?)?)?,
+        _ => return Err(invalid_source()),
+    }
+
+    let pull = env.create_function_from_closure("pull", |ctx| {
+        let this = ctx.this::<JsObject>()?;
+        let controller = ctx.get::<JsObject>(0)?;
+        let from_iterator = this.has_named_property("iterator")?;
+        let value = match from_iterator {
+            true => {
+                let iterator = this.get_named_property::<JsObject>("iterator")?;
+                iterator.get_named_property::<JsFunction>("next")?.call_without_args(Some(&iterator))?
+            },
+            false => this.get_named_property::<JsFunction>("read")?.call_without_args(None)?,
+        };
+        // 结果可能是 Promise：统一经 Promise.resolve() 等待，receive 绑定 controller 后作为 then 回调
+        let promise = ctx.env.get_global()?.get_named_property::<JsFunction>("Promise")?.coerce_to_object()?;
+        let resolved = promise.get_named_property::<JsFunction>("resolve")?.call(Some(&promise), &[value])?.coerce_to_object()?;
+        let receive = ctx.env.create_function_from_closure("receive", move |ctx| {
+            let controller = ctx.get::<JsObject>(0)?;
+            let result = ctx.get::<JsUnknown>(1)?;
+            let chunk = match from_iterator {
+                true => {
+                    let result = result.coerce_to_object()?;
+                    match result.get_named_property::<JsUnknown>("done")?.coerce_to_bool()?.get_value()? {
+                        true => None,
+                        false => Some(result.get_named_property::<JsUnknown>("value")?),
+                    }
+                },
+                false => Some(result).filter(|result| !matches!(result.get_type(), Ok(ValueType::Null | ValueType::Undefined))),
+            };
+            match chunk {
+                Some(chunk) => controller.get_named_property::<JsFunction>("enqueue")?.call(Some(&controller), &[chunk])?,
+                None => controller.get_named_property::<JsFunction>("close")?.call_without_args(Some(&controller))?,
+            };
+            Ok(ctx.env.get_undefined()?.into_unknown())
+        })?;
+        let receive = receive.coerce_to_object()?;
+        let receive = receive.get_named_property::<JsFunction>("bind")?.call(Some(&receive), &[ctx.env.get_null()?.into_unknown(), controller.into_unknown()])?;
+        resolved.get_named_property::<JsFunction>("then")?.call(Some(&resolved), &[receive])
+    })?;
+    // 管道出错或被取消时结束迭代器，让生成器执行 finally 释放资源
+    let cancel = env.create_function_from_closure("cancel", |ctx| {
+        let this = ctx.this::<JsObject>()?;
+        if this.has_named_property("iterator")? {
+            let iterator = this.get_named_property::<JsObject>("iterator")?;
+            let finish = iterator.get_named_property::<JsUnknown>("return")?;
+            if finish.get_type()? == ValueType::Function {
+                return unsafe { finish.cast::<JsFunction>() }.call_without_args(Some(&iterator));
+            }
+        }
+        Ok(ctx.env.get_undefined()?.into_unknown())
+    })?;
+    underlying.set_named_property("pull", pull)?;
+    underlying.set_named_property("cancel", cancel)?;
+    // highWaterMark 为 0：只在下游需要数据时才调用数据源
+    let mut strategy = env.create_object()?;
+    strategy.set_named_property("highWaterMark", env.create_uint32(0)?)?;
+    constructor.new_instance(&[underlying, strategy])
+}
+
+/// 取得异步可迭代对象或可迭代对象的迭代器，优先使用 Symbol.asyncIterator
+fn iterator(env: Env, source: JsObject) -> napi::Result<JsObject> {
+    let symbol = env.get_global()?.get_named_property::<JsFunction>("Symbol")?.coerce_to_object()?;
+    for name in ["asyncIterator", "iterator"] {
+        let method = source.get_property::<_, JsUnknown>(symbol.get_named_property::<JsUnknown>(name)?)?;
+        if method.get_type()? == ValueType::Function {
+            return unsafe { method.cast::<JsFunction>() }.call_without_args(Some(&source))?.coerce_to_object();
+        }
+    }
+    Err(invalid_source())
+}
+
+fn invalid_source() -> Error {
+    Error::new(Status::InvalidArg, "Source must be a function, an async iterable or an iterable".to_string())
+}
+
+/// 以接收端函数构造 WritableStream；函数保存在 underlying sink 对象上，write 回调通过 this 取得并返回它的结果
+fn sink_stream(env: Env, sink: JsFunction) -> napi::Result<JsObject> {
+    let constructor = env.get_global()?.get_named_property::<JsFunction>("WritableStream")?;
+    let mut underlying = env.create_object()?;
+    underlying.set_named_property("sink", sink)?;
+    let write = env.create_function_from_closure("write", |ctx| {
+        let sink = ctx.this::<JsObject>()?.get_named_property::<JsFunction>("sink")?;
+        sink.call(None, &[ctx.get::<JsUnknown>(0)?])
+    })?;
+    underlying.set_named_property("write", write)?;
+    constructor.new_instance(&[underlying])
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::Cursor;
+
+    use super::*;
+    use crate::crypto::open_field;
+    use crate::format::{read_chunked_header, Layout};
+
+    const KEY: [u8; 32] = [8; 32];
+    const CHUNK_SIZE: usize = 1000;
+
+    fn plaintext(len: usize) -> Vec<u8> {
+        (0..len).map(|i| (i % 241) as u8).collect()
+    }
+
+    fn encrypt_transform(len: usize, sniff_mime_type: bool) -> EncryptTransform<Vec<u8>> {
+        let container = ContainerHeader::for_new_file(Layout::Chunked, CryptoAlgorithm::Aes, len as u64, CHUNK_SIZE as u64);
+        EncryptTransform::new(Vec::new(), CryptoAlgorithm::Aes, &KEY, Some(container), sniff_mime_type, len as u64, CHUNK_SIZE)
+    }
+
+    fn decrypt_transform() -> DecryptTransform {
+        DecryptTransform::new(CryptoAlgorithm::Aes, &KEY, Box::new(|header| header.container.as_ref().unwrap().verify(&KEY)))
+    }
+
+    /// 按 piece 字节一段输入转换流，返回每段输入产生的输出和结束时的输出
+    fn run(transform: &mut impl StreamTransform, data: &[u8], piece: usize) -> Result<Vec<Vec<u8>>, CodedError> {
+        let mut output = Vec::new();
+        for chunk in data.chunks(piece.max(1)) {
+            output.extend(transform.transform(chunk)?);
+        }
+        output.extend(transform.flush()?);
+        Ok(output)
+    }
+
+    fn encrypt(data: &[u8], piece: usize) -> Vec<u8> {
+        run(&mut encrypt_transform(data.len(), false), data, piece).unwrap().concat()
+    }
+
+    fn decrypt(file: &[u8], piece: usize) -> Result<Vec<u8>, CodedError> {
+        Ok(run(&mut decrypt_transform(), file, piece)?.concat())
+    }
+
+    /// 与 chunkDecryptFile() 相同的步骤解密整个文件
+    fn decrypt_file(file: &[u8]) -> Vec<u8> {
+        let mut reader = Cursor::new(file);
+        let header = read_chunked_header(&mut reader).unwrap();
+        header.container.as_ref().unwrap().verify(&KEY).unwrap();
+        let mut decoder = ChunkDecoder::new(reader, header, CryptoAlgorithm::Aes, &KEY).unwrap();
+        let mut plaintext = Vec::new();
+        while let Some(chunk) = decoder.next_chunk().unwrap() {
+            plaintext.extend_from_slice(&chunk);
+        }
+        decoder.finish().unwrap();
+        plaintext
+    }
+
+    #[test]
+    fn encrypts_to_the_chunked_file_format() {
+        let data = plaintext(3500);
+        for piece in [1, 333, 1000, 3500] {
+            assert_eq!(decrypt_file(&encrypt(&data, piece)), data, "piece {}", piece);
+        }
+    }
+
+    #[test]
+    fn decrypts_streams_split_at_any_point() {
+        let data = plaintext(3500);
+        let file = encrypt(&data, 3500);
+        for piece in [1, 7, 64, 999, 1000, 1001, file.len()] {
+            assert_eq!(decrypt(&file, piece).unwrap(), data, "piece {}", piece);
+        }
+    }
+
+    #[test]
+    fn emits_each_chunk_once_it_has_arrived() {
+        let data = plaintext(3500);
+        let file = encrypt(&data, 3500);
+        let mut transform = decrypt_transform();
+        let outputs: Vec<usize> = file.chunks(500).map(|chunk| transform.transform(chunk).unwrap().len()).collect();
+        assert_eq!(outputs.iter().sum::<usize>(), 4);
+        assert!(transform.flush().unwrap().is_empty());
+        // 整个文件一次到达时所有分片一起输出
+        let all = decrypt_transform().transform(&file).unwrap();
+        assert_eq!(all.iter().map(Vec::len).collect::<Vec<_>>(), [1000, 1000, 1000, 500]);
+    }
+
+    #[test]
+    fn round_trips_an_empty_stream() {
+        let file = encrypt(b"", 1);
+        assert_eq!(decrypt_file(&file), b"");
+        assert_eq!(decrypt(&file, 5).unwrap(), b"");
+    }
+
+    #[test]
+    fn requires_exactly_the_declared_size() {
+        let mut transform = encrypt_transform(100, false);
+        transform.transform(&[1; 60]).unwrap();
+        assert_eq!(transform.transform(&[1; 41]).unwrap_err().code, ErrorCode::InvalidArgument);
+
+        let mut transform = encrypt_transform(100, false);
+        transform.transform(&[1; 99]).unwrap();
+        assert_eq!(transform.flush().unwrap_err().code, ErrorCode::Truncated);
+    }
+
+    #[test]
+    fn sniffs_the_mime_type_from_the_first_chunk() {
+        let mut png = b"\x89PNG\r\n\x1a\n\0\0\0\rIHDR".to_vec();
+        png.resize(1500, 0);
+        let file = encrypt_transform(png.len(), true).transform(&png).unwrap().concat();
+        let header = read_chunked_header(&mut Cursor::new(&file)).unwrap();
+        let sealed = header.container.unwrap().encrypted_mime_type.unwrap();
+        assert_eq!(open_field(&KEY, FIELD_MIME_TYPE, &sealed).unwrap(), b"image/png");
+
+        let file = encrypt(&png, 1500);
+        let header = read_chunked_header(&mut Cursor::new(&file)).unwrap();
+        assert!(header.container.unwrap().encrypted_mime_type.is_none());
+    }
+
+    #[test]
+    fn rejects_truncated_and_extended_streams() {
+        let file = encrypt(&plaintext(2500), 2500);
+        assert_eq!(decrypt(&file[..10], 10).unwrap_err().code, ErrorCode::Truncated);
+        assert_eq!(decrypt(&file[..file.len() / 2], 100).unwrap_err().code, ErrorCode::Truncated);
+        assert!(decrypt(&file[..file.len() - 1], 100).is_err());
+        let extended = [&file[..], b"extra"].concat();
+        assert_eq!(decrypt(&extended, 100).unwrap_err().code, ErrorCode::BadFormat);
+    }
+
+    #[test]
+    fn rejects_tampered_streams_and_wrong_keys() {
+        let mut file = encrypt(&plaintext(2500), 2500);
+        let last = file.len() - 100;
+        file[last] ^= 1;
+        assert_eq!(decrypt(&file, 256).unwrap_err().code, ErrorCode::AuthFailed);
+
+        let file = encrypt(&plaintext(2500), 2500);
+        let mut other_key = DecryptTransform::new(CryptoAlgorithm::Aes, &[9; 32], Box::new(|header| header.container.as_ref().unwrap().verify(&[9; 32])));
+        assert_eq!(run(&mut other_key, &file, 256).unwrap_err().code, ErrorCode::AuthFailed);
+    }
+
+    #[test]
+    fn applies_the_header_check() {
+        let file = encrypt(&plaintext(100), 100);
+        let mut transform = DecryptTransform::new(CryptoAlgorithm::Aes, &KEY, Box::new(|_| Err(CodedError::policy("not allowed"))));
+        assert_eq!(transform.transform(&file).unwrap_err().code, ErrorCode::Policy);
+    }
+
+    #[test]
+    fn rejects_input_after_the_end() {
+        let file = encrypt(&plaintext(100), 100);
+        let mut transform = decrypt_transform();
+        transform.transform(&file).unwrap();
+        transform.flush().unwrap();
+        assert_eq!(transform.transform(b"more").unwrap_err().code, ErrorCode::InvalidArgument);
+        assert_eq!(transform.flush().unwrap_err().code, ErrorCode::InvalidArgument);
+    }
+}
@@ -0,0 +1,713 @@
+use std::fs::File;
+use std::io::{self, BufWriter, Read, Seek, SeekFrom, Write};
+use std::path::Path;
+
+use aes::cipher::{BlockEncrypt, KeyInit};
+use aes::{Aes256, Block};
+use flate2::write::DeflateEncoder;
+use hmac::{Hmac, Mac};
+use rand::RngCore;
+use sha1::Sha1;
+
+use crate::errors::{open_error, read_error, write_error, CodedError};
+use crate::fd::{self, SyncOutput, Target};
+use crate::walk::{self, InputEntry};
+
+type HmacSha1 = Hmac<Sha1>;
+
+const LOCAL_HEADER_SIGNATURE: u32 = 0x0403_4b50;
+const CENTRAL_HEADER_SIGNATURE: u32 = 0x0201_4b50;
+const END_OF_CENTRAL_DIRECTORY_SIGNATURE: u32 = 0x0605_4b50;
+const ZIP64_END_OF_CENTRAL_DIRECTORY_SIGNATURE: u32 = 0x0606_4b50;
+const ZIP64_LOCATOR_SIGNATURE: u32 = 0x0706_4b50;
+
+/// 通用标志位：bit 0 加密，bit 11 文件名为 UTF-8
+const FLAG_ENCRYPTED: u16 = 0x0001;
+const FLAG_UTF8: u16 = 0x0800;
+const METHOD_STORED: u16 = 0;
+const METHOD_DEFLATED: u16 = 8;
+/// WinZip AES 加密的条目使用的压缩方法号，实际的压缩方法记录在 0x9901 扩展字段中
+const METHOD_AES: u16 = 99;
+/// 解压所需的版本：AES 加密需要 5.1（已包含 ZIP64 所需的 4.5），目录需要 2.0
+const VERSION_AES: u16 = 51;
+const VERSION_DIRECTORY: u16 = 20;
+/// 创建者版本：高字节 3 表示 Unix（外部属性为 Unix 权限），低字节为规范版本 6.3
+const VERSION_MADE_BY: u16 = (3 << 8) | 63;
+
+const EXTRA_ZIP64: u16 = 0x0001;
+const EXTRA_TIMESTAMP: u16 = 0x5455;
+const EXTRA_AES: u16 = 0x9901;
+/// AE-2：不记录 CRC，完整性只由 HMAC 保证，避免 CRC 泄露小文件的内容
+const AES_VENDOR_VERSION: u16 = 2;
+/// 密钥强度 3 表示 AES-256
+const AES_STRENGTH_256: u8 = 3;
+
+const SALT_LEN: usize = 16;
+const KEY_LEN: usize = 32;
+const VERIFIER_LEN: usize = 2;
+const AUTH_CODE_LEN: usize = 10;
+const PBKDF2_ITERATIONS: u32 = 1000;
+
+/// 32 位字段放不下时写入的占位值，实际值记录在 ZIP64 扩展字段中
+const ZIP64_MARKER: u32 = u32::MAX;
+
+/// 复制数据时每次读取的长度，每读完一块检查一次超时并发送 chunkDone 事件
+const COPY_BUFFER_LEN: usize = 1024 * 1024;
+
+/// 外部属性：高 16 位为 Unix 文件类型和权限，目录同时设置 MS-DOS 目录位
+fn external_attributes(entry: &InputEntry) -> u32 {
+    if entry.is_dir {
+        ((0o040000 | entry.mode) << 16) | 0x10
+    } else {
+        (0o100000 | entry.mode) << 16
+    }
+}
+
+/// 把 Unix 时间转换为 MS-DOS 的（时间，日期），按 UTC 计算；早于 1980 年的时间记为 1980-01-01
+fn dos_date_time(seconds: u64) -> (u16, u16) {
+    let days = (seconds / 86400) as i64;
+    let secs = seconds % 86400;
+    // 公历日期换算（Howard Hinnant 的 civil_from_days）
+    let z = days + 719468;
+    let era = z.div_euclid(146097);
+    let doe = z - era * 146097;
+    let yoe = (doe - doe / 1460 + doe / 36524 - doe / 146096) / 365;
+    let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+    let mp = (5 * doy + 2) / 153;
+    let day = doy - (153 * mp + 2) / 5 + 1;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 };
+    let year = yoe + era * 400 + if month <= 2 { 1 } else { 0 };
+    if year < 1980 {
+        return (0, (1 << 5) | 1);
+    }
+    let year = year.min(2107);
+    let time = ((secs / 3600) << 11) | (((secs % 3600) / 60) << 5) | ((secs % 60) / 2);
+    let date = ((year - 1980) << 9) | (month << 5) | day;
+    (time as u16, date as u16)
+}
+
+/// WinZip AES 加密写入器：AES-256-CTR（小端计数器，从 1 开始），密文计算 HMAC-SHA1
+struct AesWriter<W: Write> {
+    inner: W,
+    cipher: Aes256,
+    counter: u128,
+    keystream: Block,
+    position: usize,
+    mac: HmacSha1,
+    buffer: Vec<u8>,
+}
+
+impl<W: Write> AesWriter<W> {
+    /// 写出盐和口令校验值，返回写入器
+    fn new(mut inner: W, password: &[u8]) -> io::Result<Self> {
+        let mut salt = [0u8; SALT_LEN];
+        rand::thread_rng().fill_bytes(&mut salt);
+        let mut derived = [0u8; KEY_LEN * 2 + VERIFIER_LEN];
+        pbkdf2::pbkdf2_hmac::<Sha1>(password, &salt, PBKDF2_ITERATIONS, &mut derived);
+        inner.write_all(&salt)?;
+        inner.write_all(&derived[KEY_LEN * 2..])?;
+        Ok(AesWriter {
+            inner,
+            cipher: Aes256::new_from_slice(&derived[..KEY_LEN]).expect("AES-256 key is 32 bytes"),
+            counter: 0,
+            keystream: Block::default(),
+            position: 16,
+            mac: <HmacSha1 as Mac>::new_from_slice(&derived[KEY_LEN..KEY_LEN * 2]).expect("HMAC accepts any key length"),
+            buffer: Vec::new(),
+        })
+    }
+
+    /// 写出认证码，返回内层写入器
+    fn finish(mut self) -> io::Result<W> {
+        let code = self.mac.finalize().into_bytes();
+        self.inner.write_all(&code[..AUTH_CODE_LEN])?;
+        Ok(self.inner)
+    }
+}
+
+impl<W: Write> Write for AesWriter<W> {
+    fn write(&mut self, data: &[u8]) -> io::Result<usize> {
+        self.buffer.clear();
+        self.buffer.extend_from_slice(data);
+        for byte in self.buffer.iter_mut() {
+            if self.position == 16 {
+                self.counter += 1;
+                self.keystream = Block::from(self.counter.to_le_bytes());
+                self.cipher.encrypt_block(&mut self.keystream);
+                self.position = 0;
+            }
+            *byte ^= self.keystream[self.position];
+            self.position += 1;
+        }
+        self.mac.update(&self.buffer);
+        self.inner.write_all(&self.buffer)?;
+        Ok(data.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.inner.flush()
+    }
+}
+
+/// 统计写入字节数的写入器，用于得到条目的压缩后大小
+struct CountingWriter<W: Write> {
+    inner: W,
+    written: u64,
+}
+
+impl<W: Write> Write for CountingWriter<W> {
+    fn write(&mut self, data: &[u8]) -> io::Result<usize> {
+        let written = self.inner.write(data)?;
+        self.written += written as u64;
+        Ok(written)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.inner.flush()
+    }
+}
+
+/// 已写出的条目，用于生成中央目录
+struct Written {
+    entry: InputEntry,
+    offset: u64,
+    compressed_size: u64,
+    method: u16,
+    zip64: bool,
+}
+
+fn put_u16(out: &mut Vec<u8>, value: u16) {
+    out.extend_from_slice(&value.to_le_bytes());
+}
+
+fn put_u32(out: &mut Vec<u8>, value: u32) {
+    out.extend_from_slice(&value.to_le_bytes());
+}
+
+fn put_u64(out: &mut Vec<u8>, value: u64) {
+    out.extend_from_slice(&value.to_le_bytes());
+}
+
+/// 扩展时间戳字段（UTC 修改时间），比 MS-DOS 时间精确且不受时区影响
+fn timestamp_extra(out: &mut Vec<u8>, modified: u64) {
+    put_u16(out, EXTRA_TIMESTAMP);
+    put_u16(out, 5);
+    out.push(1);
+    put_u32(out, modified.min(u32::MAX as u64) as u32);
+}
+
+/// WinZip AES 扩展字段
+fn aes_extra(out: &mut Vec<u8>, method: u16) {
+    put_u16(out, EXTRA_AES);
+    put_u16(out, 7);
+    put_u16(out, AES_VENDOR_VERSION);
+    out.extend_from_slice(b"AE");
+    out.push(AES_STRENGTH_256);
+    put_u16(out, method);
+}
+
+/// 本地文件头；大小在写完数据后回填。返回（文件头，大小字段在文件头中的位置）
+fn local_header(entry: &InputEntry, method: u16, zip64: bool) -> (Vec<u8>, usize) {
+    let (time, date) = dos_date_time(entry.modified);
+    let mut extra = Vec::new();
+    if zip64 {
+        put_u16(&mut extra, EXTRA_ZIP64);
+        put_u16(&mut extra, 16);
+        put_u64(&mut extra, 0);
+        put_u64(&mut extra, 0);
+    }
+    timestamp_extra(&mut extra, entry.modified);
+    if !entry.is_dir {
+        aes_extra(&mut extra, method);
+    }
+
+    let mut header = Vec::new();
+    put_u32(&mut header, LOCAL_HEADER_SIGNATURE);
+    put_u16(&mut header, if entry.is_dir { VERSION_DIRECTORY } else { VERSION_AES });
+    put_u16(&mut header, if entry.is_dir { FLAG_UTF8 } else { FLAG_UTF8 | FLAG_ENCRYPTED });
+    put_u16(&mut header, if entry.is_dir { METHOD_STORED } else { METHOD_AES });
+    put_u16(&mut header, time);
+    put_u16(&mut header, date);
+    // AE-2 的 CRC 固定为 0
+    put_u32(&mut header, 0);
+    let sizes = header.len();
+    let placeholder = if zip64 { ZIP64_MARKER } else { 0 };
+    put_u32(&mut header, placeholder);
+    put_u32(&mut header, placeholder);
+    put_u16(&mut header, entry.name.len() as u16);
+    put_u16(&mut header, extra.len() as u16);
+    header.extend_from_slice(entry.name.as_bytes());
+    let zip64_sizes = header.len() + 4;
+    header.extend_from_slice(&extra);
+    (header, if zip64 { zip64_sizes } else { sizes })
+}
+
+/// 中央目录中的一项
+fn central_header(written: &Written) -> Vec<u8> {
+    let entry = &written.entry;
+    let (time, date) = dos_date_time(entry.modified);
+    let size_overflow = written.zip64;
+    let offset_overflow = written.offset >= ZIP64_MARKER as u64;
+
+    let mut extra = Vec::new();
+    if size_overflow || offset_overflow {
+        let mut fields = Vec::new();
+        if size_overflow {
+            put_u64(&mut fields, entry.size);
+            put_u64(&mut fields, written.compressed_size);
+        }
+        if offset_overflow {
+            put_u64(&mut fields, written.offset);
+        }
+        put_u16(&mut extra, EXTRA_ZIP64);
+        put_u16(&mut extra, fields.len() as u16);
+        extra.extend_from_slice(&fields);
+    }
+    timestamp_extra(&mut extra, entry.modified);
+    if !entry.is_dir {
+        aes_extra(&mut extra, written.method);
+    }
+
+    let mut header = Vec::new();
+    put_u32(&mut header, CENTRAL_HEADER_SIGNATURE);
+    put_u16(&mut header, VERSION_MADE_BY);
+    put_u16(&mut header, if entry.is_dir { VERSION_DIRECTORY } else { VERSION_AES });
+    put_u16(&mut header, if entry.is_dir { FLAG_UTF8 } else { FLAG_UTF8 | FLAG_ENCRYPTED });
+    put_u16(&mut header, if entry.is_dir { METHOD_STORED } else { METHOD_AES });
+    put_u16(&mut header, time);
+    put_u16(&mut header, date);
+    put_u32(&mut header, 0);
+    if size_overflow {
+        put_u32(&mut header, ZIP64_MARKER);
+        put_u32(&mut header, ZIP64_MARKER);
+    } else {
+        put_u32(&mut header, written.compressed_size as u32);
+        put_u32(&mut header, entry.size as u32);
+    }
+    put_u16(&mut header, entry.name.len() as u16);
+    put_u16(&mut header, extra.len() as u16);
+    // 注释长度、起始磁盘号、内部属性
+    put_u16(&mut header, 0);
+    put_u16(&mut header, 0);
+    put_u16(&mut header, 0);
+    put_u32(&mut header, external_attributes(entry));
+    put_u32(&mut header, if offset_overflow { ZIP64_MARKER } else { written.offset as u32 });
+    header.extend_from_slice(entry.name.as_bytes());
+    header.extend_from_slice(&extra);
+    header
+}
+
+/// 中央目录结束记录，条目数或偏移超出范围时先写 ZIP64 结束记录和定位器
+fn end_of_central_directory(count: u64, directory_offset: u64, directory_size: u64) -> Vec<u8> {
+    let mut out = Vec::new();
+    let zip64 = count >= u16::MAX as u64 || directory_offset >= ZIP64_MARKER as u64 || directory_size >= ZIP64_MARKER as u64;
+    if zip64 {
+        let record_offset = directory_offset + directory_size;
+        put_u32(&mut out, ZIP64_END_OF_CENTRAL_DIRECTORY_SIGNATURE);
+        put_u64(&mut out, 44);
+        put_u16(&mut out, VERSION_MADE_BY);
+        put_u16(&mut out, VERSION_AES);
+        put_u32(&mut out, 0);
+        put_u32(&mut out, 0);
+        put_u64(&mut out, count);
+        put_u64(&mut out, count);
+        put_u64(&mut out, directory_size);
+        put_u64(&mut out, directory_offset);
+
+        put_u32(&mut out, ZIP64_LOCATOR_SIGNATURE);
+        put_u32(&mut out, 0);
+        put_u64(&mut out, record_offset);
+        put_u32(&mut out, 1);
+    }
+    put_u32(&mut out, END_OF_CENTRAL_DIRECTORY_SIGNATURE);
+    put_u16(&mut out, 0);
+    put_u16(&mut out, 0);
+    put_u16(&mut out, count.min(u16::MAX as u64) as u16);
+    put_u16(&mut out, count.min(u16::MAX as u64) as u16);
+    put_u32(&mut out, directory_size.min(ZIP64_MARKER as u64) as u32);
+    put_u32(&mut out, directory_offset.min(ZIP64_MARKER as u64) as u32);
+    put_u16(&mut out, 0);
+    out
+}
+
+/// 生成 ZIP 的统计
+pub struct ZipReport {
+    pub files: u32,
+    pub directories: u32,
+    pub plaintext_size: u64,
+    pub zip_size: u64,
+}
+
+/// 把文件和目录写入 WinZip AES-256（AE-2）加密的 ZIP 文件，可以用 7-Zip、WinZip 等工具以口令解压
+///
+/// 每个文件单独派生密钥；文件名、大小和修改时间不加密。compress 为 false 时不压缩（适合已压缩的内容）。
+pub fn encrypt_to_zip(inputs: &[String], output_path: &Target, password: &str, compress: bool, check: impl Fn() -> Result<(), CodedError>, mut chunk_done: impl FnMut(u32, usize)) -> Result<ZipReport, CodedError> {
+    if password.is_empty() {
+        return Err(CodedError::invalid_argument("ZIP password must not be empty"));
+    }
+    let entries = walk::collect(inputs, "ZIP file")?;
+    if let Some(entry) = entries.iter().find(|entry| entry.name.len() > u16::MAX as usize) {
+        return Err(CodedError::invalid_argument(format!("Name is too long for a ZIP file: {}", entry.name)));
+    }
+
+    let output = fd::create(output_path).map_err(|e| open_error("creating output file", e))?;
+    let mut writer = BufWriter::new(output);
+    let output_error = |e| write_error("writing output file", e);
+    let mut offset = 0u64;
+    let mut written = Vec::with_capacity(entries.len());
+    let mut buffer = vec![0u8; COPY_BUFFER_LEN];
+    let mut index = 0u32;
+    let mut plaintext_size = 0u64;
+
+    for entry in entries {
+        check()?;
+        let method = if compress { METHOD_DEFLATED } else { METHOD_STORED };
+        // 压缩后可能略大于原文件，留出余量后仍可能超过 4 GB 时使用 ZIP64
+        let zip64 = !entry.is_dir && entry.size + entry.size / 64 + 1024 >= ZIP64_MARKER as u64;
+        let (header, sizes_position) = local_header(&entry, method, zip64);
+        writer.write_all(&header).map_err(output_error)?;
+        let data_start = offset + header.len() as u64;
+
+        let mut compressed_size = 0;
+        if !entry.is_dir {
+            let mut input = File::open(&entry.path).map_err(|e| open_error(&format!("opening {}", entry.path.display()), e))?;
+            let counting = CountingWriter { inner: &mut writer, written: 0 };
+            let mut aes = AesWriter::new(counting, password.as_bytes()).map_err(output_error)?;
+            let size = if compress {
+                let mut encoder = DeflateEncoder::new(aes, flate2::Compression::default());
+                let size = copy_file(&mut input, &entry.path, &mut encoder, &mut buffer, &mut index, &check, &mut chunk_done)?;
+                aes = encoder.finish().map_err(output_error)?;
+                size
+            } else {
+                copy_file(&mut input, &entry.path, &mut aes, &mut buffer, &mut index, &check, &mut chunk_done)?
+            };
+            compressed_size = aes.finish().map_err(output_error)?.written;
+            if size != entry.size {
+                return Err(CodedError::io_read(format!("{} changed while it was being added to the ZIP file", entry.path.display())));
+            }
+            plaintext_size += size;
+
+            // 回填本地文件头中的大小
+            let mut sizes = Vec::new();
+            if zip64 {
+                put_u64(&mut sizes, size);
+                put_u64(&mut sizes, compressed_size);
+            } else {
+                put_u32(&mut sizes, compressed_size as u32);
+                put_u32(&mut sizes, size as u32);
+            }
+            writer
+                .seek(SeekFrom::Start(offset + sizes_position as u64))
+                .and_then(|_| writer.write_all(&sizes))
+                .and_then(|_| writer.seek(SeekFrom::End(0)))
+                .map_err(output_error)?;
+        }
+        written.push(Written { entry, offset, compressed_size, method, zip64 });
+        offset = data_start + compressed_size;
+    }
+
+    let directory_offset = offset;
+    for item in &written {
+        let header = central_header(item);
+        writer.write_all(&header).map_err(output_error)?;
+        offset += header.len() as u64;
+    }
+    writer
+        .write_all(&end_of_central_directory(written.len() as u64, directory_offset, offset - directory_offset))
+        .map_err(output_error)?;
+
+    let output = writer
+        .into_inner()
+        .map_err(|e| write_error("writing output file", e.into_error()))?;
+    output.sync_output().map_err(|e| write_error("flushing output file", e))?;
+    let zip_size = output
+        .metadata()
+        .map_err(|e| read_error("reading output file metadata", e))?
+        .len();
+    let directories = written.iter().filter(|item| item.entry.is_dir).count() as u32;
+    Ok(ZipReport { files: written.len() as u32 - directories, directories, plaintext_size, zip_size })
+}
+
+/// 逐块把输入文件写入条目，每块之间检查超时
+fn copy_file<W: Write>(input: &mut File, path: &Path, writer: &mut W, buffer: &mut [u8], index: &mut u32, check: &impl Fn() -> Result<(), CodedError>, chunk_done: &mut impl FnMut(u32, usize)) -> Result<u64, CodedError> {
+    let mut size = 0u64;
+    loop {
+        check()?;
+        let read = match input.read(buffer) {
+            Ok(0) => return Ok(size),
+            Ok(read) => read,
+            Err(err) if err.kind() == io::ErrorKind::Interrupted => continue,
+            Err(err) => return Err(read_error(&format!("reading {}", path.display()), err)),
+        };
+        writer
+            .write_all(&buffer[..read])
+            .map_err(|e| write_error("writing output file", e))?;
+        size += read as u64;
+        *index += 1;
+        chunk_done(*index, read);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::errors::ErrorCode;
+    use std::path::PathBuf;
+
+    const PASSWORD: &str = "zip password";
+
+    struct TempDir(PathBuf);
+
+    impl TempDir {
+        fn new(name: &str) -> Self {
+            let path = std::env::temp_dir().join(format!("zippy-zip-{}-{}", std::process::id(), name));
+            let _ = std::fs::remove_dir_all(&path);
+            std::fs::create_dir_all(&path).unwrap();
+            TempDir(path)
+        }
+
+        fn path(&self, name: &str) -> String {
+            self.0.join(name).to_string_lossy().into_owned()
+        }
+
+        fn write(&self, name: &str, data: &[u8]) {
+            let path = self.0.join(name);
+            std::fs::create_dir_all(path.parent().unwrap()).unwrap();
+            std::fs::write(path, data).unwrap();
+        }
+
+        fn zip(&self, inputs: &[&str], password: &str, compress: bool) -> Result<ZipReport, CodedError> {
+            let inputs: Vec<_> = inputs.iter().map(|input| self.path(input)).collect();
+            encrypt_to_zip(&inputs, &Target::from(&*self.path("out.zip")), password, compress, || Ok(()), |_, _| {})
+        }
+    }
+
+    impl Drop for TempDir {
+        fn drop(&mut self) {
+            let _ = std::fs::remove_dir_all(&self.0);
+        }
+    }
+
+    fn u16_at(bytes: &[u8], at: usize) -> u16 {
+        u16::from_le_bytes(bytes[at..at + 2].try_into().unwrap())
+    }
+
+    fn u32_at(bytes: &[u8], at: usize) -> u32 {
+        u32::from_le_bytes(bytes[at..at + 4].try_into().unwrap())
+    }
+
+    /// 中央目录中的一项
+    struct Entry {
+        name: String,
+        flags: u16,
+        method: u16,
+        /// 0x9901 扩展字段中记录的实际压缩方法
+        aes_method: Option<u16>,
+        compressed_size: usize,
+        size: usize,
+        external_attributes: u32,
+        data: Vec<u8>,
+    }
+
+    /// 按中央目录读出所有条目和对应的本地数据，同时核对本地文件头
+    fn read_zip(zip: &[u8]) -> Vec<Entry> {
+        let end = zip.len() - 22;
+        assert_eq!(u32_at(zip, end), END_OF_CENTRAL_DIRECTORY_SIGNATURE);
+        let count = u16_at(zip, end + 10) as usize;
+        let mut at = u32_at(zip, end + 16) as usize;
+        assert_eq!(at + u32_at(zip, end + 12) as usize, end);
+        let mut entries = Vec::new();
+        for _ in 0..count {
+            assert_eq!(u32_at(zip, at), CENTRAL_HEADER_SIGNATURE);
+            let name_len = u16_at(zip, at + 28) as usize;
+            let extra_len = u16_at(zip, at + 30) as usize;
+            let name = String::from_utf8(zip[at + 46..at + 46 + name_len].to_vec()).unwrap();
+            let extra = &zip[at + 46 + name_len..at + 46 + name_len + extra_len];
+            let mut aes_method = None;
+            let mut position = 0;
+            while position < extra.len() {
+                let (id, len) = (u16_at(extra, position), u16_at(extra, position + 2) as usize);
+                if id == EXTRA_AES {
+                    assert_eq!(&extra[position + 4..position + 9], &[2, 0, b'A', b'E', AES_STRENGTH_256]);
+                    aes_method = Some(u16_at(extra, position + 9));
+                }
+                position += 4 + len;
+            }
+            let compressed_size = u32_at(zip, at + 20) as usize;
+            let local = u32_at(zip, at + 42) as usize;
+            assert_eq!(u32_at(zip, local), LOCAL_HEADER_SIGNATURE);
+            assert_eq!(u32_at(zip, local + 18) as usize, compressed_size);
+            assert_eq!(u32_at(zip, local + 22), u32_at(zip, at + 24));
+            let data_start = local + 30 + u16_at(zip, local + 26) as usize + u16_at(zip, local + 28) as usize;
+            entries.push(Entry {
+                name,
+                flags: u16_at(zip, at + 8),
+                method: u16_at(zip, at + 10),
+                aes_method,
+                compressed_size,
+                size: u32_at(zip, at + 24) as usize,
+                external_attributes: u32_at(zip, at + 38),
+                data: zip[data_start..data_start + compressed_size].to_vec(),
+            });
+            at += 46 + name_len + extra_len + u16_at(zip, at + 32) as usize;
+        }
+        entries
+    }
+
+    /// 按 WinZip AES 规范独立实现的解密：核对口令校验值和 HMAC-SHA1 认证码，AES-256-CTR 解密后按需解压
+    fn decrypt_entry(entry: &Entry, password: &str) -> Result<Vec<u8>, &'static str> {
+        let (salt, rest) = entry.data.split_at(SALT_LEN);
+        let (verifier, rest) = rest.split_at(VERIFIER_LEN);
+        let (ciphertext, code) = rest.split_at(rest.len() - AUTH_CODE_LEN);
+        let mut derived = [0u8; 66];
+        pbkdf2::pbkdf2_hmac::<Sha1>(password.as_bytes(), salt, 1000, &mut derived);
+        if &derived[64..] != verifier {
+            return Err("wrong password");
+        }
+        let mut mac = <HmacSha1 as Mac>::new_from_slice(&derived[32..64]).unwrap();
+        mac.update(ciphertext);
+        mac.verify_truncated_left(code).map_err(|_| "authentication failed")?;
+        let cipher = Aes256::new_from_slice(&derived[..32]).unwrap();
+        let mut plaintext = ciphertext.to_vec();
+        for (index, block) in plaintext.chunks_mut(16).enumerate() {
+            let mut keystream = Block::from((index as u128 + 1).to_le_bytes());
+            cipher.encrypt_block(&mut keystream);
+            block.iter_mut().zip(keystream.iter()).for_each(|(byte, key)| *byte ^= key);
+        }
+        match entry.aes_method {
+            Some(METHOD_DEFLATED) => {
+                let mut inflated = Vec::new();
+                flate2::read::DeflateDecoder::new(&plaintext[..]).read_to_end(&mut inflated).unwrap();
+                Ok(inflated)
+            }
+            _ => Ok(plaintext),
+        }
+    }
+
+    #[test]
+    fn writes_entries_readable_with_the_password() {
+        for compress in [true, false] {
+            let dir = TempDir::new(&format!("entries-{}", compress));
+            let text = b"some text that compresses well ".repeat(200);
+            let binary: Vec<u8> = (0..3333u32).map(|i| (i * 17 % 256) as u8).collect();
+            dir.write("docs/a.txt", &text);
+            dir.write("docs/sub/b.bin", &binary);
+            let report = dir.zip(&["docs"], PASSWORD, compress).unwrap();
+            assert_eq!((report.files, report.directories), (2, 2));
+            assert_eq!(report.plaintext_size, (text.len() + binary.len()) as u64);
+            let zip = std::fs::read(dir.path("out.zip")).unwrap();
+            assert_eq!(report.zip_size, zip.len() as u64);
+
+            let entries = read_zip(&zip);
+            let names: Vec<_> = entries.iter().map(|entry| entry.name.as_str()).collect();
+            assert_eq!(names, ["docs/", "docs/a.txt", "docs/sub/", "docs/sub/b.bin"]);
+            let directory = &entries[0];
+            assert_eq!((directory.method, directory.flags, directory.compressed_size), (METHOD_STORED, FLAG_UTF8, 0));
+            assert_eq!(directory.external_attributes & 0x10, 0x10);
+
+            for (entry, expected) in [(&entries[1], &text), (&entries[3], &binary)] {
+                assert_eq!(entry.method, METHOD_AES);
+                assert_eq!(entry.flags, FLAG_UTF8 | FLAG_ENCRYPTED);
+                assert_eq!(entry.aes_method, Some(if compress { METHOD_DEFLATED } else { METHOD_STORED }));
+                assert_eq!(entry.size, expected.len());
+                assert_eq!(decrypt_entry(entry, PASSWORD).unwrap(), *expected);
+                assert_eq!(decrypt_entry(entry, "other password"), Err("wrong password"));
+            }
+            if compress {
+                assert!(entries[1].compressed_size < text.len() / 4);
+            } else {
+                assert_eq!(entries[1].compressed_size, text.len() + SALT_LEN + VERIFIER_LEN + AUTH_CODE_LEN);
+            }
+        }
+    }
+
+    #[test]
+    fn derives_a_new_key_for_every_entry() {
+        let dir = TempDir::new("salt");
+        dir.write("one.txt", b"same content");
+        dir.write("two.txt", b"same content");
+        dir.zip(&["one.txt", "two.txt"], PASSWORD, false).unwrap();
+        let entries = read_zip(&std::fs::read(dir.path("out.zip")).unwrap());
+        assert_ne!(entries[0].data[..SALT_LEN], entries[1].data[..SALT_LEN]);
+        assert_ne!(entries[0].data, entries[1].data);
+    }
+
+    #[test]
+    fn authenticates_entry_data() {
+        let dir = TempDir::new("tamper");
+        dir.write("a.txt", b"authenticated content");
+        dir.zip(&["a.txt"], PASSWORD, false).unwrap();
+        let mut entries = read_zip(&std::fs::read(dir.path("out.zip")).unwrap());
+        entries[0].data[SALT_LEN + VERIFIER_LEN] ^= 1;
+        assert_eq!(decrypt_entry(&entries[0], PASSWORD), Err("authentication failed"));
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn records_unix_permissions() {
+        use std::os::unix::fs::PermissionsExt;
+        let dir = TempDir::new("mode");
+        dir.write("run.sh", b"#!/bin/sh\n");
+        std::fs::set_permissions(dir.0.join("run.sh"), std::fs::Permissions::from_mode(0o754)).unwrap();
+        dir.zip(&["run.sh"], PASSWORD, true).unwrap();
+        let entries = read_zip(&std::fs::read(dir.path("out.zip")).unwrap());
+        assert_eq!(entries[0].external_attributes >> 16, 0o100754);
+    }
+
+    #[test]
+    fn rejects_empty_passwords_and_stops_when_cancelled() {
+        let dir = TempDir::new("reject");
+        dir.write("a.txt", b"a");
+        assert_eq!(dir.zip(&["a.txt"], "", true).err().unwrap().code, ErrorCode::InvalidArgument);
+        assert_eq!(dir.zip(&[], PASSWORD, true).err().unwrap().code, ErrorCode::InvalidArgument);
+
+        let inputs = [dir.path("a.txt")];
+        let mut progress = Vec::new();
+        encrypt_to_zip(&inputs, &Target::from(&*dir.path("out.zip")), PASSWORD, true, || Ok(()), |index, bytes| progress.push((index, bytes))).unwrap();
+        assert_eq!(progress, [(1, 1)]);
+        let cancelled = || Err(CodedError::aborted("aborted"));
+        let err = encrypt_to_zip(&inputs, &Target::from(&*dir.path("out.zip")), PASSWORD, true, cancelled, |_, _| {}).err().unwrap();
+        assert_eq!(err.code, ErrorCode::Aborted);
+    }
+
+    #[test]
+    fn converts_times_to_ms_dos_format() {
+        assert_eq!(dos_date_time(946_684_800), (0, (20 << 9) | (1 << 5) | 1));
+        // 2024-02-29 13:45:58 UTC
+        assert_eq!(dos_date_time(1_709_214_358), ((13 << 11) | (45 << 5) | 29, (44 << 9) | (2 << 5) | 29));
+        // 秒数以 2 秒为单位
+        assert_eq!(dos_date_time(1_709_214_359).0, dos_date_time(1_709_214_358).0);
+        assert_eq!(dos_date_time(0), (0, (1 << 5) | 1));
+        assert_eq!(dos_date_time(315_532_799), (0, (1 << 5) | 1));
+        // 2150-01-01 超出范围，年份记为 2107
+        assert_eq!(dos_date_time(5_680_281_600).1, (127 << 9) | (1 << 5) | 1);
+    }
+
+    #[test]
+    fn uses_zip64_records_when_fields_overflow() {
+        let plain = end_of_central_directory(3, 1000, 200);
+        assert_eq!(plain.len(), 22);
+        assert_eq!(u32_at(&plain, 0), END_OF_CENTRAL_DIRECTORY_SIGNATURE);
+
+        let many = end_of_central_directory(70_000, 1000, 200);
+        assert_eq!(many.len(), 56 + 20 + 22);
+        assert_eq!(u32_at(&many, 0), ZIP64_END_OF_CENTRAL_DIRECTORY_SIGNATURE);
+        assert_eq!(u64::from_le_bytes(many[32..40].try_into().unwrap()), 70_000);
+        assert_eq!(u32_at(&many, 56), ZIP64_LOCATOR_SIGNATURE);
+        assert_eq!(u64::from_le_bytes(many[64..72].try_into().unwrap()), 1200);
+        assert_eq!(u16_at(&many, 76 + 10), u16::MAX);
+
+        let entry = InputEntry { name: "big.bin".into(), path: PathBuf::new(), is_dir: false, size: 5 << 30, mode: 0o644, modified: 0 };
+        let written = Written { entry, offset: 6 << 30, compressed_size: (5 << 30) + 28, method: METHOD_STORED, zip64: true };
+        let header = central_header(&written);
+        assert_eq!((u32_at(&header, 20), u32_at(&header, 24), u32_at(&header, 42)), (ZIP64_MARKER, ZIP64_MARKER, ZIP64_MARKER));
+        let extra = &header[46 + 7..];
+        assert_eq!((u16_at(extra, 0), u16_at(extra, 2)), (EXTRA_ZIP64, 24));
+        assert_eq!(u64::from_le_bytes(extra[4..12].try_into().unwrap()), 5 << 30);
+        assert_eq!(u64::from_le_bytes(extra[20..28].try_into().unwrap()), 6 << 30);
+
+        let (local, sizes) = local_header(&written.entry, METHOD_STORED, true);
+        assert_eq!(u32_at(&local, 18), ZIP64_MARKER);
+        assert_eq!(u16_at(&local, sizes - 4), EXTRA_ZIP64);
+    }
+}